@@ -6,6 +6,15 @@ private let ackTimeout: TimeInterval = 3
 private let maxAckRetries = 3
 private let defaultPollInterval: TimeInterval = 0.8
 private let listenTargetKinds = Set(["direct", "group", "unknown"])
+private let supportedProtocolVersions = ["1.1", "1.0"]
+private let ipcEndpointEnv = "WEREPLY_IPC_ENDPOINT"
+private let accountEnv = "WEREPLY_ACCOUNT_ID"
+private let weChatBundleIds = ["com.tencent.xinWeChat", "com.tencent.WeChat"]
+private let maxFrameBytes = 16 * 1024 * 1024
+// Host message ids remembered to drop resent duplicates.
+private let maxHandledIds = 512
+// Commands run in order here, off the reader, so acks are never held up by a slow write.
+private let commandQueue = DispatchQueue(label: "wereply.commands")
 
 private struct PendingMessage {
     var envelope: [String: Any]
@@ -23,10 +32,19 @@ private final class AgentState {
     var cachedMessageLists: [String: AXUIElement] = [:]
     var cachedSessionLists: [String: AXUIElement] = [:]
     var cachedInputs: [String: AXUIElement] = [:]
+    var protocolVersion = "1.0"
+    var handledIds: [String] = []
 }
 
 private let state = AgentState()
 
+private final class Transport {
+    var fd: Int32 = -1
+    let lock = NSLock()
+}
+
+private let transport = Transport()
+
 private struct AxPathStep {
     let roles: [String]
     let index: Int
@@ -77,6 +95,75 @@ private let inputPaths: [[AxPathStep]] = [
 ]
 // AUTO-GENERATED UI PATHS END
 
+private func logLine(_ level: String, _ component: String, _ message: String) {
+    let line = message.components(separatedBy: .newlines).joined(separator: " ")
+    FileHandle.standardError.write("\(level)|\(component)|\(line)\n".data(using: .utf8)!)
+}
+
+private func connectTransport() {
+    guard let path = ProcessInfo.processInfo.environment[ipcEndpointEnv], !path.isEmpty else { return }
+    let fd = socket(AF_UNIX, SOCK_STREAM, 0)
+    guard fd >= 0 else { return }
+    var addr = sockaddr_un()
+    addr.sun_family = sa_family_t(AF_UNIX)
+    let capacity = MemoryLayout.size(ofValue: addr.sun_path)
+    guard path.utf8.count < capacity else {
+        close(fd)
+        return
+    }
+    withUnsafeMutablePointer(to: &addr.sun_path) { pointer in
+        pointer.withMemoryRebound(to: CChar.self, capacity: capacity) { _ = strncpy($0, path, capacity - 1) }
+    }
+    let connected = withUnsafePointer(to: &addr) { pointer in
+        pointer.withMemoryRebound(to: sockaddr.self, capacity: 1) {
+            connect(fd, $0, socklen_t(MemoryLayout<sockaddr_un>.size))
+        }
+    }
+    guard connected == 0 else {
+        close(fd)
+        logLine("WARN", "ipc", "connect ipc endpoint failed, using stdio")
+        return
+    }
+    var noSigPipe: Int32 = 1
+    setsockopt(fd, SOL_SOCKET, SO_NOSIGPIPE, &noSigPipe, socklen_t(MemoryLayout<Int32>.size))
+    transport.fd = fd
+}
+
+private func writeMessage(_ line: String) {
+    transport.lock.lock()
+    defer { transport.lock.unlock() }
+    guard transport.fd >= 0 else {
+        print(line)
+        fflush(stdout)
+        return
+    }
+    let body = Data(line.utf8)
+    var length = UInt32(body.count).bigEndian
+    var frame = Data(bytes: &length, count: 4)
+    frame.append(body)
+    frame.withUnsafeBytes { raw in
+        var offset = 0
+        while offset < raw.count {
+            let written = write(transport.fd, raw.baseAddress! + offset, raw.count - offset)
+            if written <= 0 { return }
+            offset += written
+        }
+    }
+}
+
+private func readExact(_ fd: Int32, count: Int) -> Data? {
+    var buffer = [UInt8](repeating: 0, count: count)
+    var offset = 0
+    while offset < count {
+        let received = buffer.withUnsafeMutableBytes { raw in
+            read(fd, raw.baseAddress! + offset, count - offset)
+        }
+        if received <= 0 { return nil }
+        offset += received
+    }
+    return Data(buffer)
+}
+
 private func jsonString(_ object: Any) -> String? {
     guard JSONSerialization.isValidJSONObject(object) else { return nil }
     guard let data = try? JSONSerialization.data(withJSONObject: object) else { return nil }
@@ -85,26 +172,36 @@ private func jsonString(_ object: Any) -> String? {
 
 private func sendEnvelope(type: String, payload: [String: Any], id: String? = nil, trackAck: Bool = true) {
     let envelope: [String: Any] = [
-        "version": "1.0",
+        "version": state.protocolVersion,
         "type": type,
         "id": id ?? UUID().uuidString,
         "timestamp": Int(Date().timeIntervalSince1970),
         "payload": payload,
     ]
     if let line = jsonString(envelope) {
-        print(line)
-        fflush(stdout)
+        writeMessage(line)
     }
     if trackAck, let ackId = envelope["id"] as? String {
         state.pending[ackId] = PendingMessage(envelope: envelope, sentAt: Date(), retries: 0)
     }
 }
 
+private func messageTimestamp() -> Int {
+    let now = Date().timeIntervalSince1970
+    return state.protocolVersion == "1.0" ? Int(now) : Int(now * 1000)
+}
+
+private func chooseProtocolVersion(_ offered: Any?) -> String {
+    let offered = offered as? [String] ?? []
+    return supportedProtocolVersions.first { offered.contains($0) } ?? "1.0"
+}
+
 private func sendAck(ackId: String, ok: Bool = true, error: String = "") {
     sendEnvelope(type: "event.ack", payload: ["ack_id": ackId, "ok": ok, "error": error], trackAck: false)
 }
 
 private func emitError(code: String, message: String, recoverable: Bool) {
+    logLine("ERROR", "agent", "\(code): \(message)")
     sendEnvelope(type: "agent.error", payload: [
         "code": code,
         "message": message,
@@ -121,19 +218,28 @@ private func checkAccessibility() -> Bool {
     return AXIsProcessTrustedWithOptions(options)
 }
 
-private func frontmostWeChatApp() -> NSRunningApplication? {
-    let bundleIds = ["com.tencent.xinWeChat", "com.tencent.WeChat"]
-    for bundleId in bundleIds {
-        if let app = NSRunningApplication.runningApplications(withBundleIdentifier: bundleId).first {
-            return app
-        }
-    }
-    return nil
+private func boundAccount() -> String {
+    let account = ProcessInfo.processInfo.environment[accountEnv] ?? ""
+    return account.trimmingCharacters(in: .whitespacesAndNewlines)
+}
+
+// With several WeChat instances the host names the one to bind: pid, bundle id or app name.
+private func matchesAccount(_ app: NSRunningApplication, _ account: String) -> Bool {
+    guard let bundleId = app.bundleIdentifier,
+          weChatBundleIds.contains(where: { bundleId.hasPrefix($0) }) else { return false }
+    let appName = app.bundleURL?.deletingPathExtension().lastPathComponent
+    return String(app.processIdentifier) == account
+        || bundleId == account
+        || app.localizedName == account
+        || appName == account
 }
 
 private func weChatApp() -> NSRunningApplication? {
-    let bundleIds = ["com.tencent.xinWeChat", "com.tencent.WeChat"]
-    for bundleId in bundleIds {
+    let account = boundAccount()
+    if !account.isEmpty {
+        return NSWorkspace.shared.runningApplications.first { matchesAccount($0, account) }
+    }
+    for bundleId in weChatBundleIds {
         if let app = NSRunningApplication.runningApplications(withBundleIdentifier: bundleId).first {
             return app
         }
@@ -459,7 +565,7 @@ private func pollMessages() {
             "is_group": resolveIsGroup(kind: kind, title: title) as Any,
             "sender_name": senderName as Any,
             "text": latest as Any,
-            "timestamp": Int(Date().timeIntervalSince1970) as Any,
+            "timestamp": messageTimestamp() as Any,
             "msg_id": NSNull(),
         ])
     }
@@ -473,14 +579,41 @@ private func pasteViaAppleScript() -> Bool {
     return error == nil
 }
 
-private func writeInput(chatId: String, text: String, restoreClipboard: Bool) {
+private func mentionText(sender: String, text: String) -> String {
+    let sender = sender.trimmingCharacters(in: .whitespacesAndNewlines)
+    if sender.isEmpty || text.isEmpty || text.hasPrefix("@\(sender)") {
+        return text
+    }
+    return "@\(sender)\u{2005}\(text)"
+}
+
+private func sendInputResult(requestId: String?, ok: Bool, error: String) {
+    if !ok {
+        logLine("WARN", "input", "write failed: \(error)")
+    }
+    var payload: [String: Any] = ["ok": ok, "error": error]
+    if let requestId {
+        payload["request_id"] = requestId
+    }
+    sendEnvelope(type: "input.result", payload: payload, trackAck: true)
+}
+
+private func pressReturnViaAppleScript() -> Bool {
+    let script = "tell application \"System Events\" to key code 36"
+    let appleScript = NSAppleScript(source: script)
+    var error: NSDictionary?
+    appleScript?.executeAndReturnError(&error)
+    return error == nil
+}
+
+private func writeInput(chatId: String, text: String, restoreClipboard: Bool, requestId: String?, send: Bool) {
     let _ = chatId
     guard checkAccessibility() else {
-        sendEnvelope(type: "input.result", payload: ["ok": false, "error": "Accessibility permission missing"])
+        sendInputResult(requestId: requestId, ok: false, error: "Accessibility permission missing")
         return
     }
-    guard let app = frontmostWeChatApp() else {
-        sendEnvelope(type: "input.result", payload: ["ok": false, "error": "WeChat is not running"])
+    guard let app = weChatApp() else {
+        sendInputResult(requestId: requestId, ok: false, error: "WeChat is not running")
         return
     }
     app.activate(options: [.activateAllWindows])
@@ -499,8 +632,12 @@ private func writeInput(chatId: String, text: String, restoreClipboard: Bool) {
     pasteboard.clearContents()
     pasteboard.setString(text, forType: .string)
 
-    let ok = pasteViaAppleScript()
-    sendEnvelope(type: "input.result", payload: ["ok": ok, "error": ok ? "" : "write failed"], trackAck: true)
+    var ok = pasteViaAppleScript()
+    if ok && send {
+        Thread.sleep(forTimeInterval: 0.1)
+        ok = pressReturnViaAppleScript()
+    }
+    sendInputResult(requestId: requestId, ok: ok, error: ok ? "" : "write failed")
 
     if restoreClipboard {
         pasteboard.clearContents()
@@ -539,10 +676,18 @@ private func handleCommand(_ message: [String: Any]) {
     }
 
     if !msgId.isEmpty {
-        sendAck(ackId: msgId)
+        // A resent write must not paste twice.
+        if state.handledIds.contains(msgId) { return }
+        state.handledIds.append(msgId)
+        if state.handledIds.count > maxHandledIds {
+            state.handledIds.removeFirst(state.handledIds.count - maxHandledIds)
+        }
     }
 
     switch msgType {
+    case "host.hello":
+        state.protocolVersion = chooseProtocolVersion(payload["supported_versions"])
+        sendEnvelope(type: "agent.hello", payload: ["version": state.protocolVersion], trackAck: false)
     case "listen.start", "listen.resume":
         if let interval = payload["poll_interval_ms"] as? Double, interval >= 200 {
             state.pollInterval = max(interval / 1000.0, 0.2)
@@ -575,14 +720,33 @@ private func handleCommand(_ message: [String: Any]) {
         state.cachedMessageLists.removeAll()
         state.cachedSessionLists.removeAll()
         state.cachedInputs.removeAll()
+    case "listen.config":
+        if let interval = payload["poll_interval_ms"] as? Double, interval >= 200 {
+            state.pollInterval = max(interval / 1000.0, 0.2)
+        } else if let interval = payload["poll_interval_ms"] as? Int, interval >= 200 {
+            state.pollInterval = max(Double(interval) / 1000.0, 0.2)
+        }
+        if let targetsRaw = payload["targets"] {
+            let normalized = normalizeListenTargets(targetsRaw)
+            state.listenTargets = normalized
+            state.lastMessageKeys = state.lastMessageKeys.filter { normalized.keys.contains($0.key) }
+            state.cachedMessageLists.removeAll()
+            state.cachedSessionLists.removeAll()
+            state.cachedInputs.removeAll()
+        }
     case "input.write":
         let chatId = (payload["chat_id"] as? String ?? "").trimmingCharacters(in: .whitespacesAndNewlines)
-        let text = (payload["text"] as? String ?? "").trimmingCharacters(in: .whitespacesAndNewlines)
+        var text = (payload["text"] as? String ?? "").trimmingCharacters(in: .whitespacesAndNewlines)
         let restore = payload["restore_clipboard"] as? Bool ?? true
+        let requestId = (payload["request_id"] as? String).flatMap { $0.isEmpty ? nil : $0 }
+        let send = payload["send"] as? Bool ?? false
+        if let quote = payload["quote"] as? [String: Any], let sender = quote["sender_name"] as? String {
+            text = mentionText(sender: sender, text: text)
+        }
         if chatId.isEmpty || text.isEmpty {
-            sendEnvelope(type: "input.result", payload: ["ok": false, "error": "chat_id 或内容为空"], trackAck: true)
+            sendInputResult(requestId: requestId, ok: false, error: "chat_id 或内容为空")
         } else {
-            writeInput(chatId: chatId, text: text, restoreClipboard: restore)
+            writeInput(chatId: chatId, text: text, restoreClipboard: restore, requestId: requestId, send: send)
         }
     case "chats.list":
         let requestId = (payload["request_id"] as? String ?? "").trimmingCharacters(in: .whitespacesAndNewlines)
@@ -596,16 +760,33 @@ private func handleCommand(_ message: [String: Any]) {
     }
 }
 
-private func readStdin() {
+private func handleCommandData(_ data: Data) {
+    guard let obj = try? JSONSerialization.jsonObject(with: data),
+          let dict = obj as? [String: Any] else {
+        return
+    }
+    if (dict["type"] as? String) != "event.ack", let msgId = dict["id"] as? String, !msgId.isEmpty {
+        sendAck(ackId: msgId)
+    }
+    commandQueue.async {
+        handleCommand(dict)
+    }
+}
+
+private func readCommands() {
+    if transport.fd >= 0 {
+        while let header = readExact(transport.fd, count: 4) {
+            let length = header.reduce(0) { ($0 << 8) | Int($1) }
+            guard length <= maxFrameBytes, let body = readExact(transport.fd, count: length) else { return }
+            handleCommandData(body)
+        }
+        return
+    }
     while let line = readLine() {
         let trimmed = line.trimmingCharacters(in: .whitespacesAndNewlines)
         if trimmed.isEmpty { continue }
-        guard let data = trimmed.data(using: .utf8),
-              let obj = try? JSONSerialization.jsonObject(with: data),
-              let dict = obj as? [String: Any] else {
-            continue
-        }
-        handleCommand(dict)
+        guard let data = trimmed.data(using: .utf8) else { continue }
+        handleCommandData(data)
     }
 }
 
@@ -618,13 +799,14 @@ private func processPending() {
             continue
         }
         if let line = jsonString(pending.envelope) {
-            print(line)
-            fflush(stdout)
+            writeMessage(line)
         }
         state.pending[id] = PendingMessage(envelope: pending.envelope, sentAt: now, retries: pending.retries + 1)
     }
 }
 
+connectTransport()
+
 sendEnvelope(type: "agent.ready", payload: [
     "platform": "macos",
     "agent_version": "0.1.0",
@@ -632,8 +814,12 @@ sendEnvelope(type: "agent.ready", payload: [
     "supports_clipboard_restore": true,
 ])
 
+if !boundAccount().isEmpty && weChatApp() == nil {
+    emitError(code: "WECHAT_NOT_RUNNING", message: "WeChat instance \(boundAccount()) not found", recoverable: true)
+}
+
 DispatchQueue.global().async {
-    readStdin()
+    readCommands()
 }
 
 let timer = DispatchSource.makeTimerSource(queue: DispatchQueue.global())
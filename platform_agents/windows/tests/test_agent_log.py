@@ -0,0 +1,23 @@
+import io
+import os
+import sys
+import unittest
+from contextlib import redirect_stderr
+
+ROOT = os.path.abspath(os.path.join(os.path.dirname(__file__), ".."))
+if ROOT not in sys.path:
+    sys.path.insert(0, ROOT)
+
+from wxauto_agent import log
+
+
+class AgentLogTests(unittest.TestCase):
+    def test_writes_single_structured_line(self):
+        buffer = io.StringIO()
+        with redirect_stderr(buffer):
+            log("ERROR", "agent", "LISTEN_FAILED: 第一行\n第二行")
+        self.assertEqual(buffer.getvalue(), "ERROR|agent|LISTEN_FAILED: 第一行 第二行\n")
+
+
+if __name__ == "__main__":
+    unittest.main()
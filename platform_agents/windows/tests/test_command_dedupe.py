@@ -0,0 +1,30 @@
+import os
+import sys
+import unittest
+
+ROOT = os.path.abspath(os.path.join(os.path.dirname(__file__), ".."))
+if ROOT not in sys.path:
+    sys.path.insert(0, ROOT)
+
+import wxauto_agent
+from wxauto_agent import MAX_HANDLED_IDS, first_delivery
+
+
+class CommandDedupeTests(unittest.TestCase):
+    def setUp(self):
+        wxauto_agent.STATE.handled_ids.clear()
+
+    def test_resent_ids_are_handled_once(self):
+        self.assertTrue(first_delivery("m1"))
+        self.assertFalse(first_delivery("m1"))
+        self.assertTrue(first_delivery("m2"))
+
+    def test_remembers_only_recent_ids(self):
+        for index in range(MAX_HANDLED_IDS + 1):
+            first_delivery(f"m{index}")
+        self.assertEqual(len(wxauto_agent.STATE.handled_ids), MAX_HANDLED_IDS)
+        self.assertTrue(first_delivery("m0"))
+
+
+if __name__ == "__main__":
+    unittest.main()
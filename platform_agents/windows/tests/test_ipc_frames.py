@@ -0,0 +1,44 @@
+import io
+import os
+import sys
+import unittest
+
+ROOT = os.path.abspath(os.path.join(os.path.dirname(__file__), ".."))
+if ROOT not in sys.path:
+    sys.path.insert(0, ROOT)
+
+from wxauto_agent import (
+    MAX_FRAME_BYTES,
+    binary_frames_available,
+    decode_frame,
+    encode_frame,
+    read_frame,
+)
+
+
+class IpcFrameTests(unittest.TestCase):
+    def test_round_trips_length_prefixed_frames(self):
+        stream = io.BytesIO(encode_frame({"type": "agent.ready"}) + encode_frame({"text": "你好\n"}))
+        self.assertEqual(read_frame(stream), b'{"type": "agent.ready"}')
+        self.assertEqual(read_frame(stream), '{"text": "你好\\n"}'.encode("utf-8"))
+        self.assertIsNone(read_frame(stream))
+
+    def test_rejects_truncated_and_oversized_frames(self):
+        self.assertIsNone(read_frame(io.BytesIO(encode_frame({"a": 1})[:-1])))
+        oversized = (MAX_FRAME_BYTES + 1).to_bytes(4, "big")
+        self.assertIsNone(read_frame(io.BytesIO(oversized)))
+
+    @unittest.skipUnless(binary_frames_available(), "msgpack/zstandard not installed")
+    def test_compresses_large_messages_when_negotiated(self):
+        message = {"type": "chats.list.result", "payload": {"chats": [{"title": "项目群"}] * 2000}}
+        plain = read_frame(io.BytesIO(encode_frame(message)))
+        binary = read_frame(io.BytesIO(encode_frame(message, True)))
+        self.assertLess(len(binary), len(plain))
+        self.assertEqual(decode_frame(binary), message)
+        self.assertEqual(decode_frame(plain), message)
+        small = read_frame(io.BytesIO(encode_frame({"type": "agent.ready"}, True)))
+        self.assertEqual(small[:1], b"{")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,39 @@
+import os
+import sys
+import unittest
+from unittest import mock
+
+ROOT = os.path.abspath(os.path.join(os.path.dirname(__file__), ".."))
+if ROOT not in sys.path:
+    sys.path.insert(0, ROOT)
+
+import wxauto_agent
+from wxauto_agent import STATE, emit_new_messages
+
+
+class MessageBatchTests(unittest.TestCase):
+    def tearDown(self):
+        STATE.message_batch = False
+
+    def test_bursts_are_sent_as_one_batch_when_negotiated(self):
+        payloads = [{"chat_id": "c1", "text": "在吗"}, {"chat_id": "c1", "text": "急"}]
+        STATE.message_batch = True
+        with mock.patch.object(wxauto_agent, "send_with_ack") as send:
+            emit_new_messages(payloads)
+        send.assert_called_once_with("message.batch", {"messages": payloads})
+
+    def test_single_messages_and_legacy_hosts_use_message_new(self):
+        payloads = [{"chat_id": "c1", "text": "在吗"}, {"chat_id": "c2", "text": "好的"}]
+        with mock.patch.object(wxauto_agent, "send_with_ack") as send:
+            emit_new_messages(payloads)
+            STATE.message_batch = True
+            emit_new_messages(payloads[:1])
+            emit_new_messages([])
+        self.assertEqual(
+            [call.args for call in send.call_args_list],
+            [("message.new", payloads[0]), ("message.new", payloads[1]), ("message.new", payloads[0])],
+        )
+
+
+if __name__ == "__main__":
+    unittest.main()
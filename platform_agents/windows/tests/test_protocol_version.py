@@ -0,0 +1,23 @@
+import os
+import sys
+import unittest
+
+ROOT = os.path.abspath(os.path.join(os.path.dirname(__file__), ".."))
+if ROOT not in sys.path:
+    sys.path.insert(0, ROOT)
+
+from wxauto_agent import choose_protocol_version
+
+
+class ProtocolVersionTests(unittest.TestCase):
+    def test_picks_highest_shared_version(self):
+        self.assertEqual(choose_protocol_version(["1.1", "1.0"]), "1.1")
+        self.assertEqual(choose_protocol_version(["1.0"]), "1.0")
+
+    def test_falls_back_to_legacy_version(self):
+        self.assertEqual(choose_protocol_version(["2.0"]), "1.0")
+        self.assertEqual(choose_protocol_version(None), "1.0")
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,41 @@
+import os
+import sys
+import unittest
+
+ROOT = os.path.abspath(os.path.join(os.path.dirname(__file__), ".."))
+if ROOT not in sys.path:
+    sys.path.insert(0, ROOT)
+
+import wxauto_agent
+from wxauto_agent import mention_text, remember_quotable_message, select_quote
+
+
+class ReplyQuoteTests(unittest.TestCase):
+    def setUp(self):
+        wxauto_agent.STATE.quotable_messages.clear()
+
+    def test_mention_prefixes_sender_once(self):
+        self.assertEqual(mention_text("李四", "可以"), "@李四 可以")
+        self.assertEqual(mention_text("李四", "@李四 可以"), "@李四 可以")
+        self.assertEqual(mention_text(" ", "可以"), "可以")
+
+    def test_quotable_messages_are_bounded(self):
+        for index in range(wxauto_agent.MAX_QUOTABLE_MESSAGES + 5):
+            remember_quotable_message(f"m{index}", object())
+        self.assertEqual(
+            len(wxauto_agent.STATE.quotable_messages), wxauto_agent.MAX_QUOTABLE_MESSAGES
+        )
+        self.assertNotIn("m0", wxauto_agent.STATE.quotable_messages)
+
+    def test_select_quote_uses_cached_message(self):
+        class FakeMessage:
+            def select_option(self, option, timeout=None):
+                return option == "引用"
+
+        remember_quotable_message("m1", FakeMessage())
+        self.assertTrue(select_quote({"msg_id": "m1"}))
+        self.assertFalse(select_quote({"msg_id": "missing"}))
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -1,7 +1,9 @@
 import json
 import os
 import queue
+import struct
 import sys
+import tempfile
 import threading
 import time
 import uuid
@@ -17,11 +19,31 @@ try:
 except Exception:
     WeChat = None
 
+try:
+    import msgpack
+    import zstandard
+except ImportError:
+    msgpack = None
+    zstandard = None
+
 
 ACK_TIMEOUT_SECONDS = 3
 MAX_ACK_RETRIES = 3
+# Host message ids remembered to drop resent duplicates.
+MAX_HANDLED_IDS = 512
 DEFAULT_POLL_INTERVAL = 0.8
 LISTEN_TARGET_KINDS = {"direct", "group", "unknown"}
+MAX_QUOTABLE_MESSAGES = 200
+MENTION_SEPARATOR = "\u2005"
+SUPPORTED_PROTOCOL_VERSIONS = ["1.1", "1.0"]
+IMAGE_DIR = os.path.join(tempfile.gettempdir(), "wereply_images")
+IPC_ENDPOINT_ENV = "WEREPLY_IPC_ENDPOINT"
+ACCOUNT_ENV = "WEREPLY_ACCOUNT_ID"
+MAX_FRAME_BYTES = 16 * 1024 * 1024
+CAPABILITY_MSGPACK_ZSTD = "frame.msgpack_zstd"
+CAPABILITY_MESSAGE_BATCH = "message.batch"
+BINARY_FRAME_MIN_BYTES = 16 * 1024
+ZSTD_MAGIC = b"\x28\xb5\x2f\xfd"
 
 
 @dataclass
@@ -42,16 +64,99 @@ class AgentState:
     listen_targets: Dict[str, str] = field(default_factory=dict)
     active_targets: Dict[str, str] = field(default_factory=dict)
     active_kinds: Dict[str, str] = field(default_factory=dict)
+    quotable_messages: Dict[str, Any] = field(default_factory=dict)
+    download_images: bool = False
+    protocol_version: str = "1.0"
+    binary_frames: bool = False
+    message_batch: bool = False
+    handled_ids: Dict[str, None] = field(default_factory=dict)
+
+
+@dataclass
+class Transport:
+    reader: Optional[Any] = None
+    writer: Optional[Any] = None
+    lock: Any = field(default_factory=threading.Lock)
 
 
 STATE = AgentState()
+TRANSPORT = Transport()
 COMMAND_QUEUE: "queue.Queue[Dict[str, Any]]" = queue.Queue()
 MESSAGE_QUEUE: "queue.Queue[Tuple[Any, Any, str]]" = queue.Queue()
 
 
+def binary_frames_available() -> bool:
+    return msgpack is not None and zstandard is not None
+
+
+def log(level: str, component: str, message: str) -> None:
+    line = " ".join(str(message).splitlines())
+    sys.stderr.write(f"{level}|{component}|{line}\n")
+    sys.stderr.flush()
+
+
+def encode_frame(message: Dict[str, Any], binary: bool = False) -> bytes:
+    data = json.dumps(message, ensure_ascii=False).encode("utf-8")
+    if binary and len(data) >= BINARY_FRAME_MIN_BYTES:
+        packed = msgpack.packb(message, use_bin_type=True)
+        data = zstandard.ZstdCompressor().compress(packed)
+    return struct.pack(">I", len(data)) + data
+
+
+def decode_frame(frame: bytes) -> Any:
+    if frame.startswith(ZSTD_MAGIC):
+        if not binary_frames_available():
+            raise ValueError("binary frame not supported")
+        packed = zstandard.ZstdDecompressor().decompress(frame, max_output_size=MAX_FRAME_BYTES)
+        return msgpack.unpackb(packed, raw=False)
+    return json.loads(frame.decode("utf-8"))
+
+
+def read_exact(stream: Any, size: int) -> Optional[bytes]:
+    chunks: List[bytes] = []
+    remaining = size
+    while remaining > 0:
+        chunk = stream.read(remaining)
+        if not chunk:
+            return None
+        chunks.append(chunk)
+        remaining -= len(chunk)
+    return b"".join(chunks)
+
+
+def read_frame(stream: Any) -> Optional[bytes]:
+    header = read_exact(stream, 4)
+    if header is None:
+        return None
+    (length,) = struct.unpack(">I", header)
+    if length > MAX_FRAME_BYTES:
+        return None
+    return read_exact(stream, length)
+
+
+def connect_transport() -> None:
+    endpoint = os.environ.get(IPC_ENDPOINT_ENV, "").strip()
+    if not endpoint:
+        return
+    try:
+        TRANSPORT.writer = open(endpoint + "-up", "wb")
+        TRANSPORT.reader = open(endpoint + "-down", "rb")
+    except OSError as exc:
+        if TRANSPORT.writer is not None:
+            TRANSPORT.writer.close()
+        TRANSPORT.writer = None
+        TRANSPORT.reader = None
+        log("WARN", "ipc", f"connect ipc endpoint failed, using stdio: {exc}")
+
+
 def send_json(message: Dict[str, Any]) -> None:
-    sys.stdout.write(json.dumps(message, ensure_ascii=False) + "\n")
-    sys.stdout.flush()
+    with TRANSPORT.lock:
+        if TRANSPORT.writer is not None:
+            TRANSPORT.writer.write(encode_frame(message, STATE.binary_frames))
+            TRANSPORT.writer.flush()
+            return
+        sys.stdout.write(json.dumps(message, ensure_ascii=False) + "\n")
+        sys.stdout.flush()
 
 
 def normalize_listen_targets(raw_targets: Any) -> List[Dict[str, str]]:
@@ -122,7 +227,7 @@ def find_wechat_main_hwnd() -> Optional[int]:
 
 def envelope(msg_type: str, payload: Dict[str, Any], msg_id: Optional[str] = None) -> Dict[str, Any]:
     return {
-        "version": "1.0",
+        "version": STATE.protocol_version,
         "type": msg_type,
         "id": msg_id or str(uuid.uuid4()),
         "timestamp": int(time.time()),
@@ -130,6 +235,20 @@ def envelope(msg_type: str, payload: Dict[str, Any], msg_id: Optional[str] = Non
     }
 
 
+def message_timestamp() -> int:
+    if STATE.protocol_version == "1.0":
+        return int(time.time())
+    return int(time.time() * 1000)
+
+
+def choose_protocol_version(offered: Any) -> str:
+    if isinstance(offered, list):
+        for version in SUPPORTED_PROTOCOL_VERSIONS:
+            if version in offered:
+                return version
+    return "1.0"
+
+
 def send_with_ack(msg_type: str, payload: Dict[str, Any]) -> None:
     env = envelope(msg_type, payload)
     send_json(env)
@@ -140,11 +259,14 @@ def send_ack(ack_id: str, ok: bool = True, error: str = "") -> None:
     send_json(envelope("event.ack", {"ack_id": ack_id, "ok": ok, "error": error}))
 
 
-def emit_error(code: str, message: str, recoverable: bool = True) -> None:
-    send_with_ack(
-        "agent.error",
-        {"code": code, "message": message, "recoverable": recoverable},
-    )
+def emit_error(
+    code: str, message: str, recoverable: bool = True, chat_id: Optional[str] = None
+) -> None:
+    log("ERROR", "agent", f"{code}: {message}")
+    payload = {"code": code, "message": message, "recoverable": recoverable}
+    if chat_id:
+        payload["chat_id"] = chat_id
+    send_with_ack("agent.error", payload)
 
 
 def emit_status(state: str, detail: str = "") -> None:
@@ -155,19 +277,30 @@ def ensure_wechat() -> Any:
     if STATE.wx is None:
         if WeChat is None:
             raise RuntimeError("wxauto 未安装")
+        # 多开时主程序通过环境变量指定要绑定的微信昵称
+        account = os.environ.get(ACCOUNT_ENV, "").strip()
         try:
-            STATE.wx = WeChat()
+            STATE.wx = WeChat(nickname=account) if account else WeChat()
         except Exception as exc:
             message = str(exc)
+            if account:
+                raise
             if "未找到微信窗口" not in message and "未找到已登录的微信主窗口" not in message:
                 raise
             fallback_hwnd = find_wechat_main_hwnd()
             if not fallback_hwnd:
                 raise
             STATE.wx = WeChat(hwnd=fallback_hwnd)
+        emit_profile(STATE.wx)
     return STATE.wx
 
 
+def emit_profile(wx: Any) -> None:
+    nickname = str(getattr(wx, "nickname", "") or "").strip()
+    if nickname:
+        send_with_ack("agent.profile", {"nickname": nickname})
+
+
 def get_current_chat_title(wx: Any) -> str:
     for attr in ("GetCurrentChat", "CurrentChat", "GetCurrentChatName", "GetChatName"):
         getter = getattr(wx, attr, None)
@@ -200,6 +333,39 @@ def extract_message_text(message: Any) -> str:
     return ""
 
 
+CONTENT_TYPES = {
+    "text": "text",
+    "quote": "text",
+    "image": "image",
+    "video": "image",
+    "voice": "voice",
+    "link": "link",
+    "emotion": "sticker",
+    "file": "file",
+}
+
+
+def extract_content_type(message: Any) -> str:
+    value = message.get("type") if isinstance(message, dict) else getattr(message, "type", None)
+    return CONTENT_TYPES.get(str(value or ""), "text")
+
+
+def download_image(message: Any) -> Optional[str]:
+    if not STATE.download_images or extract_content_type(message) != "image":
+        return None
+    download = getattr(message, "download", None)
+    if not callable(download):
+        return None
+    try:
+        path = download(dir_path=IMAGE_DIR)
+    except Exception as exc:
+        emit_error("IMAGE_DOWNLOAD_FAILED", str(exc), True)
+        return None
+    if isinstance(path, (str, os.PathLike)) and os.path.isfile(path):
+        return str(path)
+    return None
+
+
 def extract_sender_name(message: Any) -> str:
     if isinstance(message, dict):
         for key in ("sender_remark", "sender", "name", "from"):
@@ -264,38 +430,99 @@ def listen_callback(message: Any, chat: Any) -> None:
     MESSAGE_QUEUE.put((message, chat, chat_name))
 
 
-def handle_incoming_message(message: Any, chat: Any, chat_name: str) -> None:
+def is_self_message(message: Any) -> bool:
+    if isinstance(message, dict):
+        return message.get("attr") == "self" or message.get("type") == "self"
+    return getattr(message, "attr", None) == "self"
+
+
+def handle_incoming_message(message: Any, chat: Any, chat_name: str) -> Optional[Dict[str, Any]]:
     text = extract_message_text(message)
     if not text:
-        return
+        return None
+    if is_self_message(message):
+        chat_title = resolve_chat_title(chat, chat_name)
+        send_json(
+            envelope(
+                "message.sent",
+                {
+                    "chat_id": chat_title,
+                    "chat_title": chat_title,
+                    "text": text,
+                    "timestamp": message_timestamp(),
+                },
+            )
+        )
+        return None
     msg_id = extract_msg_id(message)
     msg_hash = getattr(message, "hash", None)
     key = msg_id or (str(msg_hash) if msg_hash else f"{extract_sender_name(message)}:{text}")
     if STATE.last_message_keys.get(chat_name) == key:
-        return
+        return None
     STATE.last_message_keys[chat_name] = key
+    if msg_id:
+        remember_quotable_message(msg_id, message)
 
     kind = STATE.active_kinds.get(chat_name, "unknown")
     chat_title = resolve_chat_title(chat, chat_name)
-    payload = {
+    return {
         "chat_id": chat_title,
         "chat_title": chat_title,
         "is_group": resolve_is_group(chat, kind),
         "sender_name": extract_sender_name(message) or chat_title,
         "text": text,
-        "timestamp": int(time.time()),
+        "timestamp": message_timestamp(),
         "msg_id": msg_id,
+        "content_type": extract_content_type(message),
+        "image_path": download_image(message),
     }
-    send_with_ack("message.new", payload)
+
+
+def emit_new_messages(payloads: List[Dict[str, Any]]) -> None:
+    if len(payloads) > 1 and STATE.message_batch:
+        send_with_ack("message.batch", {"messages": payloads})
+        return
+    for payload in payloads:
+        send_with_ack("message.new", payload)
+
+
+def remember_quotable_message(msg_id: str, message: Any) -> None:
+    STATE.quotable_messages.pop(msg_id, None)
+    STATE.quotable_messages[msg_id] = message
+    while len(STATE.quotable_messages) > MAX_QUOTABLE_MESSAGES:
+        oldest = next(iter(STATE.quotable_messages))
+        STATE.quotable_messages.pop(oldest, None)
+
+
+def mention_text(sender_name: str, text: str) -> str:
+    sender_name = sender_name.strip()
+    if not sender_name or text.startswith(f"@{sender_name}"):
+        return text
+    return f"@{sender_name}{MENTION_SEPARATOR}{text}"
+
+
+def select_quote(quote: Dict[str, Any]) -> bool:
+    msg_id = str(quote.get("msg_id") or "").strip()
+    message = STATE.quotable_messages.get(msg_id) if msg_id else None
+    if message is None or not hasattr(message, "select_option"):
+        return False
+    try:
+        return bool(message.select_option("引用", timeout=3))
+    except Exception:
+        return False
 
 
 def drain_message_queue(max_items: int = 50) -> None:
+    payloads: List[Dict[str, Any]] = []
     for _ in range(max_items):
         try:
             message, chat, chat_name = MESSAGE_QUEUE.get_nowait()
         except queue.Empty:
             break
-        handle_incoming_message(message, chat, chat_name)
+        payload = handle_incoming_message(message, chat, chat_name)
+        if payload is not None:
+            payloads.append(payload)
+    emit_new_messages(payloads)
 
 
 def try_ensure_wechat() -> Optional[Any]:
@@ -350,10 +577,12 @@ def reconcile_listeners(desired: Dict[str, str], allow_add: bool) -> None:
         try:
             result = wx.AddListenChat(target_name, listen_callback)
         except Exception as exc:
-            emit_error("LISTEN_TARGET_FAILED", f"{target_name}: {exc}", True)
+            emit_error("LISTEN_TARGET_FAILED", f"{target_name}: {exc}", True, target_name)
             continue
         if not hasattr(result, "ChatInfo"):
-            emit_error("LISTEN_TARGET_FAILED", f"{target_name}: 无法监听该会话", True)
+            emit_error(
+                "LISTEN_TARGET_FAILED", f"{target_name}: 无法监听该会话", True, target_name
+            )
             continue
         chat_name = getattr(result, "who", None) or target_name
         STATE.active_targets[target_name] = chat_name
@@ -367,25 +596,57 @@ def set_listen_targets(raw_targets: Any, allow_add: bool) -> None:
     reconcile_listeners(desired, allow_add)
 
 
-def write_input(chat_id: str, text: str, restore_clipboard: bool) -> None:
+def send_input_result(request_id: Optional[str], ok: bool, error: str = "") -> None:
+    if not ok:
+        log("WARN", "input", f"write failed: {error}")
+    send_with_ack("input.result", {"ok": ok, "error": error, "request_id": request_id})
+
+
+def current_chat(wx: Any) -> Optional[str]:
+    getter = getattr(wx, "CurrentChat", None)
+    if getter is None:
+        return None
+    try:
+        name = getter()
+    except Exception:
+        return None
+    return name.strip() if isinstance(name, str) else None
+
+
+def write_input(
+    chat_id: str,
+    text: str,
+    restore_clipboard: bool,
+    quote: Optional[Dict[str, Any]] = None,
+    request_id: Optional[str] = None,
+    send: bool = False,
+) -> None:
     try:
         wx = ensure_wechat()
     except Exception as exc:
         emit_error("WRITE_FAILED", str(exc), True)
-        send_with_ack("input.result", {"ok": False, "error": str(exc)})
+        send_input_result(request_id, False, str(exc))
         return
 
     try:
-        if hasattr(wx, "ChatWith"):
-            wx.ChatWith(chat_id)
-    except Exception:
-        pass
+        wx.ChatWith(chat_id)
+    except Exception as exc:
+        # Never paste into whichever chat happens to be open.
+        send_input_result(request_id, False, f"无法切换到会话 {chat_id}: {exc}")
+        return
+    active = current_chat(wx)
+    if active is not None and active != chat_id.strip():
+        send_input_result(request_id, False, f"当前会话是 {active}，已取消写入 {chat_id}")
+        return
+
+    if quote and not select_quote(quote):
+        text = mention_text(str(quote.get("sender_name", "")), text)
 
     try:
         import pyperclip
         import pyautogui
     except Exception as exc:
-        send_with_ack("input.result", {"ok": False, "error": str(exc)})
+        send_input_result(request_id, False, str(exc))
         return
 
     previous = None
@@ -397,10 +658,14 @@ def write_input(chat_id: str, text: str, restore_clipboard: bool) -> None:
 
     try:
         pyperclip.copy(text)
+        # Replace any half-typed draft instead of appending to it.
+        pyautogui.hotkey("ctrl", "a")
         pyautogui.hotkey("ctrl", "v")
-        send_with_ack("input.result", {"ok": True, "error": ""})
+        if send:
+            pyautogui.press("enter")
+        send_input_result(request_id, True)
     except Exception as exc:
-        send_with_ack("input.result", {"ok": False, "error": str(exc)})
+        send_input_result(request_id, False, str(exc))
     finally:
         if restore_clipboard and previous is not None:
             try:
@@ -430,6 +695,31 @@ def list_recent_chats() -> List[Dict[str, str]]:
     return results
 
 
+def apply_download_images(payload: Dict[str, Any]) -> None:
+    value = payload.get("download_images")
+    if isinstance(value, bool):
+        STATE.download_images = value
+
+
+def first_delivery(msg_id: str) -> bool:
+    """False for an id already handled, so a resent write is not pasted twice."""
+    if msg_id in STATE.handled_ids:
+        return False
+    STATE.handled_ids[msg_id] = None
+    while len(STATE.handled_ids) > MAX_HANDLED_IDS:
+        STATE.handled_ids.pop(next(iter(STATE.handled_ids)))
+    return True
+
+
+def accept_command(message: Dict[str, Any]) -> None:
+    """Acks on receipt, before the command loop runs the work, so a slow paste
+    never looks like a lost message to the host."""
+    msg_id = message.get("id", "")
+    if message.get("type") != "event.ack" and isinstance(msg_id, str) and msg_id:
+        send_ack(msg_id, True, "")
+    COMMAND_QUEUE.put(message)
+
+
 def handle_command(message: Dict[str, Any]) -> None:
     msg_type = message.get("type", "")
     msg_id = message.get("id", "")
@@ -441,13 +731,36 @@ def handle_command(message: Dict[str, Any]) -> None:
             STATE.pending.pop(ack_id, None)
         return
 
-    if msg_id:
-        send_ack(msg_id, True, "")
+    if msg_id and not first_delivery(msg_id):
+        log("INFO", "ipc", f"ignore duplicate {msg_type}: {msg_id}")
+        return
+
+    if msg_type == "host.hello":
+        STATE.protocol_version = choose_protocol_version(payload.get("supported_versions"))
+        capabilities = []
+        offered = payload.get("capabilities")
+        if not isinstance(offered, list):
+            offered = []
+        if (
+            CAPABILITY_MSGPACK_ZSTD in offered
+            and TRANSPORT.writer is not None
+            and binary_frames_available()
+        ):
+            capabilities.append(CAPABILITY_MSGPACK_ZSTD)
+        if CAPABILITY_MESSAGE_BATCH in offered:
+            capabilities.append(CAPABILITY_MESSAGE_BATCH)
+        send_json(
+            envelope("agent.hello", {"version": STATE.protocol_version, "capabilities": capabilities})
+        )
+        STATE.binary_frames = CAPABILITY_MSGPACK_ZSTD in capabilities
+        STATE.message_batch = CAPABILITY_MESSAGE_BATCH in capabilities
+        return
 
     if msg_type == "listen.start" or msg_type == "listen.resume":
         interval = payload.get("poll_interval_ms")
         if isinstance(interval, (int, float)) and interval >= 200:
             STATE.poll_interval = max(interval / 1000.0, 0.2)
+        apply_download_images(payload)
         STATE.listening = True
         targets = payload.get("targets")
         if targets is not None:
@@ -475,14 +788,31 @@ def handle_command(message: Dict[str, Any]) -> None:
         set_listen_targets(targets, STATE.listening)
         return
 
+    if msg_type == "listen.config":
+        interval = payload.get("poll_interval_ms")
+        if isinstance(interval, (int, float)) and interval >= 200:
+            STATE.poll_interval = max(interval / 1000.0, 0.2)
+        apply_download_images(payload)
+        targets = payload.get("targets")
+        if targets is not None:
+            set_listen_targets(targets, STATE.listening)
+        return
+
     if msg_type == "input.write":
         chat_id = str(payload.get("chat_id", "")).strip()
         text = str(payload.get("text", "")).strip()
         restore = bool(payload.get("restore_clipboard", True))
+        quote = payload.get("quote")
+        send = bool(payload.get("send", False))
+        request_id = payload.get("request_id")
+        if not isinstance(request_id, str) or not request_id:
+            request_id = None
         if not chat_id or not text:
-            send_with_ack("input.result", {"ok": False, "error": "chat_id or text is empty"})
+            send_input_result(request_id, False, "chat_id or text is empty")
             return
-        write_input(chat_id, text, restore)
+        write_input(
+            chat_id, text, restore, quote if isinstance(quote, dict) else None, request_id, send
+        )
         return
 
     if msg_type == "chats.list":
@@ -494,7 +824,17 @@ def handle_command(message: Dict[str, Any]) -> None:
         return
 
 
-def read_stdin() -> None:
+def read_commands() -> None:
+    if TRANSPORT.reader is not None:
+        while True:
+            frame = read_frame(TRANSPORT.reader)
+            if frame is None:
+                return
+            try:
+                msg = decode_frame(frame)
+            except Exception:
+                continue
+            accept_command(msg)
     for line in sys.stdin:
         line = line.strip()
         if not line:
@@ -503,7 +843,7 @@ def read_stdin() -> None:
             msg = json.loads(line)
         except json.JSONDecodeError:
             continue
-        COMMAND_QUEUE.put(msg)
+        accept_command(msg)
 
 
 def process_pending() -> None:
@@ -520,17 +860,20 @@ def process_pending() -> None:
 
 
 def main() -> None:
+    connect_transport()
+    if WeChat is None:
+        log("WARN", "wechat", "wxauto not available")
     send_with_ack(
         "agent.ready",
         {
             "platform": "windows",
         "agent_version": "0.1.0",
-            "capabilities": ["listen", "write", "chats.list"],
+            "capabilities": ["listen", "write", "chats.list", "quote"],
             "supports_clipboard_restore": True,
         },
     )
 
-    reader = threading.Thread(target=read_stdin, daemon=True)
+    reader = threading.Thread(target=read_commands, daemon=True)
     reader.start()
 
     while True:
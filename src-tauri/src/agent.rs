@@ -1,31 +1,261 @@
+use crate::agent_log;
 use crate::ipc::{
-    parse_envelope, AgentErrorPayload, AgentReadyPayload, AgentStatusPayload, ChatsListResultPayload,
-    IpcEnvelope, InputResultPayload, MessageNewPayload,
+    encode_message, negotiate, parse_message, AgentErrorPayload, AgentProfilePayload,
+    AgentReadyPayload, AgentStatusPayload, EventAckPayload, IpcEnvelope, InputResultPayload,
+    ListenControlPayload, MessageBatchPayload, MessageNewPayload, MessageSentPayload, Negotiation,
+};
+use crate::ipc_transport::{self, Inbound, Listener, Outbound};
+use crate::message_pipeline::{
+    handle_incoming_batch, handle_incoming_message, handle_outgoing_message,
+};
+use crate::power;
+use crate::state::{now_secs, AppState};
+use crate::types::{
+    suggested_action_for_code, AgentInfo, ErrorPayload, Platform, RuntimeState, SuggestedAction,
 };
-use crate::message_pipeline::handle_incoming_message;
-use crate::state::AppState;
-use crate::types::{ErrorPayload, Platform, RuntimeState};
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 use tauri::AppHandle;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::task::JoinHandle;
-use tokio::time::{timeout, Duration};
+use tokio::time::{timeout, Duration, Instant};
 use tauri::{Emitter, Manager};
 use tracing::{info, warn};
 
 pub struct AgentHandle {
+    account_id: String,
+    sender: mpsc::Sender<IpcEnvelope>,
+    pending: Arc<PendingRequests>,
+    negotiation: Arc<std::sync::Mutex<Negotiation>>,
+    telemetry: Arc<AgentTelemetry>,
+    started_at: u64,
+    child: tokio::process::Child,
+    read_handle: JoinHandle<()>,
+    write_handle: JoinHandle<()>,
+    stdout_handle: Option<JoinHandle<()>>,
+    stderr_handle: JoinHandle<()>,
+    ack_handle: JoinHandle<()>,
+}
+
+pub const ACCOUNT_ENV: &str = "WEREPLY_ACCOUNT_ID";
+const IPC_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const ACK_TIMEOUT: Duration = Duration::from_secs(3);
+const ACK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_ACK_RESENDS: u32 = 1;
+/// Messages that act on WeChat and must never be resent: a late ack would turn
+/// the resend into a second paste or send. Their outcome arrives as a result.
+const NO_RESEND_TYPES: [&str; 1] = ["input.write"];
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(60);
+const MAX_RESTART_ATTEMPTS: u32 = 8;
+const RESTART_STABLE_SECS: u64 = 120;
+
+#[derive(Debug, Clone, Default)]
+pub struct RestartBackoff {
+    attempts: u32,
+    last_attempt_at: u64,
+}
+
+impl RestartBackoff {
+    pub fn next_delay(&mut self, now: u64) -> Option<Duration> {
+        if now.saturating_sub(self.last_attempt_at) >= RESTART_STABLE_SECS {
+            self.attempts = 0;
+        }
+        if self.attempts >= MAX_RESTART_ATTEMPTS {
+            return None;
+        }
+        let delay = RESTART_BASE_DELAY
+            .saturating_mul(1 << self.attempts.min(16))
+            .min(RESTART_MAX_DELAY);
+        self.attempts += 1;
+        self.last_attempt_at = now;
+        Some(delay)
+    }
+}
+
+pub fn request_timeout(request_type: &str) -> Duration {
+    match request_type {
+        "chats.list" => Duration::from_secs(3),
+        "input.write" => Duration::from_secs(10),
+        _ => DEFAULT_REQUEST_TIMEOUT,
+    }
+}
+
+fn response_type(request_type: &str) -> String {
+    match request_type {
+        "input.write" => "input.result".to_string(),
+        other => format!("{}.result", other),
+    }
+}
+
+#[derive(Default)]
+pub struct PendingRequests {
+    waiting: std::sync::Mutex<HashMap<String, (String, oneshot::Sender<Value>)>>,
+}
+
+impl PendingRequests {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (String, oneshot::Sender<Value>)>> {
+        self.waiting.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub fn register(&self, request_type: &str, request_id: &str) -> oneshot::Receiver<Value> {
+        let (sender, receiver) = oneshot::channel();
+        self.lock().insert(
+            request_id.to_string(),
+            (response_type(request_type), sender),
+        );
+        receiver
+    }
+
+    pub fn cancel(&self, request_id: &str) {
+        self.lock().remove(request_id);
+    }
+
+    pub fn resolve(&self, envelope: IpcEnvelope) -> Option<IpcEnvelope> {
+        let mut waiting = self.lock();
+        let request_id = match envelope
+            .payload
+            .get("request_id")
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+        {
+            Some(request_id) => request_id.to_string(),
+            None => {
+                let mut matching = waiting
+                    .iter()
+                    .filter(|(_, (response, _))| *response == envelope.r#type);
+                match (matching.next(), matching.next()) {
+                    (Some((request_id, _)), None) => request_id.clone(),
+                    _ => return Some(envelope),
+                }
+            }
+        };
+        if !matches!(waiting.get(&request_id), Some((response, _)) if *response == envelope.r#type)
+        {
+            return Some(envelope);
+        }
+        if let Some((_, sender)) = waiting.remove(&request_id) {
+            let _ = sender.send(envelope.payload);
+        }
+        None
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+struct OutstandingEnvelope {
+    envelope: IpcEnvelope,
+    sent_at: Instant,
+    resends: u32,
+}
+
+#[derive(Debug)]
+pub enum AckAction {
+    Resend(IpcEnvelope),
+    GiveUp(IpcEnvelope),
+}
+
+#[derive(Default)]
+pub struct AckTracker {
+    outstanding: std::sync::Mutex<HashMap<String, OutstandingEnvelope>>,
+}
+
+impl AckTracker {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, OutstandingEnvelope>> {
+        self.outstanding
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub fn track(&self, envelope: &IpcEnvelope, now: Instant) {
+        if envelope.r#type == "event.ack" || NO_RESEND_TYPES.contains(&envelope.r#type.as_str()) {
+            return;
+        }
+        self.lock()
+            .entry(envelope.id.clone())
+            .or_insert_with(|| OutstandingEnvelope {
+                envelope: envelope.clone(),
+                sent_at: now,
+                resends: 0,
+            });
+    }
+
+    pub fn acknowledge(&self, ack_id: &str) -> Option<IpcEnvelope> {
+        self.lock().remove(ack_id).map(|item| item.envelope)
+    }
+
+    pub fn expire(&self, now: Instant) -> Vec<AckAction> {
+        let mut outstanding = self.lock();
+        let expired: Vec<String> = outstanding
+            .iter()
+            .filter(|(_, item)| now.duration_since(item.sent_at) >= ACK_TIMEOUT)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let mut actions = Vec::new();
+        for id in expired {
+            let Some(item) = outstanding.get_mut(&id) else {
+                continue;
+            };
+            if item.resends < MAX_ACK_RESENDS {
+                item.resends += 1;
+                item.sent_at = now;
+                actions.push(AckAction::Resend(item.envelope.clone()));
+            } else if let Some(item) = outstanding.remove(&id) {
+                actions.push(AckAction::GiveUp(item.envelope));
+            }
+        }
+        actions
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct AgentRequester {
     sender: mpsc::Sender<IpcEnvelope>,
-    _child: tokio::process::Child,
-    _read_handle: JoinHandle<()>,
-    _write_handle: JoinHandle<()>,
-    _stderr_handle: JoinHandle<()>,
+    pending: Arc<PendingRequests>,
+}
+
+impl AgentRequester {
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        request_type: &str,
+        request_id: &str,
+        payload: Value,
+    ) -> Result<T> {
+        let receiver = self.pending.register(request_type, request_id);
+        if let Err(err) = self
+            .sender
+            .send(IpcEnvelope::new(request_type, payload))
+            .await
+        {
+            self.pending.cancel(request_id);
+            return Err(err).context("Agent 写入通道已关闭");
+        }
+        match timeout(request_timeout(request_type), receiver).await {
+            Ok(Ok(payload)) => serde_json::from_value(payload)
+                .with_context(|| format!("Agent 响应格式错误: {}", request_type)),
+            Ok(Err(_)) => anyhow::bail!("Agent 连接已断开"),
+            Err(_) => {
+                self.pending.cancel(request_id);
+                anyhow::bail!("等待 Agent 响应超时: {}", request_type)
+            }
+        }
+    }
 }
 
 struct AgentCommand {
@@ -36,19 +266,51 @@ struct AgentCommand {
 }
 
 impl AgentHandle {
+    pub fn account_id(&self) -> &str {
+        &self.account_id
+    }
+
     pub fn clone_sender(&self) -> mpsc::Sender<IpcEnvelope> {
         self.sender.clone()
     }
 
-    pub async fn send(&self, message: IpcEnvelope) -> Result<()> {
-        self.sender
-            .send(message)
-            .await
-            .context("Agent 写入通道已关闭")
+    pub fn requester(&self) -> AgentRequester {
+        AgentRequester {
+            sender: self.sender.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+
+    pub fn info(&self, now: u64) -> AgentInfo {
+        self.telemetry.snapshot(
+            &self.account_id,
+            current_negotiation(&self.negotiation),
+            self.child.id(),
+            self.started_at,
+            now,
+        )
+    }
+
+    async fn shutdown(mut self) {
+        self.read_handle.abort();
+        self.write_handle.abort();
+        self.ack_handle.abort();
+        self.stderr_handle.abort();
+        if let Some(handle) = &self.stdout_handle {
+            handle.abort();
+        }
+        self.pending.clear();
+        if let Err(err) = self.child.kill().await {
+            warn!("结束 Agent 进程失败: {}", err);
+        }
     }
 }
 
-pub async fn start_agent(app: AppHandle, state: Arc<Mutex<AppState>>) -> Result<AgentHandle> {
+pub async fn start_agent(
+    app: AppHandle,
+    state: Arc<Mutex<AppState>>,
+    account_id: String,
+) -> Result<AgentHandle> {
     if cfg!(target_os = "windows") {
         ensure_windows_agent_dependencies(&app).await?;
     }
@@ -58,30 +320,98 @@ pub async fn start_agent(app: AppHandle, state: Arc<Mutex<AppState>>) -> Result<
     for (key, value) in &agent.env {
         cmd.env(key, value);
     }
+    if !account_id.is_empty() {
+        cmd.env(ACCOUNT_ENV, &account_id);
+    }
+    let listener = match Listener::bind() {
+        Ok(listener) => {
+            cmd.env(ipc_transport::ENDPOINT_ENV, listener.endpoint());
+            Some(listener)
+        }
+        Err(err) => {
+            warn!("创建 IPC 通道失败，改用标准输入输出: {:#}", err);
+            None
+        }
+    };
     let mut child = cmd
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
+        .kill_on_drop(true)
         .spawn()
         .context("启动 Agent 失败")?;
 
     let stdin = child.stdin.take().context("Agent stdin 不可用")?;
     let stdout = child.stdout.take().context("Agent stdout 不可用")?;
     let stderr = child.stderr.take().context("Agent stderr 不可用")?;
+    let (inbound, outbound, stdout_handle) = match listener {
+        Some(listener) => match listener.accept(IPC_CONNECT_TIMEOUT).await {
+            Ok((inbound, outbound)) => {
+                info!("Agent 已连接 IPC 通道");
+                let stdout_handle = tokio::spawn(forward_output(app.clone(), stdout, "stdout"));
+                (inbound, outbound, Some(stdout_handle))
+            }
+            Err(err) => {
+                warn!("Agent 未连接 IPC 通道，改用标准输入输出: {:#}", err);
+                (Inbound::lines(stdout), Outbound::lines(stdin), None)
+            }
+        },
+        None => (Inbound::lines(stdout), Outbound::lines(stdin), None),
+    };
 
     let (sender, mut receiver) = mpsc::channel::<IpcEnvelope>(32);
+    let binary_capable = outbound.is_framed();
+    let negotiation = Arc::new(std::sync::Mutex::new(Negotiation::default()));
+    let pending = Arc::new(PendingRequests::default());
+    let acks = Arc::new(AckTracker::default());
 
+    let write_negotiation = negotiation.clone();
+    let write_acks = acks.clone();
     let write_handle = tokio::spawn(async move {
-        let mut stdin = stdin;
-        while let Some(message) = receiver.recv().await {
-            if let Ok(line) = serde_json::to_string(&message) {
-                if stdin.write_all(line.as_bytes()).await.is_err() {
+        let mut outbound = outbound;
+        while let Some(mut message) = receiver.recv().await {
+            let negotiated = current_negotiation(&write_negotiation);
+            message.version = negotiated.version.as_str().to_string();
+            if let Ok(frame) = encode_message(&message, negotiated.binary_frames) {
+                if outbound.send(&frame).await.is_err() {
                     break;
                 }
-                if stdin.write_all(b"\n").await.is_err() {
-                    break;
+                write_acks.track(&message, Instant::now());
+            }
+        }
+    });
+
+    let ack_app = app.clone();
+    let ack_sender = sender.downgrade();
+    let ack_tracker = acks.clone();
+    let ack_handle = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ACK_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(sender) = ack_sender.upgrade() else {
+                break;
+            };
+            for action in ack_tracker.expire(Instant::now()) {
+                match action {
+                    AckAction::Resend(envelope) => {
+                        warn!("Agent 未确认消息，重发: type={}", envelope.r#type);
+                        if sender.send(envelope).await.is_err() {
+                            break;
+                        }
+                    }
+                    AckAction::GiveUp(envelope) => {
+                        warn!("Agent 始终未确认消息: type={}", envelope.r#type);
+                        emit_error(
+                            &ack_app,
+                            ErrorPayload {
+                                code: "AGENT_NO_ACK".to_string(),
+                                message: format!("Agent 未确认消息: {}", envelope.r#type),
+                                recoverable: true,
+                                suggested_action: Some(SuggestedAction::RestartAgent),
+                            },
+                        );
+                    }
                 }
-                let _ = stdin.flush().await;
             }
         }
     });
@@ -89,22 +419,45 @@ pub async fn start_agent(app: AppHandle, state: Arc<Mutex<AppState>>) -> Result<
     let read_app = app.clone();
     let read_state = state.clone();
     let read_sender = sender.clone();
+    let read_negotiation = negotiation.clone();
+    let read_pending = pending.clone();
+    let read_acks = acks;
+    let telemetry = Arc::new(AgentTelemetry::default());
+    let read_telemetry = telemetry.clone();
+    let read_account = account_id.clone();
     let read_handle = tokio::spawn(async move {
-        let mut lines = BufReader::new(stdout).lines();
+        let mut inbound = inbound;
         loop {
-            match lines.next_line().await {
-                Ok(Some(line)) => {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
+            match inbound.next_message().await {
+                Ok(Some(raw)) => {
+                    if raw.trim_ascii().is_empty() {
                         continue;
                     }
-                    match parse_envelope(trimmed) {
+                    match parse_message(&raw) {
                         Ok(envelope) => {
+                            read_telemetry.observe(&envelope, now_secs());
+                            if envelope.r#type == "event.ack" {
+                                apply_event_ack(&read_app, &read_acks, envelope.payload);
+                                continue;
+                            }
                             let ack = IpcEnvelope::ack_for(&envelope.id, true, "");
                             if let Err(err) = read_sender.send(ack).await {
                                 warn!("发送 ack 失败: {}", err);
                             }
-                            handle_envelope(&read_app, &read_state, envelope).await;
+                            if envelope.r#type == "agent.hello" {
+                                apply_agent_hello(
+                                    &read_app,
+                                    &read_negotiation,
+                                    envelope.payload,
+                                    binary_capable,
+                                );
+                                continue;
+                            }
+                            let Some(envelope) = read_pending.resolve(envelope) else {
+                                continue;
+                            };
+                            handle_envelope(&read_app, &read_state, &read_account, envelope)
+                                .await;
                         }
                         Err(err) => {
                             warn!("解析 Agent 消息失败: {}", err);
@@ -114,51 +467,470 @@ pub async fn start_agent(app: AppHandle, state: Arc<Mutex<AppState>>) -> Result<
                                     code: "PROTOCOL_ERROR".to_string(),
                                     message: "Agent 消息格式错误".to_string(),
                                     recoverable: true,
+                                    suggested_action: Some(SuggestedAction::RestartAgent),
                                 },
                             );
                         }
                     }
                 }
                 Ok(None) => {
+                    let previous = read_state.lock().await.account_state(&read_account);
                     emit_error(
                         &read_app,
                         ErrorPayload {
                             code: "AGENT_DISCONNECTED".to_string(),
-                            message: "Agent 连接断开".to_string(),
+                            message: account_message(&read_account, "Agent 连接断开"),
                             recoverable: true,
+                            suggested_action: Some(SuggestedAction::RestartAgent),
                         },
                     );
-                    update_agent_connected(&read_state, &read_app, false, "Agent 连接断开").await;
+                    update_agent_connected(
+                        &read_state,
+                        &read_app,
+                        &read_account,
+                        false,
+                        "Agent 连接断开",
+                    )
+                    .await;
+                    schedule_restart(
+                        read_app.clone(),
+                        read_state.clone(),
+                        read_account.clone(),
+                        previous,
+                    );
                     break;
                 }
                 Err(err) => {
                     warn!("读取 Agent 输出失败: {}", err);
+                    let previous = read_state.lock().await.account_state(&read_account);
+                    update_agent_connected(
+                        &read_state,
+                        &read_app,
+                        &read_account,
+                        false,
+                        "读取 Agent 输出失败",
+                    )
+                    .await;
+                    schedule_restart(
+                        read_app.clone(),
+                        read_state.clone(),
+                        read_account.clone(),
+                        previous,
+                    );
                     break;
                 }
             }
         }
+        read_pending.clear();
+        read_acks.clear();
     });
 
-    let stderr_handle = tokio::spawn(async move {
-        let mut lines = BufReader::new(stderr).lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            if !line.trim().is_empty() {
-                warn!("Agent stderr: {}", line);
-            }
-        }
-    });
+    let stderr_handle = tokio::spawn(forward_output(app.clone(), stderr, "stderr"));
 
-    info!("Agent 已启动");
+    sender
+        .send(IpcEnvelope::host_hello(binary_capable))
+        .await
+        .context("发送协议握手失败")?;
+    info!("Agent 已启动: {}", account_label(&account_id));
     Ok(AgentHandle {
+        account_id,
         sender,
-        _child: child,
-        _read_handle: read_handle,
-        _write_handle: write_handle,
-        _stderr_handle: stderr_handle,
+        pending,
+        negotiation,
+        telemetry,
+        started_at: now_secs(),
+        child,
+        read_handle,
+        write_handle,
+        stdout_handle,
+        stderr_handle,
+        ack_handle,
     })
 }
 
-async fn handle_envelope(app: &AppHandle, state: &Arc<Mutex<AppState>>, envelope: IpcEnvelope) {
+#[derive(Default)]
+struct AgentTelemetry {
+    last_seen: std::sync::Mutex<Option<u64>>,
+    ready: std::sync::Mutex<Option<AgentReadyPayload>>,
+}
+
+impl AgentTelemetry {
+    fn observe(&self, envelope: &IpcEnvelope, now: u64) {
+        *self.last_seen.lock().unwrap_or_else(|err| err.into_inner()) = Some(now);
+        if envelope.r#type != "agent.ready" {
+            return;
+        }
+        if let Ok(payload) = serde_json::from_value::<AgentReadyPayload>(envelope.payload.clone()) {
+            *self.ready.lock().unwrap_or_else(|err| err.into_inner()) = Some(payload);
+        }
+    }
+
+    fn snapshot(
+        &self,
+        account_id: &str,
+        negotiation: Negotiation,
+        pid: Option<u32>,
+        started_at: u64,
+        now: u64,
+    ) -> AgentInfo {
+        let ready = self
+            .ready
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+        AgentInfo {
+            account_id: account_id.to_string(),
+            platform: ready
+                .as_ref()
+                .map(|ready| ready.platform.clone())
+                .unwrap_or_default(),
+            agent_version: ready
+                .as_ref()
+                .map(|ready| ready.agent_version.clone())
+                .unwrap_or_default(),
+            capabilities: ready.map(|ready| ready.capabilities).unwrap_or_default(),
+            protocol_version: negotiation.version.as_str().to_string(),
+            pid,
+            started_at,
+            uptime_secs: now.saturating_sub(started_at),
+            last_heartbeat_at: *self.last_seen.lock().unwrap_or_else(|err| err.into_inner()),
+        }
+    }
+}
+
+async fn forward_output(app: AppHandle, stream: impl AsyncRead + Unpin, source: &str) {
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        agent_log::record(&app, &line, source);
+    }
+}
+
+fn current_negotiation(negotiation: &std::sync::Mutex<Negotiation>) -> Negotiation {
+    *negotiation.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn apply_event_ack(app: &AppHandle, acks: &AckTracker, payload: Value) {
+    let Ok(ack) = serde_json::from_value::<EventAckPayload>(payload) else {
+        warn!("Agent ack 格式错误");
+        return;
+    };
+    let Some(envelope) = acks.acknowledge(&ack.ack_id) else {
+        return;
+    };
+    if !ack.ok {
+        warn!(
+            "Agent 拒绝消息: type={}, error={}",
+            envelope.r#type, ack.error
+        );
+        emit_error(
+            app,
+            ErrorPayload {
+                code: "AGENT_REJECTED".to_string(),
+                message: format!("Agent 未能处理消息 {}: {}", envelope.r#type, ack.error),
+                recoverable: true,
+                suggested_action: Some(SuggestedAction::Retry),
+            },
+        );
+    }
+}
+
+fn apply_agent_hello(
+    app: &AppHandle,
+    negotiation: &std::sync::Mutex<Negotiation>,
+    payload: serde_json::Value,
+    offered_binary: bool,
+) {
+    match negotiate(payload, offered_binary) {
+        Ok(negotiated) => {
+            info!(
+                "IPC 协议版本协商完成: {}，二进制帧: {}",
+                negotiated.version.as_str(),
+                negotiated.binary_frames
+            );
+            *negotiation.lock().unwrap_or_else(|err| err.into_inner()) = negotiated;
+        }
+        Err(err) => {
+            warn!("IPC 协议版本协商失败: {}", err);
+            emit_error(
+                app,
+                ErrorPayload {
+                    code: "PROTOCOL_ERROR".to_string(),
+                    message: err.to_string(),
+                    recoverable: true,
+                    suggested_action: Some(SuggestedAction::RestartAgent),
+                },
+            );
+        }
+    }
+}
+
+pub async fn stop_agent(app: &AppHandle, state: &Arc<Mutex<AppState>>) -> bool {
+    let agents: Vec<AgentHandle> = {
+        let mut guard = state.lock().await;
+        guard.agent_stopped = true;
+        guard.agent_backoffs.clear();
+        let accounts = std::mem::take(&mut guard.account_agents);
+        guard.agent.take().into_iter().chain(accounts.into_values()).collect()
+    };
+    if agents.is_empty() {
+        return false;
+    }
+    for agent in agents {
+        agent.shutdown().await;
+    }
+    {
+        let mut guard = state.lock().await;
+        guard.status.agent_connected = false;
+        guard.set_session_state(RuntimeState::Idle, "");
+        for account in guard.status.accounts.values_mut() {
+            account.connected = false;
+            account.state = RuntimeState::Idle;
+            account.last_error.clear();
+        }
+        let _ = app.emit("status.changed", guard.status.clone());
+    }
+    tauri::async_runtime::spawn(crate::readiness::refresh_readiness(
+        app.clone(),
+        state.clone(),
+    ));
+    info!("Agent 已停止");
+    true
+}
+
+pub async fn restart_agent(app: &AppHandle, state: &Arc<Mutex<AppState>>) -> Result<()> {
+    let (previous, agent) = {
+        let mut guard = state.lock().await;
+        guard.agent_stopped = true;
+        guard.agent_backoffs.clear();
+        (guard.session_state(), guard.agent.take())
+    };
+    if let Some(agent) = agent {
+        agent.shutdown().await;
+    }
+    let started = start_agent(app.clone(), state.clone(), String::new()).await;
+    let mut guard = state.lock().await;
+    guard.agent_stopped = false;
+    match started {
+        Ok(agent) => {
+            guard.agent = Some(agent);
+            drop(guard);
+            info!("Agent 已手动重启");
+            resume_after_restart(app, state, "", previous).await;
+            sync_account_agents(app, state).await;
+            Ok(())
+        }
+        Err(err) => {
+            guard.status.agent_connected = false;
+            guard.set_session_state(RuntimeState::Error, "重启 Agent 失败");
+            let _ = app.emit("status.changed", guard.status.clone());
+            Err(err)
+        }
+    }
+}
+
+fn schedule_restart(
+    app: AppHandle,
+    state: Arc<Mutex<AppState>>,
+    account_id: String,
+    previous: RuntimeState,
+) {
+    tauri::async_runtime::spawn(restart_with_backoff(app, state, account_id, previous));
+}
+
+/// Restarts the agent of one account; every account keeps its own backoff so
+/// a crashing extra account never delays the default one.
+async fn restart_with_backoff(
+    app: AppHandle,
+    state: Arc<Mutex<AppState>>,
+    account_id: String,
+    previous: RuntimeState,
+) {
+    loop {
+        let delay = state
+            .lock()
+            .await
+            .agent_backoffs
+            .entry(account_id.clone())
+            .or_default()
+            .next_delay(now_secs());
+        let Some(delay) = delay else {
+            warn!(
+                "Agent 连续重启失败，停止自动重启: {}",
+                account_label(&account_id)
+            );
+            emit_error(
+                &app,
+                ErrorPayload {
+                    code: "AGENT_RESTART_FAILED".to_string(),
+                    message: account_message(&account_id, "Agent 多次自动重启失败，请手动重启"),
+                    recoverable: true,
+                    suggested_action: Some(SuggestedAction::RestartAgent),
+                },
+            );
+            return;
+        };
+        info!(
+            "Agent 将在 {} 秒后自动重启: {}",
+            delay.as_secs(),
+            account_label(&account_id)
+        );
+        tokio::time::sleep(delay).await;
+        {
+            let guard = state.lock().await;
+            let removed = !account_id.is_empty() && !guard.config.accounts.contains(&account_id);
+            if guard.agent_for_account(&account_id).is_some() || guard.agent_stopped || removed {
+                return;
+            }
+        }
+        match start_agent(app.clone(), state.clone(), account_id.clone()).await {
+            Ok(agent) => {
+                if install_agent(&state, agent).await {
+                    info!("Agent 已自动重启: {}", account_label(&account_id));
+                    resume_after_restart(&app, &state, &account_id, previous).await;
+                }
+                return;
+            }
+            Err(err) => warn!(
+                "自动重启 Agent 失败: {} {}",
+                account_label(&account_id),
+                err
+            ),
+        }
+    }
+}
+
+/// Stores a freshly started agent, unless another start for the same account
+/// got there first; the extra process is then shut down.
+async fn install_agent(state: &Arc<Mutex<AppState>>, agent: AgentHandle) -> bool {
+    let duplicate = {
+        let mut guard = state.lock().await;
+        let account_id = agent.account_id().to_string();
+        if guard.agent_for_account(&account_id).is_some() {
+            Some(agent)
+        } else if account_id.is_empty() {
+            guard.agent = Some(agent);
+            None
+        } else {
+            guard.account_agents.insert(account_id, agent);
+            None
+        }
+    };
+    match duplicate {
+        Some(agent) => {
+            agent.shutdown().await;
+            false
+        }
+        None => true,
+    }
+}
+
+pub async fn sync_account_agents(app: &AppHandle, state: &Arc<Mutex<AppState>>) {
+    let (missing, removed, previous) = {
+        let mut guard = state.lock().await;
+        let accounts = guard.config.accounts.clone();
+        let stale: Vec<String> = guard
+            .account_agents
+            .keys()
+            .filter(|account_id| !accounts.contains(*account_id))
+            .cloned()
+            .collect();
+        let removed: Vec<AgentHandle> = stale
+            .iter()
+            .filter_map(|account_id| guard.account_agents.remove(account_id))
+            .collect();
+        guard
+            .status
+            .accounts
+            .retain(|account_id, _| accounts.contains(account_id));
+        guard
+            .agent_backoffs
+            .retain(|account_id, _| account_id.is_empty() || accounts.contains(account_id));
+        let missing: Vec<String> = if guard.agent_stopped {
+            Vec::new()
+        } else {
+            accounts
+                .into_iter()
+                .filter(|account_id| !guard.account_agents.contains_key(account_id))
+                .collect()
+        };
+        (missing, removed, guard.session_state())
+    };
+    for agent in removed {
+        info!("账号已移除，停止 Agent: {}", agent.account_id());
+        agent.shutdown().await;
+    }
+    for account_id in missing {
+        match start_agent(app.clone(), state.clone(), account_id.clone()).await {
+            Ok(agent) => {
+                if install_agent(state, agent).await {
+                    resume_after_restart(app, state, &account_id, previous.clone()).await;
+                }
+            }
+            Err(err) => warn!("启动账号 {} 的 Agent 失败: {}", account_id, err),
+        }
+    }
+}
+
+async fn resume_after_restart(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    account_id: &str,
+    previous: RuntimeState,
+) {
+    let (sender, targets, control) = {
+        let guard = state.lock().await;
+        let Some(agent) = guard.agent_for_account(account_id) else {
+            return;
+        };
+        (
+            agent.clone_sender(),
+            ListenControlPayload {
+                poll_interval_ms: None,
+                targets: Some(guard.listen_targets.clone()),
+                download_images: None,
+            },
+            ListenControlPayload {
+                poll_interval_ms: Some(power::effective_poll_interval_ms(
+                    &guard.config,
+                    &guard.status.power,
+                )),
+                targets: None,
+                download_images: Some(guard.config.image_ocr_enabled),
+            },
+        )
+    };
+    let mut messages = vec![("listen.targets", targets)];
+    let resumed = match previous {
+        RuntimeState::Listening | RuntimeState::Generating => {
+            messages.push(("listen.start", control));
+            RuntimeState::Listening
+        }
+        RuntimeState::Paused => RuntimeState::Paused,
+        _ => RuntimeState::Idle,
+    };
+    for (message_type, payload) in messages {
+        let Ok(payload) = serde_json::to_value(payload) else {
+            continue;
+        };
+        if let Err(err) = sender.send(IpcEnvelope::new(message_type, payload)).await {
+            warn!("重启后发送 {} 失败: {}", message_type, err);
+            return;
+        }
+    }
+    info!(
+        "Agent 重启后恢复状态: {} {:?}",
+        account_label(account_id),
+        resumed
+    );
+    update_state(state, app, account_id, resumed, "").await;
+}
+
+async fn handle_envelope(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    account_id: &str,
+    envelope: IpcEnvelope,
+) {
     match envelope.r#type.as_str() {
         "agent.ready" => {
             if let Ok(payload) = serde_json::from_value::<AgentReadyPayload>(envelope.payload) {
@@ -169,7 +941,17 @@ async fn handle_envelope(app: &AppHandle, state: &Arc<Mutex<AppState>>, envelope
                     _ => Platform::Unknown,
                 };
                 update_platform(state, app, platform).await;
-                update_agent_connected(state, app, true, "").await;
+                update_agent_connected(state, app, account_id, true, "").await;
+            }
+        }
+        "agent.profile" => {
+            if let Ok(payload) = serde_json::from_value::<AgentProfilePayload>(envelope.payload) {
+                let nickname = payload.nickname.trim().to_string();
+                info!("识别到当前微信昵称: {} ({})", nickname, account_label(account_id));
+                let nickname = Some(nickname).filter(|name| !name.is_empty());
+                let mut guard = state.lock().await;
+                guard.set_account_nickname(account_id, nickname);
+                let _ = app.emit("status.changed", guard.status.clone());
             }
         }
         "agent.status" => {
@@ -181,55 +963,56 @@ async fn handle_envelope(app: &AppHandle, state: &Arc<Mutex<AppState>>, envelope
                     "error" => RuntimeState::Error,
                     _ => RuntimeState::Idle,
                 };
-                update_state(state, app, runtime, payload.detail).await;
+                update_state(state, app, account_id, runtime, payload.detail).await;
             }
         }
         "agent.error" => {
             if let Ok(payload) = serde_json::from_value::<AgentErrorPayload>(envelope.payload) {
                 warn!("Agent 错误: {}", payload.message);
-                update_state(state, app, RuntimeState::Error, payload.message.clone()).await;
+                match payload.chat_id.as_deref() {
+                    Some(chat_id) => {
+                        update_target_error(state, app, chat_id, &payload.code, &payload.message)
+                            .await
+                    }
+                    None => {
+                        let message = account_message(account_id, &payload.message);
+                        update_state(state, app, account_id, RuntimeState::Error, message).await
+                    }
+                }
                 emit_error(
                     app,
                     ErrorPayload {
+                        suggested_action: payload
+                            .suggested_action
+                            .or_else(|| suggested_action_for_code(&payload.code)),
                         code: payload.code,
-                        message: payload.message,
+                        message: account_message(account_id, &payload.message),
                         recoverable: payload.recoverable,
                     },
                 );
             }
         }
         "message.new" => {
-            if let Ok(payload) = serde_json::from_value::<MessageNewPayload>(envelope.payload) {
+            if let Ok(mut payload) = serde_json::from_value::<MessageNewPayload>(envelope.payload)
+            {
+                payload.account_id = account_id.to_string();
                 handle_incoming_message(app, state, payload).await;
             }
         }
-        "chats.list.result" => match serde_json::from_value::<ChatsListResultPayload>(envelope.payload)
-        {
-            Ok(payload) => {
-                let sender = {
-                    let mut guard = state.lock().await;
-                    let Some((pending_id, _)) = guard.pending_chats_list.as_ref() else {
-                        return;
-                    };
-                    if pending_id != &payload.request_id {
-                        return;
-                    }
-                    guard.recent_chats = payload.chats.clone();
-                    guard.pending_chats_list.take().map(|(_, sender)| sender)
-                };
-                if let Some(sender) = sender {
-                    let _ = sender.send(payload.chats);
+        "message.batch" => {
+            if let Ok(mut payload) = serde_json::from_value::<MessageBatchPayload>(envelope.payload)
+            {
+                for message in &mut payload.messages {
+                    message.account_id = account_id.to_string();
                 }
+                handle_incoming_batch(app, state, payload.messages).await;
             }
-            Err(err) => {
-                warn!("会话列表解析失败: {}", err);
-                let sender = {
-                    let mut guard = state.lock().await;
-                    guard.pending_chats_list.take()
-                };
-                drop(sender);
+        }
+        "message.sent" => {
+            if let Ok(payload) = serde_json::from_value::<MessageSentPayload>(envelope.payload) {
+                handle_outgoing_message(app, state, payload).await;
             }
-        },
+        }
         "input.result" => {
             if let Ok(payload) = serde_json::from_value::<InputResultPayload>(envelope.payload) {
                 if !payload.ok {
@@ -239,6 +1022,7 @@ async fn handle_envelope(app: &AppHandle, state: &Arc<Mutex<AppState>>, envelope
                             code: "WRITE_FAILED".to_string(),
                             message: payload.error,
                             recoverable: true,
+                            suggested_action: Some(SuggestedAction::Retry),
                         },
                     );
                 }
@@ -251,12 +1035,31 @@ async fn handle_envelope(app: &AppHandle, state: &Arc<Mutex<AppState>>, envelope
 async fn update_state(
     state: &Arc<Mutex<AppState>>,
     app: &AppHandle,
+    account_id: &str,
     runtime: RuntimeState,
     last_error: impl Into<String>,
 ) {
     let mut guard = state.lock().await;
-    guard.status.state = runtime;
-    guard.status.last_error = last_error.into();
+    guard.set_account_state(account_id, runtime, last_error);
+    let _ = app.emit("status.changed", guard.status.clone());
+}
+
+async fn update_target_error(
+    state: &Arc<Mutex<AppState>>,
+    app: &AppHandle,
+    chat_id: &str,
+    code: &str,
+    detail: &str,
+) {
+    let mut guard = state.lock().await;
+    let target = guard.set_target_state(
+        chat_id,
+        RuntimeState::Error,
+        Some(code.to_string()),
+        detail,
+        now_secs(),
+    );
+    let _ = app.emit("target.status.changed", target);
     let _ = app.emit("status.changed", guard.status.clone());
 }
 
@@ -273,23 +1076,52 @@ async fn update_platform(
 async fn update_agent_connected(
     state: &Arc<Mutex<AppState>>,
     app: &AppHandle,
+    account_id: &str,
     connected: bool,
-    last_error: impl Into<String>,
+    last_error: &str,
 ) {
     let mut guard = state.lock().await;
-    guard.status.agent_connected = connected;
+    guard.set_account_connected(account_id, connected);
     if !connected {
-        guard.status.state = RuntimeState::Error;
-        guard.status.last_error = last_error.into();
-        guard.agent = None;
+        warn!("Agent 已断开: {}", account_label(account_id));
+        guard.set_account_state(account_id, RuntimeState::Error, last_error);
+        if account_id.is_empty() {
+            guard.agent = None;
+        } else {
+            guard.account_agents.remove(account_id);
+        }
     }
     let _ = app.emit("status.changed", guard.status.clone());
+    if !account_id.is_empty() {
+        return;
+    }
+    drop(guard);
+    tauri::async_runtime::spawn(crate::readiness::refresh_readiness(
+        app.clone(),
+        state.clone(),
+    ));
 }
 
 fn emit_error(app: &AppHandle, payload: ErrorPayload) {
     let _ = app.emit("error.raised", payload);
 }
 
+fn account_label(account_id: &str) -> &str {
+    if account_id.is_empty() {
+        "默认账号"
+    } else {
+        account_id
+    }
+}
+
+fn account_message(account_id: &str, message: &str) -> String {
+    if account_id.is_empty() {
+        message.to_string()
+    } else {
+        format!("[{}] {}", account_id, message)
+    }
+}
+
 fn resolve_agent_command(app: &AppHandle) -> Result<AgentCommand> {
     let base = find_agent_root(app)?;
     let platform_agents = base.join("platform_agents");
@@ -316,7 +1148,7 @@ fn resolve_agent_command(app: &AppHandle) -> Result<AgentCommand> {
     }
 }
 
-fn find_agent_root(app: &AppHandle) -> Result<PathBuf> {
+pub fn find_agent_root(app: &AppHandle) -> Result<PathBuf> {
     if let Ok(resource_dir) = app.path().resource_dir() {
         if resource_dir.join("platform_agents").exists() {
             return Ok(resource_dir);
@@ -561,6 +1393,159 @@ async fn ensure_windows_agent_dependencies(app: &AppHandle) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ipc::ChatsListResultPayload;
+
+    fn input_result(request_id: Option<&str>, ok: bool) -> IpcEnvelope {
+        IpcEnvelope::new(
+            "input.result",
+            serde_json::json!({"ok": ok, "request_id": request_id}),
+        )
+    }
+
+    #[test]
+    fn reports_ready_details_and_last_heartbeat() {
+        let telemetry = AgentTelemetry::default();
+        let info = telemetry.snapshot("", Negotiation::default(), Some(42), 100, 130);
+        assert_eq!(info.uptime_secs, 30);
+        assert_eq!(info.last_heartbeat_at, None);
+        assert!(info.platform.is_empty());
+
+        let ready = IpcEnvelope::new(
+            "agent.ready",
+            serde_json::json!({
+                "platform": "windows",
+                "agent_version": "0.1.0",
+                "capabilities": ["listen", "write"],
+                "supports_clipboard_restore": true,
+            }),
+        );
+        telemetry.observe(&ready, 110);
+        let status = IpcEnvelope::new("agent.status", serde_json::json!({}));
+        telemetry.observe(&status, 125);
+        let negotiation = Negotiation {
+            version: crate::ipc::ProtocolVersion::V1_1,
+            binary_frames: false,
+        };
+        let info = telemetry.snapshot("work", negotiation, Some(42), 100, 130);
+        assert_eq!(info.account_id, "work");
+        assert_eq!(info.platform, "windows");
+        assert_eq!(info.agent_version, "0.1.0");
+        assert_eq!(info.capabilities, vec!["listen", "write"]);
+        assert_eq!(info.protocol_version, "1.1");
+        assert_eq!(info.pid, Some(42));
+        assert_eq!(info.last_heartbeat_at, Some(125));
+    }
+
+    #[test]
+    fn correlates_responses_by_request_id_and_type() {
+        let pending = PendingRequests::default();
+        let mut first = pending.register("input.write", "w1");
+        let mut second = pending.register("input.write", "w2");
+        let mut chats = pending.register("chats.list", "c1");
+
+        assert!(pending.resolve(input_result(Some("w2"), false)).is_none());
+        assert_eq!(second.try_recv().unwrap()["ok"], false);
+        assert!(first.try_recv().is_err());
+        assert!(pending.resolve(input_result(Some("w9"), true)).is_some());
+        assert!(pending.resolve(input_result(Some("c1"), true)).is_some());
+
+        assert!(pending.resolve(input_result(None, true)).is_none());
+        assert_eq!(first.try_recv().unwrap()["ok"], true);
+
+        let _third = pending.register("input.write", "w3");
+        pending.cancel("w3");
+        assert!(pending.resolve(input_result(None, false)).is_some());
+
+        pending.clear();
+        assert!(chats.try_recv().is_err());
+        assert_eq!(request_timeout("chats.list"), Duration::from_secs(3));
+        assert_eq!(request_timeout("history.fetch"), DEFAULT_REQUEST_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn awaits_typed_responses() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let pending = Arc::new(PendingRequests::default());
+        let requester = AgentRequester {
+            sender,
+            pending: pending.clone(),
+        };
+        let responder = tokio::spawn(async move {
+            let request = receiver.recv().await.unwrap();
+            assert_eq!(request.r#type, "chats.list");
+            let response = IpcEnvelope::new(
+                "chats.list.result",
+                serde_json::json!({"request_id": "r1", "chats": []}),
+            );
+            assert!(pending.resolve(response).is_none());
+        });
+        let result: ChatsListResultPayload = requester
+            .request("chats.list", "r1", serde_json::json!({"request_id": "r1"}))
+            .await
+            .unwrap();
+        assert_eq!(result.request_id, "r1");
+        assert!(result.chats.is_empty());
+        responder.await.unwrap();
+    }
+
+    #[test]
+    fn resends_once_then_gives_up_on_unacknowledged_envelopes() {
+        let tracker = AckTracker::default();
+        let start = Instant::now();
+        let listen = IpcEnvelope::new("listen.start", serde_json::json!({}));
+        let write = IpcEnvelope::new("input.write", serde_json::json!({}));
+        tracker.track(&listen, start);
+        tracker.track(&write, start);
+        tracker.track(&IpcEnvelope::ack_for("x", true, ""), start);
+        assert!(tracker.expire(start + Duration::from_secs(1)).is_empty());
+        assert!(tracker.acknowledge(&write.id).is_none());
+
+        let resend_at = start + ACK_TIMEOUT;
+        let actions = tracker.expire(resend_at);
+        assert!(matches!(actions.as_slice(), [AckAction::Resend(item)] if item.id == listen.id));
+        tracker.track(&listen, resend_at);
+        assert!(tracker
+            .expire(resend_at + Duration::from_secs(1))
+            .is_empty());
+        let actions = tracker.expire(resend_at + ACK_TIMEOUT);
+        assert!(matches!(actions.as_slice(), [AckAction::GiveUp(item)] if item.id == listen.id));
+        assert!(tracker.expire(resend_at + ACK_TIMEOUT * 2).is_empty());
+    }
+
+    #[test]
+    fn late_ack_settles_without_resending_writes() {
+        let tracker = AckTracker::default();
+        let start = Instant::now();
+        let write = IpcEnvelope::new("input.write", serde_json::json!({"send": true}));
+        let targets = IpcEnvelope::new("listen.targets", serde_json::json!({}));
+        tracker.track(&write, start);
+        tracker.track(&targets, start);
+
+        // A slow paste keeps the agent busy past the ack timeout.
+        let actions = tracker.expire(start + ACK_TIMEOUT);
+        assert!(matches!(actions.as_slice(), [AckAction::Resend(item)] if item.id == targets.id));
+        assert!(tracker.acknowledge(&write.id).is_none());
+        assert_eq!(
+            tracker.acknowledge(&targets.id).unwrap().r#type,
+            "listen.targets"
+        );
+        assert!(tracker.expire(start + ACK_TIMEOUT * 3).is_empty());
+    }
+
+    #[test]
+    fn restart_backoff_doubles_until_limit_and_resets_when_stable() {
+        let mut backoff = RestartBackoff::default();
+        let now = 10_000;
+        let delays: Vec<u64> = (0..u64::from(MAX_RESTART_ATTEMPTS))
+            .map(|offset| backoff.next_delay(now + offset).unwrap().as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert!(backoff.next_delay(now + 10).is_none());
+
+        let later = now + 10 + RESTART_STABLE_SECS;
+        assert_eq!(backoff.next_delay(later), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.next_delay(later + 5), Some(Duration::from_secs(2)));
+    }
 
     #[test]
     fn python_check_args_include_required_modules() {
@@ -0,0 +1,77 @@
+use crate::state::now_secs;
+use crate::types::{AgentLogEntry, AgentLogLevel};
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, error, info, warn};
+
+fn parse_level(value: &str) -> Option<AgentLogLevel> {
+    match value.trim().to_ascii_uppercase().as_str() {
+        "DEBUG" => Some(AgentLogLevel::Debug),
+        "INFO" => Some(AgentLogLevel::Info),
+        "WARN" | "WARNING" => Some(AgentLogLevel::Warn),
+        "ERROR" => Some(AgentLogLevel::Error),
+        _ => None,
+    }
+}
+
+pub fn parse_line(line: &str, source: &str, now: u64) -> Option<AgentLogEntry> {
+    let line = line.trim_end();
+    if line.trim().is_empty() {
+        return None;
+    }
+    let mut parts = line.splitn(3, '|');
+    if let (Some(level), Some(component), Some(message)) =
+        (parts.next(), parts.next(), parts.next())
+    {
+        if let Some(level) = parse_level(level) {
+            return Some(AgentLogEntry {
+                level,
+                component: component.trim().to_string(),
+                message: message.trim().to_string(),
+                timestamp: now,
+            });
+        }
+    }
+    Some(AgentLogEntry {
+        level: AgentLogLevel::Warn,
+        component: source.to_string(),
+        message: line.to_string(),
+        timestamp: now,
+    })
+}
+
+pub fn record(app: &AppHandle, line: &str, source: &str) {
+    let Some(entry) = parse_line(line, source, now_secs()) else {
+        return;
+    };
+    match entry.level {
+        AgentLogLevel::Debug => debug!("Agent[{}]: {}", entry.component, entry.message),
+        AgentLogLevel::Info => info!("Agent[{}]: {}", entry.component, entry.message),
+        AgentLogLevel::Warn => warn!("Agent[{}]: {}", entry.component, entry.message),
+        AgentLogLevel::Error => error!("Agent[{}]: {}", entry.component, entry.message),
+    }
+    let _ = app.emit("agent.log", entry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_structured_lines_and_falls_back_for_noise() {
+        let entry = parse_line("INFO|listener|已开始监听 3 个会话\n", "stderr", 7).unwrap();
+        assert_eq!(entry.level, AgentLogLevel::Info);
+        assert_eq!(entry.component, "listener");
+        assert_eq!(entry.message, "已开始监听 3 个会话");
+        assert_eq!(entry.timestamp, 7);
+
+        let entry = parse_line("warning|ui|a|b", "stderr", 7).unwrap();
+        assert_eq!(entry.level, AgentLogLevel::Warn);
+        assert_eq!(entry.message, "a|b");
+
+        let noise = parse_line("DeprecationWarning: x|y|z", "stdout", 7).unwrap();
+        assert_eq!(noise.level, AgentLogLevel::Warn);
+        assert_eq!(noise.component, "stdout");
+        assert_eq!(noise.message, "DeprecationWarning: x|y|z");
+        assert!(parse_line("   ", "stderr", 7).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::canned_responses::CannedResponseStore;
+use crate::types::{AutoReplyRule, BusinessHours};
+use anyhow::Result;
+use std::collections::VecDeque;
+
+pub const MAX_AUTO_REPLY_RULES: usize = 50;
+pub const MAX_TEMPLATE_CHARS: usize = 500;
+const WINDOW_SECS: u64 = 3600;
+
+#[derive(Default)]
+pub struct AutoReplyLimiter {
+    sent: VecDeque<u64>,
+}
+
+impl AutoReplyLimiter {
+    pub fn try_acquire(&mut self, now: u64, max_per_hour: u32) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent_at| now.saturating_sub(*sent_at) >= WINDOW_SECS)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= max_per_hour as usize {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+pub fn validate_rules(rules: &[AutoReplyRule]) -> Result<()> {
+    if rules.len() > MAX_AUTO_REPLY_RULES {
+        anyhow::bail!("自动回复规则不能超过 {} 条", MAX_AUTO_REPLY_RULES);
+    }
+    for rule in rules {
+        if rule.target.trim().is_empty() || rule.keyword.trim().is_empty() {
+            anyhow::bail!("自动回复规则的会话与关键词不能为空");
+        }
+        if rule.template.trim().is_empty() && rule.canned_response_id.is_none() {
+            anyhow::bail!("自动回复内容不能为空");
+        }
+        if rule.template.chars().count() > MAX_TEMPLATE_CHARS {
+            anyhow::bail!("自动回复内容不能超过 {} 字", MAX_TEMPLATE_CHARS);
+        }
+        if let Some(hours) = rule.hours.as_ref() {
+            if parse_minute(&hours.start).is_none() || parse_minute(&hours.end).is_none() {
+                anyhow::bail!("营业时间格式应为 HH:MM");
+            }
+            if !(-720..=840).contains(&hours.utc_offset_minutes) {
+                anyhow::bail!("时区偏移超出范围");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rules name their chat the way listen targets do, by id or by title, so
+/// either one of the incoming chat matches.
+pub fn match_rule<'a>(
+    rules: &'a [AutoReplyRule],
+    chat_id: &str,
+    chat_title: &str,
+    text: &str,
+    now: u64,
+) -> Option<&'a AutoReplyRule> {
+    let text = text.to_lowercase();
+    rules.iter().find(|rule| {
+        let target = rule.target.trim();
+        (target == chat_id || target == chat_title.trim())
+            && text.contains(&rule.keyword.trim().to_lowercase())
+            && rule
+                .hours
+                .as_ref()
+                .is_none_or(|hours| within_hours(hours, now))
+    })
+}
+
+pub fn resolve_reply(
+    rule: &AutoReplyRule,
+    canned: &CannedResponseStore,
+    incoming: &str,
+) -> Option<String> {
+    let text = match rule.canned_response_id.as_deref() {
+        Some(id) => canned.get(id)?.text.clone(),
+        None => rule.template.trim().to_string(),
+    };
+    (text != incoming.trim()).then_some(text)
+}
+
+fn within_hours(hours: &BusinessHours, now: u64) -> bool {
+    let (Some(start), Some(end)) = (parse_minute(&hours.start), parse_minute(&hours.end)) else {
+        return false;
+    };
+    let local = now as i64 + hours.utc_offset_minutes as i64 * 60;
+    let day = local.div_euclid(86_400);
+    let minute = (local.rem_euclid(86_400) / 60) as u32;
+    let weekday = (day + 3).rem_euclid(7);
+    if hours.weekdays_only && weekday >= 5 {
+        return false;
+    }
+    if start <= end {
+        (start..end).contains(&minute)
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+fn parse_minute(value: &str) -> Option<u32> {
+    let (hour, minute) = value.trim().split_once(':')?;
+    let hour = hour.parse::<u32>().ok().filter(|hour| *hour <= 24)?;
+    let minute = minute.parse::<u32>().ok().filter(|minute| *minute < 60)?;
+    let total = hour * 60 + minute;
+    (total <= 24 * 60).then_some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONDAY_0930_CST: u64 = 1_717_378_200;
+
+    fn rule(keyword: &str, hours: Option<BusinessHours>) -> AutoReplyRule {
+        AutoReplyRule {
+            target: "客户群".to_string(),
+            keyword: keyword.to_string(),
+            template: "您好，稍后回复".to_string(),
+            canned_response_id: None,
+            hours,
+        }
+    }
+
+    fn office(weekdays_only: bool) -> BusinessHours {
+        BusinessHours {
+            start: "09:00".to_string(),
+            end: "18:00".to_string(),
+            weekdays_only,
+            utc_offset_minutes: 480,
+        }
+    }
+
+    #[test]
+    fn matches_keyword_target_and_business_hours() {
+        let rules = vec![rule("价格", Some(office(true))), rule("Hello", None)];
+        let matched = match_rule(&rules, "客户群", "", "请问价格多少", MONDAY_0930_CST);
+        assert_eq!(matched.map(|rule| rule.keyword.as_str()), Some("价格"));
+        assert!(match_rule(&rules, "张三", "张三", "请问价格多少", MONDAY_0930_CST).is_none());
+        let evening = MONDAY_0930_CST + 9 * 3600;
+        assert!(match_rule(&rules, "客户群", "", "请问价格多少", evening).is_none());
+        let saturday = MONDAY_0930_CST + 5 * 86_400;
+        assert!(match_rule(&rules, "客户群", "", "请问价格多少", saturday).is_none());
+        assert!(match_rule(&rules, "客户群", "", "hello there", saturday).is_some());
+
+        let night = BusinessHours {
+            start: "22:00".to_string(),
+            end: "08:00".to_string(),
+            weekdays_only: false,
+            utc_offset_minutes: 480,
+        };
+        assert!(within_hours(&night, MONDAY_0930_CST - 3 * 3600));
+        assert!(!within_hours(&night, MONDAY_0930_CST));
+    }
+
+    #[test]
+    fn matches_rules_keyed_by_chat_title() {
+        let rules = vec![rule("价格", None)];
+        let matched = match_rule(
+            &rules,
+            "12345678@chatroom",
+            " 客户群 ",
+            "请问价格多少",
+            MONDAY_0930_CST,
+        );
+        assert_eq!(matched.map(|rule| rule.target.as_str()), Some("客户群"));
+        assert!(match_rule(
+            &rules,
+            "12345678@chatroom",
+            "供应商群",
+            "请问价格多少",
+            MONDAY_0930_CST
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn resolves_template_or_canned_response() {
+        let mut canned = CannedResponseStore::default();
+        let response = canned
+            .create("报价", "报价单稍后发您", Vec::new(), 100)
+            .unwrap();
+        let plain = rule("价格", None);
+        assert_eq!(
+            resolve_reply(&plain, &canned, "价格多少").as_deref(),
+            Some("您好，稍后回复")
+        );
+        assert!(resolve_reply(&plain, &canned, "您好，稍后回复").is_none());
+
+        let linked = AutoReplyRule {
+            template: String::new(),
+            canned_response_id: Some(response.id),
+            ..plain.clone()
+        };
+        assert_eq!(
+            resolve_reply(&linked, &canned, "价格多少").as_deref(),
+            Some("报价单稍后发您")
+        );
+        let missing = AutoReplyRule {
+            canned_response_id: Some("missing".to_string()),
+            ..linked
+        };
+        assert!(resolve_reply(&missing, &canned, "价格多少").is_none());
+        assert!(validate_rules(&[missing]).is_ok());
+    }
+
+    #[test]
+    fn caps_sends_per_hour() {
+        let mut limiter = AutoReplyLimiter::default();
+        assert!(limiter.try_acquire(100, 2));
+        assert!(limiter.try_acquire(200, 2));
+        assert!(!limiter.try_acquire(300, 2));
+        assert!(limiter.try_acquire(100 + WINDOW_SECS, 2));
+    }
+
+    #[test]
+    fn validates_rules() {
+        assert!(validate_rules(&[rule("价格", Some(office(false)))]).is_ok());
+        assert!(validate_rules(&[rule(" ", None)]).is_err());
+        let mut invalid = office(false);
+        invalid.end = "25:00".to_string();
+        assert!(validate_rules(&[rule("价格", Some(invalid))]).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::types::ChatAvatar;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use md5::{Digest, Md5};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Image type of cached avatar bytes; WeChat stores JPEG thumbnails, PNG and
+/// GIF are seen for some official accounts.
+pub fn mime_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Wraps avatar bytes for the front end, either inline as base64 or written
+/// to `file_dir` (one file per chat, overwritten on every fetch).
+pub fn chat_avatar(
+    chat_id: &str,
+    bytes: &[u8],
+    file_dir: Option<&Path>,
+) -> Result<Option<ChatAvatar>> {
+    let Some(mime_type) = mime_type(bytes) else {
+        return Ok(None);
+    };
+    let mut avatar = ChatAvatar {
+        chat_id: chat_id.to_string(),
+        mime_type: mime_type.to_string(),
+        data_base64: None,
+        path: None,
+    };
+    match file_dir {
+        Some(dir) => {
+            let path = avatar_path(dir, chat_id, mime_type);
+            fs::create_dir_all(dir).context("创建头像目录失败")?;
+            fs::write(&path, bytes)
+                .with_context(|| format!("写入头像文件失败: {}", path.display()))?;
+            avatar.path = Some(path.to_string_lossy().to_string());
+        }
+        None => avatar.data_base64 = Some(STANDARD.encode(bytes)),
+    }
+    Ok(Some(avatar))
+}
+
+pub fn avatar_dir() -> PathBuf {
+    std::env::temp_dir().join("wereply").join("avatars")
+}
+
+fn avatar_path(dir: &Path, chat_id: &str, mime_type: &str) -> PathBuf {
+    let hash = Md5::digest(chat_id.as_bytes());
+    let name: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let ext = mime_type.trim_start_matches("image/").replace("jpeg", "jpg");
+    dir.join(format!("{}.{}", name, ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+
+    #[test]
+    fn encodes_or_writes_cached_avatars() {
+        assert_eq!(mime_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(mime_type(b"<html>"), None);
+        assert!(chat_avatar("wxid_a", b"", None).unwrap().is_none());
+
+        let inline = chat_avatar("wxid_a", JPEG, None).unwrap().unwrap();
+        assert_eq!(inline.mime_type, "image/jpeg");
+        assert_eq!(inline.data_base64.as_deref(), Some("/9j/4AAQ"));
+        assert!(inline.path.is_none());
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("avatars");
+        let file = chat_avatar("wxid_a", JPEG, Some(&dir)).unwrap().unwrap();
+        let path = PathBuf::from(file.path.unwrap());
+        assert!(file.data_base64.is_none());
+        assert_eq!(path.extension().unwrap(), "jpg");
+        assert_eq!(fs::read(path).unwrap(), JPEG);
+    }
+}
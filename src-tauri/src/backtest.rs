@@ -0,0 +1,240 @@
+use crate::deepseek::{self, ContextMessage, GenerationFailure, SuggestionRequest};
+use crate::listen_targets::prompt_override_for_chat;
+use crate::state::ChatMessage;
+use crate::types::{BacktestCase, Config, ListenTarget, Suggestion, SuggestionRecord};
+
+pub const DEFAULT_TEMPLATE: &str = "default";
+pub const CURRENT_TEMPLATE: &str = "current";
+pub const DEFAULT_BACKTEST_CASES: u32 = 10;
+pub const MAX_BACKTEST_CASES: u32 = 30;
+
+pub struct PlannedCase {
+    pub message: ChatMessage,
+    pub request: SuggestionRequest,
+    pub original: Option<SuggestionRecord>,
+}
+
+pub fn resolve_template(
+    template_id: &str,
+    targets: &[ListenTarget],
+    chat_id: &str,
+) -> Result<Option<String>, String> {
+    match template_id.trim() {
+        "" | DEFAULT_TEMPLATE => Ok(None),
+        CURRENT_TEMPLATE => Ok(prompt_override_for_chat(targets, chat_id)),
+        name => prompt_override_for_chat(targets, name)
+            .map(Some)
+            .ok_or_else(|| format!("未找到提示词模板: {}", name)),
+    }
+}
+
+pub fn plan_cases(
+    config: &Config,
+    messages: &[ChatMessage],
+    records: &[SuggestionRecord],
+    since: u64,
+    limit: usize,
+    prompt_override: Option<String>,
+) -> Vec<PlannedCase> {
+    let eligible: Vec<usize> = (0..messages.len())
+        .filter(|index| {
+            let message = &messages[*index];
+            message.timestamp >= since && !message.is_outgoing()
+        })
+        .collect();
+    let skip = eligible.len().saturating_sub(limit);
+    let context_max = (config.context_max_messages as usize).max(1);
+    eligible
+        .into_iter()
+        .skip(skip)
+        .map(|index| {
+            let message = &messages[index];
+            let start = (index + 1).saturating_sub(context_max);
+            let context_messages = messages[start..=index]
+                .iter()
+                .map(|item| ContextMessage {
+                    text: item.context_text(),
+                    age_secs: message.timestamp.saturating_sub(item.timestamp),
+                })
+                .filter(|item| {
+                    config.context_max_age_secs == 0 || item.age_secs <= config.context_max_age_secs
+                })
+                .collect();
+            let next = messages.get(index + 1).map(|next| next.timestamp);
+            let original = records
+                .iter()
+                .filter(|record| {
+                    record.created_at >= message.timestamp
+                        && next.is_none_or(|next| record.created_at < next)
+                })
+                .min_by_key(|record| record.created_at)
+                .cloned();
+            PlannedCase {
+                message: message.clone(),
+                request: SuggestionRequest {
+                    context_messages,
+                    session_instruction: None,
+                    prompt_override: prompt_override.clone(),
+                    language_instruction: None,
+                    contact_note: None,
+                    knowledge: Vec::new(),
+                    context_summary: None,
+                    style_presets: config.style_presets.clone(),
+                    length_limits: config.reply_length_limits.clone(),
+                    followups: false,
+                    prompt_version: 0,
+                    experiment: None,
+                    experiment_instruction: None,
+                    style_instruction: None,
+                },
+                original,
+            }
+        })
+        .collect()
+}
+
+pub async fn replay_case(
+    config: &Config,
+    api_key: &str,
+    case: PlannedCase,
+) -> (BacktestCase, Result<deepseek::Generated, GenerationFailure>) {
+    let result =
+        deepseek::generate_suggestions(config, Some(api_key.to_string()), &case.request).await;
+    let report = match result.as_ref() {
+        Ok(generated) => case_report(case, generated.suggestions.clone(), None),
+        Err(failure) => case_report(case, Vec::new(), Some(failure.reason())),
+    };
+    (report, result)
+}
+
+pub fn skipped_case(case: PlannedCase, reason: &str) -> BacktestCase {
+    case_report(case, Vec::new(), Some(reason.to_string()))
+}
+
+fn case_report(
+    case: PlannedCase,
+    generated: Vec<Suggestion>,
+    error: Option<String>,
+) -> BacktestCase {
+    BacktestCase {
+        timestamp: case.message.timestamp,
+        incoming: case.message.text,
+        generated,
+        sent: case.original.as_ref().and_then(sent_text),
+        original: case
+            .original
+            .map(|record| record.suggestions)
+            .unwrap_or_default(),
+        error,
+    }
+}
+
+fn sent_text(record: &SuggestionRecord) -> Option<String> {
+    let written = record.written_suggestion_id.as_deref()?;
+    record
+        .suggestions
+        .iter()
+        .find(|suggestion| suggestion.id == written)
+        .map(|suggestion| suggestion.text.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MessageDirection;
+    use crate::types::{ChatKind, Politeness, SuggestionStyle, TargetPriority};
+
+    fn message(text: &str, timestamp: u64) -> ChatMessage {
+        ChatMessage {
+            text: text.to_string(),
+            timestamp,
+            msg_id: None,
+            direction: MessageDirection::Incoming,
+        }
+    }
+
+    fn record(created_at: u64, written: Option<&str>) -> SuggestionRecord {
+        SuggestionRecord {
+            id: format!("set-{}", created_at),
+            chat_id: "张三".to_string(),
+            context_hash: String::new(),
+            model: "deepseek-chat".to_string(),
+            fallback: false,
+            latency_ms: 0,
+            suggestions: vec![Suggestion {
+                id: "a".to_string(),
+                style: SuggestionStyle::neutral(),
+                text: format!("回复{}", created_at),
+            }],
+            written_suggestion_id: written.map(str::to_string),
+            written_at: None,
+            created_at,
+            prompt_version: 0,
+            experiment: None,
+        }
+    }
+
+    #[test]
+    fn plans_recent_incoming_messages_with_context_and_original() {
+        let config = Config {
+            context_max_messages: 2,
+            context_max_age_secs: 0,
+            ..Config::default()
+        };
+        let messages = vec![
+            message("在吗", 100),
+            message("我：在", 110),
+            message("明天开会吗", 120),
+            message("几点？", 130),
+        ];
+        let records = vec![record(105, None), record(125, Some("a")), record(140, None)];
+        let cases = plan_cases(&config, &messages, &records, 0, 2, Some("简短".to_string()));
+
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].message.text, "明天开会吗");
+        let context: Vec<_> = cases[0]
+            .request
+            .context_messages
+            .iter()
+            .map(|item| (item.text.as_str(), item.age_secs))
+            .collect();
+        assert_eq!(context, vec![("我：在", 10), ("明天开会吗", 0)]);
+        assert_eq!(cases[0].request.prompt_override.as_deref(), Some("简短"));
+        let original = cases[0].original.as_ref().unwrap();
+        assert_eq!(original.created_at, 125);
+        assert_eq!(sent_text(original).as_deref(), Some("回复125"));
+        assert_eq!(cases[1].original.as_ref().unwrap().created_at, 140);
+
+        let skipped = skipped_case(cases.into_iter().next().unwrap(), "今日用量已达上限");
+        assert_eq!(skipped.sent.as_deref(), Some("回复125"));
+        assert!(skipped.generated.is_empty());
+    }
+
+    #[test]
+    fn resolves_templates_from_listen_targets() {
+        let targets = vec![ListenTarget {
+            name: "客户群".to_string(),
+            kind: ChatKind::Group,
+            prompt_override: Some("语气正式".to_string()),
+            persona: None,
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language: None,
+            politeness: Politeness::Polite,
+        }];
+        assert_eq!(resolve_template("default", &targets, "客户群"), Ok(None));
+        assert_eq!(
+            resolve_template("current", &targets, "客户群"),
+            Ok(Some("语气正式".to_string()))
+        );
+        assert_eq!(resolve_template("current", &targets, "张三"), Ok(None));
+        assert_eq!(
+            resolve_template("客户群", &targets, "张三"),
+            Ok(Some("语气正式".to_string()))
+        );
+        assert!(resolve_template("不存在", &targets, "张三").is_err());
+    }
+}
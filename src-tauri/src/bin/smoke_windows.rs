@@ -0,0 +1,3 @@
+fn main() {
+    std::process::exit(wereply_lib::smoke::run_windows());
+}
@@ -4,9 +4,24 @@ use anyhow::Result;
 use specta::ts::{export, BigIntExportBehavior, ExportConfiguration};
 
 use crate::types::{
-    ApiResponse, ChatKind, ChatSummary, Config, DeepseekDiagnostics, DeepseekEndpointStatus,
-    ErrorPayload, ListenTarget, Platform, RuntimeState, Status, Suggestion, SuggestionStyle,
-    SuggestionsUpdated, UiPathStep, UiPathsStatus, UiTreeExport, UiTreeLearnResult,
+    AccountStatus, AgentInfo, AgentLogEntry, AgentLogLevel, ApiResponse, AutoReplyRule,
+    AutoReplySent, AutomationMetrics, AutomationStrategy, AutomationTraceEntry,
+    AutomationTraceExport, BacktestCase, BacktestRange, BacktestReport, BusinessHours,
+    CannedResponse, ChatActivityStats, ChatAvatar, ChatKind, ChatSummary, CipherSelfTest, Config,
+    ConnectionTiming, ContactLanguage, ContactNote, DbKeyMethod, DbKeyReport, DecryptExport,
+    DecryptMethod, DeepseekBalance, DeepseekDiagnostics, DeepseekEndpointStatus, EmojiPolicy,
+    ErrorPayload, ExperimentReport, ExperimentVariant, FallbackMode, FollowupsUpdated,
+    FrontendSync, HandoverBrief, InputWriteResult, InputWriteStatus, IntegrationScope,
+    IntegrationToken, IntegrationTokenCreated, KnowledgeBaseStatus, ListenTarget,
+    ListenTargetResult, ListenTargetsReport, LocatorCue, LocatorDiagnostic, LowPowerMode,
+    MaintenanceItem, MaintenanceKind, MaintenanceReport, MessageSearchHit, ModelInfo, Persona,
+    Platform, Politeness, PowerSource, ProfileSummary, PromptChange, PromptVersion, Readiness,
+    ReadinessCheck, RecentChats, ReplyLengthLimit, ReplyMode, RuntimeState, SafetyAction,
+    SafetyRule, SafetyWarning, SeedContextResult, SessionInstruction, Status, StylePreset,
+    SuggestedAction, Suggestion, SuggestionAcceptance, SuggestionReasoning, SuggestionRecord,
+    SuggestionStyle, SuggestionUsed, SuggestionsUnavailable, SuggestionsUpdated, TargetPriority,
+    TargetStatus, TranscriptionCompleted, UiPathStep, UiPathsStatus, UiTreeExport,
+    UiTreeLearnResult,
 };
 
 fn export_types() -> Result<String> {
@@ -17,36 +32,186 @@ fn export_types() -> Result<String> {
     output.push_str("\n\n");
     output.push_str(&export::<SuggestionStyle>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<EmojiPolicy>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<StylePreset>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ReplyLengthLimit>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SafetyAction>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SafetyRule>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SafetyWarning>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<Platform>(&config)?);
     output.push_str("\n\n");
     output.push_str(&export::<ChatKind>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<TargetPriority>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ContactLanguage>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<Politeness>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<ListenTarget>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<ListenTargetResult>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<BusinessHours>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AutoReplyRule>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<CannedResponse>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ContactNote>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<KnowledgeBaseStatus>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<Persona>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<PromptVersion>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<PromptChange>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<IntegrationScope>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<IntegrationToken>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<IntegrationTokenCreated>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<HandoverBrief>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AutoReplySent>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<TranscriptionCompleted>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SuggestionReasoning>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<FollowupsUpdated>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ListenTargetsReport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ChatActivityStats>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SeedContextResult>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<MessageSearchHit>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<ChatSummary>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<RecentChats>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ChatAvatar>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<Suggestion>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<PowerSource>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<LowPowerMode>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AutomationStrategy>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<FallbackMode>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<TargetStatus>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AccountStatus>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<Status>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<ReadinessCheck>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<Readiness>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<Config>(&config)?);
     output.push_str("\n\n");
     output.push_str(&export::<UiTreeExport>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<AutomationMetrics>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AutomationTraceEntry>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AutomationTraceExport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<MaintenanceKind>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<MaintenanceItem>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<MaintenanceReport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<LocatorCue>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<LocatorDiagnostic>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<UiPathStep>(&config)?);
     output.push_str("\n\n");
     output.push_str(&export::<UiTreeLearnResult>(&config)?);
     output.push_str("\n\n");
     output.push_str(&export::<UiPathsStatus>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<ReplyMode>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<SuggestionsUpdated>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<SuggestionsUnavailable>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<FrontendSync>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SuggestionRecord>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SuggestionAcceptance>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ExperimentVariant>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ExperimentReport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SuggestionUsed>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<BacktestRange>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<BacktestCase>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<BacktestReport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<CipherSelfTest>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<DecryptMethod>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<DecryptExport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<DbKeyMethod>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<DbKeyReport>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SessionInstruction>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ProfileSummary>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<InputWriteStatus>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<InputWriteResult>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<SuggestedAction>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<ErrorPayload>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<AgentLogLevel>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AgentLogEntry>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<AgentInfo>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<DeepseekEndpointStatus>(&config)?);
     output.push_str("\n\n");
     output.push_str(&export::<DeepseekDiagnostics>(&config)?);
     output.push_str("\n\n");
+    output.push_str(&export::<ConnectionTiming>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<DeepseekBalance>(&config)?);
+    output.push_str("\n\n");
+    output.push_str(&export::<ModelInfo>(&config)?);
+    output.push_str("\n\n");
     output.push_str(&export::<ApiResponse<()>>(&config)?);
     output.push_str("\n\n");
 
@@ -76,10 +241,26 @@ pub fn export_typescript_bindings(path: &Path) -> Result<()> {
     output.push_str(
         "    invoke(\"set_listen_targets\", { targets }),\n",
     );
+    output.push_str(
+        "  addListenTargets: (batch: ListenTarget[]): Promise<ApiResponse<ListenTargetsReport>> =>\n",
+    );
+    output.push_str("    invoke(\"add_listen_targets\", { batch }),\n");
+    output.push_str(
+        "  removeListenTargets: (names: string[]): Promise<ApiResponse<ListenTargetsReport>> =>\n",
+    );
+    output.push_str("    invoke(\"remove_listen_targets\", { names }),\n");
+    output.push_str(
+        "  clearListenTargets: (): Promise<ApiResponse<ListenTargetsReport>> =>\n",
+    );
+    output.push_str("    invoke(\"clear_listen_targets\"),\n");
     output.push_str(
         "  startListening: (): Promise<ApiResponse<null>> => invoke(\"start_listening\"),\n",
     );
     output.push_str("  stopListening: (): Promise<ApiResponse<null>> => invoke(\"stop_listening\"),\n");
+    output.push_str("  restartAgent: (): Promise<ApiResponse<null>> => invoke(\"restart_agent\"),\n");
+    output.push_str("  stopAgent: (): Promise<ApiResponse<null>> => invoke(\"stop_agent\"),\n");
+    output.push_str("  getAgentInfo: (accountId?: string): Promise<ApiResponse<AgentInfo>> =>\n");
+    output.push_str("    invoke(\"get_agent_info\", { accountId: accountId ?? null }),\n");
     output.push_str(
         "  pauseListening: (): Promise<ApiResponse<null>> => invoke(\"pause_listening\"),\n",
     );
@@ -87,10 +268,10 @@ pub fn export_typescript_bindings(path: &Path) -> Result<()> {
         "  resumeListening: (): Promise<ApiResponse<null>> => invoke(\"resume_listening\"),\n",
     );
     output.push_str(
-        "  writeSuggestion: (chatId: string, text: string): Promise<ApiResponse<null>> =>\n",
+        "  writeSuggestion: (chatId: string, text: string, replyMode?: ReplyMode): Promise<ApiResponse<null>> =>\n",
     );
     output.push_str(
-        "    invoke(\"write_suggestion\", { chat_id: chatId, text }),\n",
+        "    invoke(\"write_suggestion\", { chat_id: chatId, text, replyMode: replyMode ?? null }),\n",
     );
     output.push_str(
         "  saveApiKey: (apiKey: string): Promise<ApiResponse<null>> => invoke(\"save_api_key\", { apiKey }),\n",
@@ -100,6 +281,11 @@ pub fn export_typescript_bindings(path: &Path) -> Result<()> {
     );
     output.push_str("  getApiKey: (): Promise<ApiResponse<string>> => invoke(\"get_api_key\"),\n");
     output.push_str("  deleteApiKey: (): Promise<ApiResponse<null>> => invoke(\"delete_api_key\"),\n");
+    output.push_str("  setTranscriptionApiKey: (apiKey: string): Promise<ApiResponse<null>> =>\n");
+    output.push_str("    invoke(\"set_transcription_api_key\", { apiKey }),\n");
+    output.push_str("  setWechatDbKey: (key: string): Promise<ApiResponse<null>> =>\n");
+    output.push_str("    invoke(\"set_wechat_db_key\", { key }),\n");
+    output.push_str("  acquireWechatDbKey: (): Promise<ApiResponse<DbKeyReport>> => invoke(\"acquire_wechat_db_key\"),\n");
     output.push_str(
         "  diagnoseDeepseek: (apiKey?: string): Promise<ApiResponse<DeepseekDiagnostics>> =>\n",
     );
@@ -107,11 +293,25 @@ pub fn export_typescript_bindings(path: &Path) -> Result<()> {
         "    invoke(\"diagnose_deepseek\", apiKey ? { apiKey } : {}),\n",
     );
     output.push_str(
-        "  listModels: (): Promise<ApiResponse<string[]>> => invoke(\"list_models\"),\n",
+        "  getDeepseekBalance: (): Promise<ApiResponse<DeepseekBalance>> => invoke(\"get_deepseek_balance\"),\n",
+    );
+    output.push_str(
+        "  listModels: (): Promise<ApiResponse<ModelInfo[]>> => invoke(\"list_models\"),\n",
     );
     output.push_str(
-        "  listRecentChats: (): Promise<ApiResponse<ChatSummary[]>> => invoke(\"list_recent_chats\"),\n",
+        "  listChats: (offset?: number, limit?: number, query?: string): Promise<ApiResponse<RecentChats>> =>\n",
     );
+    output.push_str(
+        "    invoke(\"list_chats\", { offset: offset ?? null, limit: limit ?? null, query: query ?? null }),\n",
+    );
+    output.push_str(
+        "  refreshChats: (limit?: number): Promise<ApiResponse<RecentChats>> =>\n",
+    );
+    output.push_str("    invoke(\"refresh_chats\", { limit: limit ?? null }),\n");
+    output.push_str(
+        "  getChatAvatar: (chatId: string, asFile?: boolean): Promise<ApiResponse<ChatAvatar | null>> =>\n",
+    );
+    output.push_str("    invoke(\"get_chat_avatar\", { chatId, asFile: asFile ?? null }),\n");
     output.push_str(
         "  exportWeChatUiTree: (maxDepth?: number, outputPath?: string): Promise<ApiResponse<UiTreeExport>> =>\n",
     );
@@ -132,6 +332,174 @@ pub fn export_typescript_bindings(path: &Path) -> Result<()> {
         "  setDeepseekModel: (model: string): Promise<ApiResponse<null>> =>\n",
     );
     output.push_str("    invoke(\"set_deepseek_model\", { model }),\n");
+    output.push_str(
+        "  setSessionInstruction: (chatId: string, text: string, ttlSecs: number): Promise<ApiResponse<null>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"set_session_instruction\", { chatId, text, ttlSecs }),\n",
+    );
+    output.push_str(
+        "  getSessionInstructions: (): Promise<ApiResponse<SessionInstruction[]>> =>\n",
+    );
+    output.push_str("    invoke(\"get_session_instructions\"),\n");
+    output.push_str(
+        "  setHideDockIcon: (hidden: boolean): Promise<ApiResponse<null>> =>\n",
+    );
+    output.push_str("    invoke(\"set_hide_dock_icon\", { hidden }),\n");
+    output.push_str(
+        "  getLatestSuggestions: (): Promise<ApiResponse<SuggestionsUpdated | null>> =>\n",
+    );
+    output.push_str("    invoke(\"get_latest_suggestions\"),\n");
+    output.push_str("  frontendHeartbeat: (): Promise<ApiResponse<FrontendSync | null>> =>\n");
+    output.push_str("    invoke(\"frontend_heartbeat\"),\n");
+    output.push_str(
+        "  listProfiles: (): Promise<ApiResponse<ProfileSummary[]>> =>\n",
+    );
+    output.push_str("    invoke(\"list_profiles\"),\n");
+    output.push_str(
+        "  saveProfile: (name: string): Promise<ApiResponse<ProfileSummary[]>> =>\n",
+    );
+    output.push_str("    invoke(\"save_profile\", { name }),\n");
+    output.push_str(
+        "  deleteProfile: (name: string): Promise<ApiResponse<ProfileSummary[]>> =>\n",
+    );
+    output.push_str("    invoke(\"delete_profile\", { name }),\n");
+    output.push_str(
+        "  switchProfile: (name: string): Promise<ApiResponse<ProfileSummary[]>> =>\n",
+    );
+    output.push_str("    invoke(\"switch_profile\", { name }),\n");
+    output.push_str("  getChatActivityStats: (): Promise<ApiResponse<ChatActivityStats[]>> =>\n");
+    output.push_str("    invoke(\"get_chat_activity_stats\"),\n");
+    output.push_str(
+        "  searchMessages: (query: string, chatId?: string): Promise<ApiResponse<MessageSearchHit[]>> =>\n",
+    );
+    output.push_str("    invoke(\"search_messages\", { query, chatId: chatId ?? null }),\n");
+    output.push_str(
+        "  getSuggestionHistory: (chatId?: string, limit?: number): Promise<ApiResponse<SuggestionRecord[]>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"get_suggestion_history\", { chatId: chatId ?? null, limit: limit ?? null }),\n",
+    );
+    output.push_str(
+        "  getSuggestionAcceptance: (chatId?: string, days?: number): Promise<ApiResponse<SuggestionAcceptance>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"get_suggestion_acceptance\", { chatId: chatId ?? null, days: days ?? null }),\n",
+    );
+    output.push_str(
+        "  getExperimentReport: (days?: number): Promise<ApiResponse<ExperimentReport>> => invoke(\"get_experiment_report\", { days: days ?? null }),\n",
+    );
+    output.push_str(
+        "  exportAutomationTrace: (outputPath?: string): Promise<ApiResponse<AutomationTraceExport>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"export_automation_trace\", { outputPath: outputPath ?? null }),\n",
+    );
+    output.push_str(
+        "  getReadiness: (): Promise<ApiResponse<Readiness>> => invoke(\"get_readiness\"),\n",
+    );
+    output.push_str(
+        "  composeReply: (chatId: string, fragments: string[], style?: SuggestionStyle): Promise<ApiResponse<Suggestion>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"compose_reply\", { chatId, fragments, style: style ?? null }),\n",
+    );
+    output.push_str(
+        "  generateHandoverBrief: (chatId: string, outputPath?: string): Promise<ApiResponse<HandoverBrief>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"generate_handover_brief\", { chatId, outputPath: outputPath ?? null }),\n",
+    );
+    output.push_str(
+        "  seedContext: (chatId: string, transcriptText: string): Promise<ApiResponse<SeedContextResult>> =>\n",
+    );
+    output.push_str("    invoke(\"seed_context\", { chatId, transcriptText }),\n");
+    output.push_str(
+        "  runMaintenance: (dryRun?: boolean): Promise<ApiResponse<MaintenanceReport>> =>\n",
+    );
+    output.push_str("    invoke(\"run_maintenance\", { dryRun: dryRun ?? null }),\n");
+    output.push_str("  getLocatorDiagnostics: (): Promise<ApiResponse<LocatorDiagnostic[]>> =>\n");
+    output.push_str("    invoke(\"get_locator_diagnostics\"),\n");
+    output.push_str(
+        "  backtestPrompts: (chatId: string, range?: BacktestRange, templateId?: string): Promise<ApiResponse<BacktestReport>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"backtest_prompts\", { chatId, range: range ?? null, templateId: templateId ?? null }),\n",
+    );
+    output.push_str("  cipherSelfTest: (): Promise<ApiResponse<CipherSelfTest>> => invoke(\"cipher_self_test\"),\n");
+    output.push_str(
+        "  exportDecryptedDb: (dbPath: string, key: string, outputPath?: string, compatibility?: number): Promise<ApiResponse<DecryptExport>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"export_decrypted_db\", { dbPath, key, outputPath: outputPath ?? null, compatibility: compatibility ?? null }),\n",
+    );
+    output.push_str(
+        "  getAutomationMetrics: (): Promise<ApiResponse<AutomationMetrics>> => invoke(\"get_automation_metrics\"),\n",
+    );
+    output.push_str(
+        "  listCannedResponses: (tag?: string): Promise<ApiResponse<CannedResponse[]>> =>\n",
+    );
+    output.push_str("    invoke(\"list_canned_responses\", { tag: tag ?? null }),\n");
+    output.push_str(
+        "  createCannedResponse: (title: string, text: string, tags?: string[]): Promise<ApiResponse<CannedResponse>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"create_canned_response\", { title, text, tags: tags ?? null }),\n",
+    );
+    output.push_str(
+        "  updateCannedResponse: (id: string, title: string, text: string, tags?: string[]): Promise<ApiResponse<CannedResponse>> =>\n",
+    );
+    output.push_str(
+        "    invoke(\"update_canned_response\", { id, title, text, tags: tags ?? null }),\n",
+    );
+    output.push_str(
+        "  deleteCannedResponse: (id: string): Promise<ApiResponse<null>> => invoke(\"delete_canned_response\", { id }),\n",
+    );
+    output.push_str(
+        "  listContactNotes: (): Promise<ApiResponse<ContactNote[]>> => invoke(\"list_contact_notes\"),\n",
+    );
+    output.push_str(
+        "  setContactNote: (chatId: string, note: string): Promise<ApiResponse<ContactNote>> =>\n",
+    );
+    output.push_str("    invoke(\"set_contact_note\", { chatId, note }),\n");
+    output.push_str(
+        "  deleteContactNote: (chatId: string): Promise<ApiResponse<null>> => invoke(\"delete_contact_note\", { chatId }),\n",
+    );
+    output.push_str(
+        "  getKnowledgeBaseStatus: (): Promise<ApiResponse<KnowledgeBaseStatus>> => invoke(\"get_knowledge_base_status\"),\n",
+    );
+    output.push_str(
+        "  rebuildKnowledgeBase: (): Promise<ApiResponse<KnowledgeBaseStatus>> => invoke(\"rebuild_knowledge_base\"),\n",
+    );
+    output.push_str(
+        "  listPersonas: (): Promise<ApiResponse<Persona[]>> => invoke(\"list_personas\"),\n",
+    );
+    output.push_str(
+        "  savePersona: (persona: Persona): Promise<ApiResponse<Persona>> => invoke(\"save_persona\", { persona }),\n",
+    );
+    output.push_str(
+        "  deletePersona: (name: string): Promise<ApiResponse<null>> => invoke(\"delete_persona\", { name }),\n",
+    );
+    output.push_str(
+        "  listPromptVersions: (): Promise<ApiResponse<PromptVersion[]>> => invoke(\"list_prompt_versions\"),\n",
+    );
+    output.push_str(
+        "  diffPromptVersions: (from: number, to: number): Promise<ApiResponse<PromptChange[]>> =>\n",
+    );
+    output.push_str("    invoke(\"diff_prompt_versions\", { from, to }),\n");
+    output.push_str(
+        "  rollbackPromptVersion: (version: number): Promise<ApiResponse<number>> => invoke(\"rollback_prompt_version\", { version }),\n",
+    );
+    output.push_str(
+        "  listIntegrationTokens: (): Promise<ApiResponse<IntegrationToken[]>> => invoke(\"list_integration_tokens\"),\n",
+    );
+    output.push_str(
+        "  createIntegrationToken: (name: string, scopes: IntegrationScope[]): Promise<ApiResponse<IntegrationTokenCreated>> =>\n",
+    );
+    output.push_str("    invoke(\"create_integration_token\", { name, scopes }),\n");
+    output.push_str(
+        "  revokeIntegrationToken: (id: string): Promise<ApiResponse<null>> => invoke(\"revoke_integration_token\", { id }),\n",
+    );
     output.push_str("};\n");
 
     std::fs::write(path, output)?;
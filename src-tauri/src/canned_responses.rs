@@ -0,0 +1,198 @@
+use crate::types::CannedResponse;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+use uuid::Uuid;
+
+const CANNED_RESPONSES_FILE: &str = "canned_responses.json";
+pub const MAX_CANNED_RESPONSES: usize = 200;
+const MAX_TITLE_CHARS: usize = 32;
+const MAX_TEXT_CHARS: usize = 1000;
+const MAX_TAGS: usize = 10;
+const MAX_TAG_CHARS: usize = 16;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CannedResponseStore {
+    #[serde(default)]
+    responses: Vec<CannedResponse>,
+}
+
+impl CannedResponseStore {
+    pub fn list(&self, tag: Option<&str>) -> Vec<CannedResponse> {
+        let tag = tag.map(str::trim).filter(|tag| !tag.is_empty());
+        self.responses
+            .iter()
+            .filter(|response| tag.is_none_or(|tag| response.tags.iter().any(|item| item == tag)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CannedResponse> {
+        self.responses.iter().find(|response| response.id == id)
+    }
+
+    pub fn create(
+        &mut self,
+        title: &str,
+        text: &str,
+        tags: Vec<String>,
+        now: u64,
+    ) -> Result<CannedResponse> {
+        if self.responses.len() >= MAX_CANNED_RESPONSES {
+            anyhow::bail!("快捷回复数量已达上限");
+        }
+        let response = CannedResponse {
+            id: Uuid::new_v4().to_string(),
+            title: normalize_title(title)?,
+            text: normalize_text(text)?,
+            tags: normalize_tags(tags)?,
+            updated_at: now,
+        };
+        self.responses.push(response.clone());
+        Ok(response)
+    }
+
+    pub fn update(
+        &mut self,
+        id: &str,
+        title: &str,
+        text: &str,
+        tags: Vec<String>,
+        now: u64,
+    ) -> Result<CannedResponse> {
+        let title = normalize_title(title)?;
+        let text = normalize_text(text)?;
+        let tags = normalize_tags(tags)?;
+        let Some(response) = self.responses.iter_mut().find(|response| response.id == id) else {
+            anyhow::bail!("快捷回复不存在");
+        };
+        response.title = title;
+        response.text = text;
+        response.tags = tags;
+        response.updated_at = now;
+        Ok(response.clone())
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.responses.len();
+        self.responses.retain(|response| response.id != id);
+        self.responses.len() != before
+    }
+}
+
+fn normalize_title(title: &str) -> Result<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        anyhow::bail!("快捷回复标题不能为空");
+    }
+    if title.chars().count() > MAX_TITLE_CHARS {
+        anyhow::bail!("快捷回复标题过长");
+    }
+    Ok(title.to_string())
+}
+
+fn normalize_text(text: &str) -> Result<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        anyhow::bail!("快捷回复内容不能为空");
+    }
+    if text.chars().count() > MAX_TEXT_CHARS {
+        anyhow::bail!("快捷回复内容不能超过 {} 字", MAX_TEXT_CHARS);
+    }
+    Ok(text.to_string())
+}
+
+fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() || normalized.iter().any(|item| item == tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_CHARS {
+            anyhow::bail!("标签过长: {}", tag);
+        }
+        normalized.push(tag.to_string());
+    }
+    if normalized.len() > MAX_TAGS {
+        anyhow::bail!("标签不能超过 {} 个", MAX_TAGS);
+    }
+    Ok(normalized)
+}
+
+pub fn load_canned_responses(app: &AppHandle) -> Result<CannedResponseStore> {
+    let path = canned_responses_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(CannedResponseStore::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取快捷回复失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<CannedResponseStore>(&contents) {
+        Ok(store) => Ok(store),
+        Err(err) => {
+            warn!("解析快捷回复失败，忽略已保存内容: {}", err);
+            Ok(CannedResponseStore::default())
+        }
+    }
+}
+
+pub fn save_canned_responses(app: &AppHandle, store: &CannedResponseStore) -> Result<()> {
+    let path = canned_responses_path(app)?;
+    let contents = serde_json::to_string_pretty(store).context("序列化快捷回复失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入快捷回复失败: {}", path.display()))
+}
+
+fn canned_responses_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
+    fs::create_dir_all(&dir).context("创建配置目录失败")?;
+    Ok(dir.join(CANNED_RESPONSES_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_updates_filters_and_removes_responses() {
+        let mut store = CannedResponseStore::default();
+        let created = store
+            .create(
+                " 报价 ",
+                "您好，报价单稍后发您",
+                vec!["销售".to_string(), " 销售".to_string(), "".to_string()],
+                100,
+            )
+            .unwrap();
+        assert_eq!(created.title, "报价");
+        assert_eq!(created.tags, vec!["销售"]);
+        store
+            .create("问候", "在的，请讲", vec!["客服".to_string()], 110)
+            .unwrap();
+
+        assert_eq!(store.list(None).len(), 2);
+        assert_eq!(store.list(Some("销售")).len(), 1);
+        assert!(store.list(Some("私人")).is_empty());
+
+        let updated = store
+            .update(&created.id, "报价", "报价已发送", Vec::new(), 120)
+            .unwrap();
+        assert_eq!(updated.updated_at, 120);
+        assert_eq!(store.get(&created.id).unwrap().text, "报价已发送");
+        assert!(store
+            .update("missing", "标题", "内容", Vec::new(), 130)
+            .is_err());
+        assert!(store.create("空", "  ", Vec::new(), 130).is_err());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let mut restored: CannedResponseStore = serde_json::from_str(&json).unwrap();
+        assert!(restored.remove(&created.id));
+        assert!(!restored.remove(&created.id));
+        assert_eq!(restored.list(None).len(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::types::ChatSummary;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+const CHAT_IDENTITY_FILE: &str = "chat_identities.json";
+const MAX_CHAT_ALIASES: usize = 2000;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChatIdentityResolver {
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+}
+
+impl ChatIdentityResolver {
+    pub fn resolve(&self, raw: &str) -> String {
+        let key = raw.trim();
+        self.aliases
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn learn(&mut self, chat_id: &str, chat_title: &str) -> bool {
+        let chat_id = chat_id.trim();
+        let chat_title = chat_title.trim();
+        if chat_id.is_empty() || chat_title.is_empty() || chat_id == chat_title {
+            return false;
+        }
+        let canonical = self.resolve(chat_id);
+        if canonical == chat_title {
+            return false;
+        }
+        if self.aliases.get(chat_title) == Some(&canonical) {
+            return false;
+        }
+        if !self.aliases.contains_key(chat_title) && self.aliases.len() >= MAX_CHAT_ALIASES {
+            warn!("会话映射数量已达上限，忽略: {}", chat_title);
+            return false;
+        }
+        self.aliases.insert(chat_title.to_string(), canonical);
+        true
+    }
+
+    pub fn learn_chats(&mut self, chats: &[ChatSummary]) -> Vec<(String, String)> {
+        let mut learned = Vec::new();
+        for chat in chats {
+            if self.learn(&chat.chat_id, &chat.chat_title) {
+                let canonical = self.resolve(&chat.chat_id);
+                learned.push((chat.chat_title.trim().to_string(), canonical));
+            }
+        }
+        learned
+    }
+
+    pub fn canonicalize(&mut self, chat_id: &str, chat_title: &str) -> (String, bool) {
+        let learned = self.learn(chat_id, chat_title);
+        (self.resolve(chat_id), learned)
+    }
+}
+
+pub fn load_chat_identities(app: &AppHandle) -> Result<ChatIdentityResolver> {
+    let path = chat_identity_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Ok(ChatIdentityResolver::default());
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取会话映射失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<ChatIdentityResolver>(&contents) {
+        Ok(resolver) => Ok(resolver),
+        Err(err) => {
+            warn!("解析会话映射失败，忽略已保存映射: {}", err);
+            Ok(ChatIdentityResolver::default())
+        }
+    }
+}
+
+pub fn save_chat_identities(app: &AppHandle, resolver: &ChatIdentityResolver) -> Result<()> {
+    let path = chat_identity_path(app)?;
+    let contents = serde_json::to_string_pretty(resolver).context("序列化会话映射失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入会话映射失败: {}", path.display()))
+}
+
+fn chat_identity_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
+    fs::create_dir_all(&dir).context("创建配置目录失败")?;
+    Ok(dir.join(CHAT_IDENTITY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChatKind;
+
+    #[test]
+    fn resolves_title_to_agent_id_after_learning() {
+        let mut resolver = ChatIdentityResolver::default();
+        assert_eq!(resolver.resolve("项目群"), "项目群");
+        assert!(resolver.learn("123@chatroom", "项目群"));
+        assert!(!resolver.learn("123@chatroom", "项目群"));
+        assert_eq!(resolver.resolve(" 项目群 "), "123@chatroom");
+        assert_eq!(resolver.resolve("123@chatroom"), "123@chatroom");
+    }
+
+    #[test]
+    fn ignores_title_only_sources() {
+        let mut resolver = ChatIdentityResolver::default();
+        let (chat_id, learned) = resolver.canonicalize("张三", "张三");
+        assert_eq!(chat_id, "张三");
+        assert!(!learned);
+        assert_eq!(resolver.resolve("张三"), "张三");
+    }
+
+    #[test]
+    fn follows_renamed_titles_and_chats_list() {
+        let mut resolver = ChatIdentityResolver::default();
+        let learned = resolver.learn_chats(&[
+            ChatSummary {
+                chat_id: "wxid_a".to_string(),
+                chat_title: "张三".to_string(),
+                kind: ChatKind::Direct,
+                account_id: String::new(),
+                last_message: String::new(),
+                last_active_at: 0,
+                unread_count: 0,
+            },
+            ChatSummary {
+                chat_id: "李四".to_string(),
+                chat_title: "李四".to_string(),
+                kind: ChatKind::Direct,
+                account_id: String::new(),
+                last_message: String::new(),
+                last_active_at: 0,
+                unread_count: 0,
+            },
+        ]);
+        assert_eq!(learned, vec![("张三".to_string(), "wxid_a".to_string())]);
+        assert!(resolver.learn("wxid_b", "张三"));
+        assert_eq!(resolver.resolve("张三"), "wxid_b");
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut resolver = ChatIdentityResolver::default();
+        resolver.learn("wxid_a", "张三");
+        let json = serde_json::to_string(&resolver).unwrap();
+        let restored: ChatIdentityResolver = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.resolve("张三"), "wxid_a");
+        let empty: ChatIdentityResolver = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty.resolve("张三"), "张三");
+    }
+}
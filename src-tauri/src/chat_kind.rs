@@ -0,0 +1,69 @@
+use crate::types::ChatKind;
+
+/// WeChat ids of group chats end with this suffix.
+pub const CHATROOM_SUFFIX: &str = "@chatroom";
+
+/// Kind of a chat from its WeChat id, as stored in the databases. Every id that
+/// is not a chatroom (contacts, official accounts, WeCom `@openim`) is direct.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn from_user_name(user_name: &str) -> ChatKind {
+    if user_name.trim().ends_with(CHATROOM_SUFFIX) {
+        ChatKind::Group
+    } else {
+        ChatKind::Direct
+    }
+}
+
+/// Best guess from a chat title, for the UI paths where the id is not visible.
+/// WeChat appends the member count to group titles (`项目组 (12)`), and group
+/// names usually mention "群"; anything else stays unknown.
+pub fn from_title(title: &str) -> ChatKind {
+    let title = title.trim();
+    if has_member_count(title) || title.contains('\u{7fa4}') {
+        ChatKind::Group
+    } else {
+        ChatKind::Unknown
+    }
+}
+
+/// Keeps a known kind and only guesses from the title when it is unknown.
+pub fn or_from_title(kind: ChatKind, title: &str) -> ChatKind {
+    match kind {
+        ChatKind::Unknown => from_title(title),
+        known => known,
+    }
+}
+
+fn has_member_count(title: &str) -> bool {
+    let Some(rest) = title.strip_suffix(')').or_else(|| title.strip_suffix('）')) else {
+        return false;
+    };
+    let Some(open) = rest.rfind(['(', '（']) else {
+        return false;
+    };
+    let name = rest[..open].trim();
+    let count = rest[open..].trim_start_matches(['(', '（']);
+    !name.is_empty() && !count.is_empty() && count.chars().all(|ch| ch.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_groups_from_ids_and_titles() {
+        assert_eq!(from_user_name("123456@chatroom"), ChatKind::Group);
+        assert_eq!(from_user_name("wxid_abc"), ChatKind::Direct);
+        assert_eq!(from_user_name("gh_3dfda90e39d6"), ChatKind::Direct);
+
+        assert_eq!(from_title("项目组 (12)"), ChatKind::Group);
+        assert_eq!(from_title("项目组（3）"), ChatKind::Group);
+        assert_eq!(from_title("产品交流群"), ChatKind::Group);
+        assert_eq!(from_title("张三"), ChatKind::Unknown);
+        assert_eq!(from_title("张三 (公司)"), ChatKind::Unknown);
+        assert_eq!(from_title("(3)"), ChatKind::Unknown);
+
+        assert_eq!(or_from_title(ChatKind::Direct, "产品交流群"), ChatKind::Direct);
+        assert_eq!(or_from_title(ChatKind::Unknown, "产品交流群"), ChatKind::Group);
+    }
+}
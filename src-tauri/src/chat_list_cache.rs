@@ -0,0 +1,139 @@
+use crate::types::{ChatSummary, RecentChats};
+use crate::ui_automation::ChatPage;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+const CHAT_LIST_CACHE_FILE: &str = "recent_chats.json";
+const FRESH_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatListCache {
+    #[serde(default)]
+    pub chats: Vec<ChatSummary>,
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default)]
+    pub fetched_at: u64,
+    #[serde(skip)]
+    pub refreshing: bool,
+}
+
+impl ChatListCache {
+    pub fn is_empty(&self) -> bool {
+        self.fetched_at == 0
+    }
+
+    pub fn is_stale(&self, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) > FRESH_SECS
+    }
+
+    /// Whether the cached first page can answer a first page of `limit` chats.
+    pub fn covers(&self, limit: usize) -> bool {
+        !self.is_empty() && (self.chats.len() >= limit || !self.has_more)
+    }
+
+    pub fn update(&mut self, page: ChatPage, now: u64) {
+        self.chats = page.chats;
+        self.has_more = page.has_more;
+        self.fetched_at = now;
+    }
+
+    pub fn snapshot(&self, now: u64, limit: usize) -> RecentChats {
+        RecentChats {
+            chats: self.chats.iter().take(limit).cloned().collect(),
+            has_more: self.has_more || self.chats.len() > limit,
+            fetched_at: self.fetched_at,
+            stale: self.is_stale(now),
+            refreshing: self.refreshing,
+        }
+    }
+
+    pub fn claim_refresh(&mut self, now: u64) -> bool {
+        if self.refreshing || !self.is_stale(now) {
+            return false;
+        }
+        self.refreshing = true;
+        true
+    }
+}
+
+pub fn load_chat_list_cache(app: &AppHandle) -> Result<ChatListCache> {
+    let path = chat_list_cache_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ChatListCache::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取会话列表缓存失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<ChatListCache>(&contents) {
+        Ok(cache) => Ok(cache),
+        Err(err) => {
+            warn!("解析会话列表缓存失败，忽略缓存: {}", err);
+            Ok(ChatListCache::default())
+        }
+    }
+}
+
+pub fn save_chat_list_cache(app: &AppHandle, cache: &ChatListCache) -> Result<()> {
+    let path = chat_list_cache_path(app)?;
+    let contents = serde_json::to_string(cache).context("序列化会话列表缓存失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入会话列表缓存失败: {}", path.display()))
+}
+
+fn chat_list_cache_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
+    fs::create_dir_all(&dir).context("创建配置目录失败")?;
+    Ok(dir.join(CHAT_LIST_CACHE_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChatKind;
+
+    #[test]
+    fn claims_one_background_refresh_once_stale() {
+        let mut cache = ChatListCache::default();
+        assert!(cache.is_empty());
+        let chat = |chat_id: &str| ChatSummary {
+            chat_id: chat_id.to_string(),
+            chat_title: "张三".to_string(),
+            kind: ChatKind::Direct,
+            account_id: String::new(),
+            last_message: String::new(),
+            last_active_at: 0,
+            unread_count: 0,
+        };
+        cache.update(
+            ChatPage {
+                chats: vec![chat("wxid_a"), chat("wxid_b")],
+                has_more: true,
+            },
+            100,
+        );
+        assert!(cache.covers(2) && !cache.covers(3));
+        let first = cache.snapshot(100, 1);
+        assert_eq!(first.chats.len(), 1);
+        assert!(first.has_more);
+        assert!(!cache.claim_refresh(100 + FRESH_SECS));
+        assert!(!cache.snapshot(100 + FRESH_SECS, 2).stale);
+
+        assert!(cache.claim_refresh(101 + FRESH_SECS));
+        assert!(!cache.claim_refresh(102 + FRESH_SECS));
+        let snapshot = cache.snapshot(102 + FRESH_SECS, 2);
+        assert!(snapshot.stale && snapshot.refreshing);
+        assert_eq!(snapshot.fetched_at, 100);
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let restored: ChatListCache = serde_json::from_str(&json).unwrap();
+        assert!(!restored.refreshing);
+        assert_eq!(restored.chats.len(), 2);
+        assert!(restored.has_more);
+    }
+}
@@ -1,20 +1,209 @@
+use crate::auto_reply;
 use crate::deepseek::is_supported_model;
-use crate::types::{Config, ListenTarget};
+use crate::experiments;
+use crate::http_client;
+use crate::listen_targets::{normalize_listen_targets, MAX_LISTEN_TARGETS};
+use crate::reply_length;
+use crate::safety_filter;
+use crate::styles;
+use crate::types::{
+    AutoReplyRule, AutomationStrategy, Config, FallbackMode, ListenTarget, LowPowerMode, ProfileSummary,
+    PromptExperiment, ReplyLengthLimit, SafetyRule, StylePreset,
+};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
 use tauri::Manager;
 use tracing::warn;
 
 const CONFIG_FILE: &str = "config.json";
+const PROFILES_FILE: &str = "profiles.json";
+pub const MAX_PROFILES: usize = 20;
+pub const MAX_PROFILE_NAME_CHARS: usize = 32;
+const MAX_SELF_NICKNAME_CHARS: usize = 32;
+const MAX_ACCOUNTS: usize = 4;
+const MAX_ACCOUNT_ID_CHARS: usize = 32;
 
 #[derive(Debug, Serialize, Deserialize)]
+struct StoredProfile {
+    name: String,
+    config: StoredConfig,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProfileStore {
+    #[serde(default)]
+    active: Option<String>,
+    #[serde(default)]
+    profiles: Vec<StoredProfile>,
+}
+
+impl ProfileStore {
+    pub fn summaries(&self) -> Vec<ProfileSummary> {
+        self.profiles
+            .iter()
+            .map(|profile| {
+                let config = profile.config.to_config();
+                ProfileSummary {
+                    name: profile.name.clone(),
+                    deepseek_model: config.deepseek_model,
+                    listen_target_count: config.listen_targets.len() as u32,
+                    active: self.active.as_deref() == Some(profile.name.as_str()),
+                }
+            })
+            .collect()
+    }
+
+    pub fn save_profile(&mut self, name: &str, config: &Config) -> Result<()> {
+        let name = normalize_profile_name(name)?;
+        let stored = StoredConfig::from_config(config);
+        if let Some(profile) = self
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.name == name)
+        {
+            profile.config = stored;
+            return Ok(());
+        }
+        if self.profiles.len() >= MAX_PROFILES {
+            anyhow::bail!("配置方案数量已达上限");
+        }
+        self.profiles.push(StoredProfile {
+            name,
+            config: stored,
+        });
+        Ok(())
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        let before = self.profiles.len();
+        self.profiles.retain(|profile| profile.name != name);
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.profiles.len() != before
+    }
+
+    pub fn switch_profile(&mut self, name: &str, current: &Config) -> Result<Config> {
+        let name = name.trim();
+        let Some(profile) = self.profiles.iter().find(|profile| profile.name == name) else {
+            anyhow::bail!("配置方案不存在");
+        };
+        let next = prepare_config(profile.config.to_config())?;
+        if let Some(active) = self.active.clone() {
+            if active != name {
+                self.save_profile(&active, current)?;
+            }
+        }
+        self.active = Some(name.to_string());
+        Ok(next)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct StoredConfig {
     deepseek_model: Option<String>,
     listen_targets: Option<Vec<ListenTarget>>,
+    #[serde(default)]
+    hide_dock_icon: Option<bool>,
+    #[serde(default)]
+    suggestion_count: Option<u32>,
+    #[serde(default)]
+    context_max_messages: Option<u32>,
+    #[serde(default)]
+    context_max_chars: Option<u32>,
+    #[serde(default)]
+    context_max_age_secs: Option<u64>,
+    #[serde(default)]
+    poll_interval_ms: Option<u64>,
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    top_p: Option<f32>,
+    #[serde(default)]
+    base_url: Option<String>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    max_retries: Option<u32>,
+    #[serde(default)]
+    log_level: Option<String>,
+    #[serde(default)]
+    log_to_file: Option<bool>,
+    #[serde(default)]
+    low_power_mode: Option<LowPowerMode>,
+    #[serde(default)]
+    history_retention_days: Option<u32>,
+    #[serde(default)]
+    fallback_mode: Option<FallbackMode>,
+    #[serde(default)]
+    automation_trace: Option<bool>,
+    #[serde(default)]
+    automation_trace_minutes: Option<u32>,
+    #[serde(default)]
+    daily_request_limit: Option<u32>,
+    #[serde(default)]
+    daily_token_limit: Option<u32>,
+    #[serde(default)]
+    max_concurrent_generations: Option<u32>,
+    #[serde(default)]
+    automation_concurrency: Option<u32>,
+    #[serde(default)]
+    auto_reply_enabled: Option<bool>,
+    #[serde(default)]
+    auto_reply_max_per_hour: Option<u32>,
+    #[serde(default)]
+    auto_reply_rules: Option<Vec<AutoReplyRule>>,
+    #[serde(default)]
+    self_nickname: Option<String>,
+    #[serde(default)]
+    image_ocr_enabled: Option<bool>,
+    #[serde(default)]
+    tesseract_path: Option<String>,
+    #[serde(default)]
+    voice_transcription_enabled: Option<bool>,
+    #[serde(default)]
+    transcription_base_url: Option<String>,
+    #[serde(default)]
+    transcription_model: Option<String>,
+    #[serde(default)]
+    knowledge_base_dir: Option<String>,
+    #[serde(default)]
+    knowledge_top_k: Option<u32>,
+    #[serde(default)]
+    style_presets: Option<Vec<StylePreset>>,
+    #[serde(default)]
+    reply_length_limits: Option<Vec<ReplyLengthLimit>>,
+    #[serde(default)]
+    safety_rules: Option<Vec<SafetyRule>>,
+    #[serde(default)]
+    pii_redaction_enabled: Option<bool>,
+    #[serde(default)]
+    expose_reasoning: Option<bool>,
+    #[serde(default)]
+    best_pick_mode: Option<bool>,
+    #[serde(default)]
+    followup_questions_enabled: Option<bool>,
+    #[serde(default)]
+    prompt_experiment: Option<PromptExperiment>,
+    #[serde(default)]
+    style_learning_enabled: Option<bool>,
+    #[serde(default)]
+    ca_bundle_path: Option<String>,
+    #[serde(default)]
+    pin_ca_bundle: Option<bool>,
+    #[serde(default)]
+    accounts: Option<Vec<String>>,
+    #[serde(default)]
+    automation_strategies: Option<Vec<AutomationStrategy>>,
+    #[serde(default)]
+    pause_when_wechat_unfocused: Option<bool>,
+    #[serde(default)]
+    integration_port: Option<u16>,
 }
 
 impl StoredConfig {
@@ -22,9 +211,63 @@ impl StoredConfig {
         Self {
             deepseek_model: Some(config.deepseek_model.clone()),
             listen_targets: Some(config.listen_targets.clone()),
+            hide_dock_icon: Some(config.hide_dock_icon),
+            suggestion_count: Some(config.suggestion_count),
+            context_max_messages: Some(config.context_max_messages),
+            context_max_chars: Some(config.context_max_chars),
+            context_max_age_secs: Some(config.context_max_age_secs),
+            poll_interval_ms: Some(config.poll_interval_ms),
+            temperature: Some(config.temperature),
+            top_p: Some(config.top_p),
+            base_url: Some(config.base_url.clone()),
+            timeout_ms: Some(config.timeout_ms),
+            max_retries: Some(config.max_retries),
+            log_level: Some(config.log_level.clone()),
+            log_to_file: Some(config.log_to_file),
+            low_power_mode: Some(config.low_power_mode),
+            history_retention_days: Some(config.history_retention_days),
+            fallback_mode: Some(config.fallback_mode),
+            automation_trace: Some(config.automation_trace),
+            automation_trace_minutes: Some(config.automation_trace_minutes),
+            daily_request_limit: Some(config.daily_request_limit),
+            daily_token_limit: Some(config.daily_token_limit),
+            max_concurrent_generations: Some(config.max_concurrent_generations),
+            automation_concurrency: Some(config.automation_concurrency),
+            auto_reply_enabled: Some(config.auto_reply_enabled),
+            auto_reply_max_per_hour: Some(config.auto_reply_max_per_hour),
+            auto_reply_rules: Some(config.auto_reply_rules.clone()),
+            self_nickname: Some(config.self_nickname.clone()),
+            image_ocr_enabled: Some(config.image_ocr_enabled),
+            tesseract_path: Some(config.tesseract_path.clone()),
+            voice_transcription_enabled: Some(config.voice_transcription_enabled),
+            transcription_base_url: Some(config.transcription_base_url.clone()),
+            transcription_model: Some(config.transcription_model.clone()),
+            knowledge_base_dir: Some(config.knowledge_base_dir.clone()),
+            knowledge_top_k: Some(config.knowledge_top_k),
+            style_presets: Some(config.style_presets.clone()),
+            reply_length_limits: Some(config.reply_length_limits.clone()),
+            safety_rules: Some(config.safety_rules.clone()),
+            pii_redaction_enabled: Some(config.pii_redaction_enabled),
+            expose_reasoning: Some(config.expose_reasoning),
+            best_pick_mode: Some(config.best_pick_mode),
+            followup_questions_enabled: Some(config.followup_questions_enabled),
+            prompt_experiment: Some(config.prompt_experiment.clone()),
+            style_learning_enabled: Some(config.style_learning_enabled),
+            ca_bundle_path: Some(config.ca_bundle_path.clone()),
+            pin_ca_bundle: Some(config.pin_ca_bundle),
+            accounts: Some(config.accounts.clone()),
+            automation_strategies: Some(config.automation_strategies.clone()),
+            pause_when_wechat_unfocused: Some(config.pause_when_wechat_unfocused),
+            integration_port: Some(config.integration_port),
         }
     }
 
+    fn to_config(&self) -> Config {
+        let mut config = Config::default();
+        self.clone().apply(&mut config);
+        config
+    }
+
     fn apply(self, config: &mut Config) {
         if let Some(model) = self.deepseek_model {
             config.deepseek_model = model;
@@ -32,6 +275,150 @@ impl StoredConfig {
         if let Some(listen_targets) = self.listen_targets {
             config.listen_targets = listen_targets;
         }
+        if let Some(hide_dock_icon) = self.hide_dock_icon {
+            config.hide_dock_icon = hide_dock_icon;
+        }
+        if let Some(suggestion_count) = self.suggestion_count {
+            config.suggestion_count = suggestion_count;
+        }
+        if let Some(context_max_messages) = self.context_max_messages {
+            config.context_max_messages = context_max_messages;
+        }
+        if let Some(context_max_chars) = self.context_max_chars {
+            config.context_max_chars = context_max_chars;
+        }
+        if let Some(context_max_age_secs) = self.context_max_age_secs {
+            config.context_max_age_secs = context_max_age_secs;
+        }
+        if let Some(poll_interval_ms) = self.poll_interval_ms {
+            config.poll_interval_ms = poll_interval_ms;
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = top_p;
+        }
+        if let Some(base_url) = self.base_url {
+            config.base_url = base_url;
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            config.timeout_ms = timeout_ms;
+        }
+        if let Some(max_retries) = self.max_retries {
+            config.max_retries = max_retries;
+        }
+        if let Some(log_level) = self.log_level {
+            config.log_level = log_level;
+        }
+        if let Some(log_to_file) = self.log_to_file {
+            config.log_to_file = log_to_file;
+        }
+        if let Some(low_power_mode) = self.low_power_mode {
+            config.low_power_mode = low_power_mode;
+        }
+        if let Some(history_retention_days) = self.history_retention_days {
+            config.history_retention_days = history_retention_days;
+        }
+        if let Some(fallback_mode) = self.fallback_mode {
+            config.fallback_mode = fallback_mode;
+        }
+        if let Some(automation_trace) = self.automation_trace {
+            config.automation_trace = automation_trace;
+        }
+        if let Some(automation_trace_minutes) = self.automation_trace_minutes {
+            config.automation_trace_minutes = automation_trace_minutes;
+        }
+        if let Some(daily_request_limit) = self.daily_request_limit {
+            config.daily_request_limit = daily_request_limit;
+        }
+        if let Some(daily_token_limit) = self.daily_token_limit {
+            config.daily_token_limit = daily_token_limit;
+        }
+        if let Some(max_concurrent_generations) = self.max_concurrent_generations {
+            config.max_concurrent_generations = max_concurrent_generations;
+        }
+        if let Some(automation_concurrency) = self.automation_concurrency {
+            config.automation_concurrency = automation_concurrency;
+        }
+        if let Some(auto_reply_enabled) = self.auto_reply_enabled {
+            config.auto_reply_enabled = auto_reply_enabled;
+        }
+        if let Some(auto_reply_max_per_hour) = self.auto_reply_max_per_hour {
+            config.auto_reply_max_per_hour = auto_reply_max_per_hour;
+        }
+        if let Some(auto_reply_rules) = self.auto_reply_rules {
+            config.auto_reply_rules = auto_reply_rules;
+        }
+        if let Some(self_nickname) = self.self_nickname {
+            config.self_nickname = self_nickname;
+        }
+        if let Some(image_ocr_enabled) = self.image_ocr_enabled {
+            config.image_ocr_enabled = image_ocr_enabled;
+        }
+        if let Some(tesseract_path) = self.tesseract_path {
+            config.tesseract_path = tesseract_path;
+        }
+        if let Some(voice_transcription_enabled) = self.voice_transcription_enabled {
+            config.voice_transcription_enabled = voice_transcription_enabled;
+        }
+        if let Some(transcription_base_url) = self.transcription_base_url {
+            config.transcription_base_url = transcription_base_url;
+        }
+        if let Some(transcription_model) = self.transcription_model {
+            config.transcription_model = transcription_model;
+        }
+        if let Some(knowledge_base_dir) = self.knowledge_base_dir {
+            config.knowledge_base_dir = knowledge_base_dir;
+        }
+        if let Some(knowledge_top_k) = self.knowledge_top_k {
+            config.knowledge_top_k = knowledge_top_k;
+        }
+        if let Some(style_presets) = self.style_presets {
+            config.style_presets = style_presets;
+        }
+        if let Some(reply_length_limits) = self.reply_length_limits {
+            config.reply_length_limits = reply_length_limits;
+        }
+        if let Some(safety_rules) = self.safety_rules {
+            config.safety_rules = safety_rules;
+        }
+        if let Some(pii_redaction_enabled) = self.pii_redaction_enabled {
+            config.pii_redaction_enabled = pii_redaction_enabled;
+        }
+        if let Some(expose_reasoning) = self.expose_reasoning {
+            config.expose_reasoning = expose_reasoning;
+        }
+        if let Some(best_pick_mode) = self.best_pick_mode {
+            config.best_pick_mode = best_pick_mode;
+        }
+        if let Some(followup_questions_enabled) = self.followup_questions_enabled {
+            config.followup_questions_enabled = followup_questions_enabled;
+        }
+        if let Some(prompt_experiment) = self.prompt_experiment {
+            config.prompt_experiment = prompt_experiment;
+        }
+        if let Some(style_learning_enabled) = self.style_learning_enabled {
+            config.style_learning_enabled = style_learning_enabled;
+        }
+        if let Some(ca_bundle_path) = self.ca_bundle_path {
+            config.ca_bundle_path = ca_bundle_path;
+        }
+        if let Some(pin_ca_bundle) = self.pin_ca_bundle {
+            config.pin_ca_bundle = pin_ca_bundle;
+        }
+        if let Some(accounts) = self.accounts {
+            config.accounts = accounts;
+        }
+        if let Some(automation_strategies) = self.automation_strategies {
+            config.automation_strategies = automation_strategies;
+        }
+        if let Some(pause_when_wechat_unfocused) = self.pause_when_wechat_unfocused {
+            config.pause_when_wechat_unfocused = pause_when_wechat_unfocused;
+        }
+        if let Some(integration_port) = self.integration_port {
+            config.integration_port = integration_port;
+        }
     }
 }
 
@@ -58,7 +445,6 @@ pub fn load_config(app: &AppHandle) -> Result<Config> {
     Ok(config)
 }
 
-#[allow(dead_code)]
 pub fn save_config(app: &AppHandle, config: &Config) -> Result<()> {
     let path = config_path(app)?;
     let stored = StoredConfig::from_config(config);
@@ -66,7 +452,60 @@ pub fn save_config(app: &AppHandle, config: &Config) -> Result<()> {
     fs::write(&path, contents).with_context(|| format!("写入配置失败: {}", path.display()))
 }
 
-#[allow(dead_code)]
+pub fn prepare_config(mut config: Config) -> Result<Config> {
+    config.listen_targets = normalize_listen_targets(config.listen_targets, MAX_LISTEN_TARGETS)?;
+    config.base_url = config.base_url.trim().trim_end_matches('/').to_string();
+    config.log_level = config.log_level.trim().to_lowercase();
+    config.self_nickname = config.self_nickname.trim().to_string();
+    config.tesseract_path = config.tesseract_path.trim().to_string();
+    config.transcription_base_url = config
+        .transcription_base_url
+        .trim()
+        .trim_end_matches('/')
+        .to_string();
+    config.transcription_model = config.transcription_model.trim().to_string();
+    config.knowledge_base_dir = config.knowledge_base_dir.trim().to_string();
+    config.style_presets = styles::normalize_presets(config.style_presets);
+    config.reply_length_limits = reply_length::normalize_limits(config.reply_length_limits);
+    config.safety_rules = safety_filter::normalize_rules(config.safety_rules);
+    config.prompt_experiment = experiments::normalize_experiment(config.prompt_experiment);
+    config.ca_bundle_path = config.ca_bundle_path.trim().to_string();
+    config.accounts = normalize_accounts(config.accounts);
+    config.automation_strategies = normalize_strategies(config.automation_strategies);
+    validate_config(&config)?;
+    if !config.knowledge_base_dir.is_empty() && !Path::new(&config.knowledge_base_dir).is_dir() {
+        anyhow::bail!("知识库目录不存在");
+    }
+    if !config.ca_bundle_path.is_empty() {
+        if !Path::new(&config.ca_bundle_path).is_file() {
+            anyhow::bail!("CA 证书文件不存在");
+        }
+        http_client::load_ca_bundle(&config.ca_bundle_path)?;
+    }
+    Ok(config)
+}
+
+fn normalize_accounts(accounts: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for account in accounts {
+        let account = account.trim().to_string();
+        if !account.is_empty() && !normalized.contains(&account) {
+            normalized.push(account);
+        }
+    }
+    normalized
+}
+
+fn normalize_strategies(strategies: Vec<AutomationStrategy>) -> Vec<AutomationStrategy> {
+    let mut normalized: Vec<AutomationStrategy> = Vec::new();
+    for strategy in strategies {
+        if !normalized.contains(&strategy) {
+            normalized.push(strategy);
+        }
+    }
+    normalized
+}
+
 pub fn validate_config(config: &Config) -> Result<()> {
     if config.suggestion_count == 0 {
         anyhow::bail!("建议数量必须大于 0");
@@ -86,14 +525,115 @@ pub fn validate_config(config: &Config) -> Result<()> {
     if !is_supported_model(&config.deepseek_model) {
         anyhow::bail!("不支持的模型");
     }
+    if !config.base_url.starts_with("https://") && !config.base_url.starts_with("http://") {
+        anyhow::bail!("base_url 必须以 http:// 或 https:// 开头");
+    }
+    if config.timeout_ms < 1000 {
+        anyhow::bail!("请求超时不能小于 1000ms");
+    }
+    if !(1..=120).contains(&config.automation_trace_minutes) {
+        anyhow::bail!("自动化追踪时长必须在 1 到 120 分钟之间");
+    }
+    if !(1..=8).contains(&config.max_concurrent_generations) {
+        anyhow::bail!("并发生成数必须在 1 到 8 之间");
+    }
+    if !(1..=4).contains(&config.automation_concurrency) {
+        anyhow::bail!("自动化并发数必须在 1 到 4 之间");
+    }
+    if !(1..=60).contains(&config.auto_reply_max_per_hour) {
+        anyhow::bail!("每小时自动回复上限必须在 1 到 60 之间");
+    }
+    if config.self_nickname.chars().count() > MAX_SELF_NICKNAME_CHARS {
+        anyhow::bail!("我的群昵称不能超过 {} 字", MAX_SELF_NICKNAME_CHARS);
+    }
+    if config.image_ocr_enabled && config.tesseract_path.is_empty() {
+        anyhow::bail!("开启图片识别时必须填写 tesseract 路径");
+    }
+    if config.voice_transcription_enabled {
+        let url = config.transcription_base_url.as_str();
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            anyhow::bail!("语音转写地址必须以 http:// 或 https:// 开头");
+        }
+        if config.transcription_model.is_empty() {
+            anyhow::bail!("开启语音转写时必须填写模型名称");
+        }
+    }
+    if !(1..=10).contains(&config.knowledge_top_k) {
+        anyhow::bail!("知识库引用片段数必须在 1 到 10 之间");
+    }
+    auto_reply::validate_rules(&config.auto_reply_rules)?;
+    styles::validate_presets(&config.style_presets)?;
+    reply_length::validate_limits(&config.reply_length_limits, &config.style_presets)?;
+    safety_filter::validate_rules(&config.safety_rules)?;
+    experiments::validate_experiment(&config.prompt_experiment)?;
+    if config.pin_ca_bundle && config.ca_bundle_path.is_empty() {
+        anyhow::bail!("仅信任自定义证书时必须指定 CA 证书文件");
+    }
+    if config.accounts.len() > MAX_ACCOUNTS {
+        anyhow::bail!("额外账号最多 {} 个", MAX_ACCOUNTS);
+    }
+    if config.accounts.iter().any(|account| {
+        account.chars().count() > MAX_ACCOUNT_ID_CHARS
+            || account.chars().any(|ch| ch.is_whitespace() || ch.is_control())
+    }) {
+        anyhow::bail!("账号标识不能包含空白且不能超过 {} 字", MAX_ACCOUNT_ID_CHARS);
+    }
+    if config.automation_strategies.is_empty() {
+        anyhow::bail!("至少需要选择一种自动化方式");
+    }
+    if config.integration_port != 0 && config.integration_port < 1024 {
+        anyhow::bail!("集成接口端口必须为 0（关闭）或不小于 1024");
+    }
+    if !matches!(
+        config.log_level.as_str(),
+        "trace" | "debug" | "info" | "warn" | "error"
+    ) {
+        anyhow::bail!("不支持的日志级别");
+    }
     Ok(())
 }
 
+pub fn load_profiles(app: &AppHandle) -> Result<ProfileStore> {
+    let path = profiles_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ProfileStore::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取配置方案失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<ProfileStore>(&contents) {
+        Ok(store) => Ok(store),
+        Err(err) => {
+            warn!("解析配置方案失败，忽略已保存方案: {}", err);
+            Ok(ProfileStore::default())
+        }
+    }
+}
+
+pub fn save_profiles(app: &AppHandle, store: &ProfileStore) -> Result<()> {
+    let path = profiles_path(app)?;
+    let contents = serde_json::to_string_pretty(store).context("序列化配置方案失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入配置方案失败: {}", path.display()))
+}
+
+fn normalize_profile_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("方案名称不能为空");
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_CHARS {
+        anyhow::bail!("方案名称过长");
+    }
+    Ok(name.to_string())
+}
+
+fn profiles_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(config_path(app)?.with_file_name(PROFILES_FILE))
+}
+
 fn config_path(app: &AppHandle) -> Result<PathBuf> {
-    let dir = app
-        .path()
-        .app_config_dir()
-        .context("无法获取配置目录")?;
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
     fs::create_dir_all(&dir).context("创建配置目录失败")?;
     Ok(dir.join(CONFIG_FILE))
 }
@@ -101,6 +641,7 @@ fn config_path(app: &AppHandle) -> Result<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{EmojiPolicy, SafetyAction, SuggestionStyle};
 
     #[test]
     fn validate_config_rejects_invalid_values() {
@@ -112,11 +653,267 @@ mod tests {
     }
 
     #[test]
-    fn validate_config_rejects_unknown_model() {
+    fn prepare_config_normalizes_and_validates() {
+        let config = prepare_config(Config {
+            base_url: " https://api.deepseek.com/ ".to_string(),
+            log_level: "INFO".to_string(),
+            ..Config::default()
+        })
+        .unwrap();
+        assert_eq!(config.base_url, "https://api.deepseek.com");
+        assert_eq!(config.log_level, "info");
+
+        let invalid = Config {
+            poll_interval_ms: 100,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            base_url: "api.deepseek.com".to_string(),
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            automation_trace_minutes: 0,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            max_concurrent_generations: 0,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            automation_concurrency: 5,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            auto_reply_max_per_hour: 0,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            integration_port: 80,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            pin_ca_bundle: true,
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let invalid = Config {
+            accounts: vec!["work account".to_string()],
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let accounts = Config {
+            accounts: vec![" work ".to_string(), String::new(), "work".to_string()],
+            ..Config::default()
+        };
+        assert_eq!(prepare_config(accounts).unwrap().accounts, vec!["work"]);
+        let invalid = Config {
+            automation_strategies: Vec::new(),
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+        let strategies = Config {
+            automation_strategies: vec![
+                AutomationStrategy::Db,
+                AutomationStrategy::Ui,
+                AutomationStrategy::Db,
+            ],
+            ..Config::default()
+        };
+        assert_eq!(
+            prepare_config(strategies).unwrap().automation_strategies,
+            vec![AutomationStrategy::Db, AutomationStrategy::Ui]
+        );
+        let invalid = Config {
+            ca_bundle_path: " /nonexistent/corp-ca.pem ".to_string(),
+            ..Config::default()
+        };
+        assert!(prepare_config(invalid).is_err());
+    }
+
+    #[test]
+    fn stored_config_round_trips_all_fields() {
+        let config = Config {
+            poll_interval_ms: 1500,
+            temperature: 0.3,
+            log_to_file: !Config::default().log_to_file,
+            low_power_mode: LowPowerMode::Off,
+            fallback_mode: FallbackMode::RetryOnly,
+            automation_trace: true,
+            automation_trace_minutes: 30,
+            daily_request_limit: 200,
+            daily_token_limit: 500_000,
+            max_concurrent_generations: 4,
+            automation_concurrency: 2,
+            auto_reply_enabled: true,
+            auto_reply_max_per_hour: 5,
+            self_nickname: "小王".to_string(),
+            image_ocr_enabled: true,
+            tesseract_path: "/opt/homebrew/bin/tesseract".to_string(),
+            voice_transcription_enabled: true,
+            transcription_base_url: "http://127.0.0.1:9000/v1".to_string(),
+            transcription_model: "whisper-large-v3".to_string(),
+            knowledge_base_dir: "/Users/me/notes".to_string(),
+            knowledge_top_k: 5,
+            style_presets: vec![StylePreset {
+                name: "buddy".to_string(),
+                description: "哥们".to_string(),
+                prompt: "像老朋友一样说话".to_string(),
+                emoji: EmojiPolicy::Prefer,
+            }],
+            reply_length_limits: vec![ReplyLengthLimit {
+                style: SuggestionStyle::new("buddy"),
+                min_chars: 4,
+                max_chars: 30,
+            }],
+            safety_rules: vec![SafetyRule {
+                pattern: r"\d{11}".to_string(),
+                regex: true,
+                action: SafetyAction::Flag,
+            }],
+            pii_redaction_enabled: true,
+            expose_reasoning: true,
+            best_pick_mode: true,
+            followup_questions_enabled: true,
+            prompt_experiment: PromptExperiment {
+                enabled: true,
+                name: "简短回复".to_string(),
+                variant_a: "回复尽量简短".to_string(),
+                variant_b: "回复先寒暄再回答".to_string(),
+            },
+            style_learning_enabled: true,
+            ca_bundle_path: "/etc/ssl/corp-ca.pem".to_string(),
+            pin_ca_bundle: true,
+            accounts: vec!["work".to_string()],
+            automation_strategies: vec![AutomationStrategy::Db, AutomationStrategy::Agent],
+            pause_when_wechat_unfocused: true,
+            integration_port: 18_790,
+            auto_reply_rules: vec![AutoReplyRule {
+                target: "客户群".to_string(),
+                keyword: "价格".to_string(),
+                template: "稍后回复".to_string(),
+                canned_response_id: None,
+                hours: None,
+            }],
+            ..Config::default()
+        };
+        let json = serde_json::to_string(&StoredConfig::from_config(&config)).unwrap();
+        let mut restored = Config::default();
+        serde_json::from_str::<StoredConfig>(&json)
+            .unwrap()
+            .apply(&mut restored);
+        assert_eq!(restored.poll_interval_ms, 1500);
+        assert_eq!(restored.temperature, 0.3);
+        assert_eq!(restored.log_to_file, config.log_to_file);
+        assert_eq!(restored.low_power_mode, LowPowerMode::Off);
+        assert_eq!(restored.fallback_mode, FallbackMode::RetryOnly);
+        assert!(json.contains(r#""fallback_mode":"retry_only""#));
+        assert!(restored.automation_trace);
+        assert_eq!(restored.automation_trace_minutes, 30);
+        assert_eq!(restored.daily_request_limit, 200);
+        assert_eq!(restored.daily_token_limit, 500_000);
+        assert_eq!(restored.max_concurrent_generations, 4);
+        assert_eq!(restored.automation_concurrency, 2);
+        assert!(restored.auto_reply_enabled);
+        assert_eq!(restored.auto_reply_max_per_hour, 5);
+        assert_eq!(restored.auto_reply_rules, config.auto_reply_rules);
+        assert_eq!(restored.self_nickname, "小王");
+        assert!(restored.image_ocr_enabled);
+        assert_eq!(restored.tesseract_path, "/opt/homebrew/bin/tesseract");
+        assert!(restored.voice_transcription_enabled);
+        assert_eq!(restored.transcription_base_url, "http://127.0.0.1:9000/v1");
+        assert_eq!(restored.transcription_model, "whisper-large-v3");
+        assert_eq!(restored.knowledge_base_dir, "/Users/me/notes");
+        assert_eq!(restored.knowledge_top_k, 5);
+        assert_eq!(restored.style_presets, config.style_presets);
+        assert_eq!(restored.reply_length_limits, config.reply_length_limits);
+        assert_eq!(restored.safety_rules, config.safety_rules);
+        assert!(restored.pii_redaction_enabled);
+        assert!(restored.expose_reasoning);
+        assert!(restored.best_pick_mode);
+        assert!(restored.followup_questions_enabled);
+        assert_eq!(restored.prompt_experiment, config.prompt_experiment);
+        assert!(restored.style_learning_enabled);
+        assert_eq!(restored.ca_bundle_path, "/etc/ssl/corp-ca.pem");
+        assert!(restored.pin_ca_bundle);
+        assert_eq!(restored.accounts, vec!["work"]);
+        assert_eq!(
+            restored.automation_strategies,
+            vec![AutomationStrategy::Db, AutomationStrategy::Agent]
+        );
+        assert!(restored.pause_when_wechat_unfocused);
+        assert_eq!(restored.integration_port, 18_790);
+
+        let mut legacy = Config::default();
+        serde_json::from_str::<StoredConfig>(r#"{"deepseek_model":"deepseek-chat"}"#)
+            .unwrap()
+            .apply(&mut legacy);
+        assert_eq!(legacy.poll_interval_ms, Config::default().poll_interval_ms);
+    }
+
+    #[test]
+    fn profiles_switch_and_keep_active_edits() {
+        let mut store = ProfileStore::default();
+        let work = Config {
+            deepseek_model: "deepseek-reasoner".to_string(),
+            listen_targets: vec![ListenTarget {
+                name: "项目群".to_string(),
+                kind: crate::types::ChatKind::Group,
+                prompt_override: Some("语气正式".to_string()),
+                persona: None,
+                muted: false,
+                priority: crate::types::TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: crate::types::Politeness::Polite,
+            }],
+            ..Config::default()
+        };
+        store.save_profile(" 工作 ", &work).unwrap();
+        store.save_profile("私人", &Config::default()).unwrap();
+        assert!(store.save_profile("  ", &work).is_err());
+
+        let active = store.switch_profile("工作", &Config::default()).unwrap();
+        assert_eq!(active.deepseek_model, "deepseek-reasoner");
+        assert_eq!(active.listen_targets.len(), 1);
+
+        let edited = Config {
+            poll_interval_ms: 2000,
+            ..active
+        };
+        store.switch_profile("私人", &edited).unwrap();
+        let back = store.switch_profile("工作", &Config::default()).unwrap();
+        assert_eq!(back.poll_interval_ms, 2000);
+
+        let summaries = store.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries
+            .iter()
+            .any(|profile| profile.name == "工作" && profile.active));
+        assert!(store.switch_profile("不存在", &Config::default()).is_err());
+        assert!(store.remove_profile("工作"));
+        assert!(store.summaries().iter().all(|profile| !profile.active));
+    }
+
+    #[test]
+    fn validate_config_rejects_malformed_model() {
         let config = Config {
-            deepseek_model: "unknown".to_string(),
+            deepseek_model: "deepseek chat".to_string(),
             ..Config::default()
         };
         assert!(validate_config(&config).is_err());
+        let listed = Config {
+            deepseek_model: "deepseek-v3.2-exp".to_string(),
+            ..Config::default()
+        };
+        assert!(validate_config(&listed).is_ok());
     }
 }
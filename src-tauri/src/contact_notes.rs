@@ -0,0 +1,152 @@
+use crate::types::ContactNote;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+const CONTACT_NOTES_FILE: &str = "contact_notes.json";
+const MAX_CONTACT_NOTES: usize = 500;
+const MAX_CHAT_ID_CHARS: usize = 128;
+const MAX_NOTE_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContactNoteStore {
+    #[serde(default)]
+    notes: Vec<ContactNote>,
+}
+
+impl ContactNoteStore {
+    pub fn list(&self) -> Vec<ContactNote> {
+        let mut notes = self.notes.clone();
+        notes.sort_by_key(|note| std::cmp::Reverse(note.updated_at));
+        notes
+    }
+
+    pub fn get(&self, chat_id: &str) -> Option<&ContactNote> {
+        self.notes.iter().find(|note| note.chat_id == chat_id)
+    }
+
+    pub fn note_for_chat(&self, chat_id: &str, chat_title: &str) -> Option<String> {
+        self.get(chat_id)
+            .or_else(|| self.get(chat_title))
+            .map(|note| note.note.clone())
+    }
+
+    pub fn set(&mut self, chat_id: &str, note: &str, now: u64) -> Result<ContactNote> {
+        let chat_id = normalize_chat_id(chat_id)?;
+        let note = normalize_note(note)?;
+        if let Some(existing) = self.notes.iter_mut().find(|item| item.chat_id == chat_id) {
+            existing.note = note;
+            existing.updated_at = now;
+            return Ok(existing.clone());
+        }
+        if self.notes.len() >= MAX_CONTACT_NOTES {
+            anyhow::bail!("联系人备注数量已达上限");
+        }
+        let created = ContactNote {
+            chat_id,
+            note,
+            updated_at: now,
+        };
+        self.notes.push(created.clone());
+        Ok(created)
+    }
+
+    pub fn remove(&mut self, chat_id: &str) -> bool {
+        let chat_id = chat_id.trim();
+        let before = self.notes.len();
+        self.notes.retain(|note| note.chat_id != chat_id);
+        self.notes.len() != before
+    }
+}
+
+fn normalize_chat_id(chat_id: &str) -> Result<String> {
+    let chat_id = chat_id.trim();
+    if chat_id.is_empty() {
+        anyhow::bail!("会话不能为空");
+    }
+    if chat_id.chars().count() > MAX_CHAT_ID_CHARS {
+        anyhow::bail!("会话标识过长");
+    }
+    Ok(chat_id.to_string())
+}
+
+fn normalize_note(note: &str) -> Result<String> {
+    let note = note.split_whitespace().collect::<Vec<_>>().join(" ");
+    if note.is_empty() {
+        anyhow::bail!("联系人备注不能为空");
+    }
+    if note.chars().count() > MAX_NOTE_CHARS {
+        anyhow::bail!("联系人备注不能超过 {} 字", MAX_NOTE_CHARS);
+    }
+    Ok(note)
+}
+
+pub fn load_contact_notes(app: &AppHandle) -> Result<ContactNoteStore> {
+    let path = contact_notes_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ContactNoteStore::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取联系人备注失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<ContactNoteStore>(&contents) {
+        Ok(store) => Ok(store),
+        Err(err) => {
+            warn!("解析联系人备注失败，忽略已保存内容: {}", err);
+            Ok(ContactNoteStore::default())
+        }
+    }
+}
+
+pub fn save_contact_notes(app: &AppHandle, store: &ContactNoteStore) -> Result<()> {
+    let path = contact_notes_path(app)?;
+    let contents = serde_json::to_string_pretty(store).context("序列化联系人备注失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入联系人备注失败: {}", path.display()))
+}
+
+fn contact_notes_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
+    fs::create_dir_all(&dir).context("创建配置目录失败")?;
+    Ok(dir.join(CONTACT_NOTES_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_updates_and_removes_notes() {
+        let mut store = ContactNoteStore::default();
+        let created = store
+            .set(" 房东王姐 ", "房东，说话客气些\n常聊房租", 100)
+            .unwrap();
+        assert_eq!(created.chat_id, "房东王姐");
+        assert_eq!(created.note, "房东，说话客气些 常聊房租");
+        store.set("wxid_abc", "老同学", 110).unwrap();
+        store.set("房东王姐", "房东，房租每月 5 号交", 120).unwrap();
+
+        let listed = store.list();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].note, "房东，房租每月 5 号交");
+        assert_eq!(
+            store.note_for_chat("wxid_missing", "房东王姐").as_deref(),
+            Some("房东，房租每月 5 号交")
+        );
+        assert!(store.note_for_chat("wxid_missing", "陌生人").is_none());
+        assert!(store.set("张三", "  ", 130).is_err());
+        assert!(store
+            .set("张三", &"长".repeat(MAX_NOTE_CHARS + 1), 130)
+            .is_err());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let mut restored: ContactNoteStore = serde_json::from_str(&json).unwrap();
+        assert!(restored.remove("wxid_abc"));
+        assert!(!restored.remove("wxid_abc"));
+        assert_eq!(restored.list().len(), 1);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::graphemes;
+use crate::types::MessageContentType;
+use crate::ui_automation::split_group_sender;
+
+const PLACEHOLDERS: [(&str, MessageContentType); 8] = [
+    ("[图片]", MessageContentType::Image),
+    ("[视频]", MessageContentType::Image),
+    ("[语音]", MessageContentType::Voice),
+    ("[链接]", MessageContentType::Link),
+    ("[动画表情]", MessageContentType::Sticker),
+    ("[表情]", MessageContentType::Sticker),
+    ("[文件]", MessageContentType::File),
+    ("[小程序]", MessageContentType::Link),
+];
+
+pub fn classify(text: &str) -> MessageContentType {
+    let text = text.trim();
+    PLACEHOLDERS
+        .iter()
+        .find(|(placeholder, _)| text.starts_with(placeholder))
+        .map(|(_, content_type)| *content_type)
+        .unwrap_or_default()
+}
+
+/// A database message as WeChat's UI shows it: the content type plus the same
+/// placeholder text (`[链接]标题`, `[文件]报价单.xlsx`) the UI backends read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedMessage {
+    pub content_type: MessageContentType,
+    pub text: String,
+}
+
+/// Decodes a row of WeChat's message tables; the `Type` codes are shared by the
+/// Windows and macOS databases. Media and app messages are stored as XML, which
+/// is reduced to the fields worth showing to the model.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn decode_wechat_message(msg_type: i64, content: &str) -> DecodedMessage {
+    let xml = content.find('<').map_or("", |start| &content[start..]);
+    let (content_type, text) = match msg_type {
+        1 => (classify(content), content.to_string()),
+        3 => (MessageContentType::Image, "[图片]".to_string()),
+        34 => {
+            let seconds = xml_attr(xml, "voicemsg", "voicelength")
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .map(|ms| format!("{}\"", ms.div_ceil(1000)));
+            (MessageContentType::Voice, labeled("[语音]", seconds))
+        }
+        43 | 62 => (MessageContentType::Image, "[视频]".to_string()),
+        47 => (MessageContentType::Sticker, "[动画表情]".to_string()),
+        42 => (MessageContentType::Text, labeled("[名片]", xml_attr(xml, "msg", "nickname"))),
+        48 => {
+            let place = xml_attr(xml, "location", "poiname")
+                .or_else(|| xml_attr(xml, "location", "label"));
+            (MessageContentType::Text, labeled("[位置]", place))
+        }
+        49 => decode_app_message(xml),
+        _ if xml.is_empty() => (classify(content), content.to_string()),
+        _ => (MessageContentType::Text, "[暂不支持的消息]".to_string()),
+    };
+    DecodedMessage { content_type, text }
+}
+
+const PREVIEW_GRAPHEMES: usize = 60;
+
+/// One-line preview of a chat's last message for the session list. Session
+/// tables usually keep a ready-made summary, but raw XML rows are decoded too.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn session_preview(msg_type: i64, content: &str) -> String {
+    let content = split_group_sender(content).map_or(content, |(_, rest)| rest).trim();
+    let text = if content.starts_with('<') {
+        decode_wechat_message(msg_type, content).text
+    } else {
+        content.to_string()
+    };
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    graphemes::truncate(line, PREVIEW_GRAPHEMES).to_string()
+}
+
+fn decode_app_message(xml: &str) -> (MessageContentType, String) {
+    let appmsg = xml_element(xml, "appmsg").unwrap_or(xml);
+    let title = xml_text(appmsg, "title");
+    let app_type = xml_text(appmsg, "type").and_then(|value| value.parse::<u32>().ok());
+    match app_type {
+        Some(6) => (MessageContentType::File, labeled("[文件]", title)),
+        Some(33 | 36) => (MessageContentType::Link, labeled("[小程序]", title)),
+        // Quote replies keep the reply itself in the title.
+        Some(57) => match title {
+            Some(reply) => (classify(&reply), reply),
+            None => (MessageContentType::Text, "[引用消息]".to_string()),
+        },
+        Some(19) => (MessageContentType::Text, labeled("[聊天记录]", title)),
+        Some(2000) => (MessageContentType::Text, labeled("[转账]", xml_text(appmsg, "des"))),
+        Some(2001) => (MessageContentType::Text, "[红包]".to_string()),
+        _ => (MessageContentType::Link, labeled("[链接]", title)),
+    }
+}
+
+fn labeled(placeholder: &str, detail: Option<String>) -> String {
+    match detail {
+        Some(detail) => format!("{}{}", placeholder, detail),
+        None => placeholder.to_string(),
+    }
+}
+
+/// Start of `<tag ...>`, skipping longer tags that share the prefix.
+fn find_open_tag(xml: &str, tag: &str) -> Option<usize> {
+    let pattern = format!("<{}", tag);
+    let mut offset = 0;
+    while let Some(found) = xml[offset..].find(&pattern) {
+        let start = offset + found;
+        let next = xml[start + pattern.len()..].chars().next();
+        if matches!(next, Some('>' | '/') | Some(' ' | '\t' | '\r' | '\n')) {
+            return Some(start);
+        }
+        offset = start + pattern.len();
+    }
+    None
+}
+
+fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = find_open_tag(xml, tag)?;
+    let open_end = start + xml[start..].find('>')?;
+    if xml[..open_end].ends_with('/') {
+        return None;
+    }
+    let inner = &xml[open_end + 1..];
+    let close = inner.find(&format!("</{}>", tag))?;
+    Some(&inner[..close])
+}
+
+fn xml_text(xml: &str, tag: &str) -> Option<String> {
+    let inner = xml_element(xml, tag)?.trim();
+    let inner = inner
+        .strip_prefix("<![CDATA[")
+        .and_then(|rest| rest.strip_suffix("]]>"))
+        .map(str::to_string)
+        .unwrap_or_else(|| unescape_xml(inner));
+    let inner = inner.trim();
+    (!inner.is_empty()).then(|| inner.to_string())
+}
+
+fn xml_attr(xml: &str, tag: &str, attr: &str) -> Option<String> {
+    let start = find_open_tag(xml, tag)?;
+    let open_tag = &xml[start..start + xml[start..].find('>')?];
+    let pattern = format!(" {}=\"", attr);
+    let value_start = open_tag.find(&pattern)? + pattern.len();
+    let value_len = open_tag[value_start..].find('"')?;
+    let value = unescape_xml(&open_tag[value_start..value_start + value_len]);
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            }?;
+            Some((ch, end + 1))
+        });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn resolve(declared: MessageContentType, text: &str) -> MessageContentType {
+    match declared {
+        MessageContentType::Text => classify(text),
+        other => other,
+    }
+}
+
+pub fn context_text(content_type: MessageContentType, text: &str) -> Option<String> {
+    let text = text.trim();
+    let label = match content_type {
+        MessageContentType::Text => {
+            return Some(text.to_string()).filter(|text| !text.is_empty());
+        }
+        MessageContentType::Link => "链接",
+        MessageContentType::File => "文件",
+        MessageContentType::Image | MessageContentType::Voice | MessageContentType::Sticker => {
+            return None;
+        }
+    };
+    let detail = strip_placeholder(text);
+    if detail.is_empty() {
+        return None;
+    }
+    Some(format!("（对方发送了{}：{}）", label, detail))
+}
+
+pub fn image_context(ocr_text: &str) -> Option<String> {
+    let ocr_text = ocr_text.trim();
+    if ocr_text.is_empty() {
+        return None;
+    }
+    Some(format!("（对方发送了图片，图中文字：{}）", ocr_text))
+}
+
+pub fn voice_context(transcript: &str) -> Option<String> {
+    let transcript = transcript.trim();
+    if transcript.is_empty() {
+        return None;
+    }
+    Some(format!("（对方发送了语音，内容：{}）", transcript))
+}
+
+fn strip_placeholder(text: &str) -> &str {
+    PLACEHOLDERS
+        .iter()
+        .find_map(|(placeholder, _)| text.strip_prefix(placeholder))
+        .unwrap_or(text)
+        .trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_wechat_placeholders() {
+        assert_eq!(classify("[图片]"), MessageContentType::Image);
+        assert_eq!(classify(" [语音]3\" "), MessageContentType::Voice);
+        assert_eq!(classify("[动画表情]"), MessageContentType::Sticker);
+        assert_eq!(classify("[文件]报价单.xlsx"), MessageContentType::File);
+        assert_eq!(classify("发你一张[图片]"), MessageContentType::Text);
+        assert_eq!(
+            resolve(MessageContentType::Voice, "你好"),
+            MessageContentType::Voice
+        );
+        assert_eq!(
+            resolve(MessageContentType::Text, "[链接]"),
+            MessageContentType::Link
+        );
+    }
+
+    #[test]
+    fn decodes_wechat_database_messages() {
+        let decode = |msg_type, content| {
+            let decoded = decode_wechat_message(msg_type, content);
+            (decoded.content_type, decoded.text)
+        };
+        assert_eq!(decode(1, "在吗"), (MessageContentType::Text, "在吗".to_string()));
+        assert_eq!(decode(3, "<?xml version=\"1.0\"?><msg><img length=\"1\"/></msg>").1, "[图片]");
+        assert_eq!(
+            decode(34, "wxid_a:\n<msg><voicemsg endflag=\"1\" voicelength=\"2120\" /></msg>"),
+            (MessageContentType::Voice, "[语音]3\"".to_string())
+        );
+        let link = "<msg><appmsg appid=\"\"><title>周报 &amp; 计划</title><des>摘要</des>\
+                    <type>5</type><url>https://example.com</url></appmsg></msg>";
+        assert_eq!(decode(49, link), (MessageContentType::Link, "[链接]周报 & 计划".to_string()));
+        let file = "<msg><appmsg><title><![CDATA[报价单.xlsx]]></title><type>6</type></appmsg></msg>";
+        assert_eq!(decode(49, file), (MessageContentType::File, "[文件]报价单.xlsx".to_string()));
+        let quote = "<msg><appmsg><title>好的</title><type>57</type>\
+                     <refermsg><type>1</type><title>x</title></refermsg></appmsg></msg>";
+        assert_eq!(decode(49, quote), (MessageContentType::Text, "好的".to_string()));
+        assert_eq!(decode(49, "").1, "[链接]");
+        let place = "<msg><location x=\"31.2\" label=\"上海市\" poiname=\"人民广场\" /></msg>";
+        assert_eq!(decode(48, place).1, "[位置]人民广场");
+        assert_eq!(decode(10002, "<sysmsg type=\"revokemsg\"/>").1, "[暂不支持的消息]");
+        assert_eq!(unescape_xml("a&lt;b&#x4F60;&#22909;&bogus"), "a<b你好&bogus");
+        assert_eq!(xml_text("<titles>x</titles><title/>", "title"), None);
+    }
+
+    #[test]
+    fn session_preview_is_one_short_line() {
+        assert_eq!(session_preview(1, "wxid_a:\n第一行\n第二行"), "第一行");
+        assert_eq!(session_preview(1, "  张三: 好的 "), "张三: 好的");
+        let file = "<msg><appmsg><title>报价单.xlsx</title><type>6</type></appmsg></msg>";
+        assert_eq!(session_preview(49, file), "[文件]报价单.xlsx");
+        assert_eq!(session_preview(1, &"长".repeat(100)).chars().count(), PREVIEW_GRAPHEMES);
+        assert_eq!(session_preview(1, ""), "");
+    }
+
+    #[test]
+    fn keeps_placeholders_out_of_context() {
+        assert_eq!(
+            context_text(MessageContentType::Text, " 在吗 ").as_deref(),
+            Some("在吗")
+        );
+        assert!(context_text(MessageContentType::Image, "[图片]").is_none());
+        assert!(context_text(MessageContentType::Sticker, "[动画表情]").is_none());
+        assert!(context_text(MessageContentType::Link, "[链接]").is_none());
+        let file = context_text(MessageContentType::File, "[文件]报价单.xlsx");
+        assert_eq!(file.as_deref(), Some("（对方发送了文件：报价单.xlsx）"));
+        assert!(image_context("  ").is_none());
+        assert_eq!(
+            image_context("订单号 20240518").as_deref(),
+            Some("（对方发送了图片，图中文字：订单号 20240518）")
+        );
+        assert!(voice_context("").is_none());
+        assert_eq!(
+            voice_context("明天下午三点开会").as_deref(),
+            Some("（对方发送了语音，内容：明天下午三点开会）")
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use notify::event::{CreateKind, EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Safety net for missed events while watching.
+pub const IDLE_POLL: Duration = Duration::from_secs(30);
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Wakes the automation poller when a WeChat database or its WAL is written,
+/// so the databases are read only after they actually change.
+pub struct DbWatcher {
+    watcher: Option<RecommendedWatcher>,
+    paths: Vec<PathBuf>,
+    tx: mpsc::UnboundedSender<()>,
+    rx: mpsc::UnboundedReceiver<()>,
+}
+
+impl Default for DbWatcher {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            watcher: None,
+            paths: Vec::new(),
+            tx,
+            rx,
+        }
+    }
+}
+
+impl DbWatcher {
+    /// Watches `paths` (re-subscribing only when they change) and reports
+    /// whether change events are flowing; `false` means poll on a timer.
+    pub fn sync(&mut self, paths: &[PathBuf]) -> bool {
+        if paths == self.paths.as_slice() {
+            return self.watcher.is_some();
+        }
+        self.paths = paths.to_vec();
+        self.watcher = None;
+        if paths.is_empty() {
+            return false;
+        }
+        match self.subscribe() {
+            Ok(watcher) => {
+                info!("开始监听微信数据库文件变化: {} 个", paths.len());
+                self.watcher = Some(watcher);
+                true
+            }
+            Err(err) => {
+                warn!("监听微信数据库文件失败，改为定时轮询: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Waits for the next change, folding a burst of writes into one wake-up.
+    pub async fn changed(&mut self) {
+        let _ = self.rx.recv().await;
+        tokio::time::sleep(DEBOUNCE).await;
+        while self.rx.try_recv().is_ok() {}
+    }
+
+    fn subscribe(&self) -> notify::Result<RecommendedWatcher> {
+        let paths = self.paths.clone();
+        let tx = self.tx.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if event.is_ok_and(|event| is_db_change(&event, &paths)) {
+                let _ = tx.send(());
+            }
+        })?;
+        // WAL files come and go, so watch the directories instead of the files.
+        let dirs: BTreeSet<&Path> = self.paths.iter().filter_map(|path| path.parent()).collect();
+        for dir in dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+        Ok(watcher)
+    }
+}
+
+/// Writes to a watched database or its `-wal`, or a watched database being
+/// created. Our own readers may create the WAL or touch `-shm`, which must not
+/// count as a change.
+pub fn is_db_change(event: &Event, watched: &[PathBuf]) -> bool {
+    let wal_too = match event.kind {
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => true,
+        EventKind::Create(CreateKind::File | CreateKind::Any) => false,
+        _ => return false,
+    };
+    event.paths.iter().any(|path| {
+        watched.iter().any(|db| {
+            path == db || (wal_too && path.as_os_str() == wal_path(db).as_os_str())
+        })
+    })
+}
+
+fn wal_path(db: &Path) -> PathBuf {
+    let mut wal = db.as_os_str().to_owned();
+    wal.push("-wal");
+    PathBuf::from(wal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, DataChange};
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn only_database_writes_count_as_changes() {
+        let watched = vec![PathBuf::from("/wx/Message/msg_0.db")];
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        let create = EventKind::Create(CreateKind::File);
+        assert!(is_db_change(&event(write, "/wx/Message/msg_0.db-wal"), &watched));
+        assert!(is_db_change(&event(write, "/wx/Message/msg_0.db"), &watched));
+        assert!(is_db_change(&event(create, "/wx/Message/msg_0.db"), &watched));
+        assert!(!is_db_change(&event(create, "/wx/Message/msg_0.db-wal"), &watched));
+        assert!(!is_db_change(&event(write, "/wx/Message/msg_0.db-shm"), &watched));
+        assert!(!is_db_change(&event(write, "/wx/Message/msg_1.db-wal"), &watched));
+        let read = EventKind::Access(AccessKind::Read);
+        assert!(!is_db_change(&event(read, "/wx/Message/msg_0.db"), &watched));
+    }
+
+    #[tokio::test]
+    async fn wakes_when_the_wal_is_written() {
+        let temp = tempfile::tempdir().unwrap();
+        let db = temp.path().join("MSG0.db");
+        std::fs::write(&db, b"").unwrap();
+        let mut watcher = DbWatcher::default();
+        assert!(!watcher.sync(&[]));
+        assert!(watcher.sync(std::slice::from_ref(&db)));
+
+        std::fs::write(temp.path().join("MSG0.db-shm"), b"x").unwrap();
+        std::fs::write(wal_path(&db), b"x").unwrap();
+        std::fs::write(wal_path(&db), b"xy").unwrap();
+        tokio::time::timeout(Duration::from_secs(5), watcher.changed())
+            .await
+            .expect("WAL 写入后应收到变化通知");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::types::{
+    ExperimentArm, ExperimentReport, ExperimentVariant, ExperimentVariantStats, PromptExperiment,
+    SuggestionAcceptance,
+};
+use anyhow::Result;
+
+const MAX_NAME_CHARS: usize = 32;
+const MAX_VARIANT_CHARS: usize = 500;
+const MIN_SETS_PER_VARIANT: u32 = 20;
+const CONFIDENCE_Z: f64 = 1.96;
+
+pub fn normalize_experiment(experiment: PromptExperiment) -> PromptExperiment {
+    PromptExperiment {
+        enabled: experiment.enabled,
+        name: experiment.name.trim().to_string(),
+        variant_a: experiment.variant_a.trim().to_string(),
+        variant_b: experiment.variant_b.trim().to_string(),
+    }
+}
+
+pub fn validate_experiment(experiment: &PromptExperiment) -> Result<()> {
+    if !experiment.enabled {
+        return Ok(());
+    }
+    if experiment.name.is_empty() || experiment.name.chars().count() > MAX_NAME_CHARS {
+        anyhow::bail!("实验名称必须为 1 到 {} 个字符", MAX_NAME_CHARS);
+    }
+    for variant in [&experiment.variant_a, &experiment.variant_b] {
+        if variant.is_empty() || variant.chars().count() > MAX_VARIANT_CHARS {
+            anyhow::bail!("实验提示词必须为 1 到 {} 字", MAX_VARIANT_CHARS);
+        }
+    }
+    if experiment.variant_a == experiment.variant_b {
+        anyhow::bail!("实验的两个提示词不能相同");
+    }
+    Ok(())
+}
+
+pub fn variant_for_turn(chat_id: &str, turn: u64) -> ExperimentVariant {
+    let offset: u64 = chat_id.bytes().map(u64::from).sum();
+    if (offset + turn).is_multiple_of(2) {
+        ExperimentVariant::A
+    } else {
+        ExperimentVariant::B
+    }
+}
+
+pub fn prompt_for(experiment: &PromptExperiment, variant: ExperimentVariant) -> &str {
+    match variant {
+        ExperimentVariant::A => &experiment.variant_a,
+        ExperimentVariant::B => &experiment.variant_b,
+    }
+}
+
+pub fn assign(
+    experiment: &PromptExperiment,
+    chat_id: &str,
+    turn: u64,
+) -> Option<(ExperimentArm, String)> {
+    if !experiment.enabled {
+        return None;
+    }
+    let variant = variant_for_turn(chat_id, turn);
+    let arm = ExperimentArm {
+        name: experiment.name.clone(),
+        variant,
+    };
+    Some((arm, prompt_for(experiment, variant).to_string()))
+}
+
+fn rate(acceptance: &SuggestionAcceptance) -> f64 {
+    if acceptance.suggestion_sets == 0 {
+        0.0
+    } else {
+        acceptance.accepted as f64 / acceptance.suggestion_sets as f64
+    }
+}
+
+fn z_score(a: &SuggestionAcceptance, b: &SuggestionAcceptance) -> f64 {
+    let total = (a.suggestion_sets + b.suggestion_sets) as f64;
+    let pooled = (a.accepted + b.accepted) as f64 / total;
+    let spread = (pooled
+        * (1.0 - pooled)
+        * (1.0 / a.suggestion_sets as f64 + 1.0 / b.suggestion_sets as f64))
+        .sqrt();
+    if spread == 0.0 {
+        0.0
+    } else {
+        (rate(a) - rate(b)) / spread
+    }
+}
+
+pub fn report(
+    experiment: &PromptExperiment,
+    a: SuggestionAcceptance,
+    b: SuggestionAcceptance,
+) -> ExperimentReport {
+    let enough =
+        a.suggestion_sets >= MIN_SETS_PER_VARIANT && b.suggestion_sets >= MIN_SETS_PER_VARIANT;
+    let z = if enough { z_score(&a, &b) } else { 0.0 };
+    let winner = if z >= CONFIDENCE_Z {
+        Some(ExperimentVariant::A)
+    } else if z <= -CONFIDENCE_Z {
+        Some(ExperimentVariant::B)
+    } else {
+        None
+    };
+    let summary = match winner {
+        Some(ExperimentVariant::A) => "提示词 A 的采纳率明显更高".to_string(),
+        Some(ExperimentVariant::B) => "提示词 B 的采纳率明显更高".to_string(),
+        None if !enough => format!(
+            "样本不足，每个提示词至少需要 {} 组建议",
+            MIN_SETS_PER_VARIANT
+        ),
+        None => "两个提示词的采纳率暂无明显差异".to_string(),
+    };
+    let stats =
+        |variant: ExperimentVariant, acceptance: SuggestionAcceptance| ExperimentVariantStats {
+            variant,
+            prompt: prompt_for(experiment, variant).to_string(),
+            acceptance_rate: rate(&acceptance),
+            acceptance,
+        };
+    ExperimentReport {
+        name: experiment.name.clone(),
+        enabled: experiment.enabled,
+        variants: vec![
+            stats(ExperimentVariant::A, a),
+            stats(ExperimentVariant::B, b),
+        ],
+        winner,
+        summary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment() -> PromptExperiment {
+        PromptExperiment {
+            enabled: true,
+            name: " 简短回复 ".to_string(),
+            variant_a: "回复尽量简短".to_string(),
+            variant_b: "回复先寒暄再回答".to_string(),
+        }
+    }
+
+    fn acceptance(suggestion_sets: u32, accepted: u32) -> SuggestionAcceptance {
+        SuggestionAcceptance {
+            suggestion_sets,
+            accepted,
+            observed: 0,
+        }
+    }
+
+    #[test]
+    fn validates_and_alternates_variants() {
+        let experiment = normalize_experiment(experiment());
+        assert_eq!(experiment.name, "简短回复");
+        assert!(validate_experiment(&experiment).is_ok());
+        let same = PromptExperiment {
+            variant_b: experiment.variant_a.clone(),
+            ..experiment.clone()
+        };
+        assert!(validate_experiment(&same).is_err());
+        let disabled = PromptExperiment {
+            enabled: false,
+            ..same
+        };
+        assert!(validate_experiment(&disabled).is_ok());
+
+        let first = variant_for_turn("张三", 0);
+        assert_ne!(variant_for_turn("张三", 1), first);
+        assert_eq!(variant_for_turn("张三", 2), first);
+        assert_eq!(
+            prompt_for(&experiment, ExperimentVariant::B),
+            "回复先寒暄再回答"
+        );
+        let (arm, prompt) = assign(&experiment, "张三", 1).unwrap();
+        assert_eq!(arm.name, "简短回复");
+        assert_eq!(prompt, prompt_for(&experiment, arm.variant));
+        assert!(assign(&disabled, "张三", 1).is_none());
+    }
+
+    #[test]
+    fn reports_winner_only_with_enough_evidence() {
+        let experiment = experiment();
+        let clear = report(&experiment, acceptance(60, 40), acceptance(60, 20));
+        assert_eq!(clear.winner, Some(ExperimentVariant::A));
+        assert!((clear.variants[0].acceptance_rate - 40.0 / 60.0).abs() < 1e-9);
+
+        let close = report(&experiment, acceptance(60, 31), acceptance(60, 29));
+        assert_eq!(close.winner, None);
+        assert_eq!(close.summary, "两个提示词的采纳率暂无明显差异");
+
+        let small = report(&experiment, acceptance(10, 10), acceptance(10, 0));
+        assert_eq!(small.winner, None);
+        assert!(small.summary.starts_with("样本不足"));
+    }
+}
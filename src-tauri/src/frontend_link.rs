@@ -0,0 +1,133 @@
+use crate::state::now_secs;
+use crate::types::{AutoReplySent, FrontendSync, SuggestionsUpdated};
+use crate::SharedState;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tracing::info;
+
+const HEARTBEAT_TIMEOUT_SECS: u64 = 45;
+const WATCHDOG_INTERVAL_SECS: u64 = 15;
+const MAX_MISSED_EVENTS: usize = 50;
+
+#[derive(Default)]
+pub struct FrontendLink {
+    last_heartbeat: Option<u64>,
+    detached_since: Option<u64>,
+    missed_suggestions: VecDeque<SuggestionsUpdated>,
+    missed_auto_replies: VecDeque<AutoReplySent>,
+}
+
+impl FrontendLink {
+    pub fn heartbeat(&mut self, now: u64) -> Option<u64> {
+        self.last_heartbeat = Some(now);
+        let since = self.detached_since.take()?;
+        Some(now.saturating_sub(since))
+    }
+
+    pub fn check(&mut self, now: u64) -> bool {
+        let last = *self.last_heartbeat.get_or_insert(now);
+        if self.detached_since.is_some() || now.saturating_sub(last) < HEARTBEAT_TIMEOUT_SECS {
+            return false;
+        }
+        self.detached_since = Some(last);
+        true
+    }
+
+    pub fn is_detached(&mut self, now: u64) -> bool {
+        self.check(now);
+        self.detached_since.is_some()
+    }
+
+    pub fn buffer_suggestions(&mut self, updated: &SuggestionsUpdated, now: u64) {
+        if self.is_detached(now) {
+            push_capped(&mut self.missed_suggestions, updated.clone());
+        }
+    }
+
+    pub fn buffer_auto_reply(&mut self, reply: &AutoReplySent, now: u64) {
+        if self.is_detached(now) {
+            push_capped(&mut self.missed_auto_replies, reply.clone());
+        }
+    }
+
+    pub fn take_missed(&mut self) -> (Vec<SuggestionsUpdated>, Vec<AutoReplySent>) {
+        (
+            self.missed_suggestions.drain(..).collect(),
+            self.missed_auto_replies.drain(..).collect(),
+        )
+    }
+}
+
+fn push_capped<T>(queue: &mut VecDeque<T>, item: T) {
+    if queue.len() >= MAX_MISSED_EVENTS {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
+
+pub async fn heartbeat(state: &SharedState) -> Option<FrontendSync> {
+    let mut guard = state.lock().await;
+    let detached_secs = guard.frontend.heartbeat(now_secs())?;
+    let (missed_suggestions, missed_auto_replies) = guard.frontend.take_missed();
+    info!(
+        "前端已重新连接: 离线 {}s，补发建议 {} 组，自动回复 {} 条",
+        detached_secs,
+        missed_suggestions.len(),
+        missed_auto_replies.len()
+    );
+    Some(FrontendSync {
+        status: guard.status.clone(),
+        latest_suggestions: guard.latest_suggestions.clone(),
+        missed_suggestions,
+        missed_auto_replies,
+        detached_secs,
+    })
+}
+
+pub fn spawn_frontend_watchdog(state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(WATCHDOG_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let mut guard = state.lock().await;
+            if guard.frontend.check(now_secs()) {
+                info!("前端失联，进入后台模式：建议继续保存，配额照常生效");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn updated(chat_id: &str) -> SuggestionsUpdated {
+        SuggestionsUpdated {
+            chat_id: chat_id.to_string(),
+            suggestions: Vec::new(),
+            reply_source: None,
+            warnings: Vec::new(),
+            best_pick: false,
+        }
+    }
+
+    #[test]
+    fn buffers_events_only_while_detached() {
+        let mut link = FrontendLink::default();
+        assert!(!link.check(100));
+        link.buffer_suggestions(&updated("张三"), 110);
+        assert!(link.check(100 + HEARTBEAT_TIMEOUT_SECS));
+        assert!(!link.check(200));
+        for index in 0..MAX_MISSED_EVENTS + 5 {
+            link.buffer_suggestions(&updated(&index.to_string()), 200);
+        }
+        assert_eq!(link.heartbeat(220), Some(120));
+        let (missed, replies) = link.take_missed();
+        assert_eq!(missed.len(), MAX_MISSED_EVENTS);
+        assert_eq!(missed[0].chat_id, "5");
+        assert!(replies.is_empty());
+        assert_eq!(link.heartbeat(230), None);
+        link.buffer_suggestions(&updated("李四"), 240);
+        assert!(link.take_missed().0.is_empty());
+    }
+}
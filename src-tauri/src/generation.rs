@@ -0,0 +1,227 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::oneshot;
+
+pub struct GenerationTicket {
+    pub seq: u64,
+    pub gate: Arc<GenerationGate>,
+    pub superseded: oneshot::Receiver<()>,
+}
+
+/// Caps concurrent generations. When every slot is taken, a freed slot goes
+/// to the oldest waiting high-priority chat before any normal one.
+pub struct GenerationGate {
+    capacity: usize,
+    state: Mutex<GateState>,
+}
+
+#[derive(Default)]
+struct GateState {
+    running: usize,
+    next_id: u64,
+    high: VecDeque<(u64, oneshot::Sender<()>)>,
+    normal: VecDeque<(u64, oneshot::Sender<()>)>,
+}
+
+/// Holds one slot until dropped.
+pub struct GenerationPermit {
+    gate: Arc<GenerationGate>,
+}
+
+/// A queued request; dropping it before the slot arrives leaves the queue,
+/// dropping it right after hands the slot on.
+struct Waiter {
+    gate: Arc<GenerationGate>,
+    id: u64,
+    granted: oneshot::Receiver<()>,
+    done: bool,
+}
+
+impl GenerationGate {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(GateState::default()),
+        }
+    }
+
+    pub async fn acquire(self: Arc<Self>, high_priority: bool) -> GenerationPermit {
+        let mut waiter = {
+            let mut state = self.lock();
+            if state.running < self.capacity {
+                state.running += 1;
+                drop(state);
+                return GenerationPermit { gate: self };
+            }
+            state.next_id += 1;
+            let id = state.next_id;
+            let (sender, granted) = oneshot::channel();
+            let queue = if high_priority {
+                &mut state.high
+            } else {
+                &mut state.normal
+            };
+            queue.push_back((id, sender));
+            Waiter {
+                gate: self.clone(),
+                id,
+                granted,
+                done: false,
+            }
+        };
+        // The gate never drops a queued sender without sending.
+        let _ = (&mut waiter.granted).await;
+        waiter.done = true;
+        GenerationPermit { gate: self }
+    }
+
+    #[cfg(test)]
+    fn running(&self) -> usize {
+        self.lock().running
+    }
+
+    fn lock(&self) -> MutexGuard<'_, GateState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl GateState {
+    /// Passes a freed slot to the next live waiter, high priority first.
+    fn release(&mut self) {
+        while let Some((_, sender)) = self.high.pop_front().or_else(|| self.normal.pop_front()) {
+            if sender.send(()).is_ok() {
+                return;
+            }
+        }
+        self.running = self.running.saturating_sub(1);
+    }
+}
+
+impl Drop for GenerationPermit {
+    fn drop(&mut self) {
+        self.gate.lock().release();
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let mut state = self.gate.lock();
+        let before = state.high.len() + state.normal.len();
+        state.high.retain(|(id, _)| *id != self.id);
+        state.normal.retain(|(id, _)| *id != self.id);
+        if state.high.len() + state.normal.len() == before {
+            // Already granted while the request was being cancelled.
+            state.release();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GenerationLimiter {
+    gate: Option<(u32, Arc<GenerationGate>)>,
+    running: HashMap<String, (u64, oneshot::Sender<()>)>,
+    next_seq: u64,
+}
+
+impl GenerationLimiter {
+    pub fn begin(&mut self, chat_id: &str, capacity: u32) -> GenerationTicket {
+        let capacity = capacity.max(1);
+        let gate = match self.gate.as_ref() {
+            Some((current, gate)) if *current == capacity => gate.clone(),
+            _ => {
+                let gate = Arc::new(GenerationGate::new(capacity as usize));
+                self.gate = Some((capacity, gate.clone()));
+                gate
+            }
+        };
+        self.next_seq += 1;
+        let (sender, superseded) = oneshot::channel();
+        if let Some((_, previous)) = self
+            .running
+            .insert(chat_id.to_string(), (self.next_seq, sender))
+        {
+            let _ = previous.send(());
+        }
+        GenerationTicket {
+            seq: self.next_seq,
+            gate,
+            superseded,
+        }
+    }
+
+    pub fn finish(&mut self, chat_id: &str, seq: u64) {
+        if self
+            .running
+            .get(chat_id)
+            .is_some_and(|(current, _)| *current == seq)
+        {
+            self.running.remove(chat_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newer_generation_supersedes_older_one_in_same_chat() {
+        let mut limiter = GenerationLimiter::default();
+        let mut first = limiter.begin("张三", 2);
+        let mut other = limiter.begin("李四", 2);
+        let mut second = limiter.begin("张三", 2);
+
+        assert_eq!(first.superseded.try_recv(), Ok(()));
+        assert!(other.superseded.try_recv().is_err());
+        assert!(second.superseded.try_recv().is_err());
+        assert!(Arc::ptr_eq(&first.gate, &second.gate));
+        assert_eq!(second.gate.capacity, 2);
+
+        limiter.finish("张三", first.seq);
+        assert!(limiter.running.contains_key("张三"));
+        limiter.finish("张三", second.seq);
+        assert!(!limiter.running.contains_key("张三"));
+    }
+
+    #[test]
+    fn rebuilds_gate_when_capacity_changes() {
+        let mut limiter = GenerationLimiter::default();
+        let before = limiter.begin("张三", 2).gate;
+        let after = limiter.begin("李四", 0).gate;
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert_eq!(after.capacity, 1);
+    }
+
+    #[tokio::test]
+    async fn high_priority_waiters_take_freed_slots_first() {
+        let gate = Arc::new(GenerationGate::new(1));
+        let running = gate.clone().acquire(false).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, high) in [("普通", false), ("优先", true)] {
+            let (gate, order) = (gate.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = gate.acquire(high).await;
+                order.lock().unwrap().push(name);
+            }));
+            tokio::task::yield_now().await;
+        }
+        let cancelled = tokio::spawn(gate.clone().acquire(true));
+        tokio::task::yield_now().await;
+        cancelled.abort();
+        let _ = cancelled.await;
+
+        assert_eq!(gate.running(), 1);
+        drop(running);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec!["优先", "普通"]);
+        assert_eq!(gate.running(), 0);
+    }
+}
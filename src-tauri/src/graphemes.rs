@@ -0,0 +1,81 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const MAX_WRITE_GRAPHEMES: usize = 2000;
+pub const MAX_MESSAGE_GRAPHEMES: usize = 10_000;
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub const KEYBOARD_CHUNK_UNITS: usize = 64;
+
+pub fn count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+pub fn truncate(text: &str, max: usize) -> &str {
+    match text.grapheme_indices(true).nth(max) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn chunks(text: &str, max_utf16_units: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut units = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        let len = grapheme.encode_utf16().count();
+        if units > 0 && units + len > max_utf16_units {
+            chunks.push(&text[start..index]);
+            start = index;
+            units = 0;
+        }
+        units += len;
+    }
+    if start < text.len() {
+        chunks.push(&text[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAMILY: &str = "👨‍👩‍👧‍👦";
+    const FLAG: &str = "🇨🇳";
+    const RARE_CJK: &str = "𠮷野家";
+    const ARABIC: &str = "مَرْحَبًا";
+
+    #[test]
+    fn counts_user_perceived_characters() {
+        assert_eq!(count(FAMILY), 1);
+        assert_eq!(count(FLAG), 1);
+        assert_eq!(count("👍🏽好的"), 3);
+        assert_eq!(count(RARE_CJK), 3);
+        assert_eq!(count(ARABIC), 5);
+        assert_eq!(FAMILY.len(), 25);
+    }
+
+    #[test]
+    fn truncates_on_grapheme_boundaries() {
+        let text = format!("{}{}好", FAMILY, FLAG);
+        assert_eq!(truncate(&text, 1), FAMILY);
+        assert_eq!(truncate(&text, 2), format!("{}{}", FAMILY, FLAG));
+        assert_eq!(truncate(&text, 5), text);
+        assert_eq!(truncate(ARABIC, 2), "مَرْ");
+    }
+
+    #[test]
+    fn chunks_never_split_surrogates_or_sequences() {
+        let text = format!("{}{}{}{}", RARE_CJK, FAMILY, FLAG, ARABIC);
+        for max in 1..=12 {
+            let parts = chunks(&text, max);
+            assert_eq!(parts.concat(), text);
+            for part in &parts {
+                assert!(count(part) == 1 || part.encode_utf16().count() <= max);
+            }
+        }
+        assert_eq!(chunks(FAMILY, 4), vec![FAMILY]);
+        assert_eq!(chunks("𠮷𠮷𠮷", 4), vec!["𠮷𠮷", "𠮷"]);
+        assert!(chunks("", 4).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::deepseek;
+use crate::pii;
+use crate::prompt_guard;
+use crate::state::ChatMessage;
+use crate::types::{Config, HandoverBrief};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+pub const MAX_HANDOVER_MESSAGES: u32 = 200;
+const HANDOVER_PROMPT: &str = "你是客服交接助手。请阅读聊天记录，为接手的同事整理交接摘要。\
+返回 JSON 对象，包含 contact（对方是谁、身份与背景）、open_issues（尚未解决的问题数组）、\
+promised_actions（我方已承诺但未完成的事项数组）、tone_guidance（与对方沟通的语气建议）。\
+只返回 JSON，不要添加解释。";
+
+#[derive(Debug, Default, Deserialize)]
+struct BriefContent {
+    #[serde(default)]
+    contact: String,
+    #[serde(default)]
+    open_issues: Vec<String>,
+    #[serde(default)]
+    promised_actions: Vec<String>,
+    #[serde(default)]
+    tone_guidance: String,
+}
+
+pub async fn generate(
+    config: &Config,
+    api_key: &str,
+    chat_id: &str,
+    messages: &[ChatMessage],
+    now: u64,
+) -> Result<(HandoverBrief, u64)> {
+    let mut redaction = pii::Redaction::default();
+    let prompt = build_prompt(chat_id, messages, now);
+    let prompt = if config.pii_redaction_enabled {
+        redaction.redact(&prompt)
+    } else {
+        prompt
+    };
+    let system_prompt = format!("{}\n{}", HANDOVER_PROMPT, prompt_guard::GUARD_PROMPT);
+    let (content, tokens) = deepseek::complete(config, api_key, &system_prompt, &prompt).await?;
+    let content = redaction.restore(&content);
+    let brief = parse_brief(chat_id, &content, messages.len() as u32, now)?;
+    Ok((brief, tokens))
+}
+
+fn build_prompt(chat_id: &str, messages: &[ChatMessage], now: u64) -> String {
+    let lines: Vec<String> = messages
+        .iter()
+        .map(|message| {
+            let age_days = now.saturating_sub(message.timestamp) / 86_400;
+            let speaker = if message.is_outgoing() {
+                ""
+            } else {
+                "对方："
+            };
+            let text = prompt_guard::sanitize(message.context_text().trim());
+            format!("[{} 天前] {}{}", age_days, speaker, text)
+        })
+        .collect();
+    format!(
+        "会话：{}\n聊天记录（按时间顺序，“我：”开头为我方发言）：\n{}",
+        chat_id,
+        prompt_guard::wrap_untrusted(&lines.join("\n"))
+    )
+}
+
+fn parse_brief(
+    chat_id: &str,
+    content: &str,
+    message_count: u32,
+    now: u64,
+) -> Result<HandoverBrief> {
+    let cleaned = content
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let parsed: BriefContent = serde_json::from_str(cleaned).context("交接摘要格式错误")?;
+    let clean_items = |items: Vec<String>| -> Vec<String> {
+        items
+            .into_iter()
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    };
+    let mut brief = HandoverBrief {
+        chat_id: chat_id.to_string(),
+        contact: parsed.contact.trim().to_string(),
+        open_issues: clean_items(parsed.open_issues),
+        promised_actions: clean_items(parsed.promised_actions),
+        tone_guidance: parsed.tone_guidance.trim().to_string(),
+        markdown: String::new(),
+        message_count,
+        generated_at: now,
+        saved_to: None,
+    };
+    brief.markdown = render_markdown(&brief);
+    Ok(brief)
+}
+
+fn render_markdown(brief: &HandoverBrief) -> String {
+    let section = |title: &str, body: String| format!("## {}\n\n{}\n", title, body);
+    let text_or_none = |text: &str| {
+        if text.is_empty() {
+            "（无）".to_string()
+        } else {
+            text.to_string()
+        }
+    };
+    let list_or_none = |items: &[String]| {
+        if items.is_empty() {
+            "（无）".to_string()
+        } else {
+            items
+                .iter()
+                .map(|item| format!("- {}", item))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+    [
+        format!(
+            "# 交接摘要：{}\n\n基于最近 {} 条消息生成。\n",
+            brief.chat_id, brief.message_count
+        ),
+        section("联系人", text_or_none(&brief.contact)),
+        section("待解决问题", list_or_none(&brief.open_issues)),
+        section("已承诺事项", list_or_none(&brief.promised_actions)),
+        section("语气建议", text_or_none(&brief.tone_guidance)),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::MessageDirection;
+
+    fn message(text: &str, timestamp: u64) -> ChatMessage {
+        ChatMessage {
+            text: text.to_string(),
+            timestamp,
+            msg_id: None,
+            direction: MessageDirection::Incoming,
+        }
+    }
+
+    #[test]
+    fn builds_prompt_with_speakers_and_age() {
+        let messages = vec![
+            message("订单什么时候发货？", 0),
+            message("我：明天上午安排发货", 86_400 * 2),
+        ];
+        let prompt = build_prompt("王总", &messages, 86_400 * 3);
+        assert!(prompt.contains("会话：王总"));
+        assert!(prompt.contains("[3 天前] 对方：订单什么时候发货？"));
+        assert!(prompt.contains("[1 天前] 我：明天上午安排发货"));
+    }
+
+    #[test]
+    fn parses_brief_and_renders_markdown() {
+        let content =
+            "```json\n{\"contact\": \"王总，老客户\", \"open_issues\": [\"发货时间\", \" \"], \
+            \"promised_actions\": [\"明天上午发货\"], \"tone_guidance\": \"\"}\n```";
+        let brief = parse_brief("王总", content, 2, 100).unwrap();
+        assert_eq!(brief.open_issues, vec!["发货时间"]);
+        assert_eq!(brief.promised_actions, vec!["明天上午发货"]);
+        assert!(brief.markdown.starts_with("# 交接摘要：王总"));
+        assert!(brief.markdown.contains("## 待解决问题\n\n- 发货时间\n"));
+        assert!(brief.markdown.contains("## 语气建议\n\n（无）\n"));
+        assert!(parse_brief("王总", "抱歉，我无法完成", 2, 100).is_err());
+    }
+}
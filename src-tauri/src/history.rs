@@ -0,0 +1,823 @@
+use crate::quota::DailyUsage;
+use crate::state::{ChatMessage, MessageDirection};
+use crate::types::{
+    ExperimentArm, ExperimentVariant, MessageSearchHit, Suggestion, SuggestionAcceptance,
+    SuggestionRecord,
+};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const HISTORY_FILE: &str = "history.db";
+const SECS_PER_DAY: u64 = 86_400;
+const MAX_SEARCH_RESULTS: u32 = 50;
+const MIN_FTS_QUERY_CHARS: usize = 3;
+const OBSERVE_WINDOW_SECS: u64 = 30 * 60;
+const MIN_OBSERVED_SIMILARITY: f64 = 0.8;
+
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("打开历史记录失败: {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chat_id TEXT NOT NULL,
+                text TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                msg_id TEXT,
+                direction INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_messages_chat_time ON messages (chat_id, timestamp);
+            CREATE TABLE IF NOT EXISTS suggestion_sets (
+                id TEXT PRIMARY KEY,
+                chat_id TEXT NOT NULL,
+                context_hash TEXT NOT NULL,
+                model TEXT NOT NULL,
+                fallback INTEGER NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                suggestions TEXT NOT NULL,
+                written_suggestion_id TEXT,
+                written_at INTEGER,
+                created_at INTEGER NOT NULL,
+                written_observed INTEGER NOT NULL DEFAULT 0,
+                prompt_version INTEGER NOT NULL DEFAULT 0,
+                experiment TEXT,
+                experiment_variant TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_suggestion_sets_chat_time
+                ON suggestion_sets (chat_id, created_at);
+            CREATE TABLE IF NOT EXISTS daily_usage (
+                day INTEGER PRIMARY KEY,
+                requests INTEGER NOT NULL,
+                tokens INTEGER NOT NULL
+            );",
+        )
+        .context("初始化历史记录失败")?;
+        let has_observed: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info('suggestion_sets')
+                WHERE name = 'written_observed')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_observed {
+            conn.execute(
+                "ALTER TABLE suggestion_sets
+                ADD COLUMN written_observed INTEGER NOT NULL DEFAULT 0",
+                [],
+            )
+            .context("升级建议记录失败")?;
+        }
+        let has_prompt_version: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info('suggestion_sets')
+                WHERE name = 'prompt_version')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_prompt_version {
+            conn.execute(
+                "ALTER TABLE suggestion_sets
+                ADD COLUMN prompt_version INTEGER NOT NULL DEFAULT 0",
+                [],
+            )
+            .context("升级建议记录失败")?;
+        }
+        let has_experiment: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info('suggestion_sets')
+                WHERE name = 'experiment')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_experiment {
+            conn.execute_batch(
+                "ALTER TABLE suggestion_sets ADD COLUMN experiment TEXT;
+                ALTER TABLE suggestion_sets ADD COLUMN experiment_variant TEXT;",
+            )
+            .context("升级建议记录失败")?;
+        }
+        let has_direction: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info('messages')
+                WHERE name = 'direction')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_direction {
+            conn.execute(
+                "ALTER TABLE messages ADD COLUMN direction INTEGER NOT NULL DEFAULT 0",
+                [],
+            )
+            .context("升级历史记录失败")?;
+        }
+        let has_fts: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'messages_fts')",
+            [],
+            |row| row.get(0),
+        )?;
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+                text, content='messages', content_rowid='id', tokenize='trigram'
+            );
+            CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+                INSERT INTO messages_fts (rowid, text) VALUES (new.id, new.text);
+            END;
+            CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+                INSERT INTO messages_fts (messages_fts, rowid, text) VALUES ('delete', old.id, old.text);
+            END;",
+        )
+        .context("初始化全文索引失败")?;
+        if !has_fts {
+            conn.execute(
+                "INSERT INTO messages_fts (messages_fts) VALUES ('rebuild')",
+                [],
+            )
+            .context("重建全文索引失败")?;
+        }
+        Ok(Self { conn })
+    }
+
+    pub fn append(&self, chat_id: &str, message: &ChatMessage) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO messages (chat_id, text, timestamp, msg_id, direction)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    chat_id,
+                    message.text,
+                    message.timestamp as i64,
+                    message.msg_id,
+                    message.direction.as_i64()
+                ],
+            )
+            .context("写入历史记录失败")?;
+        Ok(())
+    }
+
+    pub fn rename_chat(&self, alias: &str, canonical: &str) -> Result<()> {
+        for sql in [
+            "UPDATE messages SET chat_id = ?2 WHERE chat_id = ?1",
+            "UPDATE suggestion_sets SET chat_id = ?2 WHERE chat_id = ?1",
+        ] {
+            self.conn
+                .execute(sql, params![alias, canonical])
+                .context("合并历史记录失败")?;
+        }
+        Ok(())
+    }
+
+    pub fn prune(&self, retention_days: u32, now: u64) -> Result<usize> {
+        if retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now.saturating_sub(retention_days as u64 * SECS_PER_DAY) as i64;
+        self.conn
+            .execute(
+                "DELETE FROM suggestion_sets WHERE created_at < ?1",
+                params![cutoff],
+            )
+            .context("清理建议记录失败")?;
+        self.conn
+            .execute("DELETE FROM messages WHERE timestamp < ?1", params![cutoff])
+            .context("清理历史记录失败")
+    }
+
+    pub fn load_recent(&self, max_per_chat: u32) -> Result<HashMap<String, Vec<ChatMessage>>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT chat_id, text, timestamp, msg_id, direction FROM (
+                SELECT chat_id, text, timestamp, msg_id, direction, id,
+                    ROW_NUMBER() OVER (PARTITION BY chat_id ORDER BY timestamp DESC, id DESC) AS rank
+                FROM messages
+            ) WHERE rank <= ?1 ORDER BY chat_id, timestamp, id",
+        )?;
+        let rows = stmt.query_map(params![max_per_chat], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                ChatMessage {
+                    text: row.get(1)?,
+                    timestamp: row.get::<_, i64>(2)?.max(0) as u64,
+                    msg_id: row.get(3)?,
+                    direction: MessageDirection::from_i64(row.get(4)?),
+                },
+            ))
+        })?;
+        let mut conversations: HashMap<String, Vec<ChatMessage>> = HashMap::new();
+        for row in rows {
+            let (chat_id, message) = row.context("读取历史记录失败")?;
+            conversations.entry(chat_id).or_default().push(message);
+        }
+        Ok(conversations)
+    }
+
+    pub fn messages_until(
+        &self,
+        chat_id: &str,
+        until: u64,
+        limit: u32,
+    ) -> Result<Vec<ChatMessage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT text, timestamp, msg_id, direction FROM messages
+            WHERE chat_id = ?1 AND timestamp <= ?2
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![chat_id, until as i64, limit], |row| {
+            Ok(ChatMessage {
+                text: row.get(0)?,
+                timestamp: row.get::<_, i64>(1)?.max(0) as u64,
+                msg_id: row.get(2)?,
+                direction: MessageDirection::from_i64(row.get(3)?),
+            })
+        })?;
+        let mut messages = rows
+            .map(|row| row.context("读取历史记录失败"))
+            .collect::<Result<Vec<_>>>()?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    pub fn outgoing_texts(&self, chat_id: &str, limit: u32) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT text FROM messages
+            WHERE chat_id = ?1 AND direction = 1
+            ORDER BY timestamp DESC, id DESC
+            LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![chat_id, limit], |row| row.get(0))?;
+        rows.map(|row| row.context("读取历史记录失败")).collect()
+    }
+
+    pub fn search(&self, query: &str, chat_id: Option<&str>) -> Result<Vec<MessageSearchHit>> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let map_hit = |row: &rusqlite::Row<'_>| {
+            Ok(MessageSearchHit {
+                chat_id: row.get(0)?,
+                text: row.get(1)?,
+                timestamp: row.get::<_, i64>(2)?.max(0) as u64,
+                msg_id: row.get(3)?,
+            })
+        };
+        let hits = if query.chars().count() >= MIN_FTS_QUERY_CHARS {
+            let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+            let mut stmt = self.conn.prepare(
+                "SELECT m.chat_id, m.text, m.timestamp, m.msg_id
+                FROM messages_fts JOIN messages m ON m.id = messages_fts.rowid
+                WHERE messages_fts MATCH ?1 AND (?2 IS NULL OR m.chat_id = ?2)
+                ORDER BY bm25(messages_fts), m.timestamp DESC
+                LIMIT ?3",
+            )?;
+            let rows = stmt.query_map(params![phrase, chat_id, MAX_SEARCH_RESULTS], map_hit)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        } else {
+            let pattern = format!("%{}%", escape_like(query));
+            let mut stmt = self.conn.prepare(
+                "SELECT chat_id, text, timestamp, msg_id FROM messages
+                WHERE text LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR chat_id = ?2)
+                ORDER BY timestamp DESC
+                LIMIT ?3",
+            )?;
+            let rows = stmt.query_map(params![pattern, chat_id, MAX_SEARCH_RESULTS], map_hit)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        };
+        hits.context("搜索历史消息失败")
+    }
+
+    pub fn append_suggestions(&self, record: &SuggestionRecord) -> Result<()> {
+        let suggestions = serde_json::to_string(&record.suggestions).context("序列化建议失败")?;
+        self.conn
+            .execute(
+                "INSERT INTO suggestion_sets (
+                    id, chat_id, context_hash, model, fallback, latency_ms, suggestions,
+                    written_suggestion_id, written_at, created_at, prompt_version,
+                    experiment, experiment_variant
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    record.id,
+                    record.chat_id,
+                    record.context_hash,
+                    record.model,
+                    record.fallback,
+                    record.latency_ms as i64,
+                    suggestions,
+                    record.written_suggestion_id,
+                    record.written_at.map(|value| value as i64),
+                    record.created_at as i64,
+                    record.prompt_version,
+                    record.experiment.as_ref().map(|arm| arm.name.as_str()),
+                    record.experiment.as_ref().map(|arm| arm.variant.as_str())
+                ],
+            )
+            .context("保存建议记录失败")?;
+        Ok(())
+    }
+
+    pub fn mark_written(&self, chat_id: &str, text: &str, now: u64) -> Result<Option<String>> {
+        let latest = self
+            .suggestion_history(Some(chat_id), 1)?
+            .into_iter()
+            .next();
+        let Some(record) = latest else {
+            return Ok(None);
+        };
+        let text = text.trim();
+        let Some(suggestion) = record
+            .suggestions
+            .iter()
+            .find(|suggestion| suggestion.text.trim() == text)
+        else {
+            return Ok(None);
+        };
+        self.conn
+            .execute(
+                "UPDATE suggestion_sets
+                SET written_suggestion_id = ?2, written_at = ?3, written_observed = 0
+                WHERE id = ?1",
+                params![record.id, suggestion.id, now as i64],
+            )
+            .context("更新建议记录失败")?;
+        Ok(Some(suggestion.id.clone()))
+    }
+
+    pub fn mark_observed(&self, chat_id: &str, text: &str, now: u64) -> Result<Option<String>> {
+        let latest = self
+            .suggestion_history(Some(chat_id), 1)?
+            .into_iter()
+            .next();
+        let Some(record) = latest else {
+            return Ok(None);
+        };
+        if record.written_suggestion_id.is_some()
+            || now.saturating_sub(record.created_at) > OBSERVE_WINDOW_SECS
+        {
+            return Ok(None);
+        }
+        let best = record
+            .suggestions
+            .iter()
+            .map(|suggestion| (similarity(&suggestion.text, text), suggestion))
+            .filter(|(score, _)| *score >= MIN_OBSERVED_SIMILARITY)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        let Some((_, suggestion)) = best else {
+            return Ok(None);
+        };
+        self.conn
+            .execute(
+                "UPDATE suggestion_sets
+                SET written_suggestion_id = ?2, written_at = ?3, written_observed = 1
+                WHERE id = ?1 AND written_suggestion_id IS NULL",
+                params![record.id, suggestion.id, now as i64],
+            )
+            .context("更新建议记录失败")?;
+        Ok(Some(suggestion.id.clone()))
+    }
+
+    pub fn acceptance(&self, chat_id: Option<&str>, since: u64) -> Result<SuggestionAcceptance> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*), COUNT(written_suggestion_id),
+                    COALESCE(SUM(written_suggestion_id IS NOT NULL AND written_observed = 1), 0)
+                FROM suggestion_sets
+                WHERE (?1 IS NULL OR chat_id = ?1) AND created_at >= ?2",
+                params![chat_id, since as i64],
+                |row| {
+                    Ok(SuggestionAcceptance {
+                        suggestion_sets: row.get(0)?,
+                        accepted: row.get(1)?,
+                        observed: row.get(2)?,
+                    })
+                },
+            )
+            .context("统计建议采纳失败")
+    }
+
+    pub fn experiment_acceptance(
+        &self,
+        name: &str,
+        variant: ExperimentVariant,
+        since: u64,
+    ) -> Result<SuggestionAcceptance> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*), COUNT(written_suggestion_id),
+                    COALESCE(SUM(written_suggestion_id IS NOT NULL AND written_observed = 1), 0)
+                FROM suggestion_sets
+                WHERE experiment = ?1 AND experiment_variant = ?2 AND created_at >= ?3",
+                params![name, variant.as_str(), since as i64],
+                |row| {
+                    Ok(SuggestionAcceptance {
+                        suggestion_sets: row.get(0)?,
+                        accepted: row.get(1)?,
+                        observed: row.get(2)?,
+                    })
+                },
+            )
+            .context("统计实验采纳失败")
+    }
+
+    pub fn suggestion_history(
+        &self,
+        chat_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<SuggestionRecord>> {
+        self.query_suggestion_sets(
+            "WHERE ?1 IS NULL OR chat_id = ?1
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ?2",
+            params![chat_id, limit],
+        )
+    }
+
+    pub fn suggestion_sets_between(
+        &self,
+        chat_id: &str,
+        since: u64,
+        until: u64,
+    ) -> Result<Vec<SuggestionRecord>> {
+        self.query_suggestion_sets(
+            "WHERE chat_id = ?1 AND created_at >= ?2 AND created_at <= ?3
+            ORDER BY created_at, rowid",
+            params![chat_id, since as i64, until as i64],
+        )
+    }
+
+    fn query_suggestion_sets(
+        &self,
+        filter: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SuggestionRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, chat_id, context_hash, model, fallback, latency_ms, suggestions,
+                written_suggestion_id, written_at, created_at, prompt_version,
+                experiment, experiment_variant
+            FROM suggestion_sets
+            {}",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                SuggestionRecord {
+                    id: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    context_hash: row.get(2)?,
+                    model: row.get(3)?,
+                    fallback: row.get(4)?,
+                    latency_ms: row.get::<_, i64>(5)?.max(0) as u64,
+                    suggestions: Vec::new(),
+                    written_suggestion_id: row.get(7)?,
+                    written_at: row
+                        .get::<_, Option<i64>>(8)?
+                        .map(|value| value.max(0) as u64),
+                    created_at: row.get::<_, i64>(9)?.max(0) as u64,
+                    prompt_version: row.get(10)?,
+                    experiment: experiment_arm(row.get(11)?, row.get(12)?),
+                },
+                row.get::<_, String>(6)?,
+            ))
+        })?;
+        let mut records = Vec::new();
+        for row in rows {
+            let (mut record, suggestions) = row.context("读取建议记录失败")?;
+            record.suggestions = serde_json::from_str::<Vec<Suggestion>>(&suggestions)
+                .context("解析建议记录失败")?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    pub fn save_usage(&self, usage: &DailyUsage) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO daily_usage (day, requests, tokens) VALUES (?1, ?2, ?3)",
+                params![usage.day as i64, usage.requests, usage.tokens as i64],
+            )
+            .context("保存用量失败")?;
+        Ok(())
+    }
+
+    pub fn load_usage(&self, day: u64) -> Result<DailyUsage> {
+        let row = self.conn.query_row(
+            "SELECT requests, tokens FROM daily_usage WHERE day = ?1",
+            params![day as i64],
+            |row| Ok((row.get::<_, u32>(0)?, row.get::<_, i64>(1)?)),
+        );
+        match row {
+            Ok((requests, tokens)) => Ok(DailyUsage {
+                day,
+                requests,
+                tokens: tokens.max(0) as u64,
+            }),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DailyUsage {
+                day,
+                ..DailyUsage::default()
+            }),
+            Err(err) => Err(err).context("读取用量失败"),
+        }
+    }
+}
+
+fn experiment_arm(name: Option<String>, variant: Option<String>) -> Option<ExperimentArm> {
+    Some(ExperimentArm {
+        name: name?,
+        variant: ExperimentVariant::parse(&variant?)?,
+    })
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub fn open_history(app: &AppHandle) -> Result<HistoryStore> {
+    HistoryStore::open(&history_path(app)?)
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_data_dir().context("无法获取数据目录")?;
+    fs::create_dir_all(&dir).context("创建数据目录失败")?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+fn similarity(left: &str, right: &str) -> f64 {
+    let normalize = |text: &str| -> Vec<char> {
+        text.chars()
+            .filter(|ch| ch.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let (left, right) = (normalize(left), normalize(right));
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+    if left == right {
+        return 1.0;
+    }
+    if left.len() < 2 || right.len() < 2 {
+        return 0.0;
+    }
+    let mut bigrams: HashMap<(char, char), u32> = HashMap::new();
+    for pair in left.windows(2) {
+        *bigrams.entry((pair[0], pair[1])).or_default() += 1;
+    }
+    let mut shared = 0;
+    for pair in right.windows(2) {
+        if let Some(count) = bigrams
+            .get_mut(&(pair[0], pair[1]))
+            .filter(|count| **count > 0)
+        {
+            *count -= 1;
+            shared += 1;
+        }
+    }
+    2.0 * shared as f64 / (left.len() + right.len() - 2) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str, timestamp: u64) -> ChatMessage {
+        ChatMessage {
+            text: text.to_string(),
+            timestamp,
+            msg_id: None,
+            direction: MessageDirection::Incoming,
+        }
+    }
+
+    #[test]
+    fn loads_latest_messages_per_chat_in_order() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        for (chat_id, text, timestamp) in [
+            ("a", "一", 1),
+            ("a", "二", 2),
+            ("b", "你好", 5),
+            ("a", "三", 3),
+        ] {
+            store.append(chat_id, &message(text, timestamp)).unwrap();
+        }
+        let conversations = store.load_recent(2).unwrap();
+        let texts: Vec<&str> = conversations["a"].iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, vec!["二", "三"]);
+        assert_eq!(conversations["b"].len(), 1);
+
+        let until: Vec<_> = store
+            .messages_until("a", 2, 5)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.text)
+            .collect();
+        assert_eq!(until, vec!["一", "二"]);
+    }
+
+    #[test]
+    fn prunes_messages_past_retention() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let now = 10 * SECS_PER_DAY;
+        store.append("a", &message("旧消息", SECS_PER_DAY)).unwrap();
+        store.append("a", &message("新消息", now)).unwrap();
+        assert_eq!(store.prune(0, now).unwrap(), 0);
+        assert_eq!(store.prune(7, now).unwrap(), 1);
+        assert_eq!(store.load_recent(10).unwrap()["a"].len(), 1);
+    }
+
+    #[test]
+    fn searches_messages_by_phrase_and_chat() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        store
+            .append("张三", &message("下周一之前把合同发给你", 1))
+            .unwrap();
+        store.append("李四", &message("合同已经签好了", 2)).unwrap();
+        store.append("李四", &message("100% 确认", 3)).unwrap();
+
+        let hits = store.search("把合同发", None).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chat_id, "张三");
+        assert_eq!(store.search("合同", None).unwrap().len(), 2);
+        assert_eq!(store.search("合同", Some("李四")).unwrap().len(), 1);
+        assert_eq!(store.search("0%", None).unwrap().len(), 1);
+        assert!(store.search("  ", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn indexes_existing_rows_and_prunes_from_search() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        store.append("张三", &message("周五交付第一版", 1)).unwrap();
+        store.conn.execute_batch("DROP TABLE messages_fts").unwrap();
+        let store = HistoryStore::init(store.conn).unwrap();
+        assert_eq!(store.search("交付第一版", None).unwrap().len(), 1);
+        store.prune(1, 10 * SECS_PER_DAY).unwrap();
+        assert!(store.search("交付第一版", None).unwrap().is_empty());
+    }
+
+    fn suggestion_set(id: &str, chat_id: &str, created_at: u64) -> SuggestionRecord {
+        SuggestionRecord {
+            id: id.to_string(),
+            chat_id: chat_id.to_string(),
+            context_hash: "abc".to_string(),
+            model: "deepseek-chat".to_string(),
+            fallback: false,
+            latency_ms: 820,
+            suggestions: vec![Suggestion {
+                id: format!("{}-1", id),
+                style: crate::types::SuggestionStyle::neutral(),
+                text: "收到".to_string(),
+            }],
+            written_suggestion_id: None,
+            written_at: None,
+            created_at,
+            prompt_version: 3,
+            experiment: None,
+        }
+    }
+
+    #[test]
+    fn records_suggestion_sets_and_written_choice() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        for (id, chat_id, created_at) in
+            [("s1", "张三", 10), ("s2", "张三", 20), ("s3", "李四", 30)]
+        {
+            let record = suggestion_set(id, chat_id, created_at);
+            store.append_suggestions(&record).unwrap();
+        }
+
+        assert_eq!(
+            store.mark_written("张三", " 收到 ", 25).unwrap(),
+            Some("s2-1".to_string())
+        );
+        assert_eq!(store.mark_written("张三", "不在建议里", 26).unwrap(), None);
+        assert_eq!(store.mark_written("王五", "收到", 27).unwrap(), None);
+
+        let records = store.suggestion_history(Some("张三"), 10).unwrap();
+        let ids: Vec<_> = records.iter().map(|record| record.id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "s1"]);
+        assert_eq!(records[0].written_suggestion_id.as_deref(), Some("s2-1"));
+        assert_eq!(records[0].written_at, Some(25));
+        assert_eq!(records[0].suggestions[0].text, "收到");
+        assert_eq!(records[0].prompt_version, 3);
+        assert!(records[1].written_suggestion_id.is_none());
+        assert_eq!(store.suggestion_history(None, 2).unwrap()[0].id, "s3");
+        let between = store.suggestion_sets_between("张三", 15, 30).unwrap();
+        assert_eq!(between.len(), 1);
+        assert_eq!(between[0].id, "s2");
+
+        store.rename_chat("张三", "wxid_a").unwrap();
+        let renamed = store.suggestion_history(Some("wxid_a"), 10).unwrap();
+        assert_eq!(renamed.len(), 2);
+        store.prune(1, SECS_PER_DAY + 15).unwrap();
+        assert_eq!(store.suggestion_history(None, 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn observed_outgoing_messages_mark_close_suggestions() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let suggested = "好的，明天上午十点前发您报价单。";
+        let pasted = "好的 明天上午十点前发你报价单";
+        let mut record = suggestion_set("s1", "张三", 100);
+        record.suggestions[0].text = suggested.to_string();
+        store.append_suggestions(&record).unwrap();
+
+        assert_eq!(
+            store.mark_observed("张三", "不用了谢谢", 120).unwrap(),
+            None
+        );
+        assert_eq!(store.mark_observed("李四", suggested, 120).unwrap(), None);
+        let expired = 100 + OBSERVE_WINDOW_SECS + 1;
+        assert_eq!(store.mark_observed("张三", pasted, expired).unwrap(), None);
+        assert_eq!(
+            store.mark_observed("张三", pasted, 130).unwrap(),
+            Some("s1-1".to_string())
+        );
+        assert_eq!(store.mark_observed("张三", suggested, 140).unwrap(), None);
+
+        for (id, created_at) in [("s2", 200), ("s3", 300)] {
+            let record = suggestion_set(id, "张三", created_at);
+            store.append_suggestions(&record).unwrap();
+        }
+        store.mark_written("张三", "收到", 310).unwrap();
+        let acceptance = store.acceptance(Some("张三"), 0).unwrap();
+        assert_eq!(acceptance.suggestion_sets, 3);
+        assert_eq!((acceptance.accepted, acceptance.observed), (2, 1));
+        assert_eq!(store.acceptance(None, 250).unwrap().observed, 0);
+    }
+
+    #[test]
+    fn counts_acceptance_per_experiment_variant() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let arms = [
+            ("s1", "简短", ExperimentVariant::A, 10),
+            ("s2", "简短", ExperimentVariant::B, 20),
+            ("s3", "简短", ExperimentVariant::A, 30),
+            ("s4", "旧实验", ExperimentVariant::A, 40),
+        ];
+        for (id, name, variant, created_at) in arms {
+            let mut record = suggestion_set(id, "张三", created_at);
+            record.experiment = Some(ExperimentArm {
+                name: name.to_string(),
+                variant,
+            });
+            store.append_suggestions(&record).unwrap();
+            if id == "s3" {
+                store.mark_written("张三", "收到", 35).unwrap();
+            }
+        }
+        store
+            .append_suggestions(&suggestion_set("s5", "张三", 50))
+            .unwrap();
+
+        let a = store
+            .experiment_acceptance("简短", ExperimentVariant::A, 0)
+            .unwrap();
+        assert_eq!((a.suggestion_sets, a.accepted), (2, 1));
+        let b = store
+            .experiment_acceptance("简短", ExperimentVariant::B, 0)
+            .unwrap();
+        assert_eq!((b.suggestion_sets, b.accepted), (1, 0));
+        let records = store.suggestion_history(Some("张三"), 10).unwrap();
+        assert!(records[0].experiment.is_none());
+        assert_eq!(
+            records[1].experiment.as_ref().map(|arm| arm.variant),
+            Some(ExperimentVariant::A)
+        );
+    }
+
+    #[test]
+    fn persists_daily_usage() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        assert_eq!(store.load_usage(3).unwrap().requests, 0);
+        let mut usage = DailyUsage::default();
+        usage.record(3, 120);
+        usage.record(3, 80);
+        store.save_usage(&usage).unwrap();
+        assert_eq!(store.load_usage(3).unwrap(), usage);
+        assert_eq!(store.load_usage(4).unwrap().tokens, 0);
+    }
+
+    #[test]
+    fn renames_alias_to_canonical_chat() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        store.append("张三", &message("在吗", 1)).unwrap();
+        store.rename_chat("张三", "wxid_a").unwrap();
+        let conversations = store.load_recent(10).unwrap();
+        assert!(!conversations.contains_key("张三"));
+        assert_eq!(conversations["wxid_a"][0].text, "在吗");
+    }
+}
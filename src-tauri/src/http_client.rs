@@ -0,0 +1,263 @@
+use crate::types::Config;
+use anyhow::{Context, Result};
+use reqwest::{Certificate, Client, ClientBuilder};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::info;
+
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+/// Idle connections are dropped quickly in low power mode instead of being
+/// kept warm for the next request.
+const LOW_POWER_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+const PROXY_ENV_KEYS: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsSettings {
+    pub ca_bundle_path: Option<String>,
+    pub pin: bool,
+}
+
+impl TlsSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ca_bundle_path: Some(config.ca_bundle_path.trim().to_string())
+                .filter(|path| !path.is_empty()),
+            pin: config.pin_ca_bundle,
+        }
+    }
+
+    pub fn load(&self) -> Result<Vec<Certificate>> {
+        match self.ca_bundle_path.as_deref() {
+            Some(path) => load_ca_bundle(path),
+            None if self.pin => anyhow::bail!("仅信任自定义证书时必须指定 CA 证书文件"),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Pinning only covers the DeepSeek host; other services keep the built-in
+    /// roots and just add the custom CA.
+    pub fn unpinned(&self) -> Self {
+        Self {
+            ca_bundle_path: self.ca_bundle_path.clone(),
+            pin: false,
+        }
+    }
+
+    pub fn apply(&self, builder: ClientBuilder) -> Result<ClientBuilder> {
+        let builder = self
+            .load()?
+            .into_iter()
+            .fold(builder, |builder, cert| builder.add_root_certificate(cert));
+        Ok(builder.tls_built_in_root_certs(!self.pin))
+    }
+}
+
+pub fn load_ca_bundle(path: &str) -> Result<Vec<Certificate>> {
+    let pem = fs::read(path).with_context(|| format!("读取 CA 证书失败: {}", path))?;
+    let certs = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("解析 CA 证书失败: {}", path))?;
+    if certs.is_empty() {
+        anyhow::bail!("CA 证书文件中没有证书: {}", path);
+    }
+    Ok(certs)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientKey {
+    base_url: String,
+    proxy: Option<String>,
+    tls: TlsSettings,
+    keep_alive: bool,
+}
+
+impl ClientKey {
+    pub fn new(base_url: &str, proxy: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            proxy: proxy
+                .map(|proxy| proxy.trim().to_string())
+                .filter(|proxy| !proxy.is_empty()),
+            tls: TlsSettings::default(),
+            keep_alive: true,
+        }
+    }
+
+    pub fn with_tls(mut self, tls: TlsSettings) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn from_env(base_url: &str) -> Self {
+        let proxy = PROXY_ENV_KEYS
+            .iter()
+            .find_map(|key| std::env::var(key).ok().filter(|value| !value.is_empty()));
+        Self::new(base_url, proxy).with_keep_alive(keep_alive())
+    }
+
+    pub fn uses_proxy(&self) -> bool {
+        self.proxy.is_some()
+    }
+}
+
+#[derive(Default)]
+struct ClientSlot {
+    key: Option<ClientKey>,
+    client: Option<Client>,
+    builds: u32,
+}
+
+impl ClientSlot {
+    fn get_or_build(&mut self, key: ClientKey) -> Result<Client> {
+        if self.key.as_ref() == Some(&key) {
+            if let Some(client) = self.client.as_ref() {
+                return Ok(client.clone());
+            }
+        }
+        let client = build_client(&key)?;
+        info!(
+            "创建共享 HTTP 客户端: base_url={}, proxy={}, ca_bundle={}, pin={}, keep_alive={}",
+            key.base_url,
+            key.proxy.is_some(),
+            key.tls.ca_bundle_path.is_some(),
+            key.tls.pin,
+            key.keep_alive
+        );
+        self.key = Some(key);
+        self.client = Some(client.clone());
+        self.builds += 1;
+        Ok(client)
+    }
+}
+
+static SHARED: OnceLock<Mutex<ClientSlot>> = OnceLock::new();
+static SERVICES: OnceLock<Mutex<ClientSlot>> = OnceLock::new();
+static KEEP_ALIVE: AtomicBool = AtomicBool::new(true);
+
+/// Turned off in low power mode; the shared clients are rebuilt on their next
+/// use.
+pub fn set_keep_alive(enabled: bool) {
+    KEEP_ALIVE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn keep_alive() -> bool {
+    KEEP_ALIVE.load(Ordering::Relaxed)
+}
+
+/// The client for the DeepSeek `base_url`, the only host the CA pin applies to.
+pub fn shared_client(config: &Config) -> Result<Client> {
+    let key = ClientKey::from_env(&config.base_url).with_tls(TlsSettings::from_config(config));
+    get_or_build(&SHARED, key)
+}
+
+/// The client for other services such as transcription: the custom CA is
+/// trusted too, but never pinned.
+pub fn service_client(config: &Config, base_url: &str) -> Result<Client> {
+    let key = ClientKey::from_env(base_url).with_tls(TlsSettings::from_config(config).unpinned());
+    get_or_build(&SERVICES, key)
+}
+
+fn get_or_build(slot: &OnceLock<Mutex<ClientSlot>>, key: ClientKey) -> Result<Client> {
+    let slot = slot.get_or_init(|| Mutex::new(ClientSlot::default()));
+    let mut guard = slot.lock().unwrap_or_else(|err| err.into_inner());
+    guard.get_or_build(key)
+}
+
+/// Builder shared by the pooled clients and the connection timing probe, so
+/// both see the same certificates.
+pub fn client_builder(tls: &TlsSettings) -> Result<ClientBuilder> {
+    let builder = Client::builder()
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(TCP_KEEPALIVE);
+    tls.apply(builder)
+}
+
+fn build_client(key: &ClientKey) -> Result<Client> {
+    let builder = client_builder(&key.tls)?;
+    let builder = if key.keep_alive {
+        builder
+    } else {
+        builder
+            .pool_idle_timeout(LOW_POWER_POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(None)
+    };
+    builder.build().context("创建 HTTP 客户端失败")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn reuses_client_until_base_url_or_proxy_changes() {
+        let mut slot = ClientSlot::default();
+        slot.get_or_build(ClientKey::new("https://api.deepseek.com/", None))
+            .unwrap();
+        slot.get_or_build(ClientKey::new(
+            "https://api.deepseek.com",
+            Some(" ".to_string()),
+        ))
+        .unwrap();
+        assert_eq!(slot.builds, 1);
+
+        slot.get_or_build(ClientKey::new("https://proxy.example.com", None))
+            .unwrap();
+        assert_eq!(slot.builds, 2);
+        slot.get_or_build(ClientKey::new(
+            "https://proxy.example.com",
+            Some("http://127.0.0.1:7890".to_string()),
+        ))
+        .unwrap();
+        assert_eq!(slot.builds, 3);
+        slot.get_or_build(ClientKey::new(
+            "https://proxy.example.com",
+            Some("http://127.0.0.1:7890".to_string()),
+        ))
+        .unwrap();
+        assert_eq!(slot.builds, 3);
+        slot.get_or_build(
+            ClientKey::new(
+                "https://proxy.example.com",
+                Some("http://127.0.0.1:7890".to_string()),
+            )
+            .with_keep_alive(false),
+        )
+        .unwrap();
+        assert_eq!(slot.builds, 4);
+    }
+
+    #[test]
+    fn rejects_invalid_ca_bundles() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"not a certificate").unwrap();
+        let path = file.path().to_string_lossy().to_string();
+        let err = load_ca_bundle(&path).unwrap_err();
+        assert!(err.to_string().contains("CA 证书文件中没有证书"));
+        assert!(load_ca_bundle("/nonexistent/ca.pem").is_err());
+
+        let pinned = TlsSettings {
+            ca_bundle_path: None,
+            pin: true,
+        };
+        assert!(pinned.load().is_err());
+        assert!(!pinned.unpinned().pin);
+        assert!(pinned.unpinned().load().unwrap().is_empty());
+        assert!(TlsSettings::default().load().unwrap().is_empty());
+
+        let mut slot = ClientSlot::default();
+        let key = ClientKey::new("https://api.deepseek.com", None).with_tls(TlsSettings {
+            ca_bundle_path: Some(path),
+            pin: false,
+        });
+        assert!(slot.get_or_build(key).is_err());
+        assert_eq!(slot.builds, 0);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::integration_tokens::{Denied, IntegrationTokenStore};
+use crate::state::now_secs;
+use crate::types::{api_err, api_ok, IntegrationScope, ReplyMode};
+use crate::SharedState;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::time::{timeout, Duration};
+use tracing::{info, warn};
+
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const READ_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HttpRequest {
+    method: String,
+    path: String,
+    bearer: Option<String>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct WriteRequest {
+    chat_id: String,
+    text: String,
+    #[serde(default)]
+    send: bool,
+}
+
+/// Everything the integration endpoint exposes. Each route names the token
+/// scope it needs, so no route can be added without deciding that.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    Status,
+    Suggestions,
+    Write(WriteRequest),
+}
+
+impl Route {
+    fn scope(&self) -> IntegrationScope {
+        match self {
+            Route::Status | Route::Suggestions => IntegrationScope::Read,
+            Route::Write(_) => IntegrationScope::Write,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+pub async fn read_request<R: AsyncRead + Unpin>(reader: &mut R) -> Result<HttpRequest, HttpError> {
+    let mut buffer = Vec::new();
+    let head_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(HttpError::new(431, "请求头过大"));
+        }
+        let mut chunk = [0u8; 1024];
+        let read = reader
+            .read(&mut chunk)
+            .await
+            .map_err(|err| HttpError::new(400, format!("读取请求失败: {}", err)))?;
+        if read == 0 {
+            return Err(HttpError::new(400, "请求不完整"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let head = std::str::from_utf8(&buffer[..head_end])
+        .map_err(|_| HttpError::new(400, "请求头不是 UTF-8"))?;
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(HttpError::new(400, "请求行格式错误"));
+    };
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        ..HttpRequest::default()
+    };
+    let mut content_length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse::<usize>()
+                .map_err(|_| HttpError::new(400, "Content-Length 格式错误"))?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            request.bearer = value
+                .strip_prefix("Bearer ")
+                .map(|token| token.trim().to_string());
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err(HttpError::new(413, "请求内容过大"));
+    }
+    let mut body = buffer.split_off(head_end + 4);
+    body.truncate(content_length);
+    if body.len() < content_length {
+        let start = body.len();
+        body.resize(content_length, 0);
+        reader
+            .read_exact(&mut body[start..])
+            .await
+            .map_err(|_| HttpError::new(400, "请求内容不完整"))?;
+    }
+    request.body = body;
+    Ok(request)
+}
+
+pub fn route(request: &HttpRequest) -> Result<Route, HttpError> {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/status") => Ok(Route::Status),
+        ("GET", "/v1/suggestions") => Ok(Route::Suggestions),
+        ("POST", "/v1/write") => serde_json::from_slice(&request.body)
+            .map(Route::Write)
+            .map_err(|err| HttpError::new(400, format!("请求内容格式错误: {}", err))),
+        _ => Err(HttpError::new(404, "接口不存在")),
+    }
+}
+
+/// Resolves the route and checks that the bearer token grants its scope.
+pub fn authorize(
+    store: &mut IntegrationTokenStore,
+    request: &HttpRequest,
+    now: u64,
+) -> Result<Route, HttpError> {
+    let Some(secret) = request.bearer.as_deref() else {
+        return Err(HttpError::new(401, "缺少集成令牌"));
+    };
+    let route = route(request)?;
+    match store.authorize(secret, route.scope(), now) {
+        Ok(_) => Ok(route),
+        Err(denied @ Denied::Unknown) => Err(HttpError::new(401, denied.message())),
+        Err(denied @ Denied::MissingScope(_)) => Err(HttpError::new(403, denied.message())),
+    }
+}
+
+async fn dispatch(app: &AppHandle, state: &SharedState, route: Route) -> Vec<u8> {
+    match route {
+        Route::Status => {
+            let status = state.lock().await.status.clone();
+            to_json(&api_ok(status))
+        }
+        Route::Suggestions => {
+            let latest = state.lock().await.latest_suggestions.clone();
+            to_json(&api_ok(latest))
+        }
+        Route::Write(write) => {
+            let res = crate::write_suggestion_inner(
+                app,
+                state.clone(),
+                write.chat_id,
+                write.text,
+                ReplyMode::default(),
+                write.send,
+            )
+            .await;
+            to_json(&res)
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).unwrap_or_default()
+}
+
+async fn respond<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: u16,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason,
+        body.len()
+    );
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+    app: &AppHandle,
+    state: &SharedState,
+    stream: &mut S,
+) -> std::io::Result<()> {
+    let request = timeout(Duration::from_secs(READ_TIMEOUT_SECS), read_request(stream))
+        .await
+        .unwrap_or_else(|_| Err(HttpError::new(408, "读取请求超时")));
+    let route = match request {
+        Ok(request) => {
+            let mut guard = state.lock().await;
+            authorize(&mut guard.integration_tokens, &request, now_secs())
+        }
+        Err(err) => Err(err),
+    };
+    match route {
+        Ok(route) => {
+            let body = dispatch(app, state, route).await;
+            respond(stream, 200, &body).await
+        }
+        Err(err) => {
+            warn!("集成接口拒绝请求: {} {}", err.status, err.message);
+            respond(stream, err.status, &to_json(&api_err::<()>(err.message))).await
+        }
+    }
+}
+
+/// Serves the integration endpoint on 127.0.0.1 when `integration_port` is set.
+/// Every request needs an integration token with the scope of its route.
+pub fn spawn_integration_server(app: AppHandle, state: SharedState, port: u16) {
+    if port == 0 {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!("启动集成接口失败: 端口 {}, {}", port, err);
+                return;
+            }
+        };
+        info!("集成接口已启动: http://127.0.0.1:{}", port);
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    warn!("接受集成接口连接失败: {}", err);
+                    continue;
+                }
+            };
+            let app = app.clone();
+            let state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = handle_connection(&app, &state, &mut stream).await {
+                    warn!("集成接口响应失败: {}", err);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse(raw: &str) -> Result<HttpRequest, HttpError> {
+        read_request(&mut raw.as_bytes()).await
+    }
+
+    #[tokio::test]
+    async fn reads_headers_and_body() {
+        let body = r#"{"chat_id":"张三","text":"好的"}"#;
+        let raw = format!(
+            "POST /v1/write HTTP/1.1\r\nHost: 127.0.0.1\r\nauthorization: Bearer wr_abc \r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = parse(&raw).await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/write");
+        assert_eq!(request.bearer.as_deref(), Some("wr_abc"));
+        assert_eq!(
+            route(&request).unwrap(),
+            Route::Write(WriteRequest {
+                chat_id: "张三".to_string(),
+                text: "好的".to_string(),
+                send: false,
+            })
+        );
+
+        let truncated = "POST /v1/write HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+        assert_eq!(parse(truncated).await.unwrap_err().status, 400);
+        let oversized = format!(
+            "POST /v1/write HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        assert_eq!(parse(&oversized).await.unwrap_err().status, 413);
+    }
+
+    #[tokio::test]
+    async fn enforces_token_scopes_per_route() {
+        let mut store = IntegrationTokenStore::default();
+        let reader = store
+            .create("看板", vec![IntegrationScope::Read], 100)
+            .unwrap();
+        let writer = store
+            .create("机器人", vec![IntegrationScope::Write], 100)
+            .unwrap();
+        let request = |method: &str, path: &str, bearer: Option<&str>| HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            bearer: bearer.map(str::to_string),
+            body: r#"{"chat_id":"张三","text":"好的","send":true}"#.as_bytes().to_vec(),
+        };
+
+        let anonymous = request("GET", "/v1/status", None);
+        assert_eq!(
+            authorize(&mut store, &anonymous, 200).unwrap_err().status,
+            401
+        );
+        let unknown = request("GET", "/v1/status", Some("wr_unknown"));
+        assert_eq!(
+            authorize(&mut store, &unknown, 200).unwrap_err().status,
+            401
+        );
+
+        let read = request("GET", "/v1/suggestions", Some(&reader.secret));
+        assert_eq!(
+            authorize(&mut store, &read, 200).unwrap(),
+            Route::Suggestions
+        );
+        let write = request("POST", "/v1/write", Some(&reader.secret));
+        assert_eq!(authorize(&mut store, &write, 200).unwrap_err().status, 403);
+
+        let write = request("POST", "/v1/write", Some(&writer.secret));
+        assert!(matches!(
+            authorize(&mut store, &write, 200).unwrap(),
+            Route::Write(WriteRequest { send: true, .. })
+        ));
+        let missing = request("GET", "/v1/history", Some(&writer.secret));
+        assert_eq!(
+            authorize(&mut store, &missing, 200).unwrap_err().status,
+            404
+        );
+
+        assert!(store.revoke(&writer.token.id));
+        assert_eq!(authorize(&mut store, &write, 300).unwrap_err().status, 401);
+    }
+}
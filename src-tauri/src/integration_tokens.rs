@@ -0,0 +1,207 @@
+use crate::secret::ApiKeyManager;
+use crate::types::{IntegrationScope, IntegrationToken, IntegrationTokenCreated};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use uuid::Uuid;
+
+pub const MAX_INTEGRATION_TOKENS: usize = 20;
+const MAX_NAME_CHARS: usize = 32;
+const TOKEN_PREFIX: &str = "wr_";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: IntegrationToken,
+    hash: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntegrationTokenStore {
+    #[serde(default)]
+    tokens: Vec<StoredToken>,
+}
+
+impl IntegrationTokenStore {
+    pub fn list(&self) -> Vec<IntegrationToken> {
+        self.tokens.iter().map(|item| item.token.clone()).collect()
+    }
+
+    pub fn create(
+        &mut self,
+        name: &str,
+        scopes: Vec<IntegrationScope>,
+        now: u64,
+    ) -> Result<IntegrationTokenCreated> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("令牌名称不能为空");
+        }
+        if name.chars().count() > MAX_NAME_CHARS {
+            anyhow::bail!("令牌名称过长");
+        }
+        let mut normalized = Vec::new();
+        for scope in scopes {
+            if !normalized.contains(&scope) {
+                normalized.push(scope);
+            }
+        }
+        if normalized.is_empty() {
+            anyhow::bail!("至少需要一个权限范围");
+        }
+        if self.tokens.len() >= MAX_INTEGRATION_TOKENS {
+            anyhow::bail!("集成令牌数量已达上限");
+        }
+        let secret = format!(
+            "{}{}{}",
+            TOKEN_PREFIX,
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let token = IntegrationToken {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            scopes: normalized,
+            created_at: now,
+            last_used_at: None,
+        };
+        self.tokens.push(StoredToken {
+            token: token.clone(),
+            hash: hash_secret(&secret),
+        });
+        Ok(IntegrationTokenCreated { token, secret })
+    }
+
+    pub fn revoke(&mut self, id: &str) -> bool {
+        let before = self.tokens.len();
+        self.tokens.retain(|item| item.token.id != id);
+        self.tokens.len() != before
+    }
+
+    pub fn authorize(
+        &mut self,
+        secret: &str,
+        required: IntegrationScope,
+        now: u64,
+    ) -> Result<IntegrationToken, Denied> {
+        let hash = hash_secret(secret.trim());
+        let Some(item) = self.tokens.iter_mut().find(|item| item.hash == hash) else {
+            return Err(Denied::Unknown);
+        };
+        if !grants(&item.token.scopes, required) {
+            return Err(Denied::MissingScope(required));
+        }
+        item.token.last_used_at = Some(now);
+        Ok(item.token.clone())
+    }
+}
+
+/// Why `authorize` refused a token; the integration endpoint maps these to 401 and 403.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    Unknown,
+    MissingScope(IntegrationScope),
+}
+
+impl Denied {
+    pub fn message(self) -> String {
+        match self {
+            Denied::Unknown => "集成令牌无效或已撤销".to_string(),
+            Denied::MissingScope(scope) => format!("集成令牌没有{}权限", scope_label(scope)),
+        }
+    }
+}
+
+fn grants(scopes: &[IntegrationScope], required: IntegrationScope) -> bool {
+    match required {
+        IntegrationScope::Read => !scopes.is_empty(),
+        IntegrationScope::Write => scopes.contains(&IntegrationScope::Write),
+    }
+}
+
+fn scope_label(scope: IntegrationScope) -> &'static str {
+    match scope {
+        IntegrationScope::Read => "读取",
+        IntegrationScope::Write => "写入",
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn load_integration_tokens() -> Result<IntegrationTokenStore> {
+    let Some(contents) = ApiKeyManager::get_integration_tokens()? else {
+        return Ok(IntegrationTokenStore::default());
+    };
+    match serde_json::from_str::<IntegrationTokenStore>(&contents) {
+        Ok(store) => Ok(store),
+        Err(err) => {
+            warn!("解析集成令牌失败，忽略已保存内容: {}", err);
+            Ok(IntegrationTokenStore::default())
+        }
+    }
+}
+
+pub fn save_integration_tokens(store: &IntegrationTokenStore) -> Result<()> {
+    let contents = serde_json::to_string(store).context("序列化集成令牌失败")?;
+    ApiKeyManager::set_integration_tokens(&contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_only_hashes_and_enforces_scopes() {
+        let mut store = IntegrationTokenStore::default();
+        let reader = store
+            .create(" 看板 ", vec![IntegrationScope::Read], 100)
+            .unwrap();
+        let writer = store
+            .create(
+                "机器人",
+                vec![IntegrationScope::Write, IntegrationScope::Write],
+                100,
+            )
+            .unwrap();
+        assert_eq!(reader.token.name, "看板");
+        assert_eq!(writer.token.scopes, vec![IntegrationScope::Write]);
+        assert!(store.create("空", Vec::new(), 100).is_err());
+
+        let json = serde_json::to_string(&store).unwrap();
+        assert!(!json.contains(&reader.secret));
+        let mut store: IntegrationTokenStore = serde_json::from_str(&json).unwrap();
+
+        let used = store
+            .authorize(&reader.secret, IntegrationScope::Read, 200)
+            .unwrap();
+        assert_eq!(used.last_used_at, Some(200));
+        assert_eq!(
+            store
+                .authorize(&reader.secret, IntegrationScope::Write, 200)
+                .unwrap_err(),
+            Denied::MissingScope(IntegrationScope::Write)
+        );
+        assert!(store
+            .authorize(&writer.secret, IntegrationScope::Read, 200)
+            .is_ok());
+        assert_eq!(
+            store
+                .authorize("wr_unknown", IntegrationScope::Read, 200)
+                .unwrap_err(),
+            Denied::Unknown
+        );
+
+        assert!(store.revoke(&writer.token.id));
+        assert!(!store.revoke(&writer.token.id));
+        assert!(store
+            .authorize(&writer.secret, IntegrationScope::Write, 300)
+            .is_err());
+        assert_eq!(store.list().len(), 1);
+    }
+}
@@ -1,11 +1,64 @@
 use anyhow::{Context, Result};
-use crate::types::{ChatSummary, ListenTarget};
+use crate::graphemes;
+use crate::types::{ChatSummary, ListenTarget, MessageContentType, ReplySource, SuggestedAction};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashSet;
 use std::time::{SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 const MAX_RAW_MESSAGE_LEN: usize = 100_000;
+const MAX_BINARY_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const BINARY_FRAME_MIN_LEN: usize = 16 * 1024;
+pub const CAPABILITY_MSGPACK_ZSTD: &str = "frame.msgpack_zstd";
+pub const CAPABILITY_MESSAGE_BATCH: &str = "message.batch";
+pub const SUPPORTED_PROTOCOL_VERSIONS: [ProtocolVersion; 2] =
+    [ProtocolVersion::V1_1, ProtocolVersion::V1_0];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    #[default]
+    V1_0,
+    V1_1,
+}
+
+impl ProtocolVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V1_0 => "1.0",
+            Self::V1_1 => "1.1",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        SUPPORTED_PROTOCOL_VERSIONS
+            .into_iter()
+            .find(|version| version.as_str() == value.trim())
+    }
+
+    // 1.1 起 message.new / message.sent / message.batch 的时间戳为毫秒，统一换算为秒
+    fn adapt_payload(self, message_type: &str, payload: &mut Value) {
+        if self == Self::V1_0 {
+            return;
+        }
+        match message_type {
+            "message.new" | "message.sent" => millis_to_secs(payload),
+            "message.batch" => {
+                if let Some(messages) = payload.get_mut("messages").and_then(Value::as_array_mut) {
+                    messages.iter_mut().for_each(millis_to_secs);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn millis_to_secs(payload: &mut Value) {
+    if let Some(millis) = payload.get("timestamp").and_then(Value::as_u64) {
+        payload["timestamp"] = Value::from(millis / 1000);
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct IpcEnvelope {
@@ -25,6 +78,32 @@ pub struct AgentReadyPayload {
     pub supports_clipboard_restore: bool,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Negotiation {
+    pub version: ProtocolVersion,
+    pub binary_frames: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostHelloPayload {
+    pub supported_versions: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentHelloPayload {
+    pub version: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentProfilePayload {
+    #[serde(default)]
+    pub nickname: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AgentStatusPayload {
     pub state: String,
@@ -37,6 +116,10 @@ pub struct AgentErrorPayload {
     pub code: String,
     pub message: String,
     pub recoverable: bool,
+    #[serde(default)]
+    pub suggested_action: Option<SuggestedAction>,
+    #[serde(default)]
+    pub chat_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,6 +132,28 @@ pub struct MessageNewPayload {
     pub timestamp: u64,
     #[serde(default)]
     pub msg_id: Option<String>,
+    #[serde(default)]
+    pub content_type: MessageContentType,
+    #[serde(default)]
+    pub image_path: Option<String>,
+    #[serde(default)]
+    pub audio_path: Option<String>,
+    #[serde(default)]
+    pub account_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageBatchPayload {
+    pub messages: Vec<MessageNewPayload>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageSentPayload {
+    pub chat_id: String,
+    #[serde(default)]
+    pub chat_title: String,
+    pub text: String,
+    pub timestamp: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -59,6 +164,12 @@ pub struct InputWritePayload {
     pub mode: Option<String>,
     #[serde(default)]
     pub restore_clipboard: Option<bool>,
+    #[serde(default)]
+    pub quote: Option<ReplySource>,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub send: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -67,6 +178,8 @@ pub struct ListenControlPayload {
     pub poll_interval_ms: Option<u64>,
     #[serde(default)]
     pub targets: Option<Vec<ListenTarget>>,
+    #[serde(default)]
+    pub download_images: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -90,9 +203,10 @@ pub struct InputResultPayload {
     pub ok: bool,
     #[serde(default)]
     pub error: String,
+    #[serde(default)]
+    pub request_id: Option<String>,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EventAckPayload {
     pub ack_id: String,
@@ -124,36 +238,111 @@ impl IpcEnvelope {
         });
         Self::new("event.ack", payload)
     }
+
+    pub fn host_hello(binary_frames: bool) -> Self {
+        let mut capabilities = vec![CAPABILITY_MESSAGE_BATCH.to_string()];
+        if binary_frames {
+            capabilities.push(CAPABILITY_MSGPACK_ZSTD.to_string());
+        }
+        let payload = HostHelloPayload {
+            supported_versions: SUPPORTED_PROTOCOL_VERSIONS
+                .iter()
+                .map(|version| version.as_str().to_string())
+                .collect(),
+            capabilities,
+        };
+        Self::new(
+            "host.hello",
+            serde_json::to_value(payload).unwrap_or_default(),
+        )
+    }
+}
+
+pub fn negotiate(payload: Value, offered_binary: bool) -> Result<Negotiation> {
+    let hello: AgentHelloPayload =
+        serde_json::from_value(payload).context("agent.hello 格式错误")?;
+    let version = ProtocolVersion::parse(&hello.version)
+        .with_context(|| format!("Agent 选择了不支持的协议版本: {}", hello.version))?;
+    let binary_frames = offered_binary
+        && hello
+            .capabilities
+            .iter()
+            .any(|capability| capability == CAPABILITY_MSGPACK_ZSTD);
+    Ok(Negotiation {
+        version,
+        binary_frames,
+    })
+}
+
+pub fn encode_message(envelope: &IpcEnvelope, binary_frames: bool) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(envelope).context("编码 IPC 消息失败")?;
+    if !binary_frames || json.len() < BINARY_FRAME_MIN_LEN {
+        return Ok(json);
+    }
+    let packed = rmp_serde::to_vec_named(envelope).context("编码 IPC 消息失败")?;
+    zstd::encode_all(packed.as_slice(), 0).context("压缩 IPC 消息失败")
+}
+
+pub fn parse_message(raw: &[u8]) -> Result<IpcEnvelope> {
+    if raw.starts_with(&ZSTD_MAGIC) {
+        return parse_binary_envelope(raw);
+    }
+    let line = std::str::from_utf8(raw).context("Agent 消息不是有效的 UTF-8")?;
+    parse_envelope(line.trim())
+}
+
+fn parse_binary_envelope(raw: &[u8]) -> Result<IpcEnvelope> {
+    let packed =
+        zstd::bulk::decompress(raw, MAX_BINARY_MESSAGE_LEN).context("解压 Agent 消息失败")?;
+    let mut envelope: IpcEnvelope = rmp_serde::from_slice(&packed).context("Agent 消息格式错误")?;
+    let version = validate_envelope(&envelope)?;
+    version.adapt_payload(&envelope.r#type, &mut envelope.payload);
+    Ok(envelope)
 }
 
 pub fn parse_envelope(line: &str) -> Result<IpcEnvelope> {
     if line.len() > MAX_RAW_MESSAGE_LEN {
         anyhow::bail!("Agent 消息过大");
     }
-    let envelope: IpcEnvelope =
+    let mut envelope: IpcEnvelope =
         serde_json::from_str(line).context("Agent 消息格式错误")?;
-    validate_envelope(&envelope)?;
+    let version = validate_envelope(&envelope)?;
+    version.adapt_payload(&envelope.r#type, &mut envelope.payload);
     Ok(envelope)
 }
 
-fn validate_envelope(envelope: &IpcEnvelope) -> Result<()> {
-    if envelope.version != "1.0" {
-        anyhow::bail!("IPC 协议版本不匹配");
-    }
+fn validate_envelope(envelope: &IpcEnvelope) -> Result<ProtocolVersion> {
+    let Some(version) = ProtocolVersion::parse(&envelope.version) else {
+        anyhow::bail!("IPC 协议版本不支持: {}", envelope.version);
+    };
     if envelope.id.trim().is_empty() || envelope.r#type.trim().is_empty() {
         anyhow::bail!("IPC 消息缺少必要字段");
     }
-    Ok(())
+    Ok(version)
+}
+
+// 按时间排序并去掉同一会话内重复的消息，排序稳定以保留 Agent 的原始顺序
+pub fn order_message_batch(mut messages: Vec<MessageNewPayload>) -> Vec<MessageNewPayload> {
+    messages.sort_by_key(|message| message.timestamp);
+    let mut seen = HashSet::new();
+    messages.retain(|message| {
+        let key = match message.msg_id.as_deref().filter(|id| !id.is_empty()) {
+            Some(msg_id) => format!("id:{}", msg_id),
+            None => format!("{}:{}", message.timestamp, message.text),
+        };
+        seen.insert((message.chat_id.clone(), key))
+    });
+    messages
 }
 
 pub fn validate_message_new(payload: &MessageNewPayload) -> Result<()> {
     if payload.chat_id.trim().is_empty() {
         anyhow::bail!("chat_id 不能为空");
     }
-    if payload.text.trim().is_empty() {
+    if payload.text.trim().is_empty() && payload.content_type == MessageContentType::Text {
         anyhow::bail!("消息内容为空");
     }
-    if payload.text.len() > 10_000 {
+    if graphemes::count(&payload.text) > graphemes::MAX_MESSAGE_GRAPHEMES {
         anyhow::bail!("消息内容过长");
     }
     Ok(())
@@ -162,7 +351,7 @@ pub fn validate_message_new(payload: &MessageNewPayload) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::ChatKind;
+    use crate::types::{ChatKind, Politeness, TargetPriority};
 
     #[test]
     fn serialize_message_new() {
@@ -184,8 +373,111 @@ mod tests {
             text: "".to_string(),
             timestamp: 1,
             msg_id: None,
+            content_type: MessageContentType::Text,
+            image_path: None,
+            audio_path: None,
+            account_id: String::new(),
         };
         assert!(validate_message_new(&payload).is_err());
+        let image = MessageNewPayload {
+            content_type: MessageContentType::Image,
+            ..payload
+        };
+        assert!(validate_message_new(&image).is_ok());
+    }
+
+    #[test]
+    fn negotiates_version_and_adapts_payloads() {
+        let hello = IpcEnvelope::host_hello(false);
+        assert_eq!(
+            hello.payload["supported_versions"],
+            serde_json::json!(["1.1", "1.0"])
+        );
+        assert_eq!(
+            negotiate(serde_json::json!({"version": "1.1"}), true).unwrap(),
+            Negotiation {
+                version: ProtocolVersion::V1_1,
+                binary_frames: false,
+            }
+        );
+        assert!(negotiate(serde_json::json!({"version": "2.0"}), true).is_err());
+
+        let line = r#"{"version":"1.1","type":"message.sent","id":"m1","timestamp":1700000000,"payload":{"chat_id":"c1","text":"好的","timestamp":1700000000123}}"#;
+        let envelope = parse_envelope(line).unwrap();
+        assert_eq!(envelope.payload["timestamp"], 1_700_000_000);
+        let legacy = line.replace("\"1.1\"", "\"1.0\"");
+        let envelope = parse_envelope(&legacy).unwrap();
+        assert_eq!(envelope.payload["timestamp"], 1_700_000_000_123u64);
+        assert!(parse_envelope(&line.replace("\"1.1\"", "\"2.0\"")).is_err());
+
+        let batch = r#"{"version":"1.1","type":"message.batch","id":"b1","timestamp":1700000000,"payload":{"messages":[{"chat_id":"c1","chat_title":"c1","is_group":false,"sender_name":"c1","text":"在吗","timestamp":1700000000123}]}}"#;
+        let envelope = parse_envelope(batch).unwrap();
+        let payload: MessageBatchPayload = serde_json::from_value(envelope.payload).unwrap();
+        assert_eq!(payload.messages[0].timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn orders_and_dedupes_message_batches() {
+        let message = |chat_id: &str, text: &str, timestamp: u64, msg_id: Option<&str>| {
+            MessageNewPayload {
+                chat_id: chat_id.to_string(),
+                chat_title: chat_id.to_string(),
+                is_group: false,
+                sender_name: chat_id.to_string(),
+                text: text.to_string(),
+                timestamp,
+                msg_id: msg_id.map(str::to_string),
+                content_type: MessageContentType::Text,
+                image_path: None,
+                audio_path: None,
+                account_id: String::new(),
+            }
+        };
+        let ordered = order_message_batch(vec![
+            message("c1", "第二条", 20, Some("m2")),
+            message("c2", "你好", 10, None),
+            message("c1", "第一条", 10, Some("m1")),
+            message("c1", "第二条", 20, Some("m2")),
+            message("c2", "你好", 10, None),
+            message("c1", "第三条", 20, None),
+        ]);
+        let texts: Vec<&str> = ordered.iter().map(|message| message.text.as_str()).collect();
+        assert_eq!(texts, vec!["你好", "第一条", "第二条", "第三条"]);
+    }
+
+    #[test]
+    fn negotiates_and_round_trips_binary_frames() {
+        let hello = IpcEnvelope::host_hello(true);
+        assert_eq!(
+            hello.payload["capabilities"],
+            serde_json::json!([CAPABILITY_MESSAGE_BATCH, CAPABILITY_MSGPACK_ZSTD])
+        );
+        let agent_hello = serde_json::json!({
+            "version": "1.1",
+            "capabilities": [CAPABILITY_MSGPACK_ZSTD],
+        });
+        assert!(negotiate(agent_hello.clone(), true).unwrap().binary_frames);
+        assert!(!negotiate(agent_hello, false).unwrap().binary_frames);
+
+        let chats: Vec<Value> = (0..5000)
+            .map(|index| serde_json::json!({"chat_id": format!("c{}", index), "title": "项目群"}))
+            .collect();
+        let mut envelope = IpcEnvelope::new(
+            "chats.list.result",
+            serde_json::json!({"request_id": "r1", "chats": chats}),
+        );
+        envelope.version = "1.1".to_string();
+        let json = encode_message(&envelope, false).unwrap();
+        assert!(json.len() > MAX_RAW_MESSAGE_LEN);
+        assert!(parse_message(&json).is_err());
+        let binary = encode_message(&envelope, true).unwrap();
+        assert!(binary.len() < json.len());
+        let decoded = parse_message(&binary).unwrap();
+        assert_eq!(decoded.r#type, "chats.list.result");
+        assert_eq!(decoded.payload["chats"][4999]["chat_id"], "c4999");
+
+        let small = IpcEnvelope::new("listen.stop", serde_json::json!({}));
+        assert_eq!(encode_message(&small, true).unwrap()[0], b'{');
     }
 
     #[test]
@@ -193,6 +485,7 @@ mod tests {
         let payload = ListenControlPayload {
             poll_interval_ms: Some(800),
             targets: None,
+            download_images: None,
         };
         let value = serde_json::to_value(payload).unwrap();
         assert_eq!(value["poll_interval_ms"], 800);
@@ -205,7 +498,17 @@ mod tests {
             targets: Some(vec![ListenTarget {
                 name: "Team A".into(),
                 kind: ChatKind::Group,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
             }]),
+            download_images: Some(true),
         };
         let value = serde_json::to_value(payload).unwrap();
         assert!(value.get("targets").is_some());
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use std::io::{Error, ErrorKind};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+pub const ENDPOINT_ENV: &str = "WEREPLY_IPC_ENDPOINT";
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, payload: &[u8]) -> Result<()> {
+    if payload.len() > MAX_FRAME_LEN {
+        anyhow::bail!("IPC 消息过大: {} 字节", payload.len());
+    }
+    writer
+        .write_all(&(payload.len() as u32).to_be_bytes())
+        .await?;
+    writer.write_all(payload).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("IPC 帧长度异常: {}", len),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok(Some(payload))
+}
+
+pub struct Inbound {
+    reader: BufReader<BoxedReader>,
+    framed: bool,
+}
+
+impl Inbound {
+    pub fn lines(reader: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        Self {
+            reader: BufReader::new(Box::new(reader)),
+            framed: false,
+        }
+    }
+
+    pub fn framed(reader: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        Self {
+            reader: BufReader::new(Box::new(reader)),
+            framed: true,
+        }
+    }
+
+    pub async fn next_message(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        if self.framed {
+            return read_frame(&mut self.reader).await;
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line))
+    }
+}
+
+pub struct Outbound {
+    writer: BoxedWriter,
+    framed: bool,
+}
+
+impl Outbound {
+    pub fn lines(writer: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+            framed: false,
+        }
+    }
+
+    pub fn framed(writer: impl AsyncWrite + Send + Unpin + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+            framed: true,
+        }
+    }
+
+    pub fn is_framed(&self) -> bool {
+        self.framed
+    }
+
+    pub async fn send(&mut self, message: &[u8]) -> Result<()> {
+        if self.framed {
+            return write_frame(&mut self.writer, message).await;
+        }
+        self.writer.write_all(message).await?;
+        self.writer.write_all(b"\n").await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+pub struct Listener {
+    endpoint: String,
+    #[cfg(unix)]
+    listener: tokio::net::UnixListener,
+    #[cfg(windows)]
+    pipes: (
+        tokio::net::windows::named_pipe::NamedPipeServer,
+        tokio::net::windows::named_pipe::NamedPipeServer,
+    ),
+}
+
+impl Listener {
+    #[cfg(unix)]
+    pub fn bind() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("wereply-{}.sock", Uuid::new_v4().simple()));
+        let listener = tokio::net::UnixListener::bind(&path)
+            .with_context(|| format!("创建 IPC 套接字失败: {}", path.display()))?;
+        Ok(Self {
+            endpoint: path.to_string_lossy().to_string(),
+            listener,
+        })
+    }
+
+    #[cfg(windows)]
+    pub fn bind() -> Result<Self> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let endpoint = format!(r"\\.\pipe\wereply-{}", Uuid::new_v4().simple());
+        let upstream = ServerOptions::new()
+            .first_pipe_instance(true)
+            .access_outbound(false)
+            .create(format!("{}-up", endpoint))
+            .context("创建 IPC 管道失败")?;
+        let downstream = ServerOptions::new()
+            .first_pipe_instance(true)
+            .access_inbound(false)
+            .create(format!("{}-down", endpoint))
+            .context("创建 IPC 管道失败")?;
+        Ok(Self {
+            endpoint,
+            pipes: (upstream, downstream),
+        })
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    #[cfg(unix)]
+    pub async fn accept(self, wait: Duration) -> Result<(Inbound, Outbound)> {
+        let accepted = timeout(wait, self.listener.accept()).await;
+        let _ = std::fs::remove_file(&self.endpoint);
+        let (stream, _) = accepted
+            .context("等待 Agent 连接 IPC 通道超时")?
+            .context("接受 IPC 连接失败")?;
+        let (reader, writer) = stream.into_split();
+        Ok((Inbound::framed(reader), Outbound::framed(writer)))
+    }
+
+    #[cfg(windows)]
+    pub async fn accept(self, wait: Duration) -> Result<(Inbound, Outbound)> {
+        let (upstream, downstream) = self.pipes;
+        timeout(wait, async {
+            upstream.connect().await?;
+            downstream.connect().await
+        })
+        .await
+        .context("等待 Agent 连接 IPC 通道超时")?
+        .context("接受 IPC 连接失败")?;
+        Ok((Inbound::framed(upstream), Outbound::framed(downstream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frames_round_trip_and_reject_oversized_lengths() {
+        let (client, server) = tokio::io::duplex(1024);
+        let mut outbound = Outbound::framed(client);
+        let mut inbound = Inbound::framed(server);
+        outbound.send(b"{\"type\":\"agent.ready\"}").await.unwrap();
+        outbound.send("第二条\n含换行".as_bytes()).await.unwrap();
+        drop(outbound);
+        assert_eq!(
+            inbound.next_message().await.unwrap().as_deref(),
+            Some(&b"{\"type\":\"agent.ready\"}"[..])
+        );
+        assert_eq!(
+            inbound.next_message().await.unwrap().as_deref(),
+            Some("第二条\n含换行".as_bytes())
+        );
+        assert!(inbound.next_message().await.unwrap().is_none());
+
+        let oversized = (MAX_FRAME_LEN as u32 + 1).to_be_bytes();
+        let mut reader = &oversized[..];
+        assert!(read_frame(&mut reader).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reads_lines_in_stdio_mode() {
+        let mut inbound = Inbound::lines(&b"{\"a\":1}\n\n{\"b\":2}"[..]);
+        assert_eq!(
+            inbound.next_message().await.unwrap().as_deref(),
+            Some(&b"{\"a\":1}\n"[..])
+        );
+        assert_eq!(
+            inbound.next_message().await.unwrap().as_deref(),
+            Some(&b"\n"[..])
+        );
+        assert_eq!(
+            inbound.next_message().await.unwrap().as_deref(),
+            Some(&b"{\"b\":2}"[..])
+        );
+        assert!(inbound.next_message().await.unwrap().is_none());
+    }
+}
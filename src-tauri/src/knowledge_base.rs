@@ -0,0 +1,342 @@
+use crate::deepseek::ContextMessage;
+use crate::state::{now_secs, SELF_PREFIX};
+use crate::types::KnowledgeBaseStatus;
+use crate::SharedState;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const SUPPORTED_EXTENSIONS: [&str; 4] = ["txt", "md", "markdown", "csv"];
+/// Buckets of the hashed term vectors; collisions only add a little noise.
+const TERM_BUCKETS: usize = 512;
+const MAX_CHUNK_CHARS: usize = 400;
+const MAX_DOCUMENTS: usize = 500;
+const MAX_CHUNKS: usize = 5000;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_DEPTH: usize = 4;
+const MIN_SCORE: f32 = 0.2;
+const QUERY_MESSAGES: usize = 2;
+
+#[derive(Debug, Clone)]
+struct KnowledgeChunk {
+    source: String,
+    text: String,
+    terms: Vec<f32>,
+}
+
+/// Lexical index over a local folder: chunks are matched on the words and
+/// CJK characters/bigrams they share with the query, not on meaning, so a
+/// question phrased without any of the document's terms finds nothing. Nothing
+/// leaves the machine.
+#[derive(Debug, Clone, Default)]
+pub struct KnowledgeBase {
+    dir: String,
+    documents: usize,
+    chunks: Vec<KnowledgeChunk>,
+    built_at: Option<u64>,
+}
+
+impl KnowledgeBase {
+    pub fn build(dir: &str, now: u64) -> Result<Self> {
+        let root = Path::new(dir);
+        if !root.is_dir() {
+            anyhow::bail!("知识库目录不存在: {}", dir);
+        }
+        let mut files = Vec::new();
+        collect_files(root, 0, &mut files)?;
+        files.sort();
+        let mut chunks = Vec::new();
+        let mut documents = 0;
+        for path in files.iter().take(MAX_DOCUMENTS) {
+            let contents = match fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(err) => {
+                    warn!("读取知识库文档失败 {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+            let source = path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string();
+            documents += 1;
+            for text in chunk_text(&contents) {
+                if chunks.len() >= MAX_CHUNKS {
+                    break;
+                }
+                chunks.push(KnowledgeChunk {
+                    source: source.clone(),
+                    terms: term_vector(&text),
+                    text,
+                });
+            }
+        }
+        if files.len() > MAX_DOCUMENTS || chunks.len() >= MAX_CHUNKS {
+            warn!(
+                "知识库内容过多，仅索引前 {} 篇文档、{} 个片段",
+                documents,
+                chunks.len()
+            );
+        }
+        Ok(Self {
+            dir: dir.to_string(),
+            documents,
+            chunks,
+            built_at: Some(now),
+        })
+    }
+
+    pub fn status(&self) -> KnowledgeBaseStatus {
+        KnowledgeBaseStatus {
+            dir: self.dir.clone(),
+            documents: self.documents as u32,
+            chunks: self.chunks.len() as u32,
+            built_at: self.built_at,
+        }
+    }
+
+    pub fn retrieve(&self, query: &str, top_k: usize) -> Vec<String> {
+        if self.chunks.is_empty() || query.trim().is_empty() {
+            return Vec::new();
+        }
+        let query = term_vector(query);
+        let mut scored: Vec<(f32, &KnowledgeChunk)> = self
+            .chunks
+            .iter()
+            .map(|chunk| (similarity(&query, &chunk.terms), chunk))
+            .filter(|(score, _)| *score >= MIN_SCORE)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(top_k)
+            .map(|(_, chunk)| format!("（{}）{}", chunk.source, chunk.text))
+            .collect()
+    }
+
+    pub fn retrieve_for_context(&self, messages: &[ContextMessage], top_k: usize) -> Vec<String> {
+        let incoming: Vec<&str> = messages
+            .iter()
+            .rev()
+            .filter(|message| !message.text.starts_with(SELF_PREFIX))
+            .take(QUERY_MESSAGES)
+            .map(|message| message.text.as_str())
+            .collect();
+        self.retrieve(&incoming.join("\n"), top_k)
+    }
+}
+
+fn collect_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("读取知识库目录失败: {}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if hidden || files.len() > MAX_DOCUMENTS {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if depth < MAX_DEPTH {
+                collect_files(&path, depth + 1, files)?;
+            }
+        } else if metadata.len() <= MAX_FILE_BYTES && is_supported(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            SUPPORTED_EXTENSIONS
+                .iter()
+                .any(|item| item.eq_ignore_ascii_case(ext))
+        })
+}
+
+fn chunk_text(contents: &str) -> Vec<String> {
+    let contents = contents.replace("\r\n", "\n");
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for paragraph in contents.split("\n\n") {
+        let paragraph = paragraph.split_whitespace().collect::<Vec<_>>().join(" ");
+        if paragraph.is_empty() {
+            continue;
+        }
+        let merged = current.chars().count() + paragraph.chars().count() + 1;
+        if !current.is_empty() && merged > MAX_CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        let chars: Vec<char> = paragraph.chars().collect();
+        if chars.len() > MAX_CHUNK_CHARS {
+            chunks.extend(
+                chars
+                    .chunks(MAX_CHUNK_CHARS)
+                    .map(|piece| piece.iter().collect::<String>()),
+            );
+            continue;
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&paragraph);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}')
+}
+
+fn bucket(feature: &str) -> usize {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in feature.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % TERM_BUCKETS as u64) as usize
+}
+
+/// Normalized bag of terms hashed into `TERM_BUCKETS`: latin words, CJK
+/// characters and CJK bigrams, weighted towards the longer terms.
+fn term_vector(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; TERM_BUCKETS];
+    let lowered = text.to_lowercase();
+    let mut word = String::new();
+    let mut previous_cjk: Option<char> = None;
+    for ch in lowered.chars().chain([' ']) {
+        if is_cjk(ch) {
+            vector[bucket(ch.encode_utf8(&mut [0; 4]))] += 1.0;
+            if let Some(previous) = previous_cjk {
+                vector[bucket(&format!("{}{}", previous, ch))] += 1.5;
+            }
+            previous_cjk = Some(ch);
+        } else {
+            previous_cjk = None;
+        }
+        if ch.is_alphanumeric() && !is_cjk(ch) {
+            word.push(ch);
+        } else if !word.is_empty() {
+            vector[bucket(&word)] += 2.0;
+            word.clear();
+        }
+    }
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub async fn refresh(state: &SharedState, force: bool) -> Result<KnowledgeBaseStatus> {
+    let dir = {
+        let guard = state.lock().await;
+        let dir = guard.config.knowledge_base_dir.clone();
+        if !force && guard.knowledge.dir == dir {
+            return Ok(guard.knowledge.status());
+        }
+        dir
+    };
+    let knowledge = if dir.is_empty() {
+        KnowledgeBase::default()
+    } else {
+        let build_dir = dir.clone();
+        tokio::task::spawn_blocking(move || KnowledgeBase::build(&build_dir, now_secs()))
+            .await
+            .context("知识库索引任务异常")??
+    };
+    let status = knowledge.status();
+    if !dir.is_empty() {
+        info!(
+            "知识库索引完成: {} 篇文档，{} 个片段",
+            status.documents, status.chunks
+        );
+    }
+    state.lock().await.knowledge = knowledge;
+    Ok(status)
+}
+
+pub fn spawn_refresh(state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = refresh(&state, false).await {
+            warn!("构建知识库索引失败: {}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_long_documents_by_paragraph() {
+        let text = format!(
+            "第一段\n\n第二段\r\n\r\n{}",
+            "长".repeat(MAX_CHUNK_CHARS + 10)
+        );
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], "第一段 第二段");
+        assert_eq!(chunks[1].chars().count(), MAX_CHUNK_CHARS);
+        assert_eq!(chunks[2], "长".repeat(10));
+    }
+
+    #[test]
+    fn retrieves_relevant_chunks_from_folder() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(
+            temp.path().join("pricing.md"),
+            "# 价格\n\n标准版每年 1999 元，专业版每年 4999 元。\n\n团购 10 套以上打八折。",
+        )
+        .unwrap();
+        fs::create_dir(temp.path().join("faq")).unwrap();
+        fs::write(
+            temp.path().join("faq").join("shipping.txt"),
+            "发货时间：工作日下单后 48 小时内发货，默认顺丰快递。",
+        )
+        .unwrap();
+        fs::write(temp.path().join("logo.png"), [0u8, 1, 2]).unwrap();
+        fs::write(temp.path().join(".draft.md"), "草稿").unwrap();
+
+        let base = KnowledgeBase::build(&temp.path().display().to_string(), 100).unwrap();
+        let status = base.status();
+        assert_eq!(status.documents, 2);
+        assert_eq!(status.built_at, Some(100));
+
+        let hits = base.retrieve("专业版一年多少钱？", 1);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].starts_with("（pricing.md）"));
+        assert!(hits[0].contains("4999"));
+
+        let messages = vec![
+            ContextMessage {
+                text: "你们什么时候发货？".to_string(),
+                age_secs: 30,
+            },
+            ContextMessage {
+                text: "我：稍等我查一下".to_string(),
+                age_secs: 10,
+            },
+        ];
+        let hits = base.retrieve_for_context(&messages, 3);
+        assert!(hits[0].contains("48 小时"));
+        assert!(base.retrieve("周末一起去爬山吗", 3).is_empty());
+        // Lexical only: a paraphrase sharing no terms with the FAQ misses it.
+        assert!(base.retrieve("寄出要多久", 3).is_empty());
+        assert!(KnowledgeBase::build("/nonexistent/wereply", 0).is_err());
+    }
+}
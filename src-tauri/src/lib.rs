@@ -1,39 +1,127 @@
 mod agent;
+mod agent_log;
+mod auto_reply;
+mod avatar;
+mod backtest;
 pub mod bindings;
+mod canned_responses;
+mod chat_identity;
+mod chat_kind;
+mod chat_list_cache;
 mod config;
+mod contact_notes;
+mod content_type;
+mod db_watch;
 mod deepseek;
+mod experiments;
+mod frontend_link;
+mod generation;
+mod graphemes;
+mod handover;
+mod history;
+mod http_client;
+mod integration_server;
+mod integration_tokens;
 mod ipc;
+mod ipc_transport;
+mod knowledge_base;
 mod listen_targets;
 mod logging;
+mod maintenance;
+mod menu_bar;
 mod message_pipeline;
+mod net_timing;
+mod notification;
+mod ocr;
+mod personas;
+mod pii;
+mod politeness;
+mod power;
+mod prompt_guard;
+mod prompt_versions;
+mod quota;
+mod ranking;
+mod readiness;
+mod reply;
+mod reply_language;
+mod reply_length;
+mod safety_filter;
 mod secret;
+pub mod smoke;
+mod sqlcipher;
 mod state;
+mod style_fingerprint;
+mod styles;
+mod tokens;
+mod transcript;
+mod transcription;
 mod types;
 mod ui_automation;
+mod wechat_db_key;
+mod wechat_presence;
+mod write_queue;
 
 use crate::agent::start_agent;
-use crate::config::load_config;
+use crate::canned_responses::{load_canned_responses, save_canned_responses};
+use crate::chat_identity::{load_chat_identities, save_chat_identities};
+use crate::chat_list_cache::{load_chat_list_cache, save_chat_list_cache};
+use crate::config::{load_config, load_profiles, prepare_config, save_profiles};
 use crate::config::save_config;
+use crate::contact_notes::{load_contact_notes, save_contact_notes};
+use crate::safety_filter::SafetyFilter;
 use crate::secret::ApiKeyManager;
-use crate::state::AppState;
-use crate::ui_automation::build_platform_automation;
+use crate::state::{now_secs, AppState};
+use crate::ui_automation::pool::BUSY_CODE;
+use crate::ui_automation::{AutomationManager, ChatPage, ChatQuery, IncomingMessage};
+use crate::integration_tokens::{load_integration_tokens, save_integration_tokens};
 use crate::ipc::{
-    ChatsListPayload, InputWritePayload, IpcEnvelope, ListenControlPayload, ListenTargetsPayload,
+    ChatsListPayload, ChatsListResultPayload, InputResultPayload, InputWritePayload, IpcEnvelope,
+    ListenControlPayload, ListenTargetsPayload, MessageSentPayload,
 };
 use crate::listen_targets::{normalize_listen_targets, MAX_LISTEN_TARGETS};
+use crate::personas::{load_personas, save_personas};
+use crate::prompt_versions::{load_prompt_versions, save_prompt_versions};
 use crate::types::{
-    api_err, api_ok, ApiResponse, ChatSummary, Config, DeepseekDiagnostics, ListenTarget, Platform,
-    RuntimeState, Status, UiPathStep, UiPathsStatus, UiTreeExport, UiTreeLearnResult,
+    api_err, api_ok, AgentInfo, ApiResponse, AutomationMetrics, AutomationStrategy,
+    AutomationTraceExport, BacktestRange, BacktestReport, CannedResponse, ChatActivityStats,
+    ChatAvatar, ChatSummary, CipherSelfTest, Config, ContactNote, DbKeyReport, DecryptExport,
+    DeepseekBalance, DeepseekDiagnostics, ErrorPayload, ExperimentReport, ExperimentVariant,
+    FrontendSync, HandoverBrief, InputWriteResult, InputWriteStatus, IntegrationScope,
+    IntegrationToken, IntegrationTokenCreated, KnowledgeBaseStatus, ListenTarget,
+    ListenTargetResult, ListenTargetsReport, LocatorDiagnostic, MaintenanceReport,
+    MessageSearchHit, ModelInfo, Persona, Platform, PowerStatus, ProfileSummary, PromptChange,
+    PromptVersion, Readiness, RecentChats, ReplyMode, ReplySource, RuntimeState, SeedContextResult,
+    SessionInstruction, Status, SuggestedAction, Suggestion, SuggestionAcceptance,
+    SuggestionRecord, SuggestionStyle, SuggestionsUpdated, UiPathStep, UiPathsStatus, UiTreeExport,
+    UiTreeLearnResult,
 };
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Instant;
 use tauri::{AppHandle, Emitter, LogicalSize, Manager, Size, State};
-use tokio::sync::{Mutex, oneshot, watch};
-use tokio::time::{timeout, Duration};
+use tokio::sync::{Mutex, watch};
+use tokio::time::Duration;
 use uuid::Uuid;
 use tracing::{info, warn};
 
 type SharedState = Arc<Mutex<AppState>>;
 
+const MAX_SESSION_INSTRUCTION_CHARS: usize = 500;
+const MAX_SESSION_INSTRUCTION_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+const WRITE_MAX_ATTEMPTS: u32 = 2;
+const WRITE_RETRY_DELAY_MS: u64 = 300;
+const DEFAULT_SUGGESTION_HISTORY_LIMIT: u32 = 50;
+const MAX_SUGGESTION_HISTORY_LIMIT: u32 = 200;
+const DEFAULT_CHAT_PAGE: u32 = 50;
+const MAX_CHAT_PAGE: u32 = 200;
+const DEFAULT_ACCEPTANCE_DAYS: u32 = 30;
+const MAX_ACCEPTANCE_DAYS: u32 = 365;
+const AUTOMATION_TRACE_FILE: &str = "automation_trace.json";
+const MIN_COMPOSE_FRAGMENTS: usize = 2;
+const MAX_COMPOSE_FRAGMENTS: usize = 5;
+const MAX_COMPOSE_CHARS: usize = 2000;
+const MAX_TRANSCRIPT_CHARS: usize = 50_000;
+
 #[tauri::command]
 #[specta::specta]
 async fn get_config(state: State<'_, SharedState>) -> Result<ApiResponse<Config>, String> {
@@ -44,247 +132,1649 @@ async fn get_config(state: State<'_, SharedState>) -> Result<ApiResponse<Config>
 #[tauri::command]
 #[specta::specta]
 async fn set_config(
-    _app: AppHandle,
-    _state: State<'_, SharedState>,
-    _config: Config,
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    config: Config,
 ) -> Result<ApiResponse<()>, String> {
-    Ok(api_err("配置已固定为默认值"))
+    let next_config = match prepare_config(config) {
+        Ok(config) => config,
+        Err(err) => {
+            warn!("配置校验失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+    };
+    {
+        let mut guard = state.lock().await;
+        if let Err(err) = save_config(&app, &next_config) {
+            warn!("保存配置失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        guard.config = next_config.clone();
+        guard.listen_targets = next_config.listen_targets.clone();
+        track_prompt_version(&app, &mut guard, "配置");
+    }
+    info!(
+        "配置已更新: model={}, poll_interval_ms={}, targets={}",
+        next_config.deepseek_model,
+        next_config.poll_interval_ms,
+        next_config.listen_targets.len()
+    );
+    hot_apply_config(&app, state.inner().clone(), next_config).await;
+    Ok(api_ok(()))
 }
 
 #[tauri::command]
 #[specta::specta]
-async fn list_models(state: State<'_, SharedState>) -> Result<ApiResponse<Vec<String>>, String> {
-    let config = {
-        let guard = state.lock().await;
-        guard.config.clone()
+async fn list_profiles(app: AppHandle) -> Result<ApiResponse<Vec<ProfileSummary>>, String> {
+    match load_profiles(&app) {
+        Ok(store) => Ok(api_ok(store.summaries())),
+        Err(err) => Ok(api_err(err.to_string())),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn save_profile(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    name: String,
+) -> Result<ApiResponse<Vec<ProfileSummary>>, String> {
+    let guard = state.lock().await;
+    let mut store = match load_profiles(&app) {
+        Ok(store) => store,
+        Err(err) => return Ok(api_err(err.to_string())),
     };
-    let api_key = match ApiKeyManager::get_deepseek_api_key() {
-        Ok(key) => key,
+    if let Err(err) = store.save_profile(&name, &guard.config) {
+        return Ok(api_err(err.to_string()));
+    }
+    if let Err(err) = save_profiles(&app, &store) {
+        warn!("保存配置方案失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    info!("已保存配置方案: {}", name.trim());
+    Ok(api_ok(store.summaries()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_profile(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    name: String,
+) -> Result<ApiResponse<Vec<ProfileSummary>>, String> {
+    let _guard = state.lock().await;
+    let mut store = match load_profiles(&app) {
+        Ok(store) => store,
         Err(err) => return Ok(api_err(err.to_string())),
     };
-    match deepseek::list_models(&config, &api_key).await {
-        Ok(models) => Ok(api_ok(models)),
-        Err(err) => Ok(api_err(err.to_string())),
+    if !store.remove_profile(&name) {
+        return Ok(api_err("配置方案不存在"));
+    }
+    if let Err(err) = save_profiles(&app, &store) {
+        warn!("保存配置方案失败: {}", err);
+        return Ok(api_err(err.to_string()));
     }
+    info!("已删除配置方案: {}", name.trim());
+    Ok(api_ok(store.summaries()))
 }
 
 #[tauri::command]
 #[specta::specta]
-async fn get_status(state: State<'_, SharedState>) -> Result<ApiResponse<Status>, String> {
+async fn switch_profile(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    name: String,
+) -> Result<ApiResponse<Vec<ProfileSummary>>, String> {
+    let (next_config, summaries) = {
+        let mut guard = state.lock().await;
+        let mut store = match load_profiles(&app) {
+            Ok(store) => store,
+            Err(err) => return Ok(api_err(err.to_string())),
+        };
+        let next_config = match store.switch_profile(&name, &guard.config) {
+            Ok(config) => config,
+            Err(err) => return Ok(api_err(err.to_string())),
+        };
+        if let Err(err) = save_config(&app, &next_config) {
+            warn!("保存配置失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        if let Err(err) = save_profiles(&app, &store) {
+            warn!("保存配置方案失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        guard.config = next_config.clone();
+        guard.listen_targets = next_config.listen_targets.clone();
+        track_prompt_version(&app, &mut guard, "配置方案");
+        (next_config, store.summaries())
+    };
+    info!("已切换配置方案: {}", name.trim());
+    hot_apply_config(&app, state.inner().clone(), next_config).await;
+    Ok(api_ok(summaries))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_canned_responses(
+    state: State<'_, SharedState>,
+    tag: Option<String>,
+) -> Result<ApiResponse<Vec<CannedResponse>>, String> {
     let guard = state.lock().await;
-    Ok(api_ok(guard.status.clone()))
+    Ok(api_ok(guard.canned_responses.list(tag.as_deref())))
 }
 
 #[tauri::command]
 #[specta::specta]
-async fn start_listening(
+async fn create_canned_response(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    title: String,
+    text: String,
+    tags: Option<Vec<String>>,
+) -> Result<ApiResponse<CannedResponse>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.canned_responses.clone();
+    let response = match store.create(&title, &text, tags.unwrap_or_default(), now_secs()) {
+        Ok(response) => response,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    if let Err(err) = save_canned_responses(&app, &store) {
+        warn!("保存快捷回复失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.canned_responses = store;
+    info!("已新增快捷回复: {}", response.title);
+    Ok(api_ok(response))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn update_canned_response(
     app: AppHandle,
     state: State<'_, SharedState>,
+    id: String,
+    title: String,
+    text: String,
+    tags: Option<Vec<String>>,
+) -> Result<ApiResponse<CannedResponse>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.canned_responses.clone();
+    let response = match store.update(&id, &title, &text, tags.unwrap_or_default(), now_secs()) {
+        Ok(response) => response,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    if let Err(err) = save_canned_responses(&app, &store) {
+        warn!("保存快捷回复失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.canned_responses = store;
+    info!("已更新快捷回复: {}", response.title);
+    Ok(api_ok(response))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_canned_response(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    id: String,
 ) -> Result<ApiResponse<()>, String> {
-    info!("收到开始监听请求");
-    {
-        let guard = state.lock().await;
-        if guard.status.state == RuntimeState::Listening {
-            info!("已在监听中，忽略重复请求");
-            return Ok(api_ok(()));
-        }
-        if guard.listen_targets.is_empty() {
-            warn!("未设置监听对象，拒绝开始监听");
-            return Ok(api_err("请先设置监听对象"));
-        }
+    let mut guard = state.lock().await;
+    let mut store = guard.canned_responses.clone();
+    if !store.remove(&id) {
+        return Ok(api_err("快捷回复不存在"));
+    }
+    if let Err(err) = save_canned_responses(&app, &store) {
+        warn!("保存快捷回复失败: {}", err);
+        return Ok(api_err(err.to_string()));
     }
+    guard.canned_responses = store;
+    info!("已删除快捷回复: {}", id);
+    Ok(api_ok(()))
+}
 
-    let (automation, targets) = {
-        let guard = state.lock().await;
-        (guard.automation.clone(), guard.listen_targets.clone())
+#[tauri::command]
+#[specta::specta]
+async fn list_contact_notes(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Vec<ContactNote>>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.contact_notes.list()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_contact_note(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    chat_id: String,
+    note: String,
+) -> Result<ApiResponse<ContactNote>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.contact_notes.clone();
+    let saved = match store.set(&chat_id, &note, now_secs()) {
+        Ok(saved) => saved,
+        Err(err) => return Ok(api_err(err.to_string())),
     };
-    if automation.is_ready() {
-        info!(
-            "使用本地自动化路径启动监听: targets={}",
-            targets.len()
-        );
-        let res = automation.start_listening(targets).await;
-        if res.success {
-            start_automation_polling(app.clone(), state.inner().clone()).await;
-            set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
-            info!("本地自动化监听已启动");
-        } else {
-            warn!("本地自动化监听启动失败: {}", res.message);
+    if let Err(err) = save_contact_notes(&app, &store) {
+        warn!("保存联系人备注失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.contact_notes = store;
+    info!("已更新联系人备注: {}", saved.chat_id);
+    Ok(api_ok(saved))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_contact_note(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    chat_id: String,
+) -> Result<ApiResponse<()>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.contact_notes.clone();
+    if !store.remove(&chat_id) {
+        return Ok(api_err("联系人备注不存在"));
+    }
+    if let Err(err) = save_contact_notes(&app, &store) {
+        warn!("保存联系人备注失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.contact_notes = store;
+    info!("已删除联系人备注: {}", chat_id);
+    Ok(api_ok(()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_knowledge_base_status(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<KnowledgeBaseStatus>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.knowledge.status()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn rebuild_knowledge_base(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<KnowledgeBaseStatus>, String> {
+    match knowledge_base::refresh(state.inner(), true).await {
+        Ok(status) => Ok(api_ok(status)),
+        Err(err) => {
+            warn!("重建知识库索引失败: {}", err);
+            Ok(api_err(err.to_string()))
         }
-        return Ok(res);
     }
+}
 
-    info!("使用 Agent 路径启动监听");
-    if let Err(err) = ensure_agent_running(app.clone(), state.inner().clone()).await {
-        warn!("启动 Agent 失败: {}", err);
+#[tauri::command]
+#[specta::specta]
+async fn list_personas(state: State<'_, SharedState>) -> Result<ApiResponse<Vec<Persona>>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.personas.list()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn save_persona(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    persona: Persona,
+) -> Result<ApiResponse<Persona>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.personas.clone();
+    let persona = match store.save(persona) {
+        Ok(persona) => persona,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    if let Err(err) = save_personas(&app, &store) {
+        warn!("保存人设失败: {}", err);
         return Ok(api_err(err.to_string()));
     }
-    info!("Agent 已连接，发送监听指令");
-    if let Err(err) =
-        send_listen_control(state.inner().clone(), "listen.start", true, true).await
-    {
-        warn!("发送监听指令失败: {}", err);
-        return Ok(api_err(err));
+    guard.personas = store;
+    track_prompt_version(&app, &mut guard, "人设");
+    info!("已保存人设: {}", persona.name);
+    Ok(api_ok(persona))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_persona(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    name: String,
+) -> Result<ApiResponse<()>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.personas.clone();
+    if !store.remove(name.trim()) {
+        return Ok(api_err("人设不存在"));
     }
-    set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
-    info!("监听已启动");
+    if let Err(err) = save_personas(&app, &store) {
+        warn!("保存人设失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.personas = store;
+    track_prompt_version(&app, &mut guard, "人设");
+    info!("已删除人设: {}", name.trim());
     Ok(api_ok(()))
 }
 
+fn track_prompt_version(app: &AppHandle, guard: &mut AppState, source: &str) {
+    let snapshot = prompt_versions::snapshot(&guard.config, &guard.personas.list());
+    let Some(version) = guard.prompt_versions.record(snapshot, source, now_secs()) else {
+        return;
+    };
+    if let Err(err) = save_prompt_versions(app, &guard.prompt_versions) {
+        warn!("保存提示词版本失败: {}", err);
+    }
+    info!("提示词已更新到版本 {}（{}）", version, source);
+}
+
 #[tauri::command]
 #[specta::specta]
-async fn stop_listening(
+async fn list_prompt_versions(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Vec<PromptVersion>>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.prompt_versions.list()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn diff_prompt_versions(
+    state: State<'_, SharedState>,
+    from: u32,
+    to: u32,
+) -> Result<ApiResponse<Vec<PromptChange>>, String> {
+    let guard = state.lock().await;
+    let (Some(from), Some(to)) = (
+        guard.prompt_versions.get(from),
+        guard.prompt_versions.get(to),
+    ) else {
+        return Ok(api_err("提示词版本不存在"));
+    };
+    Ok(api_ok(prompt_versions::diff(&from.snapshot, &to.snapshot)))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn rollback_prompt_version(
     app: AppHandle,
     state: State<'_, SharedState>,
-) -> Result<ApiResponse<()>, String> {
-    info!("收到停止监听请求");
-    let automation = {
-        let guard = state.lock().await;
-        guard.automation.clone()
+    version: u32,
+) -> Result<ApiResponse<u32>, String> {
+    let (next_config, current) = {
+        let mut guard = state.lock().await;
+        let Some(target) = guard.prompt_versions.get(version).cloned() else {
+            return Ok(api_err("提示词版本不存在"));
+        };
+        let mut next_config = guard.config.clone();
+        let mut personas = guard.personas.list();
+        prompt_versions::restore(&target.snapshot, &mut next_config, &mut personas);
+        let next_config = match prepare_config(next_config) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("回滚提示词失败: {}", err);
+                return Ok(api_err(err.to_string()));
+            }
+        };
+        let mut store = guard.personas.clone();
+        for persona in personas {
+            if let Err(err) = store.save(persona) {
+                return Ok(api_err(err.to_string()));
+            }
+        }
+        if let Err(err) = save_config(&app, &next_config) {
+            warn!("保存配置失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        if let Err(err) = save_personas(&app, &store) {
+            warn!("保存人设失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        guard.config = next_config.clone();
+        guard.listen_targets = next_config.listen_targets.clone();
+        guard.personas = store;
+        track_prompt_version(&app, &mut guard, &format!("回滚到版本 {}", version));
+        (next_config, guard.prompt_versions.current())
     };
-    if automation.is_ready() {
-        let res = automation.stop_listening().await;
-        if res.success {
-            stop_automation_polling(state.inner().clone()).await;
-            set_runtime_state(&app, state.inner().clone(), RuntimeState::Idle, "").await;
+    info!("提示词已回滚到版本 {}", version);
+    hot_apply_config(&app, state.inner().clone(), next_config).await;
+    Ok(api_ok(current))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_integration_tokens(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Vec<IntegrationToken>>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.integration_tokens.list()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn create_integration_token(
+    state: State<'_, SharedState>,
+    name: String,
+    scopes: Vec<IntegrationScope>,
+) -> Result<ApiResponse<IntegrationTokenCreated>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.integration_tokens.clone();
+    let created = match store.create(&name, scopes, now_secs()) {
+        Ok(created) => created,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    if let Err(err) = save_integration_tokens(&store) {
+        warn!("保存集成令牌失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.integration_tokens = store;
+    info!("已创建集成令牌: {}", created.token.name);
+    Ok(api_ok(created))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn revoke_integration_token(
+    state: State<'_, SharedState>,
+    id: String,
+) -> Result<ApiResponse<()>, String> {
+    let mut guard = state.lock().await;
+    let mut store = guard.integration_tokens.clone();
+    if !store.revoke(&id) {
+        return Ok(api_err("集成令牌不存在"));
+    }
+    if let Err(err) = save_integration_tokens(&store) {
+        warn!("保存集成令牌失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    guard.integration_tokens = store;
+    info!("已撤销集成令牌: {}", id);
+    Ok(api_ok(()))
+}
+
+async fn hot_apply_config(app: &AppHandle, state: SharedState, config: Config) {
+    ui_automation::trace::configure(config.automation_trace, config.automation_trace_minutes);
+    {
+        let mut guard = state.lock().await;
+        guard.automation.configure(config.automation_concurrency);
+        let next = power::resolve_power_status(guard.status.power.source, config.low_power_mode);
+        if next != guard.status.power {
+            power::apply_power_status(&mut guard.status.power, next);
+            let _ = app.emit("status.changed", guard.status.clone());
+        }
+    }
+    knowledge_base::spawn_refresh(state.clone());
+    refresh_automation_strategy(app, state.clone()).await;
+    apply_listen_settings(app, state).await;
+    #[cfg(target_os = "macos")]
+    if let Err(err) = menu_bar::apply_dock_icon(app, config.hide_dock_icon) {
+        warn!("切换 Dock 图标失败: {}", err);
+    }
+    let _ = app.emit("config.changed", config);
+}
+
+/// Re-runs strategy selection when the configured order changed. Skipped while
+/// listening so the backend is not swapped underneath a running watcher; the
+/// next start picks it up instead.
+async fn refresh_automation_strategy(app: &AppHandle, state: SharedState) {
+    let (order, concurrency) = {
+        let guard = state.lock().await;
+        if guard.automation.order() == guard.config.automation_strategies.as_slice()
+            || guard.session_state() == RuntimeState::Listening
+        {
+            return;
+        }
+        (
+            guard.config.automation_strategies.clone(),
+            guard.config.automation_concurrency,
+        )
+    };
+    let automation =
+        match tokio::task::spawn_blocking(move || AutomationManager::select(&order)).await {
+            Ok(automation) => automation,
+            Err(err) => {
+                warn!("切换自动化方式失败: {}", err);
+                return;
+            }
+        };
+    automation.configure(concurrency);
+    let mut guard = state.lock().await;
+    guard.automation = automation;
+    guard.status.strategy = guard.automation.strategy();
+    let _ = app.emit("status.changed", guard.status.clone());
+}
+
+async fn apply_listen_settings(app: &AppHandle, state: SharedState) {
+    let (agent_connected, polling) = {
+        let guard = state.lock().await;
+        (guard.agent.is_some(), guard.automation_stop.is_some())
+    };
+    if agent_connected {
+        agent::sync_account_agents(app, &state).await;
+        if let Err(err) = send_listen_control(state.clone(), "listen.config", true, true).await {
+            warn!("推送配置到 Agent 失败: {}", err);
+        }
+    }
+    if polling {
+        start_automation_polling(app.clone(), state).await;
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn list_models(state: State<'_, SharedState>) -> Result<ApiResponse<Vec<ModelInfo>>, String> {
+    let config = {
+        let guard = state.lock().await;
+        guard.config.clone()
+    };
+    let api_key = match ApiKeyManager::get_deepseek_api_key() {
+        Ok(key) => key,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    match deepseek::list_models(&config, &api_key).await {
+        Ok(models) => Ok(api_ok(models)),
+        Err(err) => Ok(api_err(err.to_string())),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_status(state: State<'_, SharedState>) -> Result<ApiResponse<Status>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.status.clone()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_readiness(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Readiness>, String> {
+    Ok(api_ok(
+        readiness::refresh_readiness(app, state.inner().clone()).await,
+    ))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn start_listening(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
+    info!("收到开始监听请求");
+    {
+        let guard = state.lock().await;
+        if guard.session_state() == RuntimeState::Listening {
+            info!("已在监听中，忽略重复请求");
+            return Ok(api_ok(()));
+        }
+        if guard.listen_targets.is_empty() {
+            warn!("未设置监听对象，拒绝开始监听");
+            return Ok(api_err("请先设置监听对象"));
+        }
+    }
+    refresh_automation_strategy(&app, state.inner().clone()).await;
+
+    let (automation, targets) = {
+        let guard = state.lock().await;
+        (guard.automation.clone(), guard.listen_targets.clone())
+    };
+    if automation.is_ready() {
+        info!(
+            "使用本地自动化路径启动监听: targets={}",
+            targets.len()
+        );
+        let res = automation.start_listening(targets).await;
+        if res.success {
+            start_automation_polling(app.clone(), state.inner().clone()).await;
+            set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
+            info!("本地自动化监听已启动");
+        } else {
+            warn!("本地自动化监听启动失败: {}", res.message);
+            let suggested_action = if cfg!(target_os = "macos") {
+                SuggestedAction::RelearnPaths
+            } else {
+                SuggestedAction::Retry
+            };
+            let _ = app.emit(
+                "error.raised",
+                ErrorPayload {
+                    code: "LISTEN_FAILED".to_string(),
+                    message: res.message.clone(),
+                    recoverable: true,
+                    suggested_action: Some(suggested_action),
+                },
+            );
+        }
+        return Ok(res);
+    }
+
+    info!("使用 Agent 路径启动监听");
+    if let Err(err) = ensure_agent_running(app.clone(), state.inner().clone()).await {
+        warn!("启动 Agent 失败: {}", err);
+        return Ok(api_err(err.to_string()));
+    }
+    info!("Agent 已连接，发送监听指令");
+    if let Err(err) =
+        send_listen_control(state.inner().clone(), "listen.start", true, true).await
+    {
+        warn!("发送监听指令失败: {}", err);
+        return Ok(api_err(err));
+    }
+    set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
+    info!("监听已启动");
+    Ok(api_ok(()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_listening(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
+    info!("收到停止监听请求");
+    let automation = {
+        let guard = state.lock().await;
+        guard.automation.clone()
+    };
+    if automation.is_ready() {
+        let res = automation.stop_listening().await;
+        if res.success {
+            stop_automation_polling(state.inner().clone()).await;
+            set_runtime_state(&app, state.inner().clone(), RuntimeState::Idle, "").await;
+        }
+        return Ok(res);
+    }
+
+    if let Err(err) =
+        send_listen_control(state.inner().clone(), "listen.stop", false, false).await
+    {
+        warn!("发送停止监听指令失败: {}", err);
+        return Ok(api_err(err));
+    }
+    set_runtime_state(&app, state.inner().clone(), RuntimeState::Idle, "").await;
+    info!("监听已停止");
+    Ok(api_ok(()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn restart_agent(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
+    info!("收到重启 Agent 请求");
+    match agent::restart_agent(&app, state.inner()).await {
+        Ok(()) => Ok(api_ok(())),
+        Err(err) => {
+            warn!("重启 Agent 失败: {}", err);
+            Ok(api_err(format!("重启 Agent 失败: {}", err)))
+        }
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn stop_agent(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
+    info!("收到停止 Agent 请求");
+    if !agent::stop_agent(&app, state.inner()).await {
+        return Ok(api_err("Agent 未运行"));
+    }
+    Ok(api_ok(()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_agent_info(
+    state: State<'_, SharedState>,
+    account_id: Option<String>,
+) -> Result<ApiResponse<AgentInfo>, String> {
+    let guard = state.lock().await;
+    match guard.agent_for_account(account_id.as_deref().unwrap_or_default()) {
+        Some(agent) => Ok(api_ok(agent.info(now_secs()))),
+        None => Ok(api_err("Agent 未运行")),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn pause_listening(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
+    info!("收到暂停监听请求");
+    let automation = {
+        let guard = state.lock().await;
+        guard.automation.clone()
+    };
+    if automation.is_ready() {
+        stop_automation_polling(state.inner().clone()).await;
+        set_runtime_state(&app, state.inner().clone(), RuntimeState::Paused, "").await;
+        info!("监听已暂停");
+        return Ok(api_ok(()));
+    }
+    if let Err(err) =
+        send_listen_control(state.inner().clone(), "listen.pause", false, false).await
+    {
+        warn!("发送暂停监听指令失败: {}", err);
+        return Ok(api_err(err));
+    }
+    set_runtime_state(&app, state.inner().clone(), RuntimeState::Paused, "").await;
+    info!("监听已暂停");
+    Ok(api_ok(()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn resume_listening(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
+    info!("收到恢复监听请求");
+    {
+        let guard = state.lock().await;
+        if guard.listen_targets.is_empty() {
+            warn!("未设置监听对象，拒绝恢复监听");
+            return Ok(api_err("请先设置监听对象"));
+        }
+    }
+    let automation = {
+        let guard = state.lock().await;
+        guard.automation.clone()
+    };
+    if automation.is_ready() {
+        let targets = {
+            let guard = state.lock().await;
+            guard.listen_targets.clone()
+        };
+        let res = automation.start_listening(targets).await;
+        if res.success {
+            start_automation_polling(app.clone(), state.inner().clone()).await;
+            set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
+        }
+        return Ok(res);
+    }
+    if let Err(err) =
+        send_listen_control(state.inner().clone(), "listen.resume", true, true).await
+    {
+        warn!("发送恢复监听指令失败: {}", err);
+        return Ok(api_err(err));
+    }
+    set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
+    info!("监听已恢复");
+    Ok(api_ok(()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_listen_targets(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Vec<ListenTarget>>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.listen_targets.clone()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_listen_targets(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    targets: Vec<ListenTarget>,
+) -> Result<ApiResponse<()>, String> {
+    match apply_listen_targets(&app, state.inner().clone(), targets).await {
+        Ok(_) => Ok(api_ok(())),
+        Err(err) => Ok(api_err(err)),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn add_listen_targets(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    batch: Vec<ListenTarget>,
+) -> Result<ApiResponse<ListenTargetsReport>, String> {
+    let mut results = Vec::new();
+    let targets = update_listen_targets(&app, state.inner().clone(), |current| {
+        let (next, outcome) =
+            listen_targets::add_listen_targets(current, batch, MAX_LISTEN_TARGETS);
+        results = outcome;
+        results.iter().any(|result| result.ok).then_some(next)
+    })
+    .await;
+    Ok(listen_targets_report(targets, results))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn remove_listen_targets(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    names: Vec<String>,
+) -> Result<ApiResponse<ListenTargetsReport>, String> {
+    let mut results = Vec::new();
+    let targets = update_listen_targets(&app, state.inner().clone(), |current| {
+        let (next, outcome) = listen_targets::remove_listen_targets(current, names);
+        results = outcome;
+        results.iter().any(|result| result.ok).then_some(next)
+    })
+    .await;
+    Ok(listen_targets_report(targets, results))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn clear_listen_targets(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<ListenTargetsReport>, String> {
+    let mut results = Vec::new();
+    let targets = update_listen_targets(&app, state.inner().clone(), |current| {
+        let names = current.iter().map(|target| target.name.clone()).collect();
+        let (next, outcome) = listen_targets::remove_listen_targets(current, names);
+        results = outcome;
+        results.iter().any(|result| result.ok).then_some(next)
+    })
+    .await;
+    Ok(listen_targets_report(targets, results))
+}
+
+fn listen_targets_report(
+    targets: Result<Vec<ListenTarget>, String>,
+    results: Vec<ListenTargetResult>,
+) -> ApiResponse<ListenTargetsReport> {
+    let targets = match targets {
+        Ok(targets) => targets,
+        Err(err) => return api_err(err),
+    };
+    info!(
+        "批量更新监听对象: ok={}, failed={}",
+        results.iter().filter(|result| result.ok).count(),
+        results.iter().filter(|result| !result.ok).count()
+    );
+    api_ok(ListenTargetsReport { targets, results })
+}
+
+async fn apply_listen_targets(
+    app: &AppHandle,
+    state: SharedState,
+    targets: Vec<ListenTarget>,
+) -> Result<Vec<ListenTarget>, String> {
+    update_listen_targets(app, state, |_| Some(targets)).await
+}
+
+/// Reads the current targets, applies `edit` and saves the result under one
+/// lock, so concurrent updates cannot overwrite each other. When `edit`
+/// returns `None` the targets are left as they are.
+async fn update_listen_targets(
+    app: &AppHandle,
+    state: SharedState,
+    edit: impl FnOnce(&[ListenTarget]) -> Option<Vec<ListenTarget>>,
+) -> Result<Vec<ListenTarget>, String> {
+    let (normalized, senders) = {
+        let mut guard = state.lock().await;
+        let Some(targets) = edit(&guard.listen_targets) else {
+            return Ok(guard.listen_targets.clone());
+        };
+        let mut normalized =
+            normalize_listen_targets(targets, MAX_LISTEN_TARGETS).map_err(|err| err.to_string())?;
+        listen_targets::resolve_target_kinds(&mut normalized, &guard.recent_chats.chats);
+        let mut next_config = guard.config.clone();
+        next_config.listen_targets = normalized.clone();
+        if let Err(err) = save_config(app, &next_config) {
+            warn!("保存监听对象失败: {}", err);
+            return Err(err.to_string());
+        }
+        guard.config = next_config;
+        guard.listen_targets = normalized.clone();
+        track_prompt_version(app, &mut guard, "监听对象");
+        let senders = guard
+            .agents()
+            .map(|agent| agent.clone_sender())
+            .collect::<Vec<_>>();
+        (normalized, senders)
+    };
+
+    let payload = ListenTargetsPayload {
+        targets: normalized.clone(),
+    };
+    let payload_value = serde_json::to_value(payload).map_err(|err| err.to_string())?;
+    for sender in senders {
+        let envelope = IpcEnvelope::new("listen.targets", payload_value.clone());
+        if let Err(err) = sender.send(envelope).await {
+            warn!("发送监听对象失败: {}", err);
+            return Err(err.to_string());
+        }
+    }
+
+    Ok(normalized)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_session_instruction(
+    state: State<'_, SharedState>,
+    chat_id: String,
+    text: String,
+    ttl_secs: u64,
+) -> Result<ApiResponse<()>, String> {
+    Ok(set_session_instruction_inner(state.inner().clone(), chat_id, text, ttl_secs).await)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_session_instructions(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Vec<SessionInstruction>>, String> {
+    let mut guard = state.lock().await;
+    Ok(api_ok(guard.session_instructions(now_secs())))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_chat_activity_stats(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Vec<ChatActivityStats>>, String> {
+    Ok(get_chat_activity_stats_inner(state.inner().clone()).await)
+}
+
+async fn get_chat_activity_stats_inner(state: SharedState) -> ApiResponse<Vec<ChatActivityStats>> {
+    let automation = state.lock().await.automation.clone();
+    let res = automation.chat_activity(now_secs()).await;
+    let Some(mut stats) = res.data.filter(|_| res.success) else {
+        warn!("统计会话活跃度失败: {}", res.message);
+        return api_err(res.message);
+    };
+    let guard = state.lock().await;
+    for item in &mut stats {
+        item.listened = guard
+            .listen_targets
+            .iter()
+            .any(|target| guard.chat_identities.resolve(&target.name) == item.chat_id);
+    }
+    api_ok(stats)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn search_messages(
+    state: State<'_, SharedState>,
+    query: String,
+    chat_id: Option<String>,
+) -> Result<ApiResponse<Vec<MessageSearchHit>>, String> {
+    Ok(search_messages_inner(state.inner().clone(), query, chat_id).await)
+}
+
+async fn search_messages_inner(
+    state: SharedState,
+    query: String,
+    chat_id: Option<String>,
+) -> ApiResponse<Vec<MessageSearchHit>> {
+    if query.trim().is_empty() {
+        return api_err("搜索内容不能为空");
+    }
+    let guard = state.lock().await;
+    let Some(history) = guard.history.as_ref() else {
+        return api_err("历史记录不可用");
+    };
+    let chat_id = chat_id
+        .filter(|chat_id| !chat_id.trim().is_empty())
+        .map(|chat_id| guard.chat_identities.resolve(&chat_id));
+    match history.search(&query, chat_id.as_deref()) {
+        Ok(hits) => api_ok(hits),
+        Err(err) => {
+            warn!("搜索历史消息失败: {}", err);
+            api_err(err.to_string())
+        }
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_suggestion_history(
+    state: State<'_, SharedState>,
+    chat_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<ApiResponse<Vec<SuggestionRecord>>, String> {
+    Ok(get_suggestion_history_inner(state.inner().clone(), chat_id, limit).await)
+}
+
+async fn get_suggestion_history_inner(
+    state: SharedState,
+    chat_id: Option<String>,
+    limit: Option<u32>,
+) -> ApiResponse<Vec<SuggestionRecord>> {
+    let guard = state.lock().await;
+    let Some(history) = guard.history.as_ref() else {
+        return api_err("历史记录不可用");
+    };
+    let chat_id = chat_id
+        .filter(|chat_id| !chat_id.trim().is_empty())
+        .map(|chat_id| guard.chat_identities.resolve(&chat_id));
+    let limit = limit
+        .unwrap_or(DEFAULT_SUGGESTION_HISTORY_LIMIT)
+        .clamp(1, MAX_SUGGESTION_HISTORY_LIMIT);
+    match history.suggestion_history(chat_id.as_deref(), limit) {
+        Ok(records) => api_ok(records),
+        Err(err) => {
+            warn!("读取建议记录失败: {}", err);
+            api_err(err.to_string())
+        }
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_suggestion_acceptance(
+    state: State<'_, SharedState>,
+    chat_id: Option<String>,
+    days: Option<u32>,
+) -> Result<ApiResponse<SuggestionAcceptance>, String> {
+    let guard = state.lock().await;
+    let Some(history) = guard.history.as_ref() else {
+        return Ok(api_err("历史记录不可用"));
+    };
+    let chat_id = chat_id
+        .filter(|chat_id| !chat_id.trim().is_empty())
+        .map(|chat_id| guard.chat_identities.resolve(&chat_id));
+    let days = days
+        .unwrap_or(DEFAULT_ACCEPTANCE_DAYS)
+        .clamp(1, MAX_ACCEPTANCE_DAYS);
+    let since = now_secs().saturating_sub(days as u64 * 86_400);
+    Ok(match history.acceptance(chat_id.as_deref(), since) {
+        Ok(acceptance) => api_ok(acceptance),
+        Err(err) => {
+            warn!("统计建议采纳失败: {}", err);
+            api_err(err.to_string())
+        }
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_experiment_report(
+    state: State<'_, SharedState>,
+    days: Option<u32>,
+) -> Result<ApiResponse<ExperimentReport>, String> {
+    let guard = state.lock().await;
+    let Some(history) = guard.history.as_ref() else {
+        return Ok(api_err("历史记录不可用"));
+    };
+    let experiment = &guard.config.prompt_experiment;
+    if experiment.name.is_empty() {
+        return Ok(api_err("尚未配置提示词实验"));
+    }
+    let days = days
+        .unwrap_or(DEFAULT_ACCEPTANCE_DAYS)
+        .clamp(1, MAX_ACCEPTANCE_DAYS);
+    let since = now_secs().saturating_sub(days as u64 * 86_400);
+    let acceptance = |variant| history.experiment_acceptance(&experiment.name, variant, since);
+    Ok(
+        match acceptance(ExperimentVariant::A)
+            .and_then(|a| Ok((a, acceptance(ExperimentVariant::B)?)))
+        {
+            Ok((a, b)) => api_ok(experiments::report(experiment, a, b)),
+            Err(err) => {
+                warn!("统计实验采纳失败: {}", err);
+                api_err(err.to_string())
+            }
+        },
+    )
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn compose_reply(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    chat_id: String,
+    fragments: Vec<String>,
+    style: Option<SuggestionStyle>,
+) -> Result<ApiResponse<Suggestion>, String> {
+    Ok(compose_reply_inner(&app, state.inner().clone(), chat_id, fragments, style).await)
+}
+
+async fn compose_reply_inner(
+    app: &AppHandle,
+    state: SharedState,
+    chat_id: String,
+    fragments: Vec<String>,
+    style: Option<SuggestionStyle>,
+) -> ApiResponse<Suggestion> {
+    if chat_id.trim().is_empty() {
+        return api_err("chat_id 不能为空");
+    }
+    let fragments = match normalize_compose_fragments(fragments) {
+        Ok(fragments) => fragments,
+        Err(message) => {
+            warn!("合并回复失败: {}", message);
+            return api_err(message);
+        }
+    };
+    let api_key = match ApiKeyManager::get_deepseek_api_key() {
+        Ok(key) => key,
+        Err(err) => return api_err(err.to_string()),
+    };
+    let (chat_id, request, config) = {
+        let mut guard = state.lock().await;
+        if guard.quota_exceeded(now_secs()) {
+            warn!("合并回复失败: 今日用量已达上限");
+            return api_err("今日 DeepSeek 用量已达上限");
+        }
+        let chat_id = guard.chat_identities.resolve(&chat_id);
+        let request = guard.suggestion_request(&chat_id, &chat_id, now_secs());
+        (chat_id, request, guard.config.clone())
+    };
+    let style = style.unwrap_or_else(SuggestionStyle::neutral);
+    if !styles::is_known(&style, &config.style_presets) {
+        warn!("合并回复失败: 未知的回复风格 {}", style.as_str());
+        return api_err("未知的回复风格");
+    }
+    let started = Instant::now();
+    let result = deepseek::compose_reply(&config, &api_key, &request, &fragments, style).await;
+    let tokens = result.as_ref().map(|(_, tokens)| *tokens).unwrap_or(0);
+    state.lock().await.record_usage(tokens, now_secs());
+    let suggestion = match result {
+        Ok((suggestion, _)) => suggestion,
+        Err(err) => {
+            warn!("合并回复失败: {}", err);
+            return api_err(err.to_string());
+        }
+    };
+    let screened = SafetyFilter::new(&config.safety_rules).screen(vec![suggestion]);
+    let Some(suggestion) = screened.suggestions.into_iter().next() else {
+        warn!("合并回复命中安全过滤，已拦截: {}", chat_id);
+        return api_err("合并后的回复命中安全过滤，已拦截");
+    };
+    info!(
+        "合并回复完成: chat_id={}, fragments={}",
+        chat_id,
+        fragments.len()
+    );
+    let record = message_pipeline::suggestion_record(
+        &chat_id,
+        &request,
+        &config.deepseek_model,
+        started,
+        vec![suggestion.clone()],
+    );
+    let updated = {
+        let mut guard = state.lock().await;
+        guard.record_suggestions(&record);
+        let mut updated = match guard.latest_suggestions.clone() {
+            Some(latest) if latest.chat_id == chat_id => latest,
+            _ => SuggestionsUpdated {
+                chat_id: chat_id.clone(),
+                suggestions: Vec::new(),
+                reply_source: guard.reply_source(&chat_id),
+                warnings: Vec::new(),
+                best_pick: false,
+            },
+        };
+        updated.suggestions.push(suggestion.clone());
+        updated.warnings.extend(screened.warnings);
+        guard.latest_suggestions = Some(updated.clone());
+        updated
+    };
+    let _ = app.emit("suggestions.updated", updated);
+    api_ok(suggestion)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn generate_handover_brief(
+    state: State<'_, SharedState>,
+    chat_id: String,
+    output_path: Option<String>,
+) -> Result<ApiResponse<HandoverBrief>, String> {
+    if chat_id.trim().is_empty() {
+        return Ok(api_err("chat_id 不能为空"));
+    }
+    let api_key = match ApiKeyManager::get_deepseek_api_key() {
+        Ok(key) => key,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    let now = now_secs();
+    let (chat_id, messages, config) = {
+        let guard = state.lock().await;
+        if guard.quota_exceeded(now) {
+            return Ok(api_err("今日 DeepSeek 用量已达上限"));
+        }
+        let Some(history) = guard.history.as_ref() else {
+            return Ok(api_err("历史记录不可用"));
+        };
+        let chat_id = guard.chat_identities.resolve(&chat_id);
+        let messages = match history.messages_until(&chat_id, now, handover::MAX_HANDOVER_MESSAGES)
+        {
+            Ok(messages) => messages,
+            Err(err) => {
+                warn!("读取历史记录失败: {}", err);
+                return Ok(api_err(err.to_string()));
+            }
+        };
+        (chat_id, messages, guard.config.clone())
+    };
+    if messages.is_empty() {
+        return Ok(api_err("该会话暂无历史消息"));
+    }
+    let result = handover::generate(&config, &api_key, &chat_id, &messages, now).await;
+    let tokens = result.as_ref().map(|(_, tokens)| *tokens).unwrap_or(0);
+    state.lock().await.record_usage(tokens, now_secs());
+    let mut brief = match result {
+        Ok((brief, _)) => brief,
+        Err(err) => {
+            warn!("生成交接摘要失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+    };
+    if let Some(path) = output_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+    {
+        let path = std::path::PathBuf::from(path);
+        if let Some(parent) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(parent) {
+                return Ok(api_err(format!("创建目录失败: {}", err)));
+            }
+        }
+        if let Err(err) = std::fs::write(&path, &brief.markdown) {
+            warn!("写入交接摘要失败: {}", err);
+            return Ok(api_err(format!("写入交接摘要失败: {}", err)));
+        }
+        brief.saved_to = Some(path.to_string_lossy().to_string());
+    }
+    info!(
+        "已生成交接摘要: chat_id={}, messages={}",
+        chat_id, brief.message_count
+    );
+    Ok(api_ok(brief))
+}
+
+fn normalize_compose_fragments(fragments: Vec<String>) -> Result<Vec<String>, &'static str> {
+    let fragments: Vec<String> = fragments
+        .into_iter()
+        .map(|fragment| fragment.trim().to_string())
+        .filter(|fragment| !fragment.is_empty())
+        .collect();
+    if fragments.len() < MIN_COMPOSE_FRAGMENTS {
+        return Err("请至少选择两段内容");
+    }
+    if fragments.len() > MAX_COMPOSE_FRAGMENTS {
+        return Err("最多合并 5 段内容");
+    }
+    let total: usize = fragments
+        .iter()
+        .map(|fragment| fragment.chars().count())
+        .sum();
+    if total > MAX_COMPOSE_CHARS {
+        return Err("待合并内容过长");
+    }
+    Ok(fragments)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn backtest_prompts(
+    state: State<'_, SharedState>,
+    chat_id: String,
+    range: Option<BacktestRange>,
+    template_id: Option<String>,
+) -> Result<ApiResponse<BacktestReport>, String> {
+    let range = range.unwrap_or_default();
+    Ok(backtest_prompts_inner(state.inner().clone(), chat_id, range, template_id).await)
+}
+
+async fn backtest_prompts_inner(
+    state: SharedState,
+    chat_id: String,
+    range: BacktestRange,
+    template_id: Option<String>,
+) -> ApiResponse<BacktestReport> {
+    if chat_id.trim().is_empty() {
+        return api_err("chat_id 不能为空");
+    }
+    let template_id = template_id
+        .map(|template_id| template_id.trim().to_string())
+        .filter(|template_id| !template_id.is_empty())
+        .unwrap_or_else(|| backtest::CURRENT_TEMPLATE.to_string());
+    let now = now_secs();
+    let since = range.since.unwrap_or(0);
+    let until = range.until.unwrap_or(now);
+    if since > until {
+        return api_err("回放时间范围无效");
+    }
+    let limit = range
+        .limit
+        .unwrap_or(backtest::DEFAULT_BACKTEST_CASES)
+        .clamp(1, backtest::MAX_BACKTEST_CASES);
+    let api_key = match ApiKeyManager::get_deepseek_api_key() {
+        Ok(key) => key,
+        Err(err) => return api_err(err.to_string()),
+    };
+    let (chat_id, config, cases) = {
+        let guard = state.lock().await;
+        let Some(history) = guard.history.as_ref() else {
+            return api_err("历史记录不可用");
+        };
+        let chat_id = guard.chat_identities.resolve(&chat_id);
+        let prompt_override =
+            match backtest::resolve_template(&template_id, &guard.listen_targets, &chat_id) {
+                Ok(prompt_override) => prompt_override,
+                Err(message) => return api_err(message),
+            };
+        let fetch = limit * 2 + guard.config.context_max_messages;
+        let loaded = history
+            .messages_until(&chat_id, until, fetch)
+            .and_then(|messages| {
+                let records = history.suggestion_sets_between(&chat_id, since, now)?;
+                Ok((messages, records))
+            });
+        let (messages, records) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                warn!("读取回放数据失败: {}", err);
+                return api_err(err.to_string());
+            }
+        };
+        let cases = backtest::plan_cases(
+            &guard.config,
+            &messages,
+            &records,
+            since,
+            limit as usize,
+            prompt_override,
+        );
+        (chat_id, guard.config.clone(), cases)
+    };
+    if cases.is_empty() {
+        return api_err("所选范围内没有可回放的消息");
+    }
+    info!(
+        "开始回放提示词: chat_id={}, template={}, cases={}",
+        chat_id,
+        template_id,
+        cases.len()
+    );
+    let mut report = BacktestReport {
+        chat_id,
+        template_id,
+        model: config.deepseek_model.clone(),
+        cases: Vec::with_capacity(cases.len()),
+        total_tokens: 0,
+    };
+    for case in cases {
+        if state.lock().await.quota_exceeded(now_secs()) {
+            report
+                .cases
+                .push(backtest::skipped_case(case, "今日 DeepSeek 用量已达上限"));
+            continue;
+        }
+        let (item, result) = backtest::replay_case(&config, &api_key, case).await;
+        message_pipeline::track_usage(&state, &result).await;
+        report.total_tokens += result
+            .as_ref()
+            .map(|generated| generated.total_tokens)
+            .unwrap_or(0);
+        report.cases.push(item);
+    }
+    api_ok(report)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn seed_context(
+    state: State<'_, SharedState>,
+    chat_id: String,
+    transcript_text: String,
+) -> Result<ApiResponse<SeedContextResult>, String> {
+    Ok(seed_context_inner(state.inner().clone(), chat_id, transcript_text).await)
+}
+
+async fn seed_context_inner(
+    state: SharedState,
+    chat_id: String,
+    transcript_text: String,
+) -> ApiResponse<SeedContextResult> {
+    if chat_id.trim().is_empty() {
+        return api_err("chat_id 不能为空");
+    }
+    if transcript_text.trim().is_empty() {
+        return api_err("聊天记录不能为空");
+    }
+    if transcript_text.chars().count() > MAX_TRANSCRIPT_CHARS {
+        return api_err("聊天记录过长");
+    }
+    let messages = transcript::parse_transcript(&transcript_text, chat_id.trim());
+    if messages.is_empty() {
+        warn!("导入聊天记录失败: 未识别到消息");
+        return api_err("未识别到聊天记录，请确认包含发送者");
+    }
+    let group = messages
+        .iter()
+        .filter(|message| !message.from_self)
+        .map(|message| message.speaker.as_str())
+        .collect::<std::collections::HashSet<_>>()
+        .len()
+        > 1;
+    let total = messages.len();
+    let self_messages = messages.iter().filter(|message| message.from_self).count();
+    let seeded = messages
+        .into_iter()
+        .enumerate()
+        .map(|(index, message)| {
+            let text = if message.from_self {
+                format!("我：{}", message.text)
+            } else if group {
+                format!("{}：{}", message.speaker, message.text)
+            } else {
+                message.text
+            };
+            let offset_secs = message.offset_secs.unwrap_or((total - 1 - index) as u64);
+            (text, offset_secs)
+        })
+        .collect();
+    let mut guard = state.lock().await;
+    let chat_id = guard.chat_identities.resolve(&chat_id);
+    let kept = guard.seed_conversation(&chat_id, seeded, now_secs());
+    info!(
+        "已导入聊天记录: chat_id={}, parsed={}, kept={}",
+        chat_id, total, kept
+    );
+    api_ok(SeedContextResult {
+        parsed: total as u32,
+        kept: kept as u32,
+        self_messages: self_messages as u32,
+    })
+}
+
+async fn set_session_instruction_inner(
+    state: SharedState,
+    chat_id: String,
+    text: String,
+    ttl_secs: u64,
+) -> ApiResponse<()> {
+    if chat_id.trim().is_empty() {
+        return api_err("chat_id 不能为空");
+    }
+    let text = text.trim().to_string();
+    let mut guard = state.lock().await;
+    let chat_id = guard.chat_identities.resolve(&chat_id);
+    if text.is_empty() || ttl_secs == 0 {
+        if guard.remove_session_instruction(&chat_id) {
+            info!("已清除会话临时指令: chat_id={}", chat_id);
         }
-        return Ok(res);
+        return api_ok(());
     }
-
-    if let Err(err) =
-        send_listen_control(state.inner().clone(), "listen.stop", false, false).await
-    {
-        warn!("发送停止监听指令失败: {}", err);
-        return Ok(api_err(err));
+    if text.chars().count() > MAX_SESSION_INSTRUCTION_CHARS {
+        return api_err("临时指令过长");
     }
-    set_runtime_state(&app, state.inner().clone(), RuntimeState::Idle, "").await;
-    info!("监听已停止");
-    Ok(api_ok(()))
+    if ttl_secs > MAX_SESSION_INSTRUCTION_TTL_SECS {
+        return api_err("临时指令有效期不能超过 7 天");
+    }
+    guard.set_session_instruction(SessionInstruction {
+        chat_id: chat_id.clone(),
+        text,
+        expires_at: now_secs() + ttl_secs,
+    });
+    info!(
+        "已设置会话临时指令: chat_id={}, ttl_secs={}",
+        chat_id, ttl_secs
+    );
+    api_ok(())
 }
 
+/// Cached avatar of a chat (by id or title), inline as base64 or, with
+/// `as_file`, written to a temp file. `None` when the backend has no avatar.
 #[tauri::command]
 #[specta::specta]
-async fn pause_listening(
-    app: AppHandle,
+async fn get_chat_avatar(
     state: State<'_, SharedState>,
-) -> Result<ApiResponse<()>, String> {
-    info!("收到暂停监听请求");
-    let automation = {
+    chat_id: String,
+    as_file: Option<bool>,
+) -> Result<ApiResponse<Option<ChatAvatar>>, String> {
+    let chat_id = chat_id.trim().to_string();
+    if chat_id.is_empty() {
+        return Ok(api_err("会话 ID 不能为空"));
+    }
+    let (automation, chat_id) = {
         let guard = state.lock().await;
-        guard.automation.clone()
+        (guard.automation.clone(), guard.chat_identities.resolve(&chat_id))
     };
-    if automation.is_ready() {
-        stop_automation_polling(state.inner().clone()).await;
-        set_runtime_state(&app, state.inner().clone(), RuntimeState::Paused, "").await;
-        info!("监听已暂停");
-        return Ok(api_ok(()));
+    let res = automation.chat_avatar(chat_id.clone()).await;
+    if !res.success {
+        return Ok(api_err(res.message));
     }
-    if let Err(err) =
-        send_listen_control(state.inner().clone(), "listen.pause", false, false).await
-    {
-        warn!("发送暂停监听指令失败: {}", err);
-        return Ok(api_err(err));
+    let Some(bytes) = res.data.flatten() else {
+        return Ok(api_ok(None));
+    };
+    let dir = as_file.unwrap_or(false).then(avatar::avatar_dir);
+    match avatar::chat_avatar(&chat_id, &bytes, dir.as_deref()) {
+        Ok(avatar) => Ok(api_ok(avatar)),
+        Err(err) => {
+            warn!("读取会话头像失败: {}", err);
+            Ok(api_err(err.to_string()))
+        }
     }
-    set_runtime_state(&app, state.inner().clone(), RuntimeState::Paused, "").await;
-    info!("监听已暂停");
-    Ok(api_ok(()))
 }
 
 #[tauri::command]
 #[specta::specta]
-async fn resume_listening(
+async fn list_chats(
     app: AppHandle,
     state: State<'_, SharedState>,
-) -> Result<ApiResponse<()>, String> {
-    info!("收到恢复监听请求");
-    {
-        let guard = state.lock().await;
-        if guard.listen_targets.is_empty() {
-            warn!("未设置监听对象，拒绝恢复监听");
-            return Ok(api_err("请先设置监听对象"));
-        }
+    offset: Option<u32>,
+    limit: Option<u32>,
+    query: Option<String>,
+) -> Result<ApiResponse<RecentChats>, String> {
+    let limit = limit.unwrap_or(DEFAULT_CHAT_PAGE).clamp(1, MAX_CHAT_PAGE);
+    let query = ChatQuery::new(
+        offset.unwrap_or(0) as usize,
+        limit as usize,
+        query.as_deref().unwrap_or_default(),
+    );
+    // Only the first unfiltered page is cached; later pages and searches go
+    // straight to the backend.
+    if query.offset > 0 || !query.query.is_empty() {
+        let res = list_chats_inner(state.inner().clone(), query).await?;
+        return Ok(match res.data {
+            Some(page) if res.success => api_ok(RecentChats {
+                chats: page.chats,
+                has_more: page.has_more,
+                fetched_at: now_secs(),
+                stale: false,
+                refreshing: false,
+            }),
+            _ => api_err(res.message),
+        });
     }
-    let automation = {
-        let guard = state.lock().await;
-        guard.automation.clone()
-    };
-    if automation.is_ready() {
-        let targets = {
-            let guard = state.lock().await;
-            guard.listen_targets.clone()
-        };
-        let res = automation.start_listening(targets).await;
-        if res.success {
-            start_automation_polling(app.clone(), state.inner().clone()).await;
-            set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
+    let now = now_secs();
+    let cached = {
+        let mut guard = state.lock().await;
+        if !guard.recent_chats.covers(query.limit) {
+            None
+        } else {
+            let refresh = guard.automation.refreshes_chats_in_background()
+                && guard.recent_chats.claim_refresh(now);
+            Some((guard.recent_chats.snapshot(now, query.limit), refresh))
         }
-        return Ok(res);
-    }
-    if let Err(err) =
-        send_listen_control(state.inner().clone(), "listen.resume", true, true).await
-    {
-        warn!("发送恢复监听指令失败: {}", err);
-        return Ok(api_err(err));
+    };
+    let Some((snapshot, refresh)) = cached else {
+        return refresh_recent_chats(&app, state.inner().clone(), query).await;
+    };
+    if refresh {
+        let app = app.clone();
+        let state = state.inner().clone();
+        tauri::async_runtime::spawn(async move {
+            let res = refresh_recent_chats(&app, state.clone(), query).await;
+            state.lock().await.recent_chats.refreshing = false;
+            match res {
+                Ok(res) if res.success => {
+                    if let Some(snapshot) = res.data {
+                        let _ = app.emit("chats.updated", snapshot);
+                    }
+                }
+                Ok(res) => warn!("后台刷新会话列表失败: {}", res.message),
+                Err(err) => warn!("后台刷新会话列表失败: {}", err),
+            }
+        });
     }
-    set_runtime_state(&app, state.inner().clone(), RuntimeState::Listening, "").await;
-    info!("监听已恢复");
-    Ok(api_ok(()))
+    Ok(api_ok(snapshot))
 }
 
+/// Re-reads the first page of chats, bypassing the cache. With UI automation
+/// this is the only way the session list gets scrolled again.
 #[tauri::command]
 #[specta::specta]
-async fn get_listen_targets(
+async fn refresh_chats(
+    app: AppHandle,
     state: State<'_, SharedState>,
-) -> Result<ApiResponse<Vec<ListenTarget>>, String> {
-    let guard = state.lock().await;
-    Ok(api_ok(guard.listen_targets.clone()))
+    limit: Option<u32>,
+) -> Result<ApiResponse<RecentChats>, String> {
+    let limit = limit.unwrap_or(DEFAULT_CHAT_PAGE).clamp(1, MAX_CHAT_PAGE);
+    let query = ChatQuery::new(0, limit as usize, "");
+    refresh_recent_chats(&app, state.inner().clone(), query).await
 }
 
-#[tauri::command]
-#[specta::specta]
-async fn set_listen_targets(
-    app: AppHandle,
-    state: State<'_, SharedState>,
-    targets: Vec<ListenTarget>,
-) -> Result<ApiResponse<()>, String> {
-    let normalized = match normalize_listen_targets(targets, MAX_LISTEN_TARGETS) {
-        Ok(targets) => targets,
-        Err(err) => return Ok(api_err(err.to_string())),
+async fn refresh_recent_chats(
+    app: &AppHandle,
+    state: SharedState,
+    query: ChatQuery,
+) -> Result<ApiResponse<RecentChats>, String> {
+    let res = list_chats_inner(state.clone(), query.clone()).await?;
+    let page = match res.data {
+        Some(page) if res.success => page,
+        _ => return Ok(api_err(res.message)),
     };
-
-    let sender = {
+    let chats = page.chats.clone();
+    let now = now_secs();
+    let (snapshot, cache, resolver) = {
         let mut guard = state.lock().await;
-        let mut next_config = guard.config.clone();
-        next_config.listen_targets = normalized.clone();
-        if let Err(err) = save_config(&app, &next_config) {
-            warn!("保存监听对象失败: {}", err);
-            return Ok(api_err(err.to_string()));
-        }
-        guard.config = next_config;
-        guard.listen_targets = normalized.clone();
-        guard.agent.as_ref().map(|agent| agent.clone_sender())
+        guard.recent_chats.update(page, now);
+        let resolver = guard
+            .learn_chat_identities(&chats)
+            .then(|| guard.chat_identities.clone());
+        (
+            guard.recent_chats.snapshot(now, query.limit),
+            guard.recent_chats.clone(),
+            resolver,
+        )
     };
-
-    if let Some(sender) = sender {
-        let payload = ListenTargetsPayload {
-            targets: normalized,
-        };
-        let payload_value = serde_json::to_value(payload).map_err(|err| err.to_string())?;
-        if let Err(err) = sender.send(IpcEnvelope::new("listen.targets", payload_value)).await {
-            warn!("发送监听对象失败: {}", err);
-            return Ok(api_err(err.to_string()));
+    if let Err(err) = save_chat_list_cache(app, &cache) {
+        warn!("保存会话列表缓存失败: {}", err);
+    }
+    if let Some(resolver) = resolver {
+        if let Err(err) = save_chat_identities(app, &resolver) {
+            warn!("保存会话映射失败: {}", err);
         }
     }
-
-    Ok(api_ok(()))
-}
-
-#[tauri::command]
-#[specta::specta]
-async fn list_recent_chats(
-    state: State<'_, SharedState>,
-) -> Result<ApiResponse<Vec<ChatSummary>>, String> {
-    list_recent_chats_inner(state.inner().clone()).await
+    Ok(api_ok(snapshot))
 }
 
 #[tauri::command]
@@ -334,6 +1824,156 @@ async fn export_wechat_ui_tree(
     }
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn run_maintenance(
+    app: AppHandle,
+    dry_run: Option<bool>,
+) -> Result<ApiResponse<MaintenanceReport>, String> {
+    let dirs = maintenance::MaintenanceDirs::from_app(&app);
+    let dry_run = dry_run.unwrap_or(true);
+    let result = tokio::task::spawn_blocking(move || {
+        maintenance::run_maintenance(&dirs, dry_run, std::time::SystemTime::now())
+    })
+    .await;
+    Ok(match result {
+        Ok(report) => {
+            info!(
+                "清理检查完成: dry_run={}, items={}, reclaimed={}",
+                report.dry_run,
+                report.items.len(),
+                report.reclaimed_bytes
+            );
+            api_ok(report)
+        }
+        Err(err) => {
+            warn!("清理任务失败: {}", err);
+            api_err(format!("清理任务失败: {}", err))
+        }
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_automation_metrics(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<AutomationMetrics>, String> {
+    let automation = state.lock().await.automation.clone();
+    Ok(api_ok(automation.metrics()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn cipher_self_test(app: AppHandle) -> Result<ApiResponse<CipherSelfTest>, String> {
+    let scratch_dir = match app.path().app_cache_dir() {
+        Ok(dir) => dir,
+        Err(err) => return Ok(api_err(format!("无法获取缓存目录: {}", err))),
+    };
+    let result = tokio::task::spawn_blocking(move || sqlcipher::self_test(&scratch_dir)).await;
+    Ok(match result {
+        Ok(report) => {
+            info!(
+                "SQLCipher 自检: version={:?}, roundtrip={}, cli={:?}",
+                report.cipher_version, report.roundtrip_ok, report.cli_path
+            );
+            api_ok(report)
+        }
+        Err(err) => api_err(format!("SQLCipher 自检失败: {}", err)),
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn export_decrypted_db(
+    app: AppHandle,
+    db_path: String,
+    key: String,
+    output_path: Option<String>,
+    compatibility: Option<u32>,
+) -> Result<ApiResponse<DecryptExport>, String> {
+    let source = std::path::PathBuf::from(db_path.trim());
+    let output = match output_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+    {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let stem = source
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "wechat".to_string());
+            match app.path().app_data_dir() {
+                Ok(dir) => dir.join("decrypted").join(format!("{}_plain.db", stem)),
+                Err(err) => return Ok(api_err(format!("无法获取数据目录: {}", err))),
+            }
+        }
+    };
+    let result = tokio::task::spawn_blocking(move || {
+        sqlcipher::export_decrypted(&source, &output, &key, compatibility)
+    })
+    .await;
+    Ok(match result {
+        Ok(Ok(export)) => api_ok(export),
+        Ok(Err(err)) => {
+            warn!("解密导出失败: {}", err);
+            api_err(err)
+        }
+        Err(err) => api_err(format!("解密导出失败: {}", err)),
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn export_automation_trace(
+    app: AppHandle,
+    output_path: Option<String>,
+) -> Result<ApiResponse<AutomationTraceExport>, String> {
+    Ok(export_automation_trace_inner(&app, output_path))
+}
+
+fn export_automation_trace_inner(
+    app: &AppHandle,
+    output_path: Option<String>,
+) -> ApiResponse<AutomationTraceExport> {
+    if !ui_automation::trace::is_enabled() {
+        return api_err("未开启自动化追踪");
+    }
+    let entries = ui_automation::trace::snapshot();
+    let json = match serde_json::to_string_pretty(&entries) {
+        Ok(json) => json,
+        Err(err) => return api_err(format!("序列化自动化追踪失败: {}", err)),
+    };
+    let path = match output_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+    {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match app.path().app_log_dir() {
+            Ok(dir) => dir.join(AUTOMATION_TRACE_FILE),
+            Err(err) => return api_err(format!("无法获取日志目录: {}", err)),
+        },
+    };
+    if let Some(parent) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(parent) {
+            return api_err(format!("创建目录失败: {}", err));
+        }
+    }
+    if let Err(err) = std::fs::write(&path, &json) {
+        warn!("写入自动化追踪失败: {}", err);
+        return api_err(format!("写入自动化追踪失败: {}", err));
+    }
+    info!(
+        "已导出自动化追踪: {} 条 -> {}",
+        entries.len(),
+        path.display()
+    );
+    api_ok(AutomationTraceExport {
+        json,
+        entries: entries.len() as u32,
+        saved_to: Some(path.display().to_string()),
+    })
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn learn_wechat_ui_paths(
@@ -466,101 +2106,242 @@ async fn get_wechat_ui_paths_status(
     }
 }
 
-async fn list_recent_chats_inner(
+#[tauri::command]
+#[specta::specta]
+async fn get_locator_diagnostics() -> Result<ApiResponse<Vec<LocatorDiagnostic>>, String> {
+    #[cfg(not(target_os = "windows"))]
+    {
+        Ok(api_err("仅支持 Windows"))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Ok(api_ok(ui_automation::windows::locator::diagnostics()))
+    }
+}
+
+async fn list_chats_inner(
     state: SharedState,
-) -> Result<ApiResponse<Vec<ChatSummary>>, String> {
+    query: ChatQuery,
+) -> Result<ApiResponse<ChatPage>, String> {
     let automation = {
         let guard = state.lock().await;
         guard.automation.clone()
     };
-    if automation.is_ready() {
-        let res = automation.list_recent_chats().await;
-        if res.success {
-            if let Some(chats) = res.data.clone() {
-                let mut guard = state.lock().await;
-                guard.recent_chats = chats;
+    match automation.strategy() {
+        AutomationStrategy::Ui | AutomationStrategy::Db => {
+            let res = automation.list_chats(query.clone()).await;
+            if res.success || !automation.falls_back_to_agent() {
+                return Ok(res);
             }
+            warn!(
+                "{:?} 方式获取会话列表失败，改用 Agent: {}",
+                automation.strategy(),
+                res.message
+            );
         }
-        return Ok(res);
+        AutomationStrategy::Agent => {}
     }
 
-    let request_id = Uuid::new_v4().to_string();
-    let (sender, receiver) = {
-        let mut guard = state.lock().await;
-        if guard.pending_chats_list.is_some() {
-            return Ok(api_err("已有会话列表请求进行中"));
+    let requesters: Vec<_> = {
+        let guard = state.lock().await;
+        guard
+            .agents()
+            .map(|agent| (agent.account_id().to_string(), agent.requester()))
+            .collect()
+    };
+    if requesters.is_empty() {
+        return Ok(api_err("Agent 未连接"));
+    }
+
+    let mut chats = Vec::new();
+    for (account_id, requester) in requesters {
+        let request_id = Uuid::new_v4().to_string();
+        let payload_value =
+            serde_json::to_value(ChatsListPayload { request_id: request_id.clone() })
+                .map_err(|err| err.to_string())?;
+        match requester
+            .request::<ChatsListResultPayload>("chats.list", &request_id, payload_value)
+            .await
+        {
+            Ok(result) => chats.extend(result.chats.into_iter().map(|chat| ChatSummary {
+                account_id: account_id.clone(),
+                kind: chat_kind::or_from_title(chat.kind, &chat.chat_title),
+                ..chat
+            })),
+            Err(err) if account_id.is_empty() => {
+                warn!("会话列表获取失败: {}", err);
+                return Ok(api_err(err.to_string()));
+            }
+            Err(err) => warn!("账号 {} 的会话列表获取失败: {}", account_id, err),
+        }
+    }
+    // The Agent always returns its whole list, so page it here.
+    Ok(api_ok(query.page(chats)))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn write_suggestion(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    chat_id: String,
+    text: String,
+    reply_mode: Option<ReplyMode>,
+) -> Result<ApiResponse<()>, String> {
+    let reply_mode = reply_mode.unwrap_or_default();
+    let state = state.inner().clone();
+    Ok(write_suggestion_inner(&app, state, chat_id, text, reply_mode, false).await)
+}
+
+async fn write_suggestion_inner(
+    app: &AppHandle,
+    state: SharedState,
+    chat_id: String,
+    text: String,
+    reply_mode: ReplyMode,
+    send: bool,
+) -> ApiResponse<()> {
+    if chat_id.trim().is_empty() {
+        warn!("写入建议失败: chat_id 为空");
+        return api_err("chat_id 不能为空");
+    }
+    if text.trim().is_empty() {
+        warn!("写入建议失败: 回复内容为空");
+        return api_err("回复内容不能为空");
+    }
+    if graphemes::count(&text) > graphemes::MAX_WRITE_GRAPHEMES {
+        warn!("写入建议失败: 回复内容过长");
+        return api_err("回复内容过长");
+    }
+
+    let (automation, write_queue, reply_source) = {
+        let guard = state.lock().await;
+        (
+            guard.automation.clone(),
+            guard.write_queue.clone(),
+            guard.reply_source(&chat_id),
+        )
+    };
+    let suggestion_text = text.clone();
+    let plan = match reply::plan_reply(reply_mode, reply_source, text, !automation.is_ready()) {
+        Ok(plan) => plan,
+        Err(message) => {
+            warn!("写入建议失败: {}", message);
+            return api_err(message);
         }
-        let sender = match guard.agent.as_ref() {
-            Some(agent) => agent.clone_sender(),
-            None => return Ok(api_err("Agent 未连接")),
+    };
+    let ticket = write_queue.enqueue(&chat_id);
+    if ticket.position > 0 {
+        info!(
+            "写入请求排队: chat_id={}, position={}",
+            chat_id, ticket.position
+        );
+        emit_input_result(app, &chat_id, InputWriteStatus::Queued, ticket.position, "");
+    }
+    let _exclusive = ticket.acquire().await;
+    let mut attempt = 1;
+    let res = loop {
+        let write = if automation.is_ready() {
+            let res = if send {
+                automation
+                    .send_input(chat_id.clone(), plan.text.clone())
+                    .await
+            } else {
+                automation
+                    .write_input(chat_id.clone(), plan.text.clone())
+                    .await
+            };
+            AgentWrite {
+                rejected: !res.success && is_busy_rejection(&res.message),
+                res,
+            }
+        } else {
+            write_input_via_agent(
+                &state,
+                chat_id.clone(),
+                plan.text.clone(),
+                plan.quote.clone(),
+                send,
+            )
+            .await
         };
-        let (tx, rx) = oneshot::channel();
-        guard.pending_chats_list = Some((request_id.clone(), tx));
-        (sender, rx)
+        let AgentWrite { res, rejected } = write;
+        if res.success || !rejected || attempt >= WRITE_MAX_ATTEMPTS {
+            break res;
+        }
+        warn!(
+            "写入失败，准备重试: chat_id={}, error={}",
+            chat_id, res.message
+        );
+        emit_input_result(
+            app,
+            &chat_id,
+            InputWriteStatus::Retrying,
+            ticket.position,
+            &res.message,
+        );
+        tokio::time::sleep(Duration::from_millis(WRITE_RETRY_DELAY_MS)).await;
+        attempt += 1;
     };
-
-    let payload_value =
-        serde_json::to_value(ChatsListPayload { request_id: request_id.clone() })
-            .map_err(|err| err.to_string())?;
-    if let Err(err) = sender.send(IpcEnvelope::new("chats.list", payload_value)).await {
+    let status = if res.success {
         let mut guard = state.lock().await;
-        guard.pending_chats_list = None;
-        warn!("发送会话列表请求失败: {}", err);
-        return Ok(api_err(err.to_string()));
+        guard.mark_suggestion_written(&chat_id, &suggestion_text);
+        guard.record_outgoing(&chat_id, &suggestion_text, now_secs());
+        InputWriteStatus::Written
+    } else {
+        InputWriteStatus::Failed
+    };
+    emit_input_result(app, &chat_id, status, ticket.position, &res.message);
+    res
+}
+
+async fn send_auto_reply(state: &SharedState, chat_id: String, text: String) -> ApiResponse<()> {
+    let (automation, write_queue) = {
+        let guard = state.lock().await;
+        (guard.automation.clone(), guard.write_queue.clone())
+    };
+    let ticket = write_queue.enqueue(&chat_id);
+    let _exclusive = ticket.acquire().await;
+    if automation.is_ready() {
+        automation.send_input(chat_id, text).await
+    } else {
+        write_input_via_agent(state, chat_id, text, None, true).await.res
     }
+}
 
-    match timeout(Duration::from_secs(3), receiver).await {
-        Ok(Ok(chats)) => Ok(api_ok(chats)),
-        Ok(Err(_)) => {
-            let mut guard = state.lock().await;
-            if matches!(guard.pending_chats_list.as_ref(), Some((pending_id, _)) if pending_id == &request_id) {
-                guard.pending_chats_list = None;
-            }
-            Ok(api_err("会话列表获取失败"))
-        }
-        Err(_) => {
-            let mut guard = state.lock().await;
-            if matches!(guard.pending_chats_list.as_ref(), Some((pending_id, _)) if pending_id == &request_id) {
-                guard.pending_chats_list = None;
-            }
-            Ok(api_err("会话列表请求超时"))
+/// 一次写入的结果。`rejected` 表示写入在开始前就被队列以 BUSY 拒绝，输入框没有被改动，
+/// 可以重试；其他失败可能发生在粘贴或回车之后，超时或连接断开时也无法确定是否已经写入，
+/// 重试可能粘贴或发送两次，因此直接报告失败。
+struct AgentWrite {
+    res: ApiResponse<()>,
+    rejected: bool,
+}
+
+impl AgentWrite {
+    fn no_retry(res: ApiResponse<()>) -> Self {
+        Self {
+            res,
+            rejected: false,
         }
     }
 }
 
-#[tauri::command]
-#[specta::specta]
-async fn write_suggestion(
-    state: State<'_, SharedState>,
+async fn write_input_via_agent(
+    state: &SharedState,
     chat_id: String,
     text: String,
-) -> Result<ApiResponse<()>, String> {
-    if chat_id.trim().is_empty() {
-        warn!("写入建议失败: chat_id 为空");
-        return Ok(api_err("chat_id 不能为空"));
-    }
-    if text.trim().is_empty() {
-        warn!("写入建议失败: 回复内容为空");
-        return Ok(api_err("回复内容不能为空"));
-    }
-    if text.len() > 2000 {
-        warn!("写入建议失败: 回复内容过长");
-        return Ok(api_err("回复内容过长"));
-    }
-
-    let automation = {
+    quote: Option<ReplySource>,
+    send: bool,
+) -> AgentWrite {
+    let request_id = Uuid::new_v4().to_string();
+    let requester = {
         let guard = state.lock().await;
-        guard.automation.clone()
-    };
-    if automation.is_ready() {
-        let res = automation.write_input(chat_id, text).await;
-        return Ok(res);
-    }
-
-    let guard = state.lock().await;
-    let Some(agent) = guard.agent.as_ref() else {
-        warn!("写入建议失败: Agent 未连接");
-        return Ok(api_err("Agent 未连接"));
+        let Some(agent) = guard.agent_for_chat(&chat_id) else {
+            warn!("写入建议失败: Agent 未连接");
+            return AgentWrite::no_retry(api_err("Agent 未连接"));
+        };
+        agent.requester()
     };
 
     let payload = InputWritePayload {
@@ -568,26 +2349,68 @@ async fn write_suggestion(
         text,
         mode: Some("paste".to_string()),
         restore_clipboard: Some(true),
+        quote,
+        request_id: Some(request_id.clone()),
+        send,
     };
     let payload_value = match serde_json::to_value(payload) {
         Ok(value) => value,
-        Err(err) => return Ok(api_err(err.to_string())),
+        Err(err) => return AgentWrite::no_retry(api_err(err.to_string())),
     };
-    if let Err(err) =
-        agent
-            .send(crate::ipc::IpcEnvelope::new("input.write", payload_value))
-            .await
+    match requester
+        .request::<InputResultPayload>("input.write", &request_id, payload_value)
+        .await
     {
-        warn!("写入建议失败: {}", err);
-        return Ok(api_err(err.to_string()));
+        Ok(result) if result.ok => {
+            info!("写入建议完成: request_id={}", request_id);
+            AgentWrite::no_retry(api_ok(()))
+        }
+        Ok(result) => {
+            warn!("写入建议失败: {}", result.error);
+            let rejected = is_busy_rejection(&result.error);
+            let message = if result.error.is_empty() {
+                "写入失败".to_string()
+            } else {
+                result.error
+            };
+            AgentWrite {
+                res: api_err(message),
+                rejected,
+            }
+        }
+        Err(err) => {
+            warn!("等待写入结果失败: request_id={}, {}", request_id, err);
+            AgentWrite::no_retry(api_err(err.to_string()))
+        }
     }
-    info!("写入建议完成");
-    Ok(api_ok(()))
+}
+
+fn is_busy_rejection(message: &str) -> bool {
+    message.starts_with(BUSY_CODE)
+}
+
+fn emit_input_result(
+    app: &AppHandle,
+    chat_id: &str,
+    status: InputWriteStatus,
+    queue_position: u32,
+    message: &str,
+) {
+    let _ = app.emit(
+        "input.result",
+        InputWriteResult {
+            chat_id: chat_id.to_string(),
+            status,
+            queue_position,
+            message: message.to_string(),
+        },
+    );
 }
 
 #[tauri::command]
 #[specta::specta]
 async fn save_api_key(
+    app: AppHandle,
     state: State<'_, SharedState>,
     api_key: String,
 ) -> Result<ApiResponse<()>, String> {
@@ -604,6 +2427,8 @@ async fn save_api_key(
     match deepseek::validate_api_key(&config, &api_key).await {
         Ok(()) => {
             info!("API 密钥验证成功");
+            state.lock().await.api_key_rejected = false;
+            readiness::refresh_readiness(app, state.inner().clone()).await;
             Ok(api_ok(()))
         }
         Err(err) => {
@@ -633,6 +2458,54 @@ async fn set_deepseek_model(
     Ok(api_ok(()))
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn set_hide_dock_icon(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+    hidden: bool,
+) -> Result<ApiResponse<()>, String> {
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (app, state, hidden);
+        return Ok(api_err("仅支持 macOS"));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut guard = state.lock().await;
+        let mut next_config = guard.config.clone();
+        next_config.hide_dock_icon = hidden;
+        if let Err(err) = save_config(&app, &next_config) {
+            warn!("保存 Dock 图标设置失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        guard.config = next_config;
+        if let Err(err) = menu_bar::apply_dock_icon(&app, hidden) {
+            warn!("切换 Dock 图标失败: {}", err);
+            return Ok(api_err(err.to_string()));
+        }
+        Ok(api_ok(()))
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_latest_suggestions(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Option<SuggestionsUpdated>>, String> {
+    let guard = state.lock().await;
+    Ok(api_ok(guard.latest_suggestions.clone()))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn frontend_heartbeat(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<Option<FrontendSync>>, String> {
+    Ok(api_ok(frontend_link::heartbeat(&state).await))
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn get_api_key_status() -> Result<ApiResponse<bool>, String> {
@@ -653,17 +2526,75 @@ async fn get_api_key() -> Result<ApiResponse<String>, String> {
 
 #[tauri::command]
 #[specta::specta]
-async fn delete_api_key() -> Result<ApiResponse<()>, String> {
+async fn delete_api_key(
+    app: AppHandle,
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<()>, String> {
     info!("删除 API 密钥");
     Ok(match ApiKeyManager::delete_deepseek_api_key() {
         Ok(()) => {
             info!("API 密钥已删除");
+            readiness::refresh_readiness(app, state.inner().clone()).await;
+            api_ok(())
+        }
+        Err(err) => api_err(err.to_string()),
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_transcription_api_key(api_key: String) -> Result<ApiResponse<()>, String> {
+    Ok(
+        match ApiKeyManager::set_transcription_api_key(api_key.trim()) {
+            Ok(()) => {
+                info!("语音转写密钥已更新");
+                api_ok(())
+            }
+            Err(err) => api_err(err.to_string()),
+        },
+    )
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_wechat_db_key(key: String) -> Result<ApiResponse<()>, String> {
+    Ok(match ApiKeyManager::set_wechat_db_key(key.trim()) {
+        Ok(()) => {
+            info!("微信数据库密钥已更新");
             api_ok(())
         }
         Err(err) => api_err(err.to_string()),
     })
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn acquire_wechat_db_key() -> Result<ApiResponse<DbKeyReport>, String> {
+    Ok(match tokio::task::spawn_blocking(wechat_db_key::acquire).await {
+        Ok(report) => api_ok(report),
+        Err(err) => api_err(format!("获取微信数据库密钥失败: {}", err)),
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_deepseek_balance(
+    state: State<'_, SharedState>,
+) -> Result<ApiResponse<DeepseekBalance>, String> {
+    let config = {
+        let guard = state.lock().await;
+        guard.config.clone()
+    };
+    let api_key = match ApiKeyManager::get_deepseek_api_key() {
+        Ok(key) => key,
+        Err(err) => return Ok(api_err(err.to_string())),
+    };
+    match deepseek::get_balance(&config, &api_key).await {
+        Ok(balance) => Ok(api_ok(balance)),
+        Err(err) => Ok(api_err(err.to_string())),
+    }
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn diagnose_deepseek(
@@ -693,12 +2624,17 @@ async fn ensure_agent_running(app: AppHandle, state: SharedState) -> anyhow::Res
         guard.agent.is_some()
     };
     if exists {
+        agent::sync_account_agents(&app, &state).await;
         return Ok(());
     }
-    match start_agent(app.clone(), state.clone()).await {
+    match start_agent(app.clone(), state.clone(), String::new()).await {
         Ok(agent) => {
-            let mut guard = state.lock().await;
-            guard.agent = Some(agent);
+            {
+                let mut guard = state.lock().await;
+                guard.agent = Some(agent);
+                guard.agent_stopped = false;
+            }
+            agent::sync_account_agents(&app, &state).await;
             Ok(())
         }
         Err(err) => {
@@ -714,15 +2650,21 @@ async fn send_listen_control(
     include_poll_interval: bool,
     include_targets: bool,
 ) -> Result<(), String> {
-    let (sender, poll_interval_ms, targets) = {
+    let (senders, poll_interval_ms, targets, download_images) = {
         let guard = state.lock().await;
-        let Some(agent) = guard.agent.as_ref() else {
+        if guard.agent.is_none() {
             return Err("Agent 未连接".to_string());
-        };
+        }
         (
-            agent.clone_sender(),
+            guard
+                .agents()
+                .map(|agent| agent.clone_sender())
+                .collect::<Vec<_>>(),
             if include_poll_interval {
-                Some(guard.config.poll_interval_ms)
+                Some(power::effective_poll_interval_ms(
+                    &guard.config,
+                    &guard.status.power,
+                ))
             } else {
                 None
             },
@@ -731,6 +2673,7 @@ async fn send_listen_control(
             } else {
                 None
             },
+            guard.config.image_ocr_enabled,
         )
     };
     info!(
@@ -744,12 +2687,16 @@ async fn send_listen_control(
     let payload = ListenControlPayload {
         poll_interval_ms,
         targets,
+        download_images: Some(download_images),
     };
     let payload_value = serde_json::to_value(payload).map_err(|err| err.to_string())?;
-    sender
-        .send(crate::ipc::IpcEnvelope::new(message_type, payload_value))
-        .await
-        .map_err(|err| err.to_string())
+    for sender in senders {
+        sender
+            .send(crate::ipc::IpcEnvelope::new(message_type, payload_value.clone()))
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(())
 }
 
 async fn set_runtime_state(
@@ -759,14 +2706,13 @@ async fn set_runtime_state(
     last_error: impl Into<String>,
 ) {
     let mut guard = state.lock().await;
-    guard.status.state = runtime;
-    guard.status.last_error = last_error.into();
+    guard.set_session_state(runtime, last_error);
     let _ = app.emit("status.changed", guard.status.clone());
 }
 
 async fn start_automation_polling(app: AppHandle, state: SharedState) {
     let (stop_tx, mut stop_rx) = watch::channel(false);
-    let (automation, config, targets) = {
+    let (automation, poll_interval_ms, targets, focus_required) = {
         let mut guard = state.lock().await;
         if let Some(stop) = guard.automation_stop.take() {
             let _ = stop.send(true);
@@ -774,45 +2720,89 @@ async fn start_automation_polling(app: AppHandle, state: SharedState) {
         guard.automation_stop = Some(stop_tx);
         (
             guard.automation.clone(),
-            guard.config.clone(),
+            power::effective_poll_interval_ms(&guard.config, &guard.status.power),
             guard.listen_targets.clone(),
+            guard.config.pause_when_wechat_unfocused,
         )
     };
     if !automation.is_ready() {
         return;
     }
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+        let mut interval = tokio::time::interval(Duration::from_millis(poll_interval_ms));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut db_watcher = db_watch::DbWatcher::default();
+        let mut presence_gate = wechat_presence::PresenceGate::default();
         loop {
+            let watching = db_watcher.sync(&automation.watch_paths());
             tokio::select! {
                 _ = stop_rx.changed() => {
                     if *stop_rx.borrow() {
                         break;
                     }
+                    continue;
+                }
+                _ = db_watcher.changed(), if watching => {
+                    // 数据库变化触发的读取也不超过轮询频率
+                    interval.tick().await;
+                }
+                _ = tokio::time::sleep(db_watch::IDLE_POLL), if watching => {}
+                _ = interval.tick(), if !watching && !presence_gate.is_suspended() => {}
+                _ = tokio::time::sleep(wechat_presence::CHECK_INTERVAL),
+                    if presence_gate.is_suspended() => {}
+            }
+            let presence = tokio::task::spawn_blocking(wechat_presence::detect)
+                .await
+                .unwrap_or(wechat_presence::Presence {
+                    running: true,
+                    focused: true,
+                });
+            match presence_gate.next(presence, focus_required) {
+                wechat_presence::Gate::Poll => {}
+                wechat_presence::Gate::Skip => continue,
+                wechat_presence::Gate::Suspend => {
+                    info!("微信已退出，暂停本地监听直到微信重新运行");
+                    let _ = automation.stop_listening().await;
+                    let mut guard = state.lock().await;
+                    guard.set_wechat_not_running();
+                    let _ = app.emit("status.changed", guard.status.clone());
+                    let _ = app.emit(
+                        "error.raised",
+                        ErrorPayload {
+                            code: wechat_presence::WECHAT_NOT_RUNNING.to_string(),
+                            message: guard.status.last_error.clone(),
+                            recoverable: true,
+                            suggested_action: None,
+                        },
+                    );
+                    continue;
                 }
-                _ = interval.tick() => {
-                    let res = automation.poll_latest_message().await;
+                wechat_presence::Gate::Resume => {
+                    let res = automation.start_listening(targets.clone()).await;
                     if !res.success {
+                        warn!("微信已运行，但恢复监听失败，稍后重试: {}", res.message);
+                        presence_gate.resume_failed();
                         continue;
                     }
-                    let Some(message) = res.data.flatten() else {
-                        continue;
-                    };
-                    if !should_handle_message(&message.chat_id, &targets) {
-                        continue;
-                    }
-                    let payload = crate::ipc::MessageNewPayload {
-                        chat_id: message.chat_id.clone(),
-                        chat_title: message.chat_id.clone(),
-                        is_group: infer_is_group(&message.chat_id, &targets),
-                        sender_name: String::new(),
-                        text: message.text.clone(),
-                        timestamp: message.timestamp,
-                        msg_id: message.msg_id.clone(),
-                    };
-                    crate::message_pipeline::handle_incoming_message(&app, &state, payload).await;
+                    info!("微信已重新运行，本地监听已恢复");
+                    set_runtime_state(&app, state.clone(), RuntimeState::Listening, "").await;
+                    continue;
                 }
             }
+            let res = automation.poll_new_messages().await;
+            if !res.success {
+                continue;
+            }
+            let polled = res.data.unwrap_or_default();
+            let (mut payloads, sent) = split_polled_messages(polled, &targets);
+            for payload in sent {
+                crate::message_pipeline::handle_outgoing_message(&app, &state, payload).await;
+            }
+            if payloads.len() > 1 {
+                crate::message_pipeline::handle_incoming_batch(&app, &state, payloads).await;
+            } else if let Some(payload) = payloads.pop() {
+                crate::message_pipeline::handle_incoming_message(&app, &state, payload).await;
+            }
         }
     });
 }
@@ -827,6 +2817,44 @@ async fn stop_automation_polling(state: SharedState) {
     }
 }
 
+/// Splits a poll of the local backend into received messages and the user's
+/// own sent messages, keeping only listened chats.
+fn split_polled_messages(
+    messages: Vec<IncomingMessage>,
+    targets: &[ListenTarget],
+) -> (Vec<crate::ipc::MessageNewPayload>, Vec<MessageSentPayload>) {
+    let mut received = Vec::new();
+    let mut sent = Vec::new();
+    for message in messages {
+        if !should_handle_message(&message.chat_id, targets) {
+            continue;
+        }
+        if message.is_self {
+            sent.push(MessageSentPayload {
+                chat_title: message.chat_id.clone(),
+                chat_id: message.chat_id,
+                text: message.text,
+                timestamp: message.timestamp,
+            });
+            continue;
+        }
+        received.push(crate::ipc::MessageNewPayload {
+            is_group: message.is_group || infer_is_group(&message.chat_id, targets),
+            chat_title: message.chat_id.clone(),
+            chat_id: message.chat_id,
+            sender_name: message.sender_name,
+            text: message.text,
+            timestamp: message.timestamp,
+            msg_id: message.msg_id,
+            content_type: message.content_type,
+            image_path: None,
+            audio_path: None,
+            account_id: String::new(),
+        });
+    }
+    (received, sent)
+}
+
 fn should_handle_message(chat_id: &str, targets: &[ListenTarget]) -> bool {
     if targets.is_empty() {
         return true;
@@ -835,10 +2863,11 @@ fn should_handle_message(chat_id: &str, targets: &[ListenTarget]) -> bool {
 }
 
 fn infer_is_group(chat_id: &str, targets: &[ListenTarget]) -> bool {
-    if let Some(target) = targets.iter().find(|target| target.name == chat_id) {
-        return matches!(target.kind, crate::types::ChatKind::Group);
-    }
-    chat_id.contains("\u{7fa4}")
+    let kind = targets
+        .iter()
+        .find(|target| target.name == chat_id)
+        .map_or(crate::types::ChatKind::Unknown, |target| target.kind.clone());
+    chat_kind::or_from_title(kind, chat_id) == crate::types::ChatKind::Group
 }
 
 fn initial_status() -> Status {
@@ -854,6 +2883,11 @@ fn initial_status() -> Status {
         platform,
         agent_connected: false,
         last_error: String::new(),
+        power: PowerStatus::default(),
+        targets: BTreeMap::new(),
+        accounts: BTreeMap::new(),
+        strategy: AutomationStrategy::Agent,
+        error_code: None,
     }
 }
 
@@ -888,17 +2922,96 @@ pub fn run() {
         .setup(|app| {
             let config = load_config(app.handle())?;
             logging::init_logging(app.handle(), &config)?;
+            #[cfg(target_os = "macos")]
+            let hide_dock_icon = config.hide_dock_icon;
+            ui_automation::trace::configure(
+                config.automation_trace,
+                config.automation_trace_minutes,
+            );
             let mut app_state = AppState::new(config, initial_status());
-            let automation = build_platform_automation();
-            app_state.automation = crate::ui_automation::AutomationManager::new(automation);
+            let power = power::resolve_power_status(
+                power::detect_power_source(),
+                app_state.config.low_power_mode,
+            );
+            power::apply_power_status(&mut app_state.status.power, power);
+            match load_chat_identities(app.handle()) {
+                Ok(resolver) => app_state.chat_identities = resolver,
+                Err(err) => warn!("加载会话映射失败: {}", err),
+            }
+            match load_chat_list_cache(app.handle()) {
+                Ok(cache) => app_state.recent_chats = cache,
+                Err(err) => warn!("加载会话列表缓存失败: {}", err),
+            }
+            match load_canned_responses(app.handle()) {
+                Ok(store) => app_state.canned_responses = store,
+                Err(err) => warn!("加载快捷回复失败: {}", err),
+            }
+            match load_contact_notes(app.handle()) {
+                Ok(store) => app_state.contact_notes = store,
+                Err(err) => warn!("加载联系人备注失败: {}", err),
+            }
+            match load_personas(app.handle()) {
+                Ok(store) => app_state.personas = store,
+                Err(err) => warn!("加载人设失败: {}", err),
+            }
+            match load_prompt_versions(app.handle()) {
+                Ok(store) => app_state.prompt_versions = store,
+                Err(err) => warn!("加载提示词版本失败: {}", err),
+            }
+            track_prompt_version(app.handle(), &mut app_state, "启动");
+            match load_integration_tokens() {
+                Ok(store) => app_state.integration_tokens = store,
+                Err(err) => warn!("加载集成令牌失败: {}", err),
+            }
+            match history::open_history(app.handle()) {
+                Ok(store) => {
+                    let retention_days = app_state.config.history_retention_days;
+                    match store.prune(retention_days, now_secs()) {
+                        Ok(0) => {}
+                        Ok(removed) => info!("已清理过期历史消息: {} 条", removed),
+                        Err(err) => warn!("清理历史消息失败: {}", err),
+                    }
+                    match store.load_recent(app_state.config.context_max_messages) {
+                        Ok(conversations) => app_state.restore_conversations(conversations),
+                        Err(err) => warn!("加载历史消息失败: {}", err),
+                    }
+                    match store.load_usage(quota::day_of(now_secs())) {
+                        Ok(usage) => app_state.usage = usage,
+                        Err(err) => warn!("加载今日用量失败: {}", err),
+                    }
+                    app_state.history = Some(store);
+                }
+                Err(err) => warn!("打开历史记录失败: {}", err),
+            }
+            app_state.automation =
+                AutomationManager::select(&app_state.config.automation_strategies);
+            app_state.status.strategy = app_state.automation.strategy();
+            app_state
+                .automation
+                .configure(app_state.config.automation_concurrency);
+            let integration_port = app_state.config.integration_port;
             let state = Arc::new(Mutex::new(app_state));
-            app.manage(state);
+            app.manage(state.clone());
+            power::spawn_power_monitor(app.handle().clone(), state.clone());
+            maintenance::spawn_startup_maintenance(app.handle());
+            frontend_link::spawn_frontend_watchdog(state.clone());
+            knowledge_base::spawn_refresh(state.clone());
+            integration_server::spawn_integration_server(
+                app.handle().clone(),
+                state.clone(),
+                integration_port,
+            );
+            readiness::spawn_readiness_monitor(app.handle().clone(), state);
             #[cfg(target_os = "macos")]
             if let Err(err) =
                 crate::ui_automation::macos::ui_paths_store::load_from_disk(app.handle())
             {
                 warn!("加载微信 UI 路径失败: {}", err);
             }
+            #[cfg(target_os = "macos")]
+            if let Err(err) = menu_bar::init(app.handle(), hide_dock_icon) {
+                warn!("初始化菜单栏失败: {}", err);
+            }
             adjust_window_size(app.handle());
             info!("WeReply 启动完成");
             Ok(())
@@ -908,11 +3021,19 @@ pub fn run() {
             set_config,
             start_listening,
             stop_listening,
+            restart_agent,
+            stop_agent,
+            get_agent_info,
             pause_listening,
             resume_listening,
             get_listen_targets,
             set_listen_targets,
-            list_recent_chats,
+            add_listen_targets,
+            remove_listen_targets,
+            clear_listen_targets,
+            list_chats,
+            refresh_chats,
+            get_chat_avatar,
             export_wechat_ui_tree,
             write_suggestion,
             get_status,
@@ -920,11 +3041,58 @@ pub fn run() {
             get_api_key_status,
             get_api_key,
             delete_api_key,
+            set_transcription_api_key,
+            set_wechat_db_key,
+            acquire_wechat_db_key,
             diagnose_deepseek,
+            get_deepseek_balance,
             list_models,
             learn_wechat_ui_paths,
             get_wechat_ui_paths_status,
-            set_deepseek_model
+            set_deepseek_model,
+            set_session_instruction,
+            get_session_instructions,
+            set_hide_dock_icon,
+            get_latest_suggestions,
+            frontend_heartbeat,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            switch_profile,
+            get_chat_activity_stats,
+            search_messages,
+            get_suggestion_history,
+            get_suggestion_acceptance,
+            get_experiment_report,
+            export_automation_trace,
+            get_readiness,
+            compose_reply,
+            generate_handover_brief,
+            seed_context,
+            run_maintenance,
+            get_locator_diagnostics,
+            backtest_prompts,
+            cipher_self_test,
+            export_decrypted_db,
+            get_automation_metrics,
+            list_canned_responses,
+            create_canned_response,
+            update_canned_response,
+            delete_canned_response,
+            list_contact_notes,
+            set_contact_note,
+            delete_contact_note,
+            get_knowledge_base_status,
+            rebuild_knowledge_base,
+            list_personas,
+            save_persona,
+            delete_persona,
+            list_prompt_versions,
+            diff_prompt_versions,
+            rollback_prompt_version,
+            list_integration_tokens,
+            create_integration_token,
+            revoke_integration_token
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -933,37 +3101,273 @@ pub fn run() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::ChatKind;
+    use crate::types::{ChatKind, Politeness, TargetPriority};
     use crate::ui_automation::WeChatAutomation;
     use std::sync::atomic::{AtomicBool, Ordering};
 
     #[tokio::test]
-    async fn list_recent_chats_requires_agent() {
-        let state = Arc::new(Mutex::new(AppState::new(
+    async fn chat_activity_stats_mark_listened_chats() {
+        struct MockAutomation;
+
+        impl WeChatAutomation for MockAutomation {
+            fn platform(&self) -> Platform {
+                Platform::Windows
+            }
+
+            fn list_chats(&self, query: &ChatQuery) -> anyhow::Result<ChatPage> {
+                Ok(query.page(Vec::new()))
+            }
+
+            fn start_listening(&self, _targets: Vec<ListenTarget>) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn stop_listening(&self) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn write_input(&self, _chat_id: &str, _text: &str) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn poll_new_messages(
+                &self,
+            ) -> anyhow::Result<Vec<crate::ui_automation::IncomingMessage>> {
+                Ok(Vec::new())
+            }
+
+            fn chat_activity(&self, now: u64) -> anyhow::Result<Vec<ChatActivityStats>> {
+                Ok(["项目群", "张三"]
+                    .into_iter()
+                    .filter_map(|chat_id| {
+                        crate::ui_automation::activity::summarize(
+                            chat_id.to_string(),
+                            &[(now - 60, false), (now, true)],
+                            now,
+                        )
+                    })
+                    .collect())
+            }
+        }
+
+        let mut app_state = AppState::new(Config::default(), initial_status());
+        let result = get_chat_activity_stats_inner(Arc::new(Mutex::new(AppState::new(
             Config::default(),
             initial_status(),
-        )));
-        let result = list_recent_chats_inner(state).await.unwrap();
+        ))))
+        .await;
         assert!(!result.success);
+
+        app_state.automation = AutomationManager::new(Some(Arc::new(MockAutomation)));
+        app_state.listen_targets = vec![ListenTarget {
+            name: "项目群".to_string(),
+            kind: ChatKind::Group,
+            prompt_override: None,
+            persona: None,
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language: None,
+            politeness: Politeness::Polite,
+        }];
+        let stats = get_chat_activity_stats_inner(Arc::new(Mutex::new(app_state)))
+            .await
+            .data
+            .unwrap();
+        assert_eq!(stats.len(), 2);
+        assert!(stats.iter().all(|item| item.avg_reply_secs == Some(60)));
+        assert!(stats
+            .iter()
+            .any(|item| item.chat_id == "项目群" && item.listened));
+        assert!(stats
+            .iter()
+            .any(|item| item.chat_id == "张三" && !item.listened));
+    }
+
+    #[tokio::test]
+    async fn only_busy_rejections_are_retried() {
+        let pool = Arc::new(crate::ui_automation::pool::AutomationPool::new(1));
+        let _running = pool.acquire().await.unwrap();
+        let mut queued = Vec::new();
+        for _ in 0..crate::ui_automation::pool::QUEUE_LIMIT {
+            let pool = pool.clone();
+            queued.push(tokio::spawn(async move { pool.acquire().await.map(drop) }));
+        }
+        while pool.metrics().waiting < crate::ui_automation::pool::QUEUE_LIMIT {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let busy = pool.acquire().await.err().unwrap();
+        assert!(is_busy_rejection(&busy));
+
+        assert!(!is_busy_rejection("当前会话是 李四，已取消写入 张三"));
+        assert!(!is_busy_rejection("pyautogui.FailSafeException"));
+        assert!(!is_busy_rejection(""));
+        for waiter in queued {
+            waiter.abort();
+        }
+    }
+
+    #[test]
+    fn polled_self_messages_become_sent_events() {
+        let message = |text: &str, is_self: bool| IncomingMessage {
+            chat_id: "张三".to_string(),
+            text: text.to_string(),
+            timestamp: 100,
+            msg_id: Some(text.to_string()),
+            content_type: crate::types::MessageContentType::Text,
+            sender_name: String::new(),
+            is_group: false,
+            is_self,
+        };
+        let (received, sent) =
+            split_polled_messages(vec![message("在吗", false), message("在的", true)], &[]);
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].text, "在吗");
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            (sent[0].chat_id.as_str(), sent[0].text.as_str()),
+            ("张三", "在的")
+        );
+        assert_eq!(sent[0].timestamp, 100);
+    }
+
+    #[tokio::test]
+    async fn search_messages_resolves_chat_aliases() {
+        let mut app_state = AppState::new(Config::default(), initial_status());
+        let history = crate::history::HistoryStore::open_in_memory().unwrap();
+        history
+            .append(
+                "wxid_a",
+                &crate::state::ChatMessage {
+                    text: "下周一之前发合同".to_string(),
+                    timestamp: 1,
+                    msg_id: None,
+                    direction: crate::state::MessageDirection::Incoming,
+                },
+            )
+            .unwrap();
+        app_state.history = Some(history);
+        app_state.chat_identities.learn("wxid_a", "张三");
+        let state = Arc::new(Mutex::new(app_state));
+
+        let empty = search_messages_inner(state.clone(), " ".to_string(), None).await;
+        assert!(!empty.success);
+        let hits = search_messages_inner(state, "之前发合同".to_string(), Some("张三".to_string()))
+            .await
+            .data
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chat_id, "wxid_a");
+    }
+
+    #[tokio::test]
+    async fn suggestion_history_resolves_aliases_and_clamps_limit() {
+        let mut app_state = AppState::new(Config::default(), initial_status());
+        let history = crate::history::HistoryStore::open_in_memory().unwrap();
+        for (id, created_at) in [("s1", 1), ("s2", 2)] {
+            history
+                .append_suggestions(&SuggestionRecord {
+                    id: id.to_string(),
+                    chat_id: "wxid_a".to_string(),
+                    context_hash: "abc".to_string(),
+                    model: "deepseek-chat".to_string(),
+                    fallback: false,
+                    latency_ms: 500,
+                    suggestions: Vec::new(),
+                    written_suggestion_id: None,
+                    written_at: None,
+                    created_at,
+                    prompt_version: 0,
+                    experiment: None,
+                })
+                .unwrap();
+        }
+        app_state.history = Some(history);
+        app_state.chat_identities.learn("wxid_a", "张三");
+        let state = Arc::new(Mutex::new(app_state));
+
+        let records = get_suggestion_history_inner(state.clone(), Some("张三".to_string()), None)
+            .await
+            .data
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "s2");
+        let limited = get_suggestion_history_inner(state, None, Some(0))
+            .await
+            .data
+            .unwrap();
+        assert_eq!(limited.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn seed_context_stores_parsed_messages_before_live_history() {
+        let mut app_state = AppState::new(Config::default(), initial_status());
+        app_state.chat_identities.learn("wxid_a", "张三");
+        app_state.record_message(
+            "wxid_a",
+            crate::state::ChatMessage {
+                text: "到了吗？".to_string(),
+                timestamp: now_secs(),
+                msg_id: None,
+                direction: crate::state::MessageDirection::Incoming,
+            },
+        );
+        let state = Arc::new(Mutex::new(app_state));
+
+        let empty =
+            seed_context_inner(state.clone(), "张三".to_string(), "随便说说".to_string()).await;
+        assert!(!empty.success);
+
+        let transcript = "张三 2024-05-01 10:00\n明天来取货\n我 2024-05-01 10:02\n好的，下午到";
+        let result = seed_context_inner(state.clone(), "张三".to_string(), transcript.to_string())
+            .await
+            .data
+            .unwrap();
+        assert_eq!(
+            result,
+            SeedContextResult {
+                parsed: 2,
+                kept: 2,
+                self_messages: 1,
+            }
+        );
+        let guard = state.lock().await;
+        let texts: Vec<_> = guard
+            .context_for_chat("wxid_a", now_secs())
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(texts, vec!["明天来取货", "我：好的，下午到", "到了吗？"]);
+    }
+
+    #[test]
+    fn compose_fragments_are_trimmed_and_bounded() {
+        let fragments = normalize_compose_fragments(vec![
+            " 今天下班前发您 ".to_string(),
+            "  ".to_string(),
+            "有问题随时找我".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(fragments, vec!["今天下班前发您", "有问题随时找我"]);
+        assert!(normalize_compose_fragments(vec!["好的".to_string(), " ".to_string()]).is_err());
+        assert!(normalize_compose_fragments(vec!["好".to_string(); 6]).is_err());
+        assert!(normalize_compose_fragments(vec!["好".repeat(1500), "好".repeat(501)]).is_err());
     }
 
     #[tokio::test]
-    async fn list_recent_chats_rejects_when_pending() {
+    async fn list_chats_requires_agent() {
         let state = Arc::new(Mutex::new(AppState::new(
             Config::default(),
             initial_status(),
         )));
-        let (tx, _rx) = oneshot::channel();
-        {
-            let mut guard = state.lock().await;
-            guard.pending_chats_list = Some(("req".to_string(), tx));
-        }
-        let result = list_recent_chats_inner(state).await.unwrap();
+        let result = list_chats_inner(state, ChatQuery::new(0, 50, "")).await.unwrap();
         assert!(!result.success);
     }
 
     #[tokio::test]
-    async fn list_recent_chats_uses_automation() {
+    async fn list_chats_uses_automation() {
         struct MockAutomation {
             called: Arc<AtomicBool>,
         }
@@ -973,13 +3377,17 @@ mod tests {
                 Platform::Windows
             }
 
-            fn list_recent_chats(&self) -> anyhow::Result<Vec<ChatSummary>> {
+            fn list_chats(&self, query: &ChatQuery) -> anyhow::Result<ChatPage> {
                 self.called.store(true, Ordering::SeqCst);
-                Ok(vec![ChatSummary {
+                Ok(query.page(vec![ChatSummary {
                     chat_id: "id".to_string(),
                     chat_title: "title".to_string(),
                     kind: ChatKind::Unknown,
-                }])
+                    account_id: String::new(),
+                    last_message: String::new(),
+                    last_active_at: 0,
+                    unread_count: 0,
+                }]))
             }
 
             fn start_listening(&self, _targets: Vec<ListenTarget>) -> anyhow::Result<()> {
@@ -994,8 +3402,8 @@ mod tests {
                 Ok(())
             }
 
-            fn poll_latest_message(&self) -> anyhow::Result<Option<crate::ui_automation::IncomingMessage>> {
-                Ok(None)
+            fn poll_new_messages(&self) -> anyhow::Result<Vec<crate::ui_automation::IncomingMessage>> {
+                Ok(Vec::new())
             }
         }
 
@@ -1011,8 +3419,44 @@ mod tests {
                     called: Arc::clone(&called),
                 })));
         }
-        let result = list_recent_chats_inner(state).await.unwrap();
+        let result = list_chats_inner(state, ChatQuery::new(0, 50, "TIT")).await.unwrap();
         assert!(result.success);
+        assert_eq!(result.data.unwrap().chats.len(), 1);
         assert!(called.load(Ordering::SeqCst));
     }
+
+    #[tokio::test]
+    async fn session_instruction_set_and_clear() {
+        let state = Arc::new(Mutex::new(AppState::new(
+            Config::default(),
+            initial_status(),
+        )));
+        let result = set_session_instruction_inner(
+            state.clone(),
+            "c1".to_string(),
+            "下周一发货".to_string(),
+            60,
+        )
+        .await;
+        assert!(result.success);
+        assert_eq!(state.lock().await.session_instructions(now_secs()).len(), 1);
+
+        let result =
+            set_session_instruction_inner(state.clone(), "c1".to_string(), String::new(), 60).await;
+        assert!(result.success);
+        assert!(state
+            .lock()
+            .await
+            .session_instructions(now_secs())
+            .is_empty());
+
+        let result = set_session_instruction_inner(
+            state,
+            "c1".to_string(),
+            "x".to_string(),
+            MAX_SESSION_INSTRUCTION_TTL_SECS + 1,
+        )
+        .await;
+        assert!(!result.success);
+    }
 }
@@ -1,11 +1,14 @@
-use crate::types::ListenTarget;
+use crate::chat_kind;
+use crate::types::{ChatKind, ChatSummary, ListenTarget, ListenTargetResult};
 use anyhow::Result;
 use std::collections::HashSet;
 
 #[cfg(test)]
-use crate::types::ChatKind;
+use crate::types::{Politeness, TargetPriority};
 
 pub const MAX_LISTEN_TARGETS: usize = 50;
+pub const MAX_PROMPT_OVERRIDE_CHARS: usize = 1000;
+pub const MAX_SENDER_FILTER_NAMES: usize = 100;
 
 pub fn normalize_listen_targets(targets: Vec<ListenTarget>, max: usize) -> Result<Vec<ListenTarget>> {
     if max == 0 {
@@ -22,6 +25,10 @@ pub fn normalize_listen_targets(targets: Vec<ListenTarget>, max: usize) -> Resul
             continue;
         }
         target.name = trimmed.to_string();
+        target.prompt_override = normalize_prompt_override(target.prompt_override);
+        target.persona = normalize_persona_name(target.persona);
+        target.sender_whitelist = normalize_sender_names(target.sender_whitelist);
+        target.sender_blacklist = normalize_sender_names(target.sender_blacklist);
         seen.insert(target.name.clone());
         normalized.push(target);
         if normalized.len() >= max {
@@ -31,6 +38,143 @@ pub fn normalize_listen_targets(targets: Vec<ListenTarget>, max: usize) -> Resul
     Ok(normalized)
 }
 
+pub fn normalize_prompt_override(prompt: Option<String>) -> Option<String> {
+    prompt
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .map(|text| text.chars().take(MAX_PROMPT_OVERRIDE_CHARS).collect())
+}
+
+pub fn find_listen_target<'a>(
+    targets: &'a [ListenTarget],
+    chat_id: &str,
+) -> Option<&'a ListenTarget> {
+    targets.iter().find(|target| target.name == chat_id)
+}
+
+/// Fills in the kind of targets entered by name: from the matching chat in the
+/// session list when there is one, otherwise guessed from the name.
+pub fn resolve_target_kinds(targets: &mut [ListenTarget], chats: &[ChatSummary]) {
+    for target in targets.iter_mut().filter(|target| target.kind == ChatKind::Unknown) {
+        let known = chats
+            .iter()
+            .filter(|chat| chat.chat_title.trim() == target.name || chat.chat_id == target.name)
+            .map(|chat| chat.kind.clone())
+            .find(|kind| *kind != ChatKind::Unknown);
+        target.kind = known.unwrap_or_else(|| chat_kind::from_title(&target.name));
+    }
+}
+
+pub fn prompt_override_for_chat(targets: &[ListenTarget], chat_id: &str) -> Option<String> {
+    targets
+        .iter()
+        .find(|target| target.name == chat_id)
+        .and_then(|target| target.prompt_override.clone())
+}
+
+pub fn normalize_persona_name(persona: Option<String>) -> Option<String> {
+    persona
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+pub fn persona_for_chat(targets: &[ListenTarget], chat_id: &str) -> Option<String> {
+    targets
+        .iter()
+        .find(|target| target.name == chat_id)
+        .and_then(|target| target.persona.clone())
+}
+
+pub fn normalize_sender_names(names: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for name in names {
+        let name = name.trim();
+        if name.is_empty() || normalized.iter().any(|item| item == name) {
+            continue;
+        }
+        normalized.push(name.to_string());
+        if normalized.len() >= MAX_SENDER_FILTER_NAMES {
+            break;
+        }
+    }
+    normalized
+}
+
+pub fn sender_allowed(target: &ListenTarget, sender_name: &str) -> bool {
+    let sender_name = sender_name.trim();
+    let listed = |names: &[String]| names.iter().any(|name| name == sender_name);
+    if listed(&target.sender_blacklist) {
+        return false;
+    }
+    target.sender_whitelist.is_empty() || listed(&target.sender_whitelist)
+}
+
+pub fn add_listen_targets(
+    current: &[ListenTarget],
+    batch: Vec<ListenTarget>,
+    max: usize,
+) -> (Vec<ListenTarget>, Vec<ListenTargetResult>) {
+    let mut targets = current.to_vec();
+    let mut results = Vec::new();
+    for mut target in batch {
+        let name = target.name.trim().to_string();
+        let message = if name.is_empty() {
+            Some("名称不能为空")
+        } else if targets.iter().any(|item| item.name == name) {
+            Some("监听对象已存在")
+        } else if targets.len() >= max {
+            Some("监听对象数量已达上限")
+        } else {
+            None
+        };
+        match message {
+            Some(message) => results.push(ListenTargetResult {
+                name,
+                ok: false,
+                message: message.to_string(),
+            }),
+            None => {
+                target.name = name.clone();
+                target.prompt_override = normalize_prompt_override(target.prompt_override);
+                target.persona = normalize_persona_name(target.persona);
+                target.sender_whitelist = normalize_sender_names(target.sender_whitelist);
+                target.sender_blacklist = normalize_sender_names(target.sender_blacklist);
+                targets.push(target);
+                results.push(ListenTargetResult {
+                    name,
+                    ok: true,
+                    message: String::new(),
+                });
+            }
+        }
+    }
+    (targets, results)
+}
+
+pub fn remove_listen_targets(
+    current: &[ListenTarget],
+    names: Vec<String>,
+) -> (Vec<ListenTarget>, Vec<ListenTargetResult>) {
+    let mut targets = current.to_vec();
+    let mut results = Vec::new();
+    for name in names {
+        let name = name.trim().to_string();
+        let before = targets.len();
+        targets.retain(|item| item.name != name);
+        let ok = targets.len() < before;
+        results.push(ListenTargetResult {
+            name,
+            ok,
+            message: if ok {
+                String::new()
+            } else {
+                "监听对象不存在".to_string()
+            },
+        });
+    }
+    (targets, results)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,18 +185,209 @@ mod tests {
             ListenTarget {
                 name: "  Team A ".into(),
                 kind: ChatKind::Unknown,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
             },
             ListenTarget {
                 name: "Team A".into(),
                 kind: ChatKind::Unknown,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
             },
             ListenTarget {
                 name: "".into(),
                 kind: ChatKind::Unknown,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
             },
         ];
         let out = normalize_listen_targets(input, 50).unwrap();
         assert_eq!(out.len(), 1);
         assert_eq!(out[0].name, "Team A");
     }
+
+    #[test]
+    fn prompt_override_is_trimmed_and_matched() {
+        let input = vec![ListenTarget {
+            name: "老板".into(),
+            kind: ChatKind::Direct,
+            prompt_override: Some("  语气恭敬，简短汇报进度  ".into()),
+            persona: Some(" 私人 ".into()),
+            muted: true,
+            priority: TargetPriority::High,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language: None,
+            politeness: Politeness::Polite,
+        }];
+        let out = normalize_listen_targets(input, 50).unwrap();
+        assert_eq!(
+            prompt_override_for_chat(&out, "老板").as_deref(),
+            Some("语气恭敬，简短汇报进度")
+        );
+        assert!(prompt_override_for_chat(&out, "其他").is_none());
+        assert_eq!(persona_for_chat(&out, "老板").as_deref(), Some("私人"));
+        let target = find_listen_target(&out, "老板").unwrap();
+        assert!(target.muted);
+        assert_eq!(target.priority, TargetPriority::High);
+        assert!(normalize_prompt_override(Some("   ".into())).is_none());
+    }
+
+    #[test]
+    fn sender_filters_gate_group_senders() {
+        let mut target = ListenTarget {
+            name: "项目群".into(),
+            kind: ChatKind::Group,
+            prompt_override: None,
+            persona: None,
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: vec![" 广告机器人 ".into(), "".into()],
+            mention_only: false,
+            language: None,
+            politeness: Politeness::Polite,
+        };
+        target = normalize_listen_targets(vec![target], 50)
+            .unwrap()
+            .remove(0);
+        assert_eq!(target.sender_blacklist, vec!["广告机器人"]);
+        assert!(sender_allowed(&target, "张三"));
+        assert!(!sender_allowed(&target, "广告机器人"));
+
+        target.sender_whitelist = normalize_sender_names(vec!["张三".into(), "张三".into()]);
+        assert_eq!(target.sender_whitelist.len(), 1);
+        assert!(sender_allowed(&target, " 张三 "));
+        assert!(!sender_allowed(&target, "李四"));
+    }
+
+    #[test]
+    fn bulk_add_and_remove_report_per_item() {
+        let current = vec![ListenTarget {
+            name: "Team A".into(),
+            kind: ChatKind::Group,
+            prompt_override: None,
+            persona: None,
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language: None,
+            politeness: Politeness::Polite,
+        }];
+        let batch = vec![
+            ListenTarget {
+                name: " Team B ".into(),
+                kind: ChatKind::Group,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
+            },
+            ListenTarget {
+                name: "Team A".into(),
+                kind: ChatKind::Group,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
+            },
+            ListenTarget {
+                name: "Team C".into(),
+                kind: ChatKind::Direct,
+                prompt_override: None,
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
+            },
+        ];
+        let (targets, results) = add_listen_targets(&current, batch, 2);
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[1].name, "Team B");
+        assert!(results[0].ok);
+        assert!(!results[1].ok);
+        assert!(!results[2].ok);
+
+        let (targets, results) =
+            remove_listen_targets(&targets, vec!["Team A".into(), "Nobody".into()]);
+        assert_eq!(targets.len(), 1);
+        assert!(results[0].ok);
+        assert!(!results[1].ok);
+    }
+
+    #[test]
+    fn resolves_unknown_target_kinds_from_chats() {
+        let target = |name: &str, kind: ChatKind| ListenTarget {
+            name: name.into(),
+            kind,
+            prompt_override: None,
+            persona: None,
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language: None,
+            politeness: Politeness::Polite,
+        };
+        let chats = vec![ChatSummary {
+            chat_id: "123@chatroom".into(),
+            chat_title: "周末骑行".into(),
+            kind: ChatKind::Group,
+            account_id: String::new(),
+            last_message: String::new(),
+            last_active_at: 0,
+            unread_count: 0,
+        }];
+        let mut targets = vec![
+            target("周末骑行", ChatKind::Unknown),
+            target("家人群", ChatKind::Unknown),
+            target("张三", ChatKind::Unknown),
+            target("工作群", ChatKind::Direct),
+        ];
+        resolve_target_kinds(&mut targets, &chats);
+        let kinds: Vec<_> = targets.iter().map(|target| target.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![ChatKind::Group, ChatKind::Group, ChatKind::Unknown, ChatKind::Direct]
+        );
+    }
 }
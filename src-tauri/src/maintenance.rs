@@ -0,0 +1,330 @@
+use crate::types::{MaintenanceItem, MaintenanceKind, MaintenanceReport};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+const STALE_EXPORT_AGE: Duration = Duration::from_secs(7 * 86_400);
+const MAX_LOG_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_SCAN_DEPTH: usize = 6;
+const HISTORY_FILE: &str = "history.db";
+const LOG_FILE: &str = "wereply.log";
+const UI_TREE_FILE: &str = "wechat_ui_tree.json";
+const AUTOMATION_TRACE_FILE: &str = "automation_trace.json";
+const SQLITE_SIDECARS: [&str; 3] = ["-wal", "-shm", "-journal"];
+/// Databases the app itself creates in the data directory; any other `.db`
+/// there belongs to the user and is never touched.
+const APP_DATABASES: [&str; 1] = [HISTORY_FILE];
+
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceDirs {
+    pub config_dir: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+    pub agent_root: Option<PathBuf>,
+}
+
+impl MaintenanceDirs {
+    pub fn from_app(app: &AppHandle) -> Self {
+        let path = app.path();
+        Self {
+            config_dir: path.app_config_dir().ok(),
+            data_dir: path.app_data_dir().ok(),
+            log_dir: path.app_log_dir().ok(),
+            agent_root: crate::agent::find_agent_root(app)
+                .ok()
+                .map(|root| root.join("platform_agents")),
+        }
+    }
+}
+
+pub fn run_maintenance(
+    dirs: &MaintenanceDirs,
+    dry_run: bool,
+    now: SystemTime,
+) -> MaintenanceReport {
+    let mut items = scan(dirs, now);
+    if !dry_run {
+        apply(&mut items);
+    }
+    let total_bytes = items.iter().map(|item| item.bytes).sum();
+    let reclaimed_bytes = items
+        .iter()
+        .filter(|item| item.removed)
+        .map(|item| item.bytes)
+        .sum();
+    MaintenanceReport {
+        dry_run,
+        items,
+        total_bytes,
+        reclaimed_bytes,
+    }
+}
+
+/// Only reports at startup; files are removed when the user confirms the
+/// cleanup in settings.
+pub fn spawn_startup_maintenance(app: &AppHandle) {
+    let dirs = MaintenanceDirs::from_app(app);
+    tauri::async_runtime::spawn(async move {
+        let task =
+            tokio::task::spawn_blocking(move || run_maintenance(&dirs, true, SystemTime::now()));
+        let report = match task.await {
+            Ok(report) => report,
+            Err(err) => {
+                warn!("启动检查可清理文件失败: {}", err);
+                return;
+            }
+        };
+        if report.items.is_empty() {
+            return;
+        }
+        info!(
+            "发现 {} 项可清理文件，共 {} 字节，需在设置中确认后清理",
+            report.items.len(),
+            report.total_bytes
+        );
+    });
+}
+
+fn scan(dirs: &MaintenanceDirs, now: SystemTime) -> Vec<MaintenanceItem> {
+    let mut items = Vec::new();
+    if let Some(dir) = dirs.config_dir.as_deref() {
+        for (path, metadata) in list_files(dir) {
+            let name = file_name(&path);
+            if name == UI_TREE_FILE && is_older_than(&metadata, now, STALE_EXPORT_AGE) {
+                items.push(item(MaintenanceKind::UiTree, &path, metadata.len()));
+            } else if name.ends_with(".tmp") {
+                items.push(item(MaintenanceKind::TempFile, &path, metadata.len()));
+            }
+        }
+    }
+    if let Some(dir) = dirs.log_dir.as_deref() {
+        for (path, metadata) in list_files(dir) {
+            let name = file_name(&path);
+            let stale = match name.as_str() {
+                LOG_FILE => metadata.len() > MAX_LOG_BYTES,
+                AUTOMATION_TRACE_FILE => is_older_than(&metadata, now, STALE_EXPORT_AGE),
+                _ => name.starts_with("wereply.log."),
+            };
+            if stale {
+                items.push(item(MaintenanceKind::Log, &path, metadata.len()));
+            }
+        }
+    }
+    if let Some(dir) = dirs.data_dir.as_deref() {
+        for (path, metadata) in list_files(dir) {
+            if is_orphaned_db(&path) {
+                items.push(item(MaintenanceKind::OrphanedDb, &path, metadata.len()));
+            } else if file_name(&path).ends_with(".tmp") {
+                items.push(item(MaintenanceKind::TempFile, &path, metadata.len()));
+            }
+        }
+    }
+    if let Some(root) = dirs.agent_root.as_deref() {
+        scan_python_cache(root, 0, &mut items);
+    }
+    items
+}
+
+fn apply(items: &mut [MaintenanceItem]) {
+    for item in items {
+        let path = Path::new(&item.path);
+        let result = if item.kind == MaintenanceKind::Log && file_name(path) == LOG_FILE {
+            fs::OpenOptions::new()
+                .write(true)
+                .open(path)
+                .and_then(|file| file.set_len(0))
+        } else {
+            fs::remove_file(path)
+        };
+        match result {
+            Ok(()) => item.removed = true,
+            Err(err) => item.error = Some(err.to_string()),
+        }
+    }
+}
+
+/// A journal left behind by one of the app's own databases after the database
+/// itself is gone.
+fn is_orphaned_db(path: &Path) -> bool {
+    let name = file_name(path);
+    SQLITE_SIDECARS
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .is_some_and(|base| APP_DATABASES.contains(&base) && !path.with_file_name(base).exists())
+}
+
+fn scan_python_cache(dir: &Path, depth: usize, items: &mut Vec<MaintenanceItem>) {
+    if depth > MAX_SCAN_DEPTH {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let in_cache = file_name(dir) == "__pycache__";
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            scan_python_cache(&path, depth + 1, items);
+        } else if in_cache && is_orphaned_pyc(&path) {
+            items.push(item(MaintenanceKind::PythonCache, &path, metadata.len()));
+        }
+    }
+}
+
+fn is_orphaned_pyc(path: &Path) -> bool {
+    let name = file_name(path);
+    if !name.ends_with(".pyc") {
+        return false;
+    }
+    let module = name.split('.').next().unwrap_or_default();
+    let Some(package_dir) = path.parent().and_then(Path::parent) else {
+        return false;
+    };
+    !package_dir.join(format!("{}.py", module)).exists()
+}
+
+fn list_files(dir: &Path) -> Vec<(PathBuf, fs::Metadata)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| (entry.path(), metadata))
+        })
+        .collect()
+}
+
+fn is_older_than(metadata: &fs::Metadata, now: SystemTime, age: Duration) -> bool {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|elapsed| elapsed > age)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn item(kind: MaintenanceKind, path: &Path, bytes: u64) -> MaintenanceItem {
+    MaintenanceItem {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        bytes,
+        removed: false,
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; bytes]).unwrap();
+    }
+
+    #[test]
+    fn dry_run_reports_orphans_without_removing() {
+        let temp = tempdir().unwrap();
+        let config_dir = temp.path().join("config");
+        let data_dir = temp.path().join("data");
+        let agent_root = temp.path().join("platform_agents");
+        write(&config_dir.join(UI_TREE_FILE), 10);
+        write(&config_dir.join("config.json.tmp"), 4);
+        write(&data_dir.join(HISTORY_FILE), 100);
+        write(&data_dir.join("history.db-wal"), 7);
+        write(&data_dir.join("old_chat.db"), 20);
+        write(&data_dir.join("gone.db-shm"), 3);
+        write(&agent_root.join("windows/wxauto_agent.py"), 1);
+        write(
+            &agent_root.join("windows/__pycache__/wxauto_agent.cpython-311.pyc"),
+            5,
+        );
+        write(
+            &agent_root.join("windows/__pycache__/legacy.cpython-311.pyc"),
+            6,
+        );
+        let dirs = MaintenanceDirs {
+            config_dir: Some(config_dir.clone()),
+            data_dir: Some(data_dir.clone()),
+            log_dir: None,
+            agent_root: Some(agent_root),
+        };
+
+        let report = run_maintenance(&dirs, true, SystemTime::now());
+        let mut found: Vec<_> = report
+            .items
+            .iter()
+            .map(|item| (item.kind, file_name(Path::new(&item.path))))
+            .collect();
+        found.sort_by(|left, right| left.1.cmp(&right.1));
+        assert_eq!(
+            found,
+            vec![
+                (MaintenanceKind::TempFile, "config.json.tmp".to_string()),
+                (
+                    MaintenanceKind::PythonCache,
+                    "legacy.cpython-311.pyc".to_string()
+                ),
+            ]
+        );
+        assert_eq!(report.total_bytes, 10);
+        assert_eq!(report.reclaimed_bytes, 0);
+        assert!(config_dir.join("config.json.tmp").exists());
+
+        let stale = SystemTime::now() + STALE_EXPORT_AGE + Duration::from_secs(60);
+        let report = run_maintenance(&dirs, false, stale);
+        assert_eq!(report.items.len(), 3);
+        assert_eq!(report.reclaimed_bytes, 20);
+        assert!(data_dir.join("old_chat.db").exists());
+        assert!(data_dir.join("gone.db-shm").exists());
+        assert!(!config_dir.join(UI_TREE_FILE).exists());
+        assert!(data_dir.join(HISTORY_FILE).exists());
+        assert!(data_dir.join("history.db-wal").exists());
+    }
+
+    #[test]
+    fn only_journals_of_app_databases_are_orphaned() {
+        let temp = tempdir().unwrap();
+        write(&temp.path().join("history.db-wal"), 1);
+        write(&temp.path().join("backup.db"), 1);
+        write(&temp.path().join("backup.db-shm"), 1);
+        assert!(is_orphaned_db(&temp.path().join("history.db-wal")));
+        assert!(!is_orphaned_db(&temp.path().join("backup.db")));
+        assert!(!is_orphaned_db(&temp.path().join("backup.db-shm")));
+
+        write(&temp.path().join(HISTORY_FILE), 1);
+        assert!(!is_orphaned_db(&temp.path().join("history.db-wal")));
+    }
+
+    #[test]
+    fn truncates_oversized_log_in_place() {
+        let temp = tempdir().unwrap();
+        let log = temp.path().join(LOG_FILE);
+        let file = fs::File::create(&log).unwrap();
+        file.set_len(MAX_LOG_BYTES + 1).unwrap();
+        write(&temp.path().join("wereply.log.2024-05-01"), 8);
+        let dirs = MaintenanceDirs {
+            log_dir: Some(temp.path().to_path_buf()),
+            ..MaintenanceDirs::default()
+        };
+
+        let report = run_maintenance(&dirs, false, SystemTime::now());
+        assert_eq!(report.items.len(), 2);
+        assert!(report.items.iter().all(|item| item.removed));
+        assert_eq!(fs::metadata(&log).unwrap().len(), 0);
+        assert!(!temp.path().join("wereply.log.2024-05-01").exists());
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(any(target_os = "macos", test))]
+use crate::types::RuntimeState;
+
+#[cfg(target_os = "macos")]
+pub use tray::{apply_dock_icon, init};
+
+#[cfg(any(target_os = "macos", test))]
+pub fn runtime_state_label(state: &RuntimeState) -> &'static str {
+    match state {
+        RuntimeState::Idle => "空闲",
+        RuntimeState::Listening => "监听中",
+        RuntimeState::Generating => "生成中",
+        RuntimeState::Paused => "已暂停",
+        RuntimeState::Error => "异常",
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod tray {
+    use super::runtime_state_label;
+    use crate::types::{RuntimeState, Status};
+    use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+    use tauri::{
+        ActivationPolicy, AppHandle, Listener, Manager, PhysicalPosition, Rect, WebviewUrl,
+        WebviewWindow, WebviewWindowBuilder, WindowEvent,
+    };
+    use tracing::warn;
+
+    const TRAY_ID: &str = "wereply-menubar";
+    const PANEL_LABEL: &str = "panel";
+    const PANEL_WIDTH: f64 = 320.0;
+    const PANEL_HEIGHT: f64 = 420.0;
+
+    pub fn init(app: &AppHandle, hide_dock_icon: bool) -> tauri::Result<()> {
+        apply_dock_icon(app, hide_dock_icon)?;
+        let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+            .tooltip("WeReply")
+            .show_menu_on_left_click(false)
+            .on_tray_icon_event(|tray, event| {
+                if let TrayIconEvent::Click {
+                    button: MouseButton::Left,
+                    button_state: MouseButtonState::Up,
+                    rect,
+                    ..
+                } = event
+                {
+                    if let Err(err) = toggle_panel(tray.app_handle(), rect) {
+                        warn!("切换菜单栏面板失败: {}", err);
+                    }
+                }
+            });
+        if let Some(icon) = app.default_window_icon() {
+            builder = builder.icon(icon.clone());
+        }
+        builder.build(app)?;
+
+        let app_handle = app.clone();
+        app.listen("status.changed", move |event| {
+            if let Ok(status) = serde_json::from_str::<Status>(event.payload()) {
+                update_tray_state(&app_handle, &status.state);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn apply_dock_icon(app: &AppHandle, hidden: bool) -> tauri::Result<()> {
+        let policy = if hidden {
+            ActivationPolicy::Accessory
+        } else {
+            ActivationPolicy::Regular
+        };
+        app.set_activation_policy(policy)
+    }
+
+    fn update_tray_state(app: &AppHandle, state: &RuntimeState) {
+        let Some(tray) = app.tray_by_id(TRAY_ID) else {
+            return;
+        };
+        let label = runtime_state_label(state);
+        let _ = tray.set_tooltip(Some(format!("WeReply · {}", label)));
+        let title = match state {
+            RuntimeState::Idle => None,
+            _ => Some(label),
+        };
+        let _ = tray.set_title(title);
+    }
+
+    fn toggle_panel(app: &AppHandle, rect: Rect) -> tauri::Result<()> {
+        let window = match app.get_webview_window(PANEL_LABEL) {
+            Some(window) => window,
+            None => build_panel(app)?,
+        };
+        if window.is_visible()? {
+            return window.hide();
+        }
+        let scale_factor = window.scale_factor()?;
+        let position = rect.position.to_physical::<f64>(scale_factor);
+        let size = rect.size.to_physical::<f64>(scale_factor);
+        let x = position.x + size.width / 2.0 - PANEL_WIDTH * scale_factor / 2.0;
+        let y = position.y + size.height;
+        window.set_position(PhysicalPosition::new(x, y))?;
+        window.show()?;
+        window.set_focus()
+    }
+
+    fn build_panel(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+        let window = WebviewWindowBuilder::new(
+            app,
+            PANEL_LABEL,
+            WebviewUrl::App("index.html?view=panel".into()),
+        )
+        .title("WeReply")
+        .inner_size(PANEL_WIDTH, PANEL_HEIGHT)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .build()?;
+        let panel = window.clone();
+        window.on_window_event(move |event| {
+            if let WindowEvent::Focused(false) = event {
+                let _ = panel.hide();
+            }
+        });
+        Ok(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_state_labels_match_frontend() {
+        assert_eq!(runtime_state_label(&RuntimeState::Listening), "监听中");
+        assert_eq!(runtime_state_label(&RuntimeState::Idle), "空闲");
+    }
+}
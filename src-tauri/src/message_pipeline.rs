@@ -1,65 +1,830 @@
-use crate::deepseek;
-use crate::ipc::{validate_message_new, MessageNewPayload};
+use crate::auto_reply;
+use crate::chat_identity::save_chat_identities;
+use crate::content_type;
+use crate::deepseek::{self, Generated, GenerationFailure};
+use crate::generation::GenerationTicket;
+use crate::graphemes;
+use crate::ipc::{
+    order_message_batch, validate_message_new, MessageNewPayload, MessageSentPayload,
+};
+use crate::listen_targets;
+use crate::notification;
+use crate::ocr;
+use crate::personas;
+use crate::politeness;
+use crate::prompt_guard;
+use crate::ranking;
+use crate::reply;
+use crate::safety_filter::{self, SafetyFilter};
 use crate::secret::ApiKeyManager;
-use crate::state::{AppState, ChatMessage};
-use crate::types::{ErrorPayload, RuntimeState, SuggestionsUpdated};
+use crate::state::{now_secs, AppState, ChatMessage, MessageDirection};
+use crate::transcription;
+use crate::types::{
+    AutoReplySent, ErrorPayload, FallbackMode, FollowupsUpdated, MessageContentType, Persona,
+    ReplySource, RuntimeState, SuggestedAction, Suggestion, SuggestionReasoning, SuggestionRecord,
+    SuggestionUsed, SuggestionsUnavailable, SuggestionsUpdated, TargetPriority,
+    TranscriptionCompleted,
+};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use tokio::sync::Mutex;
 use tracing::{info, warn};
+use uuid::Uuid;
+
+const RETRY_INTERVAL_SECS: u64 = 30;
+const RETRY_MAX_ATTEMPTS: u32 = 20;
+const TEMPLATE_MODEL: &str = "template";
 
 pub async fn handle_incoming_message(
     app: &AppHandle,
     state: &Arc<Mutex<AppState>>,
     payload: MessageNewPayload,
 ) {
+    if let Some(payload) = ingest_message(app, state, payload).await {
+        respond_to_message(app, state, payload).await;
+    }
+}
+
+// 一批消息全部记入上下文，但每个会话只按最后一条生成一次建议
+pub async fn handle_incoming_batch(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    messages: Vec<MessageNewPayload>,
+) {
+    let total = messages.len();
+    let mut latest: Vec<MessageNewPayload> = Vec::new();
+    for payload in order_message_batch(messages) {
+        if let Some(payload) = ingest_message(app, state, payload).await {
+            latest.retain(|item| item.chat_id != payload.chat_id);
+            latest.push(payload);
+        }
+    }
+    info!("收到批量消息: {} 条，涉及 {} 个会话", total, latest.len());
+    for payload in latest {
+        respond_to_message(app, state, payload).await;
+    }
+}
+
+async fn ingest_message(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: MessageNewPayload,
+) -> Option<MessageNewPayload> {
     if let Err(err) = validate_message_new(&payload) {
         warn!("消息验证失败: {}", err);
-        return;
+        return None;
     }
+    let payload = canonicalize_chat(app, state, payload).await;
     if is_duplicate_message(state, &payload).await {
-        return;
+        return None;
     }
+    let payload = classify_content(app, state, payload).await?;
     record_message(state, &payload).await;
-    info!("收到新消息，生成回复建议");
-    update_state(state, app, RuntimeState::Generating, "").await;
-    let context = {
+    Some(payload)
+}
+
+async fn respond_to_message(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: MessageNewPayload,
+) {
+    let (target, nickname) = {
         let guard = state.lock().await;
-        guard.context_for_chat(&payload.chat_id)
+        let target = guard
+            .listen_target_for_chat(&payload.chat_id, &payload.chat_title)
+            .cloned();
+        (target, guard.self_nickname(&payload.account_id))
     };
-    let config = {
-        let guard = state.lock().await;
-        guard.config.clone()
+    let high_priority = target
+        .as_ref()
+        .is_some_and(|target| target.priority == TargetPriority::High);
+    if let Some(target) = target.as_ref() {
+        if target.muted {
+            info!("会话已静音，仅记录消息: {}", payload.chat_id);
+            return;
+        }
+        if payload.is_group && !listen_targets::sender_allowed(target, &payload.sender_name) {
+            info!(
+                "群成员不在建议名单内，仅记录消息: {} / {}",
+                payload.chat_id, payload.sender_name
+            );
+            return;
+        }
+        if payload.is_group && target.mention_only {
+            let Some(nickname) = nickname else {
+                warn!("未配置我的群昵称，无法判断是否被 @: {}", payload.chat_id);
+                return;
+            };
+            if !reply::mentions_self(&payload.text, &nickname) {
+                info!("群消息未 @ 我，仅记录消息: {}", payload.chat_id);
+                return;
+            }
+        }
+    }
+    let auto_replied = maybe_auto_reply(app, state, &payload).await;
+    info!("收到新消息，生成回复建议");
+    let chat_id = payload.chat_id.clone();
+    update_target_state(state, app, &chat_id, RuntimeState::Generating, None).await;
+    let (request, persona) = {
+        let mut guard = state.lock().await;
+        let persona = guard.persona_for_chat(&payload.chat_id, &payload.chat_title);
+        let mut request =
+            guard.suggestion_request(&payload.chat_id, &payload.chat_title, now_secs());
+        guard.assign_experiment(&payload.chat_id, &mut request);
+        (request, persona)
+    };
+    let (config, ticket) = {
+        let mut guard = state.lock().await;
+        let capacity = guard.config.max_concurrent_generations;
+        let ticket = guard.generations.begin(&chat_id, capacity);
+        (guard.config.clone(), ticket)
     };
     let app_handle = app.clone();
     let state_handle = state.clone();
     tokio::spawn(async move {
+        let GenerationTicket {
+            seq,
+            gate,
+            mut superseded,
+        } = ticket;
+        let started = Instant::now();
+        let over_quota = {
+            let guard = state_handle.lock().await;
+            let now = now_secs();
+            guard.quota_exceeded(now)
+                || persona
+                    .as_ref()
+                    .is_some_and(|persona| guard.personas.quota_exceeded(persona, now))
+        };
+        if over_quota {
+            publish_quota_fallback(&app_handle, &state_handle, &payload, &request, started).await;
+            finish_generation(&state_handle, &chat_id, seq).await;
+            update_target_state(
+                &state_handle,
+                &app_handle,
+                &chat_id,
+                RuntimeState::Listening,
+                None,
+            )
+            .await;
+            return;
+        }
         let api_key = ApiKeyManager::get_deepseek_api_key().ok();
-        let suggestions = deepseek::generate_suggestions(&config, api_key, &context)
-            .await
-            .unwrap_or_else(|_| Vec::new());
-        if suggestions.is_empty() {
-            warn!("生成建议为空");
+        let generation = async {
+            let _permit = gate.acquire(high_priority).await;
+            deepseek::generate_suggestions(&config, api_key, &request).await
+        };
+        let result = tokio::select! {
+            result = generation => result,
+            _ = &mut superseded => {
+                info!("会话有新消息，取消旧的建议生成: {}", chat_id);
+                return;
+            }
+        };
+        track_api_key_state(&state_handle, &result).await;
+        track_usage(&state_handle, &result).await;
+        if superseded.try_recv().is_ok() {
+            info!("会话有新消息，丢弃旧的建议: {}", chat_id);
+            return;
+        }
+        finish_generation(&state_handle, &chat_id, seq).await;
+        let failure = result
+            .as_ref()
+            .err()
+            .map(|failure| (failure.code(), failure.reason()));
+        match result {
+            Ok(generated) => {
+                let suggestions = {
+                    let guard = state_handle.lock().await;
+                    shape_suggestions(&guard, &payload, generated.suggestions)
+                };
+                let record = suggestion_record(
+                    &payload.chat_id,
+                    &request,
+                    &config.deepseek_model,
+                    started,
+                    suggestions,
+                );
+                let followups = (!generated.followups.is_empty()).then(|| FollowupsUpdated {
+                    chat_id: payload.chat_id.clone(),
+                    record_id: record.id.clone(),
+                    questions: generated.followups,
+                });
+                let reasoning =
+                    generated
+                        .reasoning
+                        .filter(|_| config.expose_reasoning)
+                        .map(|text| SuggestionReasoning {
+                            chat_id: payload.chat_id.clone(),
+                            record_id: record.id.clone(),
+                            model: config.deepseek_model.clone(),
+                            text,
+                        });
+                let guarded = prompt_guard::looks_like_injection(&payload.text);
+                if guarded {
+                    warn!("消息疑似指令注入，本条不自动发送: {}", payload.chat_id);
+                }
+                if let Some(persona) = persona.as_ref() {
+                    state_handle.lock().await.personas.record_usage(
+                        &persona.name,
+                        generated.total_tokens,
+                        now_secs(),
+                    );
+                }
+                let updated =
+                    publish_suggestions(&app_handle, &state_handle, &payload, record).await;
+                if let Some(reasoning) = reasoning {
+                    let _ = app_handle.emit("suggestion.reasoning", reasoning);
+                }
+                if let Some(followups) = followups {
+                    let _ = app_handle.emit("followups.updated", followups);
+                }
+                let first = updated.suggestions.first().filter(|suggestion| {
+                    let flagged = safety_filter::is_flagged(&updated.warnings, &suggestion.id);
+                    if flagged {
+                        warn!("建议命中安全过滤，本条不自动发送: {}", payload.chat_id);
+                    }
+                    !flagged
+                });
+                if auto_replied && persona.as_ref().is_some_and(|persona| persona.auto_send) {
+                    info!(
+                        "规则已自动回复本条消息，人设不再自动发送: {}",
+                        payload.chat_id
+                    );
+                }
+                let auto_send = persona_reply(
+                    persona.as_ref(),
+                    first,
+                    &payload.chat_id,
+                    guarded || auto_replied,
+                );
+                if let Some(reply) = auto_send {
+                    dispatch_auto_reply(&app_handle, &state_handle, reply).await;
+                }
+            }
+            Err(failure) => {
+                handle_generation_failure(
+                    &app_handle,
+                    &state_handle,
+                    config.fallback_mode,
+                    payload,
+                    request,
+                    failure,
+                    started,
+                )
+                .await;
+            }
+        }
+        let runtime = if failure.is_some() {
+            RuntimeState::Error
+        } else {
+            RuntimeState::Listening
+        };
+        update_target_state(&state_handle, &app_handle, &chat_id, runtime, failure).await;
+    });
+}
+
+pub async fn handle_outgoing_message(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: MessageSentPayload,
+) {
+    if payload.chat_id.trim().is_empty() || payload.text.trim().is_empty() {
+        return;
+    }
+    let (chat_id, used) = {
+        let mut guard = state.lock().await;
+        let chat_id = guard.chat_identities.resolve(&payload.chat_id);
+        guard.record_outgoing(&chat_id, &payload.text, payload.timestamp);
+        let used = guard.mark_suggestion_observed(&chat_id, &payload.text);
+        (chat_id, used)
+    };
+    let Some(suggestion_id) = used else {
+        return;
+    };
+    info!("检测到已发送的建议，标记为已采纳: {}", chat_id);
+    let _ = app.emit(
+        "suggestion.used",
+        SuggestionUsed {
+            chat_id,
+            suggestion_id,
+            observed: true,
+        },
+    );
+}
+
+/// Sends the rule reply for `payload`, if any. Returns whether one was sent,
+/// so the persona auto-send does not answer the same message a second time.
+async fn maybe_auto_reply(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: &MessageNewPayload,
+) -> bool {
+    let reply = {
+        let guard = state.lock().await;
+        rule_reply(&guard, payload, now_secs())
+    };
+    match reply {
+        Some(reply) => dispatch_auto_reply(app, state, reply).await,
+        None => false,
+    }
+}
+
+fn rule_reply(state: &AppState, payload: &MessageNewPayload, now: u64) -> Option<AutoReplySent> {
+    let rule = auto_reply::match_rule(
+        &state.config.auto_reply_rules,
+        &payload.chat_id,
+        &payload.chat_title,
+        &payload.text,
+        now,
+    )?;
+    let Some(text) = auto_reply::resolve_reply(rule, &state.canned_responses, &payload.text) else {
+        warn!("自动回复内容不可用，跳过: {}", payload.chat_id);
+        return None;
+    };
+    Some(AutoReplySent {
+        chat_id: payload.chat_id.clone(),
+        keyword: rule.keyword.clone(),
+        text,
+        sent_at: 0,
+        persona: None,
+    })
+}
+
+fn persona_reply(
+    persona: Option<&Persona>,
+    suggestion: Option<&Suggestion>,
+    chat_id: &str,
+    suppressed: bool,
+) -> Option<AutoReplySent> {
+    let persona = persona.filter(|persona| persona.auto_send && !suppressed)?;
+    Some(AutoReplySent {
+        chat_id: chat_id.to_string(),
+        keyword: String::new(),
+        text: suggestion?.text.clone(),
+        sent_at: 0,
+        persona: Some(persona.name.clone()),
+    })
+}
+
+/// Hands `reply` to the send task. Returns false when the master switch is off
+/// or the hourly cap is reached, in which case nothing is sent.
+async fn dispatch_auto_reply(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    mut reply: AutoReplySent,
+) -> bool {
+    let allowed = {
+        let mut guard = state.lock().await;
+        // The master switch covers rule replies and persona auto-sends alike.
+        if !guard.config.auto_reply_enabled {
+            info!("自动回复总开关已关闭，不自动发送: {}", reply.chat_id);
+            return false;
+        }
+        let max_per_hour = guard.config.auto_reply_max_per_hour;
+        guard.auto_replies.try_acquire(now_secs(), max_per_hour)
+    };
+    if !allowed {
+        warn!("自动回复已达每小时上限，跳过: {}", reply.chat_id);
+        emit_error(
+            app,
+            ErrorPayload {
+                code: "AUTO_REPLY_CAPPED".to_string(),
+                message: "自动回复已达每小时上限，本条消息未自动回复".to_string(),
+                recoverable: true,
+                suggested_action: Some(SuggestedAction::OpenSettings),
+            },
+        );
+        return false;
+    }
+    let app = app.clone();
+    let state = state.clone();
+    tokio::spawn(async move {
+        let chat_id = reply.chat_id.clone();
+        let res = crate::send_auto_reply(&state, chat_id.clone(), reply.text.clone()).await;
+        if !res.success {
+            warn!(
+                "自动回复发送失败: chat_id={}, error={}",
+                chat_id, res.message
+            );
             emit_error(
-                &app_handle,
+                &app,
                 ErrorPayload {
-                    code: "SUGGESTION_EMPTY".to_string(),
-                    message: "未生成回复建议".to_string(),
+                    code: "AUTO_REPLY_FAILED".to_string(),
+                    message: format!("自动回复发送失败: {}", res.message),
                     recoverable: true,
+                    suggested_action: None,
                 },
             );
-        } else {
-            info!("生成建议完成: {} 条", suggestions.len());
-            let payload = SuggestionsUpdated {
-                chat_id: payload.chat_id.clone(),
-                suggestions,
+            return;
+        }
+        info!(
+            "已自动回复: chat_id={}, keyword={}, persona={}",
+            chat_id,
+            reply.keyword,
+            reply.persona.as_deref().unwrap_or("-")
+        );
+        reply.sent_at = now_secs();
+        {
+            let mut guard = state.lock().await;
+            guard.record_outgoing(&reply.chat_id, &reply.text, reply.sent_at);
+            guard.frontend.buffer_auto_reply(&reply, reply.sent_at);
+        }
+        let _ = app.emit("auto_reply.sent", reply);
+    });
+    true
+}
+
+async fn finish_generation(state: &Arc<Mutex<AppState>>, chat_id: &str, seq: u64) {
+    state.lock().await.generations.finish(chat_id, seq);
+}
+
+async fn track_api_key_state(
+    state: &Arc<Mutex<AppState>>,
+    result: &Result<Generated, GenerationFailure>,
+) {
+    let rejected = match result {
+        Ok(_) => false,
+        Err(failure) if failure.is_auth_error() => true,
+        Err(_) => return,
+    };
+    state.lock().await.api_key_rejected = rejected;
+}
+
+pub async fn track_usage(
+    state: &Arc<Mutex<AppState>>,
+    result: &Result<Generated, GenerationFailure>,
+) {
+    let tokens = match result {
+        Ok(generated) => generated.total_tokens,
+        Err(GenerationFailure::MissingApiKey | GenerationFailure::Network(_)) => return,
+        Err(_) => 0,
+    };
+    state.lock().await.record_usage(tokens, now_secs());
+}
+
+async fn publish_quota_fallback(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: &MessageNewPayload,
+    request: &deepseek::SuggestionRequest,
+    started: Instant,
+) {
+    warn!("今日用量已达上限，使用本地模板建议: {}", payload.chat_id);
+    emit_error(
+        app,
+        ErrorPayload {
+            code: "QUOTA_EXCEEDED".to_string(),
+            message: "今日 DeepSeek 用量已达上限，已改用本地模板建议".to_string(),
+            recoverable: true,
+            suggested_action: Some(SuggestedAction::OpenSettings),
+        },
+    );
+    let record = template_record(payload, request, started);
+    publish_suggestions(app, state, payload, record).await;
+}
+
+async fn handle_generation_failure(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    mode: FallbackMode,
+    payload: MessageNewPayload,
+    request: deepseek::SuggestionRequest,
+    failure: GenerationFailure,
+    started: Instant,
+) {
+    warn!("生成建议失败: {}", failure.reason());
+    match mode {
+        FallbackMode::Templates => {
+            if let GenerationFailure::Network(_) = failure {
+                emit_error(
+                    app,
+                    ErrorPayload {
+                        code: "SUGGESTION_EMPTY".to_string(),
+                        message: "未生成回复建议".to_string(),
+                        recoverable: true,
+                        suggested_action: Some(SuggestedAction::Retry),
+                    },
+                );
+                return;
+            }
+            let record = template_record(&payload, &request, started);
+            publish_suggestions(app, state, &payload, record).await;
+        }
+        FallbackMode::Silent => emit_unavailable(app, &payload, &failure, false),
+        FallbackMode::RetryOnly => {
+            let retry_scheduled = failure.is_retryable();
+            emit_unavailable(app, &payload, &failure, retry_scheduled);
+            if retry_scheduled {
+                schedule_retry(app.clone(), state.clone(), payload, request).await;
+            }
+        }
+    }
+}
+
+async fn schedule_retry(
+    app: AppHandle,
+    state: Arc<Mutex<AppState>>,
+    payload: MessageNewPayload,
+    request: deepseek::SuggestionRequest,
+) {
+    let message_key = {
+        let guard = state.lock().await;
+        guard.last_message_key(&payload.chat_id)
+    };
+    info!("已排队等待联网后重新生成: {}", payload.chat_id);
+    tokio::spawn(async move {
+        for _ in 0..RETRY_MAX_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+            let config = {
+                let guard = state.lock().await;
+                if guard.last_message_key(&payload.chat_id) != message_key {
+                    info!("会话已有新消息，取消重试: {}", payload.chat_id);
+                    return;
+                }
+                guard.config.clone()
             };
-            let _ = app_handle.emit("suggestions.updated", payload);
+            if config.fallback_mode != FallbackMode::RetryOnly {
+                return;
+            }
+            let started = Instant::now();
+            let over_quota = state.lock().await.quota_exceeded(now_secs());
+            if over_quota {
+                publish_quota_fallback(&app, &state, &payload, &request, started).await;
+                return;
+            }
+            let api_key = ApiKeyManager::get_deepseek_api_key().ok();
+            let result = deepseek::generate_suggestions(&config, api_key, &request).await;
+            track_usage(&state, &result).await;
+            match result {
+                Ok(generated) => {
+                    info!("联网恢复，重新生成建议完成: {}", payload.chat_id);
+                    let suggestions = {
+                        let guard = state.lock().await;
+                        shape_suggestions(&guard, &payload, generated.suggestions)
+                    };
+                    let record = suggestion_record(
+                        &payload.chat_id,
+                        &request,
+                        &config.deepseek_model,
+                        started,
+                        suggestions,
+                    );
+                    publish_suggestions(&app, &state, &payload, record).await;
+                    return;
+                }
+                Err(failure) if failure.is_retryable() => continue,
+                Err(failure) => {
+                    emit_unavailable(&app, &payload, &failure, false);
+                    return;
+                }
+            }
         }
-        update_state(&state_handle, &app_handle, RuntimeState::Listening, "").await;
+        warn!("重试次数已用尽，放弃生成: {}", payload.chat_id);
     });
 }
 
+/// Narrows generated suggestions to the chat's persona styles and its
+/// target's register. The first attempt and the connectivity retry both go
+/// through here, so a retried chat gets the same shape of output.
+fn shape_suggestions(
+    state: &AppState,
+    payload: &MessageNewPayload,
+    suggestions: Vec<Suggestion>,
+) -> Vec<Suggestion> {
+    let persona = state.persona_for_chat(&payload.chat_id, &payload.chat_title);
+    let target = state.listen_target_for_chat(&payload.chat_id, &payload.chat_title);
+    politeness::apply(
+        target,
+        personas::apply_styles(persona.as_ref(), suggestions),
+    )
+}
+
+async fn publish_suggestions(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: &MessageNewPayload,
+    mut record: SuggestionRecord,
+) -> SuggestionsUpdated {
+    let (filter, best_pick) = {
+        let guard = state.lock().await;
+        (
+            SafetyFilter::new(&guard.config.safety_rules),
+            guard.config.best_pick_mode,
+        )
+    };
+    let screened = filter.screen(std::mem::take(&mut record.suggestions));
+    record.suggestions = ranking::rank(screened.suggestions, &payload.text, &screened.warnings);
+    let mut surfaced = record.suggestions.clone();
+    if best_pick {
+        surfaced.truncate(1);
+    }
+    info!(
+        "生成建议完成: {} 条，耗时 {}ms",
+        record.suggestions.len(),
+        record.latency_ms
+    );
+    notification::notify_suggestions(app, &payload.chat_id, &surfaced);
+    let warnings = screened
+        .warnings
+        .into_iter()
+        .filter(|warning| {
+            surfaced
+                .iter()
+                .any(|suggestion| suggestion.id == warning.suggestion_id)
+        })
+        .collect();
+    let updated = SuggestionsUpdated {
+        chat_id: payload.chat_id.clone(),
+        suggestions: surfaced,
+        reply_source: reply_source_for(payload),
+        warnings,
+        best_pick,
+    };
+    {
+        let mut guard = state.lock().await;
+        guard.record_suggestions(&record);
+        guard.frontend.buffer_suggestions(&updated, now_secs());
+        guard.latest_suggestions = Some(updated.clone());
+    }
+    let _ = app.emit("suggestions.updated", updated.clone());
+    updated
+}
+
+pub fn suggestion_record(
+    chat_id: &str,
+    request: &deepseek::SuggestionRequest,
+    model: &str,
+    started: Instant,
+    suggestions: Vec<Suggestion>,
+) -> SuggestionRecord {
+    SuggestionRecord {
+        id: Uuid::new_v4().to_string(),
+        chat_id: chat_id.to_string(),
+        context_hash: request.context_hash(),
+        model: model.to_string(),
+        fallback: false,
+        latency_ms: started.elapsed().as_millis() as u64,
+        suggestions,
+        written_suggestion_id: None,
+        written_at: None,
+        created_at: now_secs(),
+        prompt_version: request.prompt_version,
+        experiment: request.experiment.clone(),
+    }
+}
+
+fn template_record(
+    payload: &MessageNewPayload,
+    request: &deepseek::SuggestionRequest,
+    started: Instant,
+) -> SuggestionRecord {
+    let suggestions = deepseek::template_suggestions(request);
+    let mut record = suggestion_record(
+        &payload.chat_id,
+        request,
+        TEMPLATE_MODEL,
+        started,
+        suggestions,
+    );
+    record.fallback = true;
+    record
+}
+
+fn emit_unavailable(
+    app: &AppHandle,
+    payload: &MessageNewPayload,
+    failure: &GenerationFailure,
+    retry_scheduled: bool,
+) {
+    let _ = app.emit(
+        "suggestions.unavailable",
+        SuggestionsUnavailable {
+            chat_id: payload.chat_id.clone(),
+            reason: failure.reason(),
+            retry_scheduled,
+        },
+    );
+}
+
+async fn canonicalize_chat(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: MessageNewPayload,
+) -> MessageNewPayload {
+    let (chat_id, resolver) = {
+        let mut guard = state.lock().await;
+        let (chat_id, learned) = guard.canonical_chat_id(&payload.chat_id, &payload.chat_title);
+        (chat_id, learned.then(|| guard.chat_identities.clone()))
+    };
+    if let Some(resolver) = resolver {
+        info!("记录会话映射: {} -> {}", payload.chat_title, chat_id);
+        if let Err(err) = save_chat_identities(app, &resolver) {
+            warn!("保存会话映射失败: {}", err);
+        }
+    }
+    MessageNewPayload { chat_id, ..payload }
+}
+
+async fn classify_content(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: MessageNewPayload,
+) -> Option<MessageNewPayload> {
+    let content_type = content_type::resolve(payload.content_type, &payload.text);
+    let text = match content_type {
+        MessageContentType::Image => recognize_image(state, &payload).await,
+        MessageContentType::Voice => transcribe_voice(app, state, &payload).await,
+        _ => content_type::context_text(content_type, &payload.text),
+    };
+    let Some(text) = text else {
+        info!(
+            "收到非文本消息，跳过建议生成: {} ({:?})",
+            payload.chat_id, content_type
+        );
+        return None;
+    };
+    Some(MessageNewPayload {
+        text,
+        content_type,
+        ..payload
+    })
+}
+
+async fn recognize_image(
+    state: &Arc<Mutex<AppState>>,
+    payload: &MessageNewPayload,
+) -> Option<String> {
+    let image_path = payload.image_path.as_deref()?;
+    let tesseract_path = {
+        let guard = state.lock().await;
+        guard
+            .config
+            .image_ocr_enabled
+            .then(|| guard.config.tesseract_path.clone())?
+    };
+    let image_path = Path::new(image_path);
+    let result = ocr::recognize(&tesseract_path, image_path).await;
+    ocr::remove_downloaded_image(image_path);
+    match result {
+        Ok(text) => content_type::image_context(&text),
+        Err(err) => {
+            warn!("图片文字识别失败: {}", err);
+            None
+        }
+    }
+}
+
+async fn transcribe_voice(
+    app: &AppHandle,
+    state: &Arc<Mutex<AppState>>,
+    payload: &MessageNewPayload,
+) -> Option<String> {
+    let audio_path = payload.audio_path.as_deref()?;
+    let config = {
+        let guard = state.lock().await;
+        guard
+            .config
+            .voice_transcription_enabled
+            .then(|| guard.config.clone())?
+    };
+    let api_key = match ApiKeyManager::get_transcription_api_key() {
+        Ok(key) => key,
+        Err(err) => {
+            warn!("{}", err);
+            None
+        }
+    };
+    let started = Instant::now();
+    let audio_path = Path::new(audio_path);
+    let result = transcription::transcribe(&config, api_key.as_deref(), audio_path).await;
+    transcription::remove_downloaded_audio(audio_path);
+    let text = match result {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("语音转写失败: {}", err);
+            return None;
+        }
+    };
+    info!(
+        "语音转写完成: {}，{} 字",
+        payload.chat_id,
+        graphemes::count(&text)
+    );
+    let _ = app.emit(
+        "transcription.completed",
+        TranscriptionCompleted {
+            chat_id: payload.chat_id.clone(),
+            msg_id: payload.msg_id.clone(),
+            text: text.clone(),
+            latency_ms: started.elapsed().as_millis() as u64,
+        },
+    );
+    content_type::voice_context(&text)
+}
+
 async fn is_duplicate_message(state: &Arc<Mutex<AppState>>, payload: &MessageNewPayload) -> bool {
     let guard = state.lock().await;
     guard.is_duplicate(
@@ -78,22 +843,159 @@ async fn record_message(state: &Arc<Mutex<AppState>>, payload: &MessageNewPayloa
             text: payload.text.clone(),
             timestamp: payload.timestamp,
             msg_id: payload.msg_id.clone(),
+            direction: MessageDirection::Incoming,
         },
     );
+    guard.set_reply_source(&payload.chat_id, reply_source_for(payload));
+    guard.record_chat_account(&payload.chat_id, &payload.account_id);
+}
+
+fn reply_source_for(payload: &MessageNewPayload) -> Option<ReplySource> {
+    let sender_name = payload.sender_name.trim();
+    if !payload.is_group || sender_name.is_empty() || sender_name == payload.chat_title.trim() {
+        return None;
+    }
+    Some(ReplySource {
+        msg_id: payload.msg_id.clone(),
+        sender_name: sender_name.to_string(),
+        text: payload.text.clone(),
+    })
 }
 
-async fn update_state(
+async fn update_target_state(
     state: &Arc<Mutex<AppState>>,
     app: &AppHandle,
+    chat_id: &str,
     runtime: RuntimeState,
-    last_error: impl Into<String>,
+    failure: Option<(&str, String)>,
 ) {
+    let (error_code, detail) = match failure {
+        Some((code, reason)) => (Some(code.to_string()), reason),
+        None => (None, String::new()),
+    };
     let mut guard = state.lock().await;
-    guard.status.state = runtime;
-    guard.status.last_error = last_error.into();
+    let target = guard.set_target_state(chat_id, runtime, error_code, detail, now_secs());
+    let _ = app.emit("target.status.changed", target);
     let _ = app.emit("status.changed", guard.status.clone());
 }
 
 fn emit_error(app: &AppHandle, payload: ErrorPayload) {
     let _ = app.emit("error.raised", payload);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        AutoReplyRule, ChatKind, Config, ContactLanguage, ListenTarget, Politeness, SuggestionStyle,
+    };
+
+    fn message(chat_id: &str, text: &str) -> MessageNewPayload {
+        MessageNewPayload {
+            chat_id: chat_id.to_string(),
+            chat_title: chat_id.to_string(),
+            is_group: false,
+            sender_name: chat_id.to_string(),
+            text: text.to_string(),
+            timestamp: 100,
+            msg_id: None,
+            content_type: MessageContentType::Text,
+            image_path: None,
+            audio_path: None,
+            account_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn shapes_suggestions_by_persona_and_politeness() {
+        let mut state = AppState::new(Config::default(), crate::initial_status());
+        state
+            .personas
+            .save(Persona {
+                name: "客服".to_string(),
+                prompt: None,
+                styles: vec![SuggestionStyle::formal()],
+                auto_send: false,
+                daily_request_limit: 0,
+            })
+            .unwrap();
+        state.listen_targets = vec![ListenTarget {
+            name: "田中".to_string(),
+            kind: ChatKind::Direct,
+            prompt_override: None,
+            persona: Some("客服".to_string()),
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language: Some(ContactLanguage::Ja),
+            politeness: Politeness::Polite,
+        }];
+        let suggestion = |style: SuggestionStyle, text: &str| Suggestion {
+            id: text.to_string(),
+            style,
+            text: text.to_string(),
+        };
+        let generated = vec![
+            suggestion(SuggestionStyle::formal(), "承知いたしました。"),
+            suggestion(SuggestionStyle::formal(), "了解！"),
+            suggestion(SuggestionStyle::casual(), "承知しました。"),
+        ];
+
+        let shaped = shape_suggestions(&state, &message("田中", "明日届く？"), generated.clone());
+        assert_eq!(shaped.len(), 1);
+        assert_eq!(shaped[0].text, "承知いたしました。");
+        let other = shape_suggestions(&state, &message("张三", "在吗"), generated);
+        assert_eq!(other.len(), 3);
+    }
+
+    #[test]
+    fn rule_reply_suppresses_persona_auto_send() {
+        let config = Config {
+            auto_reply_enabled: true,
+            auto_reply_rules: vec![AutoReplyRule {
+                target: "张三".to_string(),
+                keyword: "价格".to_string(),
+                template: "稍后把报价发您".to_string(),
+                canned_response_id: None,
+                hours: None,
+            }],
+            ..Config::default()
+        };
+        let state = AppState::new(config, crate::initial_status());
+        let persona = Persona {
+            name: "客服".to_string(),
+            prompt: None,
+            styles: Vec::new(),
+            auto_send: true,
+            daily_request_limit: 0,
+        };
+        let suggestion = Suggestion {
+            id: "s1".to_string(),
+            style: SuggestionStyle::neutral(),
+            text: "您好，请问需要哪款？".to_string(),
+        };
+        let payload = message("张三", "价格多少？");
+
+        let rule = rule_reply(&state, &payload, 100);
+        let auto_send = persona_reply(
+            Some(&persona),
+            Some(&suggestion),
+            &payload.chat_id,
+            rule.is_some(),
+        );
+        let sends: Vec<_> = rule.into_iter().chain(auto_send).collect();
+        assert_eq!(sends.len(), 1);
+        assert_eq!(sends[0].keyword, "价格");
+        assert_eq!(sends[0].text, "稍后把报价发您");
+
+        let greeting = MessageNewPayload {
+            text: "在吗".to_string(),
+            ..payload
+        };
+        assert!(rule_reply(&state, &greeting, 100).is_none());
+        let sent = persona_reply(Some(&persona), Some(&suggestion), "张三", false).unwrap();
+        assert_eq!(sent.persona.as_deref(), Some("客服"));
+    }
+}
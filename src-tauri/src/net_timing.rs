@@ -0,0 +1,79 @@
+use crate::http_client::{client_builder, ClientKey, TlsSettings};
+use crate::types::ConnectionTiming;
+use anyhow::{Context, Result};
+use reqwest::{Client, Url};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+pub fn estimate_tls_ms(cold_ms: u64, warm_ms: u64, dns_ms: u64, tcp_ms: u64) -> u64 {
+    cold_ms
+        .saturating_sub(warm_ms)
+        .saturating_sub(dns_ms)
+        .saturating_sub(tcp_ms)
+}
+
+fn resolve_and_connect(host: &str, port: u16, timeout: Duration) -> Result<(u64, u64)> {
+    let started = Instant::now();
+    let addr = (host, port)
+        .to_socket_addrs()
+        .context("DNS 解析失败")?
+        .next()
+        .context("DNS 未返回地址")?;
+    let dns_ms = elapsed_ms(started);
+    let started = Instant::now();
+    TcpStream::connect_timeout(&addr, timeout).context("TCP 连接失败")?;
+    Ok((dns_ms, elapsed_ms(started)))
+}
+
+async fn timed_get(client: &Client, url: &str) -> Result<u64> {
+    let started = Instant::now();
+    let response = client.get(url).send().await.context("请求失败")?;
+    response.bytes().await.context("读取响应失败")?;
+    Ok(elapsed_ms(started))
+}
+
+pub async fn measure(
+    base_url: &str,
+    tls: &TlsSettings,
+    timeout: Duration,
+) -> Result<Option<ConnectionTiming>> {
+    if ClientKey::from_env(base_url).uses_proxy() {
+        return Ok(None);
+    }
+    let url = Url::parse(base_url).context("base_url 无效")?;
+    let host = url.host_str().context("base_url 缺少主机名")?.to_string();
+    let port = url.port_or_known_default().context("无法确定端口")?;
+    let https = url.scheme() == "https";
+    let (dns_ms, tcp_ms) =
+        tokio::task::spawn_blocking(move || resolve_and_connect(&host, port, timeout))
+            .await
+            .context("测量任务失败")??;
+    let client = client_builder(tls)?
+        .timeout(timeout)
+        .no_proxy()
+        .build()
+        .context("创建 HTTP 客户端失败")?;
+    let cold_ms = timed_get(&client, base_url).await?;
+    let warm_ms = timed_get(&client, base_url).await?;
+    Ok(Some(ConnectionTiming {
+        dns_ms,
+        tcp_ms,
+        tls_ms: https.then(|| estimate_tls_ms(cold_ms, warm_ms, dns_ms, tcp_ms)),
+        request_ms: warm_ms,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_tls_from_cold_and_warm_requests() {
+        assert_eq!(estimate_tls_ms(420, 120, 30, 50), 220);
+        assert_eq!(estimate_tls_ms(100, 120, 30, 50), 0);
+    }
+}
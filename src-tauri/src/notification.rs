@@ -0,0 +1,176 @@
+use crate::types::Suggestion;
+use tauri::AppHandle;
+
+#[cfg(any(target_os = "windows", test))]
+pub const MAX_TOAST_ACTIONS: usize = 3;
+#[cfg(any(target_os = "windows", test))]
+const TOAST_ACTION_PREFIX: &str = "suggestion:";
+#[cfg(any(target_os = "windows", test))]
+const TOAST_SEND_PREFIX: &str = "send:";
+#[cfg(any(target_os = "windows", test))]
+const TOAST_BUTTON_MAX_CHARS: usize = 18;
+
+pub fn notify_suggestions(app: &AppHandle, chat_id: &str, suggestions: &[Suggestion]) {
+    #[cfg(target_os = "windows")]
+    toast::show_suggestions(app, chat_id, suggestions);
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (app, chat_id, suggestions);
+    }
+}
+
+/// A toast button: write suggestion `index` into the input box, or also send it.
+#[cfg(any(target_os = "windows", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToastAction {
+    pub index: usize,
+    pub send: bool,
+}
+
+#[cfg(any(target_os = "windows", test))]
+pub fn toast_action(action: ToastAction) -> String {
+    let prefix = if action.send {
+        TOAST_SEND_PREFIX
+    } else {
+        TOAST_ACTION_PREFIX
+    };
+    format!("{}{}", prefix, action.index)
+}
+
+#[cfg(any(target_os = "windows", test))]
+pub fn parse_toast_action(action: &str) -> Option<ToastAction> {
+    let (index, send) = match action.strip_prefix(TOAST_SEND_PREFIX) {
+        Some(index) => (index, true),
+        None => (action.strip_prefix(TOAST_ACTION_PREFIX)?, false),
+    };
+    index
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index < MAX_TOAST_ACTIONS)
+        .map(|index| ToastAction { index, send })
+}
+
+#[cfg(any(target_os = "windows", test))]
+pub fn toast_button_label(text: &str) -> String {
+    let trimmed = text.trim();
+    if crate::graphemes::count(trimmed) <= TOAST_BUTTON_MAX_CHARS {
+        return trimmed.to_string();
+    }
+    let mut label = crate::graphemes::truncate(trimmed, TOAST_BUTTON_MAX_CHARS - 1).to_string();
+    label.push('…');
+    label
+}
+
+#[cfg(target_os = "windows")]
+mod toast {
+    use super::{
+        parse_toast_action, toast_action, toast_button_label, ToastAction, MAX_TOAST_ACTIONS,
+    };
+    use crate::types::{ReplyMode, Suggestion};
+    use tauri::{AppHandle, Manager};
+    use tauri_winrt_notification::{Duration, Toast};
+    use tracing::{info, warn};
+
+    const APP_ID: &str = "com.cacr.wereply";
+
+    pub fn show_suggestions(app: &AppHandle, chat_id: &str, suggestions: &[Suggestion]) {
+        if suggestions.is_empty() || main_window_focused(app) {
+            return;
+        }
+        let texts: Vec<String> = suggestions
+            .iter()
+            .take(MAX_TOAST_ACTIONS)
+            .map(|suggestion| suggestion.text.clone())
+            .collect();
+        let app_id = if cfg!(debug_assertions) {
+            Toast::POWERSHELL_APP_ID
+        } else {
+            APP_ID
+        };
+        let mut toast = Toast::new(app_id)
+            .title(&format!("{} 的回复建议", chat_id))
+            .text1(&texts[0])
+            .duration(Duration::Short);
+        for (index, text) in texts.iter().enumerate() {
+            let action = toast_action(ToastAction { index, send: false });
+            toast = toast.add_button(&toast_button_label(text), &action);
+        }
+        // One extra button sends the top suggestion without opening WeChat.
+        let send_first = toast_action(ToastAction {
+            index: 0,
+            send: true,
+        });
+        toast = toast.add_button("直接发送第一条", &send_first);
+
+        let app_handle = app.clone();
+        let chat_id = chat_id.to_string();
+        let toast = toast.on_activated(move |action| {
+            let Some(action) = action.as_deref().and_then(parse_toast_action) else {
+                return Ok(());
+            };
+            let Some(text) = texts.get(action.index).cloned() else {
+                return Ok(());
+            };
+            let state = app_handle.state::<crate::SharedState>().inner().clone();
+            let app_handle = app_handle.clone();
+            let chat_id = chat_id.clone();
+            tauri::async_runtime::spawn(async move {
+                let res = crate::write_suggestion_inner(
+                    &app_handle,
+                    state,
+                    chat_id,
+                    text,
+                    ReplyMode::Plain,
+                    action.send,
+                )
+                .await;
+                if res.success && action.send {
+                    info!("通知快捷回复已发送");
+                } else if res.success {
+                    info!("通知快捷回复已写入输入框");
+                } else {
+                    warn!("通知快捷回复写入失败: {}", res.message);
+                }
+            });
+            Ok(())
+        });
+        if let Err(err) = toast.show() {
+            warn!("显示回复建议通知失败: {}", err);
+        }
+    }
+
+    fn main_window_focused(app: &AppHandle) -> bool {
+        app.get_webview_window("main")
+            .and_then(|window| window.is_focused().ok())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toast_action_round_trips() {
+        for send in [false, true] {
+            let action = ToastAction { index: 2, send };
+            assert_eq!(parse_toast_action(&toast_action(action)), Some(action));
+        }
+        let overflow = ToastAction {
+            index: MAX_TOAST_ACTIONS,
+            send: false,
+        };
+        assert_eq!(parse_toast_action(&toast_action(overflow)), None);
+        assert_eq!(parse_toast_action("send:x"), None);
+        assert_eq!(parse_toast_action("other"), None);
+    }
+
+    #[test]
+    fn toast_button_label_is_truncated() {
+        assert_eq!(toast_button_label(" 好的 "), "好的");
+        let label = toast_button_label("收到，我这边马上确认一下库存情况，稍后回复您");
+        assert_eq!(label.chars().count(), TOAST_BUTTON_MAX_CHARS);
+        assert!(label.ends_with('…'));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::graphemes;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+const OCR_LANGUAGES: &str = "chi_sim+eng";
+const IMAGE_DIR: &str = "wereply_images";
+const OCR_TIMEOUT: Duration = Duration::from_secs(15);
+pub const MAX_OCR_GRAPHEMES: usize = 500;
+
+pub async fn recognize(tesseract_path: &str, image_path: &Path) -> Result<String> {
+    if !image_path.is_file() {
+        anyhow::bail!("图片文件不存在: {}", image_path.display());
+    }
+    let output = Command::new(tesseract_path)
+        .arg(image_path)
+        .arg("stdout")
+        .arg("-l")
+        .arg(OCR_LANGUAGES)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(OCR_TIMEOUT, output)
+        .await
+        .context("图片识别超时")?
+        .with_context(|| format!("启动 tesseract 失败: {}", tesseract_path))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("tesseract 识别失败: {}", stderr.trim());
+    }
+    Ok(normalize_text(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub fn remove_downloaded_image(image_path: &Path) {
+    let image_dir = std::env::temp_dir().join(IMAGE_DIR);
+    if image_path.starts_with(&image_dir) {
+        let _ = std::fs::remove_file(image_path);
+    }
+}
+
+fn normalize_text(raw: &str) -> String {
+    let lines: Vec<String> = raw
+        .lines()
+        .map(collapse_cjk_spacing)
+        .filter(|line| !line.is_empty())
+        .collect();
+    graphemes::truncate(&lines.join(" "), MAX_OCR_GRAPHEMES).to_string()
+}
+
+fn collapse_cjk_spacing(line: &str) -> String {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let mut result = String::new();
+    for (index, word) in words.iter().enumerate() {
+        if index > 0 {
+            let prev_cjk = words[index - 1].chars().last().is_some_and(is_cjk);
+            let next_cjk = word.chars().next().is_some_and(is_cjk);
+            if !(prev_cjk && next_cjk) {
+                result.push(' ');
+            }
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch, '\u{3000}'..='\u{303f}' | '\u{3400}'..='\u{9fff}' | '\u{ff00}'..='\u{ffef}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tesseract_output() {
+        let raw = "订 单 号 : 20240518\n\n  收货人 张 三  \n Total 128.00 元\n\u{c}";
+        assert_eq!(
+            normalize_text(raw),
+            "订单号 : 20240518 收货人张三 Total 128.00 元"
+        );
+        let long = "字".repeat(MAX_OCR_GRAPHEMES + 10);
+        assert_eq!(graphemes::count(&normalize_text(&long)), MAX_OCR_GRAPHEMES);
+    }
+}
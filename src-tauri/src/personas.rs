@@ -0,0 +1,218 @@
+use crate::quota::{self, DailyUsage};
+use crate::types::{Persona, Suggestion};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+const PERSONAS_FILE: &str = "personas.json";
+pub const MAX_PERSONAS: usize = 20;
+const MAX_NAME_CHARS: usize = 16;
+const MAX_PROMPT_CHARS: usize = 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersonaStore {
+    #[serde(default)]
+    personas: Vec<Persona>,
+    #[serde(skip)]
+    usage: HashMap<String, DailyUsage>,
+}
+
+impl PersonaStore {
+    pub fn list(&self) -> Vec<Persona> {
+        self.personas.clone()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Persona> {
+        self.personas.iter().find(|persona| persona.name == name)
+    }
+
+    pub fn save(&mut self, persona: Persona) -> Result<Persona> {
+        let persona = normalize_persona(persona)?;
+        match self
+            .personas
+            .iter_mut()
+            .find(|item| item.name == persona.name)
+        {
+            Some(existing) => *existing = persona.clone(),
+            None => {
+                if self.personas.len() >= MAX_PERSONAS {
+                    anyhow::bail!("人设数量已达上限");
+                }
+                self.personas.push(persona.clone());
+            }
+        }
+        Ok(persona)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.personas.len();
+        self.personas.retain(|persona| persona.name != name);
+        self.usage.remove(name);
+        self.personas.len() != before
+    }
+
+    pub fn quota_exceeded(&self, persona: &Persona, now: u64) -> bool {
+        persona.daily_request_limit > 0
+            && self.usage.get(&persona.name).is_some_and(|usage| {
+                usage.day == quota::day_of(now) && usage.requests >= persona.daily_request_limit
+            })
+    }
+
+    pub fn record_usage(&mut self, name: &str, tokens: u64, now: u64) {
+        self.usage
+            .entry(name.to_string())
+            .or_default()
+            .record(quota::day_of(now), tokens);
+    }
+}
+
+pub fn apply_styles(persona: Option<&Persona>, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+    let Some(persona) = persona.filter(|persona| !persona.styles.is_empty()) else {
+        return suggestions;
+    };
+    let filtered: Vec<Suggestion> = suggestions
+        .iter()
+        .filter(|suggestion| persona.styles.contains(&suggestion.style))
+        .cloned()
+        .collect();
+    if filtered.is_empty() {
+        suggestions
+    } else {
+        filtered
+    }
+}
+
+fn normalize_persona(mut persona: Persona) -> Result<Persona> {
+    persona.name = persona.name.trim().to_string();
+    if persona.name.is_empty() {
+        anyhow::bail!("人设名称不能为空");
+    }
+    if persona.name.chars().count() > MAX_NAME_CHARS {
+        anyhow::bail!("人设名称过长");
+    }
+    persona.prompt = persona
+        .prompt
+        .map(|prompt| prompt.trim().to_string())
+        .filter(|prompt| !prompt.is_empty());
+    if persona
+        .prompt
+        .as_ref()
+        .is_some_and(|prompt| prompt.chars().count() > MAX_PROMPT_CHARS)
+    {
+        anyhow::bail!("人设提示词不能超过 {} 字", MAX_PROMPT_CHARS);
+    }
+    let mut styles = Vec::new();
+    for style in persona.styles {
+        if !styles.contains(&style) {
+            styles.push(style);
+        }
+    }
+    persona.styles = styles;
+    Ok(persona)
+}
+
+pub fn load_personas(app: &AppHandle) -> Result<PersonaStore> {
+    let path = personas_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(PersonaStore::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取人设失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<PersonaStore>(&contents) {
+        Ok(store) => Ok(store),
+        Err(err) => {
+            warn!("解析人设失败，忽略已保存内容: {}", err);
+            Ok(PersonaStore::default())
+        }
+    }
+}
+
+pub fn save_personas(app: &AppHandle, store: &PersonaStore) -> Result<()> {
+    let path = personas_path(app)?;
+    let contents = serde_json::to_string_pretty(store).context("序列化人设失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入人设失败: {}", path.display()))
+}
+
+fn personas_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
+    fs::create_dir_all(&dir).context("创建配置目录失败")?;
+    Ok(dir.join(PERSONAS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SuggestionStyle;
+
+    fn persona(name: &str, limit: u32) -> Persona {
+        Persona {
+            name: name.to_string(),
+            prompt: Some(" 语气专业，先致谢 ".to_string()),
+            styles: vec![SuggestionStyle::formal(), SuggestionStyle::formal()],
+            auto_send: false,
+            daily_request_limit: limit,
+        }
+    }
+
+    fn suggestion(style: SuggestionStyle) -> Suggestion {
+        Suggestion {
+            id: "s".to_string(),
+            style,
+            text: "好的".to_string(),
+        }
+    }
+
+    #[test]
+    fn saves_replaces_and_removes_personas() {
+        let mut store = PersonaStore::default();
+        let saved = store.save(persona(" 客服 ", 0)).unwrap();
+        assert_eq!(saved.name, "客服");
+        assert_eq!(saved.prompt.as_deref(), Some("语气专业，先致谢"));
+        assert_eq!(saved.styles, vec![SuggestionStyle::formal()]);
+
+        let mut casual = persona("客服", 0);
+        casual.styles = vec![SuggestionStyle::casual()];
+        store.save(casual).unwrap();
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(
+            store.get("客服").unwrap().styles,
+            vec![SuggestionStyle::casual()]
+        );
+        assert!(store.save(persona(" ", 0)).is_err());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let mut restored: PersonaStore = serde_json::from_str(&json).unwrap();
+        assert!(restored.remove("客服"));
+        assert!(!restored.remove("客服"));
+    }
+
+    #[test]
+    fn applies_persona_quota_and_styles() {
+        let mut store = PersonaStore::default();
+        let sales = store.save(persona("销售", 2)).unwrap();
+        // Quota days follow the local calendar; stay clear of any midnight.
+        let now = 1_700_000_000;
+        store.record_usage("销售", 100, now + 10);
+        assert!(!store.quota_exceeded(&sales, now + 10));
+        store.record_usage("销售", 100, now + 20);
+        assert!(store.quota_exceeded(&sales, now + 30));
+        assert!(!store.quota_exceeded(&sales, now + 30 + 86_400));
+        assert!(!store.quota_exceeded(&persona("销售", 0), now + 30));
+
+        let all = vec![
+            suggestion(SuggestionStyle::formal()),
+            suggestion(SuggestionStyle::casual()),
+        ];
+        assert_eq!(apply_styles(Some(&sales), all.clone()).len(), 1);
+        assert_eq!(apply_styles(None, all.clone()).len(), 2);
+        let casual_only = vec![suggestion(SuggestionStyle::casual())];
+        assert_eq!(apply_styles(Some(&sales), casual_only).len(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::deepseek::SuggestionRequest;
+use crate::types::Suggestion;
+use regex::Regex;
+use std::sync::OnceLock;
+
+const ID_WEIGHTS: [u32; 17] = [7, 9, 10, 5, 8, 4, 2, 1, 6, 3, 7, 9, 10, 5, 8, 4, 2];
+const ID_CHECK_CODES: [char; 11] = ['1', '0', 'X', '9', '8', '7', '6', '5', '4', '3', '2'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PiiKind {
+    Phone,
+    IdNumber,
+    BankCard,
+}
+
+impl PiiKind {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Phone => "手机号",
+            Self::IdNumber => "身份证号",
+            Self::BankCard => "银行卡号",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    entries: Vec<(String, String)>,
+}
+
+/// Whole runs of digit groups, bounded by anything but ASCII letters and
+/// digits, so a number is never cut out of a longer one.
+fn number_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?-u:\b)[0-9]+(?:[ -][0-9]+)*[Xx]?(?-u:\b)").expect("号码正则无效")
+    })
+}
+
+/// Byte ranges of the numbers in a run, each made of whole digit groups and
+/// the longest that classifies from the left, so neighbouring numbers such as
+/// two phone numbers separated by a space stay apart.
+fn number_spans(run: &str) -> Vec<(usize, usize, PiiKind)> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    for (index, ch) in run.char_indices() {
+        if ch == ' ' || ch == '-' {
+            groups.push((start, index));
+            start = index + 1;
+        }
+    }
+    groups.push((start, run.len()));
+    let mut spans = Vec::new();
+    let mut first = 0;
+    while first < groups.len() {
+        let found = (first..groups.len()).rev().find_map(|last| {
+            let (start, end) = (groups[first].0, groups[last].1);
+            classify(&run[start..end]).map(|kind| (last, start, end, kind))
+        });
+        match found {
+            Some((last, start, end, kind)) => {
+                spans.push((start, end, kind));
+                first = last + 1;
+            }
+            None => first += 1,
+        }
+    }
+    spans
+}
+
+fn classify(candidate: &str) -> Option<PiiKind> {
+    let compact: String = candidate
+        .chars()
+        .filter(|ch| *ch != ' ' && *ch != '-')
+        .collect();
+    let digits = compact.trim_end_matches(['X', 'x']);
+    let has_check_letter = digits.len() < compact.len();
+    match compact.len() {
+        11 if !has_check_letter && is_mobile(digits) => Some(PiiKind::Phone),
+        18 if is_id_number(&compact) => Some(PiiKind::IdNumber),
+        15 if !has_check_letter && !candidate.contains([' ', '-']) => Some(PiiKind::IdNumber),
+        16..=19 if !has_check_letter && passes_luhn(digits) => Some(PiiKind::BankCard),
+        _ => None,
+    }
+}
+
+fn is_mobile(digits: &str) -> bool {
+    let bytes = digits.as_bytes();
+    bytes[0] == b'1' && (b'3'..=b'9').contains(&bytes[1])
+}
+
+fn is_id_number(compact: &str) -> bool {
+    let chars: Vec<char> = compact.chars().collect();
+    if !chars[..17].iter().all(char::is_ascii_digit) {
+        return false;
+    }
+    let sum: u32 = chars[..17]
+        .iter()
+        .zip(ID_WEIGHTS)
+        .map(|(ch, weight)| ch.to_digit(10).unwrap_or(0) * weight)
+        .sum();
+    ID_CHECK_CODES[(sum % 11) as usize] == chars[17].to_ascii_uppercase()
+}
+
+fn passes_luhn(digits: &str) -> bool {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|ch| ch.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| match index % 2 {
+            0 => digit,
+            _ if digit * 2 > 9 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+impl Redaction {
+    pub fn redact(&mut self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        for found in number_pattern().find_iter(text) {
+            for (start, end, kind) in number_spans(found.as_str()) {
+                let (start, end) = (found.start() + start, found.start() + end);
+                output.push_str(&text[last..start]);
+                output.push_str(&self.placeholder(kind, &text[start..end]));
+                last = end;
+            }
+        }
+        output.push_str(&text[last..]);
+        output
+    }
+
+    fn placeholder(&mut self, kind: PiiKind, original: &str) -> String {
+        if let Some((placeholder, _)) = self.entries.iter().find(|(_, value)| value == original) {
+            return placeholder.clone();
+        }
+        let prefix = format!("[{}", kind.label());
+        let index = self
+            .entries
+            .iter()
+            .filter(|(placeholder, _)| placeholder.starts_with(&prefix))
+            .count()
+            + 1;
+        let placeholder = format!("{}{}]", prefix, index);
+        self.entries
+            .push((placeholder.clone(), original.to_string()));
+        placeholder
+    }
+
+    /// Masks every free-text field that reaches the prompts, not just the chat.
+    pub fn redact_request(&mut self, request: &SuggestionRequest) -> SuggestionRequest {
+        let mut redacted = request.clone();
+        for message in redacted.context_messages.iter_mut() {
+            message.text = self.redact(&message.text);
+        }
+        for chunk in redacted.knowledge.iter_mut() {
+            *chunk = self.redact(chunk);
+        }
+        for field in [
+            &mut redacted.context_summary,
+            &mut redacted.contact_note,
+            &mut redacted.session_instruction,
+            &mut redacted.prompt_override,
+            &mut redacted.language_instruction,
+            &mut redacted.style_instruction,
+            &mut redacted.experiment_instruction,
+        ] {
+            if let Some(text) = field.as_mut() {
+                *text = self.redact(text);
+            }
+        }
+        redacted
+    }
+
+    pub fn restore(&self, text: &str) -> String {
+        self.entries
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original)| {
+                text.replace(placeholder.as_str(), original)
+            })
+    }
+
+    pub fn restore_suggestions(&self, suggestions: &mut [Suggestion]) {
+        if self.entries.is_empty() {
+            return;
+        }
+        for suggestion in suggestions.iter_mut() {
+            suggestion.text = self.restore(&suggestion.text);
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deepseek::ContextMessage;
+
+    #[test]
+    fn masks_phone_id_and_bank_card_numbers() {
+        let mut redaction = Redaction::default();
+        let text = "电话13800138000，身份证 11010519491231002X，卡号 6222 0202 0000 0000 006";
+        let masked = redaction.redact(text);
+        assert_eq!(
+            masked,
+            "电话[手机号1]，身份证 [身份证号1]，卡号 [银行卡号1]"
+        );
+        assert_eq!(redaction.count(), 3);
+        assert_eq!(redaction.restore(&masked), text);
+
+        assert_eq!(
+            redaction.redact("再确认下 13800138000"),
+            "再确认下 [手机号1]"
+        );
+        assert_eq!(redaction.redact("打 13900139000"), "打 [手机号2]");
+        let untouched = "订单号 20240101123456789，数量 12345678901";
+        assert_eq!(redaction.redact(untouched), untouched);
+        assert_eq!(redaction.redact("123456789012345678"), "123456789012345678");
+    }
+
+    #[test]
+    fn keeps_neighbouring_numbers_apart() {
+        let mut redaction = Redaction::default();
+        assert_eq!(
+            redaction.redact("13800138000 13900139000"),
+            "[手机号1] [手机号2]"
+        );
+        assert_eq!(
+            redaction.redact("卡号6222 0202 0000 0000 006 13800138000"),
+            "卡号[银行卡号1] [手机号1]"
+        );
+        assert_eq!(
+            redaction.redact("订单 1234567890123 13700137000"),
+            "订单 1234567890123 [手机号3]"
+        );
+        assert_eq!(redaction.redact("ab13800138000"), "ab13800138000");
+    }
+
+    #[test]
+    fn redacts_requests_and_restores_suggestions() {
+        let request = SuggestionRequest {
+            context_messages: vec![ContextMessage {
+                text: "我手机号是 13800138000，打这个".to_string(),
+                age_secs: 10,
+            }],
+            context_summary: Some("对方留了号码 13800138000".to_string()),
+            contact_note: Some("王总，身份证 11010519491231002X，备用 13900139000".to_string()),
+            session_instruction: Some("回款打到 6222 0202 0000 0000 006".to_string()),
+            knowledge: vec!["售后热线 13700137000".to_string()],
+            ..SuggestionRequest::default()
+        };
+        let mut redaction = Redaction::default();
+        let redacted = redaction.redact_request(&request);
+        assert_eq!(
+            redacted.context_messages[0].text,
+            "我手机号是 [手机号1]，打这个"
+        );
+        assert_eq!(
+            redacted.context_summary.as_deref(),
+            Some("对方留了号码 [手机号1]")
+        );
+        assert_eq!(
+            redacted.contact_note.as_deref(),
+            Some("王总，身份证 [身份证号1]，备用 [手机号3]")
+        );
+        assert_eq!(
+            redacted.session_instruction.as_deref(),
+            Some("回款打到 [银行卡号1]")
+        );
+        assert_eq!(redacted.knowledge, vec!["售后热线 [手机号2]"]);
+        let mut suggestions = vec![Suggestion {
+            id: "a".to_string(),
+            style: crate::types::SuggestionStyle::neutral(),
+            text: "好的，稍后打 [手机号1] 联系您".to_string(),
+        }];
+        redaction.restore_suggestions(&mut suggestions);
+        assert_eq!(suggestions[0].text, "好的，稍后打 13800138000 联系您");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::types::{ContactLanguage, ListenTarget, Politeness, Suggestion};
+
+const JA_POLITE_MARKERS: [&str; 8] = [
+    "です",
+    "ます",
+    "ました",
+    "ません",
+    "ください",
+    "ございます",
+    "でしょう",
+    "いたします",
+];
+const KO_POLITE_ENDINGS: [&str; 4] = ["요", "니다", "니까", "세요"];
+const SENTENCE_PUNCTUATION: [char; 10] = ['.', '!', '?', '。', '！', '？', '~', '…', ' ', '^'];
+
+pub fn instruction(target: &ListenTarget) -> Option<String> {
+    let language = target.language?;
+    let text = match (language, target.politeness) {
+        (ContactLanguage::Ja, Politeness::Polite) => {
+            "对方使用日语：请全部用日语回复，并使用敬语（です・ます体）。"
+        }
+        (ContactLanguage::Ja, Politeness::Casual) => {
+            "对方使用日语：请全部用日语回复，使用普通体（タメ口），不要使用です・ます。"
+        }
+        (ContactLanguage::Ko, Politeness::Polite) => {
+            "对方使用韩语：请全部用韩语回复，并使用존댓말（以 -요 或 -습니다 结尾）。"
+        }
+        (ContactLanguage::Ko, Politeness::Casual) => {
+            "对方使用韩语：请全部用韩语回复，使用반말，不要以 -요 或 -습니다 结尾。"
+        }
+    };
+    Some(text.to_string())
+}
+
+pub fn is_polite(language: ContactLanguage, text: &str) -> bool {
+    match language {
+        ContactLanguage::Ja => JA_POLITE_MARKERS.iter().any(|marker| text.contains(marker)),
+        ContactLanguage::Ko => text
+            .split(['.', '!', '?', '\n'])
+            .map(|sentence| sentence.trim_end_matches(SENTENCE_PUNCTUATION))
+            .any(|sentence| {
+                KO_POLITE_ENDINGS
+                    .iter()
+                    .any(|ending| sentence.ends_with(ending))
+            }),
+    }
+}
+
+pub fn apply(target: Option<&ListenTarget>, suggestions: Vec<Suggestion>) -> Vec<Suggestion> {
+    let Some((language, politeness)) = target.and_then(|target| {
+        target
+            .language
+            .map(|language| (language, target.politeness))
+    }) else {
+        return suggestions;
+    };
+    let want_polite = politeness == Politeness::Polite;
+    let filtered: Vec<Suggestion> = suggestions
+        .iter()
+        .filter(|suggestion| is_polite(language, &suggestion.text) == want_polite)
+        .cloned()
+        .collect();
+    if filtered.is_empty() {
+        suggestions
+    } else {
+        filtered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChatKind, SuggestionStyle, TargetPriority};
+
+    fn target(language: Option<ContactLanguage>, politeness: Politeness) -> ListenTarget {
+        ListenTarget {
+            name: "田中".into(),
+            kind: ChatKind::Direct,
+            prompt_override: None,
+            persona: None,
+            muted: false,
+            priority: TargetPriority::Normal,
+            sender_whitelist: Vec::new(),
+            sender_blacklist: Vec::new(),
+            mention_only: false,
+            language,
+            politeness,
+        }
+    }
+
+    fn suggestion(text: &str) -> Suggestion {
+        Suggestion {
+            id: text.to_string(),
+            style: SuggestionStyle::neutral(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn detects_japanese_and_korean_politeness() {
+        let keigo = "承知しました。明日お送りします。";
+        assert!(is_polite(ContactLanguage::Ja, keigo));
+        assert!(!is_polite(ContactLanguage::Ja, "了解、明日送るね！"));
+        assert!(is_polite(ContactLanguage::Ko, "네, 내일 보내드릴게요!"));
+        assert!(is_polite(ContactLanguage::Ko, "감사합니다."));
+        assert!(!is_polite(ContactLanguage::Ko, "알았어, 내일 보낼게~"));
+    }
+
+    #[test]
+    fn builds_instruction_and_filters_mismatched_register() {
+        let keigo = target(Some(ContactLanguage::Ja), Politeness::Polite);
+        assert!(instruction(&keigo).unwrap().contains("敬语"));
+        assert!(instruction(&target(None, Politeness::Casual)).is_none());
+
+        let mixed = vec![suggestion("承知いたしました。"), suggestion("了解！")];
+        let kept = apply(Some(&keigo), mixed.clone());
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].text, "承知いたしました。");
+
+        let banmal = target(Some(ContactLanguage::Ko), Politeness::Casual);
+        let all_polite = vec![suggestion("알겠습니다.")];
+        assert_eq!(apply(Some(&banmal), all_polite).len(), 1);
+        assert_eq!(apply(None, mixed).len(), 2);
+    }
+}
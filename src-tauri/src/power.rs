@@ -0,0 +1,211 @@
+use crate::http_client;
+use crate::types::{Config, LowPowerMode, PowerSource, PowerStatus};
+use crate::SharedState;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::info;
+
+const POWER_CHECK_INTERVAL_SECS: u64 = 60;
+const LOW_POWER_POLL_MULTIPLIER: u64 = 3;
+/// Readiness re-checks only run on every third tick in low power mode.
+const LOW_POWER_CHECK_MULTIPLIER: u64 = 3;
+
+pub fn resolve_power_status(source: PowerSource, mode: LowPowerMode) -> PowerStatus {
+    let low_power = match mode {
+        LowPowerMode::On => true,
+        LowPowerMode::Off => false,
+        LowPowerMode::Auto => source == PowerSource::Battery,
+    };
+    let adjustments = if low_power {
+        vec![
+            format!("监听间隔延长至 {} 倍", LOW_POWER_POLL_MULTIPLIER),
+            "关闭 HTTP 连接保活".to_string(),
+            format!("就绪检查间隔延长至 {} 倍", LOW_POWER_CHECK_MULTIPLIER),
+        ]
+    } else {
+        Vec::new()
+    };
+    PowerStatus {
+        source,
+        low_power,
+        adjustments,
+    }
+}
+
+pub fn effective_poll_interval_ms(config: &Config, power: &PowerStatus) -> u64 {
+    if power.low_power {
+        config
+            .poll_interval_ms
+            .saturating_mul(LOW_POWER_POLL_MULTIPLIER)
+    } else {
+        config.poll_interval_ms
+    }
+}
+
+/// Stores `next` and switches HTTP keep-alives to match; true when low power
+/// mode flipped.
+pub fn apply_power_status(current: &mut PowerStatus, next: PowerStatus) -> bool {
+    let flipped = current.low_power != next.low_power;
+    http_client::set_keep_alive(!next.low_power);
+    *current = next;
+    flipped
+}
+
+/// Whether a periodic background check runs on its `tick`-th wake-up; low
+/// power mode defers all but every `LOW_POWER_CHECK_MULTIPLIER`-th.
+pub fn runs_background_check(power: &PowerStatus, tick: u64) -> bool {
+    !power.low_power || tick % LOW_POWER_CHECK_MULTIPLIER == 0
+}
+
+pub fn spawn_power_monitor(app: AppHandle, state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(POWER_CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let source = detect_power_source();
+            let changed = {
+                let mut guard = state.lock().await;
+                let next = resolve_power_status(source, guard.config.low_power_mode);
+                if next == guard.status.power {
+                    false
+                } else {
+                    info!(
+                        "供电状态变化: source={:?}, low_power={}",
+                        next.source, next.low_power
+                    );
+                    let interval_changed = apply_power_status(&mut guard.status.power, next);
+                    let _ = app.emit("status.changed", guard.status.clone());
+                    interval_changed
+                }
+            };
+            if changed {
+                crate::apply_listen_settings(&app, state.clone()).await;
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+pub fn detect_power_source() -> PowerSource {
+    use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+    use core_foundation::string::{CFString, CFStringRef};
+    use tracing::warn;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOPSCopyPowerSourcesInfo() -> CFTypeRef;
+        fn IOPSGetProvidingPowerSourceType(snapshot: CFTypeRef) -> CFStringRef;
+    }
+
+    unsafe {
+        let snapshot = IOPSCopyPowerSourcesInfo();
+        if snapshot.is_null() {
+            warn!("读取供电信息失败");
+            return PowerSource::Unknown;
+        }
+        let source_type = IOPSGetProvidingPowerSourceType(snapshot);
+        let source = if source_type.is_null() {
+            PowerSource::Unknown
+        } else {
+            power_source_from_label(&CFString::wrap_under_get_rule(source_type).to_string())
+        };
+        CFRelease(snapshot);
+        source
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn detect_power_source() -> PowerSource {
+    use tracing::warn;
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    if let Err(err) = unsafe { GetSystemPowerStatus(&mut status) } {
+        warn!("读取供电信息失败: {}", err);
+        return PowerSource::Unknown;
+    }
+    match status.ACLineStatus {
+        0 => PowerSource::Battery,
+        1 => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn detect_power_source() -> PowerSource {
+    PowerSource::Unknown
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn power_source_from_label(label: &str) -> PowerSource {
+    match label {
+        "AC Power" => PowerSource::Ac,
+        "Battery Power" | "UPS Power" => PowerSource::Battery,
+        _ => PowerSource::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_mode_follows_battery() {
+        let battery = resolve_power_status(PowerSource::Battery, LowPowerMode::Auto);
+        assert!(battery.low_power);
+        assert_eq!(battery.adjustments.len(), 3);
+        let ac = resolve_power_status(PowerSource::Ac, LowPowerMode::Auto);
+        assert!(!ac.low_power);
+        assert!(ac.adjustments.is_empty());
+        assert!(!resolve_power_status(PowerSource::Unknown, LowPowerMode::Auto).low_power);
+    }
+
+    #[test]
+    fn config_overrides_power_source() {
+        assert!(resolve_power_status(PowerSource::Ac, LowPowerMode::On).low_power);
+        assert!(!resolve_power_status(PowerSource::Battery, LowPowerMode::Off).low_power);
+    }
+
+    #[test]
+    fn low_power_stretches_poll_interval() {
+        let config = Config::default();
+        let normal = resolve_power_status(PowerSource::Ac, LowPowerMode::Auto);
+        let low = resolve_power_status(PowerSource::Battery, LowPowerMode::Auto);
+        assert_eq!(
+            effective_poll_interval_ms(&config, &normal),
+            config.poll_interval_ms
+        );
+        assert_eq!(
+            effective_poll_interval_ms(&config, &low),
+            config.poll_interval_ms * LOW_POWER_POLL_MULTIPLIER
+        );
+    }
+
+    #[test]
+    fn low_power_defers_background_checks() {
+        let normal = resolve_power_status(PowerSource::Ac, LowPowerMode::Auto);
+        let low = resolve_power_status(PowerSource::Battery, LowPowerMode::Auto);
+        assert!((0..3).all(|tick| runs_background_check(&normal, tick)));
+        let runs: Vec<u64> = (0..7)
+            .filter(|tick| runs_background_check(&low, *tick))
+            .collect();
+        assert_eq!(runs, vec![0, 3, 6]);
+
+        let mut current = normal.clone();
+        assert!(apply_power_status(&mut current, low.clone()));
+        assert!(!http_client::keep_alive());
+        assert!(!apply_power_status(&mut current, low));
+        assert!(apply_power_status(&mut current, normal));
+        assert!(http_client::keep_alive());
+    }
+
+    #[test]
+    fn parses_macos_power_labels() {
+        assert_eq!(power_source_from_label("AC Power"), PowerSource::Ac);
+        assert_eq!(
+            power_source_from_label("Battery Power"),
+            PowerSource::Battery
+        );
+        assert_eq!(power_source_from_label("Off Line"), PowerSource::Unknown);
+    }
+}
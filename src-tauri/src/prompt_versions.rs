@@ -0,0 +1,277 @@
+use crate::styles;
+use crate::types::{
+    Config, Persona, PromptChange, PromptEntry, PromptSnapshot, PromptVersion, StylePreset,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+const PROMPT_VERSIONS_FILE: &str = "prompt_versions.json";
+pub const MAX_PROMPT_VERSIONS: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptVersionStore {
+    #[serde(default)]
+    versions: Vec<PromptVersion>,
+}
+
+impl PromptVersionStore {
+    pub fn list(&self) -> Vec<PromptVersion> {
+        self.versions.iter().rev().cloned().collect()
+    }
+
+    pub fn get(&self, version: u32) -> Option<&PromptVersion> {
+        self.versions.iter().find(|item| item.version == version)
+    }
+
+    pub fn current(&self) -> u32 {
+        self.versions.last().map(|item| item.version).unwrap_or(0)
+    }
+
+    pub fn record(&mut self, snapshot: PromptSnapshot, source: &str, now: u64) -> Option<u32> {
+        if self
+            .versions
+            .last()
+            .is_some_and(|latest| latest.snapshot == snapshot)
+        {
+            return None;
+        }
+        let version = self.current() + 1;
+        self.versions.push(PromptVersion {
+            version,
+            source: source.to_string(),
+            created_at: now,
+            snapshot,
+        });
+        if self.versions.len() > MAX_PROMPT_VERSIONS {
+            let excess = self.versions.len() - MAX_PROMPT_VERSIONS;
+            self.versions.drain(..excess);
+        }
+        Some(version)
+    }
+}
+
+pub fn snapshot(config: &Config, personas: &[Persona]) -> PromptSnapshot {
+    PromptSnapshot {
+        target_prompts: config
+            .listen_targets
+            .iter()
+            .filter_map(|target| {
+                target.prompt_override.as_ref().map(|prompt| PromptEntry {
+                    name: target.name.clone(),
+                    prompt: prompt.clone(),
+                })
+            })
+            .collect(),
+        persona_prompts: personas
+            .iter()
+            .filter_map(|persona| {
+                persona.prompt.as_ref().map(|prompt| PromptEntry {
+                    name: persona.name.clone(),
+                    prompt: prompt.clone(),
+                })
+            })
+            .collect(),
+        style_presets: config.style_presets.clone(),
+    }
+}
+
+fn lookup<'a>(entries: &'a [PromptEntry], name: &str) -> Option<&'a str> {
+    entries
+        .iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| entry.prompt.as_str())
+}
+
+pub fn restore(snapshot: &PromptSnapshot, config: &mut Config, personas: &mut [Persona]) {
+    for target in config.listen_targets.iter_mut() {
+        target.prompt_override = lookup(&snapshot.target_prompts, &target.name).map(str::to_string);
+    }
+    for persona in personas.iter_mut() {
+        persona.prompt = lookup(&snapshot.persona_prompts, &persona.name).map(str::to_string);
+    }
+    config.style_presets = snapshot.style_presets.clone();
+}
+
+fn describe_preset(preset: &StylePreset) -> String {
+    let instruction = styles::preset_instruction(preset);
+    if instruction.is_empty() {
+        preset.description.clone()
+    } else {
+        format!("{}：{}", preset.description, instruction)
+    }
+}
+
+fn diff_entries(
+    label: &str,
+    from: &[PromptEntry],
+    to: &[PromptEntry],
+    changes: &mut Vec<PromptChange>,
+) {
+    let names = from
+        .iter()
+        .chain(to.iter())
+        .map(|entry| entry.name.as_str());
+    let mut seen: Vec<&str> = Vec::new();
+    for name in names {
+        if seen.contains(&name) {
+            continue;
+        }
+        seen.push(name);
+        let before = lookup(from, name);
+        let after = lookup(to, name);
+        if before != after {
+            changes.push(PromptChange {
+                item: format!("{}：{}", label, name),
+                before: before.map(str::to_string),
+                after: after.map(str::to_string),
+            });
+        }
+    }
+}
+
+pub fn diff(from: &PromptSnapshot, to: &PromptSnapshot) -> Vec<PromptChange> {
+    let mut changes = Vec::new();
+    diff_entries(
+        "监听对象",
+        &from.target_prompts,
+        &to.target_prompts,
+        &mut changes,
+    );
+    diff_entries(
+        "人设",
+        &from.persona_prompts,
+        &to.persona_prompts,
+        &mut changes,
+    );
+    let presets = |snapshot: &PromptSnapshot| -> Vec<PromptEntry> {
+        snapshot
+            .style_presets
+            .iter()
+            .map(|preset| PromptEntry {
+                name: preset.name.clone(),
+                prompt: describe_preset(preset),
+            })
+            .collect()
+    };
+    diff_entries("风格", &presets(from), &presets(to), &mut changes);
+    changes
+}
+
+pub fn load_prompt_versions(app: &AppHandle) -> Result<PromptVersionStore> {
+    let path = prompt_versions_path(app)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(PromptVersionStore::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("读取提示词版本失败: {}", path.display()));
+        }
+    };
+    match serde_json::from_str::<PromptVersionStore>(&contents) {
+        Ok(store) => Ok(store),
+        Err(err) => {
+            warn!("解析提示词版本失败，忽略已保存内容: {}", err);
+            Ok(PromptVersionStore::default())
+        }
+    }
+}
+
+pub fn save_prompt_versions(app: &AppHandle, store: &PromptVersionStore) -> Result<()> {
+    let path = prompt_versions_path(app)?;
+    let contents = serde_json::to_string_pretty(store).context("序列化提示词版本失败")?;
+    fs::write(&path, contents).with_context(|| format!("写入提示词版本失败: {}", path.display()))
+}
+
+fn prompt_versions_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().context("无法获取配置目录")?;
+    fs::create_dir_all(&dir).context("创建配置目录失败")?;
+    Ok(dir.join(PROMPT_VERSIONS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ChatKind, EmojiPolicy, ListenTarget, Politeness, TargetPriority};
+
+    fn config(prompt: Option<&str>) -> Config {
+        Config {
+            listen_targets: vec![ListenTarget {
+                name: "张三".to_string(),
+                kind: ChatKind::Direct,
+                prompt_override: prompt.map(str::to_string),
+                persona: None,
+                muted: false,
+                priority: TargetPriority::Normal,
+                sender_whitelist: Vec::new(),
+                sender_blacklist: Vec::new(),
+                mention_only: false,
+                language: None,
+                politeness: Politeness::Polite,
+            }],
+            ..Config::default()
+        }
+    }
+
+    fn persona(prompt: Option<&str>) -> Persona {
+        Persona {
+            name: "客服".to_string(),
+            prompt: prompt.map(str::to_string),
+            styles: Vec::new(),
+            auto_send: false,
+            daily_request_limit: 0,
+        }
+    }
+
+    #[test]
+    fn records_only_changed_versions() {
+        let mut store = PromptVersionStore::default();
+        assert_eq!(store.current(), 0);
+        let first = snapshot(&config(Some("简洁一点")), &[]);
+        assert_eq!(store.record(first.clone(), "启动", 10), Some(1));
+        assert_eq!(store.record(first, "配置", 20), None);
+        let second = snapshot(&config(None), &[persona(Some("耐心解答"))]);
+        assert_eq!(store.record(second, "人设", 30), Some(2));
+        assert_eq!(store.current(), 2);
+        assert_eq!(store.list()[0].version, 2);
+
+        for index in 0..MAX_PROMPT_VERSIONS {
+            let snapshot = snapshot(&config(Some(&format!("第{}版", index))), &[]);
+            store.record(snapshot, "配置", 40);
+        }
+        assert_eq!(store.list().len(), MAX_PROMPT_VERSIONS);
+        assert!(store.get(1).is_none());
+        assert_eq!(store.current(), MAX_PROMPT_VERSIONS as u32 + 2);
+    }
+
+    #[test]
+    fn diffs_and_restores_snapshots() {
+        let mut old_config = config(Some("简洁一点"));
+        old_config.style_presets = vec![StylePreset {
+            name: "buddy".to_string(),
+            description: "哥们".to_string(),
+            prompt: "像朋友一样说话".to_string(),
+            emoji: EmojiPolicy::Allow,
+        }];
+        let old = snapshot(&old_config, &[persona(None)]);
+        let mut config = config(Some("详细一点"));
+        let mut personas = vec![persona(Some("耐心解答"))];
+        let new = snapshot(&config, &personas);
+
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].item, "监听对象：张三");
+        assert_eq!(changes[0].before.as_deref(), Some("简洁一点"));
+        assert_eq!(changes[0].after.as_deref(), Some("详细一点"));
+        assert_eq!(changes[1].before, None);
+        assert_eq!(changes[2].item, "风格：buddy");
+        assert_eq!(changes[2].before.as_deref(), Some("哥们：像朋友一样说话"));
+        assert!(diff(&new, &new).is_empty());
+
+        restore(&old, &mut config, &mut personas);
+        assert_eq!(snapshot(&config, &personas), old);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::types::Config;
+use chrono::{Local, TimeZone};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DailyUsage {
+    pub day: u64,
+    pub requests: u32,
+    pub tokens: u64,
+}
+
+/// Days since the epoch on the local calendar, so daily limits reset at local
+/// midnight rather than UTC midnight.
+pub fn day_of(now: u64) -> u64 {
+    let offset = Local
+        .timestamp_opt(now as i64, 0)
+        .single()
+        .map(|time| time.offset().local_minus_utc())
+        .unwrap_or(0);
+    day_at_offset(now, offset)
+}
+
+fn day_at_offset(now: u64, offset_secs: i32) -> u64 {
+    now.saturating_add_signed(offset_secs as i64) / SECS_PER_DAY
+}
+
+impl DailyUsage {
+    pub fn record(&mut self, day: u64, tokens: u64) {
+        if self.day != day {
+            *self = Self {
+                day,
+                ..Self::default()
+            };
+        }
+        self.requests = self.requests.saturating_add(1);
+        self.tokens = self.tokens.saturating_add(tokens);
+    }
+
+    pub fn exceeded(&self, config: &Config, day: u64) -> bool {
+        if self.day != day {
+            return false;
+        }
+        let requests_exceeded =
+            config.daily_request_limit > 0 && self.requests >= config.daily_request_limit;
+        let tokens_exceeded =
+            config.daily_token_limit > 0 && self.tokens >= config.daily_token_limit as u64;
+        requests_exceeded || tokens_exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_roll_over_at_local_midnight() {
+        // 2024-01-01 16:30 UTC is already 2024-01-02 00:30 in UTC+8.
+        let now = 1_704_126_600;
+        assert_eq!(day_at_offset(now, 0), 19_723);
+        assert_eq!(day_at_offset(now, 8 * 3600), 19_724);
+        assert_eq!(day_at_offset(now, -5 * 3600), 19_723);
+        assert_eq!(day_at_offset(now - 3600, 8 * 3600), 19_723);
+    }
+
+    #[test]
+    fn limits_apply_within_the_same_day_only() {
+        let config = Config {
+            daily_request_limit: 2,
+            daily_token_limit: 1_000,
+            ..Config::default()
+        };
+        let mut usage = DailyUsage::default();
+        usage.record(10, 300);
+        assert!(!usage.exceeded(&config, 10));
+        usage.record(10, 300);
+        assert!(usage.exceeded(&config, 10));
+        assert!(!usage.exceeded(&config, 11));
+
+        usage.record(11, 1_200);
+        assert_eq!(usage.requests, 1);
+        assert!(usage.exceeded(&config, 11));
+        assert!(!usage.exceeded(&Config::default(), 11));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::graphemes;
+use crate::safety_filter;
+use crate::types::{SafetyWarning, Suggestion, SuggestionStyle};
+use std::collections::HashSet;
+use tracing::debug;
+
+const RELEVANCE_WEIGHT: f32 = 0.5;
+const TONE_WEIGHT: f32 = 0.3;
+const LENGTH_WEIGHT: f32 = 0.2;
+const FLAGGED_PENALTY: f32 = 1.0;
+const MIN_SUGGESTION_GRAPHEMES: usize = 2;
+const MIN_IDEAL_GRAPHEMES: usize = 4;
+const MAX_LENGTH_RATIO: usize = 3;
+const FORMAL_MARKERS: [&str; 8] = ["您", "请", "麻烦", "劳驾", "贵司", "敬请", "烦请", "感谢"];
+const CASUAL_MARKERS: [&str; 10] = ["哈", "啦", "呀", "嘛", "哦", "呗", "滴", "咋", "~", "～"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Formal,
+    Neutral,
+    Casual,
+}
+
+pub fn is_emoji(ch: char) -> bool {
+    matches!(ch, '\u{1f000}'..='\u{1faff}' | '\u{2600}'..='\u{27bf}')
+}
+
+pub fn register(text: &str) -> Register {
+    let formal = FORMAL_MARKERS
+        .iter()
+        .filter(|marker| text.contains(*marker))
+        .count();
+    let casual = CASUAL_MARKERS
+        .iter()
+        .filter(|marker| text.contains(*marker))
+        .count()
+        + usize::from(text.chars().any(is_emoji));
+    match formal.cmp(&casual) {
+        std::cmp::Ordering::Greater => Register::Formal,
+        std::cmp::Ordering::Less => Register::Casual,
+        std::cmp::Ordering::Equal => Register::Neutral,
+    }
+}
+
+fn bigrams(text: &str) -> HashSet<(char, char)> {
+    let chars: Vec<char> = text
+        .chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+fn relevance(text: &str, incoming: &HashSet<(char, char)>) -> f32 {
+    if incoming.is_empty() {
+        return 0.0;
+    }
+    let shared = bigrams(text).intersection(incoming).count();
+    shared as f32 / incoming.len() as f32
+}
+
+fn tone(style: &SuggestionStyle, register: Register) -> f32 {
+    let style = match style.as_str() {
+        "formal" => Register::Formal,
+        "neutral" => Register::Neutral,
+        "casual" => Register::Casual,
+        _ => return 0.5,
+    };
+    match (style, register) {
+        (style, register) if style == register => 1.0,
+        (Register::Neutral, _) | (_, Register::Neutral) => 0.5,
+        _ => 0.0,
+    }
+}
+
+fn length(text: &str, incoming_len: usize) -> f32 {
+    let len = graphemes::count(text);
+    let ideal_max = (incoming_len * MAX_LENGTH_RATIO).max(MIN_IDEAL_GRAPHEMES);
+    let ideal_min = (incoming_len / 2).min(MIN_IDEAL_GRAPHEMES);
+    if len < MIN_SUGGESTION_GRAPHEMES {
+        0.0
+    } else if (ideal_min..=ideal_max).contains(&len) {
+        1.0
+    } else {
+        0.5
+    }
+}
+
+fn score(
+    suggestion: &Suggestion,
+    incoming: &HashSet<(char, char)>,
+    register: Register,
+    incoming_len: usize,
+) -> f32 {
+    RELEVANCE_WEIGHT * relevance(&suggestion.text, incoming)
+        + TONE_WEIGHT * tone(&suggestion.style, register)
+        + LENGTH_WEIGHT * length(&suggestion.text, incoming_len)
+}
+
+pub fn rank(
+    suggestions: Vec<Suggestion>,
+    incoming: &str,
+    warnings: &[SafetyWarning],
+) -> Vec<Suggestion> {
+    let incoming_bigrams = bigrams(incoming);
+    let register = register(incoming);
+    let incoming_len = graphemes::count(incoming);
+    let mut scored: Vec<(f32, Suggestion)> = suggestions
+        .into_iter()
+        .map(|suggestion| {
+            let mut score = score(&suggestion, &incoming_bigrams, register, incoming_len);
+            if safety_filter::is_flagged(warnings, &suggestion.id) {
+                score -= FLAGGED_PENALTY;
+            }
+            (score, suggestion)
+        })
+        .collect();
+    scored.sort_by(|(left, _), (right, _)| right.total_cmp(left));
+    if let Some((top, best)) = scored.first() {
+        debug!("建议排序完成: 最佳 {} 得分 {:.2}", best.style.as_str(), top);
+    }
+    scored
+        .into_iter()
+        .map(|(_, suggestion)| suggestion)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(id: &str, style: &str, text: &str) -> Suggestion {
+        Suggestion {
+            id: id.to_string(),
+            style: SuggestionStyle::new(style),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn detects_register_of_incoming_message() {
+        assert_eq!(register("麻烦您明天上午把合同发过来"), Register::Formal);
+        assert_eq!(register("哈哈晚上吃啥呀"), Register::Casual);
+        assert_eq!(register("好的👍"), Register::Casual);
+        assert_eq!(register("明天开会"), Register::Neutral);
+    }
+
+    #[test]
+    fn ranks_by_relevance_tone_and_safety() {
+        let suggestions = vec![
+            suggestion("a", "casual", "行啊，没问题"),
+            suggestion("b", "neutral", "好"),
+            suggestion("c", "formal", "好的，合同我明天上午发给您"),
+        ];
+        let ranked = rank(suggestions.clone(), "麻烦您明天上午把合同发过来", &[]);
+        let ids: Vec<&str> = ranked.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+
+        let warnings = vec![SafetyWarning {
+            suggestion_id: "c".to_string(),
+            pattern: "合同".to_string(),
+        }];
+        let ranked = rank(suggestions, "麻烦您明天上午把合同发过来", &warnings);
+        assert_eq!(ranked.last().map(|item| item.id.as_str()), Some("c"));
+    }
+
+    #[test]
+    fn keeps_model_order_on_ties() {
+        let suggestions = vec![
+            suggestion("a", "buddy", "收到"),
+            suggestion("b", "brief", "收到"),
+        ];
+        let ranked = rank(suggestions, "", &[]);
+        assert_eq!(ranked[0].id, "a");
+    }
+}
@@ -0,0 +1,218 @@
+use crate::power;
+use crate::secret::ApiKeyManager;
+use crate::types::{Readiness, ReadinessCheck};
+use crate::ui_automation;
+use crate::SharedState;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+const READINESS_CHECK_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadinessFacts {
+    pub agent_connected: bool,
+    pub automation_ready: bool,
+    pub api_key_present: bool,
+    pub api_key_rejected: bool,
+    pub permissions_granted: bool,
+    pub wechat_running: bool,
+}
+
+pub fn evaluate_readiness(facts: &ReadinessFacts) -> Readiness {
+    let backend_available = facts.agent_connected || facts.automation_ready;
+    let api_key_issue = if !facts.api_key_present {
+        "未配置 DeepSeek API Key"
+    } else {
+        "DeepSeek API Key 无效，请重新设置"
+    };
+    let items = [
+        (
+            "agent",
+            "Agent 连接",
+            facts.agent_connected,
+            !backend_available,
+            "Agent 未连接",
+            15,
+        ),
+        (
+            "automation",
+            "本地自动化",
+            facts.automation_ready,
+            !backend_available,
+            "本地自动化不可用",
+            15,
+        ),
+        (
+            "api_key",
+            "API Key",
+            facts.api_key_present && !facts.api_key_rejected,
+            true,
+            api_key_issue,
+            25,
+        ),
+        (
+            "permissions",
+            "系统权限",
+            facts.permissions_granted,
+            true,
+            "缺少辅助功能权限",
+            20,
+        ),
+        (
+            "wechat",
+            "微信状态",
+            facts.wechat_running,
+            true,
+            "微信未运行或未登录",
+            25,
+        ),
+    ];
+    let mut score = 0;
+    let mut checks = Vec::with_capacity(items.len());
+    let mut blocking_issues = Vec::new();
+    for (key, label, ok, blocking, issue, weight) in items {
+        if ok {
+            score += weight;
+        } else if blocking {
+            blocking_issues.push(issue.to_string());
+        }
+        checks.push(ReadinessCheck {
+            key: key.to_string(),
+            label: label.to_string(),
+            ok,
+            blocking: !ok && blocking,
+            detail: if ok { String::new() } else { issue.to_string() },
+        });
+    }
+    Readiness {
+        score,
+        ready: blocking_issues.is_empty(),
+        checks,
+        blocking_issues,
+    }
+}
+
+pub async fn collect_readiness(state: &SharedState) -> Readiness {
+    let (agent_connected, automation, api_key_rejected) = {
+        let guard = state.lock().await;
+        (
+            guard.status.agent_connected,
+            guard.automation.clone(),
+            guard.api_key_rejected,
+        )
+    };
+    let probe = tokio::task::spawn_blocking(|| {
+        (
+            ui_automation::accessibility_granted(),
+            ui_automation::wechat_window_available(),
+        )
+    });
+    let (permissions_granted, wechat_running) = probe.await.unwrap_or_else(|err| {
+        warn!("检测运行环境失败: {}", err);
+        (false, false)
+    });
+    evaluate_readiness(&ReadinessFacts {
+        agent_connected,
+        automation_ready: automation.is_ready(),
+        api_key_present: ApiKeyManager::get_deepseek_api_key().is_ok(),
+        api_key_rejected,
+        permissions_granted,
+        wechat_running,
+    })
+}
+
+pub async fn refresh_readiness(app: AppHandle, state: SharedState) -> Readiness {
+    let next = collect_readiness(&state).await;
+    let mut guard = state.lock().await;
+    if guard.readiness.as_ref() != Some(&next) {
+        info!(
+            "就绪状态变化: score={}, blocking={}",
+            next.score,
+            next.blocking_issues.len()
+        );
+        guard.readiness = Some(next.clone());
+        let _ = app.emit("readiness.changed", next.clone());
+    }
+    next
+}
+
+pub fn spawn_readiness_monitor(app: AppHandle, state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(READINESS_CHECK_INTERVAL_SECS));
+        let mut tick = 0u64;
+        loop {
+            interval.tick().await;
+            let due = power::runs_background_check(&state.lock().await.status.power, tick);
+            tick = tick.wrapping_add(1);
+            if due {
+                refresh_readiness(app.clone(), state.clone()).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_ready() -> ReadinessFacts {
+        ReadinessFacts {
+            agent_connected: true,
+            automation_ready: true,
+            api_key_present: true,
+            api_key_rejected: false,
+            permissions_granted: true,
+            wechat_running: true,
+        }
+    }
+
+    #[test]
+    fn fully_ready_scores_hundred() {
+        let readiness = evaluate_readiness(&all_ready());
+        assert_eq!(readiness.score, 100);
+        assert!(readiness.ready);
+        assert!(readiness.blocking_issues.is_empty());
+        assert_eq!(readiness.checks.len(), 5);
+    }
+
+    #[test]
+    fn one_backend_is_enough() {
+        let readiness = evaluate_readiness(&ReadinessFacts {
+            agent_connected: false,
+            ..all_ready()
+        });
+        assert!(readiness.ready);
+        assert_eq!(readiness.score, 85);
+        let agent = &readiness.checks[0];
+        assert!(!agent.ok);
+        assert!(!agent.blocking);
+
+        let readiness = evaluate_readiness(&ReadinessFacts {
+            agent_connected: false,
+            automation_ready: false,
+            ..all_ready()
+        });
+        assert!(!readiness.ready);
+        assert_eq!(
+            readiness.blocking_issues,
+            vec!["Agent 未连接", "本地自动化不可用"]
+        );
+    }
+
+    #[test]
+    fn rejected_api_key_blocks() {
+        let readiness = evaluate_readiness(&ReadinessFacts {
+            api_key_rejected: true,
+            wechat_running: false,
+            ..all_ready()
+        });
+        assert!(!readiness.ready);
+        assert_eq!(readiness.score, 50);
+        assert_eq!(
+            readiness.blocking_issues,
+            vec!["DeepSeek API Key 无效，请重新设置", "微信未运行或未登录"]
+        );
+    }
+}
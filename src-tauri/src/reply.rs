@@ -0,0 +1,116 @@
+use crate::types::{ReplyMode, ReplySource};
+
+const MENTION_SEPARATOR: char = '\u{2005}';
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReplyPlan {
+    pub text: String,
+    pub quote: Option<ReplySource>,
+}
+
+pub fn plan_reply(
+    mode: ReplyMode,
+    source: Option<ReplySource>,
+    text: String,
+    can_quote: bool,
+) -> Result<ReplyPlan, &'static str> {
+    if mode == ReplyMode::Plain {
+        return Ok(ReplyPlan { text, quote: None });
+    }
+    let Some(source) = source else {
+        return Err("没有可引用的群消息");
+    };
+    if mode == ReplyMode::Quote && can_quote {
+        return Ok(ReplyPlan {
+            text,
+            quote: Some(source),
+        });
+    }
+    Ok(ReplyPlan {
+        text: mention_text(&source.sender_name, &text),
+        quote: None,
+    })
+}
+
+pub fn mention_text(sender_name: &str, text: &str) -> String {
+    let sender_name = sender_name.trim();
+    if sender_name.is_empty() {
+        return text.to_string();
+    }
+    let prefix = format!("@{}", sender_name);
+    if text.starts_with(&prefix) {
+        return text.to_string();
+    }
+    format!("{}{}{}", prefix, MENTION_SEPARATOR, text)
+}
+
+pub fn mentions_self(text: &str, nickname: &str) -> bool {
+    let nickname = nickname.trim();
+    if nickname.is_empty() {
+        return false;
+    }
+    let mention = format!("@{}", nickname);
+    text.match_indices(&mention).any(|(index, _)| {
+        text[index + mention.len()..]
+            .chars()
+            .next()
+            .is_none_or(|next| next == MENTION_SEPARATOR || next.is_whitespace())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> ReplySource {
+        ReplySource {
+            msg_id: Some("m1".to_string()),
+            sender_name: "李四".to_string(),
+            text: "周五能交付吗".to_string(),
+        }
+    }
+
+    #[test]
+    fn plain_mode_keeps_text() {
+        let plan = plan_reply(ReplyMode::Plain, None, "可以".to_string(), true).unwrap();
+        assert_eq!(plan.text, "可以");
+        assert!(plan.quote.is_none());
+    }
+
+    #[test]
+    fn quote_mode_attaches_source_when_supported() {
+        let plan = plan_reply(ReplyMode::Quote, Some(source()), "可以".to_string(), true).unwrap();
+        assert_eq!(plan.text, "可以");
+        assert_eq!(plan.quote, Some(source()));
+    }
+
+    #[test]
+    fn quote_falls_back_to_mention() {
+        let plan = plan_reply(ReplyMode::Quote, Some(source()), "可以".to_string(), false).unwrap();
+        assert_eq!(plan.text, "@李四\u{2005}可以");
+        assert!(plan.quote.is_none());
+        let mention = plan_reply(
+            ReplyMode::Mention,
+            Some(source()),
+            "@李四 可以".to_string(),
+            true,
+        );
+        assert_eq!(mention.unwrap().text, "@李四 可以");
+    }
+
+    #[test]
+    fn targeted_modes_require_source() {
+        assert!(plan_reply(ReplyMode::Mention, None, "可以".to_string(), true).is_err());
+    }
+
+    #[test]
+    fn detects_mentions_of_self() {
+        let text = format!("@小王{}明天开会吗", MENTION_SEPARATOR);
+        assert!(mentions_self(&text, "小王"));
+        assert!(mentions_self("请 @小王 看一下", " 小王 "));
+        assert!(mentions_self("@小王", "小王"));
+        assert!(!mentions_self("@小王八 在吗", "小王"));
+        assert!(!mentions_self("小王在吗", "小王"));
+        assert!(!mentions_self("@小王 在吗", ""));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::deepseek::ContextMessage;
+use crate::state::SELF_PREFIX;
+
+const WRAPPED_PREFIX: &str = "（对方发送了";
+const MIN_KANA: usize = 2;
+const MIN_SCRIPT_CHARS: usize = 2;
+const MIN_LATIN_LETTERS: usize = 8;
+const VIETNAMESE_LETTERS: [char; 6] = ['ơ', 'Ơ', 'ư', 'Ư', 'đ', 'Đ'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyLanguage {
+    Chinese,
+    Japanese,
+    Korean,
+    Russian,
+    Thai,
+    Arabic,
+    Vietnamese,
+    Latin,
+}
+
+#[derive(Default)]
+struct ScriptCounts {
+    han: usize,
+    kana: usize,
+    hangul: usize,
+    cyrillic: usize,
+    thai: usize,
+    arabic: usize,
+    latin: usize,
+    vietnamese: usize,
+}
+
+fn count_scripts(text: &str) -> ScriptCounts {
+    let mut counts = ScriptCounts::default();
+    for ch in text.chars() {
+        match ch {
+            '\u{3040}'..='\u{30ff}' => counts.kana += 1,
+            '\u{3400}'..='\u{9fff}' => counts.han += 1,
+            '\u{1100}'..='\u{11ff}' | '\u{ac00}'..='\u{d7af}' => counts.hangul += 1,
+            '\u{0400}'..='\u{04ff}' => counts.cyrillic += 1,
+            '\u{0e00}'..='\u{0e7f}' => counts.thai += 1,
+            '\u{0600}'..='\u{06ff}' => counts.arabic += 1,
+            'a'..='z' | 'A'..='Z' | '\u{00c0}'..='\u{024f}' => counts.latin += 1,
+            '\u{1ea0}'..='\u{1ef9}' => {
+                counts.latin += 1;
+                counts.vietnamese += 1;
+            }
+            _ => {}
+        }
+        if VIETNAMESE_LETTERS.contains(&ch) {
+            counts.vietnamese += 1;
+        }
+    }
+    counts
+}
+
+pub fn detect(text: &str) -> Option<ReplyLanguage> {
+    let counts = count_scripts(unwrap_context(text));
+    if counts.kana >= MIN_KANA {
+        return Some(ReplyLanguage::Japanese);
+    }
+    let candidates = [
+        (ReplyLanguage::Korean, counts.hangul),
+        (ReplyLanguage::Russian, counts.cyrillic),
+        (ReplyLanguage::Thai, counts.thai),
+        (ReplyLanguage::Arabic, counts.arabic),
+    ];
+    let (language, count) = candidates
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .unwrap_or((ReplyLanguage::Chinese, 0));
+    let han_weight = counts.han * 2;
+    if count >= MIN_SCRIPT_CHARS && count >= han_weight && count >= counts.latin {
+        return Some(language);
+    }
+    if counts.latin >= MIN_LATIN_LETTERS && counts.latin > han_weight {
+        if counts.vietnamese > 0 {
+            return Some(ReplyLanguage::Vietnamese);
+        }
+        return Some(ReplyLanguage::Latin);
+    }
+    (counts.han > 0).then_some(ReplyLanguage::Chinese)
+}
+
+pub fn detect_latest(messages: &[ContextMessage]) -> Option<ReplyLanguage> {
+    let latest = messages
+        .iter()
+        .rev()
+        .find(|message| !message.text.starts_with(SELF_PREFIX))?;
+    detect(&latest.text)
+}
+
+pub fn instruction(language: ReplyLanguage) -> Option<String> {
+    let name = match language {
+        ReplyLanguage::Chinese => return None,
+        ReplyLanguage::Latin => {
+            return Some(
+                "对方使用外语：请使用与对方最后一条消息相同的语言回复，不要使用中文。".to_string(),
+            );
+        }
+        ReplyLanguage::Japanese => "日语",
+        ReplyLanguage::Korean => "韩语",
+        ReplyLanguage::Russian => "俄语",
+        ReplyLanguage::Thai => "泰语",
+        ReplyLanguage::Arabic => "阿拉伯语",
+        ReplyLanguage::Vietnamese => "越南语",
+    };
+    Some(format!(
+        "对方使用{}：请全部用{}回复，不要使用中文。",
+        name, name
+    ))
+}
+
+fn unwrap_context(text: &str) -> &str {
+    match text.strip_prefix(WRAPPED_PREFIX) {
+        Some(rest) => rest
+            .split_once('：')
+            .map(|(_, content)| content.trim_end_matches('）'))
+            .unwrap_or(rest),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_language_by_script() {
+        assert_eq!(detect("明天几点开会？"), Some(ReplyLanguage::Chinese));
+        assert_eq!(detect("这个PR你review一下"), Some(ReplyLanguage::Chinese));
+        assert_eq!(
+            detect("明日の会議は何時ですか？"),
+            Some(ReplyLanguage::Japanese)
+        );
+        assert_eq!(
+            detect("내일 회의는 몇 시예요?"),
+            Some(ReplyLanguage::Korean)
+        );
+        assert_eq!(
+            detect("Во сколько завтра встреча?"),
+            Some(ReplyLanguage::Russian)
+        );
+        assert_eq!(detect("พรุ่งนี้ประชุมกี่โมง"), Some(ReplyLanguage::Thai));
+        assert_eq!(detect("متى الاجتماع غدا؟"), Some(ReplyLanguage::Arabic));
+        assert_eq!(
+            detect("Mấy giờ họp vào ngày mai?"),
+            Some(ReplyLanguage::Vietnamese)
+        );
+        assert_eq!(
+            detect("What time is the meeting tomorrow?"),
+            Some(ReplyLanguage::Latin)
+        );
+        assert_eq!(
+            detect("¿A qué hora es la reunión?"),
+            Some(ReplyLanguage::Latin)
+        );
+        assert_eq!(detect("ok"), None);
+        assert_eq!(detect("👍 123"), None);
+    }
+
+    #[test]
+    fn uses_latest_incoming_message() {
+        let messages = vec![
+            ContextMessage {
+                text: "Can you send the invoice today?".to_string(),
+                age_secs: 60,
+            },
+            ContextMessage {
+                text: "我：好的".to_string(),
+                age_secs: 30,
+            },
+        ];
+        assert_eq!(detect_latest(&messages), Some(ReplyLanguage::Latin));
+        let voice = "（对方发送了语音，内容：See you at the airport tomorrow）";
+        assert_eq!(detect(voice), Some(ReplyLanguage::Latin));
+        assert!(instruction(ReplyLanguage::Chinese).is_none());
+        assert!(instruction(ReplyLanguage::Korean).unwrap().contains("韩语"));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::graphemes;
+use crate::styles;
+use crate::types::{ReplyLengthLimit, StylePreset, Suggestion, SuggestionStyle};
+use anyhow::Result;
+
+const MAX_LENGTH_CHARS: u32 = 500;
+const SENTENCE_BREAKS: [char; 8] = ['。', '！', '？', '!', '?', '.', '；', ';'];
+const CLAUSE_BREAKS: [char; 4] = ['，', ',', '、', '…'];
+const ELLIPSIS: &str = "…";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthViolation {
+    TooShort,
+    TooLong,
+}
+
+pub fn normalize_limits(limits: Vec<ReplyLengthLimit>) -> Vec<ReplyLengthLimit> {
+    limits
+        .into_iter()
+        .map(|limit| ReplyLengthLimit {
+            style: SuggestionStyle::new(limit.style.as_str().trim().to_lowercase()),
+            ..limit
+        })
+        .filter(|limit| limit.min_chars > 0 || limit.max_chars > 0)
+        .collect()
+}
+
+pub fn validate_limits(limits: &[ReplyLengthLimit], presets: &[StylePreset]) -> Result<()> {
+    for (index, limit) in limits.iter().enumerate() {
+        if !styles::is_known(&limit.style, presets) {
+            anyhow::bail!("回复长度限制的风格不存在: {}", limit.style.as_str());
+        }
+        if limits[..index].iter().any(|item| item.style == limit.style) {
+            anyhow::bail!("回复长度限制重复: {}", limit.style.as_str());
+        }
+        if limit.min_chars > MAX_LENGTH_CHARS || limit.max_chars > MAX_LENGTH_CHARS {
+            anyhow::bail!("回复长度限制不能超过 {} 字", MAX_LENGTH_CHARS);
+        }
+        if limit.max_chars > 0 && limit.max_chars < limit.min_chars {
+            anyhow::bail!("回复最大长度不能小于最小长度: {}", limit.style.as_str());
+        }
+    }
+    Ok(())
+}
+
+pub fn limit_for<'a>(
+    style: &SuggestionStyle,
+    limits: &'a [ReplyLengthLimit],
+) -> Option<&'a ReplyLengthLimit> {
+    limits.iter().find(|limit| &limit.style == style)
+}
+
+fn describe(limit: &ReplyLengthLimit) -> String {
+    match (limit.min_chars, limit.max_chars) {
+        (0, max) => format!("不超过 {} 字", max),
+        (min, 0) => format!("至少 {} 字", min),
+        (min, max) => format!("{} 到 {} 字", min, max),
+    }
+}
+
+pub fn instruction(limits: &[ReplyLengthLimit], presets: &[StylePreset]) -> Option<String> {
+    if limits.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = limits
+        .iter()
+        .map(|limit| {
+            format!(
+                "- {}：{}",
+                styles::label(&limit.style, presets),
+                describe(limit)
+            )
+        })
+        .collect();
+    Some(format!("回复长度要求：\n{}", lines.join("\n")))
+}
+
+pub fn style_instruction(style: &SuggestionStyle, limits: &[ReplyLengthLimit]) -> Option<String> {
+    limit_for(style, limits).map(|limit| format!("长度要求：{}", describe(limit)))
+}
+
+pub fn check(suggestion: &Suggestion, limits: &[ReplyLengthLimit]) -> Option<LengthViolation> {
+    let limit = limit_for(&suggestion.style, limits)?;
+    let length = graphemes::count(&suggestion.text) as u32;
+    if limit.max_chars > 0 && length > limit.max_chars {
+        Some(LengthViolation::TooLong)
+    } else if length < limit.min_chars {
+        Some(LengthViolation::TooShort)
+    } else {
+        None
+    }
+}
+
+pub fn too_short(suggestions: &[Suggestion], limits: &[ReplyLengthLimit]) -> Vec<SuggestionStyle> {
+    suggestions
+        .iter()
+        .filter(|item| check(item, limits) == Some(LengthViolation::TooShort))
+        .map(|item| item.style.clone())
+        .collect()
+}
+
+pub fn retry_instruction(
+    styles_to_fix: &[SuggestionStyle],
+    limits: &[ReplyLengthLimit],
+    presets: &[StylePreset],
+) -> String {
+    let lines: Vec<String> = styles_to_fix
+        .iter()
+        .filter_map(|style| limit_for(style, limits))
+        .map(|limit| {
+            format!(
+                "- {}：{}",
+                styles::label(&limit.style, presets),
+                describe(limit)
+            )
+        })
+        .collect();
+    format!(
+        "上一次生成的部分回复太短，请重新生成并严格满足长度要求：\n{}",
+        lines.join("\n")
+    )
+}
+
+pub fn merge_retry(
+    suggestions: &mut [Suggestion],
+    retried: Vec<Suggestion>,
+    limits: &[ReplyLengthLimit],
+) -> usize {
+    let mut replaced = 0;
+    for suggestion in suggestions.iter_mut() {
+        if check(suggestion, limits) != Some(LengthViolation::TooShort) {
+            continue;
+        }
+        let candidate = retried.iter().find(|item| {
+            item.style == suggestion.style && check(item, limits) != Some(LengthViolation::TooShort)
+        });
+        if let Some(candidate) = candidate {
+            suggestion.text = candidate.text.clone();
+            replaced += 1;
+        }
+    }
+    replaced
+}
+
+pub fn truncate_gracefully(text: &str, max_chars: usize) -> String {
+    if graphemes::count(text) <= max_chars {
+        return text.to_string();
+    }
+    let head = graphemes::truncate(text, max_chars);
+    let floor = graphemes::truncate(text, max_chars / 2).len();
+    let cut_after = |breaks: &[char]| {
+        head.char_indices()
+            .filter(|(index, ch)| *index >= floor && breaks.contains(ch))
+            .map(|(index, ch)| index + ch.len_utf8())
+            .next_back()
+    };
+    if let Some(end) = cut_after(&SENTENCE_BREAKS) {
+        return head[..end].trim_end().to_string();
+    }
+    let trimmed = match cut_after(&CLAUSE_BREAKS) {
+        Some(end) => head[..end].trim_end_matches(&CLAUSE_BREAKS[..]),
+        None => graphemes::truncate(text, max_chars.saturating_sub(1)),
+    };
+    format!("{}{}", trimmed.trim_end(), ELLIPSIS)
+}
+
+pub fn enforce(suggestions: &mut [Suggestion], limits: &[ReplyLengthLimit]) -> usize {
+    let mut truncated = 0;
+    for suggestion in suggestions.iter_mut() {
+        if check(suggestion, limits) != Some(LengthViolation::TooLong) {
+            continue;
+        }
+        if let Some(limit) = limit_for(&suggestion.style, limits) {
+            suggestion.text = truncate_gracefully(&suggestion.text, limit.max_chars as usize);
+            truncated += 1;
+        }
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(style: &str, min_chars: u32, max_chars: u32) -> ReplyLengthLimit {
+        ReplyLengthLimit {
+            style: SuggestionStyle::new(style),
+            min_chars,
+            max_chars,
+        }
+    }
+
+    fn suggestion(style: &str, text: &str) -> Suggestion {
+        Suggestion {
+            id: style.to_string(),
+            style: SuggestionStyle::new(style),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn validates_limits_and_formats_instruction() {
+        let limits = normalize_limits(vec![
+            limit(" Formal ", 10, 60),
+            limit("casual", 0, 20),
+            limit("neutral", 0, 0),
+        ]);
+        assert_eq!(limits.len(), 2);
+        assert!(validate_limits(&limits, &[]).is_ok());
+        assert!(validate_limits(&[limit("buddy", 0, 10)], &[]).is_err());
+        assert!(validate_limits(&[limit("formal", 30, 10)], &[]).is_err());
+        assert!(validate_limits(&[limit("formal", 0, 501)], &[]).is_err());
+        let twice = vec![limit("casual", 0, 10), limit("casual", 2, 10)];
+        assert!(validate_limits(&twice, &[]).is_err());
+
+        assert_eq!(
+            instruction(&limits, &[]).unwrap(),
+            "回复长度要求：\n- 正式：10 到 60 字\n- 轻松：不超过 20 字"
+        );
+        assert!(instruction(&[], &[]).is_none());
+        assert_eq!(
+            style_instruction(&SuggestionStyle::formal(), &limits).as_deref(),
+            Some("长度要求：10 到 60 字")
+        );
+    }
+
+    #[test]
+    fn truncates_at_natural_breaks() {
+        assert_eq!(truncate_gracefully("好的", 10), "好的");
+        assert_eq!(
+            truncate_gracefully("好的，没问题。明天上午十点前发给你", 10),
+            "好的，没问题。"
+        );
+        assert_eq!(
+            truncate_gracefully("我这边已经确认过了，明天上午发给你", 12),
+            "我这边已经确认过了…"
+        );
+        let text = truncate_gracefully("明天上午十点前一定把合同发给你", 8);
+        assert_eq!(text, "明天上午十点前…");
+        assert_eq!(graphemes::count(&text), 8);
+    }
+
+    #[test]
+    fn enforces_and_merges_retries() {
+        let limits = vec![limit("formal", 6, 0), limit("casual", 0, 4)];
+        let mut suggestions = vec![
+            suggestion("formal", "好的"),
+            suggestion("neutral", "收到，马上处理"),
+            suggestion("casual", "行啊没问题"),
+        ];
+        assert_eq!(
+            too_short(&suggestions, &limits),
+            vec![SuggestionStyle::formal()]
+        );
+        assert!(
+            retry_instruction(&[SuggestionStyle::formal()], &limits, &[])
+                .ends_with("- 正式：至少 6 字")
+        );
+
+        let retried = vec![
+            suggestion("formal", "好的，我马上安排"),
+            suggestion("casual", "好"),
+        ];
+        assert_eq!(merge_retry(&mut suggestions, retried, &limits), 1);
+        assert_eq!(suggestions[0].text, "好的，我马上安排");
+        assert_eq!(enforce(&mut suggestions, &limits), 1);
+        assert_eq!(suggestions[2].text, "行啊没…");
+        assert_eq!(suggestions[1].text, "收到，马上处理");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::graphemes;
+use crate::types::{SafetyAction, SafetyRule, SafetyWarning, Suggestion};
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use tracing::{info, warn};
+
+pub const MAX_SAFETY_RULES: usize = 100;
+const MAX_PATTERN_CHARS: usize = 200;
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+const MASK: char = '*';
+
+#[derive(Debug, Default)]
+pub struct SafetyFilter {
+    rules: Vec<(SafetyRule, Regex)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Screened {
+    pub suggestions: Vec<Suggestion>,
+    pub warnings: Vec<SafetyWarning>,
+    pub dropped: usize,
+}
+
+pub fn normalize_rules(rules: Vec<SafetyRule>) -> Vec<SafetyRule> {
+    rules
+        .into_iter()
+        .map(|rule| SafetyRule {
+            pattern: rule.pattern.trim().to_string(),
+            ..rule
+        })
+        .filter(|rule| !rule.pattern.is_empty())
+        .collect()
+}
+
+pub fn validate_rules(rules: &[SafetyRule]) -> Result<()> {
+    if rules.len() > MAX_SAFETY_RULES {
+        anyhow::bail!("安全过滤规则不能超过 {} 条", MAX_SAFETY_RULES);
+    }
+    for rule in rules {
+        if rule.pattern.chars().count() > MAX_PATTERN_CHARS {
+            anyhow::bail!("安全过滤规则不能超过 {} 个字符", MAX_PATTERN_CHARS);
+        }
+        compile(rule)?;
+    }
+    Ok(())
+}
+
+fn compile(rule: &SafetyRule) -> Result<Regex> {
+    let pattern = if rule.regex {
+        rule.pattern.clone()
+    } else {
+        regex::escape(&rule.pattern)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!rule.regex)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .with_context(|| format!("安全过滤正则无效: {}", rule.pattern))
+}
+
+impl SafetyFilter {
+    pub fn new(rules: &[SafetyRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| match compile(rule) {
+                Ok(regex) => Some((rule.clone(), regex)),
+                Err(err) => {
+                    warn!("忽略无效的安全过滤规则: {}", err);
+                    None
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn screen(&self, suggestions: Vec<Suggestion>) -> Screened {
+        let mut screened = Screened::default();
+        for mut suggestion in suggestions {
+            let matched: Vec<&(SafetyRule, Regex)> = self
+                .rules
+                .iter()
+                .filter(|(_, regex)| regex.is_match(&suggestion.text))
+                .collect();
+            if matched
+                .iter()
+                .any(|(rule, _)| rule.action == SafetyAction::Drop)
+            {
+                screened.dropped += 1;
+                continue;
+            }
+            for (rule, regex) in matched {
+                match rule.action {
+                    SafetyAction::Mask => suggestion.text = mask(regex, &suggestion.text),
+                    SafetyAction::Flag => screened.warnings.push(SafetyWarning {
+                        suggestion_id: suggestion.id.clone(),
+                        pattern: rule.pattern.clone(),
+                    }),
+                    SafetyAction::Drop => {}
+                }
+            }
+            screened.suggestions.push(suggestion);
+        }
+        if screened.dropped > 0 || !screened.warnings.is_empty() {
+            info!(
+                "安全过滤: 拦截 {} 条建议，标记 {} 处",
+                screened.dropped,
+                screened.warnings.len()
+            );
+        }
+        screened
+    }
+}
+
+fn mask(regex: &Regex, text: &str) -> String {
+    regex
+        .replace_all(text, |caps: &regex::Captures| {
+            MASK.to_string().repeat(graphemes::count(&caps[0]))
+        })
+        .into_owned()
+}
+
+pub fn is_flagged(warnings: &[SafetyWarning], suggestion_id: &str) -> bool {
+    warnings
+        .iter()
+        .any(|warning| warning.suggestion_id == suggestion_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SuggestionStyle;
+
+    fn rule(pattern: &str, regex: bool, action: SafetyAction) -> SafetyRule {
+        SafetyRule {
+            pattern: pattern.to_string(),
+            regex,
+            action,
+        }
+    }
+
+    fn suggestion(id: &str, text: &str) -> Suggestion {
+        Suggestion {
+            id: id.to_string(),
+            style: SuggestionStyle::neutral(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn validates_rules() {
+        let rules = normalize_rules(vec![
+            rule(" 傻 ", false, SafetyAction::Mask),
+            rule("  ", false, SafetyAction::Drop),
+        ]);
+        assert_eq!(rules, vec![rule("傻", false, SafetyAction::Mask)]);
+        assert!(validate_rules(&rules).is_ok());
+        assert!(validate_rules(&[rule("(未闭合", true, SafetyAction::Drop)]).is_err());
+        assert!(validate_rules(&[rule("(未闭合", false, SafetyAction::Drop)]).is_ok());
+        let long = "长".repeat(MAX_PATTERN_CHARS + 1);
+        assert!(validate_rules(&[rule(&long, false, SafetyAction::Flag)]).is_err());
+    }
+
+    #[test]
+    fn drops_masks_and_flags_suggestions() {
+        let filter = SafetyFilter::new(&[
+            rule("滚", false, SafetyAction::Drop),
+            rule("damn", false, SafetyAction::Mask),
+            rule(r"1[3-9]\d{9}", true, SafetyAction::Flag),
+        ]);
+        let screened = filter.screen(vec![
+            suggestion("a", "你给我滚"),
+            suggestion("b", "Damn，这也太巧了"),
+            suggestion("c", "我的电话是 13800138000"),
+            suggestion("d", "好的，收到"),
+        ]);
+        assert_eq!(screened.dropped, 1);
+        let texts: Vec<&str> = screened
+            .suggestions
+            .iter()
+            .map(|item| item.text.as_str())
+            .collect();
+        assert_eq!(
+            texts,
+            vec!["****，这也太巧了", "我的电话是 13800138000", "好的，收到"]
+        );
+        assert_eq!(
+            screened.warnings,
+            vec![SafetyWarning {
+                suggestion_id: "c".to_string(),
+                pattern: r"1[3-9]\d{9}".to_string(),
+            }]
+        );
+        assert!(is_flagged(&screened.warnings, "c"));
+        assert!(!is_flagged(&screened.warnings, "d"));
+    }
+}
@@ -3,6 +3,9 @@ use keyring::Entry;
 
 const SERVICE_NAME: &str = "wereply";
 const API_KEY_NAME: &str = "deepseek_api_key";
+const INTEGRATION_TOKENS_NAME: &str = "integration_tokens";
+const TRANSCRIPTION_KEY_NAME: &str = "transcription_api_key";
+const WECHAT_DB_KEY_NAME: &str = "wechat_db_key";
 pub struct ApiKeyManager;
 
 impl ApiKeyManager {
@@ -35,6 +38,79 @@ impl ApiKeyManager {
         Ok(())
     }
 
+    pub fn get_integration_tokens() -> Result<Option<String>> {
+        let entry = Entry::new(SERVICE_NAME, INTEGRATION_TOKENS_NAME)
+            .context("初始化系统密钥链失败")?;
+        match entry.get_password() {
+            Ok(contents) => Ok(Some(contents)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err).context("读取集成令牌失败"),
+        }
+    }
+
+    pub fn set_integration_tokens(contents: &str) -> Result<()> {
+        let entry = Entry::new(SERVICE_NAME, INTEGRATION_TOKENS_NAME)
+            .context("初始化系统密钥链失败")?;
+        entry
+            .set_password(contents)
+            .context("保存集成令牌失败")?;
+        Ok(())
+    }
+
+    pub fn get_transcription_api_key() -> Result<Option<String>> {
+        let entry = Entry::new(SERVICE_NAME, TRANSCRIPTION_KEY_NAME)
+            .context("初始化系统密钥链失败")?;
+        match entry.get_password() {
+            Ok(key) => Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err).context("读取语音转写密钥失败"),
+        }
+    }
+
+    pub fn set_transcription_api_key(api_key: &str) -> Result<()> {
+        let entry = Entry::new(SERVICE_NAME, TRANSCRIPTION_KEY_NAME)
+            .context("初始化系统密钥链失败")?;
+        if api_key.is_empty() {
+            return match entry.delete_password() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(err) => Err(err).context("删除语音转写密钥失败"),
+            };
+        }
+        entry
+            .set_password(api_key)
+            .context("保存语音转写密钥失败")?;
+        Ok(())
+    }
+
+    #[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+    pub fn get_wechat_db_key() -> Result<Option<String>> {
+        let entry = Entry::new(SERVICE_NAME, WECHAT_DB_KEY_NAME)
+            .context("初始化系统密钥链失败")?;
+        match entry.get_password() {
+            Ok(key) => Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err).context("读取微信数据库密钥失败"),
+        }
+    }
+
+    pub fn set_wechat_db_key(key: &str) -> Result<()> {
+        let entry = Entry::new(SERVICE_NAME, WECHAT_DB_KEY_NAME)
+            .context("初始化系统密钥链失败")?;
+        if key.is_empty() {
+            return match entry.delete_password() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(err) => Err(err).context("删除微信数据库密钥失败"),
+            };
+        }
+        let key = key.strip_prefix("0x").unwrap_or(key);
+        if key.len() != 64 || !key.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            anyhow::bail!("微信数据库密钥应为 64 位十六进制字符串");
+        }
+        entry
+            .set_password(&key.to_ascii_lowercase())
+            .context("保存微信数据库密钥失败")?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -47,4 +123,9 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn reject_invalid_wechat_db_key_format() {
+        assert!(ApiKeyManager::set_wechat_db_key("not-hex").is_err());
+        assert!(ApiKeyManager::set_wechat_db_key(&"ab".repeat(31)).is_err());
+    }
 }
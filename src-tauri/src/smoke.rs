@@ -0,0 +1,191 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+const POLL_ATTEMPTS: u32 = 5;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const PREVIEW_CHATS: usize = 3;
+const SESSION_PAGE: usize = 50;
+
+pub struct CheckResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    pub elapsed_ms: u128,
+}
+
+pub fn run_check(name: &'static str, check: impl FnOnce() -> Result<String>) -> CheckResult {
+    let started = Instant::now();
+    let result = check();
+    let elapsed_ms = started.elapsed().as_millis();
+    match result {
+        Ok(detail) => CheckResult {
+            name,
+            passed: true,
+            detail,
+            elapsed_ms,
+        },
+        Err(err) => CheckResult {
+            name,
+            passed: false,
+            detail: err.to_string(),
+            elapsed_ms,
+        },
+    }
+}
+
+pub fn render_table(results: &[CheckResult]) -> String {
+    let name_width = results
+        .iter()
+        .map(|result| result.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines = Vec::new();
+    for result in results {
+        let padding = name_width - result.name.chars().count();
+        lines.push(format!(
+            "{} {}{}  {:>6}ms  {}",
+            if result.passed { "PASS" } else { "FAIL" },
+            result.name,
+            " ".repeat(padding),
+            result.elapsed_ms,
+            result.detail
+        ));
+    }
+    let passed = results.iter().filter(|result| result.passed).count();
+    lines.push(format!("{}/{} 项通过", passed, results.len()));
+    lines.join("\n")
+}
+
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn finish(results: Vec<CheckResult>) -> i32 {
+    println!("{}", render_table(&results));
+    if results.iter().all(|result| result.passed) {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn list_sessions() -> Result<String> {
+    let automation = crate::ui_automation::build_platform_automation()
+        .ok_or_else(|| anyhow::anyhow!("自动化初始化失败"))?;
+    let page = automation.list_chats(&crate::ui_automation::ChatQuery::new(0, SESSION_PAGE, ""))?;
+    let chats = page.chats;
+    if chats.is_empty() {
+        anyhow::bail!("会话列表为空");
+    }
+    let preview: Vec<&str> = chats
+        .iter()
+        .take(PREVIEW_CHATS)
+        .map(|chat| chat.chat_title.as_str())
+        .collect();
+    let more = if page.has_more { "+" } else { "" };
+    Ok(format!("{}{} 个会话：{}", chats.len(), more, preview.join("、")))
+}
+
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn poll_messages() -> Result<String> {
+    let automation = crate::ui_automation::build_platform_automation()
+        .ok_or_else(|| anyhow::anyhow!("自动化初始化失败"))?;
+    automation.start_listening(Vec::new())?;
+    let mut latest = None;
+    for _ in 0..POLL_ATTEMPTS {
+        if let Some(message) = automation.poll_new_messages()?.pop() {
+            latest = Some(message);
+            break;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    let _ = automation.stop_listening();
+    Ok(match latest {
+        Some(message) => format!(
+            "{}：{}",
+            message.chat_id,
+            crate::graphemes::truncate(&message.text, 20)
+        ),
+        None => format!("监听已启动，{} 秒内无新消息", POLL_ATTEMPTS),
+    })
+}
+
+#[cfg(target_os = "windows")]
+pub fn run_windows() -> i32 {
+    use crate::ui_automation::windows::{UiaClient, UiaInputWriter};
+
+    let results = vec![
+        run_check("窗口发现", || {
+            let client = UiaClient::new()?;
+            let window = client.pick_wechat_window()?;
+            Ok(window.get_name().unwrap_or_default())
+        }),
+        run_check("会话列表", list_sessions),
+        run_check("消息轮询", poll_messages),
+        run_check("输入框定位（不写入）", || {
+            let client = UiaClient::new()?;
+            let window = client.pick_wechat_window()?;
+            let cue = UiaInputWriter::new(client.automation(), &window).locate()?;
+            Ok(format!("定位方式：{}", cue))
+        }),
+    ];
+    finish(results)
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn run_windows() -> i32 {
+    eprintln!("smoke_windows 只能在 Windows 上运行");
+    2
+}
+
+#[cfg(target_os = "macos")]
+pub fn run_macos() -> i32 {
+    use crate::ui_automation::macos::{ax, AxClient, AxInputWriter};
+
+    let front_window = || -> Result<ax::AxElement> {
+        if !ax::check_accessibility() {
+            anyhow::bail!("未授予辅助功能权限");
+        }
+        AxClient::new()?
+            .front_window()
+            .ok_or_else(|| anyhow::anyhow!("未找到微信窗口"))
+    };
+    let results = vec![
+        run_check("窗口发现", || {
+            let window = front_window()?;
+            Ok(ax::title(&window).unwrap_or_default())
+        }),
+        run_check("会话列表", list_sessions),
+        run_check("消息轮询", poll_messages),
+        run_check("输入框定位（不写入）", || {
+            let via = AxInputWriter::new(&front_window()?).locate()?;
+            Ok(format!("定位方式：{}", via))
+        }),
+    ];
+    finish(results)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn run_macos() -> i32 {
+    eprintln!("smoke_macos 只能在 macOS 上运行");
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_pass_fail_table() {
+        let results = vec![
+            run_check("窗口发现", || Ok("微信".to_string())),
+            run_check("输入框定位", || anyhow::bail!("not found")),
+        ];
+        let table = render_table(&results);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("PASS 窗口发现  "));
+        assert!(lines[0].ends_with("微信"));
+        assert!(lines[1].starts_with("FAIL 输入框定位  "));
+        assert!(lines[1].ends_with("not found"));
+        assert_eq!(lines[2], "1/2 项通过");
+        assert_eq!(finish(results), 1);
+    }
+}
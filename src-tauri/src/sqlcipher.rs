@@ -0,0 +1,378 @@
+use crate::types::{CipherSelfTest, DecryptExport, DecryptMethod};
+use rusqlite::{Connection, OpenFlags};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const CLI_ENV: &str = "WEREPLY_SQLCIPHER";
+const CLI_FALLBACK_DIRS: [&str; 2] = ["/opt/homebrew/bin", "/usr/local/bin"];
+const SELF_TEST_KEY: &str = "wereply-self-test";
+
+pub fn self_test(scratch_dir: &Path) -> CipherSelfTest {
+    let cipher_version = bundled_cipher_version();
+    let roundtrip = match cipher_version {
+        Some(_) => roundtrip(scratch_dir),
+        None => Err("内置 SQLite 未启用 SQLCipher".to_string()),
+    };
+    let cli_path = find_cli();
+    let cli_version = cli_path.as_deref().and_then(cli_version);
+    let detail = match (&roundtrip, &cli_path) {
+        (Ok(()), _) => "内置 SQLCipher 加解密自检通过".to_string(),
+        (Err(err), Some(_)) => format!("{}，将使用 sqlcipher 命令行解密", err),
+        (Err(err), None) => format!("{}，且未找到 sqlcipher 命令行", err),
+    };
+    CipherSelfTest {
+        cipher_version,
+        roundtrip_ok: roundtrip.is_ok(),
+        cli_path: cli_path.map(|path| path.to_string_lossy().to_string()),
+        cli_version,
+        detail,
+    }
+}
+
+pub fn export_decrypted(
+    source: &Path,
+    output: &Path,
+    key: &str,
+    compatibility: Option<u32>,
+) -> Result<DecryptExport, String> {
+    if !source.is_file() {
+        return Err(format!("数据库文件不存在: {}", source.display()));
+    }
+    if output.exists() {
+        return Err(format!("导出文件已存在: {}", output.display()));
+    }
+    let pragmas = key_pragmas(key, compatibility)?;
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| format!("创建目录失败: {}", err))?;
+    }
+    let bundled_err = match export_bundled(source, output, &pragmas) {
+        Ok(()) => return finish(source, output, DecryptMethod::Bundled),
+        Err(err) => err,
+    };
+    let _ = std::fs::remove_file(output);
+    let Some(cli) = find_cli() else {
+        return Err(format!("无法解密数据库: {}", bundled_err));
+    };
+    warn!(
+        "内置 SQLCipher 解密失败，改用 sqlcipher 命令行: {}",
+        bundled_err
+    );
+    if let Err(err) = export_cli(&cli, source, output, &pragmas) {
+        let _ = std::fs::remove_file(output);
+        return Err(format!("无法解密数据库: {}", err));
+    }
+    finish(source, output, DecryptMethod::Cli)
+}
+
+/// Opens an encrypted database read-only through the bundled SQLCipher, in
+/// place: no plaintext copy of the database is ever written to disk.
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn open_readonly(source: &Path, pragmas: &str) -> Result<Connection, String> {
+    if !source.is_file() {
+        return Err(format!("数据库文件不存在: {}", source.display()));
+    }
+    if bundled_cipher_version().is_none() {
+        return Err("内置 SQLite 未启用 SQLCipher，无法读取微信数据库".to_string());
+    }
+    let conn = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|err| err.to_string())?;
+    conn.execute_batch(pragmas).map_err(|err| err.to_string())?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|err| format!("密钥错误或数据库已损坏: {}", err))?;
+    Ok(conn)
+}
+
+/// Earlier versions kept plaintext snapshots of the WeChat databases in `dir`;
+/// they are deleted now that the databases are read in place.
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn remove_legacy_snapshots(dir: &Path) {
+    if !dir.is_dir() {
+        return;
+    }
+    match std::fs::remove_dir_all(dir) {
+        Ok(()) => info!("已删除旧版本留下的明文数据库快照: {}", dir.display()),
+        Err(err) => warn!("删除明文数据库快照失败: {}: {}", dir.display(), err),
+    }
+}
+
+/// Pragmas for Windows WeChat databases: the 32-byte key is a passphrase that
+/// still goes through SQLCipher 3's PBKDF2, with 4 KiB pages.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn wechat_windows_pragmas(key: &str) -> Result<String, String> {
+    let hex = wechat_key_hex(key)?;
+    Ok(format!(
+        "PRAGMA hexkey = '{}';\nPRAGMA cipher_compatibility = 3;\nPRAGMA cipher_page_size = 4096;\n",
+        hex
+    ))
+}
+
+/// Pragmas for macOS WeChat databases: the key is used raw (no PBKDF2) with
+/// SQLCipher 3's default 1 KiB pages.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn wechat_macos_pragmas(key: &str) -> Result<String, String> {
+    let hex = wechat_key_hex(key)?;
+    Ok(format!("PRAGMA key = \"x'{}'\";\nPRAGMA cipher_compatibility = 3;\n", hex))
+}
+
+#[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+fn wechat_key_hex(key: &str) -> Result<&str, String> {
+    let key = key.trim();
+    let hex = key.strip_prefix("0x").unwrap_or(key);
+    if hex.len() != 64 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err("微信数据库密钥应为 64 位十六进制字符串".to_string());
+    }
+    Ok(hex)
+}
+
+fn finish(source: &Path, output: &Path, method: DecryptMethod) -> Result<DecryptExport, String> {
+    let tables = Connection::open(output)
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'table'",
+                [],
+                |row| row.get::<_, u32>(0),
+            )
+        })
+        .map_err(|err| format!("导出结果无法读取: {}", err))?;
+    info!(
+        "已解密导出数据库: {} -> {} ({} 张表)",
+        source.display(),
+        output.display(),
+        tables
+    );
+    Ok(DecryptExport {
+        source_path: source.to_string_lossy().to_string(),
+        output_path: output.to_string_lossy().to_string(),
+        method,
+        tables,
+    })
+}
+
+fn key_pragmas(key: &str, compatibility: Option<u32>) -> Result<String, String> {
+    let key = key.trim();
+    let hex = key.strip_prefix("0x").unwrap_or(key);
+    let literal = if matches!(hex.len(), 64 | 96) && hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        format!("\"x'{}'\"", hex)
+    } else if key.is_empty() {
+        return Err("密钥不能为空".to_string());
+    } else {
+        sql_string(key)
+    };
+    let mut pragmas = format!("PRAGMA key = {};\n", literal);
+    match compatibility {
+        None => {}
+        Some(version @ 1..=4) => {
+            pragmas.push_str(&format!("PRAGMA cipher_compatibility = {};\n", version));
+        }
+        Some(version) => return Err(format!("不支持的 SQLCipher 兼容版本: {}", version)),
+    }
+    Ok(pragmas)
+}
+
+fn export_script(pragmas: &str, output: &Path) -> String {
+    format!(
+        "{}SELECT count(*) FROM sqlite_master;\nATTACH DATABASE {} AS plaintext KEY '';\nSELECT sqlcipher_export('plaintext');\nDETACH DATABASE plaintext;\n",
+        pragmas,
+        sql_string(&output.to_string_lossy())
+    )
+}
+
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn export_bundled(source: &Path, output: &Path, pragmas: &str) -> Result<(), String> {
+    let conn = Connection::open(source).map_err(|err| err.to_string())?;
+    conn.execute_batch(pragmas).map_err(|err| err.to_string())?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|err| format!("密钥错误或数据库已损坏: {}", err))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS plaintext KEY ''",
+        [output.to_string_lossy()],
+    )
+    .map_err(|err| err.to_string())?;
+    conn.query_row("SELECT sqlcipher_export('plaintext')", [], |_| Ok(()))
+        .map_err(|err| err.to_string())?;
+    conn.execute_batch("DETACH DATABASE plaintext")
+        .map_err(|err| err.to_string())
+}
+
+fn export_cli(cli: &Path, source: &Path, output: &Path, pragmas: &str) -> Result<(), String> {
+    let mut child = Command::new(cli)
+        .arg("-bail")
+        .arg("-batch")
+        .arg(source)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("启动 sqlcipher 失败: {}", err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(export_script(pragmas, output).as_bytes())
+            .map_err(|err| format!("写入 sqlcipher 命令失败: {}", err))?;
+    }
+    let result = child
+        .wait_with_output()
+        .map_err(|err| format!("等待 sqlcipher 失败: {}", err))?;
+    let stderr = String::from_utf8_lossy(&result.stderr).trim().to_string();
+    if !result.status.success() || !stderr.is_empty() {
+        return Err(format!("sqlcipher 执行失败: {}", stderr));
+    }
+    Ok(())
+}
+
+fn bundled_cipher_version() -> Option<String> {
+    let conn = Connection::open_in_memory().ok()?;
+    conn.query_row("PRAGMA cipher_version", [], |row| row.get::<_, String>(0))
+        .ok()
+        .filter(|version| !version.trim().is_empty())
+}
+
+fn roundtrip(scratch_dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(scratch_dir).map_err(|err| format!("创建目录失败: {}", err))?;
+    let path = scratch_dir.join(format!("cipher-self-test-{}.db", Uuid::new_v4()));
+    let result = roundtrip_at(&path);
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn roundtrip_at(path: &Path) -> Result<(), String> {
+    let pragmas = key_pragmas(SELF_TEST_KEY, None)?;
+    let conn = Connection::open(path).map_err(|err| err.to_string())?;
+    conn.execute_batch(&format!(
+        "{}CREATE TABLE probe (value TEXT); INSERT INTO probe VALUES ('ok');",
+        pragmas
+    ))
+    .map_err(|err| format!("加密写入失败: {}", err))?;
+    drop(conn);
+
+    let conn = Connection::open(path).map_err(|err| err.to_string())?;
+    conn.execute_batch(&pragmas)
+        .map_err(|err| err.to_string())?;
+    let value: String = conn
+        .query_row("SELECT value FROM probe", [], |row| row.get(0))
+        .map_err(|err| format!("使用正确密钥读取失败: {}", err))?;
+    if value != "ok" {
+        return Err("解密结果不一致".to_string());
+    }
+    drop(conn);
+
+    let conn = Connection::open(path).map_err(|err| err.to_string())?;
+    conn.execute_batch(&key_pragmas("wrong-key", None)?)
+        .map_err(|err| err.to_string())?;
+    if conn
+        .query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .is_ok()
+    {
+        return Err("错误密钥仍可读取，数据库未加密".to_string());
+    }
+    Ok(())
+}
+
+fn find_cli() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CLI_ENV).map(PathBuf::from) {
+        if path.is_file() {
+            return Some(path);
+        }
+        warn!("{} 指向的文件不存在: {}", CLI_ENV, path.display());
+    }
+    let name = if cfg!(target_os = "windows") {
+        "sqlcipher.exe"
+    } else {
+        "sqlcipher"
+    };
+    let path_dirs = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    path_dirs
+        .into_iter()
+        .chain(CLI_FALLBACK_DIRS.iter().map(PathBuf::from))
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn cli_version(cli: &Path) -> Option<String> {
+    let output = Command::new(cli).arg("-version").output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_key_pragmas_and_cli_script() {
+        let raw = "ab".repeat(32);
+        assert_eq!(
+            key_pragmas(&format!("0x{}", raw), Some(3)).unwrap(),
+            format!(
+                "PRAGMA key = \"x'{}'\";\nPRAGMA cipher_compatibility = 3;\n",
+                raw
+            )
+        );
+        assert_eq!(
+            key_pragmas("it's", None).unwrap(),
+            "PRAGMA key = 'it''s';\n"
+        );
+        assert!(key_pragmas("  ", None).is_err());
+        assert!(key_pragmas("secret", Some(5)).is_err());
+
+        let script = export_script("PRAGMA key = 'k';\n", Path::new("/tmp/o'k.db"));
+        assert!(script.starts_with("PRAGMA key = 'k';\nSELECT count(*) FROM sqlite_master;\n"));
+        assert!(script.contains("ATTACH DATABASE '/tmp/o''k.db' AS plaintext KEY '';"));
+        assert!(script.ends_with("DETACH DATABASE plaintext;\n"));
+    }
+
+    #[test]
+    fn builds_wechat_windows_pragmas() {
+        let raw = "AB".repeat(32);
+        let pragmas = wechat_windows_pragmas(&format!(" 0x{} ", raw)).unwrap();
+        assert!(pragmas.starts_with(&format!("PRAGMA hexkey = '{}';\n", raw)));
+        assert!(pragmas.contains("PRAGMA cipher_compatibility = 3;"));
+        assert!(pragmas.contains("PRAGMA cipher_page_size = 4096;"));
+        assert!(wechat_windows_pragmas("secret").is_err());
+    }
+
+    #[test]
+    fn builds_wechat_macos_pragmas() {
+        let raw = "cd".repeat(32);
+        let pragmas = wechat_macos_pragmas(&raw).unwrap();
+        assert_eq!(
+            pragmas,
+            format!("PRAGMA key = \"x'{}'\";\nPRAGMA cipher_compatibility = 3;\n", raw)
+        );
+        assert!(wechat_macos_pragmas(&raw[..62]).is_err());
+    }
+
+    #[test]
+    fn removes_legacy_plaintext_snapshots() {
+        let temp = tempfile::tempdir().unwrap();
+        let snapshots = temp.path().join("wechat-db");
+        std::fs::create_dir_all(&snapshots).unwrap();
+        std::fs::write(snapshots.join("MicroMsg.db"), b"plain").unwrap();
+        remove_legacy_snapshots(&snapshots);
+        assert!(!snapshots.exists());
+        remove_legacy_snapshots(&snapshots);
+    }
+
+    #[test]
+    fn export_rejects_missing_source_and_existing_output() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("MSG0.db");
+        let output = temp.path().join("plain.db");
+        assert!(export_decrypted(&source, &output, "secret", None).is_err());
+
+        std::fs::write(&source, b"x").unwrap();
+        std::fs::write(&output, b"x").unwrap();
+        let err = export_decrypted(&source, &output, "secret", None).unwrap_err();
+        assert!(err.contains("已存在"));
+    }
+}
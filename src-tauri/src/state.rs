@@ -1,28 +1,130 @@
-use crate::agent::AgentHandle;
-use crate::listen_targets::{normalize_listen_targets, MAX_LISTEN_TARGETS};
-use crate::types::{ChatSummary, Config, ListenTarget, Status};
+use crate::agent::{AgentHandle, RestartBackoff};
+use crate::auto_reply::AutoReplyLimiter;
+use crate::canned_responses::CannedResponseStore;
+use crate::chat_identity::ChatIdentityResolver;
+use crate::chat_list_cache::ChatListCache;
+use crate::contact_notes::ContactNoteStore;
+use crate::deepseek::{ContextMessage, SuggestionRequest};
+use crate::experiments;
+use crate::frontend_link::FrontendLink;
+use crate::generation::GenerationLimiter;
+use crate::history::HistoryStore;
+use crate::integration_tokens::IntegrationTokenStore;
+use crate::knowledge_base::KnowledgeBase;
+use crate::listen_targets::{
+    find_listen_target, normalize_listen_targets, persona_for_chat, prompt_override_for_chat,
+    MAX_LISTEN_TARGETS,
+};
+use crate::personas::PersonaStore;
+use crate::politeness;
+use crate::prompt_versions::PromptVersionStore;
+use crate::quota::{self, DailyUsage};
+use crate::reply_language;
+use crate::style_fingerprint;
+use crate::tokens;
+use crate::types::{
+    AccountStatus, ChatSummary, Config, ListenTarget, Persona, Readiness, ReplySource,
+    RuntimeState, SessionInstruction, Status, SuggestionRecord, SuggestionsUpdated, TargetStatus,
+};
 use crate::ui_automation::AutomationManager;
-use std::collections::HashMap;
-use tokio::sync::{oneshot, watch};
+use crate::write_queue::WriteQueue;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tracing::warn;
+
+/// Marks the user's own messages in the context sent to the model.
+pub const SELF_PREFIX: &str = "我：";
+const OUTGOING_DEDUPE_SECS: u64 = 120;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageDirection {
+    #[default]
+    Incoming,
+    Outgoing,
+}
+
+impl MessageDirection {
+    pub fn as_i64(self) -> i64 {
+        match self {
+            Self::Incoming => 0,
+            Self::Outgoing => 1,
+        }
+    }
+
+    pub fn from_i64(value: i64) -> Self {
+        if value == 1 {
+            Self::Outgoing
+        } else {
+            Self::Incoming
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct ChatMessage {
     pub text: String,
     pub timestamp: u64,
     pub msg_id: Option<String>,
+    pub direction: MessageDirection,
+}
+
+impl ChatMessage {
+    pub fn is_outgoing(&self) -> bool {
+        self.direction == MessageDirection::Outgoing || self.text.starts_with(SELF_PREFIX)
+    }
+
+    pub fn context_text(&self) -> String {
+        if self.direction == MessageDirection::Outgoing && !self.text.starts_with(SELF_PREFIX) {
+            format!("{}{}", SELF_PREFIX, self.text)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+struct ContextSummary {
+    text: String,
+    updated_at: u64,
 }
 
 pub struct AppState {
     pub config: Config,
     pub status: Status,
     pub agent: Option<AgentHandle>,
+    pub account_agents: BTreeMap<String, AgentHandle>,
+    pub agent_backoffs: BTreeMap<String, RestartBackoff>,
+    pub agent_stopped: bool,
     pub automation: AutomationManager,
     pub automation_stop: Option<watch::Sender<bool>>,
     pub listen_targets: Vec<ListenTarget>,
-    pub recent_chats: Vec<ChatSummary>,
-    pub pending_chats_list: Option<(String, oneshot::Sender<Vec<ChatSummary>>)>,
+    pub recent_chats: ChatListCache,
+    pub canned_responses: CannedResponseStore,
+    pub contact_notes: ContactNoteStore,
+    pub knowledge: KnowledgeBase,
+    pub personas: PersonaStore,
+    pub prompt_versions: PromptVersionStore,
+    pub integration_tokens: IntegrationTokenStore,
+    pub latest_suggestions: Option<SuggestionsUpdated>,
+    pub chat_identities: ChatIdentityResolver,
+    pub write_queue: Arc<WriteQueue>,
+    pub history: Option<HistoryStore>,
+    pub readiness: Option<Readiness>,
+    pub api_key_rejected: bool,
+    pub usage: DailyUsage,
+    pub generations: GenerationLimiter,
+    pub auto_replies: AutoReplyLimiter,
+    pub detected_nickname: Option<String>,
+    pub frontend: FrontendLink,
+    session_state: RuntimeState,
     conversations: HashMap<String, Vec<ChatMessage>>,
+    context_summaries: HashMap<String, ContextSummary>,
     last_message_keys: HashMap<String, String>,
+    session_instructions: HashMap<String, SessionInstruction>,
+    reply_sources: HashMap<String, ReplySource>,
+    chat_accounts: HashMap<String, String>,
+    experiment_turns: HashMap<String, u64>,
 }
 
 impl AppState {
@@ -35,15 +137,40 @@ impl AppState {
         config.listen_targets = listen_targets.clone();
         Self {
             config,
+            session_state: status.state.clone(),
             status,
             agent: None,
+            account_agents: BTreeMap::new(),
+            agent_backoffs: BTreeMap::new(),
+            agent_stopped: false,
             automation: AutomationManager::new(None), // Set by platform automation init.
             automation_stop: None,
             listen_targets,
-            recent_chats: Vec::new(),
-            pending_chats_list: None,
+            recent_chats: ChatListCache::default(),
+            canned_responses: CannedResponseStore::default(),
+            contact_notes: ContactNoteStore::default(),
+            knowledge: KnowledgeBase::default(),
+            personas: PersonaStore::default(),
+            prompt_versions: PromptVersionStore::default(),
+            integration_tokens: IntegrationTokenStore::default(),
+            latest_suggestions: None,
+            chat_identities: ChatIdentityResolver::default(),
+            write_queue: Arc::new(WriteQueue::default()),
+            history: None,
+            readiness: None,
+            api_key_rejected: false,
+            usage: DailyUsage::default(),
+            generations: GenerationLimiter::default(),
+            auto_replies: AutoReplyLimiter::default(),
+            detected_nickname: None,
+            frontend: FrontendLink::default(),
             conversations: HashMap::new(),
+            context_summaries: HashMap::new(),
             last_message_keys: HashMap::new(),
+            session_instructions: HashMap::new(),
+            reply_sources: HashMap::new(),
+            chat_accounts: HashMap::new(),
+            experiment_turns: HashMap::new(),
         }
     }
 
@@ -61,21 +188,558 @@ impl AppState {
             .unwrap_or(false)
     }
 
+    pub fn last_message_key(&self, chat_id: &str) -> Option<String> {
+        self.last_message_keys.get(chat_id).cloned()
+    }
+
     pub fn record_message(&mut self, chat_id: &str, message: ChatMessage) {
         let key = dedupe_key(&message.msg_id, &message.text, message.timestamp);
         self.last_message_keys.insert(chat_id.to_string(), key);
+        if let Some(history) = self.history.as_ref() {
+            if let Err(err) = history.append(chat_id, &message) {
+                warn!("保存历史消息失败: {}", err);
+            }
+        }
 
         let messages = self.conversations.entry(chat_id.to_string()).or_default();
         messages.push(message);
-        trim_messages(messages, &self.config);
+        let evicted = trim_messages(messages, &self.config);
+        self.roll_context_summary(chat_id, evicted);
+    }
+
+    pub fn record_outgoing(&mut self, chat_id: &str, text: &str, timestamp: u64) -> bool {
+        let text = text.trim();
+        if text.is_empty() {
+            return false;
+        }
+        let messages = self.conversations.entry(chat_id.to_string()).or_default();
+        let duplicate = messages.iter().rev().any(|message| {
+            message.direction == MessageDirection::Outgoing
+                && message.text.trim() == text
+                && message.timestamp.abs_diff(timestamp) <= OUTGOING_DEDUPE_SECS
+        });
+        if duplicate {
+            return false;
+        }
+        let message = ChatMessage {
+            text: text.to_string(),
+            timestamp,
+            msg_id: None,
+            direction: MessageDirection::Outgoing,
+        };
+        if let Some(history) = self.history.as_ref() {
+            if let Err(err) = history.append(chat_id, &message) {
+                warn!("保存历史消息失败: {}", err);
+            }
+        }
+        messages.push(message);
+        let evicted = trim_messages(messages, &self.config);
+        self.roll_context_summary(chat_id, evicted);
+        true
+    }
+
+    pub fn seed_conversation(
+        &mut self,
+        chat_id: &str,
+        seeded: Vec<(String, u64)>,
+        now: u64,
+    ) -> usize {
+        let messages = self.conversations.entry(chat_id.to_string()).or_default();
+        let anchor = messages
+            .first()
+            .map(|message| message.timestamp.saturating_sub(1).min(now))
+            .unwrap_or(now);
+        let seeded: Vec<ChatMessage> = seeded
+            .into_iter()
+            .map(|(text, offset_secs)| ChatMessage {
+                direction: if text.starts_with(SELF_PREFIX) {
+                    MessageDirection::Outgoing
+                } else {
+                    MessageDirection::Incoming
+                },
+                text,
+                timestamp: anchor.saturating_sub(offset_secs),
+                msg_id: None,
+            })
+            .collect();
+        if let Some(history) = self.history.as_ref() {
+            for message in &seeded {
+                if let Err(err) = history.append(chat_id, message) {
+                    warn!("保存历史消息失败: {}", err);
+                    break;
+                }
+            }
+        }
+        let seeded_len = seeded.len();
+        let existing = std::mem::replace(messages, seeded);
+        messages.extend(existing);
+        let evicted = trim_messages(messages, &self.config);
+        let kept = seeded_len.saturating_sub(evicted.len());
+        self.roll_context_summary(chat_id, evicted);
+        kept
     }
 
-    pub fn context_for_chat(&self, chat_id: &str) -> Vec<String> {
+    pub fn quota_exceeded(&self, now: u64) -> bool {
+        self.usage.exceeded(&self.config, quota::day_of(now))
+    }
+
+    pub fn record_usage(&mut self, tokens: u64, now: u64) {
+        self.usage.record(quota::day_of(now), tokens);
+        if let Some(history) = self.history.as_ref() {
+            if let Err(err) = history.save_usage(&self.usage) {
+                warn!("保存用量失败: {}", err);
+            }
+        }
+    }
+
+    pub fn session_state(&self) -> RuntimeState {
+        self.session_state.clone()
+    }
+
+    pub fn set_session_state(&mut self, runtime: RuntimeState, last_error: impl Into<String>) {
+        if runtime == RuntimeState::Idle {
+            self.status.targets.clear();
+        }
+        self.session_state = runtime;
+        self.status.last_error = last_error.into();
+        self.status.error_code = None;
+        self.status.state = rollup_state(&self.session_state, &self.status.targets);
+    }
+
+    /// Listening is suspended until WeChat starts again; targets are kept so the
+    /// resumed session picks up where it stopped.
+    /// Runtime state of the agent for `account_id`; the default account follows
+    /// the session.
+    pub fn account_state(&self, account_id: &str) -> RuntimeState {
+        if account_id.is_empty() {
+            return self.session_state();
+        }
+        self.status
+            .accounts
+            .get(account_id)
+            .map_or(RuntimeState::Idle, |account| account.state.clone())
+    }
+
+    pub fn set_account_state(
+        &mut self,
+        account_id: &str,
+        runtime: RuntimeState,
+        last_error: impl Into<String>,
+    ) {
+        if account_id.is_empty() {
+            self.set_session_state(runtime, last_error);
+            return;
+        }
+        let account = self.account_status(account_id);
+        account.state = runtime;
+        account.last_error = last_error.into();
+    }
+
+    pub fn set_account_connected(&mut self, account_id: &str, connected: bool) {
+        if account_id.is_empty() {
+            self.status.agent_connected = connected;
+        } else {
+            self.account_status(account_id).connected = connected;
+        }
+    }
+
+    pub fn set_account_nickname(&mut self, account_id: &str, nickname: Option<String>) {
+        if account_id.is_empty() {
+            self.detected_nickname = nickname;
+        } else {
+            self.account_status(account_id).nickname = nickname;
+        }
+    }
+
+    fn account_status(&mut self, account_id: &str) -> &mut AccountStatus {
+        self.status
+            .accounts
+            .entry(account_id.to_string())
+            .or_insert_with(|| AccountStatus {
+                account_id: account_id.to_string(),
+                connected: false,
+                state: RuntimeState::Idle,
+                last_error: String::new(),
+                nickname: None,
+            })
+    }
+
+    pub fn set_wechat_not_running(&mut self) {
+        self.set_session_state(RuntimeState::Paused, "微信未运行，重新打开后会自动恢复监听");
+        self.status.error_code = Some(crate::wechat_presence::WECHAT_NOT_RUNNING.to_string());
+    }
+
+    pub fn set_target_state(
+        &mut self,
+        chat_id: &str,
+        runtime: RuntimeState,
+        error_code: Option<String>,
+        detail: impl Into<String>,
+        now: u64,
+    ) -> TargetStatus {
+        let target = TargetStatus {
+            chat_id: chat_id.to_string(),
+            state: runtime,
+            error_code,
+            detail: detail.into(),
+            updated_at: now,
+        };
+        self.status
+            .targets
+            .insert(chat_id.to_string(), target.clone());
+        self.status.state = rollup_state(&self.session_state, &self.status.targets);
+        target
+    }
+
+    pub fn record_suggestions(&self, record: &SuggestionRecord) {
+        if let Some(history) = self.history.as_ref() {
+            if let Err(err) = history.append_suggestions(record) {
+                warn!("保存建议记录失败: {}", err);
+            }
+        }
+    }
+
+    pub fn mark_suggestion_written(&self, chat_id: &str, text: &str) {
+        if let Some(history) = self.history.as_ref() {
+            if let Err(err) = history.mark_written(chat_id, text, now_secs()) {
+                warn!("更新建议记录失败: {}", err);
+            }
+        }
+    }
+
+    pub fn mark_suggestion_observed(&self, chat_id: &str, text: &str) -> Option<String> {
+        let history = self.history.as_ref()?;
+        match history.mark_observed(chat_id, text, now_secs()) {
+            Ok(used) => used,
+            Err(err) => {
+                warn!("更新建议记录失败: {}", err);
+                None
+            }
+        }
+    }
+
+    pub fn restore_conversations(&mut self, conversations: HashMap<String, Vec<ChatMessage>>) {
+        for (chat_id, mut messages) in conversations {
+            let evicted = trim_messages(&mut messages, &self.config);
+            self.roll_context_summary(&chat_id, evicted);
+            let Some(last) = messages.last() else {
+                continue;
+            };
+            self.last_message_keys.insert(
+                chat_id.clone(),
+                dedupe_key(&last.msg_id, &last.text, last.timestamp),
+            );
+            self.conversations.insert(chat_id, messages);
+        }
+    }
+
+    pub fn context_for_chat(&self, chat_id: &str, now: u64) -> Vec<ContextMessage> {
+        let max_age_secs = self.config.context_max_age_secs;
         self.conversations
             .get(chat_id)
-            .map(|messages| messages.iter().map(|m| m.text.clone()).collect())
+            .map(|messages| {
+                messages
+                    .iter()
+                    .map(|m| ContextMessage {
+                        text: m.context_text(),
+                        age_secs: now.saturating_sub(m.timestamp),
+                    })
+                    .filter(|m| max_age_secs == 0 || m.age_secs <= max_age_secs)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn set_session_instruction(&mut self, instruction: SessionInstruction) {
+        self.session_instructions
+            .insert(instruction.chat_id.clone(), instruction);
+    }
+
+    pub fn remove_session_instruction(&mut self, chat_id: &str) -> bool {
+        self.session_instructions.remove(chat_id).is_some()
+    }
+
+    pub fn session_instructions(&mut self, now: u64) -> Vec<SessionInstruction> {
+        self.prune_session_instructions(now);
+        let mut items: Vec<SessionInstruction> =
+            self.session_instructions.values().cloned().collect();
+        items.sort_by_key(|item| item.expires_at);
+        items
+    }
+
+    pub fn session_instruction_for_chat(&mut self, chat_id: &str, now: u64) -> Option<String> {
+        self.prune_session_instructions(now);
+        self.session_instructions
+            .get(chat_id)
+            .map(|instruction| instruction.text.clone())
+    }
+
+    pub fn suggestion_request(
+        &mut self,
+        chat_id: &str,
+        chat_title: &str,
+        now: u64,
+    ) -> SuggestionRequest {
+        let context_messages = self.context_for_chat(chat_id, now);
+        let language_instruction = self
+            .listen_target_for_chat(chat_id, chat_title)
+            .and_then(politeness::instruction)
+            .or_else(|| {
+                reply_language::detect_latest(&context_messages)
+                    .and_then(reply_language::instruction)
+            });
+        let knowledge = self
+            .knowledge
+            .retrieve_for_context(&context_messages, self.config.knowledge_top_k as usize);
+        SuggestionRequest {
+            context_messages,
+            session_instruction: self.session_instruction_for_chat(chat_id, now),
+            prompt_override: prompt_override_for_chat(&self.listen_targets, chat_id)
+                .or_else(|| prompt_override_for_chat(&self.listen_targets, chat_title))
+                .or_else(|| {
+                    self.persona_for_chat(chat_id, chat_title)
+                        .and_then(|persona| persona.prompt)
+                }),
+            language_instruction,
+            contact_note: self.contact_notes.note_for_chat(chat_id, chat_title),
+            context_summary: self.context_summary(chat_id, now),
+            style_presets: self.config.style_presets.clone(),
+            length_limits: self.config.reply_length_limits.clone(),
+            followups: self.config.followup_questions_enabled,
+            prompt_version: self.prompt_versions.current(),
+            experiment: None,
+            experiment_instruction: None,
+            style_instruction: self.style_instruction_for_chat(chat_id),
+            knowledge,
+        }
+    }
+
+    fn style_instruction_for_chat(&self, chat_id: &str) -> Option<String> {
+        if !self.config.style_learning_enabled {
+            return None;
+        }
+        let history = self.history.as_ref()?;
+        let texts = match history.outgoing_texts(chat_id, style_fingerprint::SAMPLE_LIMIT) {
+            Ok(texts) => texts,
+            Err(err) => {
+                warn!("读取历史回复失败: {}", err);
+                return None;
+            }
+        };
+        style_fingerprint::fingerprint(&texts)
+            .map(|fingerprint| style_fingerprint::instruction(&fingerprint))
+    }
+
+    pub fn assign_experiment(&mut self, chat_id: &str, request: &mut SuggestionRequest) {
+        let turn = self
+            .experiment_turns
+            .entry(chat_id.to_string())
+            .or_insert(0);
+        let Some((arm, instruction)) =
+            experiments::assign(&self.config.prompt_experiment, chat_id, *turn)
+        else {
+            return;
+        };
+        *turn += 1;
+        request.experiment = Some(arm);
+        request.experiment_instruction = Some(instruction);
+    }
+
+    pub fn listen_target_for_chat(&self, chat_id: &str, chat_title: &str) -> Option<&ListenTarget> {
+        find_listen_target(&self.listen_targets, chat_id)
+            .or_else(|| find_listen_target(&self.listen_targets, chat_title))
+    }
+
+    /// Extra accounts are named after the WeChat nickname they bind to, so the
+    /// id stands in until their agent reports a profile.
+    pub fn self_nickname(&self, account_id: &str) -> Option<String> {
+        if !account_id.is_empty() {
+            return self
+                .status
+                .accounts
+                .get(account_id)
+                .and_then(|account| account.nickname.clone())
+                .or_else(|| Some(account_id.to_string()));
+        }
+        Some(self.config.self_nickname.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .or_else(|| self.detected_nickname.clone())
+    }
+
+    pub fn persona_for_chat(&self, chat_id: &str, chat_title: &str) -> Option<Persona> {
+        let name = persona_for_chat(&self.listen_targets, chat_id)
+            .or_else(|| persona_for_chat(&self.listen_targets, chat_title))?;
+        self.personas.get(&name).cloned()
+    }
+
+    pub fn set_reply_source(&mut self, chat_id: &str, source: Option<ReplySource>) {
+        match source {
+            Some(source) => {
+                self.reply_sources.insert(chat_id.to_string(), source);
+            }
+            None => {
+                self.reply_sources.remove(chat_id);
+            }
+        }
+    }
+
+    pub fn reply_source(&self, chat_id: &str) -> Option<ReplySource> {
+        self.reply_sources.get(chat_id).cloned()
+    }
+
+    // 空账号表示主 Agent 对应的默认微信
+    pub fn record_chat_account(&mut self, chat_id: &str, account_id: &str) {
+        if account_id.is_empty() {
+            self.chat_accounts.remove(chat_id);
+        } else {
+            self.chat_accounts
+                .insert(chat_id.to_string(), account_id.to_string());
+        }
+    }
+
+    pub fn account_for_chat(&self, chat_id: &str) -> &str {
+        self.chat_accounts
+            .get(chat_id)
+            .map(String::as_str)
             .unwrap_or_default()
     }
+
+    pub fn agent_for_account(&self, account_id: &str) -> Option<&AgentHandle> {
+        if account_id.is_empty() {
+            self.agent.as_ref()
+        } else {
+            self.account_agents.get(account_id)
+        }
+    }
+
+    pub fn agent_for_chat(&self, chat_id: &str) -> Option<&AgentHandle> {
+        self.agent_for_account(self.account_for_chat(chat_id))
+    }
+
+    pub fn agents(&self) -> impl Iterator<Item = &AgentHandle> {
+        self.agent.iter().chain(self.account_agents.values())
+    }
+
+    pub fn canonical_chat_id(&mut self, chat_id: &str, chat_title: &str) -> (String, bool) {
+        let (canonical, learned) = self.chat_identities.canonicalize(chat_id, chat_title);
+        if learned {
+            self.merge_chat_alias(chat_title.trim(), &canonical);
+        }
+        (canonical, learned)
+    }
+
+    pub fn learn_chat_identities(&mut self, chats: &[ChatSummary]) -> bool {
+        let learned = self.chat_identities.learn_chats(chats);
+        for (alias, canonical) in &learned {
+            self.merge_chat_alias(alias, canonical);
+        }
+        !learned.is_empty()
+    }
+
+    fn merge_chat_alias(&mut self, alias: &str, canonical: &str) {
+        if let Some(history) = self.history.as_ref() {
+            if let Err(err) = history.rename_chat(alias, canonical) {
+                warn!("合并历史消息失败: {}", err);
+            }
+        }
+        if let Some(mut messages) = self.conversations.remove(alias) {
+            let target = self.conversations.entry(canonical.to_string()).or_default();
+            messages.append(target);
+            messages.sort_by_key(|message| message.timestamp);
+            *target = messages;
+            let evicted = trim_messages(target, &self.config);
+            if let Some(summary) = self.context_summaries.remove(alias) {
+                self.context_summaries
+                    .entry(canonical.to_string())
+                    .or_insert(summary);
+            }
+            self.roll_context_summary(canonical, evicted);
+        }
+        if let Some(key) = self.last_message_keys.remove(alias) {
+            self.last_message_keys
+                .entry(canonical.to_string())
+                .or_insert(key);
+        }
+        if let Some(source) = self.reply_sources.remove(alias) {
+            self.reply_sources
+                .entry(canonical.to_string())
+                .or_insert(source);
+        }
+        if let Some(account_id) = self.chat_accounts.remove(alias) {
+            self.chat_accounts
+                .entry(canonical.to_string())
+                .or_insert(account_id);
+        }
+        if let Some(mut instruction) = self.session_instructions.remove(alias) {
+            instruction.chat_id = canonical.to_string();
+            self.session_instructions
+                .entry(canonical.to_string())
+                .or_insert(instruction);
+        }
+    }
+
+    fn roll_context_summary(&mut self, chat_id: &str, evicted: Vec<ChatMessage>) {
+        let Some(last) = evicted.last().map(|message| message.timestamp) else {
+            return;
+        };
+        let previous = self.context_summaries.get(chat_id);
+        let text = tokens::roll_summary(
+            previous.map(|summary| summary.text.as_str()),
+            evicted.iter().map(|message| message.text.as_str()),
+        );
+        match text {
+            Some(text) => {
+                let updated_at = previous.map_or(last, |summary| summary.updated_at.max(last));
+                self.context_summaries
+                    .insert(chat_id.to_string(), ContextSummary { text, updated_at });
+            }
+            None => {
+                self.context_summaries.remove(chat_id);
+            }
+        }
+    }
+
+    pub fn context_summary(&self, chat_id: &str, now: u64) -> Option<String> {
+        let max_age_secs = self.config.context_max_age_secs;
+        self.context_summaries
+            .get(chat_id)
+            .filter(|summary| {
+                max_age_secs == 0 || now.saturating_sub(summary.updated_at) <= max_age_secs
+            })
+            .map(|summary| summary.text.clone())
+    }
+
+    fn prune_session_instructions(&mut self, now: u64) {
+        self.session_instructions
+            .retain(|_, instruction| instruction.expires_at > now);
+    }
+}
+
+pub fn rollup_state(
+    session: &RuntimeState,
+    targets: &BTreeMap<String, TargetStatus>,
+) -> RuntimeState {
+    if *session != RuntimeState::Listening {
+        return session.clone();
+    }
+    let mut states = targets.values().map(|target| &target.state);
+    if states
+        .clone()
+        .any(|state| *state == RuntimeState::Generating)
+    {
+        RuntimeState::Generating
+    } else if !targets.is_empty() && states.all(|state| *state == RuntimeState::Error) {
+        RuntimeState::Error
+    } else {
+        RuntimeState::Listening
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
 }
 
 fn dedupe_key(msg_id: &Option<String>, text: &str, timestamp: u64) -> String {
@@ -85,32 +749,41 @@ fn dedupe_key(msg_id: &Option<String>, text: &str, timestamp: u64) -> String {
         .unwrap_or_else(|| format!("{}:{}", text, timestamp))
 }
 
-fn trim_messages(messages: &mut Vec<ChatMessage>, config: &Config) {
+fn trim_messages(messages: &mut Vec<ChatMessage>, config: &Config) -> Vec<ChatMessage> {
     let max_messages = config.context_max_messages as usize;
-    while messages.len() > max_messages {
-        messages.remove(0);
-    }
+    let overflow = messages.len().saturating_sub(max_messages);
+    let mut evicted: Vec<ChatMessage> = messages.drain(0..overflow).collect();
 
     let max_chars = config.context_max_chars as usize;
-    let mut total_chars = 0;
-    let mut keep_start = messages.len();
-    for (index, message) in messages.iter().enumerate().rev() {
-        total_chars += message.text.chars().count();
-        if total_chars > max_chars {
-            keep_start = index + 1;
-            break;
-        }
+    let keep_start = budget_start(messages, max_chars, |text| text.chars().count());
+    if keep_start > 0 && keep_start < messages.len() {
+        evicted.extend(messages.drain(0..keep_start));
     }
+
+    let max_tokens = tokens::context_budget(&config.deepseek_model);
+    let keep_start = budget_start(messages, max_tokens, tokens::estimate_message);
     if keep_start > 0 && keep_start < messages.len() {
-        messages.drain(0..keep_start);
+        evicted.extend(messages.drain(0..keep_start));
     }
+    evicted
+}
+
+fn budget_start(messages: &[ChatMessage], budget: usize, cost: impl Fn(&str) -> usize) -> usize {
+    let mut total = 0;
+    for (index, message) in messages.iter().enumerate().rev() {
+        total += cost(&message.text);
+        if total > budget {
+            return index + 1;
+        }
+    }
+    0
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::types::RuntimeState;
-    use crate::types::{Platform, Status};
+    use crate::types::{AutomationStrategy, Platform, PowerStatus, Status};
 
     #[test]
     fn trims_by_message_count() {
@@ -123,6 +796,11 @@ mod tests {
             platform: Platform::Unknown,
             agent_connected: false,
             last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
         };
         let mut state = AppState::new(config, status);
         for i in 0..3 {
@@ -132,11 +810,383 @@ mod tests {
                     text: format!("msg{}", i),
                     timestamp: i,
                     msg_id: None,
+                    direction: MessageDirection::Incoming,
                 },
             );
         }
-        let context = state.context_for_chat("c1");
+        let context = state.context_for_chat("c1", 10);
         assert_eq!(context.len(), 2);
-        assert_eq!(context[0], "msg1");
+        assert_eq!(context[0].text, "msg1");
+        assert_eq!(context[0].age_secs, 9);
+    }
+
+    #[test]
+    fn drops_context_older_than_horizon() {
+        let config = Config {
+            context_max_age_secs: 600,
+            ..Config::default()
+        };
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(config, status);
+        for (text, timestamp) in [("旧话题", 100), ("新话题", 1000)] {
+            state.record_message(
+                "c1",
+                ChatMessage {
+                    text: text.to_string(),
+                    timestamp,
+                    msg_id: None,
+                    direction: MessageDirection::Incoming,
+                },
+            );
+        }
+        let context = state.context_for_chat("c1", 1200);
+        assert_eq!(context.len(), 1);
+        assert_eq!(context[0].text, "新话题");
+        assert_eq!(context[0].age_secs, 200);
+    }
+
+    #[test]
+    fn records_outgoing_messages_with_direction() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status);
+        state.history = Some(HistoryStore::open_in_memory().unwrap());
+        state.record_message(
+            "c1",
+            ChatMessage {
+                text: "合同发了吗？".to_string(),
+                timestamp: 100,
+                msg_id: None,
+                direction: MessageDirection::Incoming,
+            },
+        );
+        assert!(state.record_outgoing("c1", " 已经发到邮箱了 ", 110));
+        assert!(!state.record_outgoing("c1", "已经发到邮箱了", 150));
+        assert!(!state.record_outgoing("c1", "  ", 150));
+        assert!(state.record_outgoing("c1", "已经发到邮箱了", 400));
+
+        let context = state.context_for_chat("c1", 400);
+        let texts: Vec<&str> = context.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["合同发了吗？", "我：已经发到邮箱了", "我：已经发到邮箱了"]
+        );
+        let restored = state.history.as_ref().unwrap().load_recent(10).unwrap();
+        let messages = &restored["c1"];
+        assert_eq!(messages[1].direction, MessageDirection::Outgoing);
+        assert_eq!(messages[1].text, "已经发到邮箱了");
+        assert!(messages[1].is_outgoing());
+        assert!(!messages[0].is_outgoing());
+    }
+
+    #[test]
+    fn learns_reply_style_from_sent_history() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status);
+        state.history = Some(HistoryStore::open_in_memory().unwrap());
+        for (offset, text) in ["哈哈好呀", "行啊👍", "没问题哦", "晚点说嘛", "收到啦"]
+            .into_iter()
+            .enumerate()
+        {
+            state.record_outgoing("c1", text, 100 + offset as u64 * 60);
+        }
+        assert!(state
+            .suggestion_request("c1", "张三", 500)
+            .style_instruction
+            .is_none());
+
+        state.config.style_learning_enabled = true;
+        let instruction = state
+            .suggestion_request("c1", "张三", 500)
+            .style_instruction;
+        assert!(instruction.unwrap().contains("平均每条约 4 字"));
+        assert!(state
+            .suggestion_request("c2", "李四", 500)
+            .style_instruction
+            .is_none());
+    }
+
+    #[test]
+    fn trims_by_token_budget_and_rolls_summary() {
+        let config = Config {
+            context_max_messages: 50,
+            context_max_chars: 100_000,
+            context_max_age_secs: 0,
+            ..Config::default()
+        };
+        let budget = tokens::context_budget(&config.deepseek_model);
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(config, status);
+        let long = "合".repeat(budget);
+        for (index, text) in ["周五前把合同发我", long.as_str(), long.as_str(), "收到"]
+            .into_iter()
+            .enumerate()
+        {
+            state.record_message(
+                "c1",
+                ChatMessage {
+                    text: text.to_string(),
+                    timestamp: index as u64,
+                    msg_id: None,
+                    direction: MessageDirection::Incoming,
+                },
+            );
+        }
+        let context = state.context_for_chat("c1", 10);
+        let used: usize = context
+            .iter()
+            .map(|message| tokens::estimate_message(&message.text))
+            .sum();
+        assert!(used <= budget);
+        assert_eq!(context.last().unwrap().text, "收到");
+        let summary = state.context_summary("c1", 10).unwrap();
+        assert!(summary.starts_with("周五前把合同发我\n"));
+        assert!(summary.ends_with('…'));
+
+        let request = state.suggestion_request("c1", "c1", 10);
+        assert_eq!(request.context_summary, Some(summary));
+        assert!(state.context_summary("c2", 10).is_none());
+    }
+
+    #[test]
+    fn session_instructions_expire() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status);
+        state.set_session_instruction(SessionInstruction {
+            chat_id: "c1".to_string(),
+            text: "今天统一回复：下周一发货".to_string(),
+            expires_at: 100,
+        });
+        assert_eq!(
+            state.session_instruction_for_chat("c1", 99).as_deref(),
+            Some("今天统一回复：下周一发货")
+        );
+        assert!(state.session_instruction_for_chat("c1", 100).is_none());
+        assert!(state.session_instructions(100).is_empty());
+    }
+
+    #[test]
+    fn merges_title_keyed_state_into_canonical_id() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status);
+        let (chat_id, learned) = state.canonical_chat_id("张三", "张三");
+        assert!(!learned);
+        state.record_message(
+            &chat_id,
+            ChatMessage {
+                text: "在吗".to_string(),
+                timestamp: 1,
+                msg_id: None,
+                direction: MessageDirection::Incoming,
+            },
+        );
+        state.set_session_instruction(SessionInstruction {
+            chat_id: chat_id.clone(),
+            text: "语气礼貌".to_string(),
+            expires_at: 100,
+        });
+        state.record_chat_account(&chat_id, "work");
+        state.record_chat_account("李四", "work");
+        state.record_chat_account("李四", "");
+
+        let (chat_id, learned) = state.canonical_chat_id("wxid_a", "张三");
+        assert_eq!(chat_id, "wxid_a");
+        assert!(learned);
+        state.record_message(
+            &chat_id,
+            ChatMessage {
+                text: "明天见".to_string(),
+                timestamp: 2,
+                msg_id: Some("m2".to_string()),
+                direction: MessageDirection::Incoming,
+            },
+        );
+        let context: Vec<String> = state
+            .context_for_chat("wxid_a", 2)
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+        assert_eq!(context, vec!["在吗", "明天见"]);
+        assert!(state.context_for_chat("张三", 2).is_empty());
+        assert!(state.is_duplicate("wxid_a", &Some("m2".to_string()), "明天见", 2));
+        assert_eq!(
+            state.session_instruction_for_chat("wxid_a", 1).as_deref(),
+            Some("语气礼貌")
+        );
+        assert_eq!(state.canonical_chat_id("张三", "张三").0, "wxid_a");
+        assert_eq!(state.account_for_chat("wxid_a"), "work");
+        assert_eq!(state.account_for_chat("李四"), "");
+        assert!(state.agent_for_chat("wxid_a").is_none());
+    }
+
+    #[test]
+    fn rehydrates_context_from_history() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status.clone());
+        state.history = Some(HistoryStore::open_in_memory().unwrap());
+        state.record_message(
+            "c1",
+            ChatMessage {
+                text: "周五开会".to_string(),
+                timestamp: 10,
+                msg_id: Some("m1".to_string()),
+                direction: MessageDirection::Incoming,
+            },
+        );
+        let conversations = state.history.as_ref().unwrap().load_recent(10).unwrap();
+
+        let mut restarted = AppState::new(Config::default(), status);
+        restarted.restore_conversations(conversations);
+        assert_eq!(restarted.context_for_chat("c1", 20)[0].text, "周五开会");
+        assert!(restarted.is_duplicate("c1", &Some("m1".to_string()), "周五开会", 10));
+    }
+
+    #[test]
+    fn rolls_up_target_states_into_global_state() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: true,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status);
+        state.set_session_state(RuntimeState::Listening, "");
+        state.set_target_state("张三", RuntimeState::Generating, None, "", 1);
+        state.set_target_state("李四", RuntimeState::Listening, None, "", 1);
+        assert_eq!(state.status.state, RuntimeState::Generating);
+
+        let failed = state.set_target_state(
+            "张三",
+            RuntimeState::Error,
+            Some("NETWORK_ERROR".to_string()),
+            "DeepSeek 请求失败",
+            2,
+        );
+        assert_eq!(failed.error_code.as_deref(), Some("NETWORK_ERROR"));
+        assert_eq!(state.status.state, RuntimeState::Listening);
+        state.set_target_state("李四", RuntimeState::Error, None, "", 3);
+        assert_eq!(state.status.state, RuntimeState::Error);
+
+        state.set_wechat_not_running();
+        assert_eq!(state.status.state, RuntimeState::Paused);
+        assert_eq!(
+            state.status.error_code.as_deref(),
+            Some("WECHAT_NOT_RUNNING")
+        );
+        assert_eq!(state.status.targets.len(), 2);
+        state.set_session_state(RuntimeState::Paused, "");
+        assert!(state.status.error_code.is_none());
+        state.set_session_state(RuntimeState::Idle, "");
+        assert!(state.status.targets.is_empty());
+        assert_eq!(state.session_state(), RuntimeState::Idle);
+    }
+
+    #[test]
+    fn keeps_agent_status_per_account() {
+        let status = Status {
+            state: RuntimeState::Idle,
+            platform: Platform::Unknown,
+            agent_connected: false,
+            last_error: String::new(),
+            power: PowerStatus::default(),
+            targets: BTreeMap::new(),
+            accounts: BTreeMap::new(),
+            strategy: AutomationStrategy::Agent,
+            error_code: None,
+        };
+        let mut state = AppState::new(Config::default(), status);
+        state.set_account_connected("", true);
+        state.set_account_state("", RuntimeState::Listening, "");
+        state.set_account_nickname("", Some("小王".to_string()));
+        state.set_account_connected("work", true);
+        state.set_account_nickname("work", Some("王经理".to_string()));
+        state.set_account_state("work", RuntimeState::Error, "Agent 连接断开");
+        state.set_account_connected("work", false);
+
+        assert!(state.status.agent_connected);
+        assert_eq!(state.account_state(""), RuntimeState::Listening);
+        assert_eq!(state.status.last_error, "");
+        assert_eq!(state.self_nickname("").as_deref(), Some("小王"));
+        let work = &state.status.accounts["work"];
+        assert!(!work.connected);
+        assert_eq!(work.last_error, "Agent 连接断开");
+        assert_eq!(state.account_state("work"), RuntimeState::Error);
+        assert_eq!(state.self_nickname("work").as_deref(), Some("王经理"));
+        assert_eq!(state.self_nickname("other").as_deref(), Some("other"));
+        assert_eq!(state.account_state("other"), RuntimeState::Idle);
     }
 }
@@ -0,0 +1,117 @@
+use crate::graphemes;
+use crate::ranking::{self, Register};
+use crate::state::SELF_PREFIX;
+
+pub const SAMPLE_LIMIT: u32 = 200;
+const MIN_SAMPLES: usize = 5;
+const RARE_EMOJI_RATE: f32 = 0.1;
+const FREQUENT_EMOJI_RATE: f32 = 0.4;
+const REGISTER_SHARE: f32 = 0.3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleFingerprint {
+    pub samples: usize,
+    pub average_length: usize,
+    pub emoji_rate: f32,
+    pub register: Register,
+}
+
+pub fn fingerprint(texts: &[String]) -> Option<StyleFingerprint> {
+    let texts: Vec<&str> = texts
+        .iter()
+        .map(|text| text.trim().trim_start_matches(SELF_PREFIX).trim())
+        .filter(|text| !text.is_empty())
+        .collect();
+    let samples = texts.len();
+    if samples < MIN_SAMPLES {
+        return None;
+    }
+    let total_length: usize = texts.iter().map(|text| graphemes::count(text)).sum();
+    let with_emoji = texts
+        .iter()
+        .filter(|text| text.chars().any(ranking::is_emoji))
+        .count();
+    let (mut formal, mut casual) = (0, 0);
+    for text in &texts {
+        match ranking::register(text) {
+            Register::Formal => formal += 1,
+            Register::Casual => casual += 1,
+            Register::Neutral => {}
+        }
+    }
+    let threshold = samples as f32 * REGISTER_SHARE;
+    let register = if formal > casual && formal as f32 >= threshold {
+        Register::Formal
+    } else if casual > formal && casual as f32 >= threshold {
+        Register::Casual
+    } else {
+        Register::Neutral
+    };
+    Some(StyleFingerprint {
+        samples,
+        average_length: (total_length as f32 / samples as f32).round() as usize,
+        emoji_rate: with_emoji as f32 / samples as f32,
+        register,
+    })
+}
+
+pub fn instruction(fingerprint: &StyleFingerprint) -> String {
+    let emoji = if fingerprint.emoji_rate < RARE_EMOJI_RATE {
+        "几乎不用表情符号"
+    } else if fingerprint.emoji_rate >= FREQUENT_EMOJI_RATE {
+        "经常使用表情符号"
+    } else {
+        "偶尔使用表情符号"
+    };
+    let register = match fingerprint.register {
+        Register::Formal => "措辞偏正式、客气",
+        Register::Casual => "语气随意、口语化",
+        Register::Neutral => "语气平实",
+    };
+    format!(
+        "用户以往在此会话中的回复习惯（请让建议听起来像用户本人）：平均每条约 {} 字，{}，{}。",
+        fingerprint.average_length, emoji, register
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn derives_fingerprint_from_sent_messages() {
+        let casual = texts(&[
+            "哈哈好呀",
+            "行啊👍",
+            "我：没问题哦",
+            "晚点说嘛",
+            "收到啦",
+            " ",
+        ]);
+        let fingerprint = fingerprint(&casual).unwrap();
+        assert_eq!(fingerprint.samples, 5);
+        assert_eq!(fingerprint.average_length, 4);
+        assert_eq!(fingerprint.register, Register::Casual);
+        assert!((fingerprint.emoji_rate - 0.2).abs() < 1e-6);
+        assert_eq!(
+            instruction(&fingerprint),
+            "用户以往在此会话中的回复习惯（请让建议听起来像用户本人）：平均每条约 4 字，偶尔使用表情符号，语气随意、口语化。"
+        );
+
+        let formal = texts(&[
+            "好的，麻烦您稍等",
+            "感谢您的耐心",
+            "请查收附件",
+            "明天给您答复",
+            "收到",
+        ]);
+        let fingerprint = super::fingerprint(&formal).unwrap();
+        assert_eq!(fingerprint.register, Register::Formal);
+        assert!(instruction(&fingerprint).contains("几乎不用表情符号，措辞偏正式、客气"));
+        assert!(super::fingerprint(&formal[..4]).is_none());
+    }
+}
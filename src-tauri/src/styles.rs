@@ -0,0 +1,199 @@
+use crate::types::{EmojiPolicy, StylePreset, SuggestionStyle};
+use anyhow::Result;
+
+pub const MAX_STYLE_PRESETS: usize = 8;
+const MAX_NAME_CHARS: usize = 24;
+const MAX_DESCRIPTION_CHARS: usize = 16;
+const MAX_PROMPT_CHARS: usize = 200;
+const BUILTIN_STYLES: [(&str, &str); 3] =
+    [("formal", "正式"), ("neutral", "中性"), ("casual", "轻松")];
+
+pub fn normalize_presets(presets: Vec<StylePreset>) -> Vec<StylePreset> {
+    presets
+        .into_iter()
+        .map(|preset| StylePreset {
+            name: preset.name.trim().to_lowercase(),
+            description: preset.description.trim().to_string(),
+            prompt: preset.prompt.trim().to_string(),
+            emoji: preset.emoji,
+        })
+        .collect()
+}
+
+pub fn validate_presets(presets: &[StylePreset]) -> Result<()> {
+    if presets.len() > MAX_STYLE_PRESETS {
+        anyhow::bail!("自定义风格不能超过 {} 个", MAX_STYLE_PRESETS);
+    }
+    for (index, preset) in presets.iter().enumerate() {
+        let name = preset.name.as_str();
+        if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+            anyhow::bail!("风格标识必须为 1 到 {} 个字符", MAX_NAME_CHARS);
+        }
+        if !name
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_' || ch == '-')
+        {
+            anyhow::bail!("风格标识只能包含小写字母、数字、下划线或连字符: {}", name);
+        }
+        if builtin_label(name).is_some() || presets[..index].iter().any(|item| item.name == name) {
+            anyhow::bail!("风格标识重复: {}", name);
+        }
+        if preset.description.is_empty()
+            || preset.description.chars().count() > MAX_DESCRIPTION_CHARS
+        {
+            anyhow::bail!("风格名称必须为 1 到 {} 字", MAX_DESCRIPTION_CHARS);
+        }
+        if preset.prompt.chars().count() > MAX_PROMPT_CHARS {
+            anyhow::bail!("风格说明不能超过 {} 字", MAX_PROMPT_CHARS);
+        }
+    }
+    Ok(())
+}
+
+fn builtin_label(name: &str) -> Option<&'static str> {
+    BUILTIN_STYLES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, label)| *label)
+}
+
+pub fn find<'a>(style: &SuggestionStyle, presets: &'a [StylePreset]) -> Option<&'a StylePreset> {
+    presets.iter().find(|preset| preset.name == style.as_str())
+}
+
+pub fn is_known(style: &SuggestionStyle, presets: &[StylePreset]) -> bool {
+    builtin_label(style.as_str()).is_some() || find(style, presets).is_some()
+}
+
+pub fn parse(name: &str, presets: &[StylePreset]) -> SuggestionStyle {
+    let style = SuggestionStyle::new(name.trim().to_lowercase());
+    if is_known(&style, presets) {
+        style
+    } else {
+        SuggestionStyle::neutral()
+    }
+}
+
+pub fn label(style: &SuggestionStyle, presets: &[StylePreset]) -> String {
+    builtin_label(style.as_str())
+        .map(str::to_string)
+        .or_else(|| find(style, presets).map(|preset| preset.description.clone()))
+        .unwrap_or_else(|| style.as_str().to_string())
+}
+
+pub fn preset_instruction(preset: &StylePreset) -> String {
+    let mut parts = Vec::new();
+    if !preset.prompt.is_empty() {
+        parts.push(preset.prompt.clone());
+    }
+    match preset.emoji {
+        EmojiPolicy::Allow => {}
+        EmojiPolicy::Avoid => parts.push("不要使用表情符号".to_string()),
+        EmojiPolicy::Prefer => parts.push("可以适当使用表情符号".to_string()),
+    }
+    parts.join("；")
+}
+
+pub fn format_instruction(presets: &[StylePreset]) -> String {
+    let mut lines: Vec<String> = BUILTIN_STYLES
+        .iter()
+        .map(|(name, label)| format!("- {}（{}）", name, label))
+        .collect();
+    for preset in presets {
+        let instruction = preset_instruction(preset);
+        if instruction.is_empty() {
+            lines.push(format!("- {}（{}）", preset.name, preset.description));
+        } else {
+            lines.push(format!(
+                "- {}（{}）：{}",
+                preset.name, preset.description, instruction
+            ));
+        }
+    }
+    format!(
+        "请根据对话内容生成 {} 条回复建议，每种风格各一条：\n{}\n\
+返回 JSON 对象 {{\"suggestions\": [...]}}，\
+数组每个元素包含 style 与 text，style 取上面括号前的英文标识。",
+        lines.len(),
+        lines.join("\n")
+    )
+}
+
+fn is_emoji(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{1f000}'..='\u{1faff}' | '\u{2600}'..='\u{27bf}' | '\u{fe0f}' | '\u{200d}'
+    )
+}
+
+pub fn apply_emoji_policy(
+    style: &SuggestionStyle,
+    text: String,
+    presets: &[StylePreset],
+) -> String {
+    match find(style, presets) {
+        Some(preset) if preset.emoji == EmojiPolicy::Avoid => {
+            let stripped: String = text.chars().filter(|ch| !is_emoji(*ch)).collect();
+            stripped.trim().to_string()
+        }
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(name: &str, description: &str, emoji: EmojiPolicy) -> StylePreset {
+        StylePreset {
+            name: name.to_string(),
+            description: description.to_string(),
+            prompt: "像朋友一样说话".to_string(),
+            emoji,
+        }
+    }
+
+    #[test]
+    fn validates_and_resolves_custom_styles() {
+        let presets = normalize_presets(vec![
+            preset(" Buddy ", " 哥们 ", EmojiPolicy::Prefer),
+            preset("brief", "简短", EmojiPolicy::Avoid),
+        ]);
+        assert!(validate_presets(&presets).is_ok());
+        assert_eq!(presets[0].name, "buddy");
+        assert!(validate_presets(&[preset("formal", "正式", EmojiPolicy::Allow)]).is_err());
+        assert!(validate_presets(&[preset("老板", "老板", EmojiPolicy::Allow)]).is_err());
+        assert!(validate_presets(&[preset("boss", "", EmojiPolicy::Allow)]).is_err());
+        let twice = vec![presets[1].clone(), presets[1].clone()];
+        assert!(validate_presets(&twice).is_err());
+
+        assert_eq!(parse("Buddy", &presets), SuggestionStyle::new("buddy"));
+        assert_eq!(parse("formal", &presets), SuggestionStyle::formal());
+        assert_eq!(parse("unknown", &presets), SuggestionStyle::neutral());
+        assert_eq!(label(&SuggestionStyle::new("buddy"), &presets), "哥们");
+        assert_eq!(label(&SuggestionStyle::casual(), &presets), "轻松");
+        assert_eq!(label(&SuggestionStyle::new("gone"), &presets), "gone");
+    }
+
+    #[test]
+    fn formats_instruction_and_applies_emoji_policy() {
+        let presets = vec![
+            preset("buddy", "哥们", EmojiPolicy::Prefer),
+            preset("brief", "简短", EmojiPolicy::Avoid),
+        ];
+        let instruction = format_instruction(&presets);
+        assert!(instruction.starts_with("请根据对话内容生成 5 条回复建议"));
+        assert!(instruction.contains("- formal（正式）\n"));
+        assert!(instruction.contains("- buddy（哥们）：像朋友一样说话；可以适当使用表情符号"));
+        assert!(instruction.contains("- brief（简短）：像朋友一样说话；不要使用表情符号"));
+
+        let brief = SuggestionStyle::new("brief");
+        let text = apply_emoji_policy(&brief, "好的👍 马上到🏃‍♂️".to_string(), &presets);
+        assert_eq!(text, "好的 马上到");
+        let buddy = SuggestionStyle::new("buddy");
+        assert_eq!(
+            apply_emoji_policy(&buddy, "好👍".to_string(), &presets),
+            "好👍"
+        );
+    }
+}
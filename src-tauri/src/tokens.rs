@@ -0,0 +1,100 @@
+use crate::graphemes;
+
+const DEFAULT_CONTEXT_WINDOW: usize = 64_000;
+const CONTEXT_WINDOWS: [(&str, usize); 2] =
+    [("deepseek-chat", 128_000), ("deepseek-reasoner", 128_000)];
+const CONTEXT_SHARE: usize = 32;
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+const SUMMARY_MAX_TOKENS: usize = 240;
+const SUMMARY_ITEM_GRAPHEMES: usize = 40;
+
+pub fn estimate(text: &str) -> usize {
+    let mut tenths: usize = 0;
+    for ch in text.chars() {
+        tenths += match ch {
+            ch if ch.is_ascii_alphanumeric() => 3,
+            ch if ch.is_ascii() => 2,
+            '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}' => 6,
+            _ => 10,
+        };
+    }
+    tenths.div_ceil(10)
+}
+
+pub fn estimate_message(text: &str) -> usize {
+    estimate(text) + MESSAGE_OVERHEAD_TOKENS
+}
+
+pub fn context_window(model: &str) -> usize {
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(name, _)| *name == model)
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+pub fn context_budget(model: &str) -> usize {
+    context_window(model) / CONTEXT_SHARE
+}
+
+pub fn roll_summary<'a>(
+    previous: Option<&str>,
+    evicted: impl IntoIterator<Item = &'a str>,
+) -> Option<String> {
+    let mut lines: Vec<String> = previous
+        .map(|summary| summary.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    for text in evicted {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            continue;
+        }
+        let mut line = graphemes::truncate(&text, SUMMARY_ITEM_GRAPHEMES).to_string();
+        if line.len() < text.len() {
+            line.push('…');
+        }
+        lines.push(line);
+    }
+    let mut total: usize = lines.iter().map(|line| estimate(line) + 1).sum();
+    let mut start = 0;
+    while total > SUMMARY_MAX_TOKENS && start < lines.len() {
+        total -= estimate(&lines[start]) + 1;
+        start += 1;
+    }
+    let kept = &lines[start..];
+    (!kept.is_empty()).then(|| kept.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_tokens_by_script() {
+        assert_eq!(estimate(""), 0);
+        assert_eq!(estimate("hello world"), 4);
+        assert_eq!(estimate("明天开会"), 3);
+        assert_eq!(estimate("👍"), 1);
+        assert_eq!(estimate_message("好"), 5);
+        assert_eq!(context_window("deepseek-chat"), 128_000);
+        assert_eq!(context_budget("deepseek-chat"), 4000);
+        assert_eq!(context_window("unknown"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn rolls_summary_within_budget() {
+        let summary = roll_summary(None, ["周五交付合同", "  ", "我：好的，\n收到"]).unwrap();
+        assert_eq!(summary, "周五交付合同\n我：好的， 收到");
+        let long = "报价".repeat(SUMMARY_ITEM_GRAPHEMES);
+        let summary = roll_summary(Some(&summary), [long.as_str()]).unwrap();
+        assert!(summary.ends_with('…'));
+        assert!(summary.starts_with("周五交付合同\n"));
+
+        let many: Vec<String> = (0..200).map(|index| format!("第{}条消息", index)).collect();
+        let summary = roll_summary(Some(&summary), many.iter().map(String::as_str)).unwrap();
+        assert!(estimate(&summary) <= SUMMARY_MAX_TOKENS + summary.lines().count());
+        assert!(summary.ends_with("第199条消息"));
+        assert!(!summary.contains("周五交付合同"));
+        assert_eq!(roll_summary(None, [" "]), None);
+    }
+}
@@ -0,0 +1,330 @@
+const SECS_PER_DAY: u64 = 86_400;
+const MAX_SPEAKER_CHARS: usize = 32;
+const SELF_SPEAKERS: [&str; 4] = ["我", "自己", "Me", "me"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptMessage {
+    pub speaker: String,
+    pub text: String,
+    pub from_self: bool,
+    pub offset_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Stamp {
+    day: Option<u64>,
+    secs: u64,
+}
+
+struct Pending {
+    speaker: String,
+    lines: Vec<String>,
+    stamp: Option<Stamp>,
+    from_header: bool,
+}
+
+pub fn parse_transcript(raw: &str, chat_title: &str) -> Vec<TranscriptMessage> {
+    let mut parsed: Vec<(String, String, Option<Stamp>)> = Vec::new();
+    let mut current: Option<Pending> = None;
+    for line in raw.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some((speaker, stamp, content)) = parse_header(line) {
+            flush(&mut parsed, current.take());
+            current = Some(Pending {
+                speaker,
+                from_header: content.is_none(),
+                lines: content.into_iter().collect(),
+                stamp,
+            });
+            continue;
+        }
+        let inside_header = current.as_ref().is_some_and(|pending| pending.from_header);
+        if !inside_header {
+            if let Some((speaker, content)) = split_speaker(line) {
+                flush(&mut parsed, current.take());
+                current = Some(Pending {
+                    speaker,
+                    lines: vec![content],
+                    stamp: None,
+                    from_header: false,
+                });
+                continue;
+            }
+        }
+        if let Some(pending) = current.as_mut() {
+            pending.lines.push(line.to_string());
+        }
+    }
+    flush(&mut parsed, current);
+
+    let self_speaker = infer_self_speaker(&parsed, chat_title);
+    let offsets = relative_offsets(&parsed);
+    parsed
+        .into_iter()
+        .zip(offsets)
+        .map(|((speaker, text, _), offset_secs)| TranscriptMessage {
+            from_self: self_speaker.as_deref() == Some(speaker.as_str()),
+            speaker,
+            text,
+            offset_secs,
+        })
+        .collect()
+}
+
+fn flush(parsed: &mut Vec<(String, String, Option<Stamp>)>, pending: Option<Pending>) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let text = pending.lines.join("\n").trim().to_string();
+    if !text.is_empty() {
+        parsed.push((pending.speaker, text, pending.stamp));
+    }
+}
+
+fn parse_header(line: &str) -> Option<(String, Option<Stamp>, Option<String>)> {
+    if let Some((stamp, rest)) = take_leading_stamp(line) {
+        if let Some((speaker, content)) = split_speaker(rest) {
+            return Some((speaker, Some(stamp), Some(content)));
+        }
+        let speaker = rest.trim();
+        if is_speaker(speaker) {
+            return Some((speaker.to_string(), Some(stamp), None));
+        }
+        return None;
+    }
+    let (speaker, stamp) = take_trailing_stamp(line)?;
+    is_speaker(speaker).then(|| (speaker.to_string(), Some(stamp), None))
+}
+
+fn take_leading_stamp(line: &str) -> Option<(Stamp, &str)> {
+    let bracketed = line
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .or_else(|| {
+            line.strip_prefix('【')
+                .and_then(|rest| rest.split_once('】'))
+        });
+    if let Some((inner, rest)) = bracketed {
+        return parse_stamp(inner.trim()).map(|stamp| (stamp, rest.trim()));
+    }
+    let mut parts = line.splitn(3, char::is_whitespace);
+    let first = parts.next()?;
+    let second = parts.next().unwrap_or("");
+    if let Some(stamp) = parse_stamp(&format!("{} {}", first, second)) {
+        return Some((stamp, parts.next().unwrap_or("").trim()));
+    }
+    let stamp = parse_time(first)?;
+    Some((stamp, line[first.len()..].trim()))
+}
+
+fn take_trailing_stamp(line: &str) -> Option<(&str, Stamp)> {
+    let mut parts = line.rsplitn(3, char::is_whitespace);
+    let last = parts.next()?;
+    let second = parts.next()?;
+    if let Some(rest) = parts.next() {
+        if let Some(stamp) = parse_stamp(&format!("{} {}", second, last)) {
+            return Some((rest.trim(), stamp));
+        }
+    }
+    let stamp = parse_time(last)?;
+    Some((line[..line.len() - last.len()].trim(), stamp))
+}
+
+fn split_speaker(line: &str) -> Option<(String, String)> {
+    let (speaker, content) = line
+        .split_once('：')
+        .into_iter()
+        .chain(line.split_once(':'))
+        .min_by_key(|(speaker, _)| speaker.len())?;
+    let speaker = speaker.trim();
+    let content = content.trim();
+    if !is_speaker(speaker) || content.is_empty() {
+        return None;
+    }
+    Some((speaker.to_string(), content.to_string()))
+}
+
+fn is_speaker(text: &str) -> bool {
+    !text.is_empty() && text.chars().count() <= MAX_SPEAKER_CHARS && !text.contains([':', '：'])
+}
+
+fn parse_stamp(text: &str) -> Option<Stamp> {
+    let (date, time) = text.trim().split_once(char::is_whitespace)?;
+    let day = parse_date(date)?;
+    let time = parse_time(time.trim())?;
+    Some(Stamp {
+        day: Some(day),
+        secs: time.secs,
+    })
+}
+
+fn parse_date(text: &str) -> Option<u64> {
+    let normalized = text.trim_end_matches('日').replace(['年', '月', '/'], "-");
+    let mut parts = normalized.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1970..=9999).contains(&year) {
+        return None;
+    }
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    u64::try_from(days_from_civil(year, month, day)).ok()
+}
+
+fn parse_time(text: &str) -> Option<Stamp> {
+    let mut parts = text.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes = parts.next()?;
+    let seconds = parts.next().unwrap_or("0");
+    if parts.next().is_some() || minutes.len() != 2 {
+        return None;
+    }
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok()?;
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    Some(Stamp {
+        day: None,
+        secs: hours * 3600 + minutes * 60 + seconds,
+    })
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn relative_offsets(parsed: &[(String, String, Option<Stamp>)]) -> Vec<Option<u64>> {
+    let mut absolute = Vec::with_capacity(parsed.len());
+    let mut previous: Option<u64> = None;
+    for (_, _, stamp) in parsed {
+        let Some(stamp) = stamp else {
+            absolute.push(None);
+            continue;
+        };
+        let value = match stamp.day {
+            Some(day) => day * SECS_PER_DAY + stamp.secs,
+            None => {
+                let base = previous
+                    .map(|value| value - value % SECS_PER_DAY)
+                    .unwrap_or(0);
+                let value = base + stamp.secs;
+                if previous.is_some_and(|previous| value < previous) {
+                    value + SECS_PER_DAY
+                } else {
+                    value
+                }
+            }
+        };
+        previous = Some(value);
+        absolute.push(Some(value));
+    }
+    let latest = absolute.iter().flatten().max().copied();
+    absolute
+        .into_iter()
+        .map(|value| {
+            value
+                .zip(latest)
+                .map(|(value, latest)| latest.saturating_sub(value))
+        })
+        .collect()
+}
+
+fn infer_self_speaker(
+    parsed: &[(String, String, Option<Stamp>)],
+    chat_title: &str,
+) -> Option<String> {
+    let mut speakers: Vec<&str> = Vec::new();
+    for (speaker, _, _) in parsed {
+        if !speakers.contains(&speaker.as_str()) {
+            speakers.push(speaker);
+        }
+    }
+    if let Some(speaker) = speakers
+        .iter()
+        .find(|speaker| SELF_SPEAKERS.contains(speaker))
+    {
+        return Some(speaker.to_string());
+    }
+    let chat_title = chat_title.trim();
+    match speakers.as_slice() {
+        [first, second] if *first == chat_title => Some(second.to_string()),
+        [first, second] if *second == chat_title => Some(first.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pc_copy_format_with_multiline_bodies() {
+        let raw = "张三 2024-05-01 10:00:00\n合同发你了\n注意：第 3 页需要签字\n\n\
+                   李四 2024-05-01 10:05:30\n收到，我看一下";
+        let messages = parse_transcript(raw, "张三");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].speaker, "张三");
+        assert_eq!(messages[0].text, "合同发你了\n注意：第 3 页需要签字");
+        assert!(!messages[0].from_self);
+        assert_eq!(messages[0].offset_secs, Some(330));
+        assert_eq!(messages[1].speaker, "李四");
+        assert!(messages[1].from_self);
+        assert_eq!(messages[1].offset_secs, Some(0));
+    }
+
+    #[test]
+    fn parses_inline_and_bracketed_formats() {
+        let raw = "[2024/5/1 23:59] 王五: 明天几点开会？\n\
+                   【2024年5月2日 00:01】我：九点\n\
+                   王五：好的";
+        let messages = parse_transcript(raw, "项目群");
+        let summary: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                (
+                    message.speaker.as_str(),
+                    message.text.as_str(),
+                    message.from_self,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("王五", "明天几点开会？", false),
+                ("我", "九点", true),
+                ("王五", "好的", false),
+            ]
+        );
+        assert_eq!(messages[0].offset_secs, Some(120));
+        assert_eq!(messages[2].offset_secs, None);
+    }
+
+    #[test]
+    fn time_only_headers_roll_over_midnight() {
+        let raw = "2024-05-01 23:58:00 张三\n还在吗\n00:02 Me\n在的";
+        let messages = parse_transcript(raw, "");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].offset_secs, Some(240));
+        assert!(messages[1].from_self);
+    }
+
+    #[test]
+    fn ignores_text_without_speakers() {
+        assert!(parse_transcript("随便一段话\n没有发送者", "张三").is_empty());
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2024, 3, 1), 19_783);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::graphemes;
+use crate::http_client::service_client;
+use crate::types::Config;
+use anyhow::{Context, Result};
+use reqwest::multipart::{Form, Part};
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+use tracing::warn;
+
+const VOICE_DIR: &str = "wereply_voices";
+const TRANSCRIPTION_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_AUDIO_BYTES: u64 = 25 * 1024 * 1024;
+pub const MAX_TRANSCRIPT_GRAPHEMES: usize = 500;
+
+pub async fn transcribe(
+    config: &Config,
+    api_key: Option<&str>,
+    audio_path: &Path,
+) -> Result<String> {
+    let size = tokio::fs::metadata(audio_path)
+        .await
+        .with_context(|| format!("语音文件不存在: {}", audio_path.display()))?
+        .len();
+    if size > MAX_AUDIO_BYTES {
+        anyhow::bail!("语音文件超过 25MB，无法转写");
+    }
+    let bytes = tokio::fs::read(audio_path)
+        .await
+        .context("读取语音文件失败")?;
+    let file_name = audio_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("voice.silk")
+        .to_string();
+    let form = Form::new()
+        .text("model", config.transcription_model.clone())
+        .text("response_format", "json")
+        .part("file", Part::bytes(bytes).file_name(file_name));
+
+    let client = service_client(config, &config.transcription_base_url)?;
+    let mut request = client
+        .post(build_transcription_url(&config.transcription_base_url))
+        .timeout(TRANSCRIPTION_TIMEOUT)
+        .multipart(form);
+    if let Some(key) = api_key {
+        request = request.bearer_auth(key);
+    }
+    let response = request.send().await.context("语音转写服务连接失败")?;
+    let status = response.status();
+    let raw = response.text().await.context("读取语音转写响应失败")?;
+    if !status.is_success() {
+        warn!("语音转写失败: {}", status);
+        let detail: String = raw.chars().take(200).collect();
+        anyhow::bail!("语音转写服务返回错误: HTTP {} {}", status, detail);
+    }
+    parse_transcript(&raw)
+}
+
+pub fn remove_downloaded_audio(audio_path: &Path) {
+    let voice_dir = std::env::temp_dir().join(VOICE_DIR);
+    if audio_path.starts_with(&voice_dir) {
+        let _ = std::fs::remove_file(audio_path);
+    }
+}
+
+fn build_transcription_url(base_url: &str) -> String {
+    format!("{}/audio/transcriptions", base_url.trim_end_matches('/'))
+}
+
+fn parse_transcript(raw: &str) -> Result<String> {
+    let value: Value = serde_json::from_str(raw).context("语音转写响应解析失败")?;
+    let text = value["text"].as_str().unwrap_or_default();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    Ok(graphemes::truncate(&text, MAX_TRANSCRIPT_GRAPHEMES).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_whisper_response() {
+        assert_eq!(
+            build_transcription_url("https://api.openai.com/v1/"),
+            "https://api.openai.com/v1/audio/transcriptions"
+        );
+        let raw = r#"{"text":"  明天下午 三点\n开会  ","language":"zh"}"#;
+        assert_eq!(parse_transcript(raw).unwrap(), "明天下午 三点 开会");
+        assert_eq!(parse_transcript(r#"{"error":"x"}"#).unwrap(), "");
+        assert!(parse_transcript("not json").is_err());
+        let long = format!(
+            r#"{{"text":"{}"}}"#,
+            "好".repeat(MAX_TRANSCRIPT_GRAPHEMES + 5)
+        );
+        let text = parse_transcript(&long).unwrap();
+        assert_eq!(graphemes::count(&text), MAX_TRANSCRIPT_GRAPHEMES);
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -32,6 +33,252 @@ pub enum ChatKind {
 pub struct ListenTarget {
     pub name: String,
     pub kind: ChatKind,
+    #[serde(default)]
+    #[specta(optional)]
+    pub prompt_override: Option<String>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub persona: Option<String>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub muted: bool,
+    #[serde(default)]
+    #[specta(optional)]
+    pub priority: TargetPriority,
+    #[serde(default)]
+    #[specta(optional)]
+    pub sender_whitelist: Vec<String>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub sender_blacklist: Vec<String>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub mention_only: bool,
+    #[serde(default)]
+    #[specta(optional)]
+    pub language: Option<ContactLanguage>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub politeness: Politeness,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageContentType {
+    #[default]
+    Text,
+    Image,
+    Voice,
+    Link,
+    Sticker,
+    File,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactLanguage {
+    Ja,
+    Ko,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Politeness {
+    #[default]
+    Polite,
+    Casual,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetPriority {
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct Persona {
+    pub name: String,
+    #[serde(default)]
+    #[specta(optional)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub styles: Vec<SuggestionStyle>,
+    #[serde(default)]
+    pub auto_send: bool,
+    #[serde(default)]
+    pub daily_request_limit: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct BusinessHours {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub weekdays_only: bool,
+    pub utc_offset_minutes: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct AutoReplyRule {
+    pub target: String,
+    pub keyword: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    #[specta(optional)]
+    pub canned_response_id: Option<String>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub hours: Option<BusinessHours>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct CannedResponse {
+    pub id: String,
+    pub title: String,
+    pub text: String,
+    pub tags: Vec<String>,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct KnowledgeBaseStatus {
+    pub dir: String,
+    pub documents: u32,
+    pub chunks: u32,
+    pub built_at: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ContactNote {
+    pub chat_id: String,
+    pub note: String,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrationScope {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct IntegrationToken {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<IntegrationScope>,
+    pub created_at: u64,
+    #[serde(default)]
+    pub last_used_at: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct IntegrationTokenCreated {
+    pub token: IntegrationToken,
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct SuggestionReasoning {
+    pub chat_id: String,
+    pub record_id: String,
+    pub model: String,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct FollowupsUpdated {
+    pub chat_id: String,
+    pub record_id: String,
+    pub questions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct TranscriptionCompleted {
+    pub chat_id: String,
+    pub msg_id: Option<String>,
+    pub text: String,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct AutoReplySent {
+    pub chat_id: String,
+    pub keyword: String,
+    pub text: String,
+    pub sent_at: u64,
+    #[serde(default)]
+    #[specta(optional)]
+    pub persona: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ListenTargetResult {
+    pub name: String,
+    pub ok: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct ListenTargetsReport {
+    pub targets: Vec<ListenTarget>,
+    pub results: Vec<ListenTargetResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub deepseek_model: String,
+    pub listen_target_count: u32,
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ChatActivityStats {
+    pub chat_id: String,
+    pub messages_7d: u32,
+    pub messages_30d: u32,
+    pub active_days_30d: u32,
+    /// Average time from an incoming message to the user's reply.
+    pub avg_reply_secs: Option<u64>,
+    pub last_message_at: u64,
+    pub listened: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct SeedContextResult {
+    pub parsed: u32,
+    pub kept: u32,
+    pub self_messages: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct MessageSearchHit {
+    pub chat_id: String,
+    pub text: String,
+    pub timestamp: u64,
+    pub msg_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
@@ -40,14 +287,211 @@ pub struct ChatSummary {
     pub chat_id: String,
     pub chat_title: String,
     pub kind: ChatKind,
+    #[serde(default)]
+    pub account_id: String,
+    #[serde(default)]
+    pub last_message: String,
+    #[serde(default)]
+    pub last_active_at: u64,
+    #[serde(default)]
+    pub unread_count: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct RecentChats {
+    pub chats: Vec<ChatSummary>,
+    pub has_more: bool,
+    pub fetched_at: u64,
+    pub stale: bool,
+    pub refreshing: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+pub struct ChatAvatar {
+    pub chat_id: String,
+    pub mime_type: String,
+    pub data_base64: Option<String>,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct SuggestionStyle(String);
+
+impl SuggestionStyle {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn formal() -> Self {
+        Self::new("formal")
+    }
+
+    pub fn neutral() -> Self {
+        Self::new("neutral")
+    }
+
+    pub fn casual() -> Self {
+        Self::new("casual")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
-pub enum SuggestionStyle {
-    Formal,
-    Neutral,
-    Casual,
+pub enum EmojiPolicy {
+    #[default]
+    Allow,
+    Avoid,
+    Prefer,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct StylePreset {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub prompt: String,
+    #[serde(default)]
+    pub emoji: EmojiPolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct PromptEntry {
+    pub name: String,
+    pub prompt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq, Default)]
+#[specta(inline)]
+pub struct PromptSnapshot {
+    #[serde(default)]
+    pub target_prompts: Vec<PromptEntry>,
+    #[serde(default)]
+    pub persona_prompts: Vec<PromptEntry>,
+    #[serde(default)]
+    pub style_presets: Vec<StylePreset>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct PromptVersion {
+    pub version: u32,
+    pub source: String,
+    pub created_at: u64,
+    pub snapshot: PromptSnapshot,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct PromptChange {
+    pub item: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq, Default)]
+#[specta(inline)]
+pub struct PromptExperiment {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub variant_a: String,
+    #[serde(default)]
+    pub variant_b: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExperimentVariant {
+    A,
+    B,
+}
+
+impl ExperimentVariant {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::A => "a",
+            Self::B => "b",
+        }
+    }
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "a" => Some(Self::A),
+            "b" => Some(Self::B),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ExperimentArm {
+    pub name: String,
+    pub variant: ExperimentVariant,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct ExperimentVariantStats {
+    pub variant: ExperimentVariant,
+    pub prompt: String,
+    pub acceptance: SuggestionAcceptance,
+    pub acceptance_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct ExperimentReport {
+    pub name: String,
+    pub enabled: bool,
+    pub variants: Vec<ExperimentVariantStats>,
+    pub winner: Option<ExperimentVariant>,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ReplyLengthLimit {
+    pub style: SuggestionStyle,
+    #[serde(default)]
+    pub min_chars: u32,
+    #[serde(default)]
+    pub max_chars: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SafetyAction {
+    Drop,
+    #[default]
+    Mask,
+    Flag,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct SafetyRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub action: SafetyAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct SafetyWarning {
+    pub suggestion_id: String,
+    pub pattern: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -58,6 +502,68 @@ pub struct Suggestion {
     pub text: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AutomationStrategy {
+    Ui,
+    Db,
+    #[default]
+    Agent,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LowPowerMode {
+    Auto,
+    On,
+    Off,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackMode {
+    Templates,
+    Silent,
+    RetryOnly,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct PowerStatus {
+    pub source: PowerSource,
+    pub low_power: bool,
+    pub adjustments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct TargetStatus {
+    pub chat_id: String,
+    pub state: RuntimeState,
+    pub error_code: Option<String>,
+    pub detail: String,
+    pub updated_at: u64,
+}
+
+/// The agent of one extra account from `Config::accounts`.
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct AccountStatus {
+    pub account_id: String,
+    pub connected: bool,
+    pub state: RuntimeState,
+    pub last_error: String,
+    pub nickname: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
 #[specta(inline)]
 pub struct Status {
@@ -65,6 +571,35 @@ pub struct Status {
     pub platform: Platform,
     pub agent_connected: bool,
     pub last_error: String,
+    pub power: PowerStatus,
+    #[serde(default)]
+    pub targets: BTreeMap<String, TargetStatus>,
+    /// Extra accounts by id; the default account is the fields above.
+    #[serde(default)]
+    pub accounts: BTreeMap<String, AccountStatus>,
+    #[serde(default)]
+    pub strategy: AutomationStrategy,
+    #[serde(default)]
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ReadinessCheck {
+    pub key: String,
+    pub label: String,
+    pub ok: bool,
+    pub blocking: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct Readiness {
+    pub score: u32,
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+    pub blocking_issues: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -74,6 +609,7 @@ pub struct Config {
     pub suggestion_count: u32,
     pub context_max_messages: u32,
     pub context_max_chars: u32,
+    pub context_max_age_secs: u64,
     pub poll_interval_ms: u64,
     pub listen_targets: Vec<ListenTarget>,
     pub temperature: f32,
@@ -83,6 +619,42 @@ pub struct Config {
     pub max_retries: u32,
     pub log_level: String,
     pub log_to_file: bool,
+    pub hide_dock_icon: bool,
+    pub low_power_mode: LowPowerMode,
+    pub history_retention_days: u32,
+    pub fallback_mode: FallbackMode,
+    pub automation_trace: bool,
+    pub automation_trace_minutes: u32,
+    pub daily_request_limit: u32,
+    pub daily_token_limit: u32,
+    pub max_concurrent_generations: u32,
+    pub automation_concurrency: u32,
+    pub auto_reply_enabled: bool,
+    pub auto_reply_max_per_hour: u32,
+    pub auto_reply_rules: Vec<AutoReplyRule>,
+    pub self_nickname: String,
+    pub image_ocr_enabled: bool,
+    pub tesseract_path: String,
+    pub voice_transcription_enabled: bool,
+    pub transcription_base_url: String,
+    pub transcription_model: String,
+    pub knowledge_base_dir: String,
+    pub knowledge_top_k: u32,
+    pub style_presets: Vec<StylePreset>,
+    pub reply_length_limits: Vec<ReplyLengthLimit>,
+    pub safety_rules: Vec<SafetyRule>,
+    pub pii_redaction_enabled: bool,
+    pub expose_reasoning: bool,
+    pub best_pick_mode: bool,
+    pub followup_questions_enabled: bool,
+    pub prompt_experiment: PromptExperiment,
+    pub style_learning_enabled: bool,
+    pub ca_bundle_path: String,
+    pub pin_ca_bundle: bool,
+    pub accounts: Vec<String>,
+    pub automation_strategies: Vec<AutomationStrategy>,
+    pub pause_when_wechat_unfocused: bool,
+    pub integration_port: u16,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -92,6 +664,145 @@ pub struct UiTreeExport {
     pub saved_to: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct AutomationMetrics {
+    pub concurrency: u32,
+    pub queue_limit: u32,
+    pub running: u32,
+    pub waiting: u32,
+    pub completed: u64,
+    pub rejected: u64,
+    pub avg_wait_ms: u64,
+    pub max_wait_ms: u64,
+    pub last_wait_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct AutomationTraceEntry {
+    pub at_ms: u64,
+    pub action: String,
+    pub element: String,
+    pub pattern: String,
+    pub ok: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceKind {
+    UiTree,
+    TempFile,
+    Log,
+    OrphanedDb,
+    PythonCache,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct MaintenanceItem {
+    pub kind: MaintenanceKind,
+    pub path: String,
+    pub bytes: u64,
+    pub removed: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct MaintenanceReport {
+    pub dry_run: bool,
+    pub items: Vec<MaintenanceItem>,
+    pub total_bytes: u64,
+    pub reclaimed_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct CipherSelfTest {
+    pub cipher_version: Option<String>,
+    pub roundtrip_ok: bool,
+    pub cli_path: Option<String>,
+    pub cli_version: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecryptMethod {
+    Bundled,
+    Cli,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct DecryptExport {
+    pub source_path: String,
+    pub output_path: String,
+    pub method: DecryptMethod,
+    pub tables: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DbKeyMethod {
+    Helper,
+    MemoryScan,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct DbKeyReport {
+    pub ok: bool,
+    pub method: Option<DbKeyMethod>,
+    pub candidates_checked: u32,
+    pub retry_after_secs: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LocatorCue {
+    AutomationId,
+    Structure,
+    Name,
+    Geometry,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct LocatorDiagnostic {
+    pub target: String,
+    pub cue: Option<LocatorCue>,
+    pub candidates: u32,
+    pub theme_sensitive: bool,
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct AutomationTraceExport {
+    pub json: String,
+    pub entries: u32,
+    pub saved_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct HandoverBrief {
+    pub chat_id: String,
+    pub contact: String,
+    pub open_issues: Vec<String>,
+    pub promised_actions: Vec<String>,
+    pub tone_guidance: String,
+    pub markdown: String,
+    pub message_count: u32,
+    pub generated_at: u64,
+    pub saved_to: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
 #[specta(inline)]
 pub struct UiPathStep {
@@ -125,6 +836,184 @@ pub struct UiPathsStatus {
 pub struct SuggestionsUpdated {
     pub chat_id: String,
     pub suggestions: Vec<Suggestion>,
+    pub reply_source: Option<ReplySource>,
+    #[serde(default)]
+    pub warnings: Vec<SafetyWarning>,
+    #[serde(default)]
+    pub best_pick: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct FrontendSync {
+    pub status: Status,
+    pub latest_suggestions: Option<SuggestionsUpdated>,
+    pub missed_suggestions: Vec<SuggestionsUpdated>,
+    pub missed_auto_replies: Vec<AutoReplySent>,
+    pub detached_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct SuggestionAcceptance {
+    pub suggestion_sets: u32,
+    pub accepted: u32,
+    pub observed: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct SuggestionUsed {
+    pub chat_id: String,
+    pub suggestion_id: String,
+    pub observed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct SuggestionRecord {
+    pub id: String,
+    pub chat_id: String,
+    pub context_hash: String,
+    pub model: String,
+    pub fallback: bool,
+    pub latency_ms: u64,
+    pub suggestions: Vec<Suggestion>,
+    pub written_suggestion_id: Option<String>,
+    pub written_at: Option<u64>,
+    pub created_at: u64,
+    #[serde(default)]
+    pub prompt_version: u32,
+    #[serde(default)]
+    pub experiment: Option<ExperimentArm>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Default)]
+#[specta(inline)]
+pub struct BacktestRange {
+    #[serde(default)]
+    #[specta(optional)]
+    pub since: Option<u64>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub until: Option<u64>,
+    #[serde(default)]
+    #[specta(optional)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct BacktestCase {
+    pub timestamp: u64,
+    pub incoming: String,
+    pub generated: Vec<Suggestion>,
+    pub original: Vec<Suggestion>,
+    pub sent: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct BacktestReport {
+    pub chat_id: String,
+    pub template_id: String,
+    pub model: String,
+    pub cases: Vec<BacktestCase>,
+    pub total_tokens: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct SuggestionsUnavailable {
+    pub chat_id: String,
+    pub reason: String,
+    pub retry_scheduled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyMode {
+    #[default]
+    Plain,
+    Quote,
+    Mention,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct ReplySource {
+    pub msg_id: Option<String>,
+    pub sender_name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[specta(inline)]
+pub struct SessionInstruction {
+    pub chat_id: String,
+    pub text: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InputWriteStatus {
+    Queued,
+    Retrying,
+    Written,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct InputWriteResult {
+    pub chat_id: String,
+    pub status: InputWriteStatus,
+    pub queue_position: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestedAction {
+    Retry,
+    OpenSettings,
+    GrantPermission,
+    RestartAgent,
+    RelearnPaths,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentLogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct AgentLogEntry {
+    pub level: AgentLogLevel,
+    pub component: String,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct AgentInfo {
+    pub account_id: String,
+    pub platform: String,
+    pub agent_version: String,
+    pub capabilities: Vec<String>,
+    pub protocol_version: String,
+    pub pid: Option<u32>,
+    pub started_at: u64,
+    pub uptime_secs: u64,
+    pub last_heartbeat_at: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -133,6 +1022,18 @@ pub struct ErrorPayload {
     pub code: String,
     pub message: String,
     pub recoverable: bool,
+    pub suggested_action: Option<SuggestedAction>,
+}
+
+pub fn suggested_action_for_code(code: &str) -> Option<SuggestedAction> {
+    match code {
+        "PERMISSION_DENIED" => Some(SuggestedAction::GrantPermission),
+        "AGENT_DISCONNECTED" | "PROTOCOL_ERROR" => Some(SuggestedAction::RestartAgent),
+        "WRITE_FAILED" | "LISTEN_FAILED" | "LISTEN_TARGET_FAILED" | "CHAT_LIST_FAILED" => {
+            Some(SuggestedAction::Retry)
+        }
+        _ => None,
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -141,6 +1042,16 @@ pub struct DeepseekEndpointStatus {
     pub ok: bool,
     pub status: Option<u16>,
     pub message: String,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct ConnectionTiming {
+    pub dns_ms: u64,
+    pub tcp_ms: u64,
+    pub tls_ms: Option<u64>,
+    pub request_ms: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -150,6 +1061,34 @@ pub struct DeepseekDiagnostics {
     pub model: String,
     pub chat: DeepseekEndpointStatus,
     pub models: DeepseekEndpointStatus,
+    pub balance: Option<DeepseekBalance>,
+    pub connection: Option<ConnectionTiming>,
+    pub tls: Option<DeepseekEndpointStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct ModelInfo {
+    pub id: String,
+    pub owned_by: Option<String>,
+    pub context_length: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct DeepseekBalanceInfo {
+    pub currency: String,
+    pub total_balance: String,
+    pub granted_balance: String,
+    pub topped_up_balance: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[specta(inline)]
+pub struct DeepseekBalance {
+    pub is_available: bool,
+    #[serde(default)]
+    pub balance_infos: Vec<DeepseekBalanceInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -176,6 +1115,16 @@ pub fn api_err<T>(message: impl Into<String>) -> ApiResponse<T> {
     }
 }
 
+impl Default for PowerStatus {
+    fn default() -> Self {
+        Self {
+            source: PowerSource::Unknown,
+            low_power: false,
+            adjustments: Vec::new(),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -183,6 +1132,7 @@ impl Default for Config {
             suggestion_count: 3,
             context_max_messages: 10,
             context_max_chars: 2000,
+            context_max_age_secs: 21_600,
             poll_interval_ms: 800,
             listen_targets: Vec::new(),
             temperature: 0.7,
@@ -192,6 +1142,42 @@ impl Default for Config {
             max_retries: 2,
             log_level: "info".to_string(),
             log_to_file: false,
+            hide_dock_icon: false,
+            low_power_mode: LowPowerMode::Auto,
+            history_retention_days: 30,
+            fallback_mode: FallbackMode::Templates,
+            automation_trace: false,
+            automation_trace_minutes: 10,
+            daily_request_limit: 0,
+            daily_token_limit: 0,
+            max_concurrent_generations: 2,
+            automation_concurrency: 1,
+            auto_reply_enabled: false,
+            auto_reply_max_per_hour: 10,
+            auto_reply_rules: Vec::new(),
+            self_nickname: String::new(),
+            image_ocr_enabled: false,
+            tesseract_path: "tesseract".to_string(),
+            voice_transcription_enabled: false,
+            transcription_base_url: "https://api.openai.com/v1".to_string(),
+            transcription_model: "whisper-1".to_string(),
+            knowledge_base_dir: String::new(),
+            knowledge_top_k: 3,
+            style_presets: Vec::new(),
+            reply_length_limits: Vec::new(),
+            safety_rules: Vec::new(),
+            pii_redaction_enabled: false,
+            expose_reasoning: false,
+            best_pick_mode: false,
+            followup_questions_enabled: false,
+            prompt_experiment: PromptExperiment::default(),
+            style_learning_enabled: false,
+            ca_bundle_path: String::new(),
+            pin_ca_bundle: false,
+            accounts: Vec::new(),
+            automation_strategies: vec![AutomationStrategy::Ui, AutomationStrategy::Agent],
+            pause_when_wechat_unfocused: false,
+            integration_port: 0,
         }
     }
 }
@@ -200,6 +1186,25 @@ impl Default for Config {
 mod tests {
     use super::*;
 
+    #[test]
+    fn suggested_actions_cover_known_codes() {
+        assert_eq!(
+            suggested_action_for_code("PERMISSION_DENIED"),
+            Some(SuggestedAction::GrantPermission)
+        );
+        assert_eq!(
+            suggested_action_for_code("AGENT_DISCONNECTED"),
+            Some(SuggestedAction::RestartAgent)
+        );
+        assert_eq!(
+            suggested_action_for_code("WRITE_FAILED"),
+            Some(SuggestedAction::Retry)
+        );
+        assert_eq!(suggested_action_for_code("UNKNOWN"), None);
+        let json = serde_json::to_value(SuggestedAction::OpenSettings).unwrap();
+        assert_eq!(json, "open_settings");
+    }
+
     #[test]
     fn default_config_values() {
         let cfg = Config::default();
@@ -207,6 +1212,7 @@ mod tests {
         assert_eq!(cfg.suggestion_count, 3);
         assert_eq!(cfg.context_max_messages, 10);
         assert_eq!(cfg.context_max_chars, 2000);
+        assert_eq!(cfg.context_max_age_secs, 21_600);
         assert_eq!(cfg.poll_interval_ms, 800);
         assert!(cfg.listen_targets.is_empty());
         assert_eq!(cfg.temperature, 0.7);
@@ -216,5 +1222,6 @@ mod tests {
         assert_eq!(cfg.max_retries, 2);
         assert_eq!(cfg.log_level, "info");
         assert!(!cfg.log_to_file);
+        assert!(!cfg.hide_dock_icon);
     }
 }
@@ -0,0 +1,92 @@
+use crate::types::ChatActivityStats;
+
+const SECS_PER_DAY: u64 = 86_400;
+/// Replies later than this answer a new topic rather than the message, and
+/// would swamp the average.
+const REPLY_WINDOW_SECS: u64 = SECS_PER_DAY;
+
+/// Start of the 30-day window the statistics cover.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn window_start(now: u64) -> u64 {
+    now.saturating_sub(30 * SECS_PER_DAY)
+}
+
+/// Summarizes one chat from its `(timestamp, is_self)` messages of the last
+/// 30 days, oldest first. Reply time runs from the first unanswered incoming
+/// message to the user's next message.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn summarize(
+    chat_id: String,
+    messages: &[(u64, bool)],
+    now: u64,
+) -> Option<ChatActivityStats> {
+    let last_message_at = messages.last()?.0;
+    let since_7d = now.saturating_sub(7 * SECS_PER_DAY);
+    let mut days: Vec<u64> = messages.iter().map(|(at, _)| at / SECS_PER_DAY).collect();
+    days.dedup();
+    let mut waiting_since = None;
+    let mut replies = Vec::new();
+    for &(at, is_self) in messages {
+        if !is_self {
+            waiting_since.get_or_insert(at);
+            continue;
+        }
+        if let Some(since) = waiting_since.take() {
+            let gap = at.saturating_sub(since);
+            if gap <= REPLY_WINDOW_SECS {
+                replies.push(gap);
+            }
+        }
+    }
+    let avg_reply_secs =
+        (!replies.is_empty()).then(|| replies.iter().sum::<u64>() / replies.len() as u64);
+    Some(ChatActivityStats {
+        chat_id,
+        messages_7d: messages.iter().filter(|(at, _)| *at >= since_7d).count() as u32,
+        messages_30d: messages.len() as u32,
+        active_days_30d: days.len() as u32,
+        avg_reply_secs,
+        last_message_at,
+        listened: false,
+    })
+}
+
+/// Busiest chats first, then the most recently active.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn sort_by_activity(stats: &mut [ChatActivityStats]) {
+    stats.sort_by(|a, b| {
+        b.messages_30d
+            .cmp(&a.messages_30d)
+            .then(b.last_message_at.cmp(&a.last_message_at))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_reply_time_from_incoming_to_outgoing() {
+        let now = 40 * SECS_PER_DAY + 3600;
+        let messages = [
+            (now - 20 * SECS_PER_DAY, false),
+            (now - 20 * SECS_PER_DAY + 60, false),
+            (now - 20 * SECS_PER_DAY + 300, true),
+            (now - 20 * SECS_PER_DAY + 310, true),
+            (now - 600, false),
+            (now - 500, true),
+            (now - 400, false),
+        ];
+        let stats = summarize("项目群".to_string(), &messages, now).unwrap();
+        assert_eq!(stats.messages_30d, 7);
+        assert_eq!(stats.messages_7d, 3);
+        assert_eq!(stats.active_days_30d, 2);
+        assert_eq!(stats.avg_reply_secs, Some((300 + 100) / 2));
+        assert_eq!(stats.last_message_at, now - 400);
+
+        let late = [(0, false), (2 * SECS_PER_DAY, true)];
+        let stats = summarize("张三".to_string(), &late, now).unwrap();
+        assert_eq!(stats.avg_reply_secs, None);
+        assert!(summarize("空".to_string(), &[], now).is_none());
+    }
+}
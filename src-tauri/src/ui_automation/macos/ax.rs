@@ -68,6 +68,9 @@ mod native {
     use core_foundation::boolean::CFBoolean;
     use core_foundation::dictionary::CFDictionary;
     use core_foundation::number::CFNumber;
+    use core_foundation::runloop::{
+        kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopSource, CFRunLoopSourceRef,
+    };
     use core_foundation::string::{CFString, CFStringRef};
     use core_graphics::geometry::{CGPoint, CGRect, CGSize};
     use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation, KeyCode};
@@ -77,11 +80,17 @@ mod native {
     use std::ffi::CString;
     use std::ptr;
     use std::ffi::c_void;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
 
     type AXUIElementRef = *const std::ffi::c_void;
     type AXValueRef = *const std::ffi::c_void;
     type AXValueType = i32;
     type AXError = i32;
+    type AXObserverRef = *const std::ffi::c_void;
+    type AXObserverCallback =
+        unsafe extern "C" fn(AXObserverRef, AXUIElementRef, CFStringRef, *mut c_void);
 
     const AX_SUCCESS: AXError = 0;
     const AX_VALUE_CGRECT: AXValueType = 3;
@@ -100,8 +109,26 @@ mod native {
             value: CFTypeRef,
         ) -> AXError;
         fn AXIsProcessTrustedWithOptions(options: CFTypeRef) -> bool;
+        fn AXIsProcessTrusted() -> bool;
         fn AXValueGetType(value: AXValueRef) -> AXValueType;
         fn AXValueGetValue(value: AXValueRef, the_type: AXValueType, value_ptr: *mut c_void) -> bool;
+        fn AXObserverCreate(
+            application: i32,
+            callback: AXObserverCallback,
+            observer: *mut AXObserverRef,
+        ) -> AXError;
+        fn AXObserverAddNotification(
+            observer: AXObserverRef,
+            element: AXUIElementRef,
+            notification: CFStringRef,
+            refcon: *mut c_void,
+        ) -> AXError;
+        fn AXObserverRemoveNotification(
+            observer: AXObserverRef,
+            element: AXUIElementRef,
+            notification: CFStringRef,
+        ) -> AXError;
+        fn AXObserverGetRunLoopSource(observer: AXObserverRef) -> CFRunLoopSourceRef;
     }
 
     #[derive(Debug)]
@@ -174,7 +201,6 @@ mod native {
             &self.app
         }
 
-        #[allow(dead_code)]
         pub fn pid(&self) -> i32 {
             self.pid
         }
@@ -189,6 +215,104 @@ mod native {
         }
     }
 
+    /// Sends the element of every `notifications` raised on `element` or its
+    /// descendants. Notifications are only delivered while the thread that
+    /// scheduled the observer runs its run loop (see [`run_loop_until`]).
+    pub struct AxObserver {
+        observer: AXObserverRef,
+        element: AxElement,
+        notifications: Vec<CFString>,
+        sender: *mut Sender<AxElement>,
+    }
+
+    impl AxObserver {
+        pub fn new(
+            pid: i32,
+            element: &AxElement,
+            notifications: &[&str],
+            sender: Sender<AxElement>,
+        ) -> Result<Self> {
+            let mut observer: AXObserverRef = ptr::null();
+            let err = unsafe { AXObserverCreate(pid, observer_callback, &mut observer) };
+            if err != AX_SUCCESS || observer.is_null() {
+                return Err(anyhow!("AXObserverCreate failed: {}", err));
+            }
+            let mut this = Self {
+                observer,
+                element: element.clone(),
+                notifications: Vec::new(),
+                sender: Box::into_raw(Box::new(sender)),
+            };
+            for name in notifications {
+                let notification = cfstr(name);
+                let err = unsafe {
+                    AXObserverAddNotification(
+                        observer,
+                        element.raw(),
+                        notification.as_concrete_TypeRef(),
+                        this.sender as *mut c_void,
+                    )
+                };
+                // Elements may not support every notification.
+                if err == AX_SUCCESS {
+                    this.notifications.push(notification);
+                }
+            }
+            if this.notifications.is_empty() {
+                return Err(anyhow!("AXObserverAddNotification failed"));
+            }
+            Ok(this)
+        }
+
+        /// Attaches the observer to the run loop of the current thread.
+        pub fn schedule(&self) {
+            let source = unsafe {
+                CFRunLoopSource::wrap_under_get_rule(AXObserverGetRunLoopSource(self.observer))
+            };
+            CFRunLoop::get_current().add_source(&source, unsafe { kCFRunLoopDefaultMode });
+        }
+    }
+
+    impl Drop for AxObserver {
+        fn drop(&mut self) {
+            unsafe {
+                for notification in &self.notifications {
+                    AXObserverRemoveNotification(
+                        self.observer,
+                        self.element.raw(),
+                        notification.as_concrete_TypeRef(),
+                    );
+                }
+                CFRelease(self.observer as _);
+                drop(Box::from_raw(self.sender));
+            }
+        }
+    }
+
+    unsafe extern "C" fn observer_callback(
+        _observer: AXObserverRef,
+        element: AXUIElementRef,
+        _notification: CFStringRef,
+        refcon: *mut c_void,
+    ) {
+        let sender = &*(refcon as *const Sender<AxElement>);
+        if let Some(element) = AxElement::from_raw(element) {
+            let _ = sender.send(element);
+        }
+    }
+
+    /// Runs the run loop of the current thread until `stop` is set, checking
+    /// the flag at least every half second.
+    pub fn run_loop_until(stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            let _ = CFRunLoop::run_in_mode(
+                unsafe { kCFRunLoopDefaultMode },
+                Duration::from_millis(500),
+                false,
+            );
+        }
+    }
+
     pub fn check_accessibility() -> bool {
         let prompt_key = CFString::new("AXTrustedCheckOptionPrompt");
         let prompt_value = CFNumber::from(1i32);
@@ -196,11 +320,27 @@ mod native {
         unsafe { AXIsProcessTrustedWithOptions(dict.as_concrete_TypeRef() as _) }
     }
 
+    pub fn is_accessibility_trusted() -> bool {
+        unsafe { AXIsProcessTrusted() }
+    }
+
     pub fn focus_element(element: &AxElement) -> Result<()> {
         let value = CFNumber::from(1i32);
         set_attribute_value(element, &cfstr("AXFocused"), value.as_concrete_TypeRef() as _)
     }
 
+    pub fn send_return() -> Result<()> {
+        let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
+            .map_err(|_| anyhow!("CGEventSource failed"))?;
+        let key_down = CGEvent::new_keyboard_event(source.clone(), KeyCode::RETURN, true)
+            .map_err(|_| anyhow!("CGEvent keydown failed"))?;
+        let key_up = CGEvent::new_keyboard_event(source, KeyCode::RETURN, false)
+            .map_err(|_| anyhow!("CGEvent keyup failed"))?;
+        key_down.post(CGEventTapLocation::HID);
+        key_up.post(CGEventTapLocation::HID);
+        Ok(())
+    }
+
     pub fn send_page_down() -> Result<()> {
         let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
             .map_err(|_| anyhow!("CGEventSource failed"))?;
@@ -215,21 +355,30 @@ mod native {
 
     pub fn paste_text(text: &str) -> Result<()> {
         set_clipboard_text(text)?;
+        send_command_key(0x09)
+    }
+
+    /// Cmd+A in the focused element, so a following paste replaces its text.
+    pub fn select_all() -> Result<()> {
+        send_command_key(0x00)
+    }
+
+    fn send_command_key(key: u16) -> Result<()> {
         let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
             .map_err(|_| anyhow!("CGEventSource failed"))?;
         let key_down = CGEvent::new_keyboard_event(source.clone(), KeyCode::COMMAND, true)
             .map_err(|_| anyhow!("CGEvent keydown failed"))?;
-        let v_down = CGEvent::new_keyboard_event(source.clone(), 0x09, true)
-            .map_err(|_| anyhow!("CGEvent V down failed"))?;
-        let v_up = CGEvent::new_keyboard_event(source.clone(), 0x09, false)
-            .map_err(|_| anyhow!("CGEvent V up failed"))?;
+        let char_down = CGEvent::new_keyboard_event(source.clone(), key, true)
+            .map_err(|_| anyhow!("CGEvent key down failed"))?;
+        let char_up = CGEvent::new_keyboard_event(source.clone(), key, false)
+            .map_err(|_| anyhow!("CGEvent key up failed"))?;
         let key_up = CGEvent::new_keyboard_event(source, KeyCode::COMMAND, false)
             .map_err(|_| anyhow!("CGEvent keyup failed"))?;
-        v_down.set_flags(CGEventFlags::CGEventFlagCommand);
-        v_up.set_flags(CGEventFlags::CGEventFlagCommand);
+        char_down.set_flags(CGEventFlags::CGEventFlagCommand);
+        char_up.set_flags(CGEventFlags::CGEventFlagCommand);
         key_down.post(CGEventTapLocation::HID);
-        v_down.post(CGEventTapLocation::HID);
-        v_up.post(CGEventTapLocation::HID);
+        char_down.post(CGEventTapLocation::HID);
+        char_up.post(CGEventTapLocation::HID);
         key_up.post(CGEventTapLocation::HID);
         Ok(())
     }
@@ -273,6 +422,18 @@ mod native {
         titles
     }
 
+    /// The row of a session list whose title is `title`.
+    pub fn find_session_row(list: &AxElement, title: &str) -> Option<AxElement> {
+        children(list).into_iter().find(|row| {
+            pick_session_title(&collect_static_texts(row, 6)).as_deref() == Some(title.trim())
+        })
+    }
+
+    pub fn select_row(row: &AxElement) -> Result<()> {
+        let selected = CFBoolean::true_value();
+        set_attribute_value(row, &cfstr("AXSelected"), selected.as_concrete_TypeRef() as _)
+    }
+
     pub fn find_lists_with_titles(root: &AxElement, depth: usize) -> Vec<(AxElement, Vec<String>)> {
         let mut items = Vec::new();
         walk(root, depth, &mut |element| {
@@ -443,16 +604,16 @@ mod native {
     }
 
     pub fn set_clipboard_text(text: &str) -> Result<()> {
-        let c_string = CString::new(text).map_err(|_| anyhow!("Clipboard text invalid"))?;
-        unsafe {
-            let ns_string: *mut Object = msg_send![class!(NSString), alloc];
-            let ns_string: *mut Object = msg_send![ns_string, initWithUTF8String: c_string.as_ptr()];
+        // CFString is toll-free bridged to NSString and keeps surrogate pairs and NUL intact.
+        let ns_string = CFString::new(text);
+        let ns_type = CFString::new("public.utf8-plain-text");
+        let ok: bool = unsafe {
             let pasteboard: *mut Object = msg_send![class!(NSPasteboard), generalPasteboard];
             let _: i64 = msg_send![pasteboard, clearContents];
-            let type_string = CString::new("public.utf8-plain-text").map_err(|_| anyhow!("Clipboard type invalid"))?;
-            let ns_type: *mut Object = msg_send![class!(NSString), alloc];
-            let ns_type: *mut Object = msg_send![ns_type, initWithUTF8String: type_string.as_ptr()];
-            let _: bool = msg_send![pasteboard, setString: ns_string forType: ns_type];
+            msg_send![pasteboard, setString: ns_string.as_concrete_TypeRef() as *mut Object forType: ns_type.as_concrete_TypeRef() as *mut Object]
+        };
+        if !ok {
+            return Err(anyhow!("Clipboard write failed"));
         }
         Ok(())
     }
@@ -0,0 +1,683 @@
+use crate::chat_kind;
+use crate::types::ChatSummary;
+use crate::ui_automation::{ChatPage, ChatQuery};
+use anyhow::{Context, Result};
+use md5::{Digest, Md5};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+pub const SESSION_DB: &str = "Session/session_new.db";
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub const CONTACT_DB: &str = "Contact/wccontact_new2.db";
+const MESSAGE_DIR: &str = "Message";
+const CHAT_TABLE_PREFIX: &str = "Chat_";
+const SYSTEM_MESSAGE_TYPE: i64 = 10000;
+/// `mesDes` is 0 for messages the user sent and 1 for received ones.
+const RECEIVED: i64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbMessage {
+    pub local_id: i64,
+    pub server_id: i64,
+    pub msg_type: i64,
+    pub create_time: u64,
+    pub content: String,
+    pub is_self: bool,
+}
+
+/// Picks the account directory (`<version>/<account hash>`) under WeChat's
+/// `Application Support` folder, preferring the most recently written session db.
+pub fn locate_account_dir_in(support_dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(support_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|version| std::fs::read_dir(version.path()).ok())
+        .flat_map(|accounts| accounts.flatten())
+        .filter_map(|entry| {
+            let modified = std::fs::metadata(entry.path().join(SESSION_DB))
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            Some((modified, entry.path()))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, account_dir)| account_dir)
+}
+
+/// Messages are sharded across `Message/msg_{n}.db`, one table per chat.
+pub fn message_dbs(account_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(account_dir.join(MESSAGE_DIR)) else {
+        return Vec::new();
+    };
+    let mut dbs: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let index = name
+                .strip_prefix("msg_")?
+                .strip_suffix(".db")?
+                .parse::<u32>()
+                .ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    dbs.sort_by_key(|(index, _)| *index);
+    dbs.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Each chat is stored in `Chat_<md5 of the user name>`.
+pub fn chat_table(user_name: &str) -> String {
+    let digest: String = Md5::digest(user_name.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}{}", CHAT_TABLE_PREFIX, digest)
+}
+
+fn is_chat_table(name: &str) -> bool {
+    name.strip_prefix(CHAT_TABLE_PREFIX).is_some_and(|digest| {
+        digest.len() == 32 && digest.chars().all(|ch| ch.is_ascii_hexdigit())
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionRow {
+    pub user_name: String,
+    pub last_time: u64,
+    pub unread_count: u32,
+}
+
+pub fn query_session_rows(session: &Connection, limit: usize) -> Result<Vec<SessionRow>> {
+    let mut stmt = session.prepare(
+        "SELECT m_nsUserName, m_uLastTime, m_uUnReadCount FROM SessionAbstract
+         WHERE m_nsUserName != '' AND m_nsUserName NOT LIKE '@%'
+         ORDER BY m_uLastTime DESC LIMIT ?1",
+    )?;
+    let rows = stmt.query_map(params![limit as i64], |row| {
+        Ok(SessionRow {
+            user_name: row.get(0)?,
+            last_time: row.get::<_, Option<i64>>(1)?.unwrap_or_default().max(0) as u64,
+            unread_count: row.get::<_, Option<i64>>(2)?.unwrap_or_default().max(0) as u32,
+        })
+    })?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取会话列表失败")
+}
+
+pub fn query_session_users(session: &Connection, limit: usize) -> Result<Vec<String>> {
+    let rows = query_session_rows(session, limit)?;
+    Ok(rows.into_iter().map(|row| row.user_name).collect())
+}
+
+/// One page of the `limit` most recent sessions, filtered by title or id.
+/// Contact names are only looked up until the page is full.
+pub fn query_sessions(
+    session: &Connection,
+    contacts: Option<&Connection>,
+    limit: usize,
+    query: &ChatQuery,
+) -> Result<ChatPage> {
+    let mut failed = None;
+    let chats = query_session_rows(session, limit)?
+        .into_iter()
+        .map_while(|row| {
+            let name = contacts.map(|contacts| query_display_name(contacts, &row.user_name));
+            let chat_title = match name {
+                Some(Ok(name)) => name,
+                Some(Err(err)) => {
+                    failed = Some(err);
+                    return None;
+                }
+                None => None,
+            };
+            Some(ChatSummary {
+                chat_title: chat_title.unwrap_or_else(|| row.user_name.clone()),
+                kind: chat_kind::from_user_name(&row.user_name),
+                chat_id: row.user_name,
+                account_id: String::new(),
+                // The session db keeps no message text; see `query_last_message`.
+                last_message: String::new(),
+                last_active_at: row.last_time,
+                unread_count: row.unread_count,
+            })
+        });
+    let page = query.page(chats);
+    match failed {
+        Some(err) => Err(err),
+        None => Ok(page),
+    }
+}
+
+pub fn query_display_name(contacts: &Connection, user_name: &str) -> Result<Option<String>> {
+    contacts
+        .query_row(
+            "SELECT COALESCE(NULLIF(m_nsRemark, ''), NULLIF(nickname, ''))
+             FROM WCContact WHERE m_nsUsrName = ?1",
+            params![user_name],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map(Option::flatten)
+        .context("读取联系人失败")
+}
+
+pub fn list_chat_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取消息表失败")?;
+    Ok(names.into_iter().filter(|name| is_chat_table(name)).collect())
+}
+
+/// Chat tables among `tables` that live in this shard.
+pub fn filter_chat_tables(conn: &Connection, tables: &[String]) -> Result<Vec<String>> {
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; tables.len()].join(", ");
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name IN ({})",
+        placeholders
+    ))?;
+    let names = stmt
+        .query_map(rusqlite::params_from_iter(tables), |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取消息表失败")?;
+    Ok(names.into_iter().filter(|name| is_chat_table(name)).collect())
+}
+
+/// Chat tables of the listen targets, matched by display name or user name,
+/// plus the target names that matched no known chat.
+pub fn target_tables(
+    chats: &[(String, String)],
+    target_names: &[String],
+) -> (Vec<String>, Vec<String>) {
+    let mut tables = Vec::new();
+    let mut unresolved = Vec::new();
+    for name in target_names {
+        let before = tables.len();
+        for (user_name, title) in chats {
+            let table = chat_table(user_name);
+            if (title == name || user_name == name) && !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+        if tables.len() == before {
+            unresolved.push(name.clone());
+        }
+    }
+    (tables, unresolved)
+}
+
+pub fn query_max_local_id(conn: &Connection, table: &str) -> Result<i64> {
+    anyhow::ensure!(is_chat_table(table), "无效的消息表: {}", table);
+    conn.prepare_cached(&format!("SELECT COALESCE(MAX(mesLocalID), 0) FROM {}", table))?
+        .query_row([], |row| row.get(0))
+        .context("读取消息表失败")
+}
+
+/// Messages in `table` after `after_local_id` in either direction, oldest
+/// first; system notices are skipped.
+pub fn query_messages_after(
+    conn: &Connection,
+    table: &str,
+    after_local_id: i64,
+    limit: usize,
+) -> Result<Vec<DbMessage>> {
+    anyhow::ensure!(is_chat_table(table), "无效的消息表: {}", table);
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT mesLocalID, mesSvrID, messageType, msgCreateTime, msgContent, mesDes FROM {}
+         WHERE mesLocalID > ?1 AND messageType != ?2
+         ORDER BY mesLocalID LIMIT ?3",
+        table
+    ))?;
+    let params = params![after_local_id, SYSTEM_MESSAGE_TYPE, limit as i64];
+    let rows = stmt.query_map(params, db_message)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取消息失败")
+}
+
+/// The newest message in `table` in either direction, for session previews.
+/// Not cached: the session list touches far more tables than polling does.
+pub fn query_last_message(conn: &Connection, table: &str) -> Result<Option<DbMessage>> {
+    anyhow::ensure!(is_chat_table(table), "无效的消息表: {}", table);
+    conn.prepare(&format!(
+        "SELECT mesLocalID, mesSvrID, messageType, msgCreateTime, msgContent, mesDes FROM {}
+         WHERE messageType != ?1 ORDER BY mesLocalID DESC LIMIT 1",
+        table
+    ))?
+    .query_row(params![SYSTEM_MESSAGE_TYPE], db_message)
+    .optional()
+    .context("读取消息失败")
+}
+
+/// `(timestamp, is_self)` of the messages in `table` since `since`, oldest
+/// first. Not cached, like `query_last_message`.
+pub fn query_message_times(
+    conn: &Connection,
+    table: &str,
+    since: u64,
+) -> Result<Vec<(u64, bool)>> {
+    anyhow::ensure!(is_chat_table(table), "无效的消息表: {}", table);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT msgCreateTime, mesDes FROM {}
+         WHERE msgCreateTime >= ?1 AND messageType != ?2
+         ORDER BY msgCreateTime, mesLocalID",
+        table
+    ))?;
+    let rows = stmt.query_map(params![since as i64, SYSTEM_MESSAGE_TYPE], |row| {
+        Ok((row.get::<_, i64>(0)?.max(0) as u64, row.get::<_, i64>(1)? != RECEIVED))
+    })?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取消息失败")
+}
+
+fn db_message(row: &rusqlite::Row<'_>) -> rusqlite::Result<DbMessage> {
+    Ok(DbMessage {
+        local_id: row.get(0)?,
+        server_id: row.get(1)?,
+        msg_type: row.get(2)?,
+        create_time: row.get::<_, i64>(3)?.max(0) as u64,
+        content: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        is_self: row.get::<_, i64>(5)? != RECEIVED,
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub mod reader {
+    use super::{
+        chat_table, filter_chat_tables, list_chat_tables, locate_account_dir_in, message_dbs,
+        query_display_name, query_last_message, query_max_local_id, query_message_times,
+        query_messages_after, query_session_users, query_sessions, target_tables, DbMessage,
+        CONTACT_DB, SESSION_DB,
+    };
+    use crate::chat_kind;
+    use crate::content_type;
+    use crate::listen_targets::MAX_LISTEN_TARGETS;
+    use crate::secret::ApiKeyManager;
+    use crate::sqlcipher;
+    use crate::types::{ChatActivityStats, ChatKind, ChatSummary, ListenTarget, Platform};
+    use crate::ui_automation::{
+        activity, split_group_sender, ChatPage, ChatQuery, IncomingMessage, WeChatAutomation,
+    };
+    use anyhow::{anyhow, Result};
+    use rusqlite::Connection;
+    use std::collections::{HashMap, HashSet};
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+    use tracing::{info, warn};
+
+    pub const DATA_DIR_ENV: &str = "WEREPLY_WECHAT_DATA_DIR";
+    const SUPPORT_DIR: &str =
+        "Library/Containers/com.tencent.xinWeChat/Data/Library/Application Support/com.tencent.xinWeChat";
+    /// Sessions scanned when paging or searching the chat list.
+    const SESSION_LIMIT: usize = 2000;
+    const NAME_LIMIT: usize = 2000;
+    const POLL_LIMIT: usize = 200;
+    /// One message query per watched table, plus the table filter.
+    const STATEMENT_CACHE: usize = MAX_LISTEN_TARGETS + 8;
+    const RESOLVE_INTERVAL: Duration = Duration::from_secs(30);
+
+    struct ChatName {
+        user_name: String,
+        title: String,
+    }
+
+    struct Listening {
+        /// `None` watches every chat table.
+        tables: Option<Vec<String>>,
+        unresolved: Vec<String>,
+        resolved_at: Instant,
+        known: HashSet<String>,
+        cursors: HashMap<String, i64>,
+    }
+
+    pub struct MacosDb {
+        account_dir: PathBuf,
+        pragmas: String,
+        listening: Mutex<Option<Listening>>,
+        conns: Mutex<HashMap<PathBuf, Connection>>,
+        names: Mutex<HashMap<String, ChatName>>,
+        senders: Mutex<HashMap<String, String>>,
+    }
+
+    impl MacosDb {
+        pub fn new() -> Result<Self> {
+            let key = ApiKeyManager::get_wechat_db_key()?
+                .ok_or_else(|| anyhow!("未设置微信数据库密钥"))?;
+            let pragmas = sqlcipher::wechat_macos_pragmas(&key).map_err(|err| anyhow!(err))?;
+            let account_dir =
+                locate_account_dir().ok_or_else(|| anyhow!("未找到微信数据目录"))?;
+            sqlcipher::remove_legacy_snapshots(
+                &std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join("Library").join("Caches"))
+                    .unwrap_or_else(std::env::temp_dir)
+                    .join("wereply")
+                    .join("wechat-db"),
+            );
+            info!("使用微信数据库读取消息: {}", account_dir.display());
+            Ok(Self {
+                account_dir,
+                pragmas,
+                listening: Mutex::new(None),
+                conns: Mutex::new(HashMap::new()),
+                names: Mutex::new(HashMap::new()),
+                senders: Mutex::new(HashMap::new()),
+            })
+        }
+
+        pub fn is_listening(&self) -> bool {
+            self.listening.lock().is_ok_and(|guard| guard.is_some())
+        }
+
+        fn open(&self, path: &Path) -> Result<Connection> {
+            sqlcipher::open_readonly(path, &self.pragmas).map_err(|err| anyhow!(err))
+        }
+
+        fn open_contacts(&self) -> Option<Connection> {
+            self.open(&self.account_dir.join(CONTACT_DB))
+                .map_err(|err| warn!("读取微信联系人数据库失败: {}", err))
+                .ok()
+        }
+
+        /// Maps chat tables back to chats; tables only carry a hash.
+        fn reload_names(&self) -> Result<()> {
+            let session = self.open(&self.account_dir.join(SESSION_DB))?;
+            let contacts = self.open_contacts();
+            let mut names = HashMap::new();
+            for user_name in query_session_users(&session, NAME_LIMIT)? {
+                let title = contacts
+                    .as_ref()
+                    .and_then(|contacts| query_display_name(contacts, &user_name).ok().flatten())
+                    .unwrap_or_else(|| user_name.clone());
+                names.insert(chat_table(&user_name), ChatName { user_name, title });
+            }
+            *self.names.lock().map_err(|_| anyhow!("Names lock poisoned"))? = names;
+            Ok(())
+        }
+
+        fn chat_names(&self) -> Vec<(String, String)> {
+            self.names
+                .lock()
+                .map(|names| {
+                    names
+                        .values()
+                        .map(|chat| (chat.user_name.clone(), chat.title.clone()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+
+        fn display_name(&self, table: &str) -> String {
+            let cached = |names: &Mutex<HashMap<String, ChatName>>| {
+                names.lock().ok().and_then(|names| names.get(table).map(|chat| chat.title.clone()))
+            };
+            if let Some(name) = cached(&self.names) {
+                return name;
+            }
+            if let Err(err) = self.reload_names() {
+                warn!("刷新微信会话名称失败: {}", err);
+            }
+            cached(&self.names).unwrap_or_else(|| table.to_string())
+        }
+
+        fn chat_user_name(&self, table: &str) -> Option<String> {
+            self.names.lock().ok()?.get(table).map(|chat| chat.user_name.clone())
+        }
+
+        /// Group members are looked up in the contact table; members the user
+        /// has no contact entry for keep their wxid.
+        fn sender_name(&self, user_name: &str) -> String {
+            let cached = self.senders.lock().ok();
+            if let Some(name) = cached.and_then(|senders| senders.get(user_name).cloned()) {
+                return name;
+            }
+            let name = self
+                .open_contacts()
+                .and_then(|contacts| query_display_name(&contacts, user_name).ok().flatten())
+                .unwrap_or_else(|| user_name.to_string());
+            if let Ok(mut senders) = self.senders.lock() {
+                senders.insert(user_name.to_string(), name.clone());
+            }
+            name
+        }
+
+        /// Runs `op` on every message shard. Connections stay open between
+        /// polls so cached statements are reused; the bundled SQLCipher sees
+        /// new commits on each read. A failing shard is reopened next time.
+        fn with_message_dbs(&self, mut op: impl FnMut(&Connection) -> Result<()>) -> Result<()> {
+            let paths = message_dbs(&self.account_dir);
+            if paths.is_empty() {
+                return Err(anyhow!("未找到微信消息数据库 msg_*.db"));
+            }
+            let mut conns = self.conns.lock().map_err(|_| anyhow!("Connections lock poisoned"))?;
+            conns.retain(|path, _| paths.contains(path));
+            for path in paths {
+                if !conns.contains_key(&path) {
+                    let conn = self.open(&path)?;
+                    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE);
+                    conns.insert(path.clone(), conn);
+                }
+                if let Err(err) = op(&conns[&path]) {
+                    conns.remove(&path);
+                    return Err(err);
+                }
+            }
+            Ok(())
+        }
+
+        /// Previews come from the newest row of each chat table across shards.
+        fn fill_last_messages(&self, chats: &mut [ChatSummary]) -> Result<()> {
+            let tables: Vec<String> = chats.iter().map(|chat| chat_table(&chat.chat_id)).collect();
+            let mut latest: HashMap<String, DbMessage> = HashMap::new();
+            self.with_message_dbs(|conn| {
+                for table in filter_chat_tables(conn, &tables)? {
+                    let Some(message) = query_last_message(conn, &table)? else {
+                        continue;
+                    };
+                    let newer = latest
+                        .get(&table)
+                        .is_none_or(|known| known.create_time < message.create_time);
+                    if newer {
+                        latest.insert(table, message);
+                    }
+                }
+                Ok(())
+            })?;
+            for (chat, table) in chats.iter_mut().zip(&tables) {
+                if let Some(message) = latest.get(table) {
+                    chat.last_message =
+                        content_type::session_preview(message.msg_type, &message.content);
+                    chat.last_active_at = chat.last_active_at.max(message.create_time);
+                }
+            }
+            Ok(())
+        }
+
+        /// Targets missing from the session list (e.g. chats created after
+        /// listening started) are looked up again every `RESOLVE_INTERVAL`.
+        fn resolve_late_targets(&self, listening: &mut Listening) {
+            listening.resolved_at = Instant::now();
+            if let Err(err) = self.reload_names() {
+                warn!("刷新微信会话名称失败: {}", err);
+                return;
+            }
+            let (tables, unresolved) = target_tables(&self.chat_names(), &listening.unresolved);
+            if let Some(watched) = listening.tables.as_mut() {
+                for table in tables {
+                    if !watched.contains(&table) {
+                        watched.push(table);
+                    }
+                }
+            }
+            listening.unresolved = unresolved;
+        }
+    }
+
+    impl WeChatAutomation for MacosDb {
+        fn platform(&self) -> Platform {
+            Platform::Macos
+        }
+
+        fn list_chats(&self, query: &ChatQuery) -> Result<ChatPage> {
+            let session = self.open(&self.account_dir.join(SESSION_DB))?;
+            let contacts = self.open_contacts();
+            let mut page = query_sessions(&session, contacts.as_ref(), SESSION_LIMIT, query)?;
+            if let Err(err) = self.fill_last_messages(&mut page.chats) {
+                warn!("读取会话最后一条消息失败: {}", err);
+            }
+            Ok(page)
+        }
+
+        fn start_listening(&self, targets: Vec<ListenTarget>) -> Result<()> {
+            if let Err(err) = self.reload_names() {
+                warn!("读取微信会话名称失败: {}", err);
+            }
+            let target_names: Vec<String> = targets
+                .iter()
+                .map(|target| target.name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect();
+            let (tables, unresolved) = if target_names.is_empty() {
+                (None, Vec::new())
+            } else {
+                let (tables, unresolved) = target_tables(&self.chat_names(), &target_names);
+                (Some(tables), unresolved)
+            };
+            if !unresolved.is_empty() {
+                info!("监听对象暂未在微信数据库中找到: {}", unresolved.join("、"));
+            }
+            let mut known = HashSet::new();
+            let mut cursors = HashMap::new();
+            self.with_message_dbs(|conn| {
+                for table in list_chat_tables(conn)? {
+                    if tables.as_ref().is_none_or(|tables| tables.contains(&table)) {
+                        cursors.insert(table.clone(), query_max_local_id(conn, &table)?);
+                    }
+                    known.insert(table);
+                }
+                Ok(())
+            })?;
+            let mut guard = self.listening.lock().map_err(|_| anyhow!("Cursor lock poisoned"))?;
+            *guard = Some(Listening {
+                tables,
+                unresolved,
+                resolved_at: Instant::now(),
+                known,
+                cursors,
+            });
+            Ok(())
+        }
+
+        fn stop_listening(&self) -> Result<()> {
+            let mut guard = self.listening.lock().map_err(|_| anyhow!("Cursor lock poisoned"))?;
+            *guard = None;
+            self.conns
+                .lock()
+                .map_err(|_| anyhow!("Connections lock poisoned"))?
+                .clear();
+            Ok(())
+        }
+
+        fn write_input(&self, _chat_id: &str, _text: &str) -> Result<()> {
+            Err(anyhow!("数据库模式只能读取消息，无法写入输入框"))
+        }
+
+        fn poll_new_messages(&self) -> Result<Vec<IncomingMessage>> {
+            let mut guard = self.listening.lock().map_err(|_| anyhow!("Cursor lock poisoned"))?;
+            let Some(listening) = guard.as_mut() else {
+                return Ok(Vec::new());
+            };
+            let stale = listening.resolved_at.elapsed() >= RESOLVE_INTERVAL;
+            if stale && !listening.unresolved.is_empty() {
+                self.resolve_late_targets(listening);
+            }
+            let mut batch: Vec<(String, DbMessage)> = Vec::new();
+            self.with_message_dbs(|conn| {
+                let tables = match listening.tables.as_deref() {
+                    Some(tables) => filter_chat_tables(conn, tables)?,
+                    None => list_chat_tables(conn)?,
+                };
+                for table in tables {
+                    let after = match listening.cursors.get(&table) {
+                        Some(local_id) => *local_id,
+                        // A late-resolved target that existed at start: skip its history.
+                        None if listening.known.contains(&table) => {
+                            query_max_local_id(conn, &table)?
+                        }
+                        None => 0,
+                    };
+                    let messages = query_messages_after(conn, &table, after, POLL_LIMIT)?;
+                    let last = messages.last().map_or(after, |message| message.local_id);
+                    listening.cursors.insert(table.clone(), last);
+                    batch.extend(messages.into_iter().map(|message| (table.clone(), message)));
+                }
+                Ok(())
+            })?;
+            drop(guard);
+            batch.sort_by_key(|(_, message)| message.create_time);
+            Ok(batch
+                .into_iter()
+                .map(|(table, message)| {
+                    let chat_id = self.display_name(&table);
+                    let is_group = self.chat_user_name(&table).is_some_and(|user_name| {
+                        chat_kind::from_user_name(&user_name) == ChatKind::Group
+                    });
+                    let (sender, content) = match split_group_sender(&message.content) {
+                        Some((sender, content)) if is_group => (Some(sender), content),
+                        _ => (None, message.content.as_str()),
+                    };
+                    let decoded = content_type::decode_wechat_message(message.msg_type, content);
+                    IncomingMessage {
+                        chat_id,
+                        content_type: decoded.content_type,
+                        text: decoded.text,
+                        timestamp: message.create_time,
+                        msg_id: Some(message.server_id.to_string()),
+                        sender_name: sender.map(|id| self.sender_name(id)).unwrap_or_default(),
+                        is_group,
+                        is_self: message.is_self,
+                    }
+                })
+                .collect())
+        }
+
+        fn watch_paths(&self) -> Vec<PathBuf> {
+            message_dbs(&self.account_dir)
+        }
+
+        fn chat_activity(&self, now: u64) -> Result<Vec<ChatActivityStats>> {
+            let since = activity::window_start(now);
+            let mut messages: HashMap<String, Vec<(u64, bool)>> = HashMap::new();
+            self.with_message_dbs(|conn| {
+                for table in list_chat_tables(conn)? {
+                    let rows = query_message_times(conn, &table, since)?;
+                    if !rows.is_empty() {
+                        messages.entry(table).or_default().extend(rows);
+                    }
+                }
+                Ok(())
+            })?;
+            let mut stats: Vec<ChatActivityStats> = messages
+                .into_iter()
+                .filter_map(|(table, mut rows)| {
+                    rows.sort_unstable();
+                    activity::summarize(self.display_name(&table), &rows, now)
+                })
+                .collect();
+            activity::sort_by_activity(&mut stats);
+            Ok(stats)
+        }
+    }
+
+    pub fn locate_account_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os(DATA_DIR_ENV).map(PathBuf::from) {
+            if dir.join(SESSION_DB).is_file() {
+                return Some(dir);
+            }
+        }
+        let home = std::env::var_os("HOME").map(PathBuf::from)?;
+        locate_account_dir_in(&home.join(SUPPORT_DIR))
+    }
+}
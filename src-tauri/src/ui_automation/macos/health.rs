@@ -0,0 +1,34 @@
+use std::time::{Duration, Instant};
+
+const FAILURE_THRESHOLD: u32 = 3;
+const COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Consecutive-failure tracker for one backend. After a few failures in a row
+/// the backend is skipped for a cooldown, then tried again on the next call.
+#[derive(Debug, Default)]
+pub struct BackendHealth {
+    failures: u32,
+    disabled_until: Option<Instant>,
+}
+
+impl BackendHealth {
+    pub fn is_available(&self, now: Instant) -> bool {
+        self.disabled_until.is_none_or(|until| now >= until)
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.disabled_until = None;
+    }
+
+    /// Returns `true` when this failure put the backend into cooldown.
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.failures += 1;
+        if self.failures < FAILURE_THRESHOLD {
+            return false;
+        }
+        self.failures = 0;
+        self.disabled_until = Some(now + COOLDOWN);
+        true
+    }
+}
@@ -43,6 +43,7 @@ pub mod ax {
     use crate::ui_automation::macos::ax::{self, AxElement};
     use crate::ui_automation::macos::static_ui_paths;
     use crate::ui_automation::macos::ui_paths_store;
+    use crate::ui_automation::trace;
     use anyhow::{anyhow, Result};
 
     pub struct AxInputWriter {
@@ -57,22 +58,56 @@ pub mod ax {
         }
 
         pub fn write(&self, text: &str) -> Result<()> {
-            let input = ui_paths_store::get_paths()
-                .and_then(|paths| ax::resolve_owned_path(&self.window, &paths.input))
-                .or_else(|| ax::resolve_any_path(&self.window, static_ui_paths::INPUT_PATHS))
-                .or_else(|| {
-                    if static_ui_paths::allow_dynamic_scan() {
-                        ax::find_input_element(&self.window, 8)
-                    } else {
-                        None
-                    }
-                })
-                .ok_or_else(|| anyhow!("Input box not found (static UI path)"))?;
-            if ax::set_input_value(&input, text).is_ok() {
+            let step = trace::step("find_element", "input");
+            let found = self.find_input();
+            step.finish(
+                found.as_ref().map_or("static_path", |(_, via)| *via),
+                &found,
+            );
+            let (input, _) = found?;
+
+            let step = trace::step("set_value", "input");
+            let result = ax::set_input_value(&input, text);
+            step.finish("ax_value", &result);
+            if result.is_ok() {
                 return Ok(());
             }
             ax::focus_element(&input).ok();
-            ax::paste_text(text)
+            let step = trace::step("paste", "input");
+            // Replace any half-typed draft instead of appending to it.
+            let result = ax::select_all().and_then(|()| ax::paste_text(text));
+            step.finish("clipboard", &result);
+            result
+        }
+
+        pub fn locate(&self) -> Result<&'static str> {
+            self.find_input().map(|(_, via)| via)
+        }
+
+        pub fn submit(&self) -> Result<()> {
+            let (input, _) = self.find_input()?;
+            ax::focus_element(&input).ok();
+            let step = trace::step("submit", "input");
+            let result = ax::send_return();
+            step.finish("keyboard", &result);
+            result
+        }
+
+        fn find_input(&self) -> Result<(AxElement, &'static str)> {
+            if let Some(input) = ui_paths_store::get_paths()
+                .and_then(|paths| ax::resolve_owned_path(&self.window, &paths.input))
+            {
+                return Ok((input, "learned_path"));
+            }
+            if let Some(input) = ax::resolve_any_path(&self.window, static_ui_paths::INPUT_PATHS) {
+                return Ok((input, "static_path"));
+            }
+            if static_ui_paths::allow_dynamic_scan() {
+                if let Some(input) = ax::find_input_element(&self.window, 8) {
+                    return Ok((input, "dynamic_scan"));
+                }
+            }
+            Err(anyhow!("Input box not found (static UI path)"))
         }
     }
 }
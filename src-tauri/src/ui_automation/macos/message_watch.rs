@@ -1,9 +1,5 @@
 #[cfg(test)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum WatchMode {
-    Event,
-    Polling,
-}
+use crate::ui_automation::WatchMode;
 
 #[cfg(test)]
 pub struct MockAxWatcher {
@@ -32,17 +28,30 @@ impl MockAxWatcher {
 
 #[cfg(target_os = "macos")]
 pub mod ax {
-    use crate::ui_automation::macos::ax::{self, AxElement};
+    use crate::ui_automation::macos::ax::{self, AxElement, AxObserver};
     use crate::ui_automation::macos::static_ui_paths;
     use crate::ui_automation::macos::ui_paths_store;
+    use crate::ui_automation::{TextChanges, WatchMode};
     use anyhow::{anyhow, Result};
     use super::{pick_row_text, score_message_list};
-    #[cfg(test)]
-    use super::WatchMode;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{channel, Receiver};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tracing::warn;
+
+    /// New rows, edited texts, and rows added to a table that only reports its
+    /// row count.
+    const NOTIFICATIONS: [&str; 3] = ["AXCreated", "AXValueChanged", "AXRowCountChanged"];
+    const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(2);
 
     pub struct AxMessageWatcher {
         window: AxElement,
         list: AxElement,
+        events: Option<Receiver<AxElement>>,
+        stop: Arc<AtomicBool>,
+        changes: TextChanges,
     }
 
     impl AxMessageWatcher {
@@ -51,16 +60,74 @@ pub mod ax {
             Ok(Self {
                 window: window.clone(),
                 list,
+                events: None,
+                stop: Arc::new(AtomicBool::new(false)),
+                changes: TextChanges::default(),
             })
         }
 
-        #[cfg(test)]
-        #[allow(dead_code)]
-        pub fn start(&self) -> WatchMode {
-            WatchMode::Polling
+        /// Observes the message list of WeChat process `pid`, falling back to
+        /// polling when the observer cannot be set up.
+        pub fn start(&mut self, pid: i32) -> WatchMode {
+            match self.try_subscribe(pid) {
+                Ok(()) => WatchMode::Event,
+                Err(err) => {
+                    warn!("订阅消息列表通知失败，改为定时轮询: {}", err);
+                    WatchMode::Polling
+                }
+            }
+        }
+
+        pub fn mode(&self) -> WatchMode {
+            if self.events.is_some() {
+                WatchMode::Event
+            } else {
+                WatchMode::Polling
+            }
+        }
+
+        /// Texts of the rows that changed since the last call, once the latest
+        /// burst of notifications has settled.
+        pub fn settled_changes(&mut self) -> Vec<String> {
+            if let Some(events) = &self.events {
+                for element in events.try_iter() {
+                    if let Some(text) = changed_text(&element) {
+                        self.changes.push(&text, Instant::now());
+                    }
+                }
+            }
+            self.changes.take_settled(Instant::now())
         }
 
-        pub fn latest_message_text(&self) -> Option<String> {
+        fn try_subscribe(&mut self, pid: i32) -> Result<()> {
+            let (tx, rx) = channel();
+            let (ready_tx, ready_rx) = channel();
+            let list = self.list.clone();
+            let stop = self.stop.clone();
+            // AX notifications need a running run loop, which the polling
+            // threads do not have, so the observer gets a thread of its own.
+            thread::Builder::new()
+                .name("wereply-ax-observer".to_string())
+                .spawn(move || match AxObserver::new(pid, &list, &NOTIFICATIONS, tx) {
+                    Ok(observer) => {
+                        observer.schedule();
+                        let _ = ready_tx.send(Ok(()));
+                        ax::run_loop_until(&stop);
+                    }
+                    Err(err) => {
+                        let _ = ready_tx.send(Err(err));
+                    }
+                })?;
+            let ready = ready_rx.recv_timeout(SUBSCRIBE_TIMEOUT).map_err(|_| {
+                self.stop.store(true, Ordering::Relaxed);
+                anyhow!("AXObserver 启动超时")
+            })?;
+            ready?;
+            self.events = Some(rx);
+            Ok(())
+        }
+
+        pub fn message_texts(&self) -> Vec<String> {
             let mut candidates = Vec::new();
             for row in ax::children(&self.list) {
                 let texts = ax::collect_static_texts(&row, 8);
@@ -68,7 +135,7 @@ pub mod ax {
                     candidates.push(text);
                 }
             }
-            candidates.into_iter().last()
+            candidates
         }
 
         pub fn window(&self) -> &AxElement {
@@ -76,6 +143,24 @@ pub mod ax {
         }
     }
 
+    impl Drop for AxMessageWatcher {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Text a notification points at: a static text itself, or the row of a
+    /// new cell; for the list itself, its newest row.
+    fn changed_text(element: &AxElement) -> Option<String> {
+        match ax::role(element).as_deref() {
+            Some("AXStaticText") => ax::value(element).or_else(|| ax::title(element)),
+            Some("AXTable" | "AXList" | "AXOutline") => ax::children(element)
+                .last()
+                .and_then(|row| pick_row_text(&ax::collect_static_texts(row, 8))),
+            _ => pick_row_text(&ax::collect_static_texts(element, 8)),
+        }
+    }
+
     fn find_message_list(window: &AxElement) -> Result<AxElement> {
         if let Some(paths) = ui_paths_store::get_paths() {
             if let Some(list) = ax::resolve_owned_path(window, &paths.message_list) {
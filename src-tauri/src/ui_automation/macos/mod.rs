@@ -2,6 +2,10 @@ pub mod ax;
 pub mod ax_path;
 pub mod ax_learn;
 pub mod ax_snapshot;
+#[cfg(any(test, target_os = "macos"))]
+pub mod db;
+#[cfg(any(test, target_os = "macos"))]
+pub mod health;
 pub mod message_watch;
 pub mod input_box;
 pub mod session_list;
@@ -12,6 +16,8 @@ pub mod ui_paths_store;
 #[cfg(target_os = "macos")]
 pub use ax::AxClient;
 #[cfg(target_os = "macos")]
+pub use db::reader::MacosDb;
+#[cfg(target_os = "macos")]
 pub use input_box::ax::AxInputWriter;
 #[cfg(target_os = "macos")]
 pub use message_watch::ax::AxMessageWatcher;
@@ -23,18 +29,30 @@ mod tests;
 
 #[cfg(target_os = "macos")]
 mod automation {
-    use super::session_list::collect_recent_chats;
-    use super::{AxClient, AxInputWriter, AxMessageWatcher, AxSessionList};
-    use crate::types::{ChatSummary, ListenTarget, Platform};
-    use crate::ui_automation::{IncomingMessage, WeChatAutomation};
+    use super::ax::AxElement;
+    use super::health::BackendHealth;
+    use super::session_list::collect_chats;
+    use super::{AxClient, AxInputWriter, AxMessageWatcher, AxSessionList, MacosDb};
+    use crate::types::{ChatActivityStats, ListenTarget, Platform};
+    use crate::ui_automation::{
+        ensure_active_chat, fresh_rows, rows_after, ChatPage, ChatQuery, IncomingMessage,
+        WatchMode, WeChatAutomation, CHAT_SWITCH_SETTLE,
+    };
     use anyhow::{anyhow, Result};
+    use std::path::PathBuf;
     use std::sync::Mutex;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::thread::sleep;
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
     use tracing::{info, warn};
 
     pub struct MacosAutomation {
         client: Option<AxClient>,
         watcher: Mutex<Option<AxMessageWatcher>>,
+        seen_rows: Mutex<Vec<String>>,
+        /// Chat whose rows `seen_rows` holds in event mode.
+        seen_chat: Mutex<String>,
+        db: Option<MacosDb>,
+        db_health: Mutex<BackendHealth>,
     }
 
     impl MacosAutomation {
@@ -50,10 +68,36 @@ mod automation {
             Ok(Self {
                 client,
                 watcher: Mutex::new(None),
+                seen_rows: Mutex::new(Vec::new()),
+                seen_chat: Mutex::new(String::new()),
+                db: None,
+                db_health: Mutex::new(BackendHealth::default()),
             })
         }
 
-        fn list_chats(&self) -> Result<Vec<ChatSummary>> {
+        /// Reads chats and messages from `MacosDb` and keeps AX for writing
+        /// input. AX also serves reads while the database keeps failing.
+        pub fn hybrid() -> Result<Self> {
+            let db = MacosDb::new()?;
+            let client = if super::ax::check_accessibility() {
+                AxClient::new().ok()
+            } else {
+                None
+            };
+            if client.is_none() {
+                warn!("辅助功能不可用，混合模式只能读取消息");
+            }
+            Ok(Self {
+                client,
+                watcher: Mutex::new(None),
+                seen_rows: Mutex::new(Vec::new()),
+                seen_chat: Mutex::new(String::new()),
+                db: Some(db),
+                db_health: Mutex::new(BackendHealth::default()),
+            })
+        }
+
+        fn scroll_chats(&self, query: &ChatQuery) -> Result<ChatPage> {
             let client = self
                 .client
                 .as_ref()
@@ -62,21 +106,49 @@ mod automation {
                 .front_window()
                 .ok_or_else(|| anyhow!("WeChat window not found"))?;
             let mut list = AxSessionList::from_window(&window)?;
-            collect_recent_chats(&mut list)
-        }
-    }
-
-    impl WeChatAutomation for MacosAutomation {
-        fn platform(&self) -> Platform {
-            Platform::Macos
+            collect_chats(&mut list, query)
         }
 
-        fn list_recent_chats(&self) -> Result<Vec<ChatSummary>> {
-            self.list_chats()
+        /// Runs `op` on the database while it is healthy; otherwise, or when
+        /// this call fails, answers the operation through AX instead.
+        fn with_db<T>(
+            &self,
+            action: &str,
+            op: impl FnOnce(&MacosDb) -> Result<T>,
+            fallback: impl FnOnce() -> Result<T>,
+        ) -> Result<T> {
+            let Some(db) = self.db.as_ref() else {
+                return fallback();
+            };
+            let available = self
+                .db_health
+                .lock()
+                .is_ok_and(|health| health.is_available(Instant::now()));
+            if !available {
+                return fallback();
+            }
+            match op(db) {
+                Ok(value) => {
+                    if let Ok(mut health) = self.db_health.lock() {
+                        health.record_success();
+                    }
+                    Ok(value)
+                }
+                Err(err) => {
+                    warn!("数据库{}失败，改用辅助功能: {}", action, err);
+                    let cooling = self
+                        .db_health
+                        .lock()
+                        .is_ok_and(|mut health| health.record_failure(Instant::now()));
+                    if cooling {
+                        warn!("微信数据库连续失败，暂时只使用辅助功能");
+                    }
+                    fallback()
+                }
+            }
         }
 
-        fn start_listening(&self, _targets: Vec<ListenTarget>) -> Result<()> {
-            info!("macOS 自动化开始监听");
+        fn start_ax_watcher(&self) -> Result<()> {
             let client = self
                 .client
                 .as_ref()
@@ -85,27 +157,142 @@ mod automation {
                 .front_window()
                 .ok_or_else(|| anyhow!("WeChat window not found"))?;
             info!("WeChat 窗口已找到，初始化消息监听器");
-            let watcher = AxMessageWatcher::new(&window).map_err(|err| {
+            let mut watcher = AxMessageWatcher::new(&window).map_err(|err| {
                 warn!("创建消息监听器失败: {}", err);
                 err
             })?;
+            let mode = watcher.start(client.pid());
+            {
+                let mut seen = self.seen_rows.lock().map_err(|_| anyhow!("Rows lock poisoned"))?;
+                seen.clear();
+                if mode == WatchMode::Event {
+                    // Rows already on screen re-render later and must not
+                    // count as new messages.
+                    *seen = watcher.message_texts();
+                    *self.seen_chat.lock().map_err(|_| anyhow!("Chat lock poisoned"))? =
+                        chat_title(&watcher);
+                }
+            }
             let mut guard = self
                 .watcher
                 .lock()
                 .map_err(|_| anyhow!("Watcher lock poisoned"))?;
             *guard = Some(watcher);
-            info!("macOS 消息监听器已就绪");
+            info!("macOS 消息监听器已就绪: {:?}", mode);
             Ok(())
         }
 
+        fn poll_ax(&self) -> Result<Vec<IncomingMessage>> {
+            let mut guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
+            let Some(watcher) = guard.as_mut() else {
+                return Ok(Vec::new());
+            };
+            // In event mode the list is only read after it reported changes.
+            let changed = match watcher.mode() {
+                WatchMode::Event => {
+                    let changed = watcher.settled_changes();
+                    if changed.is_empty() {
+                        return Ok(Vec::new());
+                    }
+                    Some(changed)
+                }
+                WatchMode::Polling => None,
+            };
+            let title = chat_title(watcher);
+            let texts = {
+                let mut seen = self.seen_rows.lock().map_err(|_| anyhow!("Rows lock poisoned"))?;
+                match changed {
+                    Some(changed) => {
+                        let mut seen_chat =
+                            self.seen_chat.lock().map_err(|_| anyhow!("Chat lock poisoned"))?;
+                        if *seen_chat == title {
+                            fresh_rows(&mut seen, changed)
+                        } else {
+                            // Switching chats renders its whole history.
+                            *seen = watcher.message_texts();
+                            *seen_chat = title.clone();
+                            Vec::new()
+                        }
+                    }
+                    None => {
+                        let rows = watcher.message_texts();
+                        let texts = rows_after(&seen, &rows);
+                        *seen = rows;
+                        texts
+                    }
+                }
+            };
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            Ok(texts
+                .into_iter()
+                .map(|text| IncomingMessage {
+                    chat_id: title.clone(),
+                    content_type: crate::content_type::classify(&text),
+                    text,
+                    timestamp,
+                    msg_id: None,
+                    sender_name: String::new(),
+                    is_group: false,
+                    is_self: false,
+                })
+                .collect())
+        }
+    }
+
+    /// Opens `chat_id` and confirms the window shows it before anything is
+    /// typed, so a write never lands in whichever chat was open.
+    fn focus_chat(window: &AxElement, chat_id: &str) -> Result<()> {
+        if super::ax::title(window).as_deref().map(str::trim) != Some(chat_id.trim()) {
+            AxSessionList::from_window(window)?.select(chat_id)?;
+            sleep(CHAT_SWITCH_SETTLE);
+        }
+        ensure_active_chat(chat_id, super::ax::title(window).as_deref())
+    }
+
+    fn chat_title(watcher: &AxMessageWatcher) -> String {
+        super::ax::title(watcher.window()).unwrap_or_else(|| "WeChat".to_string())
+    }
+
+    impl WeChatAutomation for MacosAutomation {
+        fn platform(&self) -> Platform {
+            Platform::Macos
+        }
+
+        fn list_chats(&self, query: &ChatQuery) -> Result<ChatPage> {
+            self.with_db("读取会话列表", |db| db.list_chats(query), || self.scroll_chats(query))
+        }
+
+        fn start_listening(&self, targets: Vec<ListenTarget>) -> Result<()> {
+            info!("macOS 自动化开始监听");
+            let db_started = self.with_db(
+                "开始监听",
+                |db| db.start_listening(targets).map(|_| true),
+                || Ok(false),
+            )?;
+            match self.start_ax_watcher() {
+                Ok(()) => Ok(()),
+                Err(err) if db_started => {
+                    warn!("辅助功能监听不可用，仅从数据库读取消息: {}", err);
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            }
+        }
+
         fn stop_listening(&self) -> Result<()> {
             info!("macOS 自动化停止监听");
+            if let Some(db) = self.db.as_ref() {
+                db.stop_listening()?;
+            }
             let mut guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
             *guard = None;
             Ok(())
         }
 
-        fn write_input(&self, _chat_id: &str, text: &str) -> Result<()> {
+        fn write_input(&self, chat_id: &str, text: &str) -> Result<()> {
             let client = self
                 .client
                 .as_ref()
@@ -113,31 +300,50 @@ mod automation {
             let window = client
                 .front_window()
                 .ok_or_else(|| anyhow!("WeChat window not found"))?;
+            focus_chat(&window, chat_id)?;
             let writer = AxInputWriter::new(&window);
             writer.write(text)
         }
 
-        fn poll_latest_message(&self) -> Result<Option<IncomingMessage>> {
-            let guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
-            let Some(watcher) = guard.as_ref() else {
-                return Ok(None);
-            };
-            let text = match watcher.latest_message_text() {
-                Some(text) => text,
-                None => return Ok(None),
+        fn submit_input(&self, chat_id: &str) -> Result<()> {
+            let client = self
+                .client
+                .as_ref()
+                .ok_or_else(|| anyhow!("WeChat window not found"))?;
+            let window = client
+                .front_window()
+                .ok_or_else(|| anyhow!("WeChat window not found"))?;
+            ensure_active_chat(chat_id, super::ax::title(&window).as_deref())?;
+            AxInputWriter::new(&window).submit()
+        }
+
+        fn poll_new_messages(&self) -> Result<Vec<IncomingMessage>> {
+            if !self.db.as_ref().is_some_and(MacosDb::is_listening) {
+                return self.poll_ax();
+            }
+            self.with_db("读取消息", |db| db.poll_new_messages(), || self.poll_ax())
+        }
+
+        /// Empty while the database is cooling down, so AX gets polled on a timer.
+        fn watch_paths(&self) -> Vec<PathBuf> {
+            let Some(db) = self.db.as_ref().filter(|db| db.is_listening()) else {
+                return Vec::new();
             };
-            let title = super::ax::title(watcher.window())
-                .unwrap_or_else(|| "WeChat".to_string());
-            let timestamp = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            Ok(Some(IncomingMessage {
-                chat_id: title,
-                text,
-                timestamp,
-                msg_id: None,
-            }))
+            let healthy = self
+                .db_health
+                .lock()
+                .is_ok_and(|health| health.is_available(Instant::now()));
+            if healthy {
+                db.watch_paths()
+            } else {
+                Vec::new()
+            }
+        }
+
+        /// AX only sees the visible rows, so there is nothing to fall back to.
+        fn chat_activity(&self, now: u64) -> Result<Vec<ChatActivityStats>> {
+            let db = self.db.as_ref().ok_or_else(|| anyhow!("微信数据库不可用"))?;
+            db.chat_activity(now)
         }
     }
 
@@ -1,4 +1,6 @@
-use crate::types::{ChatKind, ChatSummary};
+use crate::chat_kind;
+use crate::types::ChatSummary;
+use crate::ui_automation::{ChatPage, ChatQuery};
 use anyhow::{anyhow, Result};
 use std::collections::HashSet;
 use std::thread::sleep;
@@ -57,9 +59,13 @@ impl AxSessionListProvider for MockAxSessionList {
 }
 
 #[cfg(any(test, target_os = "macos"))]
-pub fn collect_recent_chats(provider: &mut dyn AxSessionListProvider) -> Result<Vec<ChatSummary>> {
+pub fn collect_chats(
+    provider: &mut dyn AxSessionListProvider,
+    query: &ChatQuery,
+) -> Result<ChatPage> {
     let mut seen = HashSet::new();
     let mut chats = Vec::new();
+    let mut matched = 0;
     let mut stagnant_rounds = 0;
     for _ in 0..64 {
         let mut new_count = 0;
@@ -72,11 +78,23 @@ pub fn collect_recent_chats(provider: &mut dyn AxSessionListProvider) -> Result<
                 continue;
             }
             new_count += 1;
-            chats.push(ChatSummary {
+            let chat = ChatSummary {
                 chat_id: title.clone(),
+                kind: chat_kind::from_title(&title),
                 chat_title: title,
-                kind: ChatKind::Unknown,
-            });
+                account_id: String::new(),
+                last_message: String::new(),
+                last_active_at: 0,
+                unread_count: 0,
+            };
+            if query.matches(&chat) {
+                matched += 1;
+            }
+            chats.push(chat);
+        }
+        // Stop scrolling once the requested page (plus one) is on screen.
+        if matched >= query.wanted() {
+            break;
         }
         if new_count == 0 {
             stagnant_rounds += 1;
@@ -94,7 +112,7 @@ pub fn collect_recent_chats(provider: &mut dyn AxSessionListProvider) -> Result<
     if chats.is_empty() {
         return Err(anyhow!("Session list empty"));
     }
-    Ok(chats)
+    Ok(query.page(chats))
 }
 
 #[cfg(target_os = "macos")]
@@ -114,6 +132,13 @@ pub mod ax {
             let list = find_session_list(window)?;
             Ok(Self { list })
         }
+
+        /// Selects the row titled `title` among the rows on screen.
+        pub fn select(&self, title: &str) -> Result<()> {
+            let row = ax::find_session_row(&self.list, title)
+                .ok_or_else(|| anyhow!("会话「{}」不在会话列表中", title))?;
+            ax::select_row(&row)
+        }
     }
 
     impl AxSessionListProvider for AxSessionList {
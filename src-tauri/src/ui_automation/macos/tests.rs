@@ -1,6 +1,10 @@
 use super::ax::{find_wechat_app, MockAx};
-use super::message_watch::{MockAxWatcher, WatchMode};
-use super::session_list::{collect_recent_chats, MockAxSessionList};
+use super::db;
+use super::health::BackendHealth;
+use super::message_watch::MockAxWatcher;
+use super::session_list::{collect_chats, MockAxSessionList};
+use crate::types::ChatKind;
+use crate::ui_automation::{ChatQuery, WatchMode};
 
 #[test]
 fn ax_finds_wechat_app() {
@@ -12,8 +16,9 @@ fn ax_finds_wechat_app() {
 #[test]
 fn macos_session_list_dedupes() {
     let mut mock = MockAxSessionList::with_pages(vec![vec!["A", "A"], vec!["B"]]);
-    let chats = collect_recent_chats(&mut mock).unwrap();
-    assert_eq!(chats.len(), 2);
+    let page = collect_chats(&mut mock, &ChatQuery::new(0, 1, "")).unwrap();
+    assert_eq!(page.chats.len(), 1);
+    assert!(page.has_more);
 }
 
 #[test]
@@ -22,3 +27,127 @@ fn macos_watcher_falls_back_to_polling_on_subscribe_failure() {
     let mode = mock.start();
     assert_eq!(mode, WatchMode::Polling);
 }
+
+const ALICE_TABLE: &str = "Chat_29a6db07e8bbdb53f5d54cc3c309f3f1";
+
+fn wechat_db_fixture() -> rusqlite::Connection {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    conn.execute_batch(&format!(
+        "CREATE TABLE SessionAbstract (m_nsUserName TEXT, m_uLastTime INTEGER,
+             m_uUnReadCount INTEGER);
+         CREATE TABLE WCContact (m_nsUsrName TEXT, nickname TEXT, m_nsRemark TEXT);
+         CREATE TABLE {ALICE_TABLE} (mesLocalID INTEGER PRIMARY KEY, mesSvrID INTEGER,
+             msgCreateTime INTEGER, msgContent TEXT, messageType INTEGER, mesDes INTEGER);
+         CREATE TABLE Chat_not_a_hash (mesLocalID INTEGER);
+         INSERT INTO SessionAbstract VALUES ('wxid_alice', 20, 2), ('123@chatroom', 30, 0),
+             ('@placeholder_foldgroup', 40, 0);
+         INSERT INTO WCContact VALUES ('wxid_alice', 'Alice', '爱丽丝');
+         INSERT INTO {ALICE_TABLE} VALUES (1, 11, 100, '早', 1, 1),
+             (2, 12, 101, '早呀', 1, 0),
+             (3, 13, 102, '撤回了一条消息', 10000, 1),
+             (4, 14, 103, '<msg/>', 3, 1);"
+    ))
+    .unwrap();
+    conn
+}
+
+#[test]
+fn macos_db_lists_sessions_by_display_name() {
+    let conn = wechat_db_fixture();
+    let page = db::query_sessions(&conn, Some(&conn), 10, &ChatQuery::new(0, 10, "")).unwrap();
+    assert!(!page.has_more);
+    let chats = page.chats;
+    let titles: Vec<_> = chats
+        .iter()
+        .map(|chat| (chat.chat_id.as_str(), chat.chat_title.as_str()))
+        .collect();
+    assert_eq!(titles, vec![("123@chatroom", "123@chatroom"), ("wxid_alice", "爱丽丝")]);
+    let kinds: Vec<_> = chats.iter().map(|chat| chat.kind.clone()).collect();
+    assert_eq!(kinds, vec![ChatKind::Group, ChatKind::Direct]);
+    assert_eq!((chats[1].last_active_at, chats[1].unread_count), (20, 2));
+    assert_eq!(db::chat_table("wxid_alice"), ALICE_TABLE);
+
+    let found = db::query_sessions(&conn, Some(&conn), 10, &ChatQuery::new(0, 1, "爱丽")).unwrap();
+    assert_eq!(found.chats[0].chat_id, "wxid_alice");
+    assert!(!found.has_more);
+    let first = db::query_sessions(&conn, Some(&conn), 10, &ChatQuery::new(0, 1, "")).unwrap();
+    assert!(first.has_more);
+}
+
+#[test]
+fn macos_db_polls_messages_per_table() {
+    let conn = wechat_db_fixture();
+    assert_eq!(db::list_chat_tables(&conn).unwrap(), vec![ALICE_TABLE.to_string()]);
+    assert_eq!(db::query_max_local_id(&conn, ALICE_TABLE).unwrap(), 4);
+    let batch = db::query_messages_after(&conn, ALICE_TABLE, 0, 10).unwrap();
+    let rows: Vec<_> = batch
+        .iter()
+        .map(|message| (message.local_id, message.is_self, message.content.as_str()))
+        .collect();
+    assert_eq!(rows, vec![(1, false, "早"), (2, true, "早呀"), (4, false, "<msg/>")]);
+    assert_eq!(db::query_messages_after(&conn, ALICE_TABLE, 0, 1).unwrap().len(), 1);
+    assert!(db::query_messages_after(&conn, ALICE_TABLE, 4, 10).unwrap().is_empty());
+    assert!(db::query_messages_after(&conn, "Chat_x; DROP TABLE WCContact", 0, 10).is_err());
+    let last = db::query_last_message(&conn, ALICE_TABLE).unwrap().unwrap();
+    assert_eq!((last.local_id, last.msg_type), (4, 3));
+}
+
+#[test]
+fn macos_db_reads_message_times_for_activity() {
+    let conn = wechat_db_fixture();
+    let times = db::query_message_times(&conn, ALICE_TABLE, 101).unwrap();
+    assert_eq!(times, vec![(101, true), (103, false)]);
+    assert!(db::query_message_times(&conn, "Chat_x; DROP TABLE WCContact", 0).is_err());
+}
+
+#[test]
+fn macos_db_filters_tables_to_listen_targets() {
+    let conn = wechat_db_fixture();
+    let chats = vec![
+        ("wxid_alice".to_string(), "爱丽丝".to_string()),
+        ("wxid_bob".to_string(), "Bob".to_string()),
+    ];
+    let targets = vec!["爱丽丝".to_string(), "wxid_bob".to_string(), "新群".to_string()];
+    let (tables, unresolved) = db::target_tables(&chats, &targets);
+    assert_eq!(tables, vec![ALICE_TABLE.to_string(), db::chat_table("wxid_bob")]);
+    assert_eq!(unresolved, vec!["新群".to_string()]);
+    assert_eq!(
+        db::filter_chat_tables(&conn, &tables).unwrap(),
+        vec![ALICE_TABLE.to_string()]
+    );
+    assert!(db::filter_chat_tables(&conn, &[]).unwrap().is_empty());
+}
+
+#[test]
+fn macos_db_picks_latest_account_and_sorts_shards() {
+    let temp = tempfile::tempdir().unwrap();
+    let account = temp.path().join("2.0b4.0.9").join("0123abcd");
+    std::fs::create_dir_all(account.join("Session")).unwrap();
+    std::fs::create_dir_all(account.join("Message")).unwrap();
+    std::fs::write(account.join(db::SESSION_DB), b"").unwrap();
+    for name in ["msg_10.db", "msg_2.db", "msg_2.db-wal", "fts.db"] {
+        std::fs::write(account.join("Message").join(name), b"").unwrap();
+    }
+    assert_eq!(db::locate_account_dir_in(temp.path()), Some(account.clone()));
+    let shards: Vec<_> = db::message_dbs(&account)
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(shards, vec!["msg_2.db", "msg_10.db"]);
+}
+
+#[test]
+fn hybrid_backend_cools_down_after_repeated_failures() {
+    let now = std::time::Instant::now();
+    let mut health = BackendHealth::default();
+    assert!(!health.record_failure(now));
+    assert!(!health.record_failure(now));
+    assert!(health.record_failure(now));
+    assert!(!health.is_available(now));
+    assert!(health.is_available(now + std::time::Duration::from_secs(61)));
+
+    assert!(!health.record_failure(now));
+    health.record_success();
+    assert!(!health.record_failure(now));
+    assert!(health.is_available(now));
+}
@@ -1,23 +1,163 @@
+pub mod activity;
 pub mod types;
 pub mod windows;
 pub mod macos;
+pub mod pool;
+pub mod trace;
 
-use crate::types::{api_err, api_ok, ApiResponse};
+use crate::types::{
+    api_err, api_ok, ApiResponse, AutomationMetrics, AutomationStrategy, ChatActivityStats,
+};
 use anyhow::Result;
+use pool::AutomationPool;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::task::spawn_blocking;
 use tracing::{info, warn};
-pub use types::{ChatSummary, IncomingMessage, ListenTarget, Platform};
+pub use types::{ChatPage, ChatQuery, IncomingMessage, ListenTarget, Platform};
 
 pub trait WeChatAutomation {
     #[allow(dead_code)]
     fn platform(&self) -> Platform;
-    fn list_recent_chats(&self) -> Result<Vec<ChatSummary>>;
+    fn list_chats(&self, query: &ChatQuery) -> Result<ChatPage>;
     fn start_listening(&self, targets: Vec<ListenTarget>) -> Result<()>;
     fn stop_listening(&self) -> Result<()>;
     fn write_input(&self, chat_id: &str, text: &str) -> Result<()>;
-    fn poll_latest_message(&self) -> Result<Option<IncomingMessage>>;
+    fn submit_input(&self, _chat_id: &str) -> Result<()> {
+        Err(anyhow::anyhow!("当前平台不支持自动发送"))
+    }
+    /// Every message that arrived since the previous poll, oldest first.
+    fn poll_new_messages(&self) -> Result<Vec<IncomingMessage>>;
+    /// Database files whose writes signal new messages. When non-empty the
+    /// poller waits for file changes instead of polling on a timer.
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+    /// Cached avatar image of a chat, by id or title; `None` when the backend
+    /// has no image cache or the chat has no avatar.
+    fn chat_avatar(&self, _chat_id: &str) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+    /// Per-chat message counts and reply times of the last 30 days, read from
+    /// WeChat's own database.
+    fn chat_activity(&self, _now: u64) -> Result<Vec<ChatActivityStats>> {
+        Err(anyhow::anyhow!("当前自动化方式无法读取微信数据库"))
+    }
+}
+
+const ANCHOR_ROWS: usize = 3;
+
+/// Rows appended to a message list since `previous` was read, anchored on the
+/// last few previous rows. When the anchor is gone (chat switched or list
+/// scrolled) only a changed newest row is reported.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn rows_after(previous: &[String], current: &[String]) -> Vec<String> {
+    let anchor = &previous[previous.len().saturating_sub(ANCHOR_ROWS)..];
+    if !anchor.is_empty() {
+        if let Some(start) = current
+            .windows(anchor.len())
+            .rposition(|window| window == anchor)
+        {
+            return current[start + anchor.len()..].to_vec();
+        }
+    }
+    current
+        .last()
+        .filter(|row| previous.last() != Some(*row))
+        .cloned()
+        .into_iter()
+        .collect()
+}
+
+/// Quiet time after the last text change before a burst of events is read;
+/// WeChat raises several changes while it renders one message.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub const EVENT_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Rows remembered in event mode to tell new messages from re-rendered ones.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+const SEEN_ROWS: usize = 200;
+
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WatchMode {
+    Event,
+    Polling,
+}
+
+/// Text captured by the change handler, held until the burst settles.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+#[derive(Default)]
+pub struct TextChanges {
+    pending: Vec<String>,
+    last_change: Option<Instant>,
+}
+
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+impl TextChanges {
+    pub fn push(&mut self, text: &str, now: Instant) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        if !self.pending.iter().any(|pending| pending == text) {
+            self.pending.push(text.to_string());
+        }
+        self.last_change = Some(now);
+    }
+
+    /// Every captured text, in order, once no change arrived for
+    /// [`EVENT_DEBOUNCE`]; empty while the burst is still going.
+    pub fn take_settled(&mut self, now: Instant) -> Vec<String> {
+        match self.last_change {
+            Some(last) if now.duration_since(last) >= EVENT_DEBOUNCE => {
+                self.last_change = None;
+                std::mem::take(&mut self.pending)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Changed texts that are not among the rows seen so far, remembering them.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn fresh_rows(seen: &mut Vec<String>, changed: Vec<String>) -> Vec<String> {
+    let fresh: Vec<String> = changed.into_iter().filter(|text| !seen.contains(text)).collect();
+    seen.extend(fresh.iter().cloned());
+    let overflow = seen.len().saturating_sub(SEEN_ROWS);
+    seen.drain(..overflow);
+    fresh
+}
+
+/// Wait after selecting a chat before the window reports it as active.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub const CHAT_SWITCH_SETTLE: Duration = Duration::from_millis(200);
+
+/// Writes and sends only go to the chat they were meant for; `active` is the
+/// chat WeChat currently shows, `None` when it could not be read.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn ensure_active_chat(chat_id: &str, active: Option<&str>) -> Result<()> {
+    match active.map(str::trim) {
+        Some(active) if active == chat_id.trim() => Ok(()),
+        Some(active) => Err(anyhow::anyhow!(
+            "当前会话是「{}」而不是「{}」，已取消写入",
+            active,
+            chat_id
+        )),
+        None => Err(anyhow::anyhow!("无法确认当前会话，已取消写入「{}」", chat_id)),
+    }
+}
+
+/// Received group messages are stored as `<sender wxid>:\n<content>`; splits
+/// off the sender id when the prefix is present.
+#[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+pub fn split_group_sender(content: &str) -> Option<(&str, &str)> {
+    let (sender, rest) = content.split_once(":\n")?;
+    let is_id = !sender.is_empty()
+        && sender
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '@' | '.'));
+    is_id.then_some((sender, rest))
 }
 
 pub fn build_platform_automation() -> Option<Arc<dyn WeChatAutomation + Send + Sync>> {
@@ -39,29 +179,217 @@ pub fn build_platform_automation() -> Option<Arc<dyn WeChatAutomation + Send + S
     }
 }
 
+/// Builds the backend for one strategy; the agent runs out of process and has none.
+pub fn build_automation(
+    strategy: AutomationStrategy,
+) -> Option<Arc<dyn WeChatAutomation + Send + Sync>> {
+    match strategy {
+        AutomationStrategy::Ui => build_platform_automation(),
+        AutomationStrategy::Db => build_db_automation(),
+        AutomationStrategy::Agent => None,
+    }
+}
+
+/// On macOS the database backend is paired with AX, which still writes input.
+fn build_db_automation() -> Option<Arc<dyn WeChatAutomation + Send + Sync>> {
+    #[cfg(target_os = "windows")]
+    {
+        match windows::WindowsDb::new() {
+            Ok(db) => Some(Arc::new(db) as Arc<dyn WeChatAutomation + Send + Sync>),
+            Err(err) => {
+                warn!("微信数据库读取不可用: {}", err);
+                None
+            }
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        match macos::MacosAutomation::hybrid() {
+            Ok(hybrid) => Some(Arc::new(hybrid) as Arc<dyn WeChatAutomation + Send + Sync>),
+            Err(err) => {
+                warn!("微信数据库读取不可用: {}", err);
+                None
+            }
+        }
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+pub fn accessibility_granted() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::ax::is_accessibility_trusted()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        true
+    }
+}
+
+pub fn wechat_window_available() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        windows::UiaClient::new()
+            .and_then(|client| client.pick_wechat_window())
+            .is_ok()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        macos::AxClient::new()
+            .map(|client| client.front_window().is_some())
+            .unwrap_or(false)
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        false
+    }
+}
+
 #[derive(Clone)]
 pub struct AutomationManager {
     inner: Option<Arc<dyn WeChatAutomation + Send + Sync>>,
+    pool: Arc<AutomationPool>,
+    strategy: AutomationStrategy,
+    order: Vec<AutomationStrategy>,
 }
 
 impl AutomationManager {
     pub fn new(inner: Option<Arc<dyn WeChatAutomation + Send + Sync>>) -> Self {
-        Self { inner }
+        let strategy = if inner.is_some() {
+            AutomationStrategy::Ui
+        } else {
+            AutomationStrategy::Agent
+        };
+        Self {
+            inner,
+            pool: Arc::new(AutomationPool::new(1)),
+            strategy,
+            order: Vec::new(),
+        }
+    }
+
+    /// Walks the configured fallback order and keeps the first strategy whose
+    /// backend starts. The agent is used when every configured backend fails.
+    pub fn select(order: &[AutomationStrategy]) -> Self {
+        let mut manager = Self::new(None);
+        manager.order = order.to_vec();
+        for strategy in order {
+            if *strategy == AutomationStrategy::Agent {
+                break;
+            }
+            if let Some(inner) = build_automation(*strategy) {
+                manager.inner = Some(inner);
+                manager.strategy = *strategy;
+                break;
+            }
+            warn!("自动化方式 {:?} 不可用，尝试下一种", strategy);
+        }
+        info!("当前自动化方式: {:?}", manager.strategy);
+        manager
     }
 
     pub fn is_ready(&self) -> bool {
         self.inner.is_some()
     }
 
-    pub async fn list_recent_chats(&self) -> ApiResponse<Vec<ChatSummary>> {
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        self.inner
+            .as_ref()
+            .map(|automation| automation.watch_paths())
+            .unwrap_or_default()
+    }
+
+    pub fn strategy(&self) -> AutomationStrategy {
+        self.strategy
+    }
+
+    pub fn order(&self) -> &[AutomationStrategy] {
+        &self.order
+    }
+
+    /// Whether the agent may take over when this backend fails an operation.
+    pub fn falls_back_to_agent(&self) -> bool {
+        self.strategy != AutomationStrategy::Agent
+            && self.order.contains(&AutomationStrategy::Agent)
+    }
+
+    /// Listing chats through UI automation scrolls WeChat's session list in
+    /// front of the user, so it is only redone when they ask for it.
+    pub fn refreshes_chats_in_background(&self) -> bool {
+        self.strategy != AutomationStrategy::Ui
+    }
+
+    pub fn configure(&self, concurrency: u32) {
+        self.pool.configure(concurrency);
+    }
+
+    pub fn metrics(&self) -> AutomationMetrics {
+        self.pool.metrics()
+    }
+
+    async fn spawn<T, F>(&self, task: F) -> Result<Result<T>, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let permit = self.pool.acquire().await?;
+        spawn_blocking(move || {
+            let _permit = permit;
+            task()
+        })
+        .await
+        .map_err(|err| format!("Automation task failed: {}", err))
+    }
+
+    pub async fn list_chats(&self, query: ChatQuery) -> ApiResponse<ChatPage> {
         let Some(automation) = self.inner.as_ref() else {
             return api_err("Automation not ready");
         };
         let automation = Arc::clone(automation);
-        match spawn_blocking(move || automation.list_recent_chats()).await {
-            Ok(Ok(chats)) => api_ok(chats),
+        let list_chats = move || {
+            let step = trace::step("list_chats", "session_list");
+            let result = automation.list_chats(&query);
+            step.finish("automation", &result);
+            result
+        };
+        match self.spawn(list_chats).await {
+            Ok(Ok(page)) => api_ok(page),
+            Ok(Err(err)) => api_err(err.to_string()),
+            Err(err) => api_err(err),
+        }
+    }
+
+    pub async fn chat_avatar(&self, chat_id: String) -> ApiResponse<Option<Vec<u8>>> {
+        let Some(automation) = self.inner.as_ref() else {
+            return api_ok(None);
+        };
+        let automation = Arc::clone(automation);
+        match self.spawn(move || automation.chat_avatar(&chat_id)).await {
+            Ok(Ok(avatar)) => api_ok(avatar),
+            Ok(Err(err)) => api_err(err.to_string()),
+            Err(err) => api_err(err),
+        }
+    }
+
+    /// Chat activity from WeChat's database. Backends that cannot read it
+    /// hand over to a database reader opened just for this call.
+    pub async fn chat_activity(&self, now: u64) -> ApiResponse<Vec<ChatActivityStats>> {
+        let active = self.inner.clone();
+        let read = move || {
+            if let Some(stats) = active.and_then(|automation| automation.chat_activity(now).ok()) {
+                return Ok(stats);
+            }
+            build_db_automation()
+                .ok_or_else(|| anyhow::anyhow!("微信数据库不可用，无法统计会话活跃度"))?
+                .chat_activity(now)
+        };
+        match self.spawn(read).await {
+            Ok(Ok(stats)) => api_ok(stats),
             Ok(Err(err)) => api_err(err.to_string()),
-            Err(err) => api_err(format!("Automation task failed: {}", err)),
+            Err(err) => api_err(err),
         }
     }
 
@@ -76,7 +404,13 @@ impl AutomationManager {
             timeout.as_millis()
         );
         let automation = Arc::clone(automation);
-        match tokio::time::timeout(timeout, spawn_blocking(move || automation.start_listening(targets))).await {
+        let start = move || {
+            let step = trace::step("start_listening", format!("targets={}", targets.len()));
+            let result = automation.start_listening(targets);
+            step.finish("automation", &result);
+            result
+        };
+        match tokio::time::timeout(timeout, self.spawn(start)).await {
             Ok(Ok(Ok(()))) => {
                 info!("本地自动化监听启动成功");
                 api_ok(())
@@ -87,7 +421,7 @@ impl AutomationManager {
             }
             Ok(Err(err)) => {
                 warn!("本地自动化监听任务失败: {}", err);
-                api_err(format!("Automation task failed: {}", err))
+                api_err(err)
             }
             Err(_) => {
                 warn!("本地自动化监听启动超时");
@@ -101,10 +435,16 @@ impl AutomationManager {
             return api_err("Automation not ready");
         };
         let automation = Arc::clone(automation);
-        match spawn_blocking(move || automation.stop_listening()).await {
+        let stop = move || {
+            let step = trace::step("stop_listening", "watcher");
+            let result = automation.stop_listening();
+            step.finish("automation", &result);
+            result
+        };
+        match self.spawn(stop).await {
             Ok(Ok(())) => api_ok(()),
             Ok(Err(err)) => api_err(err.to_string()),
-            Err(err) => api_err(format!("Automation task failed: {}", err)),
+            Err(err) => api_err(err),
         }
     }
 
@@ -113,22 +453,56 @@ impl AutomationManager {
             return api_err("Automation not ready");
         };
         let automation = Arc::clone(automation);
-        match spawn_blocking(move || automation.write_input(&chat_id, &text)).await {
+        let write = move || {
+            let step = trace::step("write_input", chat_id.as_str());
+            let result = automation.write_input(&chat_id, &text);
+            step.finish("automation", &result);
+            result
+        };
+        match self.spawn(write).await {
             Ok(Ok(())) => api_ok(()),
             Ok(Err(err)) => api_err(err.to_string()),
-            Err(err) => api_err(format!("Automation task failed: {}", err)),
+            Err(err) => api_err(err),
         }
     }
 
-    pub async fn poll_latest_message(&self) -> ApiResponse<Option<IncomingMessage>> {
+    pub async fn send_input(&self, chat_id: String, text: String) -> ApiResponse<()> {
         let Some(automation) = self.inner.as_ref() else {
             return api_err("Automation not ready");
         };
         let automation = Arc::clone(automation);
-        match spawn_blocking(move || automation.poll_latest_message()).await {
-            Ok(Ok(message)) => api_ok(message),
+        let send = move || {
+            let step = trace::step("send_input", chat_id.as_str());
+            let result = automation
+                .write_input(&chat_id, &text)
+                .and_then(|()| automation.submit_input(&chat_id));
+            step.finish("automation", &result);
+            result
+        };
+        match self.spawn(send).await {
+            Ok(Ok(())) => api_ok(()),
+            Ok(Err(err)) => api_err(err.to_string()),
+            Err(err) => api_err(err),
+        }
+    }
+
+    pub async fn poll_new_messages(&self) -> ApiResponse<Vec<IncomingMessage>> {
+        let Some(automation) = self.inner.as_ref() else {
+            return api_err("Automation not ready");
+        };
+        let automation = Arc::clone(automation);
+        let poll = move || {
+            let step = trace::step("poll_new_messages", "message_list");
+            let result = automation.poll_new_messages();
+            if !result.as_ref().is_ok_and(Vec::is_empty) {
+                step.finish("automation", &result);
+            }
+            result
+        };
+        match self.spawn(poll).await {
+            Ok(Ok(messages)) => api_ok(messages),
             Ok(Err(err)) => api_err(err.to_string()),
-            Err(err) => api_err(format!("Automation task failed: {}", err)),
+            Err(err) => api_err(err),
         }
     }
 }
@@ -0,0 +1,156 @@
+use crate::types::AutomationMetrics;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+pub const BUSY_CODE: &str = "BUSY";
+pub const QUEUE_LIMIT: u32 = 8;
+
+pub struct AutomationPool {
+    state: Mutex<PoolState>,
+}
+
+struct PoolState {
+    concurrency: u32,
+    semaphore: Arc<Semaphore>,
+    running: u32,
+    waiting: u32,
+    completed: u64,
+    rejected: u64,
+    total_wait_ms: u64,
+    max_wait_ms: u64,
+    last_wait_ms: u64,
+}
+
+pub struct AutomationPermit {
+    pool: Arc<AutomationPool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl AutomationPool {
+    pub fn new(concurrency: u32) -> Self {
+        let concurrency = concurrency.max(1);
+        Self {
+            state: Mutex::new(PoolState {
+                concurrency,
+                semaphore: Arc::new(Semaphore::new(concurrency as usize)),
+                running: 0,
+                waiting: 0,
+                completed: 0,
+                rejected: 0,
+                total_wait_ms: 0,
+                max_wait_ms: 0,
+                last_wait_ms: 0,
+            }),
+        }
+    }
+
+    pub fn configure(&self, concurrency: u32) {
+        let concurrency = concurrency.max(1);
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if state.concurrency != concurrency {
+            state.concurrency = concurrency;
+            state.semaphore = Arc::new(Semaphore::new(concurrency as usize));
+        }
+    }
+
+    pub async fn acquire(self: &Arc<Self>) -> Result<AutomationPermit, String> {
+        let semaphore = {
+            let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+            if state.semaphore.available_permits() == 0 && state.waiting >= QUEUE_LIMIT {
+                state.rejected += 1;
+                return Err(format!(
+                    "{}: 自动化任务排队已满（{} 个），请稍后重试",
+                    BUSY_CODE, QUEUE_LIMIT
+                ));
+            }
+            state.waiting += 1;
+            state.semaphore.clone()
+        };
+        let started = Instant::now();
+        let permit = semaphore.acquire_owned().await;
+        let waited_ms = started.elapsed().as_millis() as u64;
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.waiting -= 1;
+        let permit = permit.map_err(|err| format!("自动化任务队列已关闭: {}", err))?;
+        state.running += 1;
+        state.total_wait_ms += waited_ms;
+        state.max_wait_ms = state.max_wait_ms.max(waited_ms);
+        state.last_wait_ms = waited_ms;
+        Ok(AutomationPermit {
+            pool: self.clone(),
+            _permit: permit,
+        })
+    }
+
+    pub fn metrics(&self) -> AutomationMetrics {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let started = state.completed + state.running as u64;
+        AutomationMetrics {
+            concurrency: state.concurrency,
+            queue_limit: QUEUE_LIMIT,
+            running: state.running,
+            waiting: state.waiting,
+            completed: state.completed,
+            rejected: state.rejected,
+            avg_wait_ms: state.total_wait_ms.checked_div(started).unwrap_or(0),
+            max_wait_ms: state.max_wait_ms,
+            last_wait_ms: state.last_wait_ms,
+        }
+    }
+}
+
+impl Drop for AutomationPermit {
+    fn drop(&mut self) {
+        let mut state = self
+            .pool
+            .state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        state.running = state.running.saturating_sub(1);
+        state.completed += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn queues_up_to_limit_then_rejects_busy() {
+        let pool = Arc::new(AutomationPool::new(1));
+        let first = pool.acquire().await.unwrap();
+        let mut waiters = Vec::new();
+        for _ in 0..QUEUE_LIMIT {
+            let pool = pool.clone();
+            waiters.push(tokio::spawn(async move { pool.acquire().await.map(drop) }));
+        }
+        while pool.metrics().waiting < QUEUE_LIMIT {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let err = pool.acquire().await.err().unwrap();
+        assert!(err.starts_with(BUSY_CODE));
+        let metrics = pool.metrics();
+        assert_eq!((metrics.running, metrics.rejected), (1, 1));
+
+        drop(first);
+        for waiter in waiters {
+            assert!(waiter.await.unwrap().is_ok());
+        }
+        let metrics = pool.metrics();
+        assert_eq!(metrics.completed, 1 + QUEUE_LIMIT as u64);
+        assert_eq!((metrics.running, metrics.waiting), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn configure_changes_width() {
+        let pool = Arc::new(AutomationPool::new(1));
+        pool.configure(2);
+        let _first = pool.acquire().await.unwrap();
+        let _second = pool.acquire().await.unwrap();
+        let metrics = pool.metrics();
+        assert_eq!((metrics.concurrency, metrics.running), (2, 2));
+    }
+}
@@ -1,8 +1,23 @@
-use super::{AutomationManager, WeChatAutomation};
+use super::{
+    ensure_active_chat, fresh_rows, rows_after, split_group_sender, AutomationManager,
+    TextChanges, WeChatAutomation, EVENT_DEBOUNCE,
+};
 use crate::types::ChatSummary;
-use crate::ui_automation::IncomingMessage;
+use crate::ui_automation::{ChatPage, ChatQuery, IncomingMessage};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+fn chat(chat_id: &str, chat_title: &str) -> ChatSummary {
+    ChatSummary {
+        chat_id: chat_id.to_string(),
+        chat_title: chat_title.to_string(),
+        kind: crate::types::ChatKind::Direct,
+        account_id: String::new(),
+        last_message: String::new(),
+        last_active_at: 0,
+        unread_count: 0,
+    }
+}
 
 struct MockAutomation;
 
@@ -11,12 +26,8 @@ impl WeChatAutomation for MockAutomation {
         super::Platform::Unknown
     }
 
-    fn list_recent_chats(&self) -> anyhow::Result<Vec<ChatSummary>> {
-        Ok(vec![ChatSummary {
-            chat_id: "c1".to_string(),
-            chat_title: "Chat 1".to_string(),
-            kind: crate::types::ChatKind::Direct,
-        }])
+    fn list_chats(&self, query: &ChatQuery) -> anyhow::Result<ChatPage> {
+        Ok(query.page(vec![chat("c1", "Chat 1")]))
     }
 
     fn start_listening(&self, _targets: Vec<super::ListenTarget>) -> anyhow::Result<()> {
@@ -31,8 +42,8 @@ impl WeChatAutomation for MockAutomation {
         Ok(())
     }
 
-    fn poll_latest_message(&self) -> anyhow::Result<Option<IncomingMessage>> {
-        Ok(None)
+    fn poll_new_messages(&self) -> anyhow::Result<Vec<IncomingMessage>> {
+        Ok(Vec::new())
     }
 }
 
@@ -45,8 +56,8 @@ impl WeChatAutomation for SlowAutomation {
         super::Platform::Unknown
     }
 
-    fn list_recent_chats(&self) -> anyhow::Result<Vec<ChatSummary>> {
-        Ok(Vec::new())
+    fn list_chats(&self, _query: &ChatQuery) -> anyhow::Result<ChatPage> {
+        Ok(ChatPage::default())
     }
 
     fn start_listening(&self, _targets: Vec<super::ListenTarget>) -> anyhow::Result<()> {
@@ -62,25 +73,25 @@ impl WeChatAutomation for SlowAutomation {
         Ok(())
     }
 
-    fn poll_latest_message(&self) -> anyhow::Result<Option<IncomingMessage>> {
-        Ok(None)
+    fn poll_new_messages(&self) -> anyhow::Result<Vec<IncomingMessage>> {
+        Ok(Vec::new())
     }
 }
 
 #[tokio::test]
 async fn automation_manager_rejects_when_not_ready() {
     let mgr = AutomationManager::new(None);
-    let res = mgr.list_recent_chats().await;
+    let res = mgr.list_chats(ChatQuery::new(0, 50, "")).await;
     assert!(!res.success);
 }
 
 #[tokio::test]
 async fn automation_manager_accepts_when_ready() {
     let mgr = AutomationManager::new(Some(Arc::new(MockAutomation)));
-    let res = mgr.list_recent_chats().await;
+    let res = mgr.list_chats(ChatQuery::new(0, 50, "")).await;
     assert!(res.success);
-    let chats = res.data.unwrap_or_default();
-    assert_eq!(chats.len(), 1);
+    let page = res.data.unwrap_or_default();
+    assert_eq!(page.chats.len(), 1);
 }
 
 #[tokio::test]
@@ -94,3 +105,103 @@ async fn automation_manager_times_out_on_slow_start() {
     assert!(res.message.contains("超时"));
     std::env::remove_var("WEREPLY_AUTOMATION_START_TIMEOUT_MS");
 }
+
+#[test]
+fn strategy_selection_falls_through_to_agent() {
+    use crate::types::AutomationStrategy;
+
+    let manager = AutomationManager::select(&[AutomationStrategy::Agent, AutomationStrategy::Ui]);
+    assert_eq!(manager.strategy(), AutomationStrategy::Agent);
+    assert!(!manager.is_ready());
+    assert!(!manager.falls_back_to_agent());
+    assert!(manager.refreshes_chats_in_background());
+
+    // No database key is stored on the test host, so the agent takes over.
+    let order = [AutomationStrategy::Db];
+    let manager = AutomationManager::select(&order);
+    assert_eq!(manager.strategy(), AutomationStrategy::Agent);
+    assert_eq!(manager.order(), &order);
+
+    let manager = AutomationManager::new(Some(Arc::new(MockAutomation)));
+    assert_eq!(manager.strategy(), AutomationStrategy::Ui);
+    assert!(!manager.falls_back_to_agent());
+    assert!(!manager.refreshes_chats_in_background());
+}
+
+#[test]
+fn rows_after_reports_every_appended_row() {
+    let rows = |items: &[&str]| items.iter().map(|item| item.to_string()).collect::<Vec<_>>();
+    let previous = rows(&["a", "b", "好"]);
+    assert_eq!(
+        rows_after(&previous, &rows(&["a", "b", "好", "c", "好"])),
+        rows(&["c", "好"])
+    );
+    assert!(rows_after(&previous, &previous).is_empty());
+    assert_eq!(rows_after(&[], &rows(&["a", "b"])), rows(&["b"]));
+    assert_eq!(rows_after(&previous, &rows(&["x", "y"])), rows(&["y"]));
+    assert!(rows_after(&previous, &rows(&["x", "好"])).is_empty());
+}
+
+#[test]
+fn text_changes_settle_before_reporting_fresh_rows() {
+    let start = Instant::now();
+    let mut changes = TextChanges::default();
+    changes.push(" 你好 ", start);
+    changes.push("", start);
+    changes.push("你好", start + EVENT_DEBOUNCE / 2);
+    changes.push("在吗", start + EVENT_DEBOUNCE / 2);
+    assert!(changes.take_settled(start + EVENT_DEBOUNCE).is_empty());
+    let settled = changes.take_settled(start + EVENT_DEBOUNCE * 2);
+    assert_eq!(settled, vec!["你好", "在吗"]);
+    assert!(changes.take_settled(start + EVENT_DEBOUNCE * 3).is_empty());
+
+    let mut seen = vec!["你好".to_string()];
+    let fresh = fresh_rows(&mut seen, settled);
+    assert_eq!(fresh, vec!["在吗"]);
+    assert_eq!(seen, vec!["你好", "在吗"]);
+    let mut many: Vec<String> = (0..250).map(|index| index.to_string()).collect();
+    assert_eq!(fresh_rows(&mut seen, many.clone()).len(), 250);
+    assert_eq!(seen.len(), 200);
+    assert_eq!(seen.last(), many.pop().as_ref());
+}
+
+#[test]
+fn writes_only_go_to_the_active_target_chat() {
+    assert!(ensure_active_chat("张三", Some(" 张三 ")).is_ok());
+    assert!(ensure_active_chat("张三", Some("李四")).is_err());
+    assert!(ensure_active_chat("张三", None).is_err());
+}
+
+#[test]
+fn split_group_sender_reads_wxid_prefix() {
+    assert_eq!(split_group_sender("wxid_abc123:\n在吗"), Some(("wxid_abc123", "在吗")));
+    assert_eq!(
+        split_group_sender("zhang-san:\n<msg><img /></msg>"),
+        Some(("zhang-san", "<msg><img /></msg>"))
+    );
+    assert_eq!(split_group_sender("在吗"), None);
+    assert_eq!(split_group_sender("注意:\n明天开会"), None);
+    assert_eq!(split_group_sender(":\n在吗"), None);
+}
+
+#[test]
+fn chat_query_filters_and_pages() {
+    let chats = vec![
+        chat("wxid_a", "Alice"),
+        chat("123@chatroom", "项目群"),
+        chat("wxid_b", "Bob"),
+        chat("wxid_c", "alina"),
+    ];
+    let titles = |page: ChatPage| {
+        let titles: Vec<_> = page.chats.into_iter().map(|chat| chat.chat_title).collect();
+        (titles, page.has_more)
+    };
+    let first = ChatQuery::new(0, 2, "");
+    assert_eq!(titles(first.page(chats.clone())), (vec!["Alice".into(), "项目群".into()], true));
+    let last = ChatQuery::new(2, 2, "");
+    assert_eq!(titles(last.page(chats.clone())), (vec!["Bob".into(), "alina".into()], false));
+    let search = ChatQuery::new(0, 5, " ALI ");
+    assert_eq!(titles(search.page(chats.clone())), (vec!["Alice".into(), "alina".into()], false));
+    assert_eq!(titles(ChatQuery::new(0, 5, "chatroom").page(chats)).0, vec!["项目群"]);
+    assert_eq!(ChatQuery::new(0, 5, "50%_off").like_pattern(), "%50\\%\\_off%");
+}
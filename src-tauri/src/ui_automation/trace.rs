@@ -0,0 +1,158 @@
+use crate::types::AutomationTraceEntry;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const MAX_TRACE_ENTRIES: usize = 5_000;
+
+struct TraceBuffer {
+    enabled: bool,
+    window_ms: u64,
+    entries: VecDeque<AutomationTraceEntry>,
+}
+
+static TRACE: OnceLock<Mutex<TraceBuffer>> = OnceLock::new();
+
+fn buffer() -> &'static Mutex<TraceBuffer> {
+    TRACE.get_or_init(|| {
+        Mutex::new(TraceBuffer {
+            enabled: false,
+            window_ms: 0,
+            entries: VecDeque::new(),
+        })
+    })
+}
+
+pub fn configure(enabled: bool, window_minutes: u32) {
+    let mut guard = buffer().lock().unwrap_or_else(|err| err.into_inner());
+    guard.enabled = enabled;
+    guard.window_ms = window_minutes as u64 * 60_000;
+    if !enabled {
+        guard.entries.clear();
+    }
+}
+
+pub fn is_enabled() -> bool {
+    buffer()
+        .lock()
+        .map(|guard| guard.enabled)
+        .unwrap_or(false)
+}
+
+pub struct TraceStep {
+    action: &'static str,
+    element: String,
+    started: Instant,
+}
+
+pub fn step(action: &'static str, element: impl Into<String>) -> TraceStep {
+    TraceStep {
+        action,
+        element: element.into(),
+        started: Instant::now(),
+    }
+}
+
+impl TraceStep {
+    pub fn finish<T, E: std::fmt::Display>(self, pattern: &str, result: &Result<T, E>) {
+        if !is_enabled() {
+            return;
+        }
+        let entry = AutomationTraceEntry {
+            at_ms: now_ms(),
+            action: self.action.to_string(),
+            element: self.element,
+            pattern: pattern.to_string(),
+            ok: result.is_ok(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+        };
+        push(entry);
+    }
+}
+
+fn push(entry: AutomationTraceEntry) {
+    let mut guard = buffer().lock().unwrap_or_else(|err| err.into_inner());
+    if !guard.enabled {
+        return;
+    }
+    let cutoff = entry.at_ms.saturating_sub(guard.window_ms);
+    guard.entries.push_back(entry);
+    prune(&mut guard.entries, cutoff);
+}
+
+fn prune(entries: &mut VecDeque<AutomationTraceEntry>, cutoff: u64) {
+    while entries
+        .front()
+        .is_some_and(|entry| entry.at_ms < cutoff || entries.len() > MAX_TRACE_ENTRIES)
+    {
+        entries.pop_front();
+    }
+}
+
+pub fn snapshot() -> Vec<AutomationTraceEntry> {
+    let mut guard = buffer().lock().unwrap_or_else(|err| err.into_inner());
+    let cutoff = now_ms().saturating_sub(guard.window_ms);
+    prune(&mut guard.entries, cutoff);
+    guard.entries.iter().cloned().collect()
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at_ms: u64) -> AutomationTraceEntry {
+        AutomationTraceEntry {
+            at_ms,
+            action: "write_input".to_string(),
+            element: "张三".to_string(),
+            pattern: "value_pattern".to_string(),
+            ok: true,
+            error: None,
+            duration_ms: 12,
+        }
+    }
+
+    #[test]
+    fn prunes_entries_outside_window_and_over_capacity() {
+        let mut entries: VecDeque<_> = [entry(1), entry(5), entry(9)].into_iter().collect();
+        prune(&mut entries, 5);
+        let kept: Vec<_> = entries.iter().map(|entry| entry.at_ms).collect();
+        assert_eq!(kept, vec![5, 9]);
+
+        let mut entries: VecDeque<_> = (0..MAX_TRACE_ENTRIES as u64 + 3).map(entry).collect();
+        prune(&mut entries, 0);
+        assert_eq!(entries.len(), MAX_TRACE_ENTRIES);
+        assert_eq!(entries.front().unwrap().at_ms, 3);
+    }
+
+    #[test]
+    fn records_steps_only_while_enabled() {
+        let traced = || -> Vec<AutomationTraceEntry> {
+            snapshot()
+                .into_iter()
+                .filter(|entry| entry.element == "trace-test")
+                .collect()
+        };
+        configure(false, 10);
+        step("write_input", "trace-test").finish("value_pattern", &Ok::<(), String>(()));
+        assert!(traced().is_empty());
+
+        configure(true, 10);
+        step("write_input", "trace-test").finish("clipboard", &Err::<(), _>("Input box not found"));
+        let entries = traced();
+        assert_eq!(entries.len(), 1);
+        assert!(!entries[0].ok);
+        assert_eq!(entries[0].pattern, "clipboard");
+        assert_eq!(entries[0].error.as_deref(), Some("Input box not found"));
+        configure(false, 10);
+        assert!(traced().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-pub use crate::types::{ChatSummary, ListenTarget, Platform};
+pub use crate::types::{ChatSummary, ListenTarget, MessageContentType, Platform};
 
 #[derive(Clone, Debug)]
 pub struct IncomingMessage {
@@ -6,4 +6,84 @@ pub struct IncomingMessage {
     pub text: String,
     pub timestamp: u64,
     pub msg_id: Option<String>,
+    pub content_type: MessageContentType,
+    /// Who sent the message in a group chat; empty when unknown.
+    pub sender_name: String,
+    pub is_group: bool,
+    /// Sent by the user from this account rather than received.
+    pub is_self: bool,
+}
+
+/// One page of the chat list, filtered by title or id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatQuery {
+    pub offset: usize,
+    pub limit: usize,
+    /// Matched case-insensitively against titles and ids; empty matches all.
+    pub query: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChatPage {
+    pub chats: Vec<ChatSummary>,
+    pub has_more: bool,
+}
+
+impl ChatQuery {
+    pub fn new(offset: usize, limit: usize, query: &str) -> Self {
+        Self {
+            offset,
+            limit,
+            query: query.trim().to_lowercase(),
+        }
+    }
+
+    pub fn matches(&self, chat: &ChatSummary) -> bool {
+        self.query.is_empty()
+            || chat.chat_title.to_lowercase().contains(&self.query)
+            || chat.chat_id.to_lowercase().contains(&self.query)
+    }
+
+    /// Matches to collect before the page can be cut; the extra one tells
+    /// whether more chats follow.
+    #[cfg_attr(not(any(test, target_os = "windows", target_os = "macos")), allow(dead_code))]
+    pub fn wanted(&self) -> usize {
+        self.offset + self.limit + 1
+    }
+
+    /// Pages `chats` in list order. Stops pulling once the page is full, so
+    /// lazy iterators only resolve what is shown.
+    pub fn page(&self, chats: impl IntoIterator<Item = ChatSummary>) -> ChatPage {
+        let matches = chats
+            .into_iter()
+            .filter(|chat| self.matches(chat))
+            .skip(self.offset)
+            .take(self.limit + 1)
+            .collect();
+        self.cut(matches)
+    }
+
+    /// Cuts matches that already start at `offset` (e.g. from `LIMIT .. OFFSET`).
+    pub fn cut(&self, mut matches: Vec<ChatSummary>) -> ChatPage {
+        let has_more = matches.len() > self.limit;
+        matches.truncate(self.limit);
+        ChatPage {
+            chats: matches,
+            has_more,
+        }
+    }
+
+    /// `LIKE` pattern for the query, escaped with `\`.
+    #[cfg_attr(not(any(test, target_os = "windows")), allow(dead_code))]
+    pub fn like_pattern(&self) -> String {
+        let mut pattern = String::from("%");
+        for ch in self.query.chars() {
+            if matches!(ch, '%' | '_' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(ch);
+        }
+        pattern.push('%');
+        pattern
+    }
 }
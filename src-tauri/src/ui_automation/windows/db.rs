@@ -0,0 +1,396 @@
+use crate::chat_kind;
+use crate::content_type;
+use crate::types::{ChatActivityStats, ChatSummary};
+use crate::ui_automation::{activity, ChatPage, ChatQuery};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+
+pub const SESSION_DB: &str = "MicroMsg.db";
+const SKIPPED_ACCOUNT_DIRS: [&str; 2] = ["All Users", "Applet"];
+const SYSTEM_MESSAGE_TYPE: i64 = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbMessage {
+    pub local_id: i64,
+    pub server_id: i64,
+    pub msg_type: i64,
+    pub create_time: u64,
+    pub talker: String,
+    pub content: String,
+    pub is_self: bool,
+}
+
+/// Picks the account `Msg` directory under `WeChat Files`, preferring the
+/// account whose `MicroMsg.db` was written most recently.
+pub fn locate_msg_dir_in(wechat_files: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(wechat_files)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            !SKIPPED_ACCOUNT_DIRS.contains(&name.as_str())
+        })
+        .filter_map(|entry| {
+            let msg_dir = entry.path().join("Msg");
+            let modified = std::fs::metadata(msg_dir.join(SESSION_DB))
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            Some((modified, msg_dir))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, msg_dir)| msg_dir)
+}
+
+/// WeChat only appends to the highest `Multi/MSG{n}.db` shard.
+pub fn latest_msg_db(msg_dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(msg_dir.join("Multi"))
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let index = name
+                .strip_prefix("MSG")?
+                .strip_suffix(".db")?
+                .parse::<u32>()
+                .ok()?;
+            Some((index, entry.path()))
+        })
+        .max_by_key(|(index, _)| *index)
+        .map(|(_, path)| path)
+}
+
+/// One page of sessions in WeChat's list order, filtered by title or id.
+pub fn query_sessions(conn: &Connection, query: &ChatQuery) -> Result<ChatPage> {
+    let mut stmt = conn.prepare(
+        "SELECT user_name, title, msg_type, content, last_time, unread FROM (
+             SELECT s.strUsrName AS user_name,
+                    COALESCE(NULLIF(c.Remark, ''), NULLIF(c.NickName, ''),
+                             NULLIF(s.strNickName, ''), s.strUsrName) AS title,
+                    s.nMsgType AS msg_type, s.strContent AS content, s.nTime AS last_time,
+                    s.nUnReadCount AS unread, s.nOrder AS sort_order
+             FROM Session s LEFT JOIN Contact c ON c.UserName = s.strUsrName
+             WHERE s.strUsrName != '' AND s.strUsrName NOT LIKE '@%'
+         )
+         WHERE title LIKE ?1 ESCAPE '\\' OR user_name LIKE ?1 ESCAPE '\\'
+         ORDER BY sort_order DESC LIMIT ?2 OFFSET ?3",
+    )?;
+    let params = params![
+        query.like_pattern(),
+        (query.limit + 1) as i64,
+        query.offset as i64
+    ];
+    let rows = stmt.query_map(params, |row| {
+        let chat_id: String = row.get(0)?;
+        let msg_type = row.get::<_, Option<i64>>(2)?.unwrap_or_default();
+        let content = row.get::<_, Option<String>>(3)?.unwrap_or_default();
+        Ok(ChatSummary {
+            kind: chat_kind::from_user_name(&chat_id),
+            chat_id,
+            chat_title: row.get(1)?,
+            account_id: String::new(),
+            last_message: content_type::session_preview(msg_type, &content),
+            last_active_at: row.get::<_, Option<i64>>(4)?.unwrap_or_default().max(0) as u64,
+            unread_count: row.get::<_, Option<i64>>(5)?.unwrap_or_default().max(0) as u32,
+        })
+    })?;
+    let matches = rows
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取会话列表失败")?;
+    Ok(query.cut(matches))
+}
+
+pub fn query_display_name(conn: &Connection, user_name: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT COALESCE(NULLIF(Remark, ''), NULLIF(NickName, '')) FROM Contact WHERE UserName = ?1",
+        params![user_name],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .optional()
+    .map(Option::flatten)
+    .context("读取联系人失败")
+}
+
+/// Chat shown under `title`, matched against remarks before nicknames.
+pub fn query_user_name(conn: &Connection, title: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT UserName FROM Contact WHERE ?1 IN (Remark, NickName)
+         ORDER BY Remark = ?1 DESC LIMIT 1",
+        params![title],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("读取联系人失败")
+}
+
+/// Avatar thumbnail WeChat caches in `Misc.db` for a contact or group.
+pub fn query_head_image(misc: &Connection, user_name: &str) -> Result<Option<Vec<u8>>> {
+    misc.query_row(
+        "SELECT smallHeadBuf FROM ContactHeadImg1 WHERE usrName = ?1
+         ORDER BY createTime DESC LIMIT 1",
+        params![user_name],
+        |row| row.get::<_, Option<Vec<u8>>>(0),
+    )
+    .optional()
+    .map(|image| image.flatten().filter(|image| !image.is_empty()))
+    .context("读取头像缓存失败")
+}
+
+pub fn query_max_local_id(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(localId), 0) FROM MSG", [], |row| row.get(0))
+        .context("读取消息表失败")
+}
+
+/// Messages after `after_local_id` in either direction, oldest first; system
+/// notices are skipped.
+pub fn query_messages_after(
+    conn: &Connection,
+    after_local_id: i64,
+    limit: usize,
+) -> Result<Vec<DbMessage>> {
+    let mut stmt = conn.prepare(
+        "SELECT localId, MsgSvrID, Type, CreateTime, StrTalker, StrContent, IsSender FROM MSG
+         WHERE localId > ?1 AND Type != ?2
+         ORDER BY localId LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(params![after_local_id, SYSTEM_MESSAGE_TYPE, limit as i64], |row| {
+        Ok(DbMessage {
+            local_id: row.get(0)?,
+            server_id: row.get(1)?,
+            msg_type: row.get(2)?,
+            create_time: row.get::<_, i64>(3)?.max(0) as u64,
+            talker: row.get(4)?,
+            content: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+            is_self: row.get::<_, i64>(6)? == 1,
+        })
+    })?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .context("读取消息失败")
+}
+
+/// Per-talker activity of the last 30 days, keyed by `StrTalker`; system
+/// notices are skipped.
+pub fn query_chat_activity(conn: &Connection, now: u64) -> Result<Vec<ChatActivityStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT StrTalker, CreateTime, IsSender FROM MSG
+         WHERE CreateTime >= ?1 AND Type != ?2
+         ORDER BY StrTalker, CreateTime, localId",
+    )?;
+    let since = activity::window_start(now) as i64;
+    let rows = stmt.query_map(params![since, SYSTEM_MESSAGE_TYPE], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?.max(0) as u64,
+            row.get::<_, i64>(2)? == 1,
+        ))
+    })?;
+    let mut stats = Vec::new();
+    let mut talker = String::new();
+    let mut messages = Vec::new();
+    for row in rows {
+        let (next, at, is_self) = row.context("读取消息失败")?;
+        if next != talker {
+            stats.extend(activity::summarize(std::mem::take(&mut talker), &messages, now));
+            messages.clear();
+            talker = next;
+        }
+        messages.push((at, is_self));
+    }
+    stats.extend(activity::summarize(talker, &messages, now));
+    Ok(stats)
+}
+
+#[cfg(target_os = "windows")]
+pub mod reader {
+    use super::{
+        latest_msg_db, locate_msg_dir_in, query_display_name, query_head_image,
+        query_chat_activity, query_max_local_id, query_messages_after, query_sessions,
+        query_user_name, SESSION_DB,
+    };
+    use crate::chat_kind;
+    use crate::content_type;
+    use crate::secret::ApiKeyManager;
+    use crate::sqlcipher;
+    use crate::types::{ChatActivityStats, ChatKind, ListenTarget, Platform};
+    use crate::ui_automation::{
+        activity, split_group_sender, ChatPage, ChatQuery, IncomingMessage, WeChatAutomation,
+    };
+    use anyhow::{anyhow, Result};
+    use rusqlite::Connection;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+    use tracing::info;
+
+    pub const MSG_DIR_ENV: &str = "WEREPLY_WECHAT_MSG_DIR";
+    const MISC_DB: &str = "Misc.db";
+    const POLL_LIMIT: usize = 200;
+
+    struct Cursor {
+        db_path: PathBuf,
+        local_id: i64,
+    }
+
+    pub struct WindowsDb {
+        msg_dir: PathBuf,
+        pragmas: String,
+        cursor: Mutex<Option<Cursor>>,
+        names: Mutex<HashMap<String, String>>,
+    }
+
+    impl WindowsDb {
+        pub fn new() -> Result<Self> {
+            let key = ApiKeyManager::get_wechat_db_key()?
+                .ok_or_else(|| anyhow!("未设置微信数据库密钥"))?;
+            let pragmas = sqlcipher::wechat_windows_pragmas(&key).map_err(|err| anyhow!(err))?;
+            let msg_dir = locate_msg_dir().ok_or_else(|| anyhow!("未找到微信数据目录 Msg"))?;
+            sqlcipher::remove_legacy_snapshots(
+                &std::env::var_os("LOCALAPPDATA")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(std::env::temp_dir)
+                    .join("wereply")
+                    .join("wechat-db"),
+            );
+            info!("使用微信数据库读取消息: {}", msg_dir.display());
+            Ok(Self {
+                msg_dir,
+                pragmas,
+                cursor: Mutex::new(None),
+                names: Mutex::new(HashMap::new()),
+            })
+        }
+
+        fn open(&self, path: &Path) -> Result<Connection> {
+            sqlcipher::open_readonly(path, &self.pragmas).map_err(|err| anyhow!(err))
+        }
+
+        fn open_latest_msg_db(&self) -> Result<(PathBuf, Connection)> {
+            let path = latest_msg_db(&self.msg_dir)
+                .ok_or_else(|| anyhow!("未找到微信消息数据库 MSG*.db"))?;
+            let conn = self.open(&path)?;
+            Ok((path, conn))
+        }
+
+        fn display_name(&self, user_name: &str) -> String {
+            if let Some(name) = self.names.lock().ok().and_then(|names| names.get(user_name).cloned()) {
+                return name;
+            }
+            let name = self
+                .open(&self.msg_dir.join(SESSION_DB))
+                .and_then(|conn| query_display_name(&conn, user_name))
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| user_name.to_string());
+            if let Ok(mut names) = self.names.lock() {
+                names.insert(user_name.to_string(), name.clone());
+            }
+            name
+        }
+    }
+
+    impl WeChatAutomation for WindowsDb {
+        fn platform(&self) -> Platform {
+            Platform::Windows
+        }
+
+        fn list_chats(&self, query: &ChatQuery) -> Result<ChatPage> {
+            let conn = self.open(&self.msg_dir.join(SESSION_DB))?;
+            query_sessions(&conn, query)
+        }
+
+        fn start_listening(&self, _targets: Vec<ListenTarget>) -> Result<()> {
+            let (db_path, conn) = self.open_latest_msg_db()?;
+            let local_id = query_max_local_id(&conn)?;
+            let mut guard = self.cursor.lock().map_err(|_| anyhow!("Cursor lock poisoned"))?;
+            *guard = Some(Cursor { db_path, local_id });
+            Ok(())
+        }
+
+        fn stop_listening(&self) -> Result<()> {
+            let mut guard = self.cursor.lock().map_err(|_| anyhow!("Cursor lock poisoned"))?;
+            *guard = None;
+            Ok(())
+        }
+
+        fn write_input(&self, _chat_id: &str, _text: &str) -> Result<()> {
+            Err(anyhow!("数据库模式只能读取消息，无法写入输入框"))
+        }
+
+        fn poll_new_messages(&self) -> Result<Vec<IncomingMessage>> {
+            let mut guard = self.cursor.lock().map_err(|_| anyhow!("Cursor lock poisoned"))?;
+            let Some(cursor) = guard.as_mut() else {
+                return Ok(Vec::new());
+            };
+            let (db_path, conn) = self.open_latest_msg_db()?;
+            if db_path != cursor.db_path {
+                info!("微信消息数据库已切换: {}", db_path.display());
+                *cursor = Cursor {
+                    db_path,
+                    local_id: 0,
+                };
+            }
+            let messages = query_messages_after(&conn, cursor.local_id, POLL_LIMIT)?;
+            if let Some(last) = messages.last() {
+                cursor.local_id = last.local_id;
+            }
+            drop(guard);
+            Ok(messages
+                .into_iter()
+                .map(|message| {
+                    let is_group = chat_kind::from_user_name(&message.talker) == ChatKind::Group;
+                    let (sender, content) = match split_group_sender(&message.content) {
+                        Some((sender, content)) if is_group => (Some(sender), content),
+                        _ => (None, message.content.as_str()),
+                    };
+                    let decoded = content_type::decode_wechat_message(message.msg_type, content);
+                    IncomingMessage {
+                        chat_id: self.display_name(&message.talker),
+                        content_type: decoded.content_type,
+                        text: decoded.text,
+                        timestamp: message.create_time,
+                        msg_id: Some(message.server_id.to_string()),
+                        sender_name: sender.map(|id| self.display_name(id)).unwrap_or_default(),
+                        is_group,
+                        is_self: message.is_self,
+                    }
+                })
+                .collect())
+        }
+
+        fn watch_paths(&self) -> Vec<PathBuf> {
+            latest_msg_db(&self.msg_dir).into_iter().collect()
+        }
+
+        fn chat_avatar(&self, chat_id: &str) -> Result<Option<Vec<u8>>> {
+            let misc = self.open(&self.msg_dir.join(MISC_DB))?;
+            if let Some(image) = query_head_image(&misc, chat_id)? {
+                return Ok(Some(image));
+            }
+            let session = self.open(&self.msg_dir.join(SESSION_DB))?;
+            match query_user_name(&session, chat_id)? {
+                Some(user_name) => query_head_image(&misc, &user_name),
+                None => Ok(None),
+            }
+        }
+
+        fn chat_activity(&self, now: u64) -> Result<Vec<ChatActivityStats>> {
+            let (_, conn) = self.open_latest_msg_db()?;
+            let mut stats = query_chat_activity(&conn, now)?;
+            for item in &mut stats {
+                item.chat_id = self.display_name(&item.chat_id);
+            }
+            activity::sort_by_activity(&mut stats);
+            Ok(stats)
+        }
+    }
+
+    pub fn locate_msg_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os(MSG_DIR_ENV).map(PathBuf::from) {
+            if dir.join(SESSION_DB).is_file() {
+                return Some(dir);
+            }
+        }
+        let profile = std::env::var_os("USERPROFILE").map(PathBuf::from)?;
+        locate_msg_dir_in(&profile.join("Documents").join("WeChat Files"))
+    }
+}
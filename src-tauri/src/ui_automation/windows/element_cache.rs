@@ -0,0 +1,139 @@
+#[cfg(any(test, target_os = "windows"))]
+use anyhow::Result;
+
+/// Remembers one located element so repeated passes skip the tree walk; the
+/// element is located again once `is_alive` reports it gone.
+#[cfg(any(test, target_os = "windows"))]
+pub struct ElementMemo<T> {
+    element: Option<T>,
+}
+
+#[cfg(any(test, target_os = "windows"))]
+impl<T> Default for ElementMemo<T> {
+    fn default() -> Self {
+        Self { element: None }
+    }
+}
+
+#[cfg(any(test, target_os = "windows"))]
+impl<T: Clone> ElementMemo<T> {
+    pub fn get_or_locate(
+        &mut self,
+        is_alive: impl Fn(&T) -> bool,
+        locate: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if let Some(element) = self.element.as_ref().filter(|element| is_alive(element)) {
+            return Ok(element.clone());
+        }
+        self.element = None;
+        let element = locate()?;
+        self.element = Some(element.clone());
+        Ok(element)
+    }
+}
+
+/// Bulk reads through a UIA `CacheRequest`: one cross-process call fetches
+/// every matching element together with the properties read afterwards,
+/// instead of one call per element and property.
+#[cfg(target_os = "windows")]
+pub mod uia {
+    use anyhow::Result;
+    use uiautomation::core::{UICacheRequest, UICondition};
+    use uiautomation::types::{ControlType, Rect, UIProperty};
+    use uiautomation::variants::Variant;
+    use uiautomation::{TreeScope, UIAutomation, UIElement};
+
+    /// Properties the locator and list readers look at.
+    const CACHED_PROPERTIES: [UIProperty; 5] = [
+        UIProperty::Name,
+        UIProperty::ControlType,
+        UIProperty::AutomationId,
+        UIProperty::ClassName,
+        UIProperty::BoundingRectangle,
+    ];
+
+    pub fn cache_request(automation: &UIAutomation) -> Result<UICacheRequest> {
+        let request = automation.create_cache_request()?;
+        for property in CACHED_PROPERTIES {
+            request.add_property(property)?;
+        }
+        Ok(request)
+    }
+
+    /// Descendants of `root` with one of `control_types`, or all of them when
+    /// `control_types` is empty, with their properties cached.
+    pub fn find_all(
+        automation: &UIAutomation,
+        root: &UIElement,
+        control_types: &[ControlType],
+    ) -> Result<Vec<UIElement>> {
+        let condition = control_type_condition(automation, control_types)?;
+        let request = cache_request(automation)?;
+        Ok(root.find_all_build_cache(TreeScope::Descendants, &condition, &request)?)
+    }
+
+    fn control_type_condition(
+        automation: &UIAutomation,
+        control_types: &[ControlType],
+    ) -> Result<UICondition> {
+        let mut condition: Option<UICondition> = None;
+        for control_type in control_types {
+            let next = automation.create_property_condition(
+                UIProperty::ControlType,
+                Variant::from(*control_type as i32),
+                None,
+            )?;
+            condition = Some(match condition {
+                Some(condition) => automation.create_or_condition(condition, next)?,
+                None => next,
+            });
+        }
+        match condition {
+            Some(condition) => Ok(condition),
+            None => Ok(automation.create_true_condition()?),
+        }
+    }
+
+    pub fn name(element: &UIElement) -> String {
+        element
+            .get_cached_name()
+            .or_else(|_| element.get_name())
+            .unwrap_or_default()
+    }
+
+    pub fn automation_id(element: &UIElement) -> String {
+        element
+            .get_cached_automation_id()
+            .or_else(|_| element.get_automation_id())
+            .unwrap_or_default()
+    }
+
+    pub fn class_name(element: &UIElement) -> String {
+        element
+            .get_cached_classname()
+            .or_else(|_| element.get_classname())
+            .unwrap_or_default()
+    }
+
+    pub fn control_type(element: &UIElement) -> Option<ControlType> {
+        element
+            .get_cached_control_type()
+            .or_else(|_| element.get_control_type())
+            .ok()
+    }
+
+    pub fn rect(element: &UIElement) -> Option<Rect> {
+        element
+            .get_cached_bounding_rectangle()
+            .or_else(|_| element.get_bounding_rectangle())
+            .ok()
+    }
+
+    /// A memoized element is reused while it is still on screen; removed or
+    /// collapsed elements report an error or an empty rectangle.
+    pub fn is_alive(element: &UIElement) -> bool {
+        element
+            .get_bounding_rectangle()
+            .is_ok_and(|rect| rect.get_width() > 0 && rect.get_height() > 0)
+    }
+}
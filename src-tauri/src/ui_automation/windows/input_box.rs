@@ -36,13 +36,28 @@ impl MockInputWriter {
 
 #[cfg(target_os = "windows")]
 pub mod uia {
-    use anyhow::{anyhow, Result};
+    use crate::graphemes;
+    use crate::types::LocatorCue;
+    use crate::ui_automation::trace;
+    use crate::ui_automation::windows::locator::uia::{identity, locate};
+    use crate::ui_automation::windows::locator::{cue_label, LocatorSpec};
+    use anyhow::Result;
     use uiautomation::clipboards::Clipboard;
     use uiautomation::inputs::Keyboard;
     use uiautomation::patterns::UIValuePattern;
     use uiautomation::types::ControlType;
     use uiautomation::{UIAutomation, UIElement};
 
+    const INPUT_TYPES: [ControlType; 3] =
+        [ControlType::Edit, ControlType::Document, ControlType::Pane];
+
+    const INPUT_BOX: LocatorSpec = LocatorSpec {
+        target: "input_box",
+        automation_ids: &["chat_input_field"],
+        class_names: &[],
+        names: &[],
+    };
+
     pub struct UiaInputWriter {
         automation: UIAutomation,
         window: UIElement,
@@ -57,54 +72,65 @@ pub mod uia {
         }
 
         pub fn write(&self, text: &str) -> Result<()> {
-            let input = find_input_box(&self.automation, &self.window)?;
+            let step = trace::step("find_element", "input");
+            let input = find_input_box(&self.automation, &self.window);
+            step.finish(cue_label(input.as_ref().ok().map(|(_, cue)| *cue)), &input);
+            let (input, _) = input?;
             input.set_focus().ok();
-            if write_via_value_pattern(&input, text).is_ok() {
+
+            let step = trace::step("set_value", "input");
+            let result = write_via_value_pattern(&input, text);
+            step.finish("value_pattern", &result);
+            if result.is_ok() {
                 return Ok(());
             }
-            if write_via_keyboard(text).is_ok() {
+            let step = trace::step("send_keys", "input");
+            let result = write_via_keyboard(text);
+            step.finish("keyboard", &result);
+            if result.is_ok() {
                 return Ok(());
             }
-            write_via_clipboard(&input, text)
+            let step = trace::step("paste", "input");
+            let result = write_via_clipboard(&input, text);
+            step.finish("clipboard", &result);
+            result
+        }
+
+        pub fn locate(&self) -> Result<&'static str> {
+            let (_, cue) = find_input_box(&self.automation, &self.window)?;
+            Ok(cue_label(Some(cue)))
+        }
+
+        pub fn submit(&self) -> Result<()> {
+            let (input, _) = find_input_box(&self.automation, &self.window)?;
+            input.set_focus().ok();
+            let step = trace::step("submit", "input");
+            let result: Result<()> = Keyboard::default().send_keys("{enter}").map_err(Into::into);
+            step.finish("keyboard", &result);
+            result
         }
     }
 
-    fn find_input_box(automation: &UIAutomation, window: &UIElement) -> Result<UIElement> {
+    fn find_input_box(
+        automation: &UIAutomation,
+        window: &UIElement,
+    ) -> Result<(UIElement, LocatorCue)> {
         let window_rect = window.get_bounding_rectangle()?;
         let mid_x = window_rect.get_left() + (window_rect.get_width() / 2);
         let min_y = window_rect.get_top() + (window_rect.get_height() * 2 / 3);
-        let candidates = automation
-            .create_matcher()
-            .from_ref(window)
-            .filter_fn(Box::new(move |element| {
-                let rect = element.get_bounding_rectangle().ok();
-                if let Some(rect) = rect {
-                    if rect.get_left() < mid_x || rect.get_top() < min_y {
-                        return Ok(false);
-                    }
-                }
-                let control_type = element.get_control_type().ok();
-                Ok(matches!(
-                    control_type,
-                    Some(ControlType::Edit | ControlType::Document | ControlType::Pane)
-                ))
-            }))
-            .depth(14)
-            .timeout(0)
-            .find_all()
-            .unwrap_or_default();
-        for element in candidates {
-            if let Ok(pattern) = element.get_pattern::<UIValuePattern>() {
-                if pattern.is_readonly().unwrap_or(true) {
-                    continue;
-                }
-                return Ok(element);
-            }
-            if element.get_control_type().ok() == Some(ControlType::Edit) {
-                return Ok(element);
-            }
-        }
-        Err(anyhow!("Input box not found"))
+        locate(automation, window, &INPUT_BOX, &INPUT_TYPES, |element| {
+            let mut facts = identity(element);
+            let writable = match element.get_pattern::<UIValuePattern>() {
+                Ok(pattern) => !pattern.is_readonly().unwrap_or(true),
+                Err(_) => element.get_control_type().ok() == Some(ControlType::Edit),
+            };
+            facts.structural = writable;
+            facts.in_region = writable
+                && element
+                    .get_bounding_rectangle()
+                    .is_ok_and(|rect| rect.get_left() >= mid_x && rect.get_top() >= min_y);
+            facts
+        })
     }
 
     fn write_via_value_pattern(input: &UIElement, text: &str) -> Result<()> {
@@ -118,7 +144,9 @@ pub mod uia {
         let keyboard = Keyboard::default();
         keyboard.send_keys("{ctrl}(a)")?;
         keyboard.send_keys("{backspace}")?;
-        keyboard.send_text(text)?;
+        for chunk in graphemes::chunks(text, graphemes::KEYBOARD_CHUNK_UNITS) {
+            keyboard.send_text(chunk)?;
+        }
         Ok(())
     }
 
@@ -128,6 +156,8 @@ pub mod uia {
         clipboard.set_text(text)?;
         input.set_focus().ok();
         let keyboard = Keyboard::default();
+        // Replace any half-typed draft instead of appending to it.
+        let _ = keyboard.send_keys("{ctrl}(a)");
         let _ = keyboard.send_keys("{ctrl}(v)");
         if let Some(original) = original {
             let _ = clipboard.set_text(&original);
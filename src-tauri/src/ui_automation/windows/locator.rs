@@ -0,0 +1,158 @@
+#[cfg(any(test, target_os = "windows"))]
+use crate::types::{LocatorCue, LocatorDiagnostic};
+#[cfg(any(test, target_os = "windows"))]
+use std::collections::BTreeMap;
+#[cfg(any(test, target_os = "windows"))]
+use std::sync::{Mutex, OnceLock};
+#[cfg(any(test, target_os = "windows"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(any(test, target_os = "windows"))]
+pub struct LocatorSpec {
+    pub target: &'static str,
+    pub automation_ids: &'static [&'static str],
+    pub class_names: &'static [&'static str],
+    pub names: &'static [&'static str],
+}
+
+#[cfg(any(test, target_os = "windows"))]
+#[derive(Debug, Clone, Default)]
+pub struct ElementFacts {
+    pub automation_id: String,
+    pub class_name: String,
+    pub name: String,
+    pub structural: bool,
+    pub in_region: bool,
+    pub weight: usize,
+}
+
+#[cfg(any(test, target_os = "windows"))]
+pub fn resolve(spec: &LocatorSpec, candidates: &[ElementFacts]) -> Option<(usize, LocatorCue)> {
+    let picked = pick_candidate(spec, candidates);
+    record(spec.target, picked.map(|(_, cue)| cue), candidates.len());
+    picked
+}
+
+#[cfg(any(test, target_os = "windows"))]
+pub fn pick_candidate(
+    spec: &LocatorSpec,
+    candidates: &[ElementFacts],
+) -> Option<(usize, LocatorCue)> {
+    let by_id = candidates.iter().position(|facts| {
+        (!facts.automation_id.is_empty()
+            && spec.automation_ids.contains(&facts.automation_id.as_str()))
+            || (!facts.class_name.is_empty()
+                && spec.class_names.contains(&facts.class_name.as_str()))
+    });
+    if let Some(index) = by_id {
+        return Some((index, LocatorCue::AutomationId));
+    }
+    let structural: Vec<usize> = (0..candidates.len())
+        .filter(|index| candidates[*index].structural)
+        .collect();
+    if let [index] = structural.as_slice() {
+        return Some((*index, LocatorCue::Structure));
+    }
+    if let Some(index) = heaviest(candidates, structural.into_iter()) {
+        return Some((index, LocatorCue::Geometry));
+    }
+    let by_name = candidates
+        .iter()
+        .position(|facts| spec.names.contains(&facts.name.trim()));
+    if let Some(index) = by_name {
+        return Some((index, LocatorCue::Name));
+    }
+    heaviest(candidates, 0..candidates.len()).map(|index| (index, LocatorCue::Geometry))
+}
+
+#[cfg(any(test, target_os = "windows"))]
+fn heaviest(candidates: &[ElementFacts], indices: impl Iterator<Item = usize>) -> Option<usize> {
+    indices
+        .filter(|index| candidates[*index].in_region)
+        .max_by_key(|index| (candidates[*index].weight, std::cmp::Reverse(*index)))
+}
+
+#[cfg(any(test, target_os = "windows"))]
+static DIAGNOSTICS: OnceLock<Mutex<BTreeMap<&'static str, LocatorDiagnostic>>> = OnceLock::new();
+
+#[cfg(any(test, target_os = "windows"))]
+pub fn record(target: &'static str, cue: Option<LocatorCue>, candidates: usize) {
+    let diagnostic = LocatorDiagnostic {
+        target: target.to_string(),
+        cue,
+        candidates: candidates as u32,
+        theme_sensitive: !matches!(cue, Some(LocatorCue::AutomationId | LocatorCue::Structure)),
+        at_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0),
+    };
+    let store = DIAGNOSTICS.get_or_init(|| Mutex::new(BTreeMap::new()));
+    let mut guard = store.lock().unwrap_or_else(|err| err.into_inner());
+    guard.insert(target, diagnostic);
+}
+
+#[cfg(any(test, target_os = "windows"))]
+pub fn diagnostics() -> Vec<LocatorDiagnostic> {
+    let Some(store) = DIAGNOSTICS.get() else {
+        return Vec::new();
+    };
+    let guard = store.lock().unwrap_or_else(|err| err.into_inner());
+    guard.values().cloned().collect()
+}
+
+#[cfg(any(test, target_os = "windows"))]
+pub fn cue_label(cue: Option<LocatorCue>) -> &'static str {
+    match cue {
+        Some(LocatorCue::AutomationId) => "automation_id",
+        Some(LocatorCue::Structure) => "structure",
+        Some(LocatorCue::Name) => "name",
+        Some(LocatorCue::Geometry) => "geometry",
+        None => "not_found",
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub mod uia {
+    use super::{resolve, ElementFacts, LocatorSpec};
+    use crate::types::LocatorCue;
+    use crate::ui_automation::windows::element_cache::uia as cached;
+    use anyhow::{anyhow, Result};
+    use uiautomation::types::ControlType;
+    use uiautomation::{UIAutomation, UIElement};
+
+    pub fn locate(
+        automation: &UIAutomation,
+        window: &UIElement,
+        spec: &LocatorSpec,
+        control_types: &'static [ControlType],
+        facts_of: impl Fn(&UIElement) -> ElementFacts,
+    ) -> Result<(UIElement, LocatorCue)> {
+        // One cached walk of the window instead of a per-element property
+        // round trip for every node the filter visits.
+        let elements: Vec<UIElement> = cached::find_all(automation, window, &[])
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|element| {
+                cached::control_type(element)
+                    .is_some_and(|control_type| control_types.contains(&control_type))
+                    || spec.automation_ids.contains(&cached::automation_id(element).as_str())
+                    || spec.class_names.contains(&cached::class_name(element).as_str())
+                    || spec.names.contains(&cached::name(element).trim())
+            })
+            .collect();
+        let facts: Vec<ElementFacts> = elements.iter().map(&facts_of).collect();
+        let (index, cue) =
+            resolve(spec, &facts).ok_or_else(|| anyhow!("Failed to locate {}", spec.target))?;
+        Ok((elements[index].clone(), cue))
+    }
+
+    pub fn identity(element: &UIElement) -> ElementFacts {
+        ElementFacts {
+            automation_id: cached::automation_id(element),
+            class_name: cached::class_name(element),
+            name: cached::name(element),
+            ..ElementFacts::default()
+        }
+    }
+}
@@ -1,8 +1,18 @@
 #[cfg(any(test, target_os = "windows"))]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum WatchMode {
-    Event,
-    Polling,
+use crate::types::ListenTarget;
+#[cfg(test)]
+use crate::ui_automation::WatchMode;
+
+/// Returns whether the active chat is one of the listen targets, so the watcher
+/// can skip diffing the message list of chats nobody asked to monitor. An empty
+/// target list watches every chat.
+#[cfg(any(test, target_os = "windows"))]
+pub fn is_watched_chat(targets: &[ListenTarget], chat_title: &str) -> bool {
+    if targets.is_empty() {
+        return true;
+    }
+    let title = chat_title.trim();
+    !title.is_empty() && crate::listen_targets::find_listen_target(targets, title).is_some()
 }
 
 #[cfg(test)]
@@ -32,21 +42,39 @@ impl MockWatcher {
 
 #[cfg(target_os = "windows")]
 pub mod uia {
-    use super::WatchMode;
-    use anyhow::{anyhow, Result};
+    use crate::ui_automation::{trace, TextChanges, WatchMode};
+    use crate::ui_automation::windows::element_cache::uia as cached;
+    use crate::ui_automation::windows::locator::uia::{identity, locate};
+    use crate::ui_automation::windows::locator::{cue_label, LocatorSpec};
+    use anyhow::Result;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::time::Instant;
+    use tracing::warn;
     use uiautomation::events::{CustomEventHandlerFn, UIEventHandler, UIEventType};
+    use uiautomation::patterns::UISelectionPattern;
     use uiautomation::types::ControlType;
     use uiautomation::{TreeScope, UIAutomation, UIElement};
 
-    const MESSAGE_LIST_NAMES: [&str; 2] = [
-        "\u{6d88}\u{606f}",
-        "\u{804a}\u{5929}\u{8bb0}\u{5f55}",
+    const LIST_TYPES: [ControlType; 4] = [
+        ControlType::List,
+        ControlType::DataGrid,
+        ControlType::Table,
+        ControlType::Tree,
     ];
 
+    const MESSAGE_LIST: LocatorSpec = LocatorSpec {
+        target: "message_list",
+        automation_ids: &["chat_message_list"],
+        class_names: &[],
+        names: &["\u{6d88}\u{606f}", "\u{804a}\u{5929}\u{8bb0}\u{5f55}"],
+    };
+
     pub struct UiaMessageWatcher {
         automation: UIAutomation,
         message_list: UIElement,
         handler: Option<UIEventHandler>,
+        events: Option<Receiver<String>>,
+        changes: TextChanges,
     }
 
     impl UiaMessageWatcher {
@@ -56,19 +84,50 @@ pub mod uia {
                 automation: automation.clone(),
                 message_list,
                 handler: None,
+                events: None,
+                changes: TextChanges::default(),
             })
         }
 
         pub fn start(&mut self) -> WatchMode {
-            if self.try_subscribe().is_ok() {
+            match self.try_subscribe() {
+                Ok(()) => WatchMode::Event,
+                Err(err) => {
+                    warn!("订阅消息列表文本变化失败，改为定时轮询: {}", err);
+                    WatchMode::Polling
+                }
+            }
+        }
+
+        pub fn mode(&self) -> WatchMode {
+            if self.handler.is_some() {
                 WatchMode::Event
             } else {
                 WatchMode::Polling
             }
         }
 
+        /// Texts changed in the message list since the last call, once the
+        /// latest burst of change events has settled.
+        pub fn settled_changes(&mut self) -> Vec<String> {
+            if let Some(events) = &self.events {
+                for text in events.try_iter() {
+                    self.changes.push(&text, Instant::now());
+                }
+            }
+            self.changes.take_settled(Instant::now())
+        }
+
         fn try_subscribe(&mut self) -> Result<()> {
-            let handle_fn: Box<CustomEventHandlerFn> = Box::new(|_sender, _event_type| Ok(()));
+            let (tx, rx) = channel();
+            // UIA calls the handler on its own thread; only read the changed
+            // text there and leave the rest to the poller.
+            let handle_fn: Box<CustomEventHandlerFn> = Box::new(move |sender, _event_type| {
+                if let Ok(text) = sender.get_name() {
+                    let _ = tx.send(text);
+                }
+                Ok(())
+            });
             let handler = UIEventHandler::from(handle_fn);
             self.automation.add_automation_event_handler(
                 UIEventType::Text_TextChanged,
@@ -78,73 +137,56 @@ pub mod uia {
                 &handler,
             )?;
             self.handler = Some(handler);
+            self.events = Some(rx);
             Ok(())
         }
 
-        pub fn latest_message_text(&self) -> Option<String> {
-            let items = self
-                .automation
-                .create_matcher()
-                .from_ref(&self.message_list)
-                .control_type(ControlType::ListItem)
-                .depth(8)
-                .timeout(0)
-                .find_all()
-                .unwrap_or_default();
+        pub fn message_texts(&self) -> Vec<String> {
+            let items =
+                cached::find_all(&self.automation, &self.message_list, &[ControlType::ListItem])
+                    .unwrap_or_default();
             items
-                .into_iter()
-                .filter_map(|item| item.get_name().ok())
-                .map(|name| name.trim().to_string())
+                .iter()
+                .map(|item| cached::name(item).trim().to_string())
                 .filter(|name| !name.is_empty())
-                .last()
+                .collect()
+        }
+    }
+
+    impl Drop for UiaMessageWatcher {
+        fn drop(&mut self) {
+            if let Some(handler) = self.handler.take() {
+                let _ = self.automation.remove_automation_event_handler(
+                    UIEventType::Text_TextChanged,
+                    &self.message_list,
+                    &handler,
+                );
+            }
         }
     }
 
     fn find_message_list(automation: &UIAutomation, window: &UIElement) -> Result<UIElement> {
-        let list_types = [
-            ControlType::List,
-            ControlType::DataGrid,
-            ControlType::Table,
-            ControlType::Tree,
-        ];
+        let step = trace::step("find_element", "message_list");
         let window_rect = window.get_bounding_rectangle()?;
         let mid_x = window_rect.get_left() + (window_rect.get_width() / 2);
-        let mut best: Option<UIElement> = None;
-        for control_type in list_types {
-            let candidates = automation
-                .create_matcher()
-                .from_ref(window)
-                .control_type(control_type)
-                .depth(12)
-                .timeout(0)
-                .find_all()
-                .unwrap_or_default();
-            for candidate in candidates {
-                if let Ok(rect) = candidate.get_bounding_rectangle() {
-                    if rect.get_left() < mid_x {
-                        continue;
-                    }
-                }
-                best = Some(candidate);
-                break;
-            }
-            if best.is_some() {
-                break;
+        let located = locate(automation, window, &MESSAGE_LIST, &LIST_TYPES, |element| {
+            let mut facts = identity(element);
+            if cached::control_type(element)
+                .is_some_and(|control_type| LIST_TYPES.contains(&control_type))
+            {
+                facts.weight = cached::find_all(automation, element, &[ControlType::ListItem])
+                    .map(|items| items.len())
+                    .unwrap_or(0);
+                facts.structural =
+                    facts.weight > 0 && element.get_pattern::<UISelectionPattern>().is_err();
             }
-        }
-        if let Some(list) = best {
-            return Ok(list);
-        }
-        automation
-            .create_matcher()
-            .from_ref(window)
-            .filter_fn(Box::new(|element| {
-                let name = element.get_name().unwrap_or_default();
-                Ok(MESSAGE_LIST_NAMES.iter().any(|label| label == &name))
-            }))
-            .depth(12)
-            .timeout(0)
-            .find_first()
-            .map_err(|_| anyhow!("Message list not found"))
+            facts.in_region = cached::rect(element).is_some_and(|rect| rect.get_left() >= mid_x);
+            facts
+        });
+        step.finish(
+            cue_label(located.as_ref().ok().map(|(_, cue)| *cue)),
+            &located,
+        );
+        located.map(|(element, _)| element)
     }
 }
@@ -1,10 +1,15 @@
+#[cfg(any(test, target_os = "windows"))]
+pub mod db;
 pub mod element;
+pub mod element_cache;
 pub mod input_box;
+pub mod locator;
 pub mod message_watch;
 pub mod session_list;
 pub mod uia;
 
-
+#[cfg(target_os = "windows")]
+pub use db::reader::WindowsDb;
 #[cfg(target_os = "windows")]
 pub use input_box::uia::UiaInputWriter;
 #[cfg(target_os = "windows")]
@@ -19,32 +24,89 @@ mod tests;
 
 #[cfg(target_os = "windows")]
 mod automation {
-    use super::message_watch::WatchMode;
-    use super::session_list::collect_recent_chats;
+    use super::element_cache::{uia as cached, ElementMemo};
+    use super::message_watch::is_watched_chat;
+    use super::session_list::collect_chats;
+    use super::session_list::uia::find_session_list;
     use super::{UiaClient, UiaInputWriter, UiaMessageWatcher, UiaSessionList};
-    use crate::types::{ChatSummary, ListenTarget, Platform};
-    use crate::ui_automation::{IncomingMessage, WeChatAutomation};
+    use crate::types::{ListenTarget, Platform};
+    use crate::ui_automation::{
+        ensure_active_chat, fresh_rows, rows_after, ChatPage, ChatQuery, IncomingMessage,
+        WatchMode, WeChatAutomation, CHAT_SWITCH_SETTLE,
+    };
     use anyhow::{anyhow, Result};
     use std::sync::Mutex;
+    use std::thread::sleep;
     use std::time::{SystemTime, UNIX_EPOCH};
+    use uiautomation::UIElement;
 
     pub struct WindowsAutomation {
         client: UiaClient,
+        window: Mutex<ElementMemo<UIElement>>,
+        session_list: Mutex<ElementMemo<UIElement>>,
         watcher: Mutex<Option<UiaMessageWatcher>>,
+        targets: Mutex<Vec<ListenTarget>>,
+        seen_rows: Mutex<Vec<String>>,
+        /// Chat whose rows `seen_rows` holds in event mode.
+        seen_chat: Mutex<String>,
     }
 
     impl WindowsAutomation {
         pub fn new() -> Result<Self> {
             Ok(Self {
                 client: UiaClient::new()?,
+                window: Mutex::new(ElementMemo::default()),
+                session_list: Mutex::new(ElementMemo::default()),
                 watcher: Mutex::new(None),
+                targets: Mutex::new(Vec::new()),
+                seen_rows: Mutex::new(Vec::new()),
+                seen_chat: Mutex::new(String::new()),
             })
         }
 
-        fn list_chats(&self) -> Result<Vec<ChatSummary>> {
-            let window = self.client.pick_wechat_window()?;
-            let mut list = UiaSessionList::from_window(self.client.automation(), &window)?;
-            collect_recent_chats(&mut list)
+        /// The main window, located again only once it is closed or hidden.
+        fn window(&self) -> Result<UIElement> {
+            self.window
+                .lock()
+                .map_err(|_| anyhow!("Window lock poisoned"))?
+                .get_or_locate(cached::is_alive, || self.client.pick_wechat_window())
+        }
+
+        /// The session list, memoized so every poll and page does not walk the
+        /// whole window again.
+        fn session_list(&self, window: &UIElement) -> Result<UiaSessionList> {
+            let automation = self.client.automation();
+            let list = self
+                .session_list
+                .lock()
+                .map_err(|_| anyhow!("Session list lock poisoned"))?
+                .get_or_locate(cached::is_alive, || find_session_list(automation, window))?;
+            Ok(UiaSessionList::from_list(automation, list))
+        }
+
+        fn active_chat(&self, window: &UIElement) -> String {
+            self.session_list(window)
+                .ok()
+                .and_then(|list| list.active_title())
+                .or_else(|| window.get_name().ok())
+                .unwrap_or_else(|| "WeChat".to_string())
+        }
+
+        /// Opens `chat_id` and confirms it is the active chat before anything
+        /// is typed, so a write never lands in whichever chat was open.
+        fn focus_chat(&self, window: &UIElement, chat_id: &str) -> Result<()> {
+            let list = self.session_list(window)?;
+            if list.active_title().as_deref() != Some(chat_id.trim()) {
+                list.select(chat_id)?;
+                sleep(CHAT_SWITCH_SETTLE);
+            }
+            ensure_active_chat(chat_id, list.active_title().as_deref())
+        }
+
+        fn scroll_chats(&self, query: &ChatQuery) -> Result<ChatPage> {
+            let window = self.window()?;
+            let mut list = self.session_list(&window)?;
+            collect_chats(&mut list, query)
         }
     }
 
@@ -53,15 +115,26 @@ mod automation {
             Platform::Windows
         }
 
-        fn list_recent_chats(&self) -> Result<Vec<ChatSummary>> {
-            self.list_chats()
+        fn list_chats(&self, query: &ChatQuery) -> Result<ChatPage> {
+            self.scroll_chats(query)
         }
 
-        fn start_listening(&self, _targets: Vec<ListenTarget>) -> Result<()> {
-            let window = self.client.pick_wechat_window()?;
+        fn start_listening(&self, targets: Vec<ListenTarget>) -> Result<()> {
+            *self.targets.lock().map_err(|_| anyhow!("Targets lock poisoned"))? = targets;
+            let window = self.window()?;
             let mut watcher = UiaMessageWatcher::new(self.client.automation(), &window)?;
             let mode = watcher.start();
             if matches!(mode, WatchMode::Polling | WatchMode::Event) {
+                let mut seen = self.seen_rows.lock().map_err(|_| anyhow!("Rows lock poisoned"))?;
+                seen.clear();
+                if mode == WatchMode::Event {
+                    // Rows already on screen re-render later and must not
+                    // count as new messages.
+                    *seen = watcher.message_texts();
+                    *self.seen_chat.lock().map_err(|_| anyhow!("Chat lock poisoned"))? =
+                        self.active_chat(&window);
+                }
+                drop(seen);
                 let mut guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
                 *guard = Some(watcher);
                 return Ok(());
@@ -72,41 +145,92 @@ mod automation {
         fn stop_listening(&self) -> Result<()> {
             let mut guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
             *guard = None;
+            self.targets
+                .lock()
+                .map_err(|_| anyhow!("Targets lock poisoned"))?
+                .clear();
             Ok(())
         }
 
-        fn write_input(&self, _chat_id: &str, text: &str) -> Result<()> {
-            let window = self.client.pick_wechat_window()?;
+        fn write_input(&self, chat_id: &str, text: &str) -> Result<()> {
+            let window = self.window()?;
+            self.focus_chat(&window, chat_id)?;
             let writer = UiaInputWriter::new(self.client.automation(), &window);
             writer.write(text)
         }
 
-        fn poll_latest_message(&self) -> Result<Option<IncomingMessage>> {
-            let guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
-            let Some(watcher) = guard.as_ref() else {
-                return Ok(None);
+        fn submit_input(&self, chat_id: &str) -> Result<()> {
+            let window = self.window()?;
+            let active = self.session_list(&window)?.active_title();
+            ensure_active_chat(chat_id, active.as_deref())?;
+            UiaInputWriter::new(self.client.automation(), &window).submit()
+        }
+
+        fn poll_new_messages(&self) -> Result<Vec<IncomingMessage>> {
+            let mut guard = self.watcher.lock().map_err(|_| anyhow!("Watcher lock poisoned"))?;
+            let Some(watcher) = guard.as_mut() else {
+                return Ok(Vec::new());
             };
-            let text = match watcher.latest_message_text() {
-                Some(text) => text,
-                None => return Ok(None),
+            // In event mode the window is only read after the message list
+            // reported changes.
+            let changed = match watcher.mode() {
+                WatchMode::Event => {
+                    let changed = watcher.settled_changes();
+                    if changed.is_empty() {
+                        return Ok(Vec::new());
+                    }
+                    Some(changed)
+                }
+                WatchMode::Polling => None,
+            };
+            let window = self.window()?;
+            let chat_id = self.active_chat(&window);
+            {
+                let targets = self.targets.lock().map_err(|_| anyhow!("Targets lock poisoned"))?;
+                if !is_watched_chat(&targets, &chat_id) {
+                    return Ok(Vec::new());
+                }
+            }
+            let texts = {
+                let mut seen = self.seen_rows.lock().map_err(|_| anyhow!("Rows lock poisoned"))?;
+                match changed {
+                    Some(changed) => {
+                        let mut seen_chat =
+                            self.seen_chat.lock().map_err(|_| anyhow!("Chat lock poisoned"))?;
+                        if *seen_chat == chat_id {
+                            fresh_rows(&mut seen, changed)
+                        } else {
+                            // Switching chats renders its whole history.
+                            *seen = watcher.message_texts();
+                            *seen_chat = chat_id.clone();
+                            Vec::new()
+                        }
+                    }
+                    None => {
+                        let rows = watcher.message_texts();
+                        let texts = rows_after(&seen, &rows);
+                        *seen = rows;
+                        texts
+                    }
+                }
             };
-            let window = self.client.pick_wechat_window()?;
-            let mut list = UiaSessionList::from_window(self.client.automation(), &window).ok();
-            let chat_id = list
-                .as_ref()
-                .and_then(|list| list.active_title())
-                .or_else(|| window.get_name().ok())
-                .unwrap_or_else(|| "WeChat".to_string());
             let timestamp = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap_or_default()
                 .as_secs();
-            Ok(Some(IncomingMessage {
-                chat_id,
-                text,
-                timestamp,
-                msg_id: None,
-            }))
+            Ok(texts
+                .into_iter()
+                .map(|text| IncomingMessage {
+                    chat_id: chat_id.clone(),
+                    content_type: crate::content_type::classify(&text),
+                    text,
+                    timestamp,
+                    msg_id: None,
+                    sender_name: String::new(),
+                    is_group: false,
+                    is_self: false,
+                })
+                .collect())
         }
     }
 
@@ -1,5 +1,9 @@
 #[cfg(any(test, target_os = "windows"))]
-use crate::types::{ChatKind, ChatSummary};
+use crate::chat_kind;
+#[cfg(any(test, target_os = "windows"))]
+use crate::types::ChatSummary;
+#[cfg(any(test, target_os = "windows"))]
+use crate::ui_automation::{ChatPage, ChatQuery};
 #[cfg(any(test, target_os = "windows"))]
 use anyhow::{anyhow, Result};
 #[cfg(any(test, target_os = "windows"))]
@@ -62,9 +66,13 @@ impl SessionListProvider for MockSessionList {
 }
 
 #[cfg(any(test, target_os = "windows"))]
-pub fn collect_recent_chats(provider: &mut dyn SessionListProvider) -> Result<Vec<ChatSummary>> {
+pub fn collect_chats(
+    provider: &mut dyn SessionListProvider,
+    query: &ChatQuery,
+) -> Result<ChatPage> {
     let mut seen = HashSet::new();
     let mut chats = Vec::new();
+    let mut matched = 0;
     let mut stagnant_rounds = 0;
     for _ in 0..64 {
         let mut new_count = 0;
@@ -77,11 +85,23 @@ pub fn collect_recent_chats(provider: &mut dyn SessionListProvider) -> Result<Ve
                 continue;
             }
             new_count += 1;
-            chats.push(ChatSummary {
+            let chat = ChatSummary {
                 chat_id: title.clone(),
+                kind: chat_kind::from_title(&title),
                 chat_title: title,
-                kind: ChatKind::Unknown,
-            });
+                account_id: String::new(),
+                last_message: String::new(),
+                last_active_at: 0,
+                unread_count: 0,
+            };
+            if query.matches(&chat) {
+                matched += 1;
+            }
+            chats.push(chat);
+        }
+        // Stop scrolling once the requested page (plus one) is on screen.
+        if matched >= query.wanted() {
+            break;
         }
         if new_count == 0 {
             stagnant_rounds += 1;
@@ -99,25 +119,43 @@ pub fn collect_recent_chats(provider: &mut dyn SessionListProvider) -> Result<Ve
     if chats.is_empty() {
         return Err(anyhow!("Session list empty"));
     }
-    Ok(chats)
+    Ok(query.page(chats))
 }
 
 #[cfg(target_os = "windows")]
 pub mod uia {
     use super::SessionListProvider;
+    use crate::ui_automation::trace;
+    use crate::ui_automation::windows::element_cache::uia as cached;
+    use crate::ui_automation::windows::locator::uia::{identity, locate};
+    use crate::ui_automation::windows::locator::{cue_label, LocatorSpec};
     use anyhow::{anyhow, Result};
     use uiautomation::patterns::{UISelectionItemPattern, UIScrollPattern};
     use uiautomation::types::{ControlType, ScrollAmount};
     use uiautomation::{UIAutomation, UIElement};
     use uiautomation::inputs::Keyboard;
 
-    const SESSION_LIST_NAMES: [&str; 4] = [
-        "\u{4f1a}\u{8bdd}",
-        "\u{804a}\u{5929}",
-        "\u{804a}\u{5929}\u{8bb0}\u{5f55}",
-        "\u{6298}\u{53e0}\u{7684}\u{7fa4}\u{804a}",
+    const LIST_TYPES: [ControlType; 4] = [
+        ControlType::List,
+        ControlType::DataGrid,
+        ControlType::Table,
+        ControlType::Tree,
     ];
 
+    const ITEM_TYPES: [ControlType; 2] = [ControlType::ListItem, ControlType::DataItem];
+
+    const SESSION_LIST: LocatorSpec = LocatorSpec {
+        target: "session_list",
+        automation_ids: &["session_list"],
+        class_names: &[],
+        names: &[
+            "\u{4f1a}\u{8bdd}",
+            "\u{804a}\u{5929}",
+            "\u{804a}\u{5929}\u{8bb0}\u{5f55}",
+            "\u{6298}\u{53e0}\u{7684}\u{7fa4}\u{804a}",
+        ],
+    };
+
     pub struct UiaSessionList {
         automation: UIAutomation,
         list: UIElement,
@@ -125,37 +163,19 @@ pub mod uia {
     }
 
     impl UiaSessionList {
-        pub fn from_window(automation: &UIAutomation, window: &UIElement) -> Result<Self> {
-            let list = find_session_list(automation, window)?;
+        /// Wraps a located session list, usually the memoized one.
+        pub fn from_list(automation: &UIAutomation, list: UIElement) -> Self {
             let scroll = list.get_pattern::<UIScrollPattern>().ok();
-            Ok(Self {
+            Self {
                 automation: automation.clone(),
                 list,
                 scroll,
-            })
+            }
         }
 
         fn list_item_names(&self) -> Vec<String> {
             let mut names = Vec::new();
-            let items = self
-                .automation
-                .create_matcher()
-                .from_ref(&self.list)
-                .control_type(ControlType::ListItem)
-                .depth(6)
-                .timeout(0)
-                .find_all()
-                .or_else(|_| {
-                    self.automation
-                        .create_matcher()
-                        .from_ref(&self.list)
-                        .control_type(ControlType::DataItem)
-                        .depth(6)
-                        .timeout(0)
-                        .find_all()
-                })
-                .unwrap_or_default();
-            for item in items {
+            for item in list_items(&self.automation, &self.list) {
                 if let Some(name) = extract_item_title(&self.automation, &item) {
                     names.push(name);
                 }
@@ -164,14 +184,7 @@ pub mod uia {
         }
 
         pub fn active_title(&self) -> Option<String> {
-            let items = self
-                .automation
-                .create_matcher()
-                .from_ref(&self.list)
-                .control_type(ControlType::ListItem)
-                .depth(6)
-                .timeout(0)
-                .find_all()
+            let items = cached::find_all(&self.automation, &self.list, &[ControlType::ListItem])
                 .unwrap_or_default();
             for item in items {
                 if let Ok(selection) = item.get_pattern::<UISelectionItemPattern>() {
@@ -182,6 +195,20 @@ pub mod uia {
             }
             None
         }
+
+        /// Selects the row titled `title` among the rows on screen.
+        pub fn select(&self, title: &str) -> Result<()> {
+            let item = list_items(&self.automation, &self.list)
+                .into_iter()
+                .find(|item| {
+                    extract_item_title(&self.automation, item).as_deref() == Some(title.trim())
+                })
+                .ok_or_else(|| anyhow!("会话「{}」不在会话列表中", title))?;
+            match item.get_pattern::<UISelectionItemPattern>() {
+                Ok(selection) if selection.select().is_ok() => Ok(()),
+                _ => Ok(item.click()?),
+            }
+        }
     }
 
     impl SessionListProvider for UiaSessionList {
@@ -207,86 +234,46 @@ pub mod uia {
     }
 
     pub fn find_session_list(automation: &UIAutomation, window: &UIElement) -> Result<UIElement> {
-        let list_types = [
-            ControlType::List,
-            ControlType::DataGrid,
-            ControlType::Table,
-            ControlType::Tree,
-        ];
+        let step = trace::step("find_element", "session_list");
         let window_rect = window.get_bounding_rectangle()?;
         let mid_x = window_rect.get_left() + (window_rect.get_width() * 6 / 10);
-        let mut best: Option<(UIElement, usize)> = None;
-        for control_type in list_types {
-            let candidates = automation
-                .create_matcher()
-                .from_ref(window)
-                .control_type(control_type)
-                .depth(12)
-                .timeout(0)
-                .find_all()
-                .unwrap_or_default();
-            for candidate in candidates {
-                if let Ok(rect) = candidate.get_bounding_rectangle() {
-                    if rect.get_right() > mid_x {
-                        continue;
-                    }
-                }
-                let count = count_list_items(automation, &candidate);
-                if count < 3 {
-                    continue;
-                }
-                let score = count;
-                match best {
-                    Some((_, best_score)) if best_score >= score => {}
-                    _ => best = Some((candidate, score)),
-                }
+        let located = locate(automation, window, &SESSION_LIST, &LIST_TYPES, |element| {
+            let mut facts = identity(element);
+            if cached::control_type(element)
+                .is_some_and(|control_type| LIST_TYPES.contains(&control_type))
+            {
+                facts.weight = list_items(automation, element).len();
+                facts.structural = facts.weight >= 3;
             }
-        }
-        if let Some((element, _)) = best {
-            return Ok(element);
-        }
-        let named = automation
-            .create_matcher()
-            .from_ref(window)
-            .filter_fn(Box::new(|element| {
-                let name = element.get_name().unwrap_or_default();
-                Ok(SESSION_LIST_NAMES.iter().any(|label| label == &name))
-            }))
-            .depth(12)
-            .timeout(0)
-            .find_first();
-        named.map_err(|_| anyhow!("Failed to locate session list"))
+            facts.in_region = cached::rect(element).is_some_and(|rect| rect.get_right() <= mid_x);
+            facts
+        });
+        step.finish(
+            cue_label(located.as_ref().ok().map(|(_, cue)| *cue)),
+            &located,
+        );
+        located.map(|(element, _)| element)
     }
 
-    fn count_list_items(automation: &UIAutomation, list: &UIElement) -> usize {
-        let list_items = automation
-            .create_matcher()
-            .from_ref(list)
-            .control_type(ControlType::ListItem)
-            .depth(6)
-            .timeout(0)
-            .find_all()
-            .map(|items| items.len())
-            .unwrap_or(0)
-            ;
-        let data_items = automation
-            .create_matcher()
-            .from_ref(list)
-            .control_type(ControlType::DataItem)
-            .depth(6)
-            .timeout(0)
-            .find_all()
-            .map(|items| items.len())
-            .unwrap_or(0);
-        list_items.max(data_items)
+    /// List items of a session list in one cached call; WeChat builds expose
+    /// the rows either as `ListItem` or as `DataItem`, never both.
+    fn list_items(automation: &UIAutomation, list: &UIElement) -> Vec<UIElement> {
+        let items = cached::find_all(automation, list, &ITEM_TYPES).unwrap_or_default();
+        let (list_items, data_items): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| {
+            cached::control_type(item) == Some(ControlType::ListItem)
+        });
+        if list_items.is_empty() {
+            data_items
+        } else {
+            list_items
+        }
     }
 
     fn extract_item_title(automation: &UIAutomation, item: &UIElement) -> Option<String> {
-        if let Ok(name) = item.get_name() {
-            let trimmed = name.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
-            }
+        let name = cached::name(item);
+        let trimmed = name.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
         }
         let text = automation
             .create_matcher()
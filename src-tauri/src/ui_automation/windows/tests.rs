@@ -1,7 +1,14 @@
+use super::db;
+use super::element_cache::ElementMemo;
 use super::input_box::MockInputWriter;
-use super::message_watch::{MockWatcher, WatchMode};
-use super::session_list::{collect_recent_chats, MockSessionList};
+use super::locator::{
+    cue_label, diagnostics, pick_candidate, record, resolve, ElementFacts, LocatorSpec,
+};
+use super::message_watch::{is_watched_chat, MockWatcher};
+use super::session_list::{collect_chats, MockSessionList, SessionListProvider};
 use super::uia::{find_wechat_hwnd, MockUia};
+use crate::types::{ChatKind, ListenTarget, LocatorCue, Politeness, TargetPriority};
+use crate::ui_automation::{ChatQuery, WatchMode};
 
 #[test]
 fn uia_finds_wechat_main_window_by_process_name() {
@@ -16,8 +23,23 @@ fn session_list_scrolls_and_dedupes() {
         vec!["A", "B"],
         vec!["C", "B"],
     ]);
-    let chats = collect_recent_chats(&mut mock).unwrap();
-    assert_eq!(chats.len(), 3);
+    let page = collect_chats(&mut mock, &ChatQuery::new(0, 50, "")).unwrap();
+    assert_eq!(page.chats.len(), 3);
+    assert!(!page.has_more);
+}
+
+#[test]
+fn session_list_stops_scrolling_once_the_page_is_filled() {
+    let mut mock = MockSessionList::with_pages(vec![
+        vec!["项目群", "Alice", "产品群"],
+        vec!["Bob", "测试群"],
+        vec!["运营群"],
+    ]);
+    let page = collect_chats(&mut mock, &ChatQuery::new(1, 1, "群")).unwrap();
+    let titles: Vec<_> = page.chats.iter().map(|chat| chat.chat_title.as_str()).collect();
+    assert_eq!(titles, vec!["产品群"]);
+    assert!(page.has_more);
+    assert_eq!(mock.snapshot(), vec!["Bob", "测试群"]);
 }
 
 #[test]
@@ -27,6 +49,48 @@ fn watcher_falls_back_to_polling_on_subscribe_failure() {
     assert_eq!(mode, WatchMode::Polling);
 }
 
+#[test]
+fn watcher_only_diffs_listen_targets() {
+    let targets = vec![ListenTarget {
+        name: "Alice".to_string(),
+        kind: ChatKind::Direct,
+        prompt_override: None,
+        persona: None,
+        muted: false,
+        priority: TargetPriority::Normal,
+        sender_whitelist: Vec::new(),
+        sender_blacklist: Vec::new(),
+        mention_only: false,
+        language: None,
+        politeness: Politeness::default(),
+    }];
+    assert!(is_watched_chat(&targets, " Alice "));
+    assert!(!is_watched_chat(&targets, "Bob"));
+    assert!(!is_watched_chat(&targets, ""));
+    assert!(is_watched_chat(&[], "Bob"));
+}
+
+#[test]
+fn element_memo_relocates_only_dead_elements() {
+    let mut memo = ElementMemo::default();
+    let mut located = 0;
+    for _ in 0..3 {
+        let element = memo
+            .get_or_locate(|_| true, || {
+                located += 1;
+                Ok("session_list")
+            })
+            .unwrap();
+        assert_eq!(element, "session_list");
+    }
+    assert_eq!(located, 1);
+
+    let element = memo.get_or_locate(|_| false, || Ok("session_list_2")).unwrap();
+    assert_eq!(element, "session_list_2");
+    assert!(memo.get_or_locate(|_| false, || Err(anyhow::anyhow!("gone"))).is_err());
+    assert_eq!(memo.get_or_locate(|_| true, || Ok("fresh")).unwrap(), "fresh");
+}
+
 #[test]
 fn input_writer_uses_clipboard_on_uia_failure() {
     let mut mock = MockInputWriter::uia_fail();
@@ -34,3 +98,212 @@ fn input_writer_uses_clipboard_on_uia_failure() {
     assert!(ok);
     assert!(mock.used_clipboard());
 }
+
+#[test]
+fn locator_prefers_automation_id_and_structure_over_names() {
+    let spec = LocatorSpec {
+        target: "locator-test",
+        automation_ids: &["session_list"],
+        class_names: &[],
+        names: &["会话"],
+    };
+    let list = |name: &str, structural: bool, in_region: bool, weight: usize| ElementFacts {
+        name: name.to_string(),
+        structural,
+        in_region,
+        weight,
+        ..ElementFacts::default()
+    };
+    let themed = vec![
+        list("会话", false, true, 0),
+        list("", true, false, 12),
+        list("", false, true, 1),
+    ];
+    assert_eq!(
+        pick_candidate(&spec, &themed),
+        Some((1, LocatorCue::Structure))
+    );
+
+    let mut with_id = themed.clone();
+    with_id[2].automation_id = "session_list".to_string();
+    assert_eq!(
+        pick_candidate(&spec, &with_id),
+        Some((2, LocatorCue::AutomationId))
+    );
+
+    let ambiguous = vec![list("", true, false, 30), list("", true, true, 8)];
+    assert_eq!(
+        pick_candidate(&spec, &ambiguous),
+        Some((1, LocatorCue::Geometry))
+    );
+
+    let high_contrast = vec![list("会话", false, false, 0), list("", false, true, 2)];
+    assert_eq!(
+        pick_candidate(&spec, &high_contrast),
+        Some((0, LocatorCue::Name))
+    );
+    assert_eq!(pick_candidate(&spec, &[list("", false, false, 5)]), None);
+}
+
+#[test]
+fn locator_records_theme_sensitive_cues() {
+    let spec = LocatorSpec {
+        target: "locator-test-geometry",
+        automation_ids: &[],
+        class_names: &[],
+        names: &[],
+    };
+    let facts = vec![
+        ElementFacts::default(),
+        ElementFacts {
+            in_region: true,
+            ..ElementFacts::default()
+        },
+    ];
+    assert_eq!(resolve(&spec, &facts), Some((1, LocatorCue::Geometry)));
+    record("locator-test-id", Some(LocatorCue::AutomationId), 2);
+    let recorded: Vec<_> = diagnostics()
+        .into_iter()
+        .filter(|diagnostic| diagnostic.target.starts_with("locator-test-"))
+        .map(|diagnostic| {
+            (
+                diagnostic.target,
+                diagnostic.theme_sensitive,
+                diagnostic.candidates,
+            )
+        })
+        .collect();
+    assert_eq!(
+        recorded,
+        vec![
+            ("locator-test-geometry".to_string(), true, 2),
+            ("locator-test-id".to_string(), false, 2),
+        ]
+    );
+    assert_eq!(cue_label(None), "not_found");
+}
+
+fn wechat_db_fixture() -> rusqlite::Connection {
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE Session (strUsrName TEXT, strNickName TEXT, nOrder INTEGER,
+             nMsgType INTEGER, strContent TEXT, nTime INTEGER, nUnReadCount INTEGER);
+         CREATE TABLE Contact (UserName TEXT, Remark TEXT, NickName TEXT);
+         CREATE TABLE ContactHeadImg1 (usrName TEXT, createTime INTEGER, smallHeadBuf BLOB);
+         CREATE TABLE MSG (localId INTEGER PRIMARY KEY, MsgSvrID INTEGER, Type INTEGER,
+             IsSender INTEGER, CreateTime INTEGER, StrTalker TEXT, StrContent TEXT);
+         INSERT INTO Session VALUES ('wxid_a', '', 3, 1, '早', 100, 1),
+             ('123@chatroom', '项目群', 2, 3, '<img/>', 103, 0),
+             ('@placeholder_foldgroup', '', 1, 1, '', 0, 0);
+         INSERT INTO Contact VALUES ('wxid_a', '老王', 'Wang');
+         INSERT INTO ContactHeadImg1 VALUES ('wxid_a', 1, X'FFD8FF00'),
+             ('wxid_a', 2, X'FFD8FF01'), ('123@chatroom', 1, X'');
+         INSERT INTO MSG VALUES (1, 11, 1, 0, 100, 'wxid_a', '早'),
+             (2, 12, 1, 1, 101, 'wxid_a', '早呀'),
+             (3, 13, 10000, 0, 102, 'wxid_a', '撤回了一条消息'),
+             (4, 14, 3, 0, 103, '123@chatroom', '<img/>');",
+    )
+    .unwrap();
+    conn
+}
+
+#[test]
+fn wechat_db_lists_sessions_by_display_name() {
+    let conn = wechat_db_fixture();
+    let page = db::query_sessions(&conn, &ChatQuery::new(0, 10, "")).unwrap();
+    assert!(!page.has_more);
+    let chats = page.chats;
+    let titles: Vec<_> = chats
+        .iter()
+        .map(|chat| (chat.chat_id.as_str(), chat.chat_title.as_str()))
+        .collect();
+    assert_eq!(titles, vec![("wxid_a", "老王"), ("123@chatroom", "项目群")]);
+    assert_eq!(chats[0].kind, ChatKind::Direct);
+    assert_eq!(chats[1].kind, ChatKind::Group);
+    assert_eq!((chats[0].last_message.as_str(), chats[0].unread_count), ("早", 1));
+    assert_eq!((chats[1].last_message.as_str(), chats[1].last_active_at), ("[图片]", 103));
+    assert_eq!(
+        db::query_display_name(&conn, "wxid_a").unwrap().as_deref(),
+        Some("老王")
+    );
+    assert_eq!(db::query_display_name(&conn, "wxid_b").unwrap(), None);
+}
+
+#[test]
+fn wechat_db_reads_cached_avatars() {
+    let conn = wechat_db_fixture();
+    assert_eq!(db::query_user_name(&conn, "Wang").unwrap().as_deref(), Some("wxid_a"));
+    assert_eq!(db::query_user_name(&conn, "老王").unwrap().as_deref(), Some("wxid_a"));
+    assert_eq!(db::query_user_name(&conn, "项目群").unwrap(), None);
+    let image = db::query_head_image(&conn, "wxid_a").unwrap();
+    assert_eq!(image, Some(vec![0xFF, 0xD8, 0xFF, 0x01]));
+    assert_eq!(db::query_head_image(&conn, "123@chatroom").unwrap(), None);
+}
+
+#[test]
+fn wechat_db_pages_and_searches_sessions() {
+    let conn = wechat_db_fixture();
+    let first = db::query_sessions(&conn, &ChatQuery::new(0, 1, "")).unwrap();
+    assert_eq!(first.chats[0].chat_id, "wxid_a");
+    assert!(first.has_more);
+    let second = db::query_sessions(&conn, &ChatQuery::new(1, 1, "")).unwrap();
+    assert_eq!(second.chats[0].chat_id, "123@chatroom");
+    assert!(!second.has_more);
+
+    let by_title = db::query_sessions(&conn, &ChatQuery::new(0, 10, "老王")).unwrap();
+    assert_eq!(by_title.chats.len(), 1);
+    let by_id = db::query_sessions(&conn, &ChatQuery::new(0, 10, "CHATROOM")).unwrap();
+    assert_eq!(by_id.chats[0].chat_title, "项目群");
+    let escaped = db::query_sessions(&conn, &ChatQuery::new(0, 10, "wxid%")).unwrap();
+    assert!(escaped.chats.is_empty());
+}
+
+#[test]
+fn wechat_db_polls_messages_after_cursor() {
+    let conn = wechat_db_fixture();
+    assert_eq!(db::query_max_local_id(&conn).unwrap(), 4);
+    let batch = db::query_messages_after(&conn, 0, 10).unwrap();
+    let rows: Vec<_> = batch
+        .iter()
+        .map(|message| (message.local_id, message.is_self))
+        .collect();
+    assert_eq!(rows, vec![(1, false), (2, true), (4, false)]);
+    assert_eq!(batch[0].content, "早");
+    assert_eq!(batch[1].content, "早呀");
+    assert_eq!(batch[2].talker, "123@chatroom");
+    assert_eq!(
+        crate::content_type::decode_wechat_message(batch[2].msg_type, &batch[2].content).text,
+        "[图片]"
+    );
+    assert_eq!(db::query_messages_after(&conn, 0, 1).unwrap().len(), 1);
+    assert!(db::query_messages_after(&conn, 4, 10).unwrap().is_empty());
+}
+
+#[test]
+fn wechat_db_measures_reply_time_per_talker() {
+    let conn = wechat_db_fixture();
+    let stats = db::query_chat_activity(&conn, 200).unwrap();
+    let rows: Vec<_> = stats
+        .iter()
+        .map(|item| (item.chat_id.as_str(), item.messages_30d, item.avg_reply_secs))
+        .collect();
+    assert_eq!(rows, vec![("123@chatroom", 1, None), ("wxid_a", 2, Some(1))]);
+}
+
+#[test]
+fn wechat_db_picks_latest_account_and_shard() {
+    let temp = tempfile::tempdir().unwrap();
+    let msg_dir = temp.path().join("wxid_a").join("Msg");
+    std::fs::create_dir_all(msg_dir.join("Multi")).unwrap();
+    std::fs::create_dir_all(temp.path().join("All Users").join("Msg")).unwrap();
+    std::fs::write(msg_dir.join(db::SESSION_DB), b"x").unwrap();
+    std::fs::write(temp.path().join("All Users").join("Msg").join(db::SESSION_DB), b"x").unwrap();
+    for name in ["MSG0.db", "MSG2.db", "MSG10.db", "MSG1.db-wal"] {
+        std::fs::write(msg_dir.join("Multi").join(name), b"x").unwrap();
+    }
+    assert_eq!(db::locate_msg_dir_in(temp.path()), Some(msg_dir.clone()));
+    assert_eq!(
+        db::latest_msg_db(&msg_dir),
+        Some(msg_dir.join("Multi").join("MSG10.db"))
+    );
+}
@@ -0,0 +1,447 @@
+#![cfg_attr(not(target_os = "windows"), allow(dead_code))]
+
+use crate::types::DbKeyReport;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+pub const HELPER_ENV: &str = "WEREPLY_DB_KEY_HELPER";
+const RETRY_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+const PAGE_SIZE: usize = 4096;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const HMAC_LEN: usize = 20;
+// IV (16) + HMAC-SHA1 (20), rounded up to the 16-byte cipher block.
+const RESERVE_LEN: usize = 48;
+const KDF_ITERATIONS: u32 = 64_000;
+const MAC_SALT_MASK: u8 = 0x3a;
+const MAX_CANDIDATES: usize = 2048;
+
+static LAST_FAILURE: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Reads the database key for the running WeChat, trying the helper tool from
+/// `WEREPLY_DB_KEY_HELPER` first and then a scan of `WeChatWin.dll` memory.
+/// Every candidate is checked against the first page of `MicroMsg.db` before it
+/// is stored in the keyring. Failed attempts are not retried for 10 minutes.
+pub fn acquire() -> DbKeyReport {
+    let last_failure = LAST_FAILURE.get_or_init(|| Mutex::new(None));
+    if let Some(retry_after) = last_failure
+        .lock()
+        .ok()
+        .and_then(|guard| *guard)
+        .and_then(|failed_at| RETRY_COOLDOWN.checked_sub(failed_at.elapsed()))
+    {
+        return DbKeyReport {
+            ok: false,
+            method: None,
+            candidates_checked: 0,
+            retry_after_secs: retry_after.as_secs().max(1),
+            detail: "上次获取密钥失败，请稍后再试".to_string(),
+        };
+    }
+    let report = platform::acquire();
+    if let Ok(mut guard) = last_failure.lock() {
+        *guard = if report.ok { None } else { Some(Instant::now()) };
+    }
+    report
+}
+
+/// Checks a raw key against the first page of a WeChat Windows database the
+/// same way SQLCipher 3 verifies its page HMAC.
+pub fn verify_key(key: &[u8], first_page: &[u8]) -> bool {
+    if key.len() != KEY_LEN || first_page.len() < PAGE_SIZE {
+        return false;
+    }
+    let salt = &first_page[..SALT_LEN];
+    let mut cipher_key = [0u8; KEY_LEN];
+    pbkdf2_sha1(key, salt, KDF_ITERATIONS, &mut cipher_key);
+    let mac_salt: Vec<u8> = salt.iter().map(|byte| byte ^ MAC_SALT_MASK).collect();
+    let mut mac_key = [0u8; KEY_LEN];
+    pbkdf2_sha1(&cipher_key, &mac_salt, 2, &mut mac_key);
+    let hmac_start = PAGE_SIZE - RESERVE_LEN + SALT_LEN;
+    let Ok(mut mac) = HmacSha1::new_from_slice(&mac_key) else {
+        return false;
+    };
+    mac.update(&first_page[SALT_LEN..hmac_start]);
+    mac.update(&1u32.to_le_bytes());
+    mac.verify_slice(&first_page[hmac_start..hmac_start + HMAC_LEN])
+        .is_ok()
+}
+
+/// `WeChatWin.dll` keeps the key as a (pointer, length = 32) pair; returns the
+/// distinct pointers found in a dump of its writable data.
+pub fn key_pointer_candidates(data: &[u8]) -> Vec<u64> {
+    let mut candidates = Vec::new();
+    for pair in data.chunks_exact(8).collect::<Vec<_>>().windows(2) {
+        let pointer = u64::from_le_bytes(pair[0].try_into().unwrap_or_default());
+        let len = u64::from_le_bytes(pair[1].try_into().unwrap_or_default());
+        if len != KEY_LEN as u64 || pointer < 0x10000 || pointer % 8 != 0 {
+            continue;
+        }
+        if !candidates.contains(&pointer) {
+            candidates.push(pointer);
+            if candidates.len() >= MAX_CANDIDATES {
+                break;
+            }
+        }
+    }
+    candidates
+}
+
+/// Takes the first 64-character hex token printed by the helper tool.
+pub fn parse_helper_output(output: &str) -> Option<String> {
+    output
+        .split(|ch: char| !ch.is_ascii_alphanumeric())
+        .map(|token| token.strip_prefix("0x").unwrap_or(token))
+        .find(|token| token.len() == KEY_LEN * 2 && token.chars().all(|ch| ch.is_ascii_hexdigit()))
+        .map(|token| token.to_ascii_lowercase())
+}
+
+fn pbkdf2_sha1(password: &[u8], salt: &[u8], rounds: u32, out: &mut [u8]) {
+    let Ok(prf) = HmacSha1::new_from_slice(password) else {
+        return;
+    };
+    for (index, chunk) in out.chunks_mut(HMAC_LEN).enumerate() {
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&(index as u32 + 1).to_be_bytes());
+        let mut round = mac.finalize().into_bytes();
+        let mut block = round;
+        for _ in 1..rounds {
+            let mut mac = prf.clone();
+            mac.update(&round);
+            round = mac.finalize().into_bytes();
+            block
+                .iter_mut()
+                .zip(round.iter())
+                .for_each(|(acc, byte)| *acc ^= byte);
+        }
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::{
+        hex, key_pointer_candidates, parse_helper_output, verify_key, HELPER_ENV, KEY_LEN,
+        PAGE_SIZE,
+    };
+    use crate::secret::ApiKeyManager;
+    use crate::types::{DbKeyMethod, DbKeyReport};
+    use crate::ui_automation::windows::db::reader::locate_msg_dir;
+    use crate::ui_automation::windows::db::SESSION_DB;
+    use std::io::Read;
+    use std::process::Command;
+    use tracing::{info, warn};
+    use windows::Win32::Foundation::{CloseHandle, HANDLE, HMODULE};
+    use windows::Win32::System::Diagnostics::Debug::ReadProcessMemory;
+    use windows::Win32::System::Memory::{
+        VirtualQueryEx, MEMORY_BASIC_INFORMATION, MEM_COMMIT, PAGE_READWRITE, PAGE_WRITECOPY,
+    };
+    use windows::Win32::System::ProcessStatus::{
+        EnumProcessModules, EnumProcesses, GetModuleBaseNameW, GetModuleInformation, MODULEINFO,
+    };
+    use windows::Win32::System::Threading::{
+        OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
+    };
+
+    const WECHAT_PROCESSES: [&str; 2] = ["wechat.exe", "weixin.exe"];
+    const WECHAT_MODULES: [&str; 2] = ["wechatwin.dll", "weixin.dll"];
+
+    struct Process(HANDLE);
+
+    impl Drop for Process {
+        fn drop(&mut self) {
+            let _ = unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    pub fn acquire() -> DbKeyReport {
+        let first_page = match read_first_page() {
+            Ok(page) => page,
+            Err(detail) => return failure(None, 0, detail),
+        };
+        if let Some(helper) = std::env::var_os(HELPER_ENV) {
+            match run_helper(&helper) {
+                Ok(key) if verify_hex(&key, &first_page) => {
+                    return store(DbKeyMethod::Helper, 1, &key);
+                }
+                Ok(_) => warn!("密钥助手返回的密钥无法解密 {}", SESSION_DB),
+                Err(err) => warn!("运行密钥助手失败: {}", err),
+            }
+        }
+        match scan_memory(&first_page) {
+            Ok((Some(key), checked)) => store(DbKeyMethod::MemoryScan, checked, &key),
+            Ok((None, checked)) => failure(
+                Some(DbKeyMethod::MemoryScan),
+                checked,
+                "未在微信内存中找到可用的数据库密钥，请确认微信已登录".to_string(),
+            ),
+            Err(detail) => failure(Some(DbKeyMethod::MemoryScan), 0, detail),
+        }
+    }
+
+    fn failure(method: Option<DbKeyMethod>, checked: u32, detail: String) -> DbKeyReport {
+        warn!("获取微信数据库密钥失败: {}", detail);
+        DbKeyReport {
+            ok: false,
+            method,
+            candidates_checked: checked,
+            retry_after_secs: 0,
+            detail,
+        }
+    }
+
+    fn store(method: DbKeyMethod, checked: u32, key: &str) -> DbKeyReport {
+        if let Err(err) = ApiKeyManager::set_wechat_db_key(key) {
+            return failure(Some(method), checked, err.to_string());
+        }
+        info!("已获取微信数据库密钥: method={:?}, candidates={}", method, checked);
+        DbKeyReport {
+            ok: true,
+            method: Some(method),
+            candidates_checked: checked,
+            retry_after_secs: 0,
+            detail: "已获取并保存微信数据库密钥".to_string(),
+        }
+    }
+
+    fn read_first_page() -> Result<Vec<u8>, String> {
+        let msg_dir = locate_msg_dir().ok_or_else(|| "未找到微信数据目录 Msg".to_string())?;
+        let path = msg_dir.join(SESSION_DB);
+        let mut page = vec![0u8; PAGE_SIZE];
+        std::fs::File::open(&path)
+            .and_then(|mut file| file.read_exact(&mut page))
+            .map_err(|err| format!("读取 {} 失败: {}", path.display(), err))?;
+        Ok(page)
+    }
+
+    fn run_helper(helper: &std::ffi::OsStr) -> Result<String, String> {
+        let output = Command::new(helper)
+            .output()
+            .map_err(|err| err.to_string())?;
+        if !output.status.success() {
+            return Err(format!("退出码 {:?}", output.status.code()));
+        }
+        parse_helper_output(&String::from_utf8_lossy(&output.stdout))
+            .ok_or_else(|| "输出中没有 64 位十六进制密钥".to_string())
+    }
+
+    fn verify_hex(key: &str, first_page: &[u8]) -> bool {
+        let bytes: Option<Vec<u8>> = (0..key.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(key.get(index..index + 2)?, 16).ok())
+            .collect();
+        bytes.is_some_and(|bytes| verify_key(&bytes, first_page))
+    }
+
+    fn scan_memory(first_page: &[u8]) -> Result<(Option<String>, u32), String> {
+        let process = open_wechat().ok_or_else(|| "微信未运行或无权读取其内存".to_string())?;
+        let module = find_module(&process).ok_or_else(|| "未找到 WeChatWin.dll".to_string())?;
+        let mut checked = 0;
+        for pointer in key_pointer_candidates(&read_writable(&process, &module)) {
+            let mut key = [0u8; KEY_LEN];
+            if !read(&process, pointer as usize, &mut key) || key.iter().all(|byte| *byte == 0) {
+                continue;
+            }
+            checked += 1;
+            if verify_key(&key, first_page) {
+                return Ok((Some(hex(&key)), checked));
+            }
+        }
+        Ok((None, checked))
+    }
+
+    fn open_wechat() -> Option<Process> {
+        let mut pids = vec![0u32; 4096];
+        let mut needed = 0u32;
+        unsafe {
+            EnumProcesses(
+                pids.as_mut_ptr(),
+                (pids.len() * std::mem::size_of::<u32>()) as u32,
+                &mut needed,
+            )
+        }
+        .ok()?;
+        pids.truncate(needed as usize / std::mem::size_of::<u32>());
+        pids.into_iter().find_map(|pid| {
+            let handle =
+                unsafe { OpenProcess(PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, false, pid) }
+                    .ok()?;
+            let process = Process(handle);
+            let name = module_name(&process, None)?;
+            WECHAT_PROCESSES.contains(&name.as_str()).then_some(process)
+        })
+    }
+
+    fn module_name(process: &Process, module: Option<HMODULE>) -> Option<String> {
+        let mut buffer = [0u16; 260];
+        let len = unsafe { GetModuleBaseNameW(process.0, module, &mut buffer) } as usize;
+        (len > 0).then(|| String::from_utf16_lossy(&buffer[..len]).to_ascii_lowercase())
+    }
+
+    fn find_module(process: &Process) -> Option<MODULEINFO> {
+        let mut modules = vec![HMODULE::default(); 1024];
+        let mut needed = 0u32;
+        unsafe {
+            EnumProcessModules(
+                process.0,
+                modules.as_mut_ptr(),
+                (modules.len() * std::mem::size_of::<HMODULE>()) as u32,
+                &mut needed,
+            )
+        }
+        .ok()?;
+        modules.truncate(needed as usize / std::mem::size_of::<HMODULE>());
+        let module = modules.into_iter().find(|module| {
+            module_name(process, Some(*module))
+                .is_some_and(|name| WECHAT_MODULES.contains(&name.as_str()))
+        })?;
+        let mut info = MODULEINFO::default();
+        unsafe {
+            GetModuleInformation(
+                process.0,
+                module,
+                &mut info,
+                std::mem::size_of::<MODULEINFO>() as u32,
+            )
+        }
+        .ok()?;
+        Some(info)
+    }
+
+    /// Copies the module's writable sections, where the key pointer lives.
+    fn read_writable(process: &Process, module: &MODULEINFO) -> Vec<u8> {
+        let start = module.lpBaseOfDll as usize;
+        let end = start + module.SizeOfImage as usize;
+        let mut data = Vec::new();
+        let mut address = start;
+        while address < end {
+            let mut region = MEMORY_BASIC_INFORMATION::default();
+            let size = unsafe {
+                VirtualQueryEx(
+                    process.0,
+                    Some(address as *const _),
+                    &mut region,
+                    std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                )
+            };
+            if size == 0 || region.RegionSize == 0 {
+                break;
+            }
+            let writable = region.Protect == PAGE_READWRITE || region.Protect == PAGE_WRITECOPY;
+            if region.State == MEM_COMMIT && writable {
+                let len = region.RegionSize.min(end - address);
+                let mut buffer = vec![0u8; len];
+                if read(process, address, &mut buffer) {
+                    data.extend_from_slice(&buffer);
+                }
+            }
+            address = region.BaseAddress as usize + region.RegionSize;
+        }
+        data
+    }
+
+    fn read(process: &Process, address: usize, buffer: &mut [u8]) -> bool {
+        unsafe {
+            ReadProcessMemory(
+                process.0,
+                address as *const _,
+                buffer.as_mut_ptr() as *mut _,
+                buffer.len(),
+                None,
+            )
+        }
+        .is_ok()
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use crate::types::DbKeyReport;
+
+    pub fn acquire() -> DbKeyReport {
+        DbKeyReport {
+            ok: false,
+            method: None,
+            candidates_checked: 0,
+            retry_after_secs: 0,
+            detail: "仅 Windows 支持自动获取微信数据库密钥".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypted_page(key: &[u8]) -> Vec<u8> {
+        let mut page: Vec<u8> = (0..PAGE_SIZE).map(|index| (index * 7 % 251) as u8).collect();
+        let salt = page[..SALT_LEN].to_vec();
+        let mut cipher_key = [0u8; KEY_LEN];
+        pbkdf2_sha1(key, &salt, KDF_ITERATIONS, &mut cipher_key);
+        let mac_salt: Vec<u8> = salt.iter().map(|byte| byte ^ MAC_SALT_MASK).collect();
+        let mut mac_key = [0u8; KEY_LEN];
+        pbkdf2_sha1(&cipher_key, &mac_salt, 2, &mut mac_key);
+        let hmac_start = PAGE_SIZE - RESERVE_LEN + SALT_LEN;
+        let mut mac = HmacSha1::new_from_slice(&mac_key).unwrap();
+        mac.update(&page[SALT_LEN..hmac_start]);
+        mac.update(&1u32.to_le_bytes());
+        let digest = mac.finalize().into_bytes();
+        page[hmac_start..hmac_start + HMAC_LEN].copy_from_slice(&digest);
+        page
+    }
+
+    #[test]
+    fn pbkdf2_matches_rfc6070_vectors() {
+        let mut out = [0u8; 20];
+        pbkdf2_sha1(b"password", b"salt", 1, &mut out);
+        assert_eq!(hex(&out), "0c60c80f961f0e71f3a9b524af6012062fe037a6");
+        pbkdf2_sha1(b"password", b"salt", 2, &mut out);
+        assert_eq!(hex(&out), "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+        let mut long = [0u8; 25];
+        pbkdf2_sha1(
+            b"passwordPASSWORDpassword",
+            b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+            4096,
+            &mut long,
+        );
+        assert_eq!(hex(&long), "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038");
+    }
+
+    #[test]
+    fn verifies_key_against_first_page() {
+        let key = [0x5au8; KEY_LEN];
+        let page = encrypted_page(&key);
+        assert!(verify_key(&key, &page));
+        assert!(!verify_key(&[0x5bu8; KEY_LEN], &page));
+        assert!(!verify_key(&key[..16], &page));
+        assert!(!verify_key(&key, &page[..1024]));
+    }
+
+    #[test]
+    fn collects_pointer_length_pairs() {
+        let mut data = Vec::new();
+        for value in [0x7ff0_1000u64, 32, 0x7ff0_1000, 32, 0x20, 32, 0x7ff0_2001, 32, 0x7ff0_3000, 16] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(key_pointer_candidates(&data), vec![0x7ff0_1000]);
+    }
+
+    #[test]
+    fn parses_helper_output() {
+        let key = "AB".repeat(32);
+        assert_eq!(
+            parse_helper_output(&format!("version 3.9\nkey: 0x{}\n", key)),
+            Some(key.to_ascii_lowercase())
+        );
+        assert_eq!(parse_helper_output("key not found"), None);
+    }
+}
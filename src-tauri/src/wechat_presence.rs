@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+/// Status error code while listening waits for WeChat to start again.
+pub const WECHAT_NOT_RUNNING: &str = "WECHAT_NOT_RUNNING";
+/// How often a suspended poller looks for WeChat again.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Presence {
+    pub running: bool,
+    pub focused: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    Poll,
+    Skip,
+    /// WeChat just quit: stop the watchers.
+    Suspend,
+    /// WeChat is back: start the watchers again.
+    Resume,
+}
+
+/// Decides, before every poll, whether the automation should run at all, so a
+/// closed WeChat suspends listening instead of failing every poll.
+#[derive(Debug, Default)]
+pub struct PresenceGate {
+    suspended: bool,
+}
+
+impl PresenceGate {
+    pub fn next(&mut self, presence: Presence, focus_required: bool) -> Gate {
+        match (self.suspended, presence.running) {
+            (false, false) => {
+                self.suspended = true;
+                Gate::Suspend
+            }
+            (true, false) => Gate::Skip,
+            (true, true) => {
+                self.suspended = false;
+                Gate::Resume
+            }
+            (false, true) if focus_required && !presence.focused => Gate::Skip,
+            (false, true) => Gate::Poll,
+        }
+    }
+
+    /// WeChat is running but listening could not start yet (still logging in);
+    /// the next check resumes again.
+    pub fn resume_failed(&mut self) {
+        self.suspended = true;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn detect() -> Presence {
+    use objc::runtime::{Object, BOOL, NO};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::CString;
+
+    let mut presence = Presence {
+        running: false,
+        focused: false,
+    };
+    for bundle_id in ["com.tencent.xinWeChat", "com.tencent.WeChat"] {
+        let Ok(c_bundle) = CString::new(bundle_id) else {
+            continue;
+        };
+        unsafe {
+            let ns_string: *mut Object = msg_send![class!(NSString), alloc];
+            let ns_string: *mut Object =
+                msg_send![ns_string, initWithUTF8String: c_bundle.as_ptr()];
+            let apps: *mut Object = msg_send![
+                class!(NSRunningApplication),
+                runningApplicationsWithBundleIdentifier: ns_string
+            ];
+            let _: () = msg_send![ns_string, release];
+            let count: usize = msg_send![apps, count];
+            for index in 0..count {
+                let app: *mut Object = msg_send![apps, objectAtIndex: index];
+                let active: BOOL = msg_send![app, isActive];
+                presence.running = true;
+                presence.focused |= active != NO;
+            }
+        }
+    }
+    presence
+}
+
+#[cfg(target_os = "windows")]
+pub fn detect() -> Presence {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::ProcessStatus::EnumProcesses;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    const WECHAT_PROCESSES: [&str; 2] = ["wechat.exe", "weixin.exe"];
+
+    let is_wechat = |pid: u32| {
+        let Ok(handle) = (unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) })
+        else {
+            return false;
+        };
+        let mut buffer = [0u16; 260];
+        let mut len = buffer.len() as u32;
+        let named = unsafe {
+            QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+            )
+        };
+        let _ = unsafe { CloseHandle(handle) };
+        named.is_ok() && {
+            let path = String::from_utf16_lossy(&buffer[..len as usize]).to_ascii_lowercase();
+            let name = path.rsplit('\\').next().unwrap_or_default();
+            WECHAT_PROCESSES.contains(&name)
+        }
+    };
+
+    let mut pids = vec![0u32; 4096];
+    let mut needed = 0u32;
+    let listed = unsafe {
+        EnumProcesses(
+            pids.as_mut_ptr(),
+            (pids.len() * std::mem::size_of::<u32>()) as u32,
+            &mut needed,
+        )
+    };
+    if listed.is_err() {
+        // Never suspend listening because the process list is unreadable.
+        return Presence {
+            running: true,
+            focused: true,
+        };
+    }
+    pids.truncate(needed as usize / std::mem::size_of::<u32>());
+    let mut foreground_pid = 0u32;
+    unsafe {
+        GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut foreground_pid as *mut u32))
+    };
+    Presence {
+        running: pids.into_iter().any(|pid| pid != 0 && is_wechat(pid)),
+        focused: foreground_pid != 0 && is_wechat(foreground_pid),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn detect() -> Presence {
+    Presence {
+        running: true,
+        focused: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNNING: Presence = Presence {
+        running: true,
+        focused: false,
+    };
+    const QUIT: Presence = Presence {
+        running: false,
+        focused: false,
+    };
+
+    #[test]
+    fn suspends_once_and_resumes_when_wechat_returns() {
+        let mut gate = PresenceGate::default();
+        assert_eq!(gate.next(RUNNING, false), Gate::Poll);
+        assert_eq!(gate.next(RUNNING, true), Gate::Skip);
+        assert_eq!(gate.next(QUIT, false), Gate::Suspend);
+        assert!(gate.is_suspended());
+        assert_eq!(gate.next(QUIT, false), Gate::Skip);
+        assert_eq!(gate.next(RUNNING, true), Gate::Resume);
+        gate.resume_failed();
+        assert_eq!(gate.next(RUNNING, false), Gate::Resume);
+        assert!(!gate.is_suspended());
+        assert_eq!(gate.next(RUNNING, false), Gate::Poll);
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::{Mutex, MutexGuard};
+
+#[derive(Default)]
+pub struct WriteQueue {
+    exclusive: Mutex<()>,
+    pending: StdMutex<HashMap<String, u32>>,
+}
+
+pub struct WriteTicket {
+    queue: Arc<WriteQueue>,
+    chat_id: String,
+    pub position: u32,
+}
+
+impl WriteQueue {
+    pub fn enqueue(self: &Arc<Self>, chat_id: &str) -> WriteTicket {
+        let mut pending = self.pending.lock().unwrap_or_else(|err| err.into_inner());
+        let position = pending.values().sum();
+        *pending.entry(chat_id.to_string()).or_default() += 1;
+        WriteTicket {
+            queue: Arc::clone(self),
+            chat_id: chat_id.to_string(),
+            position,
+        }
+    }
+}
+
+impl WriteTicket {
+    pub async fn acquire(&self) -> MutexGuard<'_, ()> {
+        self.queue.exclusive.lock().await
+    }
+}
+
+impl Drop for WriteTicket {
+    fn drop(&mut self) {
+        let mut pending = self
+            .queue
+            .pending
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(count) = pending.get_mut(&self.chat_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                pending.remove(&self.chat_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_run_one_at_a_time_in_fifo_order() {
+        let queue = Arc::new(WriteQueue::default());
+        let order = Arc::new(StdMutex::new(Vec::new()));
+
+        let first = queue.enqueue("a");
+        assert_eq!(first.position, 0);
+        let guard = first.acquire().await;
+
+        let mut handles = Vec::new();
+        for (chat_id, expected_position) in [("b", 1), ("a", 2), ("a", 3)] {
+            let ticket = queue.enqueue(chat_id);
+            assert_eq!(ticket.position, expected_position);
+            let order = Arc::clone(&order);
+            handles.push(tokio::spawn(async move {
+                let _guard = ticket.acquire().await;
+                order.lock().unwrap().push(ticket.position);
+            }));
+            tokio::task::yield_now().await;
+        }
+
+        drop(guard);
+        drop(first);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![1, 2, 3]);
+        assert_eq!(queue.enqueue("c").position, 0);
+    }
+}
@@ -4,27 +4,58 @@ import {
   useEffect,
   useMemo,
   useReducer,
+  useRef,
   useState,
 } from "react";
 import { listen } from "@tauri-apps/api/event";
+import { openUrl } from "@tauri-apps/plugin-opener";
 import { Modal } from "antd";
 import "./App.css";
 import type {
+  AgentInfo,
+  AgentLogEntry,
+  AutomationStrategy,
+  CannedResponse,
+  ChatActivityStats,
+  Config,
   DeepseekDiagnostics,
   ErrorPayload,
+  ExperimentReport,
+  FallbackMode,
+  FollowupsUpdated,
+  FrontendSync,
+  InputWriteResult,
+  LowPowerMode,
+  MessageSearchHit,
+  ModelInfo,
+  ProfileSummary,
+  PromptChange,
+  PromptVersion,
+  Readiness,
+  RecentChats,
+  ReplyMode,
+  SafetyWarning,
   Status,
+  StylePreset,
+  SuggestedAction,
   Suggestion,
+  SuggestionReasoning,
+  SuggestionRecord,
+  SuggestionsUnavailable,
   SuggestionsUpdated,
   UiPathsStatus,
 } from "./bindings";
 import { commands } from "./bindings";
+import { formatAgentInfo } from "./utils/agentInfo";
+import { appendAgentLog, formatAgentLog } from "./utils/agentLog";
 import type { ApiKeyStatus } from "./utils/apiKey";
 import { getApiKeyStatusLabel, resolveApiKeySaveOutcome } from "./utils/apiKey";
 import { getApiKeyInputType, getApiKeyToggleLabel } from "./utils/apiKeyVisibility";
-import { summarizeDiagnostics } from "./utils/diagnostics";
-import { getStyleLabel } from "./utils/labels";
+import { formatBalance, summarizeDiagnostics } from "./utils/diagnostics";
+import { getStateLabel, getStyleLabel } from "./utils/labels";
 import {
   DEFAULT_MODELS,
+  formatModelLabel,
   normalizeModels,
   resolveModelSelection,
 } from "./utils/models";
@@ -35,10 +66,29 @@ import {
   mergeListenTargets,
   normalizeListenTargetList,
 } from "./utils/listenTargets";
-import { filterRecentChats, type RecentChat } from "./utils/recentChats";
+import {
+  appendRecentChats,
+  describeRecentChat,
+  describeRecentChats,
+  filterRecentChats,
+  RECENT_CHAT_PAGE,
+  sortRecentChats,
+  type RecentChat,
+} from "./utils/recentChats";
+import { ACCESSIBILITY_SETTINGS_URL, getRecoveryActionLabel } from "./utils/recovery";
 import { normalizeReplyText } from "./utils/reply";
-import { createStatusState, statusReducer } from "./utils/status";
+import { filterCannedResponses, parseTags } from "./utils/cannedResponses";
+import { createStatusState, formatTargetStatus, statusReducer } from "./utils/status";
 import { notify } from "./utils/notify";
+import { formatActivitySummary } from "./utils/activity";
+import { avatarSrc, missingAvatarIds } from "./utils/avatars";
+import { FALLBACK_MODE_LABELS, formatUnavailable } from "./utils/fallback";
+import { LOW_POWER_MODE_LABELS, formatPowerStatus } from "./utils/power";
+import { summarizeLocatorDiagnostics } from "./utils/locator";
+import { formatMaintenanceReport } from "./utils/maintenance";
+import { formatReadiness } from "./utils/readiness";
+import { formatSuggestionRecord } from "./utils/suggestionHistory";
+import { formatPromptChange } from "./utils/promptVersions";
 import { formatUiPathsStatus } from "./utils/uiPathsStatus";
 
 const DEFAULT_STATUS: Status = {
@@ -46,6 +96,22 @@ const DEFAULT_STATUS: Status = {
   platform: "unknown",
   agent_connected: false,
   last_error: "",
+  power: { source: "unknown", low_power: false, adjustments: [] },
+  targets: {},
+  accounts: {},
+  strategy: "agent",
+  error_code: null,
+};
+
+const FRONTEND_HEARTBEAT_MS = 10_000;
+const RECENT_SEARCH_DEBOUNCE_MS = 300;
+
+const AUTOMATION_STRATEGIES: AutomationStrategy[] = ["ui", "db", "agent"];
+
+const STRATEGY_LABELS: Record<AutomationStrategy, string> = {
+  ui: "界面自动化",
+  db: "数据库读取",
+  agent: "Agent",
 };
 
 const LISTEN_KIND_LABELS: Record<ListenTargetKind, string> = {
@@ -62,41 +128,106 @@ function App() {
   );
   const status = statusState.status;
   const [suggestions, setSuggestions] = useState<Suggestion[]>([]);
+  const [composeIds, setComposeIds] = useState<string[]>([]);
+  const [composing, setComposing] = useState(false);
   const [apiKeySet, setApiKeySet] = useState(false);
   const [apiKeyInput, setApiKeyInput] = useState("");
   const [apiKeyStatus, setApiKeyStatus] = useState<ApiKeyStatus>("idle");
   const [apiKeyVisible, setApiKeyVisible] = useState(false);
   const [apiKeyError, setApiKeyError] = useState<string | null>(null);
   const [lastChatId, setLastChatId] = useState<string | null>(null);
+  const [replySource, setReplySource] = useState<SuggestionsUpdated["reply_source"]>(null);
+  const [safetyWarnings, setSafetyWarnings] = useState<SafetyWarning[]>([]);
+  const [bestPick, setBestPick] = useState(false);
+  const [reasoning, setReasoning] = useState<SuggestionReasoning | null>(null);
+  const [followups, setFollowups] = useState<FollowupsUpdated | null>(null);
   const [settingsOpen, setSettingsOpen] = useState(false);
   const [listenModalOpen, setListenModalOpen] = useState(false);
+  const [activityStats, setActivityStats] = useState<ChatActivityStats[]>([]);
+  const [historyOpen, setHistoryOpen] = useState(false);
+  const [seedOpen, setSeedOpen] = useState(false);
+  const [seedChatId, setSeedChatId] = useState("");
+  const [seedText, setSeedText] = useState("");
+  const [seeding, setSeeding] = useState(false);
+  const [historyQuery, setHistoryQuery] = useState("");
+  const [historyHits, setHistoryHits] = useState<MessageSearchHit[]>([]);
+  const [historySearching, setHistorySearching] = useState(false);
+  const [suggestionLogOpen, setSuggestionLogOpen] = useState(false);
+  const [suggestionLog, setSuggestionLog] = useState<SuggestionRecord[]>([]);
   const [listenTargets, setListenTargets] = useState<ListenTarget[]>([]);
   const [recentFilter, setRecentFilter] = useState("");
   const [selectedRecentChatId, setSelectedRecentChatId] = useState("");
   const [listenDirty, setListenDirty] = useState(false);
   const [recentChats, setRecentChats] = useState<RecentChat[]>([]);
+  const [recentSnapshot, setRecentSnapshot] = useState<RecentChats | null>(null);
   const [recentLoading, setRecentLoading] = useState(false);
-  const [models, setModels] = useState<string[]>(DEFAULT_MODELS);
-  const [selectedModel, setSelectedModel] = useState(DEFAULT_MODELS[0]);
+  const [recentLoadingMore, setRecentLoadingMore] = useState(false);
+  const recentQueryRef = useRef("");
+  const [avatars, setAvatars] = useState<Record<string, string | null>>({});
+  const requestedAvatarsRef = useRef(new Set<string>());
+  const [models, setModels] = useState<ModelInfo[]>(DEFAULT_MODELS);
+  const [selectedModel, setSelectedModel] = useState(DEFAULT_MODELS[0].id);
   const [modelLoading, setModelLoading] = useState(false);
   const [diagnostics, setDiagnostics] = useState<DeepseekDiagnostics | null>(null);
+  const [agentLogs, setAgentLogs] = useState<AgentLogEntry[]>([]);
+  const [agentInfo, setAgentInfo] = useState<AgentInfo | null>(null);
   const [diagnosing, setDiagnosing] = useState(false);
   const [diagnosticsError, setDiagnosticsError] = useState<string | null>(null);
   const [uiTreeLoading, setUiTreeLoading] = useState(false);
   const [uiPathsStatus, setUiPathsStatus] = useState<UiPathsStatus | null>(null);
   const [uiPathsStatusError, setUiPathsStatusError] = useState<string | null>(null);
+  const [hideDockIcon, setHideDockIcon] = useState(false);
+  const [lowPowerMode, setLowPowerMode] = useState<LowPowerMode>("auto");
+  const [fallbackMode, setFallbackMode] = useState<FallbackMode>("templates");
+  const [automationTrace, setAutomationTrace] = useState(false);
+  const [piiRedaction, setPiiRedaction] = useState(false);
+  const [exposeReasoning, setExposeReasoning] = useState(false);
+  const [bestPickMode, setBestPickMode] = useState(false);
+  const [followupQuestions, setFollowupQuestions] = useState(false);
+  const [styleLearning, setStyleLearning] = useState(false);
+  const [dailyRequestLimit, setDailyRequestLimit] = useState(0);
+  const [dailyTokenLimit, setDailyTokenLimit] = useState(0);
+  const [stylePresets, setStylePresets] = useState<StylePreset[]>([]);
+  const [readiness, setReadiness] = useState<Readiness | null>(null);
+  const [maintenanceSummary, setMaintenanceSummary] = useState<string | null>(null);
+  const [maintenanceRunning, setMaintenanceRunning] = useState(false);
+  const [profiles, setProfiles] = useState<ProfileSummary[]>([]);
+  const [profileName, setProfileName] = useState("");
+  const [promptVersions, setPromptVersions] = useState<PromptVersion[]>([]);
+  const [promptVersionPick, setPromptVersionPick] = useState("");
+  const [promptDiff, setPromptDiff] = useState<PromptChange[] | null>(null);
+  const [promptExperiment, setPromptExperiment] = useState<Config["prompt_experiment"]>({
+    enabled: false,
+    name: "",
+    variant_a: "",
+    variant_b: "",
+  });
+  const [experimentReport, setExperimentReport] = useState<ExperimentReport | null>(null);
+  const [caBundlePath, setCaBundlePath] = useState("");
+  const [pinCaBundle, setPinCaBundle] = useState(false);
+  const [accounts, setAccounts] = useState("");
+  const [strategies, setStrategies] = useState("ui, agent");
+  const [pauseWhenUnfocused, setPauseWhenUnfocused] = useState(false);
+  const [cannedResponses, setCannedResponses] = useState<CannedResponse[]>([]);
+  const [cannedQuery, setCannedQuery] = useState("");
+  const [cannedTitle, setCannedTitle] = useState("");
+  const [cannedText, setCannedText] = useState("");
+  const [cannedTags, setCannedTags] = useState("");
+  const [recoverableError, setRecoverableError] = useState<ErrorPayload | null>(null);
   const diagnosticsSummary = summarizeDiagnostics(diagnostics, diagnosticsError || undefined);
   const isMacos = status.platform === "macos";
+  const isWindows = status.platform === "windows";
 
   useEffect(() => {
     const bootstrap = async () => {
-      const [statusRes, keyRes, configRes, targetsRes, uiPathsRes] =
+      const [statusRes, keyRes, configRes, targetsRes, uiPathsRes, profilesRes] =
         await Promise.all([
         commands.getStatus(),
         commands.getApiKeyStatus(),
         commands.getConfig(),
         commands.getListenTargets(),
         commands.getWeChatUiPathsStatus(),
+        commands.listProfiles(),
       ]);
       if (statusRes.success && statusRes.data) {
         dispatchStatus({ type: "bootstrap", status: statusRes.data });
@@ -114,17 +245,52 @@ function App() {
       if (configRes.success && configRes.data?.deepseek_model) {
         setSelectedModel(configRes.data.deepseek_model);
       }
+      if (configRes.success && configRes.data) {
+        setHideDockIcon(configRes.data.hide_dock_icon);
+        setLowPowerMode(configRes.data.low_power_mode);
+        setFallbackMode(configRes.data.fallback_mode);
+        setAutomationTrace(configRes.data.automation_trace);
+        setPiiRedaction(configRes.data.pii_redaction_enabled);
+        setExposeReasoning(configRes.data.expose_reasoning);
+        setBestPickMode(configRes.data.best_pick_mode);
+        setFollowupQuestions(configRes.data.followup_questions_enabled);
+        setStyleLearning(configRes.data.style_learning_enabled);
+        setPromptExperiment(configRes.data.prompt_experiment);
+        setCaBundlePath(configRes.data.ca_bundle_path);
+        setPinCaBundle(configRes.data.pin_ca_bundle);
+        setAccounts(configRes.data.accounts.join(", "));
+        setStrategies(configRes.data.automation_strategies.join(", "));
+        setPauseWhenUnfocused(configRes.data.pause_when_wechat_unfocused);
+        setDailyRequestLimit(configRes.data.daily_request_limit);
+        setDailyTokenLimit(configRes.data.daily_token_limit);
+        setStylePresets(configRes.data.style_presets);
+      }
       if (targetsRes.success && Array.isArray(targetsRes.data)) {
         const normalized = normalizeListenTargetList(targetsRes.data);
         setListenTargets(normalized);
         setListenDirty(false);
       }
+      if (profilesRes.success && Array.isArray(profilesRes.data)) {
+        setProfiles(profilesRes.data);
+      }
       if (uiPathsRes.success && uiPathsRes.data) {
         setUiPathsStatus(uiPathsRes.data);
         setUiPathsStatusError(null);
       } else if (statusRes.success && statusRes.data?.platform === "macos") {
         setUiPathsStatusError(uiPathsRes.message || "获取失败");
       }
+      const cannedRes = await commands.listCannedResponses();
+      if (cannedRes.success && Array.isArray(cannedRes.data)) {
+        setCannedResponses(cannedRes.data);
+      }
+      const readinessRes = await commands.getReadiness();
+      if (readinessRes.success && readinessRes.data) {
+        setReadiness(readinessRes.data);
+      }
+      const maintenanceRes = await commands.runMaintenance(true);
+      if (maintenanceRes.success && maintenanceRes.data && maintenanceRes.data.items.length > 0) {
+        setMaintenanceSummary(formatMaintenanceReport(maintenanceRes.data));
+      }
     };
     void bootstrap();
   }, []);
@@ -137,41 +303,187 @@ function App() {
       "suggestions.updated",
       (event) => {
         setSuggestions(event.payload.suggestions);
+        setComposeIds([]);
         setLastChatId(event.payload.chat_id);
+        setReplySource(event.payload.reply_source);
+        setSafetyWarnings(event.payload.warnings);
+        setBestPick(event.payload.best_pick);
+        setReasoning(null);
+        setFollowups(null);
+      },
+    );
+    const unlistenReasoning = listen<SuggestionReasoning>("suggestion.reasoning", (event) => {
+      setReasoning(event.payload);
+    });
+    const unlistenFollowups = listen<FollowupsUpdated>("followups.updated", (event) => {
+      setFollowups(event.payload);
+    });
+    const unlistenUnavailable = listen<SuggestionsUnavailable>(
+      "suggestions.unavailable",
+      (event) => {
+        notify.warning("未生成回复建议", { detail: formatUnavailable(event.payload) });
       },
     );
+    const unlistenReadiness = listen<Readiness>("readiness.changed", (event) => {
+      setReadiness(event.payload);
+    });
     const unlistenError = listen<ErrorPayload>("error.raised", (event) => {
       notify.error("发生错误", { detail: event.payload.message });
+      if (event.payload.suggested_action) {
+        setRecoverableError(event.payload);
+      }
+    });
+    const unlistenInput = listen<InputWriteResult>("input.result", (event) => {
+      if (event.payload.status === "queued") {
+        notify.info(`写入排队中，前方还有 ${event.payload.queue_position} 条`);
+      } else if (event.payload.status === "retrying") {
+        notify.info("写入失败，正在重试", { detail: event.payload.message });
+      }
+    });
+    const unlistenConfig = listen<Config>("config.changed", (event) => {
+      setSelectedModel(event.payload.deepseek_model);
+      setHideDockIcon(event.payload.hide_dock_icon);
+      setLowPowerMode(event.payload.low_power_mode);
+      setFallbackMode(event.payload.fallback_mode);
+      setAutomationTrace(event.payload.automation_trace);
+      setPiiRedaction(event.payload.pii_redaction_enabled);
+      setExposeReasoning(event.payload.expose_reasoning);
+      setBestPickMode(event.payload.best_pick_mode);
+      setFollowupQuestions(event.payload.followup_questions_enabled);
+      setStyleLearning(event.payload.style_learning_enabled);
+      setPromptExperiment(event.payload.prompt_experiment);
+      setCaBundlePath(event.payload.ca_bundle_path);
+      setPinCaBundle(event.payload.pin_ca_bundle);
+      setAccounts(event.payload.accounts.join(", "));
+      setStrategies(event.payload.automation_strategies.join(", "));
+      setPauseWhenUnfocused(event.payload.pause_when_wechat_unfocused);
+      setDailyRequestLimit(event.payload.daily_request_limit);
+      setDailyTokenLimit(event.payload.daily_token_limit);
+      setStylePresets(event.payload.style_presets);
+      void commands.listPromptVersions().then((res) => {
+        if (res.success && res.data) {
+          setPromptVersions(res.data);
+        }
+      });
+    });
+    const unlistenChats = listen<RecentChats>("chats.updated", (event) => {
+      // Background refreshes carry the unfiltered first page only.
+      if (recentQueryRef.current) {
+        return;
+      }
+      setRecentSnapshot(event.payload);
+      setRecentChats(event.payload.chats as RecentChat[]);
+    });
+    const unlistenAgentLog = listen<AgentLogEntry>("agent.log", (event) => {
+      setAgentLogs((prev) => appendAgentLog(prev, event.payload));
     });
 
     return () => {
       void unlistenStatus.then((fn) => fn());
       void unlistenSuggestions.then((fn) => fn());
+      void unlistenReasoning.then((fn) => fn());
+      void unlistenFollowups.then((fn) => fn());
+      void unlistenUnavailable.then((fn) => fn());
+      void unlistenReadiness.then((fn) => fn());
       void unlistenError.then((fn) => fn());
+      void unlistenInput.then((fn) => fn());
+      void unlistenConfig.then((fn) => fn());
+      void unlistenChats.then((fn) => fn());
+      void unlistenAgentLog.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    const applySync = (sync: FrontendSync) => {
+      dispatchStatus({ type: "bootstrap", status: sync.status });
+      if (sync.latest_suggestions) {
+        setSuggestions(sync.latest_suggestions.suggestions);
+        setComposeIds([]);
+        setLastChatId(sync.latest_suggestions.chat_id);
+        setReplySource(sync.latest_suggestions.reply_source);
+        setSafetyWarnings(sync.latest_suggestions.warnings);
+        setBestPick(sync.latest_suggestions.best_pick);
+      }
+      const missed = sync.missed_suggestions.length + sync.missed_auto_replies.length;
+      if (missed > 0) {
+        notify.info(`后台运行期间有 ${missed} 条更新`, {
+          detail: `建议 ${sync.missed_suggestions.length} 组，自动回复 ${sync.missed_auto_replies.length} 条`,
+        });
+      }
     };
+    const beat = async () => {
+      const res = await commands.frontendHeartbeat();
+      if (res.success && res.data) {
+        applySync(res.data);
+      }
+    };
+    void beat();
+    const timer = window.setInterval(() => void beat(), FRONTEND_HEARTBEAT_MS);
+    return () => window.clearInterval(timer);
   }, []);
 
-  const refreshRecentChats = useCallback(async () => {
+  const refreshRecentChats = useCallback(async (forceRefresh = false, query = "") => {
+    const normalized = query.trim();
+    recentQueryRef.current = normalized;
     setRecentLoading(true);
     try {
-      const res = await commands.listRecentChats();
-      if (res.success && Array.isArray(res.data)) {
-        setRecentChats(res.data as RecentChat[]);
+      // Searches are never cached, so only the plain list needs an explicit refresh.
+      const res =
+        forceRefresh && !normalized
+          ? await commands.refreshChats(RECENT_CHAT_PAGE)
+          : await commands.listChats(0, RECENT_CHAT_PAGE, normalized);
+      if (recentQueryRef.current !== normalized) {
+        return;
+      }
+      if (res.success && res.data) {
+        setRecentSnapshot(res.data);
+        setRecentChats(res.data.chats as RecentChat[]);
       } else {
         notify.error("会话列表获取失败", { detail: res.message });
       }
     } catch (err) {
       notify.error("会话列表获取失败");
     }
-    setRecentLoading(false);
+    if (recentQueryRef.current === normalized) {
+      setRecentLoading(false);
+    }
   }, []);
 
+  const loadMoreRecentChats = useCallback(async () => {
+    const query = recentQueryRef.current;
+    setRecentLoadingMore(true);
+    try {
+      const res = await commands.listChats(recentChats.length, RECENT_CHAT_PAGE, query);
+      if (recentQueryRef.current === query && res.success && res.data) {
+        const page = res.data;
+        setRecentSnapshot((prev) => (prev ? { ...prev, has_more: page.has_more } : page));
+        setRecentChats((prev) => appendRecentChats(prev, page.chats as RecentChat[]));
+      } else if (!res.success) {
+        notify.error("会话列表获取失败", { detail: res.message });
+      }
+    } catch (err) {
+      notify.error("会话列表获取失败");
+    }
+    setRecentLoadingMore(false);
+  }, [recentChats.length]);
+
+  useEffect(() => {
+    if (!listenModalOpen) {
+      return;
+    }
+    const delay = recentFilter.trim() ? RECENT_SEARCH_DEBOUNCE_MS : 0;
+    const timer = window.setTimeout(() => void refreshRecentChats(false, recentFilter), delay);
+    return () => window.clearTimeout(timer);
+  }, [listenModalOpen, recentFilter, refreshRecentChats]);
+
   useEffect(() => {
     if (!listenModalOpen) {
       return;
     }
-    void refreshRecentChats();
-  }, [listenModalOpen, refreshRecentChats]);
+    void commands.getChatActivityStats().then((res) => {
+      setActivityStats(res.success && Array.isArray(res.data) ? res.data : []);
+    });
+  }, [listenModalOpen]);
 
   useEffect(() => {
     if (!listenModalOpen) {
@@ -182,9 +494,34 @@ function App() {
   }, [listenModalOpen]);
 
   const filteredRecentChats = useMemo(
-    () => filterRecentChats(recentChats, recentFilter),
+    () => filterRecentChats(sortRecentChats(recentChats), recentFilter),
     [recentChats, recentFilter],
   );
+  const recentFreshness = recentSnapshot
+    ? describeRecentChats(recentSnapshot, Date.now() / 1000)
+    : "";
+
+  const loadAvatars = useCallback((chatIds: string[]) => {
+    for (const chatId of missingAvatarIds(chatIds, requestedAvatarsRef.current)) {
+      requestedAvatarsRef.current.add(chatId);
+      void commands.getChatAvatar(chatId).then((res) => {
+        const src = res.success ? avatarSrc(res.data) : null;
+        setAvatars((prev) => ({ ...prev, [chatId]: src }));
+      });
+    }
+  }, []);
+
+  useEffect(() => {
+    if (listenModalOpen) {
+      loadAvatars(filteredRecentChats.map((chat) => chat.chat_id));
+    }
+  }, [filteredRecentChats, listenModalOpen, loadAvatars]);
+
+  useEffect(() => {
+    if (lastChatId) {
+      loadAvatars([lastChatId]);
+    }
+  }, [lastChatId, loadAvatars]);
 
   useEffect(() => {
     if (!selectedRecentChatId) {
@@ -268,6 +605,16 @@ function App() {
     setListenDirty(true);
   }, []);
 
+  const handleUpdateTarget = useCallback(
+    (name: string, patch: Partial<ListenTarget>) => {
+      setListenTargets((prev) =>
+        prev.map((item) => (item.name === name ? { ...item, ...patch } : item)),
+      );
+      setListenDirty(true);
+    },
+    [],
+  );
+
   const handleSaveTargets = useCallback(async () => {
     void saveListenTargets(listenTargets, true);
   }, [listenTargets, saveListenTargets]);
@@ -304,7 +651,7 @@ function App() {
   }, []);
 
   const handleInsertSuggestion = useCallback(
-    async (suggestion: Suggestion) => {
+    async (suggestion: Suggestion, replyMode: ReplyMode = "plain") => {
       if (!lastChatId) {
         notify.warning("暂无可写入的聊天");
         return;
@@ -314,7 +661,39 @@ function App() {
         notify.warning("回复内容不可用", { detail: normalized.reason });
         return;
       }
-      const res = await commands.writeSuggestion(lastChatId, normalized.text);
+      const res = await commands.writeSuggestion(lastChatId, normalized.text, replyMode);
+      if (res.success) {
+        notify.success("已写入输入框");
+      } else {
+        notify.error("写入失败", { detail: res.message });
+      }
+    },
+    [lastChatId],
+  );
+
+  const handleInsertCanned = useCallback(
+    async (response: CannedResponse) => {
+      if (!lastChatId) {
+        notify.warning("暂无可写入的聊天");
+        return;
+      }
+      const res = await commands.writeSuggestion(lastChatId, response.text, "plain");
+      if (res.success) {
+        notify.success("已写入输入框");
+      } else {
+        notify.error("写入失败", { detail: res.message });
+      }
+    },
+    [lastChatId],
+  );
+
+  const handleInsertFollowup = useCallback(
+    async (question: string) => {
+      if (!lastChatId) {
+        notify.warning("暂无可写入的聊天");
+        return;
+      }
+      const res = await commands.writeSuggestion(lastChatId, question, "plain");
       if (res.success) {
         notify.success("已写入输入框");
       } else {
@@ -324,6 +703,109 @@ function App() {
     [lastChatId],
   );
 
+  const handleCreateCanned = useCallback(async () => {
+    const res = await commands.createCannedResponse(
+      cannedTitle,
+      cannedText,
+      parseTags(cannedTags),
+    );
+    if (res.success && res.data) {
+      const created = res.data;
+      setCannedResponses((prev) => [...prev, created]);
+      setCannedTitle("");
+      setCannedText("");
+      setCannedTags("");
+      notify.success("已保存快捷回复");
+    } else {
+      notify.error("保存快捷回复失败", { detail: res.message });
+    }
+  }, [cannedTitle, cannedText, cannedTags]);
+
+  const handleDeleteCanned = useCallback(async (id: string) => {
+    const res = await commands.deleteCannedResponse(id);
+    if (res.success) {
+      setCannedResponses((prev) => prev.filter((item) => item.id !== id));
+    } else {
+      notify.error("删除快捷回复失败", { detail: res.message });
+    }
+  }, []);
+
+  const handleToggleCompose = useCallback((id: string) => {
+    setComposeIds((prev) =>
+      prev.includes(id) ? prev.filter((item) => item !== id) : [...prev, id],
+    );
+  }, []);
+
+  const handleComposeReply = useCallback(async () => {
+    if (!lastChatId) {
+      notify.warning("暂无可写入的聊天");
+      return;
+    }
+    const selected = composeIds
+      .map((id) => suggestions.find((item) => item.id === id))
+      .filter((item): item is Suggestion => Boolean(item));
+    if (selected.length < 2) {
+      notify.warning("请至少选择两条建议");
+      return;
+    }
+    setComposing(true);
+    const res = await commands.composeReply(
+      lastChatId,
+      selected.map((item) => item.text),
+      selected[0].style,
+    );
+    setComposing(false);
+    if (res.success) {
+      notify.success("已合并为新建议");
+    } else {
+      notify.error("合并失败", { detail: res.message });
+    }
+  }, [composeIds, lastChatId, suggestions]);
+
+  const handleSeedContext = useCallback(async () => {
+    if (!seedChatId.trim() || !seedText.trim()) {
+      notify.warning("请填写聊天对象和聊天记录");
+      return;
+    }
+    setSeeding(true);
+    const res = await commands.seedContext(seedChatId.trim(), seedText);
+    setSeeding(false);
+    if (res.success && res.data) {
+      notify.success("已导入聊天记录", {
+        detail: `识别 ${res.data.parsed} 条，保留 ${res.data.kept} 条`,
+      });
+      setSeedText("");
+      setSeedOpen(false);
+    } else {
+      notify.error("导入失败", { detail: res.message });
+    }
+  }, [seedChatId, seedText]);
+
+  const handleSearchHistory = useCallback(async () => {
+    if (!historyQuery.trim()) {
+      notify.warning("请输入搜索内容");
+      return;
+    }
+    setHistorySearching(true);
+    const res = await commands.searchMessages(historyQuery);
+    setHistorySearching(false);
+    if (res.success && Array.isArray(res.data)) {
+      setHistoryHits(res.data);
+    } else {
+      notify.error("搜索失败", { detail: res.message });
+    }
+  }, [historyQuery]);
+
+  const handleOpenSuggestionLog = useCallback(async () => {
+    setSuggestionLogOpen(true);
+    const res = await commands.getSuggestionHistory();
+    if (res.success && Array.isArray(res.data)) {
+      setSuggestionLog(res.data);
+    } else {
+      notify.error("建议记录获取失败", { detail: res.message });
+    }
+  }, []);
+
   const handleSaveApiKey = useCallback(async () => {
     if (!apiKeyInput.trim()) {
       notify.warning("请输入 API 密钥");
@@ -393,6 +875,20 @@ function App() {
     }
   }, []);
 
+  const handleCheckBalance = useCallback(async () => {
+    const res = await commands.getDeepseekBalance();
+    if (!res.success || !res.data) {
+      notify.error("查询余额失败", { detail: res.message });
+      return;
+    }
+    const text = formatBalance(res.data);
+    if (res.data.is_available) {
+      notify.success(text);
+    } else {
+      notify.warning(text);
+    }
+  }, []);
+
   const handleDiagnose = useCallback(async () => {
     const trimmed = apiKeyInput.trim();
     if (!trimmed && !apiKeySet) {
@@ -443,65 +939,544 @@ function App() {
     [selectedModel],
   );
 
-  const refreshUiPathsStatus = useCallback(async (showError: boolean) => {
-    const res = await commands.getWeChatUiPathsStatus();
-    if (res.success && res.data) {
-      setUiPathsStatus(res.data);
-      setUiPathsStatusError(null);
+  const handleHideDockIconChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      setHideDockIcon(next);
+      const res = await commands.setHideDockIcon(next);
+      if (!res.success) {
+        notify.error("Dock 图标设置失败", { detail: res.message });
+        setHideDockIcon(!next);
+      }
+    },
+    [],
+  );
+
+  const handleLowPowerModeChange = useCallback(
+    async (event: ChangeEvent<HTMLSelectElement>) => {
+      const next = event.target.value as LowPowerMode;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("低功耗设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, low_power_mode: next });
+      if (!res.success) {
+        notify.error("低功耗设置失败", { detail: res.message });
+        return;
+      }
+      setLowPowerMode(next);
+    },
+    [],
+  );
+
+  const handleFallbackModeChange = useCallback(
+    async (event: ChangeEvent<HTMLSelectElement>) => {
+      const next = event.target.value as FallbackMode;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("失败策略设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, fallback_mode: next });
+      if (!res.success) {
+        notify.error("失败策略设置失败", { detail: res.message });
+        return;
+      }
+      setFallbackMode(next);
+    },
+    [],
+  );
+
+  const handleDailyLimitSave = useCallback(async () => {
+    const configRes = await commands.getConfig();
+    if (!configRes.success || !configRes.data) {
+      notify.error("用量上限设置失败", { detail: configRes.message });
       return;
     }
-    if (showError) {
-      setUiPathsStatusError(res.message || "获取失败");
+    const res = await commands.setConfig({
+      ...configRes.data,
+      daily_request_limit: Math.max(0, Math.floor(dailyRequestLimit)),
+      daily_token_limit: Math.max(0, Math.floor(dailyTokenLimit)),
+    });
+    if (!res.success) {
+      notify.error("用量上限设置失败", { detail: res.message });
+      return;
+    }
+    notify.success("用量上限已保存");
+  }, [dailyRequestLimit, dailyTokenLimit]);
+
+  const handleAutomationTraceChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("自动化追踪设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, automation_trace: next });
+      if (!res.success) {
+        notify.error("自动化追踪设置失败", { detail: res.message });
+        return;
+      }
+      setAutomationTrace(next);
+    },
+    [],
+  );
+
+  const handlePiiRedactionChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("隐私脱敏设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, pii_redaction_enabled: next });
+      if (!res.success) {
+        notify.error("隐私脱敏设置失败", { detail: res.message });
+        return;
+      }
+      setPiiRedaction(next);
+    },
+    [],
+  );
+
+  const handleExposeReasoningChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("推理过程设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, expose_reasoning: next });
+      if (!res.success) {
+        notify.error("推理过程设置失败", { detail: res.message });
+        return;
+      }
+      setExposeReasoning(next);
+    },
+    [],
+  );
+
+  const handleBestPickModeChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("最佳建议模式设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, best_pick_mode: next });
+      if (!res.success) {
+        notify.error("最佳建议模式设置失败", { detail: res.message });
+        return;
+      }
+      setBestPickMode(next);
+    },
+    [],
+  );
+
+  const handleFollowupQuestionsChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("澄清问题设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({
+        ...configRes.data,
+        followup_questions_enabled: next,
+      });
+      if (!res.success) {
+        notify.error("澄清问题设置失败", { detail: res.message });
+        return;
+      }
+      setFollowupQuestions(next);
+    },
+    [],
+  );
+
+  const handleStyleLearningChange = useCallback(async (event: ChangeEvent<HTMLInputElement>) => {
+    const next = event.target.checked;
+    const configRes = await commands.getConfig();
+    if (!configRes.success || !configRes.data) {
+      notify.error("语气学习设置失败", { detail: configRes.message });
+      return;
+    }
+    const res = await commands.setConfig({ ...configRes.data, style_learning_enabled: next });
+    if (!res.success) {
+      notify.error("语气学习设置失败", { detail: res.message });
+      return;
     }
+    setStyleLearning(next);
   }, []);
 
-  const handleCaptureUiTree = useCallback(async () => {
-    if (!isMacos) {
-      notify.warning("仅支持 macOS");
+  const handleSavePromptExperiment = useCallback(async () => {
+    const configRes = await commands.getConfig();
+    if (!configRes.success || !configRes.data) {
+      notify.error("保存提示词实验失败", { detail: configRes.message });
       return;
     }
-    setUiTreeLoading(true);
-    try {
-      const res = await commands.learnWeChatUiPaths(12);
-      if (res.success && res.data) {
-        const count = res.data.written_files.length;
-        notify.success("已保存微信 UI 树", {
-          detail: count > 0 ? `已写入 ${count} 个文件` : undefined,
-        });
-        await refreshUiPathsStatus(true);
-      } else {
-        notify.error("获取微信 UI 树失败", { detail: res.message });
-      }
-    } catch (err) {
-      notify.error("获取微信 UI 树失败", { detail: err });
-    } finally {
-      setUiTreeLoading(false);
+    const res = await commands.setConfig({ ...configRes.data, prompt_experiment: promptExperiment });
+    if (!res.success) {
+      notify.error("保存提示词实验失败", { detail: res.message });
+      return;
     }
-  }, [isMacos, refreshUiPathsStatus]);
+    setExperimentReport(null);
+    notify.success(promptExperiment.enabled ? "提示词实验已开启" : "提示词实验已保存");
+  }, [promptExperiment]);
 
-  const uiTreeStatusText = useMemo(() => {
-    if (!isMacos) {
-      return "仅支持 macOS";
+  const handleSaveTlsSettings = useCallback(async () => {
+    const configRes = await commands.getConfig();
+    if (!configRes.success || !configRes.data) {
+      notify.error("保存证书设置失败", { detail: configRes.message });
+      return;
     }
-    if (uiPathsStatusError) {
-      return "获取失败";
+    const res = await commands.setConfig({
+      ...configRes.data,
+      ca_bundle_path: caBundlePath,
+      pin_ca_bundle: pinCaBundle,
+    });
+    if (!res.success) {
+      notify.error("保存证书设置失败", { detail: res.message });
+      return;
     }
-    return formatUiPathsStatus(uiPathsStatus);
-  }, [isMacos, uiPathsStatus, uiPathsStatusError]);
+    notify.success(caBundlePath.trim() ? "自定义 CA 证书已生效" : "已恢复系统证书");
+  }, [caBundlePath, pinCaBundle]);
 
-  return (
-    <main className="app">
-      <header className="topbar">
-        <div className="control-group">
-          <button
-            className="primary"
-            onClick={handleStart}
-            disabled={status.state === "listening" || status.state === "generating"}
-          >
-            开始监听
-          </button>
-          <button className="ghost" onClick={handleStop} disabled={status.state === "idle"}>
-            停止
+  const handleSaveAccounts = useCallback(async () => {
+    const configRes = await commands.getConfig();
+    if (!configRes.success || !configRes.data) {
+      notify.error("保存多账号设置失败", { detail: configRes.message });
+      return;
+    }
+    const next = accounts
+      .split(/[,，\s]+/)
+      .map((item) => item.trim())
+      .filter(Boolean);
+    const res = await commands.setConfig({ ...configRes.data, accounts: next });
+    if (!res.success) {
+      notify.error("保存多账号设置失败", { detail: res.message });
+      return;
+    }
+    notify.success(next.length ? `已配置 ${next.length} 个额外账号` : "仅使用默认账号");
+  }, [accounts]);
+
+  const handleSaveStrategies = useCallback(async () => {
+    const configRes = await commands.getConfig();
+    if (!configRes.success || !configRes.data) {
+      notify.error("保存自动化方式失败", { detail: configRes.message });
+      return;
+    }
+    const next = strategies
+      .split(/[,，\s]+/)
+      .map((item) => item.trim().toLowerCase())
+      .filter((item): item is AutomationStrategy =>
+        AUTOMATION_STRATEGIES.includes(item as AutomationStrategy),
+      );
+    const res = await commands.setConfig({ ...configRes.data, automation_strategies: next });
+    if (!res.success) {
+      notify.error("保存自动化方式失败", { detail: res.message });
+      return;
+    }
+    notify.success(`自动化顺序：${next.map((item) => STRATEGY_LABELS[item]).join(" → ")}`);
+  }, [strategies]);
+
+  const handlePauseWhenUnfocusedChange = useCallback(
+    async (event: ChangeEvent<HTMLInputElement>) => {
+      const next = event.target.checked;
+      const configRes = await commands.getConfig();
+      if (!configRes.success || !configRes.data) {
+        notify.error("前台读取设置失败", { detail: configRes.message });
+        return;
+      }
+      const res = await commands.setConfig({ ...configRes.data, pause_when_wechat_unfocused: next });
+      if (!res.success) {
+        notify.error("前台读取设置失败", { detail: res.message });
+        return;
+      }
+      setPauseWhenUnfocused(next);
+    },
+    [],
+  );
+
+  const handleLoadExperimentReport = useCallback(async () => {
+    const res = await commands.getExperimentReport();
+    if (!res.success || !res.data) {
+      notify.error("获取实验结果失败", { detail: res.message });
+      return;
+    }
+    setExperimentReport(res.data);
+  }, []);
+
+  const handleRunMaintenance = useCallback(async (dryRun: boolean) => {
+    setMaintenanceRunning(true);
+    const res = await commands.runMaintenance(dryRun);
+    setMaintenanceRunning(false);
+    if (res.success && res.data) {
+      setMaintenanceSummary(formatMaintenanceReport(res.data));
+    } else {
+      notify.error("存储清理失败", { detail: res.message });
+    }
+  }, []);
+
+  const handleConfirmMaintenance = useCallback(async () => {
+    setMaintenanceRunning(true);
+    const preview = await commands.runMaintenance(true);
+    setMaintenanceRunning(false);
+    if (!preview.success || !preview.data) {
+      notify.error("存储清理失败", { detail: preview.message });
+      return;
+    }
+    setMaintenanceSummary(formatMaintenanceReport(preview.data));
+    if (preview.data.items.length === 0) {
+      return;
+    }
+    Modal.confirm({
+      title: "确认清理",
+      content: `${formatMaintenanceReport(preview.data)}，删除后无法恢复。`,
+      okText: "清理",
+      cancelText: "取消",
+      onOk: () => handleRunMaintenance(false),
+    });
+  }, [handleRunMaintenance]);
+
+  const handleExportAutomationTrace = useCallback(async () => {
+    const res = await commands.exportAutomationTrace();
+    if (res.success && res.data) {
+      notify.success(`已导出 ${res.data.entries} 条自动化记录`, {
+        detail: res.data.saved_to ?? undefined,
+      });
+    } else {
+      notify.error("导出自动化追踪失败", { detail: res.message });
+    }
+  }, []);
+
+  const handleLocatorDiagnostics = useCallback(async () => {
+    const res = await commands.getLocatorDiagnostics();
+    if (!res.success || !res.data) {
+      notify.error("获取定位诊断失败", { detail: res.message });
+      return;
+    }
+    const summary = summarizeLocatorDiagnostics(res.data);
+    if (summary.ok) {
+      notify.success("控件定位正常", { detail: summary.message });
+    } else {
+      notify.warning("控件定位依赖名称或位置，可能受主题影响", { detail: summary.message });
+    }
+  }, []);
+
+  const handleProfileChange = useCallback(
+    async (event: ChangeEvent<HTMLSelectElement>) => {
+      const name = event.target.value;
+      if (!name) {
+        return;
+      }
+      const res = await commands.switchProfile(name);
+      if (!res.success || !res.data) {
+        notify.error("切换配置方案失败", { detail: res.message });
+        return;
+      }
+      setProfiles(res.data);
+      const targetsRes = await commands.getListenTargets();
+      if (targetsRes.success && Array.isArray(targetsRes.data)) {
+        setListenTargets(normalizeListenTargetList(targetsRes.data));
+        setListenDirty(false);
+      }
+      notify.success(`已切换到配置方案「${name}」`);
+    },
+    [],
+  );
+
+  const handleSaveProfile = useCallback(async () => {
+    const name = profileName.trim();
+    if (!name) {
+      notify.warning("请输入方案名称");
+      return;
+    }
+    const res = await commands.saveProfile(name);
+    if (res.success && res.data) {
+      setProfiles(res.data);
+      setProfileName("");
+      notify.success(`已保存配置方案「${name}」`);
+    } else {
+      notify.error("保存配置方案失败", { detail: res.message });
+    }
+  }, [profileName]);
+
+  const loadPromptVersions = useCallback(async () => {
+    const res = await commands.listPromptVersions();
+    if (res.success && res.data) {
+      setPromptVersions(res.data);
+    }
+  }, []);
+
+  useEffect(() => {
+    void loadPromptVersions();
+  }, [loadPromptVersions]);
+
+  const handleDiffPromptVersion = useCallback(async () => {
+    const current = promptVersions[0];
+    const version = Number(promptVersionPick);
+    if (!current || !version) {
+      return;
+    }
+    const res = await commands.diffPromptVersions(version, current.version);
+    if (!res.success || !res.data) {
+      notify.error("对比提示词版本失败", { detail: res.message });
+      return;
+    }
+    setPromptDiff(res.data);
+  }, [promptVersions, promptVersionPick]);
+
+  const handleRollbackPromptVersion = useCallback(async () => {
+    const version = Number(promptVersionPick);
+    if (!version) {
+      return;
+    }
+    const res = await commands.rollbackPromptVersion(version);
+    if (!res.success) {
+      notify.error("回滚提示词失败", { detail: res.message });
+      return;
+    }
+    notify.success(`已回滚到 v${version}`, { detail: `当前版本 v${res.data}` });
+    setPromptVersionPick("");
+    setPromptDiff(null);
+    await loadPromptVersions();
+  }, [promptVersionPick, loadPromptVersions]);
+
+  const handleDeleteProfile = useCallback(async () => {
+    const active = profiles.find((profile) => profile.active);
+    if (!active) {
+      return;
+    }
+    const res = await commands.deleteProfile(active.name);
+    if (res.success && res.data) {
+      setProfiles(res.data);
+      notify.success(`已删除配置方案「${active.name}」`);
+    } else {
+      notify.error("删除配置方案失败", { detail: res.message });
+    }
+  }, [profiles]);
+
+  const refreshUiPathsStatus = useCallback(async (showError: boolean) => {
+    const res = await commands.getWeChatUiPathsStatus();
+    if (res.success && res.data) {
+      setUiPathsStatus(res.data);
+      setUiPathsStatusError(null);
+      return;
+    }
+    if (showError) {
+      setUiPathsStatusError(res.message || "获取失败");
+    }
+  }, []);
+
+  const handleCaptureUiTree = useCallback(async () => {
+    if (!isMacos) {
+      notify.warning("仅支持 macOS");
+      return;
+    }
+    setUiTreeLoading(true);
+    try {
+      const res = await commands.learnWeChatUiPaths(12);
+      if (res.success && res.data) {
+        const count = res.data.written_files.length;
+        notify.success("已保存微信 UI 树", {
+          detail: count > 0 ? `已写入 ${count} 个文件` : undefined,
+        });
+        await refreshUiPathsStatus(true);
+      } else {
+        notify.error("获取微信 UI 树失败", { detail: res.message });
+      }
+    } catch (err) {
+      notify.error("获取微信 UI 树失败", { detail: err });
+    } finally {
+      setUiTreeLoading(false);
+    }
+  }, [isMacos, refreshUiPathsStatus]);
+
+  const handleRestartAgent = useCallback(async () => {
+    const res = await commands.restartAgent();
+    if (res.success) {
+      notify.success("Agent 已重启");
+    } else {
+      notify.error("重启 Agent 失败", { detail: res.message });
+    }
+  }, []);
+
+  const handleShowAgentInfo = useCallback(async () => {
+    const res = await commands.getAgentInfo();
+    if (res.success && res.data) {
+      setAgentInfo(res.data);
+    } else {
+      setAgentInfo(null);
+      notify.error("获取 Agent 信息失败", { detail: res.message });
+    }
+  }, []);
+
+  const handleStopAgent = useCallback(async () => {
+    const res = await commands.stopAgent();
+    if (res.success) {
+      notify.info("Agent 已停止，开始监听时会自动启动");
+    } else {
+      notify.error("停止 Agent 失败", { detail: res.message });
+    }
+  }, []);
+
+  const handleRecoveryAction = useCallback(
+    async (action: SuggestedAction) => {
+      setRecoverableError(null);
+      switch (action) {
+        case "retry":
+          await handleStart();
+          break;
+        case "restart_agent":
+          await handleRestartAgent();
+          break;
+        case "open_settings":
+          setSettingsOpen(true);
+          break;
+        case "grant_permission":
+          try {
+            await openUrl(ACCESSIBILITY_SETTINGS_URL);
+          } catch (err) {
+            notify.error("无法打开系统设置", { detail: err });
+          }
+          break;
+        case "relearn_paths":
+          await handleCaptureUiTree();
+          break;
+      }
+    },
+    [handleStart, handleRestartAgent, handleCaptureUiTree],
+  );
+
+  const uiTreeStatusText = useMemo(() => {
+    if (!isMacos) {
+      return "仅支持 macOS";
+    }
+    if (uiPathsStatusError) {
+      return "获取失败";
+    }
+    return formatUiPathsStatus(uiPathsStatus);
+  }, [isMacos, uiPathsStatus, uiPathsStatusError]);
+
+  return (
+    <main className="app">
+      <header className="topbar">
+        <div className="control-group">
+          <button
+            className="primary"
+            onClick={handleStart}
+            disabled={status.state === "listening" || status.state === "generating"}
+          >
+            开始监听
+          </button>
+          <button className="ghost" onClick={handleStop} disabled={status.state === "idle"}>
+            停止
           </button>
         </div>
         <div className="top-actions">
@@ -513,6 +1488,15 @@ function App() {
             {uiTreeLoading ? "获取中..." : "获取 UI 树"}
           </button>
           <span className="ui-tree-status">{uiTreeStatusText}</span>
+          <button className="ghost" onClick={() => setHistoryOpen(true)}>
+            历史消息
+          </button>
+          <button className="ghost" onClick={handleOpenSuggestionLog}>
+            建议记录
+          </button>
+          <button className="ghost" onClick={() => setSeedOpen(true)}>
+            导入记录
+          </button>
           <button className="ghost" onClick={() => setListenModalOpen(true)}>
             监听对象
           </button>
@@ -535,28 +1519,186 @@ function App() {
         </div>
       </header>
 
+      {recoverableError?.suggested_action ? (
+        <div className="error-banner">
+          <span>{recoverableError.message}</span>
+          <div className="error-banner-actions">
+            <button
+              className="small"
+              onClick={() => {
+                if (recoverableError.suggested_action) {
+                  void handleRecoveryAction(recoverableError.suggested_action);
+                }
+              }}
+            >
+              {getRecoveryActionLabel(recoverableError.suggested_action)}
+            </button>
+            <button className="ghost small" onClick={() => setRecoverableError(null)}>
+              忽略
+            </button>
+          </div>
+        </div>
+      ) : null}
+
+      {readiness && !readiness.ready ? (
+        <div className="error-banner">
+          <span>{formatReadiness(readiness)}</span>
+        </div>
+      ) : null}
+
+      {status.error_code === "WECHAT_NOT_RUNNING" ? (
+        <div className="error-banner">
+          <span>{status.last_error}</span>
+        </div>
+      ) : null}
+
       <section className="grid">
         <div className="panel suggestions">
           <div className="panel-header">
             <h2>回复建议</h2>
-            <span>{suggestions.length} 条</span>
+            {composeIds.length >= 2 ? (
+              <button className="ghost small" onClick={handleComposeReply} disabled={composing}>
+                {composing ? "合并中..." : `合并所选 (${composeIds.length})`}
+              </button>
+            ) : (
+              <span>{suggestions.length} 条</span>
+            )}
           </div>
           {suggestions.length === 0 ? (
             <div className="empty">等待新消息触发建议</div>
           ) : (
             <div className="suggestion-list">
+              {lastChatId ? (
+                <div className="suggestion-chat">
+                  {avatars[lastChatId] ? (
+                    <img className="chat-avatar" src={avatars[lastChatId] ?? ""} alt="" />
+                  ) : null}
+                  <span>{lastChatId}</span>
+                </div>
+              ) : null}
+              {replySource ? (
+                <div className="reply-source">
+                  回复 {replySource.sender_name}：{replySource.text}
+                </div>
+              ) : null}
               {suggestions.map((item) => (
-                <button
-                  key={item.id}
-                  className="suggestion"
-                  onClick={() => handleInsertSuggestion(item)}
-                >
-                  <span className="tag">{getStyleLabel(item.style)}</span>
-                  <span className="text">{item.text}</span>
-                </button>
+                <div key={item.id} className="suggestion-item">
+                  <label className="compose-pick">
+                    <input
+                      type="checkbox"
+                      checked={composeIds.includes(item.id)}
+                      onChange={() => handleToggleCompose(item.id)}
+                    />
+                  </label>
+                  <button className="suggestion" onClick={() => handleInsertSuggestion(item)}>
+                    <span className="tag">{getStyleLabel(item.style, stylePresets)}</span>
+                    {bestPick ? <span className="tag best">最佳建议 · 点击写入</span> : null}
+                    {safetyWarnings.some((warning) => warning.suggestion_id === item.id) ? (
+                      <span className="tag warning">命中过滤规则，请确认</span>
+                    ) : null}
+                    <span className="text">{item.text}</span>
+                  </button>
+                  {replySource ? (
+                    <div className="suggestion-actions">
+                      <button
+                        className="ghost small"
+                        onClick={() => handleInsertSuggestion(item, "quote")}
+                      >
+                        引用回复
+                      </button>
+                      <button
+                        className="ghost small"
+                        onClick={() => handleInsertSuggestion(item, "mention")}
+                      >
+                        @{replySource.sender_name}
+                      </button>
+                    </div>
+                  ) : null}
+                </div>
+              ))}
+              {followups && followups.chat_id === lastChatId ? (
+                <div className="followups">
+                  <span>对方意思不明确，可以先问问：</span>
+                  {followups.questions.map((question) => (
+                    <button
+                      key={question}
+                      className="ghost small"
+                      onClick={() => handleInsertFollowup(question)}
+                    >
+                      {question}
+                    </button>
+                  ))}
+                </div>
+              ) : null}
+              {reasoning && reasoning.chat_id === lastChatId ? (
+                <details className="reasoning">
+                  <summary>推理过程（{reasoning.model}）</summary>
+                  <p>{reasoning.text}</p>
+                </details>
+              ) : null}
+            </div>
+          )}
+        </div>
+        <div className="panel suggestions">
+          <div className="panel-header">
+            <h2>快捷回复</h2>
+            <span>{cannedResponses.length} 条</span>
+          </div>
+          <input
+            type="text"
+            placeholder="按标题、内容或标签筛选"
+            value={cannedQuery}
+            onChange={(event) => setCannedQuery(event.target.value)}
+          />
+          {cannedResponses.length === 0 ? (
+            <div className="empty">尚未保存快捷回复</div>
+          ) : (
+            <div className="suggestion-list">
+              {filterCannedResponses(cannedResponses, cannedQuery).map((item) => (
+                <div key={item.id} className="suggestion-item">
+                  <button className="suggestion" onClick={() => handleInsertCanned(item)}>
+                    <span className="tag">{item.title}</span>
+                    <span className="text">{item.text}</span>
+                  </button>
+                  <div className="suggestion-actions">
+                    {item.tags.map((tag) => (
+                      <span key={tag} className="tag">
+                        {tag}
+                      </span>
+                    ))}
+                    <button className="ghost small" onClick={() => handleDeleteCanned(item.id)}>
+                      删除
+                    </button>
+                  </div>
+                </div>
               ))}
             </div>
           )}
+          <div className="listen-row">
+            <input
+              type="text"
+              placeholder="标题"
+              value={cannedTitle}
+              onChange={(event) => setCannedTitle(event.target.value)}
+            />
+            <input
+              type="text"
+              placeholder="标签，逗号分隔"
+              value={cannedTags}
+              onChange={(event) => setCannedTags(event.target.value)}
+            />
+          </div>
+          <div className="listen-row">
+            <input
+              type="text"
+              placeholder="回复内容"
+              value={cannedText}
+              onChange={(event) => setCannedText(event.target.value)}
+            />
+            <button className="small" onClick={handleCreateCanned}>
+              保存
+            </button>
+          </div>
         </div>
       </section>
 
@@ -603,6 +1745,11 @@ function App() {
                 <button className="ghost" onClick={handleDiagnose} disabled={diagnosing}>
                   {diagnosing ? "诊断中..." : "连接诊断"}
                 </button>
+                {apiKeySet ? (
+                  <button className="ghost" onClick={handleCheckBalance}>
+                    查询余额
+                  </button>
+                ) : null}
                 {apiKeySet ? (
                   <button className="ghost" onClick={handleDeleteApiKey}>
                     删除密钥
@@ -636,14 +1783,479 @@ function App() {
                 disabled={modelLoading}
               >
                 {models.map((model) => (
-                  <option key={model} value={model}>
-                    {model}
+                  <option key={model.id} value={model.id}>
+                    {formatModelLabel(model)}
                   </option>
                 ))}
+                {models.some((model) => model.id === selectedModel) ? null : (
+                  <option value={selectedModel}>{selectedModel}</option>
+                )}
               </select>
               <p>保存密钥后将刷新模型列表</p>
             </div>
           </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>配置方案</h2>
+              <span>{profiles.length ? `${profiles.length} 个` : "未保存"}</span>
+            </div>
+            <div className="model-select">
+              <select
+                value={profiles.find((profile) => profile.active)?.name ?? ""}
+                onChange={handleProfileChange}
+                disabled={profiles.length === 0}
+              >
+                <option value="">选择配置方案</option>
+                {profiles.map((profile) => (
+                  <option key={profile.name} value={profile.name}>
+                    {profile.name}（{profile.deepseek_model}，
+                    {profile.listen_target_count} 个监听对象）
+                  </option>
+                ))}
+              </select>
+              <div className="listen-row">
+                <input
+                  type="text"
+                  placeholder="方案名称，如 工作/私人"
+                  value={profileName}
+                  onChange={(event) => setProfileName(event.target.value)}
+                />
+                <button className="small" onClick={handleSaveProfile}>
+                  保存当前配置
+                </button>
+                <button
+                  className="ghost small"
+                  onClick={handleDeleteProfile}
+                  disabled={!profiles.some((profile) => profile.active)}
+                >
+                  删除
+                </button>
+              </div>
+              <p>切换方案会替换模型、提示词与监听对象</p>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>提示词版本</h2>
+              <span>{promptVersions.length ? `当前 v${promptVersions[0].version}` : "未记录"}</span>
+            </div>
+            <div className="model-select">
+              <select
+                value={promptVersionPick}
+                onChange={(event) => {
+                  setPromptVersionPick(event.target.value);
+                  setPromptDiff(null);
+                }}
+                disabled={promptVersions.length < 2}
+              >
+                <option value="">选择历史版本</option>
+                {promptVersions.slice(1).map((item) => (
+                  <option key={item.version} value={item.version}>
+                    v{item.version} · {item.source} ·{" "}
+                    {new Date(item.created_at * 1000).toLocaleString()}
+                  </option>
+                ))}
+              </select>
+              <div className="listen-row">
+                <button
+                  className="ghost small"
+                  onClick={handleDiffPromptVersion}
+                  disabled={!promptVersionPick}
+                >
+                  与当前对比
+                </button>
+                <button
+                  className="small"
+                  onClick={handleRollbackPromptVersion}
+                  disabled={!promptVersionPick}
+                >
+                  回滚到此版本
+                </button>
+              </div>
+              {promptDiff ? (
+                <ul>
+                  {promptDiff.length === 0 ? <li>与当前版本相同</li> : null}
+                  {promptDiff.map((change) => (
+                    <li key={change.item}>{formatPromptChange(change)}</li>
+                  ))}
+                </ul>
+              ) : null}
+              <p>修改监听对象提示词、人设或自定义风格时自动记录版本，生成的建议会标记所用版本</p>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>提示词实验</h2>
+              <span>{promptExperiment.enabled ? "进行中" : "未开启"}</span>
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={promptExperiment.enabled}
+                onChange={(event) =>
+                  setPromptExperiment((prev) => ({ ...prev, enabled: event.target.checked }))
+                }
+              />
+              <span>同一会话交替使用两套提示词</span>
+            </label>
+            <div className="model-select">
+              <input
+                type="text"
+                placeholder="实验名称，如 简短回复"
+                value={promptExperiment.name}
+                onChange={(event) =>
+                  setPromptExperiment((prev) => ({ ...prev, name: event.target.value }))
+                }
+              />
+              <input
+                type="text"
+                placeholder="提示词 A"
+                value={promptExperiment.variant_a}
+                onChange={(event) =>
+                  setPromptExperiment((prev) => ({ ...prev, variant_a: event.target.value }))
+                }
+              />
+              <input
+                type="text"
+                placeholder="提示词 B"
+                value={promptExperiment.variant_b}
+                onChange={(event) =>
+                  setPromptExperiment((prev) => ({ ...prev, variant_b: event.target.value }))
+                }
+              />
+              <div className="listen-row">
+                <button className="small" onClick={handleSavePromptExperiment}>
+                  保存实验
+                </button>
+                <button
+                  className="ghost small"
+                  onClick={handleLoadExperimentReport}
+                  disabled={!promptExperiment.name.trim()}
+                >
+                  查看结果
+                </button>
+              </div>
+              {experimentReport ? (
+                <ul>
+                  {experimentReport.variants.map((item) => (
+                    <li key={item.variant}>
+                      {item.variant.toUpperCase()} · {item.acceptance.accepted}/
+                      {item.acceptance.suggestion_sets} · 采纳率{" "}
+                      {Math.round(item.acceptance_rate * 100)}%
+                    </li>
+                  ))}
+                  <li>{experimentReport.summary}</li>
+                </ul>
+              ) : null}
+              <p>按写入的建议统计采纳率，两组各满 20 次后给出显著性判断</p>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>低功耗</h2>
+              <span>{formatPowerStatus(status.power)}</span>
+            </div>
+            <div className="model-select">
+              <select value={lowPowerMode} onChange={handleLowPowerModeChange}>
+                {(Object.keys(LOW_POWER_MODE_LABELS) as LowPowerMode[]).map((mode) => (
+                  <option key={mode} value={mode}>
+                    {LOW_POWER_MODE_LABELS[mode]}
+                  </option>
+                ))}
+              </select>
+              <p>低功耗时延长监听间隔以减少耗电</p>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>生成失败时</h2>
+            </div>
+            <div className="model-select">
+              <select value={fallbackMode} onChange={handleFallbackModeChange}>
+                {(Object.keys(FALLBACK_MODE_LABELS) as FallbackMode[]).map((mode) => (
+                  <option key={mode} value={mode}>
+                    {FALLBACK_MODE_LABELS[mode]}
+                  </option>
+                ))}
+              </select>
+              <p>DeepSeek 不可用时的处理方式</p>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>每日用量上限</h2>
+              <button className="small" onClick={handleDailyLimitSave}>
+                保存
+              </button>
+            </div>
+            <div className="model-select">
+              <input
+                type="number"
+                min={0}
+                placeholder="每日请求次数，0 为不限"
+                value={dailyRequestLimit}
+                onChange={(event) => setDailyRequestLimit(Number(event.target.value) || 0)}
+              />
+              <input
+                type="number"
+                min={0}
+                placeholder="每日 Token 数，0 为不限"
+                value={dailyTokenLimit}
+                onChange={(event) => setDailyTokenLimit(Number(event.target.value) || 0)}
+              />
+              <p>请求次数 / Token 数，0 为不限；超出后当天改用本地模板建议</p>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>自动化追踪</h2>
+              <div className="suggestion-actions">
+                {isWindows ? (
+                  <button className="ghost small" onClick={handleLocatorDiagnostics}>
+                    定位诊断
+                  </button>
+                ) : null}
+                <button
+                  className="small"
+                  onClick={handleExportAutomationTrace}
+                  disabled={!automationTrace}
+                >
+                  导出
+                </button>
+              </div>
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={automationTrace}
+                onChange={handleAutomationTraceChange}
+              />
+              记录最近的界面自动化操作，便于排查写入失败
+            </label>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>Agent 日志</h2>
+              <div className="suggestion-actions">
+                <button className="ghost small" onClick={handleShowAgentInfo}>
+                  信息
+                </button>
+                <button className="ghost small" onClick={handleStopAgent}>
+                  停止 Agent
+                </button>
+                <button className="small" onClick={handleRestartAgent}>
+                  重启 Agent
+                </button>
+                <button
+                  className="ghost small"
+                  onClick={() => setAgentLogs([])}
+                  disabled={agentLogs.length === 0}
+                >
+                  清空
+                </button>
+              </div>
+            </div>
+            {agentInfo ? (
+              <div className="diagnostics">
+                <p>当前 Agent</p>
+                <ul>
+                  {formatAgentInfo(agentInfo, Date.now() / 1000).map((line) => (
+                    <li key={line}>{line}</li>
+                  ))}
+                </ul>
+              </div>
+            ) : null}
+            <div className="diagnostics agent-log">
+              <p>{agentLogs.length ? `最近 ${agentLogs.length} 条` : "暂无日志"}</p>
+              {agentLogs.length ? (
+                <ul>
+                  {agentLogs.map((entry, index) => (
+                    <li key={`${entry.timestamp}-${index}`}>{formatAgentLog(entry)}</li>
+                  ))}
+                </ul>
+              ) : null}
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>建议排序</h2>
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={bestPickMode}
+                onChange={handleBestPickModeChange}
+              />
+              按相关度与语气排序后只显示得分最高的一条建议，点击即可写入
+            </label>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>澄清问题</h2>
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={followupQuestions}
+                onChange={handleFollowupQuestionsChange}
+              />
+              对方消息含义不明确时，额外生成 1 到 2 个澄清问题显示在建议下方
+            </label>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>模仿我的语气</h2>
+            </div>
+            <label className="toggle-row">
+              <input type="checkbox" checked={styleLearning} onChange={handleStyleLearningChange} />
+              根据我在该会话中发出的历史消息总结长度、表情与正式程度，让建议更像我本人
+            </label>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>网络与证书</h2>
+              <span>{caBundlePath.trim() ? "自定义 CA" : "系统证书"}</span>
+            </div>
+            <div className="model-select">
+              <input
+                type="text"
+                placeholder="CA 证书文件路径（PEM），留空使用系统证书"
+                value={caBundlePath}
+                onChange={(event) => setCaBundlePath(event.target.value)}
+              />
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={pinCaBundle}
+                disabled={!caBundlePath.trim()}
+                onChange={(event) => setPinCaBundle(event.target.checked)}
+              />
+              仅信任该证书（固定 DeepSeek 连接，不再信任系统证书）
+            </label>
+            <div className="listen-row">
+              <button className="small" onClick={handleSaveTlsSettings}>
+                保存证书设置
+              </button>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>多账号</h2>
+              <span>{accounts.trim() ? "多开" : "默认账号"}</span>
+            </div>
+            <div className="model-select">
+              <input
+                type="text"
+                placeholder="额外的微信账号标识，用逗号分隔（如 work, 企业微信）"
+                value={accounts}
+                onChange={(event) => setAccounts(event.target.value)}
+              />
+            </div>
+            {Object.values(status.accounts).map((account) => (
+              <div className="model-select" key={account.account_id}>
+                <p>
+                  {account.account_id}
+                  {account.nickname ? `（${account.nickname}）` : ""}：
+                  {account.connected ? getStateLabel(account.state) : "未连接"}
+                  {account.last_error ? ` · ${account.last_error}` : ""}
+                </p>
+              </div>
+            ))}
+            <div className="listen-row">
+              <button className="small" onClick={handleSaveAccounts}>
+                保存多账号设置
+              </button>
+            </div>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>自动化方式</h2>
+              <span>当前：{STRATEGY_LABELS[status.strategy]}</span>
+            </div>
+            <div className="model-select">
+              <input
+                type="text"
+                placeholder="按优先级填写 ui, db, agent，用逗号分隔"
+                value={strategies}
+                onChange={(event) => setStrategies(event.target.value)}
+              />
+            </div>
+            <div className="listen-row">
+              <button className="small" onClick={handleSaveStrategies}>
+                保存自动化方式
+              </button>
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={pauseWhenUnfocused}
+                onChange={handlePauseWhenUnfocusedChange}
+              />
+              仅在微信位于前台时读取消息（本地自动化，下次开始监听时生效）
+            </label>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>隐私与推理</h2>
+            </div>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={piiRedaction}
+                onChange={handlePiiRedactionChange}
+              />
+              发送给 DeepSeek 前将手机号、身份证号、银行卡号替换为占位符，生成后在本地还原
+            </label>
+            <label className="toggle-row">
+              <input
+                type="checkbox"
+                checked={exposeReasoning}
+                onChange={handleExposeReasoningChange}
+              />
+              使用 deepseek-reasoner 时在建议下方显示推理过程（不会写入回复）
+            </label>
+          </div>
+          <div className="panel settings">
+            <div className="panel-header">
+              <h2>存储清理</h2>
+              <div className="suggestion-actions">
+                <button
+                  className="ghost small"
+                  onClick={() => handleRunMaintenance(true)}
+                  disabled={maintenanceRunning}
+                >
+                  检查
+                </button>
+                <button
+                  className="small"
+                  onClick={handleConfirmMaintenance}
+                  disabled={maintenanceRunning}
+                >
+                  清理
+                </button>
+              </div>
+            </div>
+            <div className="model-select">
+              <p>{maintenanceSummary ?? "清理过期的 UI 树导出、临时文件、超大日志、残留的数据库日志与 Python 缓存，清理前会先确认"}</p>
+            </div>
+          </div>
+          {isMacos ? (
+            <div className="panel settings">
+              <div className="panel-header">
+                <h2>菜单栏</h2>
+                <span>点击菜单栏图标打开快捷面板</span>
+              </div>
+              <label className="toggle-row">
+                <input
+                  type="checkbox"
+                  checked={hideDockIcon}
+                  onChange={handleHideDockIconChange}
+                />
+                隐藏 Dock 图标，仅在菜单栏运行
+              </label>
+            </div>
+          ) : null}
         </div>
       </Modal>
 
@@ -704,7 +2316,7 @@ function App() {
               </button>
               <button
                 className="ghost small"
-                onClick={refreshRecentChats}
+                onClick={() => void refreshRecentChats(true, recentFilter)}
                 disabled={recentLoading}
               >
                 {recentLoading ? "刷新中..." : "刷新会话"}
@@ -731,7 +2343,57 @@ function App() {
                           <span className="listen-kind">
                             {LISTEN_KIND_LABELS[target.kind]}
                           </span>
+                          {status.targets[target.name] && (
+                            <span
+                              className="listen-kind"
+                              title={status.targets[target.name].detail}
+                            >
+                              {formatTargetStatus(status.targets[target.name])}
+                            </span>
+                          )}
+                          {target.muted && (
+                            <span className="listen-kind">已静音</span>
+                          )}
+                          {target.priority === "high" && (
+                            <span className="listen-kind">优先</span>
+                          )}
+                          {target.mention_only && (
+                            <span className="listen-kind">仅@我</span>
+                          )}
                         </div>
+                        <button
+                          className="ghost small"
+                          onClick={() =>
+                            handleUpdateTarget(target.name, {
+                              muted: !target.muted,
+                            })
+                          }
+                        >
+                          {target.muted ? "取消静音" : "静音"}
+                        </button>
+                        <button
+                          className="ghost small"
+                          onClick={() =>
+                            handleUpdateTarget(target.name, {
+                              priority:
+                                target.priority === "high" ? "normal" : "high",
+                            })
+                          }
+                        >
+                          {target.priority === "high" ? "取消优先" : "设为优先"}
+                        </button>
+                        {target.kind !== "direct" && (
+                          <button
+                            className="ghost small"
+                            onClick={() =>
+                              handleUpdateTarget(target.name, {
+                                mention_only: !target.mention_only,
+                              })
+                            }
+                          >
+                            {target.mention_only ? "全部消息" : "仅@我"}
+                          </button>
+                        )}
                         <button
                           className="ghost small"
                           onClick={() => handleRemoveTarget(target.name)}
@@ -744,7 +2406,10 @@ function App() {
                 )}
               </div>
               <div>
-                <div className="listen-subtitle">最近会话</div>
+                <div className="listen-subtitle">
+                  最近会话
+                  {recentFreshness ? ` · ${recentFreshness}` : ""}
+                </div>
                 {recentLoading ? (
                   <div className="empty">加载中...</div>
                 ) : recentChats.length === 0 ? (
@@ -759,9 +2424,14 @@ function App() {
                         key={`${chat.chat_id}-${chat.chat_title}`}
                       >
                         <div className="listen-meta">
+                          {avatars[chat.chat_id] ? (
+                            <img className="chat-avatar" src={avatars[chat.chat_id] ?? ""} alt="" />
+                          ) : null}
                           <span className="listen-name">{chat.chat_title}</span>
                           <span className="listen-kind">
-                            {LISTEN_KIND_LABELS[chat.kind]}
+                            {[LISTEN_KIND_LABELS[chat.kind], describeRecentChat(chat, Date.now() / 1000)]
+                              .filter(Boolean)
+                              .join(" · ")}
                           </span>
                         </div>
                         <button
@@ -774,9 +2444,137 @@ function App() {
                     ))}
                   </div>
                 )}
+                {!recentLoading && recentSnapshot?.has_more ? (
+                  <button
+                    className="ghost small"
+                    onClick={() => void loadMoreRecentChats()}
+                    disabled={recentLoadingMore}
+                  >
+                    {recentLoadingMore ? "加载中..." : "加载更多"}
+                  </button>
+                ) : null}
               </div>
             </div>
+            <div>
+              <div className="listen-subtitle">会话活跃度（近 30 天）</div>
+              {activityStats.length === 0 ? (
+                <div className="empty">暂无历史消息</div>
+              ) : (
+                <div className="listen-list">
+                  {activityStats.map((item) => (
+                    <div className="listen-item" key={item.chat_id}>
+                      <div className="listen-meta">
+                        <span className="listen-name">{item.chat_id}</span>
+                        <span className="listen-kind">{formatActivitySummary(item)}</span>
+                      </div>
+                      <span className="listen-kind">{item.listened ? "监听中" : "未监听"}</span>
+                    </div>
+                  ))}
+                </div>
+              )}
+            </div>
+          </div>
+        </div>
+      </Modal>
+
+      <Modal
+        title="历史消息"
+        open={historyOpen}
+        onCancel={() => setHistoryOpen(false)}
+        footer={null}
+        width={680}
+      >
+        <div className="listen-targets">
+          <div className="listen-row">
+            <input
+              type="text"
+              placeholder="搜索聊天记录，如 合同、周五"
+              value={historyQuery}
+              onChange={(event) => setHistoryQuery(event.target.value)}
+              onKeyDown={(event) => {
+                if (event.key === "Enter") {
+                  void handleSearchHistory();
+                }
+              }}
+            />
+            <button className="small" onClick={handleSearchHistory} disabled={historySearching}>
+              {historySearching ? "搜索中..." : "搜索"}
+            </button>
+          </div>
+          {historyHits.length === 0 ? (
+            <div className="empty">暂无结果</div>
+          ) : (
+            <div className="listen-list">
+              {historyHits.map((hit, index) => (
+                <div className="listen-item" key={`${hit.chat_id}-${hit.timestamp}-${index}`}>
+                  <div className="listen-meta">
+                    <span className="listen-name">{hit.text}</span>
+                    <span className="listen-kind">
+                      {hit.chat_id} · {new Date(hit.timestamp * 1000).toLocaleString()}
+                    </span>
+                  </div>
+                </div>
+              ))}
+            </div>
+          )}
+        </div>
+      </Modal>
+
+      <Modal
+        title="导入聊天记录"
+        open={seedOpen}
+        onCancel={() => setSeedOpen(false)}
+        footer={null}
+        width={680}
+      >
+        <div className="listen-targets">
+          <div className="listen-row">
+            <input
+              type="text"
+              placeholder="聊天对象，如 张三、项目群"
+              value={seedChatId}
+              onChange={(event) => setSeedChatId(event.target.value)}
+            />
+            <button className="small" onClick={handleSeedContext} disabled={seeding}>
+              {seeding ? "导入中..." : "导入"}
+            </button>
           </div>
+          <textarea
+            rows={12}
+            placeholder={"粘贴导出的聊天记录，例如：\n张三 2024-05-01 10:00\n明天来取货\n我 2024-05-01 10:02\n好的"}
+            value={seedText}
+            onChange={(event) => setSeedText(event.target.value)}
+          />
+        </div>
+      </Modal>
+
+      <Modal
+        title="建议记录"
+        open={suggestionLogOpen}
+        onCancel={() => setSuggestionLogOpen(false)}
+        footer={null}
+        width={680}
+      >
+        <div className="listen-targets">
+          {suggestionLog.length === 0 ? (
+            <div className="empty">暂无记录</div>
+          ) : (
+            <div className="listen-list">
+              {suggestionLog.map((record) => (
+                <div className="listen-item" key={record.id}>
+                  <div className="listen-meta">
+                    <span className="listen-name">
+                      {record.suggestions.map((item) => item.text).join(" / ")}
+                    </span>
+                    <span className="listen-kind">
+                      {record.chat_id} · {new Date(record.created_at * 1000).toLocaleString()} ·{" "}
+                      {formatSuggestionRecord(record)}
+                    </span>
+                  </div>
+                </div>
+              ))}
+            </div>
+          )}
         </div>
       </Modal>
     </main>
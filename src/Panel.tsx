@@ -0,0 +1,96 @@
+import { useCallback, useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import "./App.css";
+import type { Status, Suggestion, SuggestionsUpdated } from "./bindings";
+import { commands } from "./bindings";
+import { getStateLabel, getStyleLabel } from "./utils/labels";
+import { notify } from "./utils/notify";
+import { normalizeReplyText } from "./utils/reply";
+
+function Panel() {
+  const [status, setStatus] = useState<Status | null>(null);
+  const [chatId, setChatId] = useState<string | null>(null);
+  const [suggestions, setSuggestions] = useState<Suggestion[]>([]);
+
+  useEffect(() => {
+    const bootstrap = async () => {
+      const [statusRes, latestRes] = await Promise.all([
+        commands.getStatus(),
+        commands.getLatestSuggestions(),
+      ]);
+      if (statusRes.success && statusRes.data) {
+        setStatus(statusRes.data);
+      }
+      if (latestRes.success && latestRes.data) {
+        setChatId(latestRes.data.chat_id);
+        setSuggestions(latestRes.data.suggestions);
+      }
+    };
+    void bootstrap();
+
+    const unlistenStatus = listen<Status>("status.changed", (event) => {
+      setStatus(event.payload);
+    });
+    const unlistenSuggestions = listen<SuggestionsUpdated>(
+      "suggestions.updated",
+      (event) => {
+        setChatId(event.payload.chat_id);
+        setSuggestions(event.payload.suggestions);
+      },
+    );
+    return () => {
+      void unlistenStatus.then((fn) => fn());
+      void unlistenSuggestions.then((fn) => fn());
+    };
+  }, []);
+
+  const handleInsert = useCallback(
+    async (suggestion: Suggestion) => {
+      if (!chatId) {
+        notify.warning("暂无可写入的聊天");
+        return;
+      }
+      const normalized = normalizeReplyText(suggestion.text);
+      if (!normalized.ok) {
+        notify.warning("回复内容不可用", { detail: normalized.reason });
+        return;
+      }
+      const res = await commands.writeSuggestion(chatId, normalized.text);
+      if (res.success) {
+        notify.success("已写入输入框");
+      } else {
+        notify.error("写入失败", { detail: res.message });
+      }
+    },
+    [chatId],
+  );
+
+  return (
+    <main className="quick-panel">
+      <header className="panel-header">
+        <h2>{chatId ?? "WeReply"}</h2>
+        <span className="status-pill" data-state={status?.state}>
+          {status ? getStateLabel(status.state) : "未知"}
+        </span>
+      </header>
+      {suggestions.length === 0 ? (
+        <div className="empty">等待新消息触发建议</div>
+      ) : (
+        <div className="suggestion-list">
+          {suggestions.map((item) => (
+            <button
+              key={item.id}
+              className="suggestion"
+              onClick={() => handleInsert(item)}
+            >
+              <span className="tag">{getStyleLabel(item.style)}</span>
+              <span className="text">{item.text}</span>
+            </button>
+          ))}
+        </div>
+      )}
+    </main>
+  );
+}
+
+export default Panel;
@@ -5,37 +5,187 @@ import { invoke } from "@tauri-apps/api/core";
 
 export type RuntimeState = "idle" | "listening" | "generating" | "paused" | "error"
 
-export type SuggestionStyle = "formal" | "neutral" | "casual"
+export type SuggestionStyle = string
+
+export type EmojiPolicy = "allow" | "avoid" | "prefer"
+
+export type StylePreset = { name: string; description: string; prompt: string; emoji: EmojiPolicy }
+
+export type ReplyLengthLimit = { style: SuggestionStyle; min_chars: number; max_chars: number }
+
+export type SafetyAction = "drop" | "mask" | "flag"
+
+export type SafetyRule = { pattern: string; regex: boolean; action: SafetyAction }
+
+export type SafetyWarning = { suggestion_id: string; pattern: string }
 
 export type Platform = "windows" | "macos" | "unknown"
 
 export type ChatKind = "direct" | "group" | "unknown"
 
-export type ListenTarget = { name: string; kind: ChatKind }
+export type TargetPriority = "normal" | "high"
+
+export type ContactLanguage = "ja" | "ko"
+
+export type Politeness = "polite" | "casual"
+
+export type ListenTarget = { name: string; kind: ChatKind; prompt_override?: string | null; persona?: string | null; muted?: boolean; priority?: TargetPriority; sender_whitelist?: string[]; sender_blacklist?: string[]; mention_only?: boolean; language?: ContactLanguage | null; politeness?: Politeness }
+
+export type ListenTargetResult = { name: string; ok: boolean; message: string }
+
+export type BusinessHours = { start: string; end: string; weekdays_only: boolean; utc_offset_minutes: number }
+
+export type AutoReplyRule = { target: string; keyword: string; template: string; canned_response_id?: string | null; hours?: { start: string; end: string; weekdays_only: boolean; utc_offset_minutes: number } | null }
+
+export type CannedResponse = { id: string; title: string; text: string; tags: string[]; updated_at: number }
+
+export type ContactNote = { chat_id: string; note: string; updated_at: number }
+
+export type KnowledgeBaseStatus = { dir: string; documents: number; chunks: number; built_at: number | null }
+
+export type Persona = { name: string; prompt?: string | null; styles: SuggestionStyle[]; auto_send: boolean; daily_request_limit: number }
+
+export type PromptVersion = { version: number; source: string; created_at: number; snapshot: { target_prompts: { name: string; prompt: string }[]; persona_prompts: { name: string; prompt: string }[]; style_presets: { name: string; description: string; prompt: string; emoji: EmojiPolicy }[] } }
+
+export type PromptChange = { item: string; before: string | null; after: string | null }
+
+export type IntegrationScope = "read" | "write"
 
-export type ChatSummary = { chat_id: string; chat_title: string; kind: ChatKind }
+export type IntegrationToken = { id: string; name: string; scopes: IntegrationScope[]; created_at: number; last_used_at: number | null }
+
+export type IntegrationTokenCreated = { token: { id: string; name: string; scopes: IntegrationScope[]; created_at: number; last_used_at: number | null }; secret: string }
+
+export type HandoverBrief = { chat_id: string; contact: string; open_issues: string[]; promised_actions: string[]; tone_guidance: string; markdown: string; message_count: number; generated_at: number; saved_to: string | null }
+
+export type AutoReplySent = { chat_id: string; keyword: string; text: string; sent_at: number; persona?: string | null }
+
+export type TranscriptionCompleted = { chat_id: string; msg_id: string | null; text: string; latency_ms: number }
+
+export type SuggestionReasoning = { chat_id: string; record_id: string; model: string; text: string }
+
+export type FollowupsUpdated = { chat_id: string; record_id: string; questions: string[] }
+
+export type ListenTargetsReport = { targets: { name: string; kind: ChatKind; prompt_override?: string | null; persona?: string | null; muted?: boolean; priority?: TargetPriority; sender_whitelist?: string[]; sender_blacklist?: string[]; mention_only?: boolean; language?: ContactLanguage | null; politeness?: Politeness }[]; results: { name: string; ok: boolean; message: string }[] }
+
+export type ChatActivityStats = { chat_id: string; messages_7d: number; messages_30d: number; active_days_30d: number; avg_reply_secs: number | null; last_message_at: number; listened: boolean }
+
+export type SeedContextResult = { parsed: number; kept: number; self_messages: number }
+
+export type MessageSearchHit = { chat_id: string; text: string; timestamp: number; msg_id: string | null }
+
+export type ChatSummary = { chat_id: string; chat_title: string; kind: ChatKind; account_id: string; last_message: string; last_active_at: number; unread_count: number }
+
+export type RecentChats = { chats: { chat_id: string; chat_title: string; kind: ChatKind; account_id: string; last_message: string; last_active_at: number; unread_count: number }[]; has_more: boolean; fetched_at: number; stale: boolean; refreshing: boolean }
+
+export type ChatAvatar = { chat_id: string; mime_type: string; data_base64: string | null; path: string | null }
 
 export type Suggestion = { id: string; style: SuggestionStyle; text: string }
 
-export type Status = { state: RuntimeState; platform: Platform; agent_connected: boolean; last_error: string }
+export type PowerSource = "ac" | "battery" | "unknown"
+
+export type LowPowerMode = "auto" | "on" | "off"
+
+export type AutomationStrategy = "ui" | "db" | "agent"
+
+export type FallbackMode = "templates" | "silent" | "retry_only"
+
+export type TargetStatus = { chat_id: string; state: RuntimeState; error_code: string | null; detail: string; updated_at: number }
+
+export type AccountStatus = { account_id: string; connected: boolean; state: RuntimeState; last_error: string; nickname: string | null }
+
+export type Status = { state: RuntimeState; platform: Platform; agent_connected: boolean; last_error: string; power: { source: PowerSource; low_power: boolean; adjustments: string[] }; targets: { [key: string]: { chat_id: string; state: RuntimeState; error_code: string | null; detail: string; updated_at: number } }; accounts: { [key: string]: { account_id: string; connected: boolean; state: RuntimeState; last_error: string; nickname: string | null } }; strategy: AutomationStrategy; error_code: string | null }
 
-export type Config = { deepseek_model: string; suggestion_count: number; context_max_messages: number; context_max_chars: number; poll_interval_ms: number; listen_targets: { name: string; kind: ChatKind }[]; temperature: number; top_p: number; base_url: string; timeout_ms: number; max_retries: number; log_level: string; log_to_file: boolean }
+export type ReadinessCheck = { key: string; label: string; ok: boolean; blocking: boolean; detail: string }
+
+export type Readiness = { score: number; ready: boolean; checks: { key: string; label: string; ok: boolean; blocking: boolean; detail: string }[]; blocking_issues: string[] }
+
+export type Config = { deepseek_model: string; suggestion_count: number; context_max_messages: number; context_max_chars: number; context_max_age_secs: number; poll_interval_ms: number; listen_targets: { name: string; kind: ChatKind; prompt_override?: string | null; persona?: string | null; muted?: boolean; priority?: TargetPriority; sender_whitelist?: string[]; sender_blacklist?: string[]; mention_only?: boolean; language?: ContactLanguage | null; politeness?: Politeness }[]; temperature: number; top_p: number; base_url: string; timeout_ms: number; max_retries: number; log_level: string; log_to_file: boolean; hide_dock_icon: boolean; low_power_mode: LowPowerMode; history_retention_days: number; fallback_mode: FallbackMode; automation_trace: boolean; automation_trace_minutes: number; daily_request_limit: number; daily_token_limit: number; max_concurrent_generations: number; automation_concurrency: number; auto_reply_enabled: boolean; auto_reply_max_per_hour: number; auto_reply_rules: { target: string; keyword: string; template: string; canned_response_id?: string | null; hours?: { start: string; end: string; weekdays_only: boolean; utc_offset_minutes: number } | null }[]; self_nickname: string; image_ocr_enabled: boolean; tesseract_path: string; voice_transcription_enabled: boolean; transcription_base_url: string; transcription_model: string; knowledge_base_dir: string; knowledge_top_k: number; style_presets: { name: string; description: string; prompt: string; emoji: EmojiPolicy }[]; reply_length_limits: { style: SuggestionStyle; min_chars: number; max_chars: number }[]; safety_rules: { pattern: string; regex: boolean; action: SafetyAction }[]; pii_redaction_enabled: boolean; expose_reasoning: boolean; best_pick_mode: boolean; followup_questions_enabled: boolean; prompt_experiment: { enabled: boolean; name: string; variant_a: string; variant_b: string }; style_learning_enabled: boolean; ca_bundle_path: string; pin_ca_bundle: boolean; accounts: string[]; automation_strategies: AutomationStrategy[]; pause_when_wechat_unfocused: boolean; integration_port: number }
 
 export type UiTreeExport = { json: string; saved_to: string | null }
 
+export type AutomationMetrics = { concurrency: number; queue_limit: number; running: number; waiting: number; completed: number; rejected: number; avg_wait_ms: number; max_wait_ms: number; last_wait_ms: number }
+
+export type AutomationTraceEntry = { at_ms: number; action: string; element: string; pattern: string; ok: boolean; error: string | null; duration_ms: number }
+
+export type AutomationTraceExport = { json: string; entries: number; saved_to: string | null }
+
+export type MaintenanceKind = "ui_tree" | "temp_file" | "log" | "orphaned_db" | "python_cache"
+
+export type MaintenanceItem = { kind: MaintenanceKind; path: string; bytes: number; removed: boolean; error: string | null }
+
+export type MaintenanceReport = { dry_run: boolean; items: { kind: MaintenanceKind; path: string; bytes: number; removed: boolean; error: string | null }[]; total_bytes: number; reclaimed_bytes: number }
+
+export type LocatorCue = "automation_id" | "structure" | "name" | "geometry"
+
+export type LocatorDiagnostic = { target: string; cue: LocatorCue | null; candidates: number; theme_sensitive: boolean; at_ms: number }
+
 export type UiPathStep = { roles: string[]; index: number; title_contains: string | null }
 
 export type UiTreeLearnResult = { json: string; session_list_path: { roles: string[]; index: number; title_contains: string | null }[]; message_list_path: { roles: string[]; index: number; title_contains: string | null }[]; input_path: { roles: string[]; index: number; title_contains: string | null }[]; written_files: string[] }
 
 export type UiPathsStatus = { saved: boolean; saved_at: number | null; version: number | null; paths_file: string | null; tree_file: string | null }
 
-export type SuggestionsUpdated = { chat_id: string; suggestions: { id: string; style: SuggestionStyle; text: string }[] }
+export type ReplyMode = "plain" | "quote" | "mention"
+
+export type SuggestionsUpdated = { chat_id: string; suggestions: { id: string; style: SuggestionStyle; text: string }[]; reply_source: { msg_id: string | null; sender_name: string; text: string } | null; warnings: { suggestion_id: string; pattern: string }[]; best_pick: boolean }
+
+export type SuggestionsUnavailable = { chat_id: string; reason: string; retry_scheduled: boolean }
+
+export type FrontendSync = { status: { state: RuntimeState; platform: Platform; agent_connected: boolean; last_error: string; power: { source: PowerSource; low_power: boolean; adjustments: string[] }; targets: { [key: string]: { chat_id: string; state: RuntimeState; error_code: string | null; detail: string; updated_at: number } } }; latest_suggestions: { chat_id: string; suggestions: { id: string; style: SuggestionStyle; text: string }[]; reply_source: { msg_id: string | null; sender_name: string; text: string } | null; warnings: { suggestion_id: string; pattern: string }[]; best_pick: boolean } | null; missed_suggestions: { chat_id: string; suggestions: { id: string; style: SuggestionStyle; text: string }[]; reply_source: { msg_id: string | null; sender_name: string; text: string } | null; warnings: { suggestion_id: string; pattern: string }[]; best_pick: boolean }[]; missed_auto_replies: { chat_id: string; keyword: string; text: string; sent_at: number; persona?: string | null }[]; detached_secs: number }
+
+export type SuggestionRecord = { id: string; chat_id: string; context_hash: string; model: string; fallback: boolean; latency_ms: number; suggestions: { id: string; style: SuggestionStyle; text: string }[]; written_suggestion_id: string | null; written_at: number | null; created_at: number; prompt_version: number; experiment: { name: string; variant: ExperimentVariant } | null }
+
+export type SuggestionAcceptance = { suggestion_sets: number; accepted: number; observed: number }
+
+export type ExperimentVariant = "a" | "b"
+
+export type ExperimentReport = { name: string; enabled: boolean; variants: { variant: ExperimentVariant; prompt: string; acceptance: { suggestion_sets: number; accepted: number; observed: number }; acceptance_rate: number }[]; winner: ExperimentVariant | null; summary: string }
+
+export type SuggestionUsed = { chat_id: string; suggestion_id: string; observed: boolean }
+
+export type BacktestRange = { since?: number | null; until?: number | null; limit?: number | null }
+
+export type BacktestCase = { timestamp: number; incoming: string; generated: { id: string; style: SuggestionStyle; text: string }[]; original: { id: string; style: SuggestionStyle; text: string }[]; sent: string | null; error: string | null }
+
+export type BacktestReport = { chat_id: string; template_id: string; model: string; cases: { timestamp: number; incoming: string; generated: { id: string; style: SuggestionStyle; text: string }[]; original: { id: string; style: SuggestionStyle; text: string }[]; sent: string | null; error: string | null }[]; total_tokens: number }
+
+export type CipherSelfTest = { cipher_version: string | null; roundtrip_ok: boolean; cli_path: string | null; cli_version: string | null; detail: string }
+
+export type DecryptMethod = "bundled" | "cli"
+
+export type DecryptExport = { source_path: string; output_path: string; method: DecryptMethod; tables: number }
+
+export type DbKeyMethod = "helper" | "memory_scan"
+
+export type DbKeyReport = { ok: boolean; method: DbKeyMethod | null; candidates_checked: number; retry_after_secs: number; detail: string }
+
+export type SessionInstruction = { chat_id: string; text: string; expires_at: number }
+
+export type ProfileSummary = { name: string; deepseek_model: string; listen_target_count: number; active: boolean }
+
+export type InputWriteStatus = "queued" | "retrying" | "written" | "failed"
+
+export type InputWriteResult = { chat_id: string; status: InputWriteStatus; queue_position: number; message: string }
+
+export type SuggestedAction = "retry" | "open_settings" | "grant_permission" | "restart_agent" | "relearn_paths"
+
+export type ErrorPayload = { code: string; message: string; recoverable: boolean; suggested_action: SuggestedAction | null }
+
+export type AgentLogLevel = "debug" | "info" | "warn" | "error"
+
+export type AgentLogEntry = { level: AgentLogLevel; component: string; message: string; timestamp: number }
+
+export type AgentInfo = { account_id: string; platform: string; agent_version: string; capabilities: string[]; protocol_version: string; pid: number | null; started_at: number; uptime_secs: number; last_heartbeat_at: number | null }
+
+export type DeepseekEndpointStatus = { ok: boolean; status: number | null; message: string; latency_ms: number | null }
+
+export type DeepseekDiagnostics = { base_url: string; model: string; chat: { ok: boolean; status: number | null; message: string; latency_ms: number | null }; models: { ok: boolean; status: number | null; message: string; latency_ms: number | null }; balance: { is_available: boolean; balance_infos: { currency: string; total_balance: string; granted_balance: string; topped_up_balance: string }[] } | null; connection: { dns_ms: number; tcp_ms: number; tls_ms: number | null; request_ms: number } | null; tls: { ok: boolean; status: number | null; message: string; latency_ms: number | null } | null }
 
-export type ErrorPayload = { code: string; message: string; recoverable: boolean }
+export type ConnectionTiming = { dns_ms: number; tcp_ms: number; tls_ms: number | null; request_ms: number }
 
-export type DeepseekEndpointStatus = { ok: boolean; status: number | null; message: string }
+export type DeepseekBalance = { is_available: boolean; balance_infos: { currency: string; total_balance: string; granted_balance: string; topped_up_balance: string }[] }
 
-export type DeepseekDiagnostics = { base_url: string; model: string; chat: { ok: boolean; status: number | null; message: string }; models: { ok: boolean; status: number | null; message: string } }
+export type ModelInfo = { id: string; owned_by: string | null; context_length: number | null }
 
 export type ApiResponse<T> = { success: boolean; message: string; data: T | null }
 
@@ -46,20 +196,41 @@ export const commands = {
   getListenTargets: (): Promise<ApiResponse<ListenTarget[]>> => invoke("get_listen_targets"),
   setListenTargets: (targets: ListenTarget[]): Promise<ApiResponse<null>> =>
     invoke("set_listen_targets", { targets }),
+  addListenTargets: (batch: ListenTarget[]): Promise<ApiResponse<ListenTargetsReport>> =>
+    invoke("add_listen_targets", { batch }),
+  removeListenTargets: (names: string[]): Promise<ApiResponse<ListenTargetsReport>> =>
+    invoke("remove_listen_targets", { names }),
+  clearListenTargets: (): Promise<ApiResponse<ListenTargetsReport>> =>
+    invoke("clear_listen_targets"),
   startListening: (): Promise<ApiResponse<null>> => invoke("start_listening"),
   stopListening: (): Promise<ApiResponse<null>> => invoke("stop_listening"),
+  restartAgent: (): Promise<ApiResponse<null>> => invoke("restart_agent"),
+  stopAgent: (): Promise<ApiResponse<null>> => invoke("stop_agent"),
+  getAgentInfo: (accountId?: string): Promise<ApiResponse<AgentInfo>> =>
+    invoke("get_agent_info", { accountId: accountId ?? null }),
   pauseListening: (): Promise<ApiResponse<null>> => invoke("pause_listening"),
   resumeListening: (): Promise<ApiResponse<null>> => invoke("resume_listening"),
-  writeSuggestion: (chatId: string, text: string): Promise<ApiResponse<null>> =>
-    invoke("write_suggestion", { chat_id: chatId, text }),
+  writeSuggestion: (chatId: string, text: string, replyMode?: ReplyMode): Promise<ApiResponse<null>> =>
+    invoke("write_suggestion", { chat_id: chatId, text, replyMode: replyMode ?? null }),
   saveApiKey: (apiKey: string): Promise<ApiResponse<null>> => invoke("save_api_key", { apiKey }),
   getApiKeyStatus: (): Promise<ApiResponse<boolean>> => invoke("get_api_key_status"),
   getApiKey: (): Promise<ApiResponse<string>> => invoke("get_api_key"),
   deleteApiKey: (): Promise<ApiResponse<null>> => invoke("delete_api_key"),
+  setTranscriptionApiKey: (apiKey: string): Promise<ApiResponse<null>> =>
+    invoke("set_transcription_api_key", { apiKey }),
+  setWechatDbKey: (key: string): Promise<ApiResponse<null>> =>
+    invoke("set_wechat_db_key", { key }),
+  acquireWechatDbKey: (): Promise<ApiResponse<DbKeyReport>> => invoke("acquire_wechat_db_key"),
   diagnoseDeepseek: (apiKey?: string): Promise<ApiResponse<DeepseekDiagnostics>> =>
     invoke("diagnose_deepseek", apiKey ? { apiKey } : {}),
-  listModels: (): Promise<ApiResponse<string[]>> => invoke("list_models"),
-  listRecentChats: (): Promise<ApiResponse<ChatSummary[]>> => invoke("list_recent_chats"),
+  getDeepseekBalance: (): Promise<ApiResponse<DeepseekBalance>> => invoke("get_deepseek_balance"),
+  listModels: (): Promise<ApiResponse<ModelInfo[]>> => invoke("list_models"),
+  listChats: (offset?: number, limit?: number, query?: string): Promise<ApiResponse<RecentChats>> =>
+    invoke("list_chats", { offset: offset ?? null, limit: limit ?? null, query: query ?? null }),
+  refreshChats: (limit?: number): Promise<ApiResponse<RecentChats>> =>
+    invoke("refresh_chats", { limit: limit ?? null }),
+  getChatAvatar: (chatId: string, asFile?: boolean): Promise<ApiResponse<ChatAvatar | null>> =>
+    invoke("get_chat_avatar", { chatId, asFile: asFile ?? null }),
   exportWeChatUiTree: (maxDepth?: number, outputPath?: string): Promise<ApiResponse<UiTreeExport>> =>
     invoke("export_wechat_ui_tree", { maxDepth, outputPath }),
   learnWeChatUiPaths: (maxDepth?: number, outputPath?: string): Promise<ApiResponse<UiTreeLearnResult>> =>
@@ -68,4 +239,74 @@ export const commands = {
     invoke("get_wechat_ui_paths_status"),
   setDeepseekModel: (model: string): Promise<ApiResponse<null>> =>
     invoke("set_deepseek_model", { model }),
+  setSessionInstruction: (chatId: string, text: string, ttlSecs: number): Promise<ApiResponse<null>> =>
+    invoke("set_session_instruction", { chatId, text, ttlSecs }),
+  getSessionInstructions: (): Promise<ApiResponse<SessionInstruction[]>> =>
+    invoke("get_session_instructions"),
+  setHideDockIcon: (hidden: boolean): Promise<ApiResponse<null>> =>
+    invoke("set_hide_dock_icon", { hidden }),
+  getLatestSuggestions: (): Promise<ApiResponse<SuggestionsUpdated | null>> =>
+    invoke("get_latest_suggestions"),
+  frontendHeartbeat: (): Promise<ApiResponse<FrontendSync | null>> =>
+    invoke("frontend_heartbeat"),
+  listProfiles: (): Promise<ApiResponse<ProfileSummary[]>> =>
+    invoke("list_profiles"),
+  saveProfile: (name: string): Promise<ApiResponse<ProfileSummary[]>> =>
+    invoke("save_profile", { name }),
+  deleteProfile: (name: string): Promise<ApiResponse<ProfileSummary[]>> =>
+    invoke("delete_profile", { name }),
+  switchProfile: (name: string): Promise<ApiResponse<ProfileSummary[]>> =>
+    invoke("switch_profile", { name }),
+  getChatActivityStats: (): Promise<ApiResponse<ChatActivityStats[]>> =>
+    invoke("get_chat_activity_stats"),
+  searchMessages: (query: string, chatId?: string): Promise<ApiResponse<MessageSearchHit[]>> =>
+    invoke("search_messages", { query, chatId: chatId ?? null }),
+  getSuggestionHistory: (chatId?: string, limit?: number): Promise<ApiResponse<SuggestionRecord[]>> =>
+    invoke("get_suggestion_history", { chatId: chatId ?? null, limit: limit ?? null }),
+  getSuggestionAcceptance: (chatId?: string, days?: number): Promise<ApiResponse<SuggestionAcceptance>> =>
+    invoke("get_suggestion_acceptance", { chatId: chatId ?? null, days: days ?? null }),
+  getExperimentReport: (days?: number): Promise<ApiResponse<ExperimentReport>> => invoke("get_experiment_report", { days: days ?? null }),
+  exportAutomationTrace: (outputPath?: string): Promise<ApiResponse<AutomationTraceExport>> =>
+    invoke("export_automation_trace", { outputPath: outputPath ?? null }),
+  getReadiness: (): Promise<ApiResponse<Readiness>> => invoke("get_readiness"),
+  composeReply: (chatId: string, fragments: string[], style?: SuggestionStyle): Promise<ApiResponse<Suggestion>> =>
+    invoke("compose_reply", { chatId, fragments, style: style ?? null }),
+  generateHandoverBrief: (chatId: string, outputPath?: string): Promise<ApiResponse<HandoverBrief>> =>
+    invoke("generate_handover_brief", { chatId, outputPath: outputPath ?? null }),
+  seedContext: (chatId: string, transcriptText: string): Promise<ApiResponse<SeedContextResult>> =>
+    invoke("seed_context", { chatId, transcriptText }),
+  runMaintenance: (dryRun?: boolean): Promise<ApiResponse<MaintenanceReport>> =>
+    invoke("run_maintenance", { dryRun: dryRun ?? null }),
+  getLocatorDiagnostics: (): Promise<ApiResponse<LocatorDiagnostic[]>> =>
+    invoke("get_locator_diagnostics"),
+  backtestPrompts: (chatId: string, range?: BacktestRange, templateId?: string): Promise<ApiResponse<BacktestReport>> =>
+    invoke("backtest_prompts", { chatId, range: range ?? null, templateId: templateId ?? null }),
+  cipherSelfTest: (): Promise<ApiResponse<CipherSelfTest>> => invoke("cipher_self_test"),
+  exportDecryptedDb: (dbPath: string, key: string, outputPath?: string, compatibility?: number): Promise<ApiResponse<DecryptExport>> =>
+    invoke("export_decrypted_db", { dbPath, key, outputPath: outputPath ?? null, compatibility: compatibility ?? null }),
+  getAutomationMetrics: (): Promise<ApiResponse<AutomationMetrics>> => invoke("get_automation_metrics"),
+  listCannedResponses: (tag?: string): Promise<ApiResponse<CannedResponse[]>> =>
+    invoke("list_canned_responses", { tag: tag ?? null }),
+  createCannedResponse: (title: string, text: string, tags?: string[]): Promise<ApiResponse<CannedResponse>> =>
+    invoke("create_canned_response", { title, text, tags: tags ?? null }),
+  updateCannedResponse: (id: string, title: string, text: string, tags?: string[]): Promise<ApiResponse<CannedResponse>> =>
+    invoke("update_canned_response", { id, title, text, tags: tags ?? null }),
+  deleteCannedResponse: (id: string): Promise<ApiResponse<null>> => invoke("delete_canned_response", { id }),
+  listContactNotes: (): Promise<ApiResponse<ContactNote[]>> => invoke("list_contact_notes"),
+  setContactNote: (chatId: string, note: string): Promise<ApiResponse<ContactNote>> =>
+    invoke("set_contact_note", { chatId, note }),
+  deleteContactNote: (chatId: string): Promise<ApiResponse<null>> => invoke("delete_contact_note", { chatId }),
+  getKnowledgeBaseStatus: (): Promise<ApiResponse<KnowledgeBaseStatus>> => invoke("get_knowledge_base_status"),
+  rebuildKnowledgeBase: (): Promise<ApiResponse<KnowledgeBaseStatus>> => invoke("rebuild_knowledge_base"),
+  listPersonas: (): Promise<ApiResponse<Persona[]>> => invoke("list_personas"),
+  savePersona: (persona: Persona): Promise<ApiResponse<Persona>> => invoke("save_persona", { persona }),
+  deletePersona: (name: string): Promise<ApiResponse<null>> => invoke("delete_persona", { name }),
+  listPromptVersions: (): Promise<ApiResponse<PromptVersion[]>> => invoke("list_prompt_versions"),
+  diffPromptVersions: (from: number, to: number): Promise<ApiResponse<PromptChange[]>> =>
+    invoke("diff_prompt_versions", { from, to }),
+  rollbackPromptVersion: (version: number): Promise<ApiResponse<number>> => invoke("rollback_prompt_version", { version }),
+  listIntegrationTokens: (): Promise<ApiResponse<IntegrationToken[]>> => invoke("list_integration_tokens"),
+  createIntegrationToken: (name: string, scopes: IntegrationScope[]): Promise<ApiResponse<IntegrationTokenCreated>> =>
+    invoke("create_integration_token", { name, scopes }),
+  revokeIntegrationToken: (id: string): Promise<ApiResponse<null>> => invoke("revoke_integration_token", { id }),
 };
@@ -2,9 +2,13 @@ import React from "react";
 import ReactDOM from "react-dom/client";
 import "antd/dist/reset.css";
 import App from "./App";
+import Panel from "./Panel";
+import { resolveAppView } from "./utils/view";
+
+const view = resolveAppView(window.location.search);
 
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
-    <App />
+    {view === "panel" ? <Panel /> : <App />}
   </React.StrictMode>,
 );
@@ -0,0 +1,26 @@
+import { describe, expect, it } from "vitest";
+import { formatActivityGap, formatActivitySummary } from "./activity";
+
+describe("activity", () => {
+  it("formats message gaps", () => {
+    expect(formatActivityGap(null)).toBe("—");
+    expect(formatActivityGap(30)).toBe("不到 1 分钟");
+    expect(formatActivityGap(900)).toBe("15 分钟");
+    expect(formatActivityGap(7200)).toBe("2 小时");
+    expect(formatActivityGap(172800)).toBe("2 天");
+  });
+
+  it("summarizes chat activity", () => {
+    expect(
+      formatActivitySummary({
+        chat_id: "项目群",
+        messages_7d: 12,
+        messages_30d: 40,
+        active_days_30d: 9,
+        avg_reply_secs: 3600,
+        last_message_at: 0,
+        listened: true,
+      }),
+    ).toBe("7 天 12 条 · 30 天 40 条 · 活跃 9 天 · 平均回复 1 小时");
+  });
+});
@@ -0,0 +1,20 @@
+import type { ChatActivityStats } from "../bindings";
+
+export const formatActivityGap = (secs: number | null): string => {
+  if (secs === null) {
+    return "—";
+  }
+  if (secs < 60) {
+    return "不到 1 分钟";
+  }
+  if (secs < 3600) {
+    return `${Math.round(secs / 60)} 分钟`;
+  }
+  if (secs < 86400) {
+    return `${Math.round(secs / 3600)} 小时`;
+  }
+  return `${Math.round(secs / 86400)} 天`;
+};
+
+export const formatActivitySummary = (stats: ChatActivityStats): string =>
+  `7 天 ${stats.messages_7d} 条 · 30 天 ${stats.messages_30d} 条 · 活跃 ${stats.active_days_30d} 天 · 平均回复 ${formatActivityGap(stats.avg_reply_secs)}`;
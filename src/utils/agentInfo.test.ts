@@ -0,0 +1,43 @@
+import { describe, expect, it } from "vitest";
+import type { AgentInfo } from "../bindings";
+import { formatAgentInfo } from "./agentInfo";
+
+const info: AgentInfo = {
+  account_id: "",
+  strategy: "agent",
+  platform: "windows",
+  agent_version: "0.1.0",
+  capabilities: ["listen", "write"],
+  protocol_version: "1.1",
+  pid: 4242,
+  started_at: 1_000,
+  uptime_secs: 3_900,
+  last_heartbeat_at: 4_890,
+};
+
+describe("agentInfo", () => {
+  it("summarizes what the agent is running", () => {
+    expect(formatAgentInfo(info, 4_900)).toEqual([
+      "平台：windows · 版本：0.1.0",
+      "协议：1.1 · PID：4242",
+      "已运行：1 小时 5 分钟 · 最近消息：10 秒前",
+      "能力：listen、write",
+    ]);
+  });
+
+  it("handles an agent that has not reported yet", () => {
+    const lines = formatAgentInfo(
+      { ...info, platform: "", capabilities: [], pid: null, last_heartbeat_at: null },
+      4_900,
+    );
+    expect(lines[0]).toBe("平台：未知 · 版本：0.1.0");
+    expect(lines[2]).toContain("尚未收到");
+    expect(lines[3]).toBe("能力：无");
+  });
+
+  it("names the account for additional WeChat instances", () => {
+    expect(formatAgentInfo({ ...info, account_id: "work" }, 4_900)[0]).toBe(
+      "账号：work · 平台：windows · 版本：0.1.0",
+    );
+  });
+});
@@ -0,0 +1,27 @@
+import type { AgentInfo } from "../bindings";
+
+const formatDuration = (secs: number): string => {
+  if (secs < 60) {
+    return `${secs} 秒`;
+  }
+  if (secs < 3600) {
+    return `${Math.floor(secs / 60)} 分钟`;
+  }
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor((secs % 3600) / 60);
+  return minutes ? `${hours} 小时 ${minutes} 分钟` : `${hours} 小时`;
+};
+
+export const formatAgentInfo = (info: AgentInfo, nowSecs: number): string[] => {
+  const heartbeat =
+    info.last_heartbeat_at === null
+      ? "尚未收到"
+      : `${formatDuration(Math.max(0, Math.floor(nowSecs - info.last_heartbeat_at)))}前`;
+  const account = info.account_id ? `账号：${info.account_id} · ` : "";
+  return [
+    `${account}平台：${info.platform || "未知"} · 版本：${info.agent_version || "未知"}`,
+    `协议：${info.protocol_version} · PID：${info.pid ?? "未知"}`,
+    `已运行：${formatDuration(info.uptime_secs)} · 最近消息：${heartbeat}`,
+    `能力：${info.capabilities.length ? info.capabilities.join("、") : "无"}`,
+  ];
+};
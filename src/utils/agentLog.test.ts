@@ -0,0 +1,26 @@
+import { describe, expect, it } from "vitest";
+import type { AgentLogEntry } from "../bindings";
+import { AGENT_LOG_LIMIT, appendAgentLog, formatAgentLog } from "./agentLog";
+
+const entry = (message: string): AgentLogEntry => ({
+  level: "warn",
+  component: "listener",
+  message,
+  timestamp: 0,
+});
+
+describe("agentLog", () => {
+  it("keeps the newest entries first and caps the list", () => {
+    let logs: AgentLogEntry[] = [];
+    for (let index = 0; index < AGENT_LOG_LIMIT + 5; index += 1) {
+      logs = appendAgentLog(logs, entry(`第${index}条`));
+    }
+    expect(logs).toHaveLength(AGENT_LOG_LIMIT);
+    expect(logs[0].message).toBe(`第${AGENT_LOG_LIMIT + 4}条`);
+  });
+
+  it("formats level and component", () => {
+    expect(formatAgentLog(entry("会话列表为空"))).toContain("警告 [listener] 会话列表为空");
+    expect(formatAgentLog({ ...entry("噪声"), component: "" })).toMatch(/警告 噪声$/);
+  });
+});
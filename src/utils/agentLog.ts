@@ -0,0 +1,19 @@
+import type { AgentLogEntry, AgentLogLevel } from "../bindings";
+
+export const AGENT_LOG_LIMIT = 200;
+
+const LEVEL_LABEL: Record<AgentLogLevel, string> = {
+  debug: "调试",
+  info: "信息",
+  warn: "警告",
+  error: "错误",
+};
+
+export const appendAgentLog = (logs: AgentLogEntry[], entry: AgentLogEntry): AgentLogEntry[] =>
+  [entry, ...logs].slice(0, AGENT_LOG_LIMIT);
+
+export const formatAgentLog = (entry: AgentLogEntry): string => {
+  const time = new Date(entry.timestamp * 1000).toLocaleTimeString();
+  const component = entry.component ? `[${entry.component}] ` : "";
+  return `${time} ${LEVEL_LABEL[entry.level] ?? entry.level} ${component}${entry.message}`;
+};
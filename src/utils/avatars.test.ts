@@ -0,0 +1,21 @@
+import { describe, expect, it } from "vitest";
+import { avatarSrc, missingAvatarIds } from "./avatars";
+
+describe("chat avatars", () => {
+  it("builds a data url from inline avatars only", () => {
+    const avatar = {
+      chat_id: "wxid_a",
+      mime_type: "image/jpeg",
+      data_base64: "/9j/4AAQ",
+      path: null,
+    };
+
+    expect(avatarSrc(avatar)).toBe("data:image/jpeg;base64,/9j/4AAQ");
+    expect(avatarSrc({ ...avatar, data_base64: null, path: "/tmp/a.jpg" })).toBeNull();
+    expect(avatarSrc(null)).toBeNull();
+  });
+
+  it("requests each chat avatar once", () => {
+    expect(missingAvatarIds(["a", "b", "a", " ", "c"], new Set(["b"]))).toEqual(["a", "c"]);
+  });
+});
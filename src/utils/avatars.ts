@@ -0,0 +1,13 @@
+import type { ChatAvatar } from "../bindings";
+
+export const avatarSrc = (avatar: ChatAvatar | null | undefined): string | null => {
+  if (!avatar?.data_base64) {
+    return null;
+  }
+  return `data:${avatar.mime_type};base64,${avatar.data_base64}`;
+};
+
+export const missingAvatarIds = (
+  chatIds: string[],
+  requested: ReadonlySet<string>,
+): string[] => [...new Set(chatIds.filter((id) => id.trim() && !requested.has(id)))];
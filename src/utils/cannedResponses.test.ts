@@ -0,0 +1,22 @@
+import { describe, expect, it } from "vitest";
+import type { CannedResponse } from "../bindings";
+import { filterCannedResponses, parseTags } from "./cannedResponses";
+
+describe("canned responses", () => {
+  const responses: CannedResponse[] = [
+    { id: "a", title: "报价", text: "报价单稍后发您", tags: ["销售"], updated_at: 1 },
+    { id: "b", title: "问候", text: "在的，请讲", tags: ["客服", "常用"], updated_at: 2 },
+  ];
+
+  it("parses comma or space separated tags without duplicates", () => {
+    expect(parseTags(" 销售，客服 销售,, ")).toEqual(["销售", "客服"]);
+    expect(parseTags("")).toEqual([]);
+  });
+
+  it("filters by title, text or tag", () => {
+    expect(filterCannedResponses(responses, " ")).toEqual(responses);
+    expect(filterCannedResponses(responses, "报价")).toEqual([responses[0]]);
+    expect(filterCannedResponses(responses, "请讲")).toEqual([responses[1]]);
+    expect(filterCannedResponses(responses, "常用")).toEqual([responses[1]]);
+  });
+});
@@ -0,0 +1,27 @@
+import type { CannedResponse } from "../bindings";
+
+export const parseTags = (input: string): string[] =>
+  Array.from(
+    new Set(
+      input
+        .split(/[,，\s]+/)
+        .map((tag) => tag.trim())
+        .filter(Boolean),
+    ),
+  );
+
+export const filterCannedResponses = (
+  responses: CannedResponse[],
+  query: string,
+): CannedResponse[] => {
+  const needle = query.trim().toLowerCase();
+  if (!needle) {
+    return responses;
+  }
+  return responses.filter(
+    (response) =>
+      response.title.toLowerCase().includes(needle) ||
+      response.text.toLowerCase().includes(needle) ||
+      response.tags.some((tag) => tag.toLowerCase().includes(needle)),
+  );
+};
@@ -1,5 +1,5 @@
 import { describe, expect, it } from "vitest";
-import { summarizeDiagnostics } from "./diagnostics";
+import { formatBalance, formatTlsStatus, summarizeDiagnostics } from "./diagnostics";
 
 describe("summarize diagnostics", () => {
   it("returns ok summary when both endpoints are ok", () => {
@@ -29,6 +29,64 @@ describe("summarize diagnostics", () => {
     expect(result.lines[0]).toContain("Authentication Fails");
   });
 
+  it("reports an exhausted balance as a failure", () => {
+    const result = summarizeDiagnostics({
+      base_url: "https://api.deepseek.com",
+      model: "deepseek-chat",
+      chat: { ok: false, status: 402, message: "Insufficient Balance" },
+      models: { ok: true, status: 200, message: "ok" },
+      balance: {
+        is_available: false,
+        balance_infos: [{ currency: "CNY", total_balance: "0.00" }],
+      },
+    });
+
+    expect(result.ok).toBe(false);
+    expect(result.lines[2]).toBe("账户余额: 不足（0.00 CNY），请充值");
+    expect(result.message).toContain("账户余额");
+  });
+
+  it("shows endpoint latency and connection breakdown", () => {
+    const result = summarizeDiagnostics({
+      base_url: "https://api.deepseek.com",
+      model: "deepseek-chat",
+      chat: { ok: true, status: 200, message: "ok", latency_ms: 820 },
+      models: { ok: false, status: null, message: "连接超时", latency_ms: null },
+      connection: { dns_ms: 12, tcp_ms: 35, tls_ms: 80, request_ms: 140 },
+    });
+
+    expect(result.lines[0]).toBe("聊天接口: OK (HTTP 200, 820ms)");
+    expect(result.lines[1]).toBe("模型接口: 失败 连接超时");
+    expect(result.lines[2]).toBe("连接耗时: DNS 12ms · TCP 35ms · TLS 约 80ms · 请求 140ms");
+    expect(result.message).toBe("模型接口: 失败 连接超时");
+  });
+
+  it("reports custom CA bundle status first", () => {
+    const result = summarizeDiagnostics({
+      base_url: "https://api.deepseek.com",
+      model: "deepseek-chat",
+      chat: { ok: false, status: null, message: "自定义 CA 证书无效，未发起请求" },
+      models: { ok: false, status: null, message: "自定义 CA 证书无效，未发起请求" },
+      tls: { ok: false, status: null, message: "读取 CA 证书失败: /etc/ssl/corp-ca.pem" },
+    });
+
+    expect(result.ok).toBe(false);
+    expect(result.lines[0]).toBe("自定义证书: 无效 读取 CA 证书失败: /etc/ssl/corp-ca.pem");
+    expect(result.message.startsWith("自定义证书: 无效")).toBe(true);
+    expect(
+      formatTlsStatus({ ok: true, message: "已加载 2 个证书，仅信任这些证书" }),
+    ).toBe("自定义证书: 已加载 2 个证书，仅信任这些证书");
+  });
+
+  it("formats available balances", () => {
+    expect(
+      formatBalance({
+        is_available: true,
+        balance_infos: [{ currency: "CNY", total_balance: "110.00" }],
+      }),
+    ).toBe("账户余额: 110.00 CNY");
+  });
+
   it("returns fallback summary when diagnostics missing", () => {
     const result = summarizeDiagnostics(null, "诊断失败");
     expect(result.ok).toBe(false);
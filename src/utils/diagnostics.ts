@@ -2,6 +2,19 @@ export type DiagnosticStatus = {
   ok: boolean;
   status?: number | null;
   message: string;
+  latency_ms?: number | null;
+};
+
+export type ConnectionTiming = {
+  dns_ms: number;
+  tcp_ms: number;
+  tls_ms: number | null;
+  request_ms: number;
+};
+
+export type DeepseekBalance = {
+  is_available: boolean;
+  balance_infos: { currency: string; total_balance: string }[];
 };
 
 export type DeepseekDiagnostics = {
@@ -9,18 +22,47 @@ export type DeepseekDiagnostics = {
   model: string;
   chat: DiagnosticStatus;
   models: DiagnosticStatus;
+  balance?: DeepseekBalance | null;
+  connection?: ConnectionTiming | null;
+  tls?: DiagnosticStatus | null;
 };
 
 const formatLine = (label: string, status: DiagnosticStatus): string => {
   const statusCode = status.status ?? null;
+  const details = [
+    statusCode ? `HTTP ${statusCode}` : null,
+    status.latency_ms != null ? `${status.latency_ms}ms` : null,
+  ].filter(Boolean);
   if (status.ok) {
-    return statusCode ? `${label}: OK (HTTP ${statusCode})` : `${label}: OK`;
+    return details.length ? `${label}: OK (${details.join(", ")})` : `${label}: OK`;
   }
-  const statusText = statusCode ? ` (HTTP ${statusCode})` : "";
+  const statusText = details.length ? ` (${details.join(", ")})` : "";
   const detail = status.message ? ` ${status.message}` : "";
   return `${label}: 失败${statusText}${detail}`;
 };
 
+export const formatBalance = (balance: DeepseekBalance): string => {
+  const amounts = balance.balance_infos
+    .map((info) => `${info.total_balance} ${info.currency}`)
+    .join("，");
+  if (!balance.is_available) {
+    return `账户余额: 不足${amounts ? `（${amounts}）` : ""}，请充值`;
+  }
+  return `账户余额: ${amounts || "可用"}`;
+};
+
+export const formatTlsStatus = (status: DiagnosticStatus): string =>
+  status.ok ? `自定义证书: ${status.message}` : `自定义证书: 无效 ${status.message}`;
+
+export const formatConnectionTiming = (timing: ConnectionTiming): string => {
+  const parts = [`DNS ${timing.dns_ms}ms`, `TCP ${timing.tcp_ms}ms`];
+  if (timing.tls_ms != null) {
+    parts.push(`TLS 约 ${timing.tls_ms}ms`);
+  }
+  parts.push(`请求 ${timing.request_ms}ms`);
+  return `连接耗时: ${parts.join(" · ")}`;
+};
+
 export const summarizeDiagnostics = (
   diagnostics: DeepseekDiagnostics | null,
   errorMessage?: string,
@@ -34,9 +76,20 @@ export const summarizeDiagnostics = (
     formatLine("聊天接口", diagnostics.chat),
     formatLine("模型接口", diagnostics.models),
   ];
-  const ok = diagnostics.chat.ok && diagnostics.models.ok;
-  const message = ok
-    ? "连接诊断通过"
-    : lines.filter((_, idx) => ![diagnostics.chat.ok, diagnostics.models.ok][idx]).join("；");
+  const checks = [diagnostics.chat.ok, diagnostics.models.ok];
+  if (diagnostics.tls) {
+    lines.unshift(formatTlsStatus(diagnostics.tls));
+    checks.unshift(diagnostics.tls.ok);
+  }
+  if (diagnostics.balance) {
+    lines.push(formatBalance(diagnostics.balance));
+    checks.push(diagnostics.balance.is_available);
+  }
+  if (diagnostics.connection) {
+    lines.push(formatConnectionTiming(diagnostics.connection));
+    checks.push(true);
+  }
+  const ok = checks.every(Boolean);
+  const message = ok ? "连接诊断通过" : lines.filter((_, idx) => !checks[idx]).join("；");
   return { ok, message, lines };
 };
@@ -0,0 +1,16 @@
+import { describe, expect, it } from "vitest";
+import { formatUnavailable } from "./fallback";
+
+describe("fallback", () => {
+  it("shows the failure reason", () => {
+    expect(
+      formatUnavailable({ chat_id: "c1", reason: "DeepSeek 返回错误: 401", retry_scheduled: false }),
+    ).toBe("DeepSeek 返回错误: 401");
+  });
+
+  it("mentions the scheduled retry", () => {
+    expect(
+      formatUnavailable({ chat_id: "c1", reason: "DeepSeek 请求失败", retry_scheduled: true }),
+    ).toBe("DeepSeek 请求失败，联网后将自动重试");
+  });
+});
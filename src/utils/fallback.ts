@@ -0,0 +1,10 @@
+import type { FallbackMode, SuggestionsUnavailable } from "../bindings";
+
+export const FALLBACK_MODE_LABELS: Record<FallbackMode, string> = {
+  templates: "使用模板建议",
+  silent: "仅提示失败原因",
+  retry_only: "联网后自动重试",
+};
+
+export const formatUnavailable = (payload: SuggestionsUnavailable): string =>
+  payload.retry_scheduled ? `${payload.reason}，联网后将自动重试` : payload.reason;
@@ -15,4 +15,13 @@ describe("labels", () => {
     expect(getStyleLabel("neutral")).toBe("中性");
     expect(getStyleLabel("casual")).toBe("轻松");
   });
+
+  it("falls back to custom style presets", () => {
+    const presets = [
+      { name: "buddy", description: "哥们", prompt: "", emoji: "prefer" as const },
+    ];
+    expect(getStyleLabel("buddy", presets)).toBe("哥们");
+    expect(getStyleLabel("buddy")).toBe("buddy");
+    expect(getStyleLabel("formal", presets)).toBe("正式");
+  });
 });
@@ -1,4 +1,4 @@
-import type { RuntimeState, SuggestionStyle } from "../bindings";
+import type { RuntimeState, StylePreset, SuggestionStyle } from "../bindings";
 
 const STATE_LABEL: Record<RuntimeState, string> = {
   idle: "空闲",
@@ -8,7 +8,7 @@ const STATE_LABEL: Record<RuntimeState, string> = {
   error: "异常",
 };
 
-const STYLE_LABEL: Record<SuggestionStyle, string> = {
+const STYLE_LABEL: Record<string, string> = {
   formal: "正式",
   neutral: "中性",
   casual: "轻松",
@@ -17,5 +17,10 @@ const STYLE_LABEL: Record<SuggestionStyle, string> = {
 export const getStateLabel = (state: RuntimeState): string =>
   STATE_LABEL[state] ?? "未知";
 
-export const getStyleLabel = (style: SuggestionStyle): string =>
-  STYLE_LABEL[style] ?? "未知";
+export const getStyleLabel = (
+  style: SuggestionStyle,
+  presets: StylePreset[] = [],
+): string =>
+  STYLE_LABEL[style] ??
+  presets.find((preset) => preset.name === style)?.description ??
+  style;
@@ -1,9 +1,21 @@
 import { describe, expect, it } from "vitest";
-import { normalizeListenTargets } from "./listenTargets";
+import {
+  normalizeListenTargetList,
+  normalizeListenTargets,
+} from "./listenTargets";
 
 describe("listen targets", () => {
   it("trims and dedupes names", () => {
     const targets = normalizeListenTargets(["  A ", "A", ""]);
     expect(targets.map((item) => item.name)).toEqual(["A"]);
   });
+
+  it("keeps prompt override when normalizing a list", () => {
+    const targets = normalizeListenTargetList([
+      { name: " 老板 ", kind: "direct", prompt_override: "语气恭敬" },
+    ]);
+    expect(targets).toEqual([
+      { name: "老板", kind: "direct", prompt_override: "语气恭敬" },
+    ]);
+  });
 });
@@ -3,6 +3,15 @@ export type ListenTargetKind = "direct" | "group" | "unknown";
 export type ListenTarget = {
   name: string;
   kind: ListenTargetKind;
+  prompt_override?: string | null;
+  persona?: string | null;
+  muted?: boolean;
+  priority?: "normal" | "high";
+  sender_whitelist?: string[];
+  sender_blacklist?: string[];
+  mention_only?: boolean;
+  language?: "ja" | "ko" | null;
+  politeness?: "polite" | "casual";
 };
 
 export const MAX_LISTEN_TARGETS = 50;
@@ -35,7 +44,7 @@ export const normalizeListenTargetList = (
       continue;
     }
     seen.add(name);
-    normalized.push({ name, kind: target.kind });
+    normalized.push({ ...target, name });
   }
   return normalized;
 };
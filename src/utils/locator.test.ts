@@ -0,0 +1,33 @@
+import { describe, expect, it } from "vitest";
+import type { LocatorDiagnostic } from "../bindings";
+import { summarizeLocatorDiagnostics } from "./locator";
+
+const diagnostic = (overrides: Partial<LocatorDiagnostic>): LocatorDiagnostic => ({
+  target: "session_list",
+  cue: "structure",
+  candidates: 4,
+  theme_sensitive: false,
+  at_ms: 0,
+  ...overrides,
+});
+
+describe("locator diagnostics", () => {
+  it("reports the cue used for each control", () => {
+    expect(summarizeLocatorDiagnostics([])).toEqual({ ok: true, message: "尚未定位过微信控件" });
+    expect(
+      summarizeLocatorDiagnostics([
+        diagnostic({}),
+        diagnostic({ target: "input_box", cue: "automation_id" }),
+      ]),
+    ).toEqual({ ok: true, message: "会话列表: 结构；输入框: AutomationId" });
+  });
+
+  it("flags theme-sensitive and missing cues", () => {
+    const summary = summarizeLocatorDiagnostics([
+      diagnostic({ target: "message_list", cue: "geometry", theme_sensitive: true }),
+      diagnostic({ target: "input_box", cue: null, theme_sensitive: true }),
+    ]);
+    expect(summary.ok).toBe(false);
+    expect(summary.message).toBe("消息列表: 位置；输入框: 未找到");
+  });
+});
@@ -0,0 +1,29 @@
+import type { LocatorCue, LocatorDiagnostic } from "../bindings";
+
+const TARGET_LABELS: Record<string, string> = {
+  session_list: "会话列表",
+  message_list: "消息列表",
+  input_box: "输入框",
+};
+
+const CUE_LABELS: Record<LocatorCue, string> = {
+  automation_id: "AutomationId",
+  structure: "结构",
+  name: "名称",
+  geometry: "位置",
+};
+
+export const summarizeLocatorDiagnostics = (
+  diagnostics: LocatorDiagnostic[],
+): { ok: boolean; message: string } => {
+  if (diagnostics.length === 0) {
+    return { ok: true, message: "尚未定位过微信控件" };
+  }
+  const lines = diagnostics.map((item) => {
+    const target = TARGET_LABELS[item.target] ?? item.target;
+    const cue = item.cue ? CUE_LABELS[item.cue] : "未找到";
+    return `${target}: ${cue}`;
+  });
+  const ok = diagnostics.every((item) => item.cue !== null && !item.theme_sensitive);
+  return { ok, message: lines.join("；") };
+};
@@ -0,0 +1,30 @@
+import { describe, expect, it } from "vitest";
+import type { MaintenanceReport } from "../bindings";
+import { formatBytes, formatMaintenanceReport } from "./maintenance";
+
+const report = (overrides: Partial<MaintenanceReport>): MaintenanceReport => ({
+  dry_run: true,
+  items: [
+    { kind: "orphaned_db", path: "/data/old.db", bytes: 2048, removed: false, error: null },
+    { kind: "log", path: "/logs/wereply.log", bytes: 3 * 1024 * 1024, removed: false, error: null },
+  ],
+  total_bytes: 2048 + 3 * 1024 * 1024,
+  reclaimed_bytes: 0,
+  ...overrides,
+});
+
+describe("maintenance", () => {
+  it("formats byte sizes", () => {
+    expect(formatBytes(512)).toBe("512 B");
+    expect(formatBytes(2048)).toBe("2.0 KB");
+    expect(formatBytes(3 * 1024 * 1024)).toBe("3.0 MB");
+  });
+
+  it("summarizes dry runs and cleanups", () => {
+    expect(formatMaintenanceReport(report({ items: [] }))).toBe("没有需要清理的文件");
+    expect(formatMaintenanceReport(report({}))).toBe("发现 2 项，可释放 3.0 MB");
+    const applied = report({ dry_run: false, reclaimed_bytes: 2048 });
+    applied.items[1] = { ...applied.items[1], error: "permission denied" };
+    expect(formatMaintenanceReport(applied)).toBe("已清理 1 项，释放 2.0 KB，1 项失败");
+  });
+});
@@ -0,0 +1,23 @@
+import type { MaintenanceReport } from "../bindings";
+
+export const formatBytes = (bytes: number): string => {
+  if (bytes < 1024) {
+    return `${bytes} B`;
+  }
+  if (bytes < 1024 * 1024) {
+    return `${(bytes / 1024).toFixed(1)} KB`;
+  }
+  return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+};
+
+export const formatMaintenanceReport = (report: MaintenanceReport): string => {
+  if (report.items.length === 0) {
+    return "没有需要清理的文件";
+  }
+  if (report.dry_run) {
+    return `发现 ${report.items.length} 项，可释放 ${formatBytes(report.total_bytes)}`;
+  }
+  const failed = report.items.filter((item) => item.error !== null).length;
+  const summary = `已清理 ${report.items.length - failed} 项，释放 ${formatBytes(report.reclaimed_bytes)}`;
+  return failed > 0 ? `${summary}，${failed} 项失败` : summary;
+};
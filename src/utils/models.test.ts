@@ -1,26 +1,47 @@
 import { describe, expect, it } from "vitest";
-import { DEFAULT_MODELS, normalizeModels, resolveModelSelection } from "./models";
+import type { ModelInfo } from "../bindings";
+import {
+  DEFAULT_MODELS,
+  formatModelLabel,
+  normalizeModels,
+  resolveModelSelection,
+} from "./models";
+
+const model = (id: string, overrides: Partial<ModelInfo> = {}): ModelInfo => ({
+  id,
+  owned_by: null,
+  context_length: null,
+  ...overrides,
+});
 
 describe("models", () => {
   it("normalizeModels falls back to defaults when list is empty", () => {
     expect(normalizeModels([])).toEqual(DEFAULT_MODELS);
   });
 
-  it("normalizeModels filters and preserves allowed order", () => {
-    expect(normalizeModels(["other", "deepseek-reasoner", "deepseek-chat"])).toEqual([
-      "deepseek-chat",
-      "deepseek-reasoner",
-    ]);
+  it("normalizeModels keeps every listed model in order without duplicates", () => {
+    const ids = normalizeModels([
+      model("other"),
+      model("deepseek-reasoner"),
+      model("other"),
+      model(""),
+    ]).map((item) => item.id);
+    expect(ids).toEqual(["other", "deepseek-reasoner"]);
+  });
+
+  it("formatModelLabel shows owner and context length when known", () => {
+    expect(formatModelLabel(DEFAULT_MODELS[0])).toBe("deepseek-chat · deepseek · 128K 上下文");
+    expect(formatModelLabel(model("qwen-max"))).toBe("qwen-max");
   });
 
   it("resolveModelSelection keeps selection when available", () => {
-    const result = resolveModelSelection(["deepseek-chat"], "deepseek-chat");
+    const result = resolveModelSelection([model("deepseek-chat")], "deepseek-chat");
     expect(result.selected).toBe("deepseek-chat");
     expect(result.changed).toBe(false);
   });
 
   it("resolveModelSelection falls back to first model", () => {
-    const result = resolveModelSelection(["deepseek-chat"], "deepseek-reasoner");
+    const result = resolveModelSelection([model("deepseek-chat")], "deepseek-reasoner");
     expect(result.selected).toBe("deepseek-chat");
     expect(result.changed).toBe(true);
   });
@@ -1,17 +1,35 @@
-export const DEFAULT_MODELS = ["deepseek-chat", "deepseek-reasoner"];
+import type { ModelInfo } from "../bindings";
 
-export const normalizeModels = (models: string[]): string[] => {
-  const normalized = DEFAULT_MODELS.filter((model) => models.includes(model));
+export const DEFAULT_MODELS: ModelInfo[] = [
+  { id: "deepseek-chat", owned_by: "deepseek", context_length: 131072 },
+  { id: "deepseek-reasoner", owned_by: "deepseek", context_length: 131072 },
+];
+
+export const normalizeModels = (models: ModelInfo[]): ModelInfo[] => {
+  const normalized = models.filter(
+    (model, index) => model.id && models.findIndex((item) => item.id === model.id) === index,
+  );
   return normalized.length > 0 ? normalized : [...DEFAULT_MODELS];
 };
 
+export const formatModelLabel = (model: ModelInfo): string => {
+  const parts = [model.id];
+  if (model.owned_by) {
+    parts.push(model.owned_by);
+  }
+  if (model.context_length) {
+    parts.push(`${Math.round(model.context_length / 1024)}K 上下文`);
+  }
+  return parts.join(" · ");
+};
+
 export const resolveModelSelection = (
-  models: string[],
+  models: ModelInfo[],
   selected: string,
 ): { selected: string; changed: boolean } => {
-  if (models.includes(selected)) {
+  if (models.some((model) => model.id === selected)) {
     return { selected, changed: false };
   }
-  const next = models[0] ?? DEFAULT_MODELS[0];
+  const next = models[0]?.id ?? DEFAULT_MODELS[0].id;
   return { selected: next, changed: true };
 };
@@ -0,0 +1,20 @@
+import { describe, expect, it } from "vitest";
+import { formatPowerStatus } from "./power";
+
+describe("power", () => {
+  it("shows the power source when low power is off", () => {
+    expect(formatPowerStatus({ source: "ac", low_power: false, adjustments: [] })).toBe(
+      "电源供电",
+    );
+  });
+
+  it("lists applied adjustments in low power mode", () => {
+    expect(
+      formatPowerStatus({
+        source: "battery",
+        low_power: true,
+        adjustments: ["监听间隔延长至 3 倍"],
+      }),
+    ).toBe("电池供电 · 低功耗：监听间隔延长至 3 倍");
+  });
+});
@@ -0,0 +1,22 @@
+import type { LowPowerMode, PowerSource, Status } from "../bindings";
+
+const SOURCE_LABEL: Record<PowerSource, string> = {
+  ac: "电源供电",
+  battery: "电池供电",
+  unknown: "供电未知",
+};
+
+export const LOW_POWER_MODE_LABELS: Record<LowPowerMode, string> = {
+  auto: "自动（使用电池时开启）",
+  on: "始终开启",
+  off: "关闭",
+};
+
+export const formatPowerStatus = (power: Status["power"]): string => {
+  const source = SOURCE_LABEL[power.source] ?? SOURCE_LABEL.unknown;
+  if (!power.low_power) {
+    return source;
+  }
+  const adjustments = power.adjustments.length ? `：${power.adjustments.join("，")}` : "";
+  return `${source} · 低功耗${adjustments}`;
+};
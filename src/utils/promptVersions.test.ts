@@ -0,0 +1,16 @@
+import { describe, expect, it } from "vitest";
+import { formatPromptChange } from "./promptVersions";
+
+describe("promptVersions", () => {
+  it("formats added, changed and removed prompts", () => {
+    expect(formatPromptChange({ item: "人设：客服", before: null, after: "耐心解答" })).toBe(
+      "人设：客服：（无） → 耐心解答",
+    );
+    expect(
+      formatPromptChange({ item: "监听对象：张三", before: "简洁一点", after: "详细一点" }),
+    ).toBe("监听对象：张三：简洁一点 → 详细一点");
+    expect(formatPromptChange({ item: "风格：buddy", before: "哥们", after: null })).toBe(
+      "风格：buddy：哥们 → （无）",
+    );
+  });
+});
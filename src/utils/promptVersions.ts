@@ -0,0 +1,6 @@
+import type { PromptChange } from "../bindings";
+
+const EMPTY = "（无）";
+
+export const formatPromptChange = (change: PromptChange): string =>
+  `${change.item}：${change.before ?? EMPTY} → ${change.after ?? EMPTY}`;
@@ -0,0 +1,21 @@
+import { describe, expect, it } from "vitest";
+import { formatReadiness } from "./readiness";
+
+describe("readiness", () => {
+  it("shows only the score when ready", () => {
+    expect(formatReadiness({ score: 85, ready: true, checks: [], blocking_issues: [] })).toBe(
+      "就绪度 85",
+    );
+  });
+
+  it("lists blocking issues", () => {
+    expect(
+      formatReadiness({
+        score: 50,
+        ready: false,
+        checks: [],
+        blocking_issues: ["未配置 DeepSeek API Key", "微信未运行或未登录"],
+      }),
+    ).toBe("就绪度 50 · 未配置 DeepSeek API Key；微信未运行或未登录");
+  });
+});
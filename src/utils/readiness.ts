@@ -0,0 +1,8 @@
+import type { Readiness } from "../bindings";
+
+export const formatReadiness = (readiness: Readiness): string => {
+  if (readiness.ready) {
+    return `就绪度 ${readiness.score}`;
+  }
+  return `就绪度 ${readiness.score} · ${readiness.blocking_issues.join("；")}`;
+};
@@ -1,11 +1,18 @@
 import { describe, expect, it } from "vitest";
-import { filterRecentChats, type RecentChat } from "./recentChats";
+import {
+  appendRecentChats,
+  describeRecentChat,
+  describeRecentChats,
+  filterRecentChats,
+  sortRecentChats,
+  type RecentChat,
+} from "./recentChats";
 
 describe("recent chats", () => {
   it("returns all chats when query is empty", () => {
     const chats: RecentChat[] = [
-      { chat_id: "a", chat_title: "Alpha", kind: "unknown" },
-      { chat_id: "b", chat_title: "Beta", kind: "direct" },
+      { chat_id: "a", chat_title: "Alpha", kind: "unknown", account_id: "" },
+      { chat_id: "b", chat_title: "Beta", kind: "direct", account_id: "" },
     ];
 
     expect(filterRecentChats(chats, "")).toEqual(chats);
@@ -13,9 +20,9 @@ describe("recent chats", () => {
 
   it("filters by title or id with trimming", () => {
     const chats: RecentChat[] = [
-      { chat_id: "team-01", chat_title: "项目群", kind: "group" },
-      { chat_id: "alice", chat_title: "Alice", kind: "direct" },
-      { chat_id: "report", chat_title: "", kind: "unknown" },
+      { chat_id: "team-01", chat_title: "项目群", kind: "group", account_id: "" },
+      { chat_id: "alice", chat_title: "Alice", kind: "direct", account_id: "" },
+      { chat_id: "report", chat_title: "", kind: "unknown", account_id: "" },
     ];
 
     expect(filterRecentChats(chats, " 项目 ")).toEqual([chats[0]]);
@@ -23,3 +30,57 @@ describe("recent chats", () => {
     expect(filterRecentChats(chats, "report")).toEqual([chats[2]]);
   });
 });
+
+describe("describeRecentChats", () => {
+  const snapshot = {
+    chats: [],
+    has_more: false,
+    fetched_at: 1_000,
+    stale: true,
+    refreshing: false,
+  };
+
+  it("reports background refresh and cache age", () => {
+    expect(describeRecentChats({ ...snapshot, refreshing: true }, 1_100)).toBe("后台刷新中");
+    expect(describeRecentChats(snapshot, 1_000 + 5 * 60)).toBe("缓存于 5 分钟前");
+    expect(describeRecentChats(snapshot, 1_000 + 3 * 3600)).toBe("缓存于 3 小时前");
+  });
+
+  it("stays quiet for fresh results", () => {
+    expect(describeRecentChats({ ...snapshot, stale: false }, 1_010)).toBe("");
+  });
+});
+
+describe("recent chat activity", () => {
+  const chats: RecentChat[] = [
+    { chat_id: "a", chat_title: "Alpha", kind: "direct" },
+    { chat_id: "b", chat_title: "Beta", kind: "group", last_active_at: 900, unread_count: 3 },
+    { chat_id: "c", chat_title: "Gamma", kind: "direct", last_active_at: 990, last_message: "好的" },
+  ];
+
+  it("sorts by last activity and keeps unknown ones last", () => {
+    expect(sortRecentChats(chats).map((chat) => chat.chat_id)).toEqual(["c", "b", "a"]);
+  });
+
+  it("summarizes unread count, activity and preview", () => {
+    expect(describeRecentChat(chats[0], 1_000)).toBe("");
+    expect(describeRecentChat(chats[1], 1_000)).toBe("未读 3 · 1 分钟前");
+    expect(describeRecentChat(chats[2], 1_000)).toBe("刚刚 · 好的");
+  });
+});
+
+describe("appendRecentChats", () => {
+  it("appends the next page without duplicates", () => {
+    const first: RecentChat[] = [
+      { chat_id: "a", chat_title: "Alpha", kind: "direct" },
+      { chat_id: "b", chat_title: "Beta", kind: "group" },
+    ];
+    const next: RecentChat[] = [
+      { chat_id: "b", chat_title: "Beta", kind: "group" },
+      { chat_id: "c", chat_title: "Gamma", kind: "direct" },
+    ];
+
+    expect(appendRecentChats(first, next).map((chat) => chat.chat_id)).toEqual(["a", "b", "c"]);
+    expect(appendRecentChats([], first)).toEqual(first);
+  });
+});
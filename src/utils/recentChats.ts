@@ -1,9 +1,54 @@
+import type { RecentChats } from "../bindings";
 import type { ListenTargetKind } from "./listenTargets";
 
 export type RecentChat = {
   chat_id: string;
   chat_title: string;
   kind: ListenTargetKind;
+  last_message?: string;
+  last_active_at?: number;
+  unread_count?: number;
+};
+
+export const RECENT_CHAT_PAGE = 50;
+
+export const appendRecentChats = (
+  chats: RecentChat[],
+  page: RecentChat[],
+): RecentChat[] => {
+  const known = new Set(chats.map((chat) => chat.chat_id));
+  return [...chats, ...page.filter((chat) => !known.has(chat.chat_id))];
+};
+
+export const sortRecentChats = (chats: RecentChat[]): RecentChat[] =>
+  [...chats].sort((a, b) => (b.last_active_at ?? 0) - (a.last_active_at ?? 0));
+
+const formatActiveAgo = (activeAt: number, nowSecs: number): string => {
+  const secs = Math.max(0, nowSecs - activeAt);
+  if (secs < 60) {
+    return "刚刚";
+  }
+  if (secs < 3600) {
+    return `${Math.floor(secs / 60)} 分钟前`;
+  }
+  if (secs < 86400) {
+    return `${Math.floor(secs / 3600)} 小时前`;
+  }
+  return `${Math.floor(secs / 86400)} 天前`;
+};
+
+export const describeRecentChat = (chat: RecentChat, nowSecs: number): string => {
+  const parts: string[] = [];
+  if (chat.unread_count) {
+    parts.push(`未读 ${chat.unread_count}`);
+  }
+  if (chat.last_active_at) {
+    parts.push(formatActiveAgo(chat.last_active_at, nowSecs));
+  }
+  if (chat.last_message?.trim()) {
+    parts.push(chat.last_message.trim());
+  }
+  return parts.join(" · ");
 };
 
 export const filterRecentChats = (
@@ -23,3 +68,14 @@ export const filterRecentChats = (
     );
   });
 };
+
+export const describeRecentChats = (snapshot: RecentChats, nowSecs: number): string => {
+  if (snapshot.refreshing) {
+    return "后台刷新中";
+  }
+  if (!snapshot.stale || snapshot.fetched_at === 0) {
+    return "";
+  }
+  const minutes = Math.max(1, Math.floor((nowSecs - snapshot.fetched_at) / 60));
+  return minutes < 60 ? `缓存于 ${minutes} 分钟前` : `缓存于 ${Math.floor(minutes / 60)} 小时前`;
+};
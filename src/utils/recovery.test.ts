@@ -0,0 +1,16 @@
+import { describe, expect, it } from "vitest";
+import { ACCESSIBILITY_SETTINGS_URL, getRecoveryActionLabel } from "./recovery";
+
+describe("recovery", () => {
+  it("maps suggested actions to button labels", () => {
+    expect(getRecoveryActionLabel("retry")).toBe("重试");
+    expect(getRecoveryActionLabel("open_settings")).toBe("打开设置");
+    expect(getRecoveryActionLabel("grant_permission")).toBe("授予权限");
+    expect(getRecoveryActionLabel("restart_agent")).toBe("重启 Agent");
+    expect(getRecoveryActionLabel("relearn_paths")).toBe("重新获取 UI 树");
+  });
+
+  it("points to the accessibility privacy pane", () => {
+    expect(ACCESSIBILITY_SETTINGS_URL).toContain("Privacy_Accessibility");
+  });
+});
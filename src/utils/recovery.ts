@@ -0,0 +1,15 @@
+import type { SuggestedAction } from "../bindings";
+
+const ACTION_LABEL: Record<SuggestedAction, string> = {
+  retry: "重试",
+  open_settings: "打开设置",
+  grant_permission: "授予权限",
+  restart_agent: "重启 Agent",
+  relearn_paths: "重新获取 UI 树",
+};
+
+export const ACCESSIBILITY_SETTINGS_URL =
+  "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility";
+
+export const getRecoveryActionLabel = (action: SuggestedAction): string =>
+  ACTION_LABEL[action] ?? "处理";
@@ -1,5 +1,5 @@
 import { describe, expect, it } from "vitest";
-import { normalizeReplyText } from "./reply";
+import { countGraphemes, MAX_REPLY_GRAPHEMES, normalizeReplyText } from "./reply";
 
 describe("reply normalization", () => {
   it("rejects empty", () => {
@@ -19,6 +19,19 @@ describe("reply normalization", () => {
     });
   });
 
+  it("counts emoji, rare CJK and RTL text by grapheme", () => {
+    expect(countGraphemes("👨‍👩‍👧‍👦")).toBe(1);
+    expect(countGraphemes("🇨🇳👍🏽")).toBe(2);
+    expect(countGraphemes("𠮷野家")).toBe(3);
+    expect(countGraphemes("مَرْحَبًا")).toBe(5);
+  });
+
+  it("measures length in graphemes rather than UTF-16 units", () => {
+    const emoji = "👨‍👩‍👧‍👦".repeat(MAX_REPLY_GRAPHEMES);
+    expect(normalizeReplyText(emoji)).toEqual({ ok: true, text: emoji });
+    expect(normalizeReplyText(`${emoji}好`).ok).toBe(false);
+  });
+
   it("accepts trimmed", () => {
     expect(normalizeReplyText(" hi ")).toEqual({ ok: true, text: "hi" });
   });
@@ -1,3 +1,22 @@
+export const MAX_REPLY_GRAPHEMES = 2000;
+
+type GraphemeSegmenter = { segment: (input: string) => Iterable<unknown> };
+type GraphemeSegmenterConstructor = new (
+  locale?: string,
+  options?: { granularity: "grapheme" },
+) => GraphemeSegmenter;
+
+const Segmenter = (Intl as unknown as { Segmenter?: GraphemeSegmenterConstructor })
+  .Segmenter;
+
+export const countGraphemes = (text: string): number => {
+  if (Segmenter) {
+    return Array.from(new Segmenter(undefined, { granularity: "grapheme" }).segment(text))
+      .length;
+  }
+  return Array.from(text).length;
+};
+
 export const normalizeReplyText = (
   input: string,
 ): { ok: true; text: string } | { ok: false; text: ""; reason: string } => {
@@ -5,7 +24,7 @@ export const normalizeReplyText = (
   if (!trimmed) {
     return { ok: false, text: "", reason: "回复内容不能为空" };
   }
-  if (trimmed.length > 2000) {
+  if (countGraphemes(trimmed) > MAX_REPLY_GRAPHEMES) {
     return { ok: false, text: "", reason: "回复内容过长" };
   }
   return { ok: true, text: trimmed };
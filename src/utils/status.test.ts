@@ -1,12 +1,17 @@
 import { describe, expect, it } from "vitest";
 import type { Status } from "../bindings";
-import { createStatusState, statusReducer } from "./status";
+import { createStatusState, formatTargetStatus, statusReducer } from "./status";
 
 const idleStatus: Status = {
   state: "idle",
   platform: "unknown",
   agent_connected: false,
   last_error: "",
+  power: { source: "unknown", low_power: false, adjustments: [] },
+  targets: {},
+  account_id: "",
+  strategy: "agent",
+  error_code: null,
 };
 
 const listeningStatus: Status = {
@@ -14,6 +19,11 @@ const listeningStatus: Status = {
   platform: "windows",
   agent_connected: true,
   last_error: "",
+  power: { source: "unknown", low_power: false, adjustments: [] },
+  targets: {},
+  account_id: "",
+  strategy: "agent",
+  error_code: null,
 };
 
 describe("status reducer", () => {
@@ -34,3 +44,27 @@ describe("status reducer", () => {
     expect(afterBootstrap.hasLiveUpdate).toBe(true);
   });
 });
+
+describe("formatTargetStatus", () => {
+  it("labels tracked targets with their error code", () => {
+    expect(formatTargetStatus(undefined)).toBeNull();
+    expect(
+      formatTargetStatus({
+        chat_id: "张三",
+        state: "generating",
+        error_code: null,
+        detail: "",
+        updated_at: 1,
+      }),
+    ).toBe("生成中");
+    expect(
+      formatTargetStatus({
+        chat_id: "客户群",
+        state: "error",
+        error_code: "LISTEN_TARGET_FAILED",
+        detail: "客户群: 无法监听该会话",
+        updated_at: 2,
+      }),
+    ).toBe("异常 · LISTEN_TARGET_FAILED");
+  });
+});
@@ -1,4 +1,5 @@
-import type { Status } from "../bindings";
+import type { Status, TargetStatus } from "../bindings";
+import { getStateLabel } from "./labels";
 
 export type StatusAction =
   | { type: "bootstrap"; status: Status }
@@ -36,3 +37,11 @@ export function statusReducer(state: StatusState, action: StatusAction): StatusS
       return state;
   }
 }
+
+export function formatTargetStatus(target: TargetStatus | undefined): string | null {
+  if (!target) {
+    return null;
+  }
+  const label = getStateLabel(target.state);
+  return target.error_code ? `${label} · ${target.error_code}` : label;
+}
@@ -0,0 +1,43 @@
+import { describe, expect, it } from "vitest";
+import type { SuggestionRecord } from "../bindings";
+import { formatSuggestionRecord } from "./suggestionHistory";
+
+const record = (overrides: Partial<SuggestionRecord> = {}): SuggestionRecord => ({
+  id: "s1",
+  chat_id: "c1",
+  context_hash: "abc",
+  model: "deepseek-chat",
+  fallback: false,
+  latency_ms: 1240,
+  suggestions: [{ id: "a", style: "formal", text: "好的" }],
+  written_suggestion_id: null,
+  written_at: null,
+  created_at: 1,
+  prompt_version: 0,
+  experiment: null,
+  ...overrides,
+});
+
+describe("suggestionHistory", () => {
+  it("summarizes model, latency and outcome", () => {
+    expect(formatSuggestionRecord(record())).toBe("deepseek-chat · 1.2s · 未写入");
+  });
+
+  it("shows the written style and template source", () => {
+    expect(
+      formatSuggestionRecord(record({ fallback: true, latency_ms: 12, written_suggestion_id: "a" })),
+    ).toBe("模板建议 · 12ms · 已写入正式");
+  });
+
+  it("tags the prompt version used", () => {
+    expect(formatSuggestionRecord(record({ prompt_version: 3 }))).toBe(
+      "deepseek-chat · 1.2s · 未写入 · 提示词 v3",
+    );
+  });
+
+  it("tags the experiment variant", () => {
+    expect(formatSuggestionRecord(record({ experiment: { name: "简短", variant: "b" } }))).toBe(
+      "deepseek-chat · 1.2s · 未写入 · 实验 简短/B",
+    );
+  });
+});
@@ -0,0 +1,20 @@
+import type { SuggestionRecord } from "../bindings";
+import { getStyleLabel } from "./labels";
+
+const formatLatency = (ms: number): string =>
+  ms < 1000 ? `${ms}ms` : `${(ms / 1000).toFixed(1)}s`;
+
+export const formatSuggestionRecord = (record: SuggestionRecord): string => {
+  const source = record.fallback ? "模板建议" : record.model;
+  const latency = formatLatency(record.latency_ms);
+  const written = record.suggestions.find((item) => item.id === record.written_suggestion_id);
+  const outcome = written ? `已写入${getStyleLabel(written.style)}` : "未写入";
+  const parts = [source, latency, outcome];
+  if (record.prompt_version > 0) {
+    parts.push(`提示词 v${record.prompt_version}`);
+  }
+  if (record.experiment) {
+    parts.push(`实验 ${record.experiment.name}/${record.experiment.variant.toUpperCase()}`);
+  }
+  return parts.join(" · ");
+};
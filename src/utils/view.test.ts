@@ -0,0 +1,13 @@
+import { describe, expect, it } from "vitest";
+import { resolveAppView } from "./view";
+
+describe("app view", () => {
+  it("resolves the menu bar panel view", () => {
+    expect(resolveAppView("?view=panel")).toBe("panel");
+  });
+
+  it("falls back to the main view", () => {
+    expect(resolveAppView("")).toBe("main");
+    expect(resolveAppView("?view=unknown")).toBe("main");
+  });
+});
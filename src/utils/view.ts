@@ -0,0 +1,6 @@
+export type AppView = "main" | "panel";
+
+export const resolveAppView = (search: string): AppView => {
+  const params = new URLSearchParams(search);
+  return params.get("view") === "panel" ? "panel" : "main";
+};